@@ -0,0 +1,475 @@
+use std::{path::Path, time::Duration};
+
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+use crate::{game::TICKS_PER_SECOND, input::InputManager};
+
+//-------------------------------------------------------------------------
+// Action layer on top of the raw key input. Each tick the keys bound to an
+// action are folded into its state, with edges (pressed, released) and how
+// long it has been held. A key pressed and let go within the same tick still
+// counts as pressed for that one tick, so quick taps aren't lost.
+//
+// Which keys are bound to what is per profile, from a "action = KEY KEY"
+// file (see Bindings::load). Key names are winit's (KeyW, ArrowUp, F3).
+//
+// Replays record what the keys did to the actions each tick (ActionInput),
+// not the keys themselves, so a run plays back the same whatever keys it was
+// flown with and whatever the viewer's bindings are.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    TurnLeft,
+    TurnRight,
+    Thrust,
+    Brake,
+    StrafeLeft,
+    StrafeRight,
+    FlightAssist,
+    SensorMode,
+    DebugLabels,
+    ExposureDown,
+    ExposureUp,
+    SaveGame,
+    SkipTutorial,
+    Repulsor,
+    Grapple,
+    Cloak,
+    LoadCheckpoint,
+    Rewind,
+}
+
+pub const NUM_ACTIONS: usize = 18;
+
+pub const ACTIONS: [Action; NUM_ACTIONS] = [
+    Action::TurnLeft,
+    Action::TurnRight,
+    Action::Thrust,
+    Action::Brake,
+    Action::StrafeLeft,
+    Action::StrafeRight,
+    Action::FlightAssist,
+    Action::SensorMode,
+    Action::DebugLabels,
+    Action::ExposureDown,
+    Action::ExposureUp,
+    Action::SaveGame,
+    Action::SkipTutorial,
+    Action::Repulsor,
+    Action::Grapple,
+    Action::Cloak,
+    Action::LoadCheckpoint,
+    Action::Rewind,
+];
+
+impl Action {
+    pub fn key(self) -> &'static str {
+        match self {
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::Thrust => "thrust",
+            Action::Brake => "brake",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::FlightAssist => "flight_assist",
+            Action::SensorMode => "sensor_mode",
+            Action::DebugLabels => "debug_labels",
+            Action::ExposureDown => "exposure_down",
+            Action::ExposureUp => "exposure_up",
+            Action::SaveGame => "save_game",
+            Action::SkipTutorial => "skip_tutorial",
+            Action::Repulsor => "repulsor",
+            Action::Grapple => "grapple",
+            Action::Cloak => "cloak",
+            Action::LoadCheckpoint => "load_checkpoint",
+            Action::Rewind => "rewind",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        ACTIONS.iter().copied().find(|action| action.key() == key)
+    }
+
+    // Actions that only change how the game is shown aren't recorded
+    pub fn affects_simulation(self) -> bool {
+        !matches!(
+            self,
+            Action::SensorMode | Action::DebugLabels | Action::ExposureDown | Action::ExposureUp
+        )
+    }
+}
+
+// What the keys bound to an action did within a tick
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActionInput {
+    // one of them was pressed
+    pub made: bool,
+    // one of them was let go
+    pub broke: bool,
+    // any of them held at the end of the tick
+    pub down: bool,
+}
+
+const DEFAULT_BINDINGS: &[(Action, KeyCode)] = &[
+    (Action::TurnLeft, KeyCode::ArrowLeft),
+    (Action::TurnLeft, KeyCode::KeyA),
+    (Action::TurnRight, KeyCode::ArrowRight),
+    (Action::TurnRight, KeyCode::KeyD),
+    (Action::Thrust, KeyCode::ArrowUp),
+    (Action::Thrust, KeyCode::KeyW),
+    (Action::Brake, KeyCode::ArrowDown),
+    (Action::Brake, KeyCode::KeyS),
+    (Action::StrafeLeft, KeyCode::KeyQ),
+    (Action::StrafeRight, KeyCode::KeyE),
+    (Action::FlightAssist, KeyCode::KeyV),
+    (Action::SensorMode, KeyCode::KeyN),
+    (Action::DebugLabels, KeyCode::F3),
+    (Action::ExposureDown, KeyCode::BracketLeft),
+    (Action::ExposureUp, KeyCode::BracketRight),
+    (Action::SaveGame, KeyCode::F5),
+    (Action::SkipTutorial, KeyCode::Tab),
+    (Action::Repulsor, KeyCode::KeyF),
+    (Action::Grapple, KeyCode::KeyG),
+    (Action::Cloak, KeyCode::KeyC),
+    (Action::LoadCheckpoint, KeyCode::F9),
+    (Action::Rewind, KeyCode::KeyR),
+];
+
+// Keys that can be bound, by their winit name
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+];
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.iter().copied().find(|key| format!("{:?}", key) == name)
+}
+
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: Vec<(Action, KeyCode)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keys: DEFAULT_BINDINGS.to_vec(),
+        }
+    }
+}
+
+impl Bindings {
+    // Each "action = KEY KEY" line replaces the default keys for that action,
+    // '#' starts a comment. A missing file is just the defaults.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut bindings = Self::default();
+        if !path.exists() {
+            return Ok(bindings);
+        }
+        let txt = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read bindings {}: {}", path.display(), err))?;
+
+        for (line_num, line) in txt.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |what: &str| format!("{}:{}: {}", path.display(), line_num + 1, what);
+            let (name, keys) = line.split_once('=').ok_or_else(|| invalid("expected 'action = KEY'"))?;
+            let action = Action::from_key(name.trim()).ok_or_else(|| invalid("unknown action"))?;
+            bindings.keys.retain(|(bound, _)| *bound != action);
+            for key in keys.split_whitespace() {
+                let key = key_from_name(key).ok_or_else(|| invalid(&format!("unknown key {}", key)))?;
+                bindings.keys.push((action, key));
+            }
+        }
+        Ok(bindings)
+    }
+
+    // Keys bound to an action the way a prompt shows them, e.g. "W or Up"
+    pub fn key_names(&self, action: Action) -> String {
+        let names: Vec<String> = self
+            .keys
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .map(|(_, key)| {
+                let name = format!("{:?}", key);
+                ["Key", "Arrow", "Digit"]
+                    .iter()
+                    .find_map(|prefix| name.strip_prefix(prefix))
+                    .unwrap_or(&name)
+                    .to_string()
+            })
+            .collect();
+        if names.is_empty() {
+            return "(unbound)".to_string();
+        }
+        names.join(" or ")
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct ActionEntry {
+    // tick the action was pressed, while it's held
+    held_since: Option<u32>,
+    just_pressed: bool,
+    just_released: bool,
+    // down this tick, or tapped within it
+    active: bool,
+    // ticks held counting this one, or on the tick it's released how long it was held
+    held_ticks: u32,
+}
+
+pub struct ActionState {
+    bindings: Bindings,
+    entries: [ActionEntry; NUM_ACTIONS],
+    // what went into the entries on the last update, for recording
+    inputs: [ActionInput; NUM_ACTIONS],
+}
+
+impl ActionState {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            entries: [ActionEntry::default(); NUM_ACTIONS],
+            inputs: [ActionInput::default(); NUM_ACTIONS],
+        }
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    // Fold this tick's key events into the actions. Called once at the start of each tick.
+    pub fn update(&mut self, input: &InputManager, tick: u32) {
+        let mut inputs = [ActionInput::default(); NUM_ACTIONS];
+        for (idx, action_input) in inputs.iter_mut().enumerate() {
+            let keys = self
+                .bindings
+                .keys
+                .iter()
+                .filter(|(action, _)| *action as usize == idx)
+                .map(|(_, key)| PhysicalKey::Code(*key));
+            for key in keys {
+                action_input.made |= input.is_make(key);
+                action_input.broke |= input.is_break(key);
+                action_input.down |= input.is_down(key);
+            }
+        }
+        self.update_from(&inputs, tick);
+    }
+
+    // Fold what the keys did this tick into the actions, from the keys
+    // themselves or from a replay
+    pub fn update_from(&mut self, inputs: &[ActionInput; NUM_ACTIONS], tick: u32) {
+        self.inputs = *inputs;
+        for (entry, input) in self.entries.iter_mut().zip(inputs) {
+            let ActionInput { made, broke, down } = *input;
+            let was_down = entry.held_since.is_some();
+            // let go and pressed again within the tick is a new press
+            entry.just_pressed = made && (!was_down || broke);
+            // a tap within the tick is pressed and released at once
+            entry.just_released = broke && !down && (was_down || made);
+            if entry.just_pressed {
+                entry.held_since = Some(tick);
+            }
+            entry.active = down || entry.just_pressed;
+            entry.held_ticks = match entry.held_since {
+                Some(since) if entry.active => tick - since + 1,
+                Some(since) => tick - since,
+                None => 0,
+            };
+            if !down {
+                entry.held_since = None;
+            }
+        }
+    }
+
+    pub fn inputs(&self) -> &[ActionInput; NUM_ACTIONS] {
+        &self.inputs
+    }
+
+    fn entry(&self, action: Action) -> &ActionEntry {
+        &self.entries[action as usize]
+    }
+
+    // Held this tick, including a tap that was let go again within it
+    pub fn is_down(&self, action: Action) -> bool {
+        self.entry(action).active
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.entry(action).just_pressed
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.entry(action).just_released
+    }
+
+    // Held for at least this long, counting this tick. On the tick it's released
+    // this is still how long it was held, for telling taps from holds.
+    pub fn pressed_for(&self, action: Action, duration: Duration) -> bool {
+        self.held_duration(action) >= duration
+    }
+
+    pub fn held_duration(&self, action: Action) -> Duration {
+        Duration::from_secs_f64(self.entry(action).held_ticks as f64 / TICKS_PER_SECOND as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESS: ActionInput = ActionInput { made: true, broke: false, down: true };
+    const HOLD: ActionInput = ActionInput { made: false, broke: false, down: true };
+    const RELEASE: ActionInput = ActionInput { made: false, broke: true, down: false };
+    const TAP: ActionInput = ActionInput { made: true, broke: true, down: false };
+    const IDLE: ActionInput = ActionInput { made: false, broke: false, down: false };
+    const REPRESS: ActionInput = ActionInput { made: true, broke: true, down: true };
+
+    // Update the state for a tick in which only the repulsor keys did something
+    fn feed(state: &mut ActionState, tick: u32, input: ActionInput) {
+        let mut inputs = [ActionInput::default(); NUM_ACTIONS];
+        inputs[Action::Repulsor as usize] = input;
+        state.update_from(&inputs, tick);
+    }
+
+    #[test]
+    fn press_hold_release() {
+        let mut state = ActionState::new(Bindings::default());
+        feed(&mut state, 10, PRESS);
+        assert!(state.just_pressed(Action::Repulsor));
+        assert!(!state.just_released(Action::Repulsor));
+        assert!(state.is_down(Action::Repulsor));
+
+        feed(&mut state, 11, HOLD);
+        assert!(!state.just_pressed(Action::Repulsor));
+        assert!(state.is_down(Action::Repulsor));
+
+        feed(&mut state, 12, RELEASE);
+        assert!(state.just_released(Action::Repulsor));
+        assert!(!state.just_pressed(Action::Repulsor));
+        assert!(!state.is_down(Action::Repulsor));
+
+        feed(&mut state, 13, IDLE);
+        assert!(!state.just_released(Action::Repulsor));
+        assert!(!state.is_down(Action::Repulsor));
+        assert!(!state.just_pressed(Action::Grapple));
+    }
+
+    #[test]
+    fn tap_within_a_tick() {
+        let mut state = ActionState::new(Bindings::default());
+        feed(&mut state, 5, TAP);
+        assert!(state.just_pressed(Action::Repulsor));
+        assert!(state.just_released(Action::Repulsor));
+        assert!(state.is_down(Action::Repulsor));
+        let tick = Duration::from_secs_f64(1.0 / TICKS_PER_SECOND as f64);
+        assert_eq!(state.held_duration(Action::Repulsor), tick);
+        assert!(!state.pressed_for(Action::Repulsor, Duration::from_secs(1)));
+
+        feed(&mut state, 6, IDLE);
+        assert!(!state.just_pressed(Action::Repulsor));
+        assert!(!state.just_released(Action::Repulsor));
+        assert!(!state.is_down(Action::Repulsor));
+        assert_eq!(state.held_duration(Action::Repulsor), Duration::ZERO);
+
+        // let go and pressed again while held
+        feed(&mut state, 7, PRESS);
+        feed(&mut state, 8, HOLD);
+        feed(&mut state, 9, REPRESS);
+        assert!(state.just_pressed(Action::Repulsor));
+        assert!(!state.just_released(Action::Repulsor));
+        assert!(state.is_down(Action::Repulsor));
+        assert_eq!(state.held_duration(Action::Repulsor), tick);
+    }
+
+    #[test]
+    fn pressed_for_counts_ticks_held() {
+        let mut state = ActionState::new(Bindings::default());
+        let second = Duration::from_secs(1);
+        let ticks = TICKS_PER_SECOND as u32;
+        feed(&mut state, 100, PRESS);
+        for tick in 101..100 + ticks - 1 {
+            feed(&mut state, tick, HOLD);
+        }
+        assert!(!state.pressed_for(Action::Repulsor, second));
+
+        feed(&mut state, 100 + ticks - 1, HOLD);
+        assert!(state.pressed_for(Action::Repulsor, second));
+
+        // still held long enough on the tick it's let go, so a hold can act on release
+        feed(&mut state, 100 + ticks, RELEASE);
+        assert!(state.just_released(Action::Repulsor));
+        assert!(state.pressed_for(Action::Repulsor, second));
+
+        feed(&mut state, 101 + ticks, IDLE);
+        assert!(!state.pressed_for(Action::Repulsor, second));
+    }
+}
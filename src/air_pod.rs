@@ -0,0 +1,88 @@
+use xilem::Color;
+
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// Air pods come in tiers: small ones are common but don't hold much air,
+// large ones are rarer and hold more, and once in a while a golden one
+// turns up that also puts a shield around the ship for a few seconds (see
+// StatusKind::Shielded). Bigger pods are easier to grab, and each tier
+// pulses in its own colors so it can be told apart from afar.
+//
+// A pod draws its tier from the air_pod spawn table every time it's picked
+// up and moves somewhere else. The tier is the pod's variant, so saves and
+// snapshots keep it.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PodTier {
+    Small,
+    // the pod every run starts with
+    #[default]
+    Medium,
+    Large,
+    Golden,
+}
+
+impl PodTier {
+    const ALL: [PodTier; 4] = [PodTier::Small, PodTier::Medium, PodTier::Large, PodTier::Golden];
+
+    // name used in the spawn tables
+    pub fn key(self) -> &'static str {
+        match self {
+            PodTier::Small => "small",
+            PodTier::Medium => "medium",
+            PodTier::Large => "large",
+            PodTier::Golden => "golden",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tier| tier.key() == key)
+    }
+
+    pub fn variant(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_variant(variant: u32) -> Self {
+        Self::ALL.get(variant as usize).copied().unwrap_or_default()
+    }
+
+    pub fn radius(self) -> f64 {
+        match self {
+            PodTier::Small => 70.0,
+            PodTier::Medium => 100.0,
+            PodTier::Large => 130.0,
+            PodTier::Golden => 110.0,
+        }
+    }
+
+    // of the air a medium pod the same distance away would get
+    pub fn air_factor(self) -> f64 {
+        match self {
+            PodTier::Small => 0.6,
+            PodTier::Medium => 1.0,
+            PodTier::Large => 1.6,
+            PodTier::Golden => 2.0,
+        }
+    }
+
+    // ticks of shield for the ship that picks it up
+    pub fn shield_ticks(self) -> u32 {
+        match self {
+            PodTier::Golden => 8 * TICKS_PER_SECOND as u32,
+            _ => 0,
+        }
+    }
+
+    // fill of the star and its outline
+    pub fn colors(self) -> (Color, Color) {
+        match self {
+            PodTier::Small => (Color::rgb8(0x70, 0xd8, 0xf0), Color::rgb8(0xe0, 0xf8, 0xff)),
+            PodTier::Medium => (Color::rgb8(0x0, 0xb4, 0xd8), Color::rgb8(0xff, 0xff, 0xff)),
+            PodTier::Large => (Color::rgb8(0x20, 0x60, 0xe0), Color::rgb8(0xa0, 0xe0, 0xff)),
+            PodTier::Golden => (Color::rgb8(0xff, 0xc0, 0x20), Color::rgb8(0xff, 0xf4, 0xb0)),
+        }
+    }
+}
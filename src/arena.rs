@@ -0,0 +1,237 @@
+use std::{
+    f64::consts::{PI, SQRT_2, TAU},
+    ops::Range,
+};
+
+use masonry::Vec2;
+
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// The border around the field. By default it's the square edge of the
+// world, but it can also be a hexagon or a circle, and turn and/or close in
+// over time, battle royale style. Polygon walls are checked side by side
+// against the circles of each body, so the out of bounds checks work for
+// any orientation and size.
+//-------------------------------------------------------------------------
+
+// a full turn takes ten minutes
+const ARENA_TURN_RATE: f64 = TAU / (600.0 * TICKS_PER_SECOND as f64);
+// how long the arena holds before closing in, and how long it takes to close
+const ARENA_SHRINK_DELAY_SECS: f64 = 60.0;
+const ARENA_SHRINK_SECS: f64 = 240.0;
+// the arena stops closing in at this fraction of its starting size
+const ARENA_MIN_SCALE: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArenaVariant {
+    Fixed,
+    Rotating,
+    Shrinking,
+    // rotating and shrinking
+    Royale,
+}
+
+impl ArenaVariant {
+    // name used on the command line, in saves and in replays
+    pub fn key(self) -> &'static str {
+        match self {
+            ArenaVariant::Fixed => "fixed",
+            ArenaVariant::Rotating => "rotating",
+            ArenaVariant::Shrinking => "shrinking",
+            ArenaVariant::Royale => "royale",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "fixed" => Some(ArenaVariant::Fixed),
+            "rotating" => Some(ArenaVariant::Rotating),
+            "shrinking" => Some(ArenaVariant::Shrinking),
+            "royale" => Some(ArenaVariant::Royale),
+            _ => None,
+        }
+    }
+
+    fn rotates(self) -> bool {
+        matches!(self, ArenaVariant::Rotating | ArenaVariant::Royale)
+    }
+
+    fn shrinks(self) -> bool {
+        matches!(self, ArenaVariant::Shrinking | ArenaVariant::Royale)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArenaShape {
+    Square,
+    Hexagon,
+    Circle,
+}
+
+impl ArenaShape {
+    // name used on the command line, in saves and in replays
+    pub fn key(self) -> &'static str {
+        match self {
+            ArenaShape::Square => "square",
+            ArenaShape::Hexagon => "hexagon",
+            ArenaShape::Circle => "circle",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "square" => Some(ArenaShape::Square),
+            "hexagon" => Some(ArenaShape::Hexagon),
+            "circle" => Some(ArenaShape::Circle),
+            _ => None,
+        }
+    }
+
+    // number of walls, None for the circle
+    pub fn sides(self) -> Option<u32> {
+        match self {
+            ArenaShape::Square => Some(4),
+            ArenaShape::Hexagon => Some(6),
+            ArenaShape::Circle => None,
+        }
+    }
+}
+
+// "VARIANT" or "VARIANT SHAPE" as written in saves and replays, the shape
+// was added later and defaults to square
+pub fn arena_name(variant: ArenaVariant, shape: ArenaShape) -> String {
+    format!("{} {}", variant.key(), shape.key())
+}
+
+pub fn parse_arena_name(name: &str) -> Option<(ArenaVariant, ArenaShape)> {
+    let mut parts = name.split_whitespace();
+    let variant = ArenaVariant::from_key(parts.next()?)?;
+    let shape = match parts.next() {
+        Some(shape) => ArenaShape::from_key(shape)?,
+        None => ArenaShape::Square,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((variant, shape))
+}
+
+pub struct Arena {
+    pub variant: ArenaVariant,
+    pub shape: ArenaShape,
+    // half size of the world the arena sits in
+    extent: f64,
+}
+
+impl Arena {
+    pub fn new(variant: ArenaVariant, shape: ArenaShape, extent: f64) -> Self {
+        Self {
+            variant,
+            shape,
+            extent,
+        }
+    }
+
+    // Ticks are f64 so rendering can go between them
+    pub fn rotation_at(&self, tick: f64) -> f64 {
+        if self.variant.rotates() {
+            ARENA_TURN_RATE * tick
+        } else {
+            0.0
+        }
+    }
+
+    // turn rate of the walls (radians/tick), for pushing things along with them
+    pub fn angular_velocity(&self) -> f64 {
+        if self.variant.rotates() {
+            ARENA_TURN_RATE
+        } else {
+            0.0
+        }
+    }
+
+    // size relative to the start
+    pub fn scale_at(&self, tick: f64) -> f64 {
+        if !self.variant.shrinks() {
+            return 1.0;
+        }
+        let secs = tick / TICKS_PER_SECOND as f64 - ARENA_SHRINK_DELAY_SECS;
+        let t = (secs / ARENA_SHRINK_SECS).clamp(0.0, 1.0);
+        1.0 - t * (1.0 - ARENA_MIN_SCALE)
+    }
+
+    // Distance from the center to the middle of each wall (the radius for the
+    // circle). Corners have to stay inside the world, which for anything but a
+    // square that doesn't turn means fitting inside the circle touching its edges.
+    pub fn apothem_at(&self, tick: f64) -> f64 {
+        let start = match self.shape.sides() {
+            Some(4) if !self.variant.rotates() => self.extent,
+            Some(sides) => self.extent * (PI / sides as f64).cos(),
+            None => self.extent,
+        };
+        start * self.scale_at(tick)
+    }
+
+    // Square that's inside the arena at this tick whichever way it's turned,
+    // for placing things
+    pub fn spawn_range(&self, tick: f64) -> Range<Vec2> {
+        let apothem = self.apothem_at(tick);
+        let half = if self.shape == ArenaShape::Square && !self.variant.rotates() {
+            apothem
+        } else {
+            apothem / SQRT_2
+        };
+        Vec2::new(-half, -half)..Vec2::new(half, half)
+    }
+
+    // How far pos is from the closest wall (negative outside it) and that wall's outward normal
+    pub fn nearest_wall(&self, pos: Vec2, tick: f64) -> (f64, Vec2) {
+        let apothem = self.apothem_at(tick);
+        let Some(sides) = self.shape.sides() else {
+            let dist = pos.length();
+            let normal = if dist > 0.0 { pos / dist } else { Vec2::new(1.0, 0.0) };
+            return (apothem - dist, normal);
+        };
+        let rotation = self.rotation_at(tick);
+        (0..sides)
+            .map(|side| {
+                let normal = Vec2::from_angle(rotation + TAU * side as f64 / sides as f64);
+                (apothem - pos.dot(normal), normal)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap()
+    }
+
+    // Calls on_contact(point, outward normal, depth) for each wall the circle is through
+    pub fn wall_contacts(
+        &self,
+        pos: Vec2,
+        radius: f64,
+        tick: f64,
+        on_contact: &mut impl FnMut(Vec2, Vec2, f64),
+    ) {
+        let apothem = self.apothem_at(tick);
+        if pos.length() + radius < apothem {
+            // clear of every wall
+            return;
+        }
+
+        let Some(sides) = self.shape.sides() else {
+            let dist = pos.length();
+            let normal = if dist > 0.0 { pos / dist } else { Vec2::new(1.0, 0.0) };
+            on_contact(apothem * normal, normal, dist + radius - apothem);
+            return;
+        };
+        let rotation = self.rotation_at(tick);
+        for side in 0..sides {
+            let normal = Vec2::from_angle(rotation + TAU * side as f64 / sides as f64);
+            let dist = pos.dot(normal);
+            let depth = dist + radius - apothem;
+            if depth > 0.0 {
+                let point = pos + (apothem - dist) * normal;
+                on_contact(point, normal, depth);
+            }
+        }
+    }
+}
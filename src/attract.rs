@@ -0,0 +1,33 @@
+use crate::{game::GameWorld, navigation::NavPath};
+
+//-------------------------------------------------------------------------
+// Attract mode. Left alone on the main menu for a while, the game plays
+// itself behind the menu to show what it's like: a copy of the world the
+// menu is over, with the ship flown by an ace pilot going after the nearest
+// air pod (GameWorld::update_autopilot) and the camera following it as
+// usual. The demo is set up as a playback, so nothing it does earns
+// anything, unlocks anything or gets saved.
+//
+// The world the menu was over is held on to while the demo runs, and any
+// key (or the demo ship running out of air) goes back to it, with the key
+// taken by the demo rather than the menu. See GameWorld::update_attract.
+//-------------------------------------------------------------------------
+
+// of nothing being pressed on the main menu before the demo starts
+pub const ATTRACT_IDLE_SECS: f64 = 20.0;
+
+pub struct Attract {
+    // the world the menu was over, put back once the demo is over
+    pub home: Box<GameWorld>,
+    // of the demo ship, around the rocks to the pod it's after
+    pub nav_path: NavPath,
+}
+
+impl Attract {
+    pub fn new(home: GameWorld) -> Self {
+        Self {
+            home: Box::new(home),
+            nav_path: NavPath::default(),
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    f64::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2, TAU},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use rodio::{
+    source::{ChannelVolume, SineWave},
+    OutputStream, OutputStreamHandle, Sink, Source,
+};
+
+//-------------------------------------------------------------------------
+// Sound effects and music. The game has no sound files, everything is
+// made up on the spot from sine waves. The output stream can't leave the
+// thread that opened it, so it lives on its own thread and the game sends
+// it requests. Without an audio device everything is quietly dropped.
+//
+// The music is two endless layers, a calm pad and an intense pulsing one,
+// crossfaded by how much danger the player is in (0..1, worked out by the
+// game each tick). Stingers are cues played over the music.
+//
+// Cues from something in the world are panned left or right and quieter the
+// further away it is, which the game works out from where it is on screen.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cue {
+    // low falling pair, entering a thick part of the asteroid field
+    DangerAsteroids,
+    // high rising pair, getting close to the border
+    DangerBorder,
+    // stinger, quick rising arpeggio
+    Pickup,
+    // whoosh, a quick slide down in pitch like something going by
+    NearMiss,
+    // thud of a collision
+    Impact,
+}
+
+impl Cue {
+    // (frequency in Hz, seconds) of each note, played one after the other
+    fn notes(self) -> &'static [(f32, f32)] {
+        match self {
+            Cue::DangerAsteroids => &[(330.0, 0.09), (247.0, 0.14)],
+            Cue::DangerBorder => &[(660.0, 0.07), (880.0, 0.11)],
+            Cue::Pickup => &[(523.0, 0.06), (659.0, 0.06), (784.0, 0.12)],
+            Cue::NearMiss => &[
+                (1047.0, 0.025),
+                (988.0, 0.025),
+                (880.0, 0.03),
+                (784.0, 0.035),
+                (659.0, 0.045),
+                (523.0, 0.06),
+            ],
+            Cue::Impact => &[(110.0, 0.04), (73.0, 0.08)],
+        }
+    }
+}
+
+enum AudioRequest {
+    // pan -1 (left) to 1 (right), gain 0..1
+    Cue { cue: Cue, pan: f64, gain: f64 },
+    Danger(f64),
+}
+
+// keeps the cues subtle under the full volume
+const CUE_GAIN: f32 = 0.25;
+const NOTE_FADE_SECS: f32 = 0.015;
+
+const MUSIC_GAIN: f32 = 0.15;
+// for the danger level the music is at to follow the game's all the way
+const MUSIC_FADE_SECS: f64 = 2.0;
+const MUSIC_STEP: Duration = Duration::from_millis(50);
+const SAMPLE_RATE: u32 = 44100;
+
+// (frequency in Hz, amplitude) of the notes in each music layer
+const CALM_CHORD: [(f64, f64); 3] = [(110.0, 0.5), (164.8, 0.3), (261.6, 0.2)];
+const INTENSE_CHORD: [(f64, f64); 3] = [(55.0, 0.5), (82.4, 0.3), (116.5, 0.2)];
+// (rate in Hz, depth 0..1) the volume goes up and down at, the calm layer
+// slowly swells and the intense one throbs
+const CALM_PULSE: (f64, f64) = (0.1, 0.4);
+const INTENSE_PULSE: (f64, f64) = (4.0, 1.0);
+
+pub struct Audio {
+    sender: Option<Sender<AudioRequest>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Audio {
+    // volumes are 0..1, music at 0 doesn't play at all
+    pub fn new(volume: f64, music_volume: f64) -> Self {
+        let (sender, receiver) = mpsc::channel::<AudioRequest>();
+        let cue_gain = volume as f32 * CUE_GAIN;
+        let music_gain = music_volume as f32 * MUSIC_GAIN;
+
+        let thread = std::thread::Builder::new()
+            .name("audio".to_string())
+            .spawn(move || {
+                let (_stream, handle) = match OutputStream::try_default() {
+                    Ok(output) => output,
+                    Err(err) => {
+                        log::warn!("No audio output, playing without sound: {}", err);
+                        return;
+                    }
+                };
+                let mut music = (music_gain > 0.0).then(|| Music::new(&handle, music_gain)).flatten();
+
+                loop {
+                    match receiver.recv_timeout(MUSIC_STEP) {
+                        Ok(AudioRequest::Cue { cue, pan, gain }) => {
+                            play_cue(&handle, cue, pan, cue_gain * gain as f32)
+                        }
+                        Ok(AudioRequest::Danger(danger)) => {
+                            if let Some(music) = music.as_mut() {
+                                music.target = danger.clamp(0.0, 1.0);
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    if let Some(music) = music.as_mut() {
+                        music.update();
+                    }
+                }
+            })
+            .expect("Failed to start audio thread");
+
+        Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    // Centered at full volume, for cues that aren't from anywhere in particular
+    pub fn play(&self, cue: Cue) {
+        self.play_at(cue, 0.0, 1.0);
+    }
+
+    // pan is -1 (left) to 1 (right), gain 0..1
+    pub fn play_at(&self, cue: Cue, pan: f64, gain: f64) {
+        self.send(AudioRequest::Cue { cue, pan, gain });
+    }
+
+    // 0 is calm, 1 is as bad as it gets
+    pub fn set_danger(&self, danger: f64) {
+        self.send(AudioRequest::Danger(danger));
+    }
+
+    fn send(&self, request: AudioRequest) {
+        if let Some(sender) = self.sender.as_ref() {
+            // only fails without an output, which has already been logged
+            let _ = sender.send(request);
+        }
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn play_cue(handle: &OutputStreamHandle, cue: Cue, pan: f64, gain: f32) {
+    if gain <= 0.0 {
+        return;
+    }
+    // equal power panning, scaled so the center is as loud as a mono cue
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+    let left = gain * (SQRT_2 * angle.cos()) as f32;
+    let right = gain * (SQRT_2 * angle.sin()) as f32;
+
+    let mut start = 0.0;
+    for &(freq, secs) in cue.notes() {
+        let note = SineWave::new(freq)
+            .take_duration(Duration::from_secs_f32(secs))
+            .fade_in(Duration::from_secs_f32(NOTE_FADE_SECS))
+            .delay(Duration::from_secs_f32(start));
+        if let Err(err) = handle.play_raw(ChannelVolume::new(note, vec![left, right])) {
+            log::warn!("Failed to play {:?}: {}", cue, err);
+        }
+        start += secs;
+    }
+}
+
+// --- MARK: Music ---
+
+struct Music {
+    calm: Sink,
+    intense: Sink,
+    gain: f32,
+    // danger level the layers are mixed for, and the one being faded to
+    level: f64,
+    target: f64,
+    last_update: Instant,
+}
+
+impl Music {
+    fn new(handle: &OutputStreamHandle, gain: f32) -> Option<Self> {
+        let sinks = Sink::try_new(handle).and_then(|calm| Ok((calm, Sink::try_new(handle)?)));
+        let (calm, intense) = match sinks {
+            Ok(sinks) => sinks,
+            Err(err) => {
+                log::warn!("Failed to start music: {}", err);
+                return None;
+            }
+        };
+        calm.append(Layer::new(&CALM_CHORD, CALM_PULSE));
+        intense.append(Layer::new(&INTENSE_CHORD, INTENSE_PULSE));
+
+        let music = Self {
+            calm,
+            intense,
+            gain,
+            level: 0.0,
+            target: 0.0,
+            last_update: Instant::now(),
+        };
+        music.set_volumes();
+        Some(music)
+    }
+
+    fn update(&mut self) {
+        let now = Instant::now();
+        let max_step = (now - self.last_update).as_secs_f64() / MUSIC_FADE_SECS;
+        self.last_update = now;
+        self.level += (self.target - self.level).clamp(-max_step, max_step);
+        self.set_volumes();
+    }
+
+    // equal power crossfade, so the music doesn't dip halfway
+    fn set_volumes(&self) {
+        let angle = self.level * FRAC_PI_2;
+        self.calm.set_volume(self.gain * angle.cos() as f32);
+        self.intense.set_volume(self.gain * angle.sin() as f32);
+    }
+}
+
+// Endless chord with its volume pulsing
+struct Layer {
+    chord: &'static [(f64, f64)],
+    // (Hz, depth)
+    pulse: (f64, f64),
+    sample: u64,
+}
+
+impl Layer {
+    fn new(chord: &'static [(f64, f64)], pulse: (f64, f64)) -> Self {
+        Self { chord, pulse, sample: 0 }
+    }
+}
+
+impl Iterator for Layer {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let t = self.sample as f64 / SAMPLE_RATE as f64;
+        self.sample += 1;
+        let tone: f64 = self.chord.iter().map(|(freq, amp)| amp * (TAU * freq * t).sin()).sum();
+        let (rate, depth) = self.pulse;
+        let envelope = 1.0 - depth * (0.5 + 0.5 * (TAU * rate * t).cos());
+        Some((tone * envelope) as f32)
+    }
+}
+
+impl Source for Layer {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
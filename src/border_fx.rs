@@ -0,0 +1,130 @@
+use masonry::Vec2;
+
+use crate::game::HashRand;
+
+//-------------------------------------------------------------------------
+// What it looks like when something hits the border: sparks spraying off
+// the impact, a glow on the spot, and the wall that was hit pulsing
+// brighter for a moment, so the edge of the field feels like something
+// solid. Started from the border contacts GameWorld::detect_collisions
+// finds. Like floating texts they run on virtual time and are only for
+// show, nothing saves them and the simulation never looks at them.
+//-------------------------------------------------------------------------
+
+// slower than this into the wall (units/tick) doesn't count as a hit, so
+// things resting against the border or sliding along it stay quiet
+pub const BORDER_HIT_SPEED: f64 = 1.5;
+// hitting at this speed and up gives the most sparks and the brightest glow
+const BORDER_HIT_FULL_SPEED: f64 = 12.0;
+const MAX_SPARKS_PER_HIT: u32 = 12;
+const SPARK_SECS: f64 = 0.5;
+// world units/second, the fastest a spark flies off
+const SPARK_SPEED: f64 = 600.0;
+pub const GLOW_SECS: f64 = 0.3;
+pub const PULSE_SECS: f64 = 0.6;
+
+pub struct Spark {
+    // world position it started at
+    pos: Vec2,
+    // world units/second
+    vel: Vec2,
+    start_time: f64,
+}
+
+impl Spark {
+    // Where it is and how bright (1 down to 0) at a virtual time. It slows
+    // down as it flies, and its tail points back the way it came.
+    pub fn pos_and_alpha(&self, time: f64) -> (Vec2, f64) {
+        let t = ((time - self.start_time) / SPARK_SECS).clamp(0.0, 1.0);
+        let travelled = SPARK_SECS * (t - 0.5 * t * t);
+        (self.pos + travelled * self.vel, 1.0 - t)
+    }
+
+    pub fn dir(&self) -> Vec2 {
+        self.vel.normalize()
+    }
+}
+
+pub struct BorderHit {
+    // world position on the wall
+    pub pos: Vec2,
+    // out of the field, picks the wall that pulses
+    pub normal: Vec2,
+    // 0..1 from how hard it hit
+    pub strength: f64,
+    start_time: f64,
+}
+
+impl BorderHit {
+    // 0..1 of the way through an effect that lasts secs
+    pub fn progress(&self, time: f64, secs: f64) -> f64 {
+        ((time - self.start_time) / secs).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Default)]
+pub struct BorderFx {
+    sparks: Vec<Spark>,
+    hits: Vec<BorderHit>,
+}
+
+impl BorderFx {
+    // Something hit the border at pos, going speed into the wall, which faces
+    // out of the field along normal. Budget is how many hits can be showing at
+    // once, the oldest make way. Seed and key make the sparks, which differ for
+    // every hit.
+    pub fn hit<V: std::hash::Hash + Copy>(
+        &mut self,
+        pos: Vec2,
+        normal: Vec2,
+        speed: f64,
+        time: f64,
+        (seed, key): (u64, V),
+        budget: usize,
+    ) {
+        if self.hits.len() >= budget {
+            let excess = self.hits.len() + 1 - budget.max(1);
+            self.hits.drain(..excess);
+        }
+        let strength = (speed - BORDER_HIT_SPEED) / (BORDER_HIT_FULL_SPEED - BORDER_HIT_SPEED);
+        let strength = strength.clamp(0.0, 1.0);
+        self.hits.push(BorderHit {
+            pos,
+            normal,
+            strength,
+            start_time: time,
+        });
+
+        // sprayed back into the field, fanning out up to 80 degrees either side
+        let num = 3 + (strength * (MAX_SPARKS_PER_HIT - 3) as f64) as u32;
+        for idx in 0..num {
+            let spread = (-1.4..1.4).hash_rand(seed, (key, idx, "spark_dir"));
+            let speed = (0.3..1.0).hash_rand(seed, (key, idx, "spark_speed")) * SPARK_SPEED;
+            let dir = Vec2::from_angle((-normal).atan2() + spread);
+            self.sparks.push(Spark {
+                pos,
+                vel: (0.5 + 0.5 * strength) * speed * dir,
+                start_time: time,
+            });
+        }
+        let max_sparks = budget * MAX_SPARKS_PER_HIT as usize;
+        if self.sparks.len() > max_sparks {
+            let excess = self.sparks.len() - max_sparks;
+            self.sparks.drain(..excess);
+        }
+    }
+
+    // Drop the ones that have died out
+    pub fn update(&mut self, time: f64) {
+        self.sparks.retain(|spark| time - spark.start_time < SPARK_SECS);
+        self.hits.retain(|hit| time - hit.start_time < PULSE_SECS.max(GLOW_SECS));
+    }
+
+    pub fn sparks(&self) -> impl Iterator<Item = &Spark> {
+        self.sparks.iter()
+    }
+
+    pub fn hits(&self) -> impl Iterator<Item = &BorderHit> {
+        self.hits.iter()
+    }
+}
@@ -0,0 +1,175 @@
+use masonry::Vec2;
+
+use crate::{
+    game::{EntityId, TICKS_PER_SECOND},
+    perception::Perception,
+};
+
+//-------------------------------------------------------------------------
+// The carrier: a boss whose body is one compound of collision circles, with
+// turrets attached to it as separate bodies. The turrets throw rocks at the
+// player and have to be knocked out (by ramming them or hitting them with
+// asteroids) before the core is exposed.
+// How it attacks depends on the phase, which follows from the turrets left.
+//-------------------------------------------------------------------------
+
+pub const CORE_RADIUS: f64 = 180.0;
+pub const HULL_RADIUS: f64 = 130.0;
+pub const TURRET_RADIUS: f64 = 45.0;
+
+// Layout in the carrier's frame, which points along y like the ships do
+pub const HULL_OFFSETS: [Vec2; 4] = [
+    Vec2::new(0.0, 260.0),
+    Vec2::new(0.0, -260.0),
+    Vec2::new(-210.0, -60.0),
+    Vec2::new(210.0, -60.0),
+];
+pub const TURRET_OFFSETS: [Vec2; 3] = [
+    Vec2::new(0.0, 400.0),
+    Vec2::new(-350.0, -60.0),
+    Vec2::new(350.0, -60.0),
+];
+// reaches the outside of the furthest part
+pub const BOUNDING_RADIUS: f64 = 450.0;
+
+pub const TURRET_HEALTH: f64 = 100.0;
+pub const CORE_HEALTH: f64 = 300.0;
+
+// rocks thrown are reused once there are this many, so the field doesn't fill up
+const MAX_ROCKS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BossPhase {
+    // all turrets up: the carrier keeps its distance and the turrets throw rocks
+    Turrets,
+    // one turret left: the carrier charges at the player between volleys
+    Charge,
+    // no turrets left: the core is open to hits and throws rocks all around
+    Exposed,
+    Destroyed,
+}
+
+impl BossPhase {
+    pub fn name(self) -> &'static str {
+        match self {
+            BossPhase::Turrets => "Turrets",
+            BossPhase::Charge => "Charge",
+            BossPhase::Exposed => "Core exposed",
+            BossPhase::Destroyed => "Destroyed",
+        }
+    }
+
+    pub fn for_state(turrets_left: usize, core_alive: bool) -> Self {
+        match turrets_left {
+            _ if !core_alive => BossPhase::Destroyed,
+            0 => BossPhase::Exposed,
+            1 => BossPhase::Charge,
+            _ => BossPhase::Turrets,
+        }
+    }
+
+    // ticks between attacks
+    pub fn attack_interval(self) -> u32 {
+        let secs = match self {
+            BossPhase::Turrets => 3.0,
+            BossPhase::Charge => 2.0,
+            BossPhase::Exposed => 4.0,
+            BossPhase::Destroyed => return u32::MAX,
+        };
+        (secs * TICKS_PER_SECOND as f64) as u32
+    }
+
+    // speed (units/tick) the carrier moves at when it isn't charging
+    pub fn cruise_speed(self) -> f64 {
+        match self {
+            BossPhase::Turrets => 2.0,
+            BossPhase::Charge => 2.5,
+            BossPhase::Exposed => 3.5,
+            BossPhase::Destroyed => 0.0,
+        }
+    }
+
+    // how close the carrier tries to get to the player
+    pub fn standoff(self) -> f64 {
+        match self {
+            BossPhase::Turrets => 1200.0,
+            BossPhase::Charge => 900.0,
+            BossPhase::Exposed | BossPhase::Destroyed => 0.0,
+        }
+    }
+}
+
+// Collision circles of the carrier's body in its own frame, the core and the hull sections
+pub fn body_circles() -> Vec<(Vec2, f64)> {
+    let mut circles = vec![(Vec2::ZERO, CORE_RADIUS)];
+    circles.extend(HULL_OFFSETS.map(|offset| (offset, HULL_RADIUS)));
+    circles
+}
+
+pub struct Boss {
+    pub core: EntityId,
+    // attached to the body at TURRET_OFFSETS
+    pub turrets: Vec<EntityId>,
+    pub phase: BossPhase,
+    // ticks until the next attack
+    pub attack_timer: u32,
+    // direction and ticks left of a charge in progress
+    pub charge: Option<(Vec2, u32)>,
+    // where it last saw the player
+    pub perception: Perception,
+    rocks: Vec<EntityId>,
+    next_rock: usize,
+}
+
+impl Boss {
+    pub fn new(core: EntityId, turrets: Vec<EntityId>) -> Self {
+        let phase = BossPhase::Turrets;
+        Self {
+            core,
+            turrets,
+            phase,
+            attack_timer: phase.attack_interval(),
+            charge: None,
+            perception: Perception::default(),
+            rocks: Vec::new(),
+            next_rock: 0,
+        }
+    }
+
+    pub fn is_part(&self, id: EntityId) -> bool {
+        self.core == id || self.turrets.contains(&id)
+    }
+
+    // body, turrets and the rocks it has thrown
+    pub fn owns(&self, id: EntityId) -> bool {
+        self.is_part(id) || self.rocks.contains(&id)
+    }
+
+    // A rock to throw again, or None while a new one should be added
+    pub fn reuse_rock(&mut self) -> Option<EntityId> {
+        if self.rocks.len() < MAX_ROCKS {
+            return None;
+        }
+        let rock = self.rocks[self.next_rock];
+        self.next_rock = (self.next_rock + 1) % self.rocks.len();
+        Some(rock)
+    }
+
+    pub fn add_rock(&mut self, id: EntityId) {
+        self.rocks.push(id);
+    }
+
+    // A turret or rock taken out of the world, a new rock is thrown in its place
+    pub fn forget(&mut self, id: EntityId) {
+        self.turrets.retain(|&turret| turret != id);
+        if let Some(idx) = self.rocks.iter().position(|&rock| rock == id) {
+            self.rocks.remove(idx);
+            if self.next_rock > idx {
+                self.next_rock -= 1;
+            }
+            if self.next_rock >= self.rocks.len() {
+                self.next_rock = 0;
+            }
+        }
+    }
+}
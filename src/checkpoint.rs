@@ -0,0 +1,60 @@
+use crate::save::WorldSnapshot;
+
+//-------------------------------------------------------------------------
+// Checkpoints in an endless run. Every CHECKPOINT_SCORE points, and as each
+// milestone on the timeline comes up, the world is snapshotted in memory the
+// same way it is for a save. Once the run is over the player can go back to
+// the last one (GameWorld::load_checkpoint), losing part of the score it had.
+// Only the last checkpoint is kept, and going back to it doesn't use it up.
+//
+// A run that went back is no longer the one its recording can play back, so
+// it drops the recording and doesn't make the leaderboard or leave a ghost.
+//-------------------------------------------------------------------------
+
+pub const CHECKPOINT_SCORE: u64 = 10_000;
+// of the checkpoint's score lost going back to it
+const CHECKPOINT_PENALTY: f64 = 0.25;
+
+#[derive(Default)]
+pub struct Checkpoints {
+    last: Option<WorldSnapshot>,
+    // score the next one is taken at
+    next_score: u64,
+    // a milestone came up, one is taken once the tick is over
+    due: bool,
+    // times the run went back
+    pub loads: u32,
+}
+
+impl Checkpoints {
+    pub fn new() -> Self {
+        Self {
+            next_score: CHECKPOINT_SCORE,
+            ..Self::default()
+        }
+    }
+
+    // A milestone came up, so there's one to take
+    pub fn milestone(&mut self) {
+        self.due = true;
+    }
+
+    pub fn is_due(&self, score: u64) -> bool {
+        self.due || score >= self.next_score
+    }
+
+    pub fn take(&mut self, snapshot: WorldSnapshot, score: u64) {
+        self.last = Some(snapshot);
+        self.next_score = (score / CHECKPOINT_SCORE + 1) * CHECKPOINT_SCORE;
+        self.due = false;
+    }
+
+    pub fn last(&self) -> Option<&WorldSnapshot> {
+        self.last.as_ref()
+    }
+}
+
+// What's left of the score a checkpoint was taken at, going back to it
+pub fn score_after_load(score: u64) -> u64 {
+    score - (score as f64 * CHECKPOINT_PENALTY).round() as u64
+}
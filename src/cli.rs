@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use clap::{CommandFactory, Parser};
+
+use crate::arena::{ArenaShape, ArenaVariant};
+
+//-------------------------------------------------------------------------
+// Command line arguments. The doc comments on the fields are the --help text.
+//-------------------------------------------------------------------------
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Args {
+    /// Seed for world generation [default: from clock]
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u64>,
+    /// Half size of the world
+    #[arg(long, value_name = "X", default_value_t = 4000.0, value_parser = parse_extent)]
+    pub extent: f64,
+    /// Number of asteroids to spawn
+    #[arg(long, value_name = "N", default_value_t = 80)]
+    pub asteroids: u32,
+    /// Border: fixed, rotating, shrinking or royale
+    #[arg(long, value_name = "NAME", default_value = "fixed", value_parser = parse_arena)]
+    pub arena: ArenaVariant,
+    /// Border shape: square, hexagon or circle
+    #[arg(long, value_name = "SHAPE", default_value = "square", value_parser = parse_border)]
+    pub border: ArenaShape,
+    /// Run the simulation without a window and print the result
+    #[arg(long)]
+    pub headless: bool,
+    /// Time a minute of ticks and frames with N asteroids and no ship, and print the percentiles
+    #[arg(long, value_name = "N")]
+    pub stress: Option<u32>,
+    /// Play back a recorded run (overrides seed, extent, asteroids and border)
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+    /// Record the run to FILE on exit
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+    /// Load the asteroid, loot and encounter spawn tables from FILE
+    #[arg(long, value_name = "FILE")]
+    pub spawn_tables: Option<PathBuf>,
+    /// Check the simulation every tick, and dump the world and stop when it breaks
+    #[arg(long)]
+    pub check_invariants: bool,
+    /// Start in borderless fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// Play as profile NAME, made if it doesn't exist [default: the last one]
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Load settings from PATH instead of the profile's
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+}
+
+// The --help text, for errors found after the arguments were parsed
+pub fn usage() -> String {
+    Args::command().render_help().to_string()
+}
+
+fn parse_extent(value: &str) -> Result<f64, String> {
+    let extent: f64 = value.parse().map_err(|_| format!("not a number: {}", value))?;
+    if !extent.is_finite() || extent <= 0.0 {
+        return Err("must be a positive number".to_string());
+    }
+    Ok(extent)
+}
+
+fn parse_arena(value: &str) -> Result<ArenaVariant, String> {
+    ArenaVariant::from_key(value).ok_or_else(|| "expected fixed, rotating, shrinking or royale".to_string())
+}
+
+fn parse_border(value: &str) -> Result<ArenaShape, String> {
+    ArenaShape::from_key(value).ok_or_else(|| "expected square, hexagon or circle".to_string())
+}
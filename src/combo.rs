@@ -0,0 +1,110 @@
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// Combo meter: risky moves in a row (near misses, picking up air again soon
+// after the last pod) build up a chain, and the chain multiplies the score
+// the player gains while it lasts. Running into anything breaks it, and so
+// does going too long without another risky move.
+//
+// Score is added in a lot of places, so rather than each one asking for the
+// multiplier the world hands the player's score over once a tick and the
+// meter works out the bonus on whatever was gained since the last one.
+//-------------------------------------------------------------------------
+
+// the chain breaks this long after the last risky move
+const COMBO_WINDOW_TICKS: u32 = 6 * TICKS_PER_SECOND as u32;
+// every this many moves in the chain add one to the multiplier
+const COMBO_STEP: u32 = 2;
+pub const COMBO_MAX_MULTIPLIER: u64 = 5;
+// picking up air this soon after the last pod counts as a risky move
+const QUICK_PICKUP_TICKS: u32 = 12 * TICKS_PER_SECOND as u32;
+
+#[derive(Clone, Debug, Default)]
+pub struct Combo {
+    chain: u32,
+    // tick of the last move in the chain
+    last_tick: u32,
+    // of the player, as of the last tick, gains after that get the multiplier
+    scored: u64,
+    last_pickup: Option<u32>,
+    // longest chain of the run
+    pub best: u32,
+}
+
+impl Combo {
+    pub fn chain(&self) -> u32 {
+        self.chain
+    }
+
+    pub fn multiplier(&self) -> u64 {
+        (1 + (self.chain / COMBO_STEP) as u64).min(COMBO_MAX_MULTIPLIER)
+    }
+
+    // A risky move adds to the chain and gives it a fresh window
+    pub fn extend(&mut self, tick: u32) {
+        self.chain += 1;
+        self.last_tick = tick;
+        self.best = self.best.max(self.chain);
+    }
+
+    // The player picked up air. Returns true if it was quick enough to extend the chain.
+    pub fn pickup(&mut self, tick: u32) -> bool {
+        let quick = self.last_pickup.is_some_and(|last| tick - last <= QUICK_PICKUP_TICKS);
+        self.last_pickup = Some(tick);
+        if quick {
+            self.extend(tick);
+        }
+        quick
+    }
+
+    // Breaks the chain, returns how long it was
+    pub fn reset(&mut self) -> u32 {
+        std::mem::take(&mut self.chain)
+    }
+
+    // Breaks the chain if its window has run out, returns how long it was
+    pub fn update(&mut self, tick: u32) -> u32 {
+        if self.chain > 0 && tick - self.last_tick > COMBO_WINDOW_TICKS {
+            return self.reset();
+        }
+        0
+    }
+
+    // Extra score for what the player gained since the last tick. The player's
+    // score is expected to go up by this before the next call.
+    pub fn bonus(&mut self, score: u64) -> u64 {
+        let gained = score.saturating_sub(self.scored);
+        let bonus = gained * (self.multiplier() - 1);
+        self.scored = score + bonus;
+        bonus
+    }
+
+    // "CHAIN LAST_TICK SCORED LAST_PICKUP BEST", as saved
+    pub fn to_text(&self) -> String {
+        let last_pickup = self.last_pickup.map_or_else(|| "-".to_string(), |tick| tick.to_string());
+        format!("{} {} {} {} {}", self.chain, self.last_tick, self.scored, last_pickup, self.best)
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        let &[chain, last_tick, scored, last_pickup, best] = parts.as_slice() else {
+            return None;
+        };
+        Some(Self {
+            chain: chain.parse().ok()?,
+            last_tick: last_tick.parse().ok()?,
+            scored: scored.parse().ok()?,
+            last_pickup: match last_pickup {
+                "-" => None,
+                tick => Some(tick.parse().ok()?),
+            },
+            best: best.parse().ok()?,
+        })
+    }
+
+    // How much of the window is left before the chain breaks, 1 right after a move
+    pub fn window_left(&self, tick: u32) -> f64 {
+        let elapsed = tick.saturating_sub(self.last_tick) as f64;
+        (1.0 - elapsed / COMBO_WINDOW_TICKS as f64).clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    backtrace::Backtrace,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, TryLockError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    actions::{ActionInput, NUM_ACTIONS},
+    game::TICKS_PER_SECOND,
+    replay::Replay,
+    save::WorldSnapshot,
+};
+
+//-------------------------------------------------------------------------
+// Crash reports. A panic hook writes what's needed to look into a crash to
+// the profile's crashes directory: the panic and its backtrace, the seed and
+// tick, the last few seconds of input and the world as it was a moment
+// before. The hook can't get at the world (the panicking thread is likely
+// holding it), so the world hands the reporter its inputs every tick and a
+// snapshot every second instead.
+//
+// If the run was being recorded, the reporter keeps a copy of the recording
+// up to the snapshot, catching up with what was added each time rather than
+// copying it whole, and with the inputs since then that makes a replay of
+// the whole run up to the crash. It's written next to the report, to play
+// back with --replay.
+//-------------------------------------------------------------------------
+
+pub const CRASHES_DIR: &str = "crashes";
+
+const CRASH_HEADER: &str = "space_survival crash 1";
+// how far back the report's inputs go
+const CRASH_INPUT_TICKS: u32 = 5 * TICKS_PER_SECOND as u32;
+// how often the world is snapshotted for a report
+pub const CRASH_SNAPSHOT_TICKS: u32 = TICKS_PER_SECOND as u32;
+
+struct CrashContext {
+    seed: u64,
+    tick: u32,
+    // inputs of the last CRASH_INPUT_TICKS, as a replay of the world
+    inputs: Option<Replay>,
+    snapshot: Option<WorldSnapshot>,
+    // the run's recording up to the snapshot
+    recording: Option<Replay>,
+}
+
+// Handle to the state a report is written from, shared with the panic hook
+#[derive(Clone)]
+pub struct CrashReporter {
+    context: Arc<Mutex<CrashContext>>,
+}
+
+impl CrashReporter {
+    // Install the panic hook, reports go to dir. Panics are still printed as before.
+    pub fn install(dir: PathBuf) -> Self {
+        let context = Arc::new(Mutex::new(CrashContext {
+            seed: 0,
+            tick: 0,
+            inputs: None,
+            snapshot: None,
+            recording: None,
+        }));
+
+        let hook_context = context.clone();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            default_hook(info);
+            let message = match info.payload().downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => info.payload().downcast_ref::<String>().cloned().unwrap_or_default(),
+            };
+            let location = info.location().map_or_else(|| "-".to_string(), |location| location.to_string());
+            write_report(&dir, &hook_context, &message, &location);
+        }));
+
+        Self { context }
+    }
+
+    // Start of a tick of the world with its seed, and the actions' inputs for it.
+    // new_recording makes an empty recording of the world, in case it's a
+    // different one than last tick.
+    pub fn record_tick(
+        &self,
+        seed: u64,
+        tick: u32,
+        inputs: &[ActionInput; NUM_ACTIONS],
+        new_recording: impl FnOnce() -> Replay,
+    ) {
+        let mut context = self.context.lock().unwrap();
+        if context.inputs.is_none() || context.seed != seed || tick < context.tick {
+            // what was kept is of the world before
+            context.inputs = Some(new_recording());
+            context.snapshot = None;
+            context.recording = None;
+        }
+        context.seed = seed;
+        context.tick = tick;
+
+        let recent = context.inputs.as_mut().unwrap();
+        recent.record(tick, inputs);
+        if tick % CRASH_SNAPSHOT_TICKS == 0 {
+            recent.forget_before(tick.saturating_sub(CRASH_INPUT_TICKS));
+        }
+    }
+
+    // The world every CRASH_SNAPSHOT_TICKS, and the run's recording as of then
+    // if it's being recorded
+    pub fn record_snapshot(&self, snapshot: WorldSnapshot, recording: Option<&Replay>) {
+        let mut context = self.context.lock().unwrap();
+        context.snapshot = Some(snapshot);
+        context.recording = match (context.recording.take(), recording) {
+            (Some(mut kept), Some(recording)) => {
+                if !kept.catch_up(recording) {
+                    // a recording of another run
+                    kept = recording.clone();
+                }
+                Some(kept)
+            }
+            (_, recording) => recording.cloned(),
+        };
+    }
+}
+
+fn write_report(dir: &Path, context: &Mutex<CrashContext>, message: &str, location: &str) {
+    let backtrace = Backtrace::force_capture();
+    let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+
+    let mut txt = String::new();
+    writeln!(txt, "{}", CRASH_HEADER).unwrap();
+    writeln!(txt, "message {}", message.replace('\n', " ")).unwrap();
+    writeln!(txt, "location {}", location).unwrap();
+    writeln!(txt, "thread {}", thread).unwrap();
+
+    // a panic while the context was being updated leaves it half done, but
+    // the seed and tick still help
+    let context = match context.try_lock() {
+        Ok(context) => Some(context),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    };
+    let mut replay = None;
+    match context.as_deref() {
+        Some(context) => {
+            writeln!(txt, "seed {}", context.seed).unwrap();
+            writeln!(txt, "tick {}", context.tick).unwrap();
+            writeln!(txt, "backtrace\n{}", backtrace).unwrap();
+            if let Some(inputs) = context.inputs.as_ref() {
+                write!(txt, "inputs\n{}", inputs.to_text()).unwrap();
+            }
+            if let Some(snapshot) = context.snapshot.as_ref() {
+                write!(txt, "snapshot\n{}", snapshot.serialize()).unwrap();
+                let recording = context.recording.as_ref();
+                if let (Some(recording), Some(inputs)) = (recording, context.inputs.as_ref()) {
+                    let mut recording = recording.clone();
+                    recording.append_from(inputs, snapshot.tick);
+                    replay = Some(recording);
+                }
+            }
+        }
+        None => writeln!(txt, "backtrace\n{}", backtrace).unwrap(),
+    }
+
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_millis());
+    let path = dir.join(format!("crash_{}.txt", millis));
+    if let Err(err) = std::fs::create_dir_all(dir).and_then(|()| std::fs::write(&path, txt)) {
+        eprintln!("Failed to write crash report {}: {}", path.display(), err);
+        return;
+    }
+    eprintln!("Crash report written to {}", path.display());
+
+    if let Some(replay) = replay {
+        let path = path.with_extension("replay");
+        match replay.save(&path) {
+            Ok(()) => eprintln!("Replay of the run up to the crash written to {}", path.display()),
+            Err(err) => eprintln!("Failed to write crash replay {}: {}", path.display(), err),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use masonry::Vec2;
+
+//-------------------------------------------------------------------------
+// Short camera moves that take over the view for a moment: easing over to
+// a station the player docks at, flying out to look at an asteroid shower
+// coming in, showing off the carrier as it arrives. A move is a list of
+// keys, each a place to look at and a zoom, and the camera goes through
+// them along a spline, easing in and out of each one. The world starts
+// them from the events of a tick (see GameWorld::update_cutscene), and
+// the player's controls are locked while one plays.
+//
+// Keys can follow the ship instead of a fixed spot, so a move can start
+// and end on the ship while it keeps drifting.
+//-------------------------------------------------------------------------
+
+// the black bars slide in and out over this long
+const BAR_SLIDE_TICKS: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug)]
+pub enum CameraTarget {
+    Ship,
+    Point(Vec2),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraKey {
+    pub target: CameraTarget,
+    // > 1 shows less of the world
+    pub zoom: f64,
+    // from the key before, 0 for the first
+    pub ticks: u32,
+}
+
+impl CameraKey {
+    pub fn new(target: CameraTarget, zoom: f64, ticks: u32) -> Self {
+        Self { target, zoom, ticks }
+    }
+}
+
+pub struct Cutscene {
+    // for logs
+    pub name: &'static str,
+    keys: Vec<CameraKey>,
+    // tick it started at
+    start: u32,
+}
+
+impl Cutscene {
+    pub fn new(name: &'static str, start: u32, keys: Vec<CameraKey>) -> Self {
+        Self { name, keys, start }
+    }
+
+    fn length(&self) -> u32 {
+        self.keys.iter().map(|key| key.ticks).sum()
+    }
+
+    pub fn is_over(&self, tick: u32) -> bool {
+        tick >= self.start + self.length()
+    }
+
+    // How far the black bars have slid in at a time in ticks, 0..1
+    pub fn bars_at(&self, tick: f64) -> f64 {
+        let elapsed = tick - self.start as f64;
+        let left = (self.start + self.length()) as f64 - tick;
+        (elapsed.min(left) / BAR_SLIDE_TICKS).clamp(0.0, 1.0)
+    }
+
+    // Where the camera looks and its zoom at a time in ticks, which can be
+    // between ticks. Keys following the ship look at ship_pos.
+    pub fn view_at(&self, tick: f64, ship_pos: Vec2) -> (Vec2, f64) {
+        let pos = |idx: usize| match self.keys[idx.min(self.keys.len() - 1)].target {
+            CameraTarget::Ship => ship_pos,
+            CameraTarget::Point(pos) => pos,
+        };
+
+        // the key the camera is heading to, and how far along from the one before
+        let mut elapsed = (tick - self.start as f64).max(0.0);
+        let mut next = 1;
+        while next < self.keys.len() && elapsed > self.keys[next].ticks as f64 {
+            elapsed -= self.keys[next].ticks as f64;
+            next += 1;
+        }
+        if next >= self.keys.len() {
+            let last = self.keys.len() - 1;
+            return (pos(last), self.keys[last].zoom);
+        }
+        let t = (elapsed / self.keys[next].ticks.max(1) as f64).clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+
+        let cam_pos = catmull_rom(pos(next.saturating_sub(2)), pos(next - 1), pos(next), pos(next + 1), t);
+        // zoom evenly in steps of scale rather than of size
+        let (from, to) = (self.keys[next - 1].zoom.ln(), self.keys[next].zoom.ln());
+        (cam_pos, (from + (to - from) * t).exp())
+    }
+}
+
+// Point t (0..1) of the way from p1 to p2 on a spline through all four
+fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f64) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * ((2.0 * p1)
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
@@ -0,0 +1,61 @@
+use crate::game::{EntityId, TICKS_PER_SECOND};
+
+//-------------------------------------------------------------------------
+// Clusters of small debris drifting through the field. A piece can't hurt
+// anything: it bounces off what it hits, barely nudging it, and pieces pass
+// through each other. They're drawn over everything else in the world, so a
+// cluster hides what's behind it.
+//
+// The pieces are made once with the world and never despawned. Every so
+// often the cluster that was scattered longest ago is gathered up and
+// scattered again as a new cluster somewhere away from the player, so the
+// field keeps changing without entities coming and going.
+//-------------------------------------------------------------------------
+
+pub const NUM_DEBRIS_CLUSTERS: usize = 8;
+pub const DEBRIS_PER_CLUSTER: usize = 40;
+// pieces are scattered this far from the cluster's center
+pub const DEBRIS_CLUSTER_RADIUS: f64 = 350.0;
+// a cluster is only gathered up once all its pieces are this far from the
+// player, and scattered again at least as far away
+pub const DEBRIS_PLAYER_DIST: f64 = 2500.0;
+// a cluster is scattered again this often
+const DEBRIS_RECYCLE_TICKS: u32 = 15 * TICKS_PER_SECOND as u32;
+
+#[derive(Default)]
+pub struct DebrisField {
+    // pieces of each cluster
+    clusters: Vec<Vec<EntityId>>,
+    // the cluster scattered longest ago
+    next: usize,
+}
+
+impl DebrisField {
+    pub fn add_cluster(&mut self, pieces: Vec<EntityId>) {
+        self.clusters.push(pieces);
+    }
+
+    // The cluster to gather up and scatter again this tick, if one is due
+    pub fn due(&self, tick: u32) -> Option<&[EntityId]> {
+        if tick == 0 || tick % DEBRIS_RECYCLE_TICKS != 0 {
+            return None;
+        }
+        self.clusters.get(self.next).map(Vec::as_slice)
+    }
+
+    // A piece taken out of the world some other way
+    pub fn forget(&mut self, id: EntityId) {
+        for pieces in &mut self.clusters {
+            pieces.retain(|&piece| piece != id);
+        }
+    }
+
+    // Done with the cluster that was due, scattered again or not. Returns its index.
+    pub fn advance(&mut self) -> usize {
+        let due = self.next;
+        if !self.clusters.is_empty() {
+            self.next = (self.next + 1) % self.clusters.len();
+        }
+        due
+    }
+}
@@ -0,0 +1,196 @@
+use masonry::Vec2;
+
+use crate::{game::EntityId, navigation::NavPath, perception::Perception};
+
+//-------------------------------------------------------------------------
+// Factions of ai ships and the player's reputation with each of them.
+// Pirates start out hostile, traders neutral and the patrol friendly, but
+// what the player does (robbing traders, taking out pirates, ...) moves
+// them around.
+//-------------------------------------------------------------------------
+
+const MIN_REPUTATION: i32 = -100;
+const MAX_REPUTATION: i32 = 100;
+// at or below this a faction is hostile, at or above the negation friendly
+const HOSTILE_REPUTATION: i32 = -25;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Faction {
+    Pirates,
+    Traders,
+    Patrol,
+}
+
+pub const FACTIONS: [Faction; 3] = [Faction::Pirates, Faction::Traders, Faction::Patrol];
+
+impl Faction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Faction::Pirates => "Pirates",
+            Faction::Traders => "Traders",
+            Faction::Patrol => "Patrol",
+        }
+    }
+
+    // name for a single ship of the faction
+    pub fn ship_name(self) -> &'static str {
+        match self {
+            Faction::Pirates => "Pirate",
+            Faction::Traders => "Trader",
+            Faction::Patrol => "Patrol",
+        }
+    }
+
+    // name used in save files
+    pub fn key(self) -> &'static str {
+        match self {
+            Faction::Pirates => "pirates",
+            Faction::Traders => "traders",
+            Faction::Patrol => "patrol",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        FACTIONS.into_iter().find(|faction| faction.key() == key)
+    }
+
+    pub fn color(self) -> xilem::Color {
+        match self {
+            Faction::Pirates => xilem::Color::rgb8(0xff, 0x40, 0x40),
+            Faction::Traders => xilem::Color::rgb8(0xf0, 0xd0, 0x40),
+            Faction::Patrol => xilem::Color::rgb8(0x40, 0x90, 0xff),
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Faction::Pirates => 0,
+            Faction::Traders => 1,
+            Faction::Patrol => 2,
+        }
+    }
+
+    fn initial_reputation(self) -> i32 {
+        match self {
+            Faction::Pirates => -50,
+            Faction::Traders => 0,
+            Faction::Patrol => 30,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stance {
+    Hostile,
+    Neutral,
+    Friendly,
+}
+
+impl Stance {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stance::Hostile => "hostile",
+            Stance::Neutral => "neutral",
+            Stance::Friendly => "friendly",
+        }
+    }
+}
+
+// The player's standing with each faction
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reputation {
+    values: [i32; 3],
+}
+
+impl Default for Reputation {
+    fn default() -> Self {
+        Self {
+            values: FACTIONS.map(Faction::initial_reputation),
+        }
+    }
+}
+
+impl Reputation {
+    // values in FACTIONS order, as saved
+    pub fn from_values(values: [i32; 3]) -> Self {
+        Self {
+            values: values.map(|value| value.clamp(MIN_REPUTATION, MAX_REPUTATION)),
+        }
+    }
+
+    pub fn values(&self) -> [i32; 3] {
+        self.values
+    }
+
+    pub fn get(&self, faction: Faction) -> i32 {
+        self.values[faction.index()]
+    }
+
+    pub fn adjust(&mut self, faction: Faction, delta: i32, reason: &str) {
+        let value = &mut self.values[faction.index()];
+        *value = (*value + delta).clamp(MIN_REPUTATION, MAX_REPUTATION);
+        log::info!(
+            "reputation faction={} delta={} value={} reason={}",
+            faction.key(),
+            delta,
+            value,
+            reason
+        );
+    }
+
+    pub fn stance(&self, faction: Faction) -> Stance {
+        let value = self.get(faction);
+        if value <= HOSTILE_REPUTATION {
+            Stance::Hostile
+        } else if value >= -HOSTILE_REPUTATION {
+            Stance::Friendly
+        } else {
+            Stance::Neutral
+        }
+    }
+}
+
+// Behavior state of an ai ship belonging to a faction
+pub struct FactionShip {
+    pub faction: Faction,
+    // where the ship goes back to when it has nothing to do
+    pub home: Vec2,
+    // stations a trader flies between, in order
+    pub route: Vec<EntityId>,
+    pub route_idx: usize,
+    // ticks left backing off after ramming someone
+    pub cooldown: u32,
+    // where it last saw the player, for hunting them
+    pub perception: Perception,
+    // how a trader gets to its next station around the rocks
+    pub nav_path: NavPath,
+}
+
+impl FactionShip {
+    pub fn new(faction: Faction, home: Vec2) -> Self {
+        Self {
+            faction,
+            home,
+            route: Vec::new(),
+            route_idx: 0,
+            cooldown: 0,
+            perception: Perception::default(),
+            nav_path: NavPath::default(),
+        }
+    }
+
+    // A station on the route taken out of the world, the trader goes on to
+    // the one it would have gone to after it
+    pub fn forget_station(&mut self, id: EntityId) {
+        let Some(idx) = self.route.iter().position(|&station| station == id) else {
+            return;
+        };
+        self.route.remove(idx);
+        if self.route_idx > idx {
+            self.route_idx -= 1;
+        }
+        if self.route_idx >= self.route.len() {
+            self.route_idx = 0;
+        }
+    }
+}
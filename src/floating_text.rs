@@ -0,0 +1,66 @@
+use masonry::Vec2;
+
+//-------------------------------------------------------------------------
+// Short lived labels anchored to a spot in the world ("+1000", "Air +15s"),
+// rising off it and fading away. They run on virtual time like animations,
+// so they stop while paused. The text is laid out once, see TextCache.
+//-------------------------------------------------------------------------
+
+// how long a label stays up
+const FLOAT_SECS: f64 = 1.2;
+// screen pixels it rises over that time
+const FLOAT_RISE: f64 = 60.0;
+
+pub struct FloatingText {
+    pub text: String,
+    // world position it started at
+    pub pos: Vec2,
+    pub color: xilem::Color,
+    // virtual time in seconds
+    pub start_time: f64,
+}
+
+impl FloatingText {
+    // Screen pixels it has risen, and its alpha, at a virtual time
+    pub fn rise_and_alpha(&self, time: f64) -> (f64, f64) {
+        let t = ((time - self.start_time) / FLOAT_SECS).clamp(0.0, 1.0);
+        // slows as it rises, and only fades over the second half
+        let rise = FLOAT_RISE * (1.0 - (1.0 - t).powi(2));
+        let alpha = (2.0 - 2.0 * t).min(1.0);
+        (rise, alpha)
+    }
+
+    fn is_done(&self, time: f64) -> bool {
+        time - self.start_time >= FLOAT_SECS
+    }
+}
+
+#[derive(Default)]
+pub struct FloatingTexts {
+    texts: Vec<FloatingText>,
+}
+
+impl FloatingTexts {
+    // Budget is how many can be up at once, the oldest make way
+    pub fn spawn(&mut self, text: String, pos: Vec2, color: xilem::Color, time: f64, budget: usize) {
+        if self.texts.len() >= budget {
+            let excess = self.texts.len() + 1 - budget.max(1);
+            self.texts.drain(..excess);
+        }
+        self.texts.push(FloatingText {
+            text,
+            pos,
+            color,
+            start_time: time,
+        });
+    }
+
+    // Drop the ones that have faded away
+    pub fn update(&mut self, time: f64) {
+        self.texts.retain(|text| !text.is_done(time));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FloatingText> {
+        self.texts.iter()
+    }
+}
@@ -1,36 +1,419 @@
 use std::{
-    collections::HashSet,
-    f64::consts::{PI, SQRT_2, TAU},
+    collections::{HashSet, VecDeque},
+    f64::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU},
+    fmt::Write as _,
     hash::{Hash, Hasher},
-    ops::Range,
+    ops::{Range, RangeInclusive},
     sync::Arc,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use masonry::{
     parley::{
         self,
         style::{FontFamily, FontStack, StyleProperty},
+        FontContext,
     },
-    Affine, PaintCtx, Size, Vec2,
+    Affine, Point, Rect, Size, Vec2,
 };
 use vello::Scene;
 use winit::{
-    event::{DeviceEvent, ElementState, RawKeyEvent, WindowEvent},
+    event::MouseButton,
     keyboard::{KeyCode, PhysicalKey},
 };
 
-use crate::game_shapes::{
-    air_pod_scene, air_pod_shape, asteroid_shape, border_shape, flame_scene, ship_shape,
+use crate::{
+    actions::{Action, ActionState, Bindings},
+    air_pod::PodTier,
+    arena::{arena_name, Arena, ArenaShape, ArenaVariant},
+    attract::{Attract, ATTRACT_IDLE_SECS},
+    audio::{Audio, Cue},
+    border_fx::{BorderFx, BORDER_HIT_SPEED, GLOW_SECS, PULSE_SECS},
+    boss::{
+        self, Boss, BossPhase, BOUNDING_RADIUS, CORE_HEALTH, TURRET_HEALTH, TURRET_OFFSETS,
+    },
+    checkpoint::{score_after_load, Checkpoints},
+    combo::{Combo, COMBO_MAX_MULTIPLIER},
+    crash::{CrashReporter, CRASHES_DIR, CRASH_SNAPSHOT_TICKS},
+    cutscene::{CameraKey, CameraTarget, Cutscene},
+    debris::{
+        DebrisField, DEBRIS_CLUSTER_RADIUS, DEBRIS_PER_CLUSTER, DEBRIS_PLAYER_DIST, NUM_DEBRIS_CLUSTERS,
+    },
+    faction::{Faction, FactionShip, Reputation, Stance, FACTIONS},
+    floating_text::FloatingTexts,
+    game_shapes::{
+        air_pod_shape, asteroid_colors, asteroid_shape, barrier_shape, border_pulse_scene, border_shape,
+        cargo_pod_shape,
+        bolt_shape, carrier_core_shape, colored_ship_shape, convoy_shape, debris_shape, derelict_shape,
+        dust_shape, gun_shape, mine_shape, ship_shape, station_shape, turret_shape, AnimationClip,
+    },
+    grapple::{Grapple, GRAPPLE_RANGE, GRAPPLE_SNAP_STRETCH},
+    haptics::{Haptics, Pulse},
+    hints::{Hint, Hints},
+    input::{Button, InputEvent, InputKind, InputManager},
+    lives::{score_after_life_lost, Lives, RESPAWN_AIR_SECS, RESPAWN_CLEARANCE, RESPAWN_SHIELD_TICKS},
+    menu::{Menu, MenuAction},
+    metrics::{MetricsRecorder, RunSummary},
+    navigation::{NavGrid, NavPath, BLOCKED_CLUTTER},
+    nebula::{self, Nebula, NEBULA_JAM_DEPTH, NEBULA_SCAN_RADIUS},
+    perception::{nebula_visibility, Senses, BOSS_MEMORY_TICKS, SIGHT_BLOCKER_RADIUS},
+    online_leaderboard::{LeaderboardClient, Submission, TopScores},
+    platform_services::{Achievement, LocalServices, PlatformServices},
+    profile::{
+        Profile, ACHIEVEMENTS_FILE, DEFAULT_PROFILE, HINTS_FILE, NUM_SAVE_SLOTS, PROGRESSION_FILE,
+        SHIP_STYLE_FILE,
+    },
+    pilot::{Pilot, PilotSkill, ShipControls, ShipState, SkillLevel},
+    progression::{credits_for_run, Loadout, Progression, ShipClass, WorldModifier, UNLOCKS},
+    post_process::{PhotoFilter, PostParams},
+    race::{
+        format_ticks, CourseId, Leaderboard, LeaderboardEntry, Race, RaceFinish, LEADERBOARD_PATH,
+    },
+    render_frame::{CameraSnapshot, FrameParams, HudNumbers, RenderEntity, RenderFrame, RenderLabel},
+    replay::Replay,
+    rewind::Rewind,
+    repulsor::{repulsor_delta_v, Repulsor, REPULSOR_HOLD, REPULSOR_RADIUS},
+    rng::{RngStream, NUM_RNG_STREAMS, RNG_STREAMS},
+    save::{self, Autosaver, EntitySnapshot, WorldSnapshot, AUTOSAVE_PATH},
+    scenery::{
+        wrap_background_ship, wrap_dust, BACKGROUND_SHIP_ALPHA, BACKGROUND_SHIP_SPEED, CARGO_POD_OFFSET,
+        DUST_DRIFT_SPEED, DUST_RADIUS, NUM_BACKGROUND_SHIPS, NUM_DUST_MOTES, SCENERY_CARGO, SCENERY_DUST,
+        SCENERY_FLAME, SCENERY_SHIP,
+    },
+    settings::{Settings, ViewportMode},
+    ship_style::{FlameColor, ShipStyle},
+    spawn_table::{self, SpawnTable, SpawnTables},
+    sprites::SpriteSheets,
+    status::{StatusEffects, StatusKind},
+    text::{draw_mono_text, draw_text, TextStyle},
+    tuning::{TuningPanel, TuningParams},
+    timeline::{MilestoneEvent, Timeline},
+    turret::{
+        Projectile, Turret, TurretAmmo, BOLT_HIT_AIR, BOLT_HULL_DAMAGE, BOLT_RADIUS, BOLT_SPEED,
+        SHIP_TURRET_OFFSET, STATION_TURRET_OFFSETS,
+    },
+    tutorial::{Tutorial, TutorialStep, NUM_STEPS},
 };
 
 const MICROS_PER_SECOND: u64 = 1_000_000;
-const TICKS_PER_SECOND: u64 = 30;
+pub const TICKS_PER_SECOND: u64 = 30;
 // Rounding is fine, this const is authorative, so ~30 ticks/sec
 const MICROS_PER_TICK: u64 = MICROS_PER_SECOND / TICKS_PER_SECOND;
 
 const TARGET_FPS: u64 = 60;
-const MAX_SHIP_SPEED: f64 = 30.0;
+pub const MAX_SHIP_SPEED: f64 = 30.0;
+
+// change in ship velocity (units/tick) from a single tick of collisions that counts as a heavy hit
+const HEAVY_HIT_DELTA_V: f64 = 4.0;
+const HIT_FLASH_TICKS: u32 = 4;
+// a heavy hit cracks the hull, each crack leaking this much more air a tick for a while
+const LEAK_TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+const LEAK_AIR_PER_TICK: u64 = 2;
+// the replay ghost is drawn see-through
+const GHOST_ALPHA: f64 = 0.35;
+
+// floating text colors, for points, air and hull damage
+const SCORE_LABEL_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0xff, 0x80);
+const AIR_LABEL_COLOR: xilem::Color = xilem::Color::rgb8(0x0, 0xd4, 0xf8);
+const DAMAGE_LABEL_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0x40, 0x40);
+// the combo meter goes from the first color to the second as the multiplier rises
+const COMBO_LOW_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0xe0, 0x60);
+const COMBO_HIGH_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0x40, 0x80);
+const HIT_INDICATOR_SECS: f64 = 1.0;
+const MAX_CAMERA_KICK: f64 = 40.0;
+// change in ship velocity (units/tick) in a tick that reads as 1 G on the G meter
+const DELTA_V_PER_G: f64 = 0.5;
+// the meter holds the hardest hit and lets it go by this much a tick
+const G_METER_DECAY: f64 = 0.95;
+// screen edges are fully red at this many G
+const G_TINT_FULL: f64 = 12.0;
+
+// an asteroid's variant is its outline plus NUM_ASTEROID_SHAPES times its material
+pub const NUM_ASTEROID_SHAPES: u32 = 6;
+// of the small, medium and large outlines, two of each
+const ASTEROID_RADII: [f64; 3] = [30.0, 100.0, 150.0];
+pub const NUM_ASTEROID_VARIANTS: u32 = NUM_ASTEROID_SHAPES * ASTEROID_MATERIALS.len() as u32;
+// rubble bigger than the small outlines breaks up when hit this hard (units/tick)...
+const RUBBLE_SPLIT_SPEED: f64 = 6.0;
+// ...into small pieces flying apart this fast
+const RUBBLE_SPREAD_SPEED: f64 = 2.0;
+
+// salvaging needs the ship within this gap of the derelict's hull...
+const SALVAGE_RANGE: f64 = 80.0;
+// ...moving along with it (relative speed in units/tick)...
+const SALVAGE_MAX_REL_SPEED: f64 = 2.0;
+// ...for this long
+const SALVAGE_TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+
+// ship trail on the minimap records a position every few ticks
+const TRAIL_SAMPLE_TICKS: u32 = 3;
+
+const WAYPOINT_REACHED_DIST: f64 = 100.0;
+const WAYPOINT_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0xe0, 0x40);
+const ESCORT_DESTINATION_COLOR: xilem::Color = xilem::Color::rgb8(0x40, 0xff, 0x80);
+const RACE_GATE_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0xa0, 0x20);
+const COMPASS_COLOR: xilem::Color = xilem::Color::rgb8(0x0, 0xd4, 0xf8);
+// screen pixels from the ship's center
+const COMPASS_RADIUS: f64 = 70.0;
+// the velocity arrow reaches where the ship will be this many ticks from now
+const VELOCITY_ARROW_TICKS: f64 = 20.0;
+// threat lines show where the closest moving asteroids drift over the next few
+// seconds, a point every few ticks, red for the ones heading into the ship
+const THREAT_LINE_TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+const THREAT_LINE_STEP: u32 = 3;
+const MAX_THREAT_LINES: usize = 20;
+const THREAT_LINE_RANGE: f64 = 1500.0;
+const THREAT_LINE_MIN_SPEED: f64 = 0.5;
+const THREAT_LINE_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0x50, 0x40);
+
+// one full turn of the radar beam
+const RADAR_SWEEP_TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+// world distance covered by the minimap (and radar) from the center to the edge
+const MINI_MAP_WORLD_RADIUS: f64 = 2000.0;
+// past this many entities in view, the maps draw asteroids and debris as
+// one dot per spatial grid cell instead of one by one
+const MAP_LOD_ENTITIES: usize = 250;
+// a cell's dot fills the cell at this many entities
+const MAP_LOD_FULL_CELL: usize = 12;
+const MAP_LOD_COLOR: xilem::Color = xilem::Color::rgb8(0x98, 0x90, 0x88);
+// stations and derelicts this close to the player's ship go on the star map
+const DISCOVER_RADIUS: f64 = 1200.0;
+
+// faction ships notice targets within this distance
+const FACTION_SIGHT: f64 = 1500.0;
+// traders move on to the next station on their route this close to the current one
+const DOCK_RANGE: f64 = 200.0;
+// the player docks coming to rest this close to a station, slower than this (units/tick)
+const DOCK_SPEED: f64 = 2.0;
+// cutscene camera moves, zoom and ticks to get there and to hold each one
+const DOCK_ZOOM: f64 = 1.6;
+const SHOWER_FLYOVER_ZOOM: f64 = 0.6;
+const BOSS_INTRO_ZOOM: f64 = 0.8;
+const CUTSCENE_MOVE_TICKS: u32 = 40;
+const CUTSCENE_HOLD_TICKS: u32 = 30;
+// black bars across the top and bottom while one plays, of the screen height
+const CUTSCENE_BAR_HEIGHT: f64 = 0.08;
+// ore a trader loads at every station
+const TRADER_CARGO_ORE: u32 = 5;
+// thrust force of a ship's engine, about what a standard hull weighs so an empty one
+// speeds up by 1 unit/tick every tick. Each thrusters upgrade adds to it.
+const SHIP_THRUST: f64 = 2700.0;
+const THRUSTERS_UPGRADE_THRUST: f64 = 400.0;
+// mass of one unit of ore in the hold
+const ORE_MASS: f64 = 40.0;
+// air pods this close to a ship with a magnet are pulled in, each magnet past
+// the first reaching half as far again (up to MAX_MAGNETS)
+const MAGNET_RADIUS: f64 = 450.0;
+const MAX_MAGNETS: usize = 3;
+// units/tick² of pull per unit away, and of drag per unit/tick the pod moves
+// relative to the ship, capped so far pods don't fly in
+const MAGNET_STIFFNESS: f64 = 0.0004;
+const MAGNET_DAMPING: f64 = 0.02;
+const MAGNET_MAX_ACCEL: f64 = 0.3;
+// force of the maneuvering thrusters used to brake and strafe, much weaker than the engine
+const RCS_THRUST: f64 = 800.0;
+// where the maneuvering thrusters are on the hull, and the angle their flames point at
+// (0 is along the ship's nose)
+pub const RCS_BRAKE_NOZZLES: [(Vec2, f64); 2] = [(Vec2::new(-4.0, 15.0), 0.3), (Vec2::new(4.0, 15.0), -0.3)];
+// strafing right fires the nozzle on the left, and the other way around
+pub const RCS_LEFT_NOZZLE: (Vec2, f64) = (Vec2::new(9.0, -5.0), -0.5 * PI);
+pub const RCS_RIGHT_NOZZLE: (Vec2, f64) = (Vec2::new(-9.0, -5.0), 0.5 * PI);
+// flight assist brakes above this speed (units/tick), and leaves drift slower than this alone
+const FLIGHT_ASSIST_MAX_SPEED: f64 = 20.0;
+const FLIGHT_ASSIST_MIN_DRIFT: f64 = 0.1;
+// extra air used every tick the assist fires a thruster
+const FLIGHT_ASSIST_AIR: u64 = 1;
+// extra air used every tick the cloak is on, and how see-through the ship is drawn with it
+const CLOAK_AIR: u64 = 2;
+const CLOAK_ALPHA: f64 = 0.3;
+// engine heat (0..1) gained per tick of main engine and of maneuvering thrusters, and lost
+// per tick with both off. Once it's full the engines are overheated and shut down for as
+// long as it takes them to cool back down to HEAT_RESTART.
+const HEAT_THRUST: f64 = 1.0 / (6.0 * TICKS_PER_SECOND as f64);
+const HEAT_RCS: f64 = 1.0 / (20.0 * TICKS_PER_SECOND as f64);
+const HEAT_COOLING: f64 = 1.0 / (4.0 * TICKS_PER_SECOND as f64);
+const HEAT_RESTART: f64 = 0.25;
+const OVERHEAT_TICKS: u32 = ((1.0 - HEAT_RESTART) / HEAT_COOLING) as u32;
+// the hull starts glowing at this much heat
+const HEAT_GLOW: f64 = 0.5;
+const HEAT_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0x50, 0x10);
+const REPULSOR_COLOR: xilem::Color = xilem::Color::rgb8(0xa0, 0xd8, 0xff);
+const GRAPPLE_COLOR: xilem::Color = xilem::Color::rgb8(0xc8, 0xc0, 0xb0);
+// change in velocity (units/tick) from a ship on ship collision that counts as ramming
+const RAM_DELTA_V: f64 = 3.0;
+// after ramming, faction ships back off to their home for a while
+const RAM_COOLDOWN_TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+const PIRATE_AIR_STEAL: u64 = 5 * TICKS_PER_SECOND;
+// taking out a pirate this close to a trader counts as protecting it
+const PROTECT_RANGE: f64 = 600.0;
+
+// the grid AI ships plan routes on is rebuilt this often
+const NAV_GRID_TICKS: u32 = TICKS_PER_SECOND as u32;
+
+// escort convoy starts and ends this far in from the world edge
+const ESCORT_EDGE_MARGIN: f64 = 400.0;
+const ESCORT_CRUISE_SPEED: f64 = 4.0;
+const ESCORT_ARRIVE_DIST: f64 = 250.0;
+const NUM_ESCORT_MINES: u32 = 12;
+const CONVOY_HULL: f64 = 100.0;
+// collision circles (offset, radius) following the ship and convoy hulls, nose along +y
+const SHIP_CIRCLES: [(Vec2, f64); 3] = [
+    (Vec2::new(0.0, -12.0), 13.0),
+    (Vec2::new(0.0, 5.0), 9.0),
+    (Vec2::new(0.0, 17.0), 5.0),
+];
+const CONVOY_CIRCLES: [(Vec2, f64); 4] = [
+    (Vec2::new(0.0, -48.0), 22.0),
+    (Vec2::new(0.0, -10.0), 22.0),
+    (Vec2::new(0.0, 28.0), 22.0),
+    (Vec2::new(0.0, 55.0), 14.0),
+];
+// collisions below this change in velocity (units/tick) don't damage a hull...
+const HULL_DAMAGE_MIN_DELTA_V: f64 = 0.5;
+// ...and above it each unit of delta v takes this much off
+const HULL_DAMAGE_PER_DELTA_V: f64 = 8.0;
+const MINE_DAMAGE: f64 = 35.0;
+// velocity (units/tick) a mine blast adds to whatever set it off
+const MINE_KNOCKBACK: f64 = 10.0;
+const MINE_SCORE: u64 = 250;
+// variant of a mine that knocks out the controls of the ship that sets it off instead
+pub const MINE_EMP: u32 = 1;
+pub const NUM_MINE_VARIANTS: u32 = 2;
+const NUM_EMP_MINES: u32 = 4;
+// an EMP stuns for this long, and the engines stay slowed for a while after
+const EMP_STUN_TICKS: u32 = 2 * TICKS_PER_SECOND as u32;
+const EMP_SLOW_TICKS: u32 = 5 * TICKS_PER_SECOND as u32;
+// of a ship's thrust while it's slowed
+const SLOWED_THRUST: f64 = 0.5;
+// stations turn this fast (radians/tick)
+const STATION_SPIN_RATE: f64 = 0.004;
+// barriers are capsules this long and thick, sweeping to either side of where they were placed
+const BARRIER_HALF_LENGTH: f64 = 200.0;
+const BARRIER_RADIUS: f64 = 20.0;
+const BARRIER_SWEEP_REACH: f64 = 300.0;
+const BARRIER_SWEEP_SECS: u32 = 8;
+const ESCORT_DELIVERY_SCORE: u64 = 10000;
+// bonus for a fully intact convoy, scaled by hull left
+const ESCORT_HULL_SCORE: f64 = 10000.0;
+
+// asteroids within DANGER_DENSITY_RADIUS for the ship to be in a dense part of the
+// field, and fewer than DANGER_DENSITY_CLEAR to be out of it again
+const DANGER_DENSITY_RADIUS: f64 = 600.0;
+const DANGER_DENSITY_COUNT: u32 = 8;
+const DANGER_DENSITY_CLEAR: u32 = 6;
+// same for the distance to the border
+const DANGER_BORDER_DIST: f64 = 250.0;
+const DANGER_BORDER_CLEAR: f64 = 350.0;
+// the border warning starts fading in this far from the wall, and further out
+// when the ship is heading for it: as far as it would go in BORDER_WARN_SECS
+const BORDER_WARN_DIST: f64 = 400.0;
+const BORDER_WARN_SECS: f64 = 2.5;
+const DANGER_TINT_FADE_SECS: f64 = 0.5;
+
+// an asteroid passing within NEAR_MISS_GAP of the ship (surface to surface) at
+// NEAR_MISS_SPEED or more without touching it is a near miss. These are the defaults,
+// the tuning panel can change them.
+pub const NEAR_MISS_GAP: f64 = 40.0;
+pub const NEAR_MISS_SPEED: f64 = 8.0;
+// a near miss slows time down to this (of the tick rate), easing back to normal
+// over NEAR_MISS_SLOW_MO_SECS of wall clock time
+pub const NEAR_MISS_SLOW_MO: f64 = 0.6;
+const NEAR_MISS_SLOW_MO_SECS: f64 = 0.5;
+// every collision circle is shrunk by this much when checking for contacts, so
+// outlines can graze each other before they bounce. The tuning panel can change it.
+pub const COLLISION_MARGIN: f64 = 1.0;
+// collisions at this speed and up make a sound, full volume at the full speed
+const IMPACT_SOUND_SPEED: f64 = 3.0;
+const IMPACT_SOUND_FULL_SPEED: f64 = 15.0;
+// loudest ones in a tick, so a pileup doesn't turn into noise
+const MAX_IMPACT_SOUNDS: usize = 3;
+// sounds from things this far to the side of the camera are all the way left or right
+const SOUND_PAN_DIST: f64 = 1000.0;
+// half volume at this distance, silent past the max
+const SOUND_FALLOFF_DIST: f64 = 800.0;
+const SOUND_MAX_DIST: f64 = 3000.0;
+// what the music counts as intense
+const MUSIC_DANGER_RADIUS: f64 = 800.0;
+const MUSIC_DANGER_ASTEROIDS: f64 = 10.0;
+const MUSIC_DANGER_CLOSING_SPEED: f64 = 10.0;
+const MUSIC_DANGER_AIR_SECS: f64 = 15.0;
+
+// endless run length for the survivor achievement
+const SURVIVOR_SECS: u64 = 5 * 60;
+
+// air left that gets a warning
+const LOW_AIR_SECS: u64 = 15;
+const LOW_AIR_COLOR: xilem::Color = xilem::Color::rgb8(0xff, 0x40, 0x40);
+// an air pod about to run dry blinks this many times a second
+const POD_BLINK_SECS: u64 = 3;
+const POD_BLINK_RATE: f64 = 4.0;
+
+// air doesn't drop below this during the tutorial
+const TUTORIAL_MIN_AIR: u64 = 30 * TICKS_PER_SECOND;
+// the asteroid to dodge comes from this far ahead of the ship, give or take the spread
+const TUTORIAL_ASTEROID_DIST: f64 = 900.0;
+const TUTORIAL_ASTEROID_SPREAD: f64 = 100.0;
+const TUTORIAL_ASTEROID_SPEED: f64 = 10.0;
+// gap it has to close to before getting away again counts as dodging it
+const TUTORIAL_DODGE_GAP: f64 = 250.0;
+const TUTORIAL_COLOR: xilem::Color = xilem::Color::rgb8(0x80, 0xff, 0xc0);
+
+// what a new world has besides its asteroids (see GameWorld::generate)
+const NUM_DERELICTS: usize = 6;
+const NUM_STATIONS: u32 = 3;
+// which factions is up to the encounter spawn table
+const NUM_FACTION_SHIPS: usize = 9;
+const NUM_BARRIERS: u32 = 3;
+// debris is light enough that a piece barely nudges whatever it hits
+const DEBRIS_DENSITY: f64 = 0.2;
+// units/tick a cluster drifts at, and how much its pieces stray from that
+const DEBRIS_DRIFT_SPEED: f64 = 1.5;
+const DEBRIS_SPREAD_SPEED: f64 = 0.3;
+
+// asteroids in a shower, and how many more each pass through the timeline
+const SHOWER_ASTEROIDS: u32 = 15;
+const SHOWER_ASTEROIDS_PER_CYCLE: u32 = 10;
+// depth of the strip along the edge that a shower comes in from
+const SHOWER_DEPTH: f64 = 300.0;
+const SHOWER_MIN_SPEED: f64 = 6.0;
+const SHOWER_MAX_SPEED: f64 = 12.0;
+// the spawner looks at the field this often, and adds at most this many at a time
+const SPAWNER_INTERVAL_TICKS: u32 = 15;
+const SPAWNER_MAX_ADDED: usize = 10;
+// strip along the edge new asteroids come in from, and how fast
+const SPAWNER_EDGE_DEPTH: f64 = 200.0;
+const SPAWNER_MIN_SPEED: f64 = 3.0;
+const SPAWNER_MAX_SPEED: f64 = 8.0;
+// asteroids are only added and taken away this far from the ship, well out of sight
+const SPAWNER_MIN_PLAYER_DIST: f64 = 1500.0;
+// an asteroid heading out within this of a wall counts as leaving the field
+const SPAWNER_EXIT_GAP: f64 = 20.0;
+// in fog, things are clearly visible up to the inner radius and gone past the outer
+const FOG_CLEAR_RADIUS: f64 = 350.0;
+const FOG_RADIUS: f64 = 900.0;
+// specks of static on the minimap deep in a nebula
+const NEBULA_NOISE_DOTS: u32 = 250;
+
+// the carrier's rocks, thrown at the player or all around once the core is exposed
+const BOSS_ROCK_SPEED: f64 = 14.0;
+const BOSS_BURST_ROCKS: u32 = 8;
+const BOSS_CHARGE_SPEED: f64 = 14.0;
+const BOSS_CHARGE_TICKS: u32 = 45;
+// max carrier rotation per tick
+const BOSS_TURN_RATE: f64 = 0.01;
+// impact speed on a turret or the exposed core that starts doing damage, and damage per unit over
+const BOSS_DAMAGE_MIN_SPEED: f64 = 4.0;
+const BOSS_DAMAGE_PER_SPEED: f64 = 4.0;
+// air (ticks) knocked out of the player by a rock or a charge
+const BOSS_HIT_AIR: u64 = 3 * TICKS_PER_SECOND;
+const BOSS_TURRET_SCORE: u64 = 2000;
+const BOSS_SCORE: u64 = 20000;
+const BOSS_COLOR: xilem::Color = xilem::Color::rgb8(0xd0, 0x40, 0xa0);
+
+// how long a split time stays on screen after passing a gate
+const RACE_SPLIT_TICKS: u32 = 2 * TICKS_PER_SECOND as u32;
+// how many leaderboard entries are shown when the race is over
+const RACE_TOP_SHOWN: usize = 5;
 
 // --- MARK: GameWorld ---
 
@@ -40,11 +423,77 @@ const MAX_SHIP_SPEED: f64 = 30.0;
 pub struct GameWorld {
     seed: u64,
     sequence: u32,
+    // where new objects go
+    placement_rng: RngStream,
+    // asteroids' make and motion, and where the spawner brings them in
+    asteroid_rng: RngStream,
+    // what's in the derelicts
+    loot_rng: RngStream,
+    // which faction ships a world gets
+    encounter_rng: RngStream,
+    spawn_tables: Arc<SpawnTables>,
     max_radius: f64,
     resources: Resources,
+    settings: Settings,
     entity_store: EntityStore,
     spatial_db: SpatialDb,
+    // how cluttered each part of the field is, for AI ships planning routes
+    nav_grid: Option<NavGrid>,
     input_manager: InputManager,
+    actions: ActionState,
+    camera: Camera,
+    // camera move playing, the player's controls are locked meanwhile
+    cutscene: Option<Cutscene>,
+    // station the player's ship is docked at, until it leaves its range
+    docked_at: Option<EntityId>,
+    hit_indicator: Option<HitIndicator>,
+    // hardest recent hit on the player's ship, in G, decaying
+    g_meter: f64,
+    // zones the player's ship is in, and when it last entered one (virtual secs)
+    danger_zones: Vec<DangerZone>,
+    danger_entered_time: f64,
+    // asteroids passing close by the player's ship fast
+    near_passes: Vec<NearPass>,
+    // wall clock time of the last near miss, time runs slow for a moment after
+    slow_mo_start: Option<Instant>,
+    combo: Combo,
+    audio: Option<Audio>,
+    haptics: Option<Haptics>,
+    // sound and rumble are wanted (not headless), remade when the profile changes
+    feedback_enabled: bool,
+    trail: Trail,
+    // placed by clicking the minimap
+    waypoint: Option<Vec2>,
+    // stations and derelicts the player has been near, shown on the star map
+    discovered: Vec<EntityId>,
+    radar: Radar,
+    // player's standing with the factions
+    reputation: Reputation,
+    mode: GameMode,
+    escort: Option<Escort>,
+    race: Option<Race>,
+    tutorial: Option<Tutorial>,
+    // the maneuvering thrusters fire on their own to kill sideways drift and hold the speed down
+    flight_assist: bool,
+    // the player's ship is hidden from the AI (see perception.rs) and its engines show no flame
+    cloaked: bool,
+    // what happened this tick, for the tutorial and hints to react to
+    events: Vec<GameEvent>,
+    // first time tips, seen ones are kept in the profile
+    hints: Hints,
+    // walls around the field
+    arena: Arena,
+    // milestones of the endless game, None in the other modes
+    timeline: Option<Timeline>,
+    // asteroids per square of the apothem the spawner keeps up, taken on the first tick of the run
+    asteroid_density: Option<f64>,
+    boss: Option<Boss>,
+    debris: DebrisField,
+    nebulae: Vec<Nebula>,
+    // copy of the world at the start of the race, flown by the best run's inputs
+    ghost: Option<Box<GameWorld>>,
+    // inputs of an endless run, saved as the ghost if it lasts longer than the last one
+    run_recording: Option<Replay>,
     exit_ready: bool,
     control_object: Option<EntityId>,
     last_time: Instant,
@@ -52,6 +501,77 @@ pub struct GameWorld {
     render_ready: bool,
     virtual_time: u128,
     last_tick: u32,
+    // number of simulation ticks run so far
+    tick_count: u32,
+    recording: Option<Replay>,
+    playback: Option<Replay>,
+    autosave: Option<Autosaver>,
+    last_autosave_tick: u32,
+    // shown on top of the game, simulation is paused while it is up
+    menu: Option<Menu>,
+    // window has keyboard focus
+    focused: bool,
+    // paused since the window lost focus, until a key is pressed
+    focus_paused: bool,
+    // the app is in the background, nothing moves until it comes back
+    suspended: bool,
+    // game that can be continued from the main menu
+    saved_game: Option<WorldSnapshot>,
+    // of the endless run, to go back to once it's over
+    checkpoints: Checkpoints,
+    // the player asked to go back to the last checkpoint, done after the ticks
+    checkpoint_requested: bool,
+    // the last few seconds of the endless run, to go back through
+    rewind: Rewind,
+    // playing the attract mode demo, with the world the menu was over
+    attract: Option<Attract>,
+    // nothing pressed on the main menu for this long
+    menu_idle_secs: f64,
+    photo_mode: Option<PhotoMode>,
+    // full screen map, simulation is paused while it is up
+    star_map: Option<StarMap>,
+    tuning: TuningParams,
+    tuning_panel: TuningPanel,
+    // the tuning panel or the inspector changed the simulation, so nothing of
+    // this world is recorded or ranked any more
+    tuned: bool,
+    metrics: Option<MetricsRecorder>,
+    crash: Option<CrashReporter>,
+    // --check-invariants
+    check_invariants: bool,
+    online: Option<LeaderboardClient>,
+    platform: Arc<dyn PlatformServices>,
+    // where settings, scores, ghosts and saves are kept
+    profile: Profile,
+    screenshot_requested: bool,
+    // wall clock seconds covered by the last update
+    frame_secs: f64,
+    // image strips for animation clips, handed to the frames for drawing
+    sprites: Arc<SpriteSheets>,
+    floating_texts: FloatingTexts,
+    // sparks and glows where things hit the border
+    border_fx: BorderFx,
+    // the player's pulse for pushing things away, and its shockwaves
+    repulsor: Repulsor,
+    // the player's grappling line while it's out
+    grapple: Option<Grapple>,
+    // spare ships of the run, and the countdown until the ship comes back
+    lives: Lives,
+    // the run is in the accessibility slow mode (see tuning.rs)
+    slow_mode: bool,
+    // of the faction ships' pilots, from the difficulty the run started with
+    pilot_skill: SkillLevel,
+    // credits, unlocks and the loadout of the profile
+    progression: Progression,
+    // how the player's ship is painted, from the profile
+    ship_style: ShipStyle,
+    // asteroid count and arena asked for before any world modifiers, None for
+    // worlds that weren't generated here (saves, replays)
+    requested_world: Option<(u32, ArenaVariant)>,
+    // the world modifiers the world was generated with
+    modifiers: Vec<WorldModifier>,
+    // earned by this run, once it's over
+    run_credits: Option<u64>,
 }
 
 impl GameWorld {
@@ -63,11 +583,53 @@ impl GameWorld {
         GameWorld {
             seed,
             sequence: 0,
+            placement_rng: RngStream::new(seed, RNG_STREAMS[0]),
+            asteroid_rng: RngStream::new(seed, RNG_STREAMS[1]),
+            loot_rng: RngStream::new(seed, RNG_STREAMS[2]),
+            encounter_rng: RngStream::new(seed, RNG_STREAMS[3]),
+            spawn_tables: Arc::new(SpawnTables::default()),
             max_radius: 0.0,
             resources,
+            settings: Settings::default(),
             entity_store,
             spatial_db,
+            nav_grid: None,
             input_manager: InputManager::new(),
+            actions: ActionState::new(Bindings::default()),
+            camera: Camera::new(),
+            cutscene: None,
+            docked_at: None,
+            hit_indicator: None,
+            g_meter: 0.0,
+            danger_zones: Vec::new(),
+            danger_entered_time: 0.0,
+            near_passes: Vec::new(),
+            slow_mo_start: None,
+            combo: Combo::default(),
+            audio: None,
+            haptics: None,
+            feedback_enabled: false,
+            trail: Trail::new(),
+            waypoint: None,
+            discovered: Vec::new(),
+            radar: Radar::new(),
+            reputation: Reputation::default(),
+            mode: GameMode::Endless,
+            escort: None,
+            race: None,
+            tutorial: None,
+            flight_assist: false,
+            cloaked: false,
+            events: Vec::new(),
+            hints: Hints::default(),
+            arena: Arena::new(ArenaVariant::Fixed, ArenaShape::Square, extent),
+            timeline: Some(Timeline::new(0)),
+            asteroid_density: None,
+            boss: None,
+            debris: DebrisField::default(),
+            nebulae: Vec::new(),
+            ghost: None,
+            run_recording: None,
             exit_ready: false,
             control_object: None,
             last_time: Instant::now(),
@@ -75,6 +637,254 @@ impl GameWorld {
             render_ready: true,
             virtual_time: 0,
             last_tick: 0,
+            tick_count: 0,
+            recording: None,
+            playback: None,
+            autosave: None,
+            last_autosave_tick: 0,
+            menu: None,
+            focused: true,
+            focus_paused: false,
+            suspended: false,
+            saved_game: None,
+            checkpoints: Checkpoints::new(),
+            checkpoint_requested: false,
+            rewind: Rewind::default(),
+            attract: None,
+            menu_idle_secs: 0.0,
+            photo_mode: None,
+            star_map: None,
+            tuning: TuningParams::default(),
+            tuning_panel: TuningPanel::new(),
+            tuned: false,
+            metrics: None,
+            crash: None,
+            check_invariants: false,
+            online: None,
+            platform: Arc::new(LocalServices::new(DEFAULT_PROFILE.to_string(), None)),
+            profile: Profile::new(DEFAULT_PROFILE),
+            screenshot_requested: false,
+            frame_secs: 0.0,
+            sprites: Arc::default(),
+            floating_texts: FloatingTexts::default(),
+            border_fx: BorderFx::default(),
+            repulsor: Repulsor::default(),
+            grapple: None,
+            lives: Lives::default(),
+            slow_mode: false,
+            pilot_skill: SkillLevel::default(),
+            progression: Progression::default(),
+            ship_style: ShipStyle::default(),
+            requested_world: None,
+            modifiers: Vec::new(),
+            run_credits: None,
+        }
+    }
+
+    // A new world from the seed with the world modifiers applied: the player's
+    // ship in the middle, stations and their traffic, asteroids, derelicts,
+    // barriers and an air pod
+    pub fn generate(
+        seed: u64,
+        extent: f64,
+        num_asteroids: u32,
+        arena: (ArenaVariant, ArenaShape),
+        modifiers: &[WorldModifier],
+        spawn_tables: Arc<SpawnTables>,
+    ) -> Self {
+        let (asteroids, variant) = modifiers
+            .iter()
+            .fold((num_asteroids, arena.0), |(asteroids, variant), modifier| {
+                modifier.apply(asteroids, variant)
+            });
+        let mut world = GameWorld::new(seed, extent);
+        world.spawn_tables = spawn_tables;
+        world.set_arena(variant, arena.1);
+        world.requested_world = Some((num_asteroids, arena.0));
+        world.modifiers = modifiers.to_vec();
+
+        // add the player ship at the origin
+        let world_center = Vec2::new(0.0, 0.0);
+        let ship_id = world.add_ship(world_center..world_center);
+        world.set_control_object(ship_id);
+
+        let spawn_range = world.arena.spawn_range(0.0);
+        let (upper_left, lower_right) = (spawn_range.start, spawn_range.end);
+
+        // stations go in before the asteroids so they have room
+        world.add_factions(upper_left, lower_right);
+
+        for _ in 0..asteroids {
+            world.add_asteroid(upper_left..lower_right, 0.0..10.0, 0.0..0.1, false);
+        }
+
+        // a few derelicts with loot to salvage
+        let loot = world.spawn_tables.derelict_loot.sample_many(NUM_DERELICTS, &mut world.loot_rng);
+        for kind in loot {
+            world.add_derelict(upper_left..lower_right, kind);
+        }
+
+        // and some barriers sweeping back and forth
+        for _ in 0..NUM_BARRIERS {
+            world.add_barrier(upper_left..lower_right);
+        }
+
+        for _ in 0..NUM_EMP_MINES {
+            world.add_mine(upper_left..lower_right, MINE_EMP);
+        }
+
+        world.add_air_pod(upper_left..lower_right);
+
+        // last, so a world rebuilt from a snapshot of this one has them in the same slots
+        world.add_debris();
+        world.add_turrets();
+        world.add_scenery();
+        world.nebulae = nebula::generate(seed, spawn_range);
+        world
+    }
+
+    // Stations with traders flying between them, pirates preying on the traders
+    // and a patrol keeping an eye on things
+    fn add_factions(&mut self, upper_left: Vec2, lower_right: Vec2) {
+        for _ in 0..NUM_STATIONS {
+            self.add_station(upper_left..lower_right);
+        }
+
+        let factions = self.spawn_tables.encounter.sample_many(NUM_FACTION_SHIPS, &mut self.encounter_rng);
+        for faction in factions {
+            self.add_faction_ship(faction, upper_left..lower_right);
+        }
+    }
+
+    // Generate the world again from its seed when the world modifiers switched on
+    // aren't the ones it was made with. Only before a run has started.
+    fn update_world_modifiers(&mut self) {
+        let modifiers = self.progression.modifiers();
+        let Some((asteroids, variant)) = self.requested_world else {
+            return;
+        };
+        if modifiers == self.modifiers || self.tick_count > 0 || self.playback.is_some() {
+            return;
+        }
+
+        let extent = self.spatial_db.get_max().x;
+        let arena = (variant, self.arena.shape);
+        let tables = self.spawn_tables.clone();
+        let mut world = GameWorld::generate(self.seed, extent, asteroids, arena, &modifiers, tables);
+        world.recording = self.recording.take();
+        self.take_over(world);
+        let course = self.course_id();
+        if let Some(recording) = self.recording.as_mut() {
+            recording.asteroids = course.asteroids;
+            recording.arena = course.arena;
+        }
+        log::info!("world_regenerated asteroids={} arena={}", course.asteroids, course.arena.key());
+    }
+
+    // Rebuild a world from a snapshot. Settings, autosave etc. are not part of the
+    // snapshot and have to be set up again by the caller. The world comes back
+    // close to what it was, not exactly: debris and guns are made again and the
+    // carrier is gone, so it has no recording and what follows can't be replayed.
+    pub fn from_snapshot(snapshot: &WorldSnapshot) -> Self {
+        let mut world = GameWorld::new(snapshot.seed, snapshot.extent);
+        world.sequence = snapshot.sequence;
+        world.tick_count = snapshot.tick;
+        world.last_tick = snapshot.tick;
+        world.last_autosave_tick = snapshot.tick;
+        world.virtual_time = snapshot.tick as u128 * MICROS_PER_TICK as u128;
+        world.reputation = Reputation::from_values(snapshot.reputation);
+        world.modifiers = snapshot.modifiers.clone();
+        world.combo = snapshot.combo.clone();
+        world.lives = snapshot.lives;
+        world.set_slow_mode(snapshot.slow_mode);
+        world.pilot_skill = snapshot.pilot_skill;
+        let [placement, asteroids, loot, encounters] = snapshot.rng_draws;
+        world.placement_rng.set_draws(placement);
+        world.asteroid_rng.set_draws(asteroids);
+        world.loot_rng.set_draws(loot);
+        world.encounter_rng.set_draws(encounters);
+        world.timeline = Some(Timeline::new(snapshot.tick));
+        world.set_arena(snapshot.arena, snapshot.arena_shape);
+
+        for entity in &snapshot.entities {
+            let object = GameObject::from_snapshot(&world.resources, entity);
+            world.insert_object(object);
+        }
+        // routes need all the stations in place
+        for (idx, entity) in snapshot.entities.iter().enumerate() {
+            if entity.faction.is_some() {
+                world.set_up_faction_ship(EntityId(idx));
+            }
+            let faction_ship = world.entity_store.get_mut(EntityId(idx)).faction_ship.as_mut();
+            if let (Some(ship), Some(nav_path)) = (faction_ship, entity.nav_path.as_ref()) {
+                ship.nav_path = nav_path.clone();
+            }
+            if entity.object_type == GameObjectType::Barrier {
+                world.set_up_barrier(EntityId(idx));
+            }
+        }
+        world.control_object = snapshot.control_object.map(EntityId);
+        // routes are planned on the saved grid until it's rebuilt on schedule
+        let db = &world.spatial_db;
+        let (min, cell_size, dim) = (db.get_min(), db.get_node_size(), db.get_dim());
+        world.nav_grid = snapshot
+            .nav_grid
+            .clone()
+            .filter(|clutter| clutter.len() == (dim * dim) as usize)
+            .map(|clutter| NavGrid::new(min, cell_size, dim, clutter));
+        // debris, scenery and nebulae aren't saved, they come from the seed again,
+        // and the guns are mounted again
+        world.add_debris();
+        world.add_turrets();
+        world.add_scenery();
+        world.nebulae = nebula::generate(snapshot.seed, world.arena.spawn_range(0.0));
+        world
+    }
+
+    pub fn snapshot(&self) -> WorldSnapshot {
+        // the carrier isn't saved, a continued run goes on without it, and
+        // neither are mines that went off or shots in flight. The slots of
+        // despawned objects are never filled again, so leaving them out keeps
+        // what is saved in the order it was made, as it is in this world.
+        let saved = |entity: &&GameObject| {
+            let spent = entity.mine.as_ref().is_some_and(|mine| !mine.armed);
+            !spent
+                && !matches!(
+                    entity.object_type,
+                    GameObjectType::Boss
+                        | GameObjectType::Debris
+                        | GameObjectType::Scenery
+                        | GameObjectType::Turret
+                        | GameObjectType::Projectile
+                        | GameObjectType::Dummy
+                )
+        };
+        let control_object = self
+            .control_object
+            .map(|id| self.entity_store.entities[..id.0].iter().filter(saved).count());
+        WorldSnapshot {
+            seed: self.seed,
+            extent: self.spatial_db.get_max().x,
+            arena: self.arena.variant,
+            arena_shape: self.arena.shape,
+            sequence: self.sequence,
+            tick: self.tick_count,
+            control_object,
+            reputation: self.reputation.values(),
+            modifiers: self.modifiers.clone(),
+            combo: self.combo.clone(),
+            lives: self.lives,
+            slow_mode: self.slow_mode,
+            pilot_skill: self.pilot_skill,
+            rng_draws: self.rng_draws(),
+            nav_grid: self.nav_grid.as_ref().map(|grid| grid.clutter().to_vec()),
+            entities: self
+                .entity_store
+                .entities
+                .iter()
+                .filter(saved)
+                .map(GameObject::snapshot)
+                .collect(),
         }
     }
 
@@ -82,11 +892,98 @@ impl GameWorld {
         self.seed
     }
 
+    // Draws of each random stream so far, in RNG_STREAMS order
+    fn rng_draws(&self) -> [u32; NUM_RNG_STREAMS] {
+        [
+            self.placement_rng.draws(),
+            self.asteroid_rng.draws(),
+            self.loot_rng.draws(),
+            self.encounter_rng.draws(),
+        ]
+    }
+
     pub fn get_sequence(&mut self) -> u32 {
         self.sequence += 1;
         self.sequence
     }
 
+    pub fn get_settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    // Switch everything that belongs to the player over to a profile. Sound,
+    // rumble and the autosave are remade to follow its settings and files.
+    pub fn set_profile(&mut self, profile: Profile, settings: Settings, bindings: Bindings) {
+        let user_name = settings.player_name.clone().unwrap_or_else(|| profile.name.clone());
+        // the place to hand the game a store's services instead
+        self.platform = Arc::new(LocalServices::new(user_name, Some(profile.path(ACHIEVEMENTS_FILE))));
+        self.actions = ActionState::new(bindings);
+        // until a run starts with its own
+        self.pilot_skill = settings.difficulty.pilot_skill;
+        self.settings = settings;
+        self.hints = Hints::load(&profile.path(HINTS_FILE));
+        self.progression = Progression::load(&profile.path(PROGRESSION_FILE));
+        self.ship_style = ShipStyle::load(&profile.path(SHIP_STYLE_FILE));
+        self.profile = profile;
+        self.apply_ship_style();
+        log::info!("profile_set name={}", self.profile.name);
+
+        if self.feedback_enabled {
+            self.enable_feedback();
+        }
+        // dropping the old autosaver finishes its writes to the old profile
+        if self.autosave.take().is_some() {
+            self.enable_autosave();
+        }
+        if let Some(url) = self.online.as_ref().map(|online| online.url().to_string()) {
+            self.enable_online_leaderboard(&url);
+        }
+    }
+
+    // Sound and rumble, as the settings ask for
+    pub fn enable_feedback(&mut self) {
+        self.feedback_enabled = true;
+        // the old output has to be let go of before opening another
+        self.audio = None;
+        self.haptics = None;
+        let (volume, music_volume) = (self.settings.volume, self.settings.music_volume);
+        if volume > 0.0 || music_volume > 0.0 {
+            self.audio = Some(Audio::new(volume, music_volume));
+        }
+        if self.settings.rumble {
+            self.haptics = Some(Haptics::new());
+        }
+    }
+
+    // Animation clips that have an image strip are drawn with it from now on
+    pub fn set_sprites(&mut self, sprites: Arc<SpriteSheets>) {
+        self.sprites = sprites;
+    }
+
+    // Runs being watched (replays, ghosts) don't earn anything
+    fn unlock_achievement(&self, achievement: Achievement) {
+        if self.playback.is_none() {
+            self.platform.unlock_achievement(achievement);
+        }
+    }
+
+    // Has to be set before the world is populated, so everything starts inside
+    pub fn set_arena(&mut self, variant: ArenaVariant, shape: ArenaShape) {
+        self.arena = Arena::new(variant, shape, self.spatial_db.get_max().x);
+        self.resources.border_shape = border_shape(shape, self.arena.apothem_at(0.0));
+    }
+
+    pub fn get_viewport(&self, screen_size: Size) -> Viewport {
+        let viewport = Viewport::new(self.settings.viewport_mode, screen_size);
+        if let Some(photo_mode) = self.photo_mode.as_ref() {
+            return viewport.with_zoom(photo_mode.zoom);
+        }
+        match self.cutscene.as_ref() {
+            Some(cutscene) => viewport.with_zoom(cutscene.view_at(self.render_tick(), Vec2::ZERO).1),
+            None => viewport,
+        }
+    }
+
     pub fn is_exit_ready(&self) -> bool {
         self.exit_ready
     }
@@ -103,1099 +1000,9005 @@ impl GameWorld {
         self.control_object = Some(id);
     }
 
-    pub fn handle_device_event(&mut self, event: &winit::event::DeviceEvent) {
-        self.input_manager.input(event);
+    // For --stress: the ship is taken out, leaving the rocks to themselves, and
+    // what goes on is watched like a replay, so it doesn't count for anything
+    pub fn disable_player(&mut self) {
+        self.playback = Some(self.new_recording());
+        self.run_recording = None;
+        if let Some(ctrl_id) = self.control_object.take() {
+            self.despawn(ctrl_id);
+        }
+        log::info!("player_disabled tick={}", self.tick_count);
     }
 
-    pub fn handle_window_key_event(&mut self, event: &winit::event::WindowEvent) {
-        if let WindowEvent::KeyboardInput { event, .. } = event {
-            // Convert the window key event to a device event
-            let raw_key = RawKeyEvent { physical_key: event.physical_key, state: event.state };
-            let device_event = DeviceEvent::Key(raw_key);
+    // Camera follows the control object (using the interpolated transform)
+    pub fn get_camera_pos(&self) -> Vec2 {
+        if let Some(photo_mode) = self.photo_mode.as_ref() {
+            return photo_mode.cam_pos;
+        }
 
-            self.input_manager.input(&device_event);
+        let target = if let Some(ctrl_id) = self.control_object {
+            self.entity_store.get(ctrl_id).render_transform.translation()
+        } else {
+            Vec2::ZERO
+        };
+        if let Some(cutscene) = self.cutscene.as_ref() {
+            return cutscene.view_at(self.render_tick(), target).0;
         }
+        target + self.camera.get_offset()
     }
 
-    fn add_object(
-        &mut self,
-        object: GameObject,
-        pos_range: Range<Vec2>,
-        retry_checks: u32,
-        add_anyway: bool,
-    ) -> Option<EntityId> {
-        let mut object = object;
+    pub fn handle_input(&mut self, input: InputEvent) {
+        if !self.focused {
+            // device events keep coming while typing into other windows
+            return;
+        }
+        if self.playback.is_some() && self.attract.is_none() {
+            // during playback input comes from the replay, but still allow quitting
+            let escape = Button::Key(PhysicalKey::Code(KeyCode::Escape));
+            if !matches!(input.kind, InputKind::Button { button, .. } if button == escape) {
+                return;
+            }
+        }
+        self.input_manager.input(input);
+    }
 
-        let our_rad = object.collision.radius();
+    // Record all input applied to the simulation from now on, for a run that
+    // goes on with the loadout the world has now
+    pub fn start_recording(&mut self, mut replay: Replay) {
+        if self.tuned {
+            log::warn!("Not recording, the simulation has been tuned");
+            return;
+        }
+        replay.loadout = self.loadout();
+        replay.spawn_tables = self.spawn_tables.hash();
+        self.recording = Some(replay);
+    }
 
-        // adjust position range to account for radius
-        let mut pos_range = pos_range;
-        let range_min = self.spatial_db.get_min() + Vec2::new(our_rad, our_rad);
-        let range_max = self.spatial_db.get_max() - Vec2::new(our_rad, our_rad);
-        pos_range.start.x = pos_range.start.x.max(range_min.x);
-        pos_range.start.y = pos_range.start.y.max(range_min.y);
-        pos_range.end.x = pos_range.end.x.min(range_max.x);
-        pos_range.end.y = pos_range.end.y.min(range_max.y);
+    // Once the tuning panel or the inspector changes the simulation, what's
+    // recorded of the run so far won't replay the same. The recordings are
+    // dropped, as when the run goes back (see go_back_to).
+    fn stop_recordings(&mut self, reason: &str) {
+        if self.recording.is_some() || self.run_recording.is_some() {
+            log::warn!("Recording stopped, {}", reason);
+        }
+        self.tuned = true;
+        self.recording = None;
+        self.run_recording = None;
+    }
 
-        object.pick_position(self.get_seed(), self.get_sequence(), pos_range.clone());
+    pub fn take_recording(&mut self) -> Option<Replay> {
+        let mut recording = self.recording.take()?;
+        recording.rng_check = Some((self.tick_count, self.rng_draws()));
+        Some(recording)
+    }
 
-        if object.collision.radius() > self.max_radius {
-            self.max_radius = object.collision.radius();
+    // Drive the simulation from a recorded replay instead of live input
+    pub fn start_playback(&mut self, replay: Replay) {
+        self.playback = Some(replay);
+    }
+
+    // Once playback reaches the tick the recording was finished at, the random
+    // streams should have drawn as often as they did then
+    fn check_playback_rng(&self) {
+        let Some((tick, draws)) = self.playback.as_ref().and_then(|replay| replay.rng_check) else {
+            return;
+        };
+        if tick != self.tick_count {
+            return;
+        }
+        if draws == self.rng_draws() {
+            log::info!("replay_rng_checked tick={}", tick);
+        } else {
+            log::warn!("replay_desync tick={} recorded={:?} played={:?}", tick, draws, self.rng_draws());
         }
+    }
 
-        for i in 1..=retry_checks {
-            let pos = object.transform.translation();
-            let mut occupied = false;
+    // A replay is being played back and has run out of recorded input
+    pub fn is_playback_finished(&self) -> bool {
+        self.playback.as_ref().is_some_and(|replay| replay.is_finished())
+    }
 
-            let min_pos = pos - Vec2::new(our_rad, our_rad);
-            let max_pos = pos + Vec2::new(our_rad, our_rad);
+    // Periodically save the world in the background so the run can be continued after a crash
+    pub fn enable_autosave(&mut self) {
+        let path = self.profile.path(AUTOSAVE_PATH);
+        self.autosave = Some(Autosaver::new(path, self.platform.clone()));
+    }
 
-            self.spatial_db
-                .probe_range(min_pos..max_pos, self.max_radius, &mut |other_id| {
-                    let other = self.get_entities().get(other_id);
-                    let other_pos = other.transform.translation();
-                    let dist = (pos - other_pos).length();
-                    let min_dist = our_rad + other.collision.radius();
-                    if dist < min_dist {
-                        occupied = true;
-                    }
-                });
+    // Record a summary of each run (see metrics.rs)
+    pub fn enable_metrics(&mut self, recorder: MetricsRecorder) {
+        self.metrics = Some(recorder);
+    }
 
-            if !occupied {
-                break;
-            }
+    pub fn enable_crash_reports(&mut self, reporter: CrashReporter) {
+        self.crash = Some(reporter);
+    }
 
-            if i == retry_checks {
-                if !add_anyway {
-                    return None;
-                }
+    // Called on exit, records the run if it was still going
+    pub fn finish_metrics(&mut self) {
+        if self.tick_count > 0 && !self.is_game_over() {
+            let summary = self.run_summary("quit");
+            if let Some(metrics) = self.metrics.as_mut() {
+                metrics.finish(&summary);
             }
+        }
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.flush();
+        }
+    }
 
-            object.pick_position(self.get_seed(), self.get_sequence(), pos_range.clone());
+    // Submit endless runs to the leaderboard at url, queueing them in the profile
+    pub fn enable_online_leaderboard(&mut self, url: &str) {
+        // the old client finishes sending to the old profile's queue first
+        self.online = None;
+        self.online = Some(LeaderboardClient::new(url, &self.profile, self.platform.clone()));
+    }
+
+    fn run_summary(&self, end: &'static str) -> RunSummary {
+        RunSummary {
+            mode: self.mode,
+            seed: self.seed,
+            arena: arena_name(self.arena.variant, self.arena.shape),
+            ticks: self.tick_count,
+            score: self.get_score(),
+            end,
         }
+    }
 
-        let id = self.get_entities_mut().insert(object);
-        let obj = self.entity_store.get_mut(id);
-        let pos = obj.transform.translation();
-        self.spatial_db.update(id, pos, &mut obj.spatial_db_ref);
-        Some(id)
+    fn update_metrics(&mut self) {
+        let Some(metrics) = self.metrics.as_mut() else {
+            return;
+        };
+        if metrics.is_finished() {
+            return;
+        }
+        if let Some(ctrl_id) = self.control_object {
+            metrics.sample_speed(self.entity_store.get(ctrl_id).rigid.velocity.length());
+        }
+        if !self.is_game_over() {
+            return;
+        }
+
+        let escort_outcome = self.escort.as_ref().and_then(|escort| escort.outcome);
+        let end = match escort_outcome {
+            Some(EscortOutcome::Delivered) => "delivered",
+            Some(EscortOutcome::Destroyed) => "convoy_destroyed",
+            None if self.race.is_some() => "finished",
+            None => self.metrics.as_ref().unwrap().death_cause(self.tick_count),
+        };
+        let summary = self.run_summary(end);
+        self.metrics.as_mut().unwrap().finish(&summary);
     }
 
-    pub fn get_resources(&self) -> &Resources {
-        &self.resources
+    // Called on a clean exit. The autosave is only kept if the player never got past
+    // the main menu, so it can still be continued next time.
+    pub fn finish_autosave(&mut self) {
+        let Some(autosave) = self.autosave.take() else {
+            return;
+        };
+        // quitting mid-run leaves it for Continue on the main menu
+        if self.is_run_in_progress() {
+            log::info!("run_suspended tick={} score={}", self.tick_count, self.get_score());
+            autosave.save(&self.snapshot());
+        } else if self.saved_game.is_none() {
+            autosave.remove();
+        }
     }
 
-    pub fn get_entities(&self) -> &EntityStore {
-        &self.entity_store
+    // An endless run that has started and isn't over, and so can be continued later
+    fn is_run_in_progress(&self) -> bool {
+        self.mode == GameMode::Endless
+            && self.playback.is_none()
+            && self.tick_count > 0
+            && !self.is_game_over()
     }
 
-    pub fn get_entities_mut(&mut self) -> &mut EntityStore {
-        &mut self.entity_store
+    fn update_autosave(&mut self) {
+        let Some(autosave) = self.autosave.as_ref() else {
+            return;
+        };
+        // escort missions are short and aren't saved
+        if self.mode != GameMode::Endless {
+            return;
+        }
+
+        let interval = (self.settings.autosave_secs * TICKS_PER_SECOND as f64) as u32;
+        if interval == 0 || self.tick_count < self.last_autosave_tick + interval {
+            return;
+        }
+        self.last_autosave_tick = self.tick_count;
+
+        // nothing to continue once the run is over
+        if self.is_game_over() {
+            autosave.remove();
+        } else {
+            autosave.save(&self.snapshot());
+        }
     }
 
-    pub fn get_spatial_db(&self) -> &SpatialDb {
-        &self.spatial_db
+    pub fn show_main_menu(&mut self, saved_game: Option<WorldSnapshot>) {
+        // the profile or the hangar may have changed the world modifiers
+        self.update_world_modifiers();
+        let can_load = self.slot_descriptions().iter().any(Option::is_some);
+        self.menu = Some(Menu::main_menu(
+            saved_game.is_some(),
+            can_load,
+            &self.profile.name,
+            self.progression.credits,
+        ));
+        self.saved_game = saved_game;
     }
 
-    pub fn add_ship(&mut self, pos_range: Range<Vec2>) -> EntityId {
-        let seq = self.get_sequence();
-        let ship = GameObject::new_ship(&self.get_resources(), self.get_seed(), seq);
+    // What is in each of the profile's save slots, None if it is empty
+    fn slot_descriptions(&self) -> Vec<Option<String>> {
+        (0..NUM_SAVE_SLOTS)
+            .map(|slot| {
+                let path = self.profile.slot_path(slot);
+                // a slot that can't be read is as good as empty
+                let snapshot = WorldSnapshot::load(&path).ok()?;
+                let secs = snapshot.tick as u64 / TICKS_PER_SECOND;
+                Some(format!("{}:{:02} seed {}", secs / 60, secs % 60, snapshot.seed))
+            })
+            .collect()
+    }
 
-        self.add_object(ship, pos_range, 10, true).unwrap()
+    // F5 during an endless run brings up the save slots. Like the autosave,
+    // only endless runs are saved.
+    fn update_save_request(&mut self) {
+        if !self.actions.just_pressed(Action::SaveGame) {
+            return;
+        }
+        if self.mode != GameMode::Endless || self.playback.is_some() || self.is_game_over() {
+            return;
+        }
+        self.menu = Some(Menu::save_game(&self.slot_descriptions()));
     }
 
-    pub fn add_asteroid(
-        &mut self,
-        pos_range: Range<Vec2>,
-        vel_range: Range<f64>,
-        ang_vel_range: Range<f64>,
-    ) -> Option<EntityId> {
-        let seq = self.get_sequence();
-        let asteroid = GameObject::new_asteroid(
-            &self.get_resources(),
-            self.get_seed(),
-            seq,
-            vel_range,
-            ang_vel_range,
-        );
+    // F9 once an endless run is over goes back to its last checkpoint
+    fn update_checkpoint_request(&mut self) {
+        if self.actions.just_pressed(Action::LoadCheckpoint)
+            && self.is_game_over()
+            && self.checkpoints.last().is_some()
+        {
+            self.checkpoint_requested = true;
+        }
+    }
 
-        self.add_object(asteroid, pos_range, 10, false)
+    // Snapshot an endless run as it goes, every so many points and as each
+    // milestone comes up
+    fn update_checkpoints(&mut self) {
+        let running = self.mode == GameMode::Endless && self.playback.is_none() && self.tick_count > 0;
+        if !running || self.is_game_over() {
+            return;
+        }
+        let score = self.get_score();
+        if !self.checkpoints.is_due(score) {
+            return;
+        }
+        self.checkpoints.take(self.snapshot(), score);
+        log::info!("checkpoint_taken score={} tick={}", score, self.tick_count);
     }
 
-    pub fn add_air_pod(&mut self, pos_range: Range<Vec2>) -> EntityId {
-        let seq = self.get_sequence();
-        let air_pod = GameObject::new_air_pod(&self.get_resources(), self.get_seed(), seq);
-        self.add_object(air_pod, pos_range, 10, true).unwrap()
+    // Score of the player's ship at the last checkpoint, None if there isn't one
+    fn checkpoint_score(&self) -> Option<u64> {
+        let snapshot = self.checkpoints.last()?;
+        snapshot.control_object.and_then(|idx| snapshot.entities[idx].score)
     }
 
-    fn update_player_controls(&mut self) {
-        let ctrl_id = self.get_control_object();
-        if let Some(ctrl_id) = ctrl_id {
-            let ctrl_obj = &mut self.entity_store.get_mut(ctrl_id);
-            if ctrl_obj.air_suuply.as_ref().map(|air| air.air).unwrap_or(0) == 0 {
-                // ship is out of air, no controls
-                ctrl_obj.animation = None;
-                return;
-            }
-            let left_down = self.input_manager.is_down(PhysicalKey::Code(KeyCode::ArrowLeft)) || self.input_manager.is_down(PhysicalKey::Code(KeyCode::KeyA));
-            let right_down = self.input_manager.is_down(PhysicalKey::Code(KeyCode::ArrowRight)) || self.input_manager.is_down(PhysicalKey::Code(KeyCode::KeyD));
-            let thrust_down = self.input_manager.is_down(PhysicalKey::Code(KeyCode::ArrowUp)) || self.input_manager.is_down(PhysicalKey::Code(KeyCode::KeyW));
-            match (left_down, right_down) {
-                (true, false) => {
-                    ctrl_obj.transform.apply_rotation(-0.15);
-                }
-                (false, true) => {
-                    ctrl_obj.transform.apply_rotation(0.15);
-                }
-                _ => {}
-            }
-            if thrust_down {
-                ctrl_obj.rigid.velocity += 1.0 * ctrl_obj.transform.get_y_vector();
-                if ctrl_obj.animation.is_none() {
-                    ctrl_obj.animation = Some(Animation {
-                        start_time: Instant::now(),
-                        animation: flame_scene,
-                    });
-                }
-            } else {
-                ctrl_obj.animation = None;
-            }
+    // Go back to the last checkpoint, with part of its score taken off. The run
+    // goes on without its recording, and keeps the credits it already earned.
+    fn load_checkpoint(&mut self) {
+        let Some(snapshot) = self.checkpoints.last().cloned() else {
+            return;
+        };
+        self.go_back_to(&snapshot);
+        // the rewind snapshots are of what came after
+        self.rewind.clear();
+        self.checkpoints.loads += 1;
+        if let Some(score) = self.control_object.and_then(|id| self.entity_store.get_mut(id).score.as_mut()) {
+            score.0 = score_after_load(score.0);
         }
+        log::info!(
+            "checkpoint_loaded score={} tick={} loads={}",
+            self.get_score(),
+            self.tick_count,
+            self.checkpoints.loads
+        );
     }
 
-    fn apply_physics(&mut self) {
-        for (id, entity) in &mut self.entity_store.iter_mut_entity() {
-            let pos = entity.transform.translation();
-            let vel = entity.rigid.velocity;
-            entity.transform.apply_translation(vel);
-            entity
-                .transform
-                .apply_rotation(entity.rigid.angular_velocity);
-            self.spatial_db.update(id, pos, &mut entity.spatial_db_ref);
+    // Put the world back as it was at snapshot, going on with the same run: its
+    // checkpoints, rewind charges and the credits it earned stay. The recording
+    // doesn't, it can't play the run back any more.
+    fn go_back_to(&mut self, snapshot: &WorldSnapshot) {
+        if self.recording.is_some() {
+            log::warn!("Recording stopped, a run that went back can't be replayed");
         }
-        for entity in &mut self.entity_store.entities {
-            entity.rigid.velocity *= 1.0 - entity.rigid.dampening;
-            entity.rigid.angular_velocity *= 1.0 - entity.rigid.angular_dampening;
+        let checkpoints = std::mem::take(&mut self.checkpoints);
+        let rewind = std::mem::take(&mut self.rewind);
+        let input_manager = std::mem::replace(&mut self.input_manager, InputManager::new());
+        let (run_credits, flight_assist) = (self.run_credits, self.flight_assist);
+        self.take_over(GameWorld::from_snapshot(snapshot));
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.went_back(snapshot.tick);
+        }
+        self.checkpoints = checkpoints;
+        self.rewind = rewind;
+        self.input_manager = input_manager;
+        self.run_credits = run_credits;
+        self.flight_assist = flight_assist;
+    }
 
-            if entity.object_type == GameObjectType::Ship {
-                let vel = entity.rigid.velocity.length();
-                if vel > MAX_SHIP_SPEED {
-                    entity.rigid.velocity *= MAX_SHIP_SPEED / vel;
-                }
-            }
+    // R during an endless run (or once it's over) uses a rewind charge
+    fn update_rewind_request(&mut self) {
+        if !self.actions.just_pressed(Action::Rewind) || !self.can_rewind() {
+            return;
+        }
+        if self.rewind.start(self.check_invariants) {
+            log::info!("rewind_started charges={} tick={}", self.rewind.charges(), self.tick_count);
         }
     }
 
-    fn detect_collisions(&mut self, contacts: &mut Vec<Contact>) {
-        let max_radius = self.max_radius;
+    // Endless runs only, as the other modes aren't in snapshots, and not with the
+    // carrier around, which isn't either
+    fn can_rewind(&self) -> bool {
+        self.mode == GameMode::Endless && self.playback.is_none() && self.boss.is_none()
+    }
 
-        self.get_spatial_db()
-            .find_neighbors(max_radius, &mut |id1, id2| {
-                let obj1 = &self.entity_store.entities[id1.0];
-                let obj2 = &self.entity_store.entities[id2.0];
+    // Snapshot for rewinding every few ticks while the run goes on
+    fn update_rewind_buffer(&mut self) {
+        if !self.can_rewind() || self.is_game_over() || !self.rewind.is_snapshot_due(self.tick_count) {
+            return;
+        }
+        let snapshot = self.snapshot();
+        self.rewind.push(snapshot);
+    }
 
-                let pos1 = obj1.transform.translation();
-                let pos2 = obj2.transform.translation();
-                let dist = (pos1 - pos2).length();
-                let min_dist = obj1.collision.radius() + obj2.collision.radius();
-                if dist < min_dist {
-                    // collision
-                    let normal = (pos2 - pos1).normalize();
-                    let c1 = pos1 + normal * obj1.collision.radius();
-                    let c2 = pos2 - normal * obj2.collision.radius();
-                    contacts.push(Contact {
-                        id1: Some(id1),
-                        id2: Some(id2),
-                        pos: 0.5 * (c1 + c2),
-                        normal1: (pos2 - pos1).normalize(),
-                        depth: min_dist - dist,
-                    });
-                }
-            });
+    // A tick's worth of rewinding: the world as it was a few ticks earlier
+    fn rewind_step(&mut self) {
+        let Some(snapshot) = self.rewind.step() else {
+            return;
+        };
+        self.go_back_to(&snapshot);
+        if !self.rewind.is_active() {
+            log::info!("rewound tick={} charges={}", self.tick_count, self.rewind.charges());
+        }
+    }
 
-        let ul = self.get_spatial_db().get_min();
-        let lr = self.get_spatial_db().get_max();
-        let ur = Vec2::new(lr.x, ul.y);
-        let ll = Vec2::new(ul.x, lr.y);
-        self.get_spatial_db()
-            .probe_range(ul..ur, max_radius, &mut |id| {
-                let obj = self.entity_store.get(id);
-                let pos = obj.transform.translation();
-                let rad = obj.collision.radius();
-                if pos.y - rad < ul.y {
-                    // out of bounds
-                    contacts.push(Contact {
-                        id1: Some(id),
-                        id2: None,
-                        pos: Vec2::new(pos.x, ul.y),
-                        normal1: Vec2::new(0.0, -1.0),
-                        depth: ul.y - (pos.y - rad),
-                    });
-                }
-            });
+    fn save_to_slot(&mut self, slot: usize) {
+        let path = self.profile.slot_path(slot);
+        match self.platform.store_cloud_file(&path, self.snapshot().serialize().as_bytes()) {
+            Ok(()) => log::info!("game_saved slot={} tick={}", slot + 1, self.tick_count),
+            Err(err) => log::error!("Failed to save game {}: {}", path.display(), err),
+        }
+    }
 
-        self.get_spatial_db()
-            .probe_range(ll..lr, max_radius, &mut |id| {
-                let obj = self.entity_store.get(id);
-                let pos = obj.transform.translation();
-                let rad = obj.collision.radius();
-                if pos.y + rad > ll.y {
-                    // out of bounds
-                    contacts.push(Contact {
-                        id1: Some(id),
-                        id2: None,
-                        pos: Vec2::new(pos.x, ll.y),
-                        normal1: Vec2::new(0.0, 1.0),
-                        depth: (pos.y + rad) - ll.y,
-                    });
-                }
-            });
-        self.get_spatial_db()
-            .probe_range(ul..ll, max_radius, &mut |id| {
-                let obj = self.entity_store.get(id);
-                let pos = obj.transform.translation();
-                let rad = obj.collision.radius();
-                if pos.x - rad < ul.x {
-                    // out of bounds
-                    contacts.push(Contact {
-                        id1: Some(id),
-                        id2: None,
-                        pos: Vec2::new(ul.x, pos.y),
-                        normal1: Vec2::new(-1.0, 0.0),
-                        depth: ul.x - (pos.x - rad),
-                    });
-                }
-            });
-        self.get_spatial_db()
-            .probe_range(ur..lr, max_radius, &mut |id| {
-                let obj = self.entity_store.get(id);
-                let pos = obj.transform.translation();
-                let rad = obj.collision.radius();
-                if pos.x + rad > ur.x {
-                    // out of bounds
-                    contacts.push(Contact {
-                        id1: Some(id),
-                        id2: None,
-                        pos: Vec2::new(ur.x, pos.y),
-                        normal1: Vec2::new(1.0, 0.0),
-                        depth: (pos.x + rad) - ur.x,
-                    });
-                }
-            });
+    // Another profile from the menu. The current one's autosave is left for it
+    // to continue next time.
+    fn switch_profile(&mut self, name: &str) {
+        let profile = match Profile::open(name) {
+            Ok(profile) => profile,
+            Err(err) => {
+                log::error!("{}", err);
+                return;
+            }
+        };
+        profile.remember();
+        let mut settings = profile.load_settings().unwrap_or_else(|err| {
+            log::error!("Using default settings: {}", err);
+            Settings::default()
+        });
+        // the renderers are already set up for this machine's preset
+        settings.quality = settings.quality.or(self.settings.quality);
+        let bindings = profile.load_bindings().unwrap_or_else(|err| {
+            log::error!("Using default bindings: {}", err);
+            Bindings::default()
+        });
+        let saved_game = save::load_save(&profile.path(AUTOSAVE_PATH));
+        self.set_profile(profile, settings, bindings);
+        self.show_main_menu(saved_game);
     }
 
-    fn resolve_collisions(&mut self, contacts: &mut Vec<Contact>) {
-        let mut dummy_obj = GameObject::new_dummy();
+    // Pointer press in screen (logical) coordinates. Primary button on the minimap
+    // places a waypoint, secondary clears it. Returns true if the press hit the minimap.
+    // The tuning panel is part of the debug overlay (see render)
+    fn tuning_panel_shown(&self) -> bool {
+        self.settings.debug_labels
+            && self.photo_mode.is_none()
+            && self.star_map.is_none()
+            && self.menu.is_none()
+    }
 
-        //
-        let mut relocate_air = None;
-        let mut ship_loc = None;
+    // Pointer press/drag/release in screen coordinates for the tuning panel.
+    // Press and drag return true if the panel took them.
+    pub fn handle_tuning_press(&mut self, pos: Point, screen_size: Size) -> bool {
+        if !self.tuning_panel_shown() {
+            return false;
+        }
+        let before = self.tuning;
+        let taken = self.tuning_panel.press(pos, screen_size, &mut self.tuning);
+        if !self.tuning.simulates_like(&before) {
+            self.stop_recordings("the tuning changed");
+        }
+        taken
+    }
 
-        for i in 0..5 {
-            for contact in contacts.iter() {
-                let id1 = contact.id1.unwrap();
+    pub fn handle_tuning_drag(&mut self, pos: Point, screen_size: Size) -> bool {
+        let before = self.tuning;
+        let taken = self.tuning_panel.drag(pos, screen_size, &mut self.tuning);
+        if !self.tuning.simulates_like(&before) {
+            self.stop_recordings("the tuning changed");
+        }
+        taken
+    }
 
-                let (obj1, obj2) = if let Some(id2) = contact.id2 {
-                    self.entity_store.get_mut_pair(id1, id2)
-                } else {
-                    (self.entity_store.get_mut(id1), &mut dummy_obj)
-                };
+    pub fn handle_tuning_release(&mut self) {
+        self.tuning_panel.release(&self.tuning);
+    }
 
-                if (obj1.object_type == GameObjectType::AidPod
-                    && obj2.object_type == GameObjectType::Ship)
-                    || (obj2.object_type == GameObjectType::AidPod
-                        && obj1.object_type == GameObjectType::Ship)
-                {
-                    // air collection
-                    if i == 0 {
-                        let (Some(air1), Some(air2)) =
-                            (obj1.air_suuply.as_mut(), obj2.air_suuply.as_mut())
-                        else {
-                            continue;
-                        };
-                        if relocate_air.is_some() {
-                            // possible to have same collision twice, so make sure to only do this once
-                            continue;
-                        }
-                        if obj1.object_type == GameObjectType::Ship {
-                            air1.air += air2.air;
-                            if let Some(score) = obj1.score.as_mut() {
-                                score.0 += air2.air + 1000;
-                            }
+    pub fn handle_minimap_press(&mut self, pos: Point, screen_size: Size, primary: bool) -> bool {
+        if let Some(star_map) = self.star_map.as_ref() {
+            // the whole window is the map
+            let layout = StarMapLayout::new(screen_size, self.spatial_db.get_max().x, star_map);
+            self.waypoint = primary.then(|| layout.map_to_world(pos));
+            log::debug!("star_map_waypoint set={}", self.waypoint.is_some());
+            return true;
+        }
+        if self.is_paused() {
+            // minimap isn't shown
+            return false;
+        }
 
-                            // save some data for finding next air pod location
-                            relocate_air = contact.id2;
-                            ship_loc = Some(obj1.transform.translation());
-                            println!(
-                                "Ship collects {} air, raising total to {}",
-                                air2.air, air1.air
-                            );
-                        } else {
-                            air2.air += air1.air;
-                            if let Some(score) = obj2.score.as_mut() {
-                                score.0 += air1.air + 1000;
-                            }
+        let layout = MiniMapLayout::new(screen_size);
+        if pos.distance(layout.center) > layout.radius {
+            return false;
+        }
 
-                            // save some data for finding next air pod location
-                            relocate_air = contact.id1;
-                            ship_loc = Some(obj2.transform.translation());
-                            println!(
-                                "Ship collects {} air, raising total to {}",
-                                air1.air, air2.air
-                            );
-                        }
-                    }
-                    continue;
+        self.waypoint = if primary {
+            let waypoint = layout.map_to_world(pos, self.get_camera_pos());
+            log::debug!("waypoint_set x={:.0} y={:.0}", waypoint.x, waypoint.y);
+            Some(waypoint)
+        } else {
+            None
+        };
+        true
+    }
+
+    fn update_waypoint(&mut self) {
+        let (Some(waypoint), Some(ctrl_id)) = (self.waypoint, self.control_object) else {
+            return;
+        };
+
+        // reached
+        let ship = self.entity_store.get(ctrl_id);
+        if (ship.transform.translation() - waypoint).length() < WAYPOINT_REACHED_DIST {
+            log::debug!("waypoint_reached");
+            self.waypoint = None;
+        }
+    }
+
+    // Warn with a sound, a rumble and a tint at the edge of the screen whenever the
+    // player's ship gets into a dangerous part of the world. Each zone has a
+    // wider threshold for leaving, so hovering at the edge doesn't keep warning.
+    fn update_danger_zones(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let pos = self.entity_store.get(ctrl_id).transform.translation();
+        let inside = |zone| self.danger_zones.contains(&zone);
+
+        let mut zones = Vec::new();
+        let radius = DANGER_DENSITY_RADIUS;
+        let mut asteroids = 0;
+        self.spatial_db.probe_range(
+            pos - Vec2::new(radius, radius)..pos + Vec2::new(radius, radius),
+            self.max_radius,
+            &mut |id| {
+                let other = self.entity_store.get(id);
+                if other.object_type == GameObjectType::Asteroid
+                    && (other.transform.translation() - pos).length() < radius
+                {
+                    asteroids += 1;
                 }
+            },
+        );
+        let count = if inside(DangerZone::AsteroidField) {
+            DANGER_DENSITY_CLEAR
+        } else {
+            DANGER_DENSITY_COUNT
+        };
+        if asteroids >= count {
+            zones.push(DangerZone::AsteroidField);
+        }
 
-                // get relative velocity of contact points on obj1 and obj2
-                let offset1 = contact.pos - obj1.transform.translation();
-                let offset2 = contact.pos - obj2.transform.translation();
-                let v1 = obj1.rigid.get_world_offset_vel(&offset1);
-                let v2: Vec2 = obj2.rigid.get_world_offset_vel(&offset2);
-                let delta_vel = v2 - v1;
-                let contact_vel = delta_vel.dot(contact.normal1);
-                let tangent_vel = delta_vel - contact_vel * contact.normal1;
+        // walls within the distance are ones a circle that size around the ship goes through
+        let dist = if inside(DangerZone::NearBorder) { DANGER_BORDER_CLEAR } else { DANGER_BORDER_DIST };
+        let mut near_border = false;
+        self.arena.wall_contacts(pos, dist, self.tick_count as f64, &mut |_, _, _| near_border = true);
+        if near_border {
+            zones.push(DangerZone::NearBorder);
+        }
 
-                let inv_mass1 = obj1.rigid.inv_mass;
-                let inv_mass2 = obj2.rigid.inv_mass;
-                let inv_inertia1 = obj1.rigid.inv_ang_inertia_sqrt;
-                let inv_inertia2 = obj2.rigid.inv_ang_inertia_sqrt;
+        let entered: Vec<DangerZone> = zones.iter().copied().filter(|zone| !inside(*zone)).collect();
+        for zone in entered {
+            log::debug!("danger_zone_entered zone={} tick={}", zone.key(), self.tick_count);
+            self.danger_entered_time = self.get_virtual_time_secs();
+            if let Some(audio) = self.audio.as_ref() {
+                audio.play(zone.cue());
+            }
+            if let Some(haptics) = self.haptics.as_ref() {
+                haptics.pulse(Pulse {
+                    strength: 0.3,
+                    duration: Duration::from_millis(120),
+                });
+            }
+        }
+        self.danger_zones = zones;
+    }
 
-                let cross1 =
-                    (offset1.x * contact.normal1.y - offset1.y * contact.normal1.x) * inv_inertia1;
-                let cross2 =
-                    (-offset2.x * contact.normal1.y + offset2.y * contact.normal1.x) * inv_inertia2;
-                let inv_mass_inertia = inv_mass1 + inv_mass2 + cross1 * cross1 + cross2 * cross2;
+    // Asteroids passing close are checked over the whole tick, from where each was
+    // against the ship at the start of it to where it is at the end, so a fast one
+    // going by between two ticks still counts
+    fn update_near_misses(&mut self, contacts: &[Contact]) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship = self.entity_store.get(ctrl_id);
+        let (pos, velocity) = (ship.transform.translation(), ship.rigid.velocity);
+        let start_pos = ship.prev_transform.translation();
+        let radius = ship.collision.radius();
+        let (max_gap, min_speed) = (self.tuning.near_miss_gap, self.tuning.near_miss_speed);
+
+        for pass in self.near_passes.iter_mut() {
+            let hit = |contact: &Contact| {
+                let ids = [contact.id1, contact.id2];
+                ids.contains(&Some(pass.asteroid)) && ids.contains(&Some(ctrl_id))
+            };
+            pass.touched |= contacts.iter().any(hit);
+        }
 
-                if contact_vel >= 0.0 {
-                    // moving apart...
-                    continue;
+        // wide enough for an asteroid that was in range partway through the tick
+        let reach = radius + max_gap + (pos - start_pos).length() + self.tuning.max_ship_speed;
+        let mut close = Vec::new();
+        self.spatial_db.probe_range(
+            pos - Vec2::new(reach, reach)..pos + Vec2::new(reach, reach),
+            self.max_radius,
+            &mut |id| {
+                let other = self.entity_store.get(id);
+                if other.object_type != GameObjectType::Asteroid {
+                    return;
                 }
+                let start = other.prev_transform.translation() - start_pos;
+                let end = other.transform.translation() - pos;
+                let gap = closest_approach(start, end) - radius - other.collision.radius();
+                if gap < max_gap && (other.rigid.velocity - velocity).length() >= min_speed {
+                    close.push((id, gap));
+                }
+            },
+        );
 
-                if i == 0 && tangent_vel.length_squared() > 1e-4 {
-                    // apply a frictional force to asteroids. Since everything is a circle, this is the only
-                    // way we get angular velocity. Ship and air pod objects are not affected.
+        // a pass is over once the asteroid is out of range or slowed down
+        let mut passes = Vec::new();
+        for pass in std::mem::take(&mut self.near_passes) {
+            if let Some(&(_, gap)) = close.iter().find(|(id, _)| *id == pass.asteroid) {
+                passes.push(NearPass {
+                    gap: pass.gap.min(gap),
+                    ..pass
+                });
+            } else if !pass.touched {
+                let id = pass.asteroid;
+                log::debug!("near_miss entity={} gap={:.1} tick={}", id.0, pass.gap, self.tick_count);
+                self.events.push(GameEvent::NearMiss { asteroid: id });
+                // the closer it came the louder it goes by
+                let closeness = 1.0 - (pass.gap / max_gap).clamp(0.0, 1.0);
+                self.play_sound_at(Cue::NearMiss, id, 0.5 + 0.5 * closeness);
+                if self.tuning.near_miss_slow_mo < 1.0 {
+                    self.slow_mo_start = Some(self.last_time);
+                }
+            }
+        }
+        for (id, gap) in close {
+            if !passes.iter().any(|pass| pass.asteroid == id) {
+                passes.push(NearPass {
+                    asteroid: id,
+                    touched: false,
+                    gap,
+                });
+            }
+        }
+        self.near_passes = passes;
+    }
 
-                    let friction_coeff = 0.25;
-                    let tangent_impulse = friction_coeff * tangent_vel / inv_mass_inertia;
+    // The player's ship docks at a station by coming to rest close to it, and
+    // undocks by flying off out of range
+    fn update_docking(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship = self.entity_store.get(ctrl_id);
+        let (pos, speed) = (ship.transform.translation(), ship.rigid.velocity.length());
+        let in_range = |id: EntityId| {
+            let station = self.entity_store.get(id);
+            (station.transform.translation() - pos).length() - station.collision.radius() < DOCK_RANGE
+        };
 
-                    if obj1.object_type == GameObjectType::Asteroid {
-                        obj1.rigid.apply_impulse(tangent_impulse, offset1);
-                    }
-                    if obj2.object_type == GameObjectType::Asteroid {
-                        obj2.rigid.apply_impulse(-tangent_impulse, offset2);
-                    }
+        if let Some(station) = self.docked_at {
+            if !in_range(station) {
+                log::info!("undocked station={} tick={}", station.0, self.tick_count);
+                self.docked_at = None;
+            }
+            return;
+        }
+        if speed >= DOCK_SPEED {
+            return;
+        }
+        let station = (0..self.entity_store.entities.len())
+            .map(EntityId)
+            .find(|id| self.entity_store.get(*id).object_type == GameObjectType::Station && in_range(*id));
+        if let Some(station) = station {
+            log::info!("docked station={} tick={}", station.0, self.tick_count);
+            self.docked_at = Some(station);
+            self.events.push(GameEvent::Docked { station });
+        }
+    }
+
+    // Ends the camera move that's done, and starts one for this tick's events
+    // if none is playing. It starts on the next tick.
+    fn update_cutscene(&mut self) {
+        let tick = self.tick_count;
+        if let Some(cutscene) = self.cutscene.as_ref().filter(|cutscene| cutscene.is_over(tick)) {
+            log::info!("cutscene_done name={} tick={}", cutscene.name, tick);
+            self.cutscene = None;
+        }
+        if self.cutscene.is_some() || self.control_object.is_none() {
+            return;
+        }
+
+        // out to look at something and back to the ship
+        let there_and_back = |target: Vec2, zoom: f64| {
+            vec![
+                CameraKey::new(CameraTarget::Ship, 1.0, 0),
+                CameraKey::new(CameraTarget::Point(target), zoom, CUTSCENE_MOVE_TICKS),
+                CameraKey::new(CameraTarget::Point(target), zoom, CUTSCENE_HOLD_TICKS),
+                CameraKey::new(CameraTarget::Ship, 1.0, CUTSCENE_MOVE_TICKS),
+            ]
+        };
+        let cutscene = self.events.iter().find_map(|event| match *event {
+            GameEvent::Docked { station } => {
+                let pos = self.entity_store.get(station).transform.translation();
+                Some(("dock", there_and_back(pos, DOCK_ZOOM)))
+            }
+            GameEvent::ShowerStarted { pos } => {
+                Some(("shower_flyover", there_and_back(pos, SHOWER_FLYOVER_ZOOM)))
+            }
+            GameEvent::BossArrived { pos } => Some(("boss_intro", there_and_back(pos, BOSS_INTRO_ZOOM))),
+            _ => None,
+        });
+        if let Some((name, keys)) = cutscene {
+            log::info!("cutscene_started name={} tick={}", name, tick);
+            self.cutscene = Some(Cutscene::new(name, tick + 1, keys));
+        }
+    }
+
+    // Multiplies what the player scored this tick by the combo, then extends the
+    // chain with this tick's risky moves or breaks it on a hit
+    fn update_combo(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let tick = self.tick_count;
+        let multiplier = self.combo.multiplier();
+        let player = self.entity_store.get_mut(ctrl_id);
+        if let Some(score) = player.score.as_mut() {
+            let bonus = self.combo.bonus(score.0);
+            score.0 += bonus;
+            if bonus > 0 {
+                let pos = player.transform.translation();
+                self.spawn_floating_text(format!("x{} +{}", multiplier, bonus), pos, COMBO_LOW_COLOR);
+            }
+        }
+
+        let mut lost = self.combo.update(tick);
+        for event in &self.events {
+            match event {
+                GameEvent::NearMiss { .. } => self.combo.extend(tick),
+                GameEvent::AirCollected { .. } => {
+                    self.combo.pickup(tick);
                 }
+                GameEvent::ShipHit { .. } => lost = lost.max(self.combo.reset()),
+                _ => {}
+            }
+        }
+        if lost > 0 {
+            log::info!("combo_broken chain={} best={} tick={}", lost, self.combo.best, tick);
+        }
+    }
 
-                // Restitution is min of restitutions.
-                let restitution = obj1.rigid.restitution.min(obj2.rigid.restitution);
+    // Play a cue from where an entity is, panned by where it is left or right of
+    // the camera and fading out with distance. Volume is 0..1 before the falloff.
+    fn play_sound_at(&self, cue: Cue, id: EntityId, volume: f64) {
+        let Some(audio) = self.audio.as_ref() else {
+            return;
+        };
+        let offset = self.entity_store.get(id).render_transform.translation() - self.get_camera_pos();
+        let dist = offset.length();
+        if dist > SOUND_MAX_DIST {
+            return;
+        }
+        let pan = (offset.x / SOUND_PAN_DIST).clamp(-1.0, 1.0);
+        let falloff = 1.0 / (1.0 + (dist / SOUND_FALLOFF_DIST).powi(2));
+        audio.play_at(cue, pan, volume.min(1.0) * falloff);
+    }
 
-                let mag = (1.0 + restitution) * contact_vel / inv_mass_inertia;
+    fn update_music(&self) {
+        if let Some(audio) = self.audio.as_ref() {
+            audio.set_danger(self.danger_score());
+        }
+    }
 
-                let impulse = contact.normal1 * mag;
-                obj1.rigid.apply_impulse(impulse, offset1);
-                if obj2.object_type != GameObjectType::Dummy {
-                    obj2.rigid.apply_impulse(-impulse, offset2);
+    // How much trouble the player is in for the music, 0..1: asteroids around the
+    // ship (more so when they're heading for it), running out of air, and enemies
+    fn danger_score(&self) -> f64 {
+        let Some(ctrl_id) = self.control_object.filter(|_| !self.is_game_over()) else {
+            return 0.0;
+        };
+        let ship = self.entity_store.get(ctrl_id);
+        let pos = ship.transform.translation();
+
+        let radius = MUSIC_DANGER_RADIUS;
+        let mut asteroids = 0.0;
+        self.spatial_db.probe_range(
+            pos - Vec2::new(radius, radius)..pos + Vec2::new(radius, radius),
+            self.max_radius,
+            &mut |id| {
+                let other = self.entity_store.get(id);
+                let offset = other.transform.translation() - pos;
+                let dist = offset.length();
+                if other.object_type != GameObjectType::Asteroid || dist >= radius || dist == 0.0 {
+                    return;
                 }
+                let closing_speed = -(other.rigid.velocity - ship.rigid.velocity).dot(offset) / dist;
+                asteroids += 1.0 + (closing_speed / MUSIC_DANGER_CLOSING_SPEED).max(0.0);
+            },
+        );
+        let asteroids = (asteroids / MUSIC_DANGER_ASTEROIDS).min(1.0);
+
+        let air_secs = ship.air_suuply.as_ref().map_or(0, |air| air.air) as f64 / TICKS_PER_SECOND as f64;
+        let air = (1.0 - air_secs / MUSIC_DANGER_AIR_SECS).max(0.0);
+
+        let boss = self.boss.as_ref().is_some_and(|boss| boss.phase != BossPhase::Destroyed);
+        let hostile = |faction: Faction| self.reputation.stance(faction) == Stance::Hostile;
+        let enemies = self
+            .entity_store
+            .entities
+            .iter()
+            .filter(|entity| {
+                entity.pilot.is_some()
+                    && entity.faction_ship.as_ref().is_some_and(|ship| hostile(ship.faction))
+                    && (entity.transform.translation() - pos).length() < FACTION_SIGHT
+            })
+            .count();
+        let enemies = if boss { 1.0 } else { (enemies as f64 / 2.0).min(1.0) };
+
+        (0.4 * asteroids + 0.3 * air + 0.3 * enemies).min(1.0)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        // the demo plays on behind the menu
+        let menu = self.menu.is_some() && self.attract.is_none();
+        menu || self.photo_mode.is_some() || self.star_map.is_some() || self.focus_paused || self.suspended
+    }
+
+    // Window focus changed. Keys held down are let go since their release goes to
+    // whatever has focus now.
+    pub fn set_focused(&mut self, focused: bool) {
+        if focused == self.focused {
+            return;
+        }
+        self.focused = focused;
+        if focused {
+            return;
+        }
+
+        self.input_manager.release_all();
+        if self.settings.pause_on_unfocus && !self.is_paused() && !self.is_game_over() {
+            self.focus_paused = true;
+            log::info!("focus_paused tick={}", self.tick_count);
+        }
+    }
+
+    // Nothing changes until the window gets focus back, so no need to update or render
+    pub fn is_idle(&self) -> bool {
+        (self.focus_paused && !self.focused) || self.suspended
+    }
+
+    // The app was sent to the background, or came back. The clock stands still
+    // in between, so nothing gets at the ship, and the run is autosaved in case
+    // the app isn't brought back. Back from the background, a run that was
+    // going is focus paused until a key is pressed.
+    pub fn set_suspended(&mut self, suspended: bool) {
+        if suspended == self.suspended {
+            return;
+        }
+        self.suspended = suspended;
+        if suspended {
+            self.input_manager.release_all();
+            if let Some(autosave) = self.autosave.as_ref().filter(|_| self.is_run_in_progress()) {
+                autosave.save(&self.snapshot());
             }
+            log::info!("app_suspended tick={}", self.tick_count);
+            return;
         }
+        // the process may not have run at all in the background, that time
+        // doesn't count either
+        self.last_time = Instant::now();
+        if !self.is_paused() && !self.is_game_over() {
+            self.focus_paused = true;
+        }
+        log::info!("app_resumed tick={} paused={}", self.tick_count, self.focus_paused);
+    }
 
-        // one more pass to apply anti-penetration force
-        for contact in contacts.iter() {
-            let id1 = contact.id1.unwrap();
+    // Back in the window any key carries on (and goes on to do what it does),
+    // a replay just carries on since it ignores keys
+    fn update_focus_pause(&mut self) {
+        if !self.focus_paused || !self.focused {
+            return;
+        }
+        if self.input_manager.any_make() || self.playback.is_some() {
+            self.focus_paused = false;
+            log::info!("focus_resumed tick={}", self.tick_count);
+        }
+    }
 
-            let (obj1, obj2) = if let Some(id2) = contact.id2 {
-                self.entity_store.get_mut_pair(id1, id2)
-            } else {
-                (self.entity_store.get_mut(id1), &mut dummy_obj)
+    pub fn get_photo_filter(&self) -> PhotoFilter {
+        self.photo_mode
+            .as_ref()
+            .map_or(PhotoFilter::None, |photo_mode| photo_mode.filter)
+    }
+
+    // Returns true once per screenshot taken in photo mode
+    pub fn take_screenshot_request(&mut self) -> bool {
+        std::mem::take(&mut self.screenshot_requested)
+    }
+
+    fn update_photo_mode(&mut self) {
+        if self.menu.is_some() || self.star_map.is_some() {
+            return;
+        }
+
+        if self.input_manager.is_make(PhysicalKey::Code(KeyCode::KeyP)) {
+            self.photo_mode = match self.photo_mode {
+                Some(_) => None,
+                None => Some(PhotoMode::new(self.get_camera_pos())),
             };
+            log::info!("photo_mode enabled={}", self.photo_mode.is_some());
+            self.input_manager.clear_events();
+            return;
+        }
 
-            if (obj1.object_type == GameObjectType::AidPod
-                && obj2.object_type == GameObjectType::Ship)
-                || (obj2.object_type == GameObjectType::AidPod
-                    && obj1.object_type == GameObjectType::Ship)
-            {
-                continue;
-            }
+        let Some(photo_mode) = self.photo_mode.as_mut() else {
+            return;
+        };
+        photo_mode.update(&self.input_manager, self.frame_secs);
+        self.update_inspector();
+        if self.input_manager.is_make(PhysicalKey::Code(KeyCode::Enter))
+            || self.input_manager.is_make(PhysicalKey::Code(KeyCode::F12))
+        {
+            self.screenshot_requested = true;
+        }
+        self.actions.update(&self.input_manager, self.tick_count);
+        self.update_settings_controls();
 
-            // apply position correction, moving in proportion to mass
-            let percent = 0.5;
-            let inv_mass1 = obj1.rigid.inv_mass;
-            let inv_mass2 = obj2.rigid.inv_mass;
-            let correction =
-                contact.normal1 * percent * contact.depth.max(0.0) / (inv_mass1 + inv_mass2);
-            obj1.transform.apply_translation(-correction * inv_mass1);
-            obj2.transform.apply_translation(correction * inv_mass2);
+        // simulation is paused, so nothing else will consume the input
+        self.input_manager.clear_events();
+    }
+
+    fn update_star_map(&mut self) {
+        if self.menu.is_some() || self.photo_mode.is_some() {
+            return;
+        }
+
+        // while the tutorial runs, Tab skips a step instead
+        let in_tutorial = self
+            .tutorial
+            .as_ref()
+            .is_some_and(|tutorial| tutorial.step != TutorialStep::Done);
+        if !in_tutorial && self.input_manager.is_make(PhysicalKey::Code(KeyCode::Tab)) {
+            let center = self.get_control_object().map_or(Vec2::ZERO, |id| {
+                self.entity_store.get(id).transform.translation()
+            });
+            self.star_map = match self.star_map {
+                Some(_) => None,
+                None => Some(StarMap::new(center)),
+            };
+            log::info!("star_map open={}", self.star_map.is_some());
+            self.input_manager.clear_events();
+            return;
         }
 
-        // slip this in here but really this is nothing to do with resolving collisions,
-        // this is responding to special collision between ship and air pod
-        if let Some(air_id) = relocate_air {
-            let seq = self.get_sequence();
-            let air = self.entity_store.get_mut(air_id);
-            air.pick_position(
-                self.seed,
-                seq,
-                self.spatial_db.get_min()..self.spatial_db.get_max(),
-            );
+        let Some(star_map) = self.star_map.as_mut() else {
+            return;
+        };
+        star_map.update(&self.input_manager, self.frame_secs, self.spatial_db.get_max().x);
+        // simulation is paused, so nothing else will consume the input
+        self.input_manager.clear_events();
+    }
+
+    // Start the demo once the main menu has sat there long enough with nothing
+    // pressed, and go back once something is (the key doesn't go on to the menu)
+    // or the demo is over
+    fn update_attract(&mut self) {
+        if self.attract.is_some() {
+            if self.input_manager.any_make() || self.is_game_over() {
+                self.input_manager.clear_events();
+                self.stop_attract();
+            }
+            return;
+        }
+        let idle = self.menu.as_ref().is_some_and(Menu::is_main)
+            && self.playback.is_none()
+            && !self.focus_paused
+            && !self.is_game_over()
+            && !self.input_manager.any_make();
+        if !idle {
+            self.menu_idle_secs = 0.0;
+            return;
+        }
+        self.menu_idle_secs += self.frame_secs;
+        if self.menu_idle_secs >= ATTRACT_IDLE_SECS {
+            self.start_attract();
+        }
+    }
+
+    // Swap in a copy of this world, flown by the autopilot, keeping this one to
+    // come back to. Saving and metrics stay with it, the demo isn't a run.
+    fn start_attract(&mut self) {
+        let mut demo = GameWorld::from_snapshot(&self.snapshot());
+        demo.playback = Some(self.new_recording());
+        self.hand_setup_to(&mut demo);
+        self.autosave = demo.autosave.take();
+        self.metrics = demo.metrics.take();
+        self.menu_idle_secs = 0.0;
+        demo.menu = self.menu.take();
+        if let Some(ctrl_id) = demo.control_object {
+            let skill = PilotSkill::for_level(SkillLevel::Ace);
+            demo.entity_store.get_mut(ctrl_id).pilot = Some(Pilot::new(skill));
+        }
+
+        let home = std::mem::replace(self, demo);
+        self.apply_ship_style();
+        self.attract = Some(Attract::new(home));
+        log::info!("attract_started seed={}", self.seed);
+    }
+
+    // Put back the world the menu was over, as it was when the demo started.
+    // Also called on exit, as that's the world to wrap up.
+    pub fn stop_attract(&mut self) {
+        let Some(attract) = self.attract.take() else {
+            return;
+        };
+        let mut home = *attract.home;
+        let (autosave, metrics) = (home.autosave.take(), home.metrics.take());
+        self.hand_setup_to(&mut home);
+        home.autosave = autosave;
+        home.metrics = metrics;
+        home.menu = self.menu.take();
+        // keys held down through the demo are let go in the menu world
+        home.input_manager = std::mem::replace(&mut self.input_manager, InputManager::new());
+        log::info!("attract_stopped tick={} score={}", self.tick_count, self.get_score());
+        *self = home;
+    }
+
+    // The demo ship goes after the nearest air pod, around the rocks on the way
+    fn update_autopilot(&mut self) {
+        let (Some(attract), Some(ctrl_id)) = (self.attract.as_mut(), self.control_object) else {
+            return;
+        };
+        let pos = self.entity_store.get(ctrl_id).transform.translation();
+        let mut pod_position = |id: EntityId| {
+            let entity = self.entity_store.get(id);
+            (entity.object_type == GameObjectType::AidPod).then(|| entity.transform.translation())
+        };
+        let pod = self.spatial_db.find_nearest(pos, &mut pod_position).map(|(id, _)| id);
+        let target = pod.map(|id| {
+            let goal = self.entity_store.get(id).transform.translation();
+            match self.nav_grid.as_ref() {
+                Some(grid) => attract.nav_path.steer(grid, pos, goal, self.tick_count),
+                None => goal,
+            }
+        });
+        if let Some(pilot) = self.entity_store.get_mut(ctrl_id).pilot.as_mut() {
+            pilot.target = target;
+        }
+    }
+
+    fn update_menu(&mut self) {
+        let Some(menu) = self.menu.as_mut() else {
+            return;
+        };
+
+        let action = menu.handle_input(&self.input_manager);
+        // menu input shouldn't leak into the simulation
+        self.input_manager.clear_events();
+
+        match action {
+            Some(MenuAction::Endless) => {
+                self.start_run();
+                self.start_endless();
+            }
+            Some(MenuAction::Escort) => {
+                self.start_run();
+                self.start_escort();
+            }
+            Some(MenuAction::TimeAttack) => {
+                self.start_run();
+                self.start_race();
+            }
+            Some(MenuAction::Tutorial) => {
+                self.start_run();
+                self.start_tutorial();
+            }
+            Some(MenuAction::Hangar) => {
+                self.menu = Some(Menu::hangar(&self.progression, 0));
+            }
+            Some(MenuAction::PaintShop) => {
+                self.menu = Some(Menu::paint_shop(self.ship_style, 0));
+            }
+            Some(MenuAction::NextHullPaint) => {
+                self.ship_style.hull = self.ship_style.hull.next();
+                self.restyle_ship(0);
+            }
+            Some(MenuAction::NextStripes) => {
+                self.ship_style.stripes = self.ship_style.stripes.next();
+                self.restyle_ship(1);
+            }
+            Some(MenuAction::NextFlame) => {
+                self.ship_style.flame = self.ship_style.flame.next();
+                self.restyle_ship(2);
+            }
+            Some(MenuAction::Unlock(idx)) => {
+                let unlock = UNLOCKS[idx];
+                match self.progression.activate(unlock) {
+                    Ok(()) => {
+                        log::info!(
+                            "unlock key={} active={} credits={}",
+                            unlock.key(),
+                            self.progression.is_active(unlock),
+                            self.progression.credits
+                        );
+                        self.save_progression();
+                    }
+                    Err(err) => log::info!("unlock_refused key={} reason=\"{}\"", unlock.key(), err),
+                }
+                self.menu = Some(Menu::hangar(&self.progression, idx));
+            }
+            Some(MenuAction::Continue) => {
+                let Some(snapshot) = self.saved_game.take() else {
+                    return;
+                };
+                log::info!("continue_game tick={}", snapshot.tick);
+                self.continue_from(&snapshot);
+            }
+            Some(MenuAction::LoadGame) => {
+                self.menu = Some(Menu::load_game(&self.slot_descriptions()));
+            }
+            Some(MenuAction::LoadSlot(slot)) => {
+                let path = self.profile.slot_path(slot);
+                match WorldSnapshot::load(&path) {
+                    Ok(snapshot) => {
+                        log::info!("game_loaded slot={} tick={}", slot + 1, snapshot.tick);
+                        // the run picked instead of the autosave, which is done with
+                        self.saved_game = None;
+                        self.continue_from(&snapshot);
+                    }
+                    Err(err) => log::error!("Failed to load game: {}", err),
+                }
+            }
+            Some(MenuAction::SaveSlot(slot)) => {
+                self.save_to_slot(slot);
+                self.menu = None;
+            }
+            Some(MenuAction::Profiles) => {
+                self.menu = Some(Menu::profiles(&Profile::list(), &self.profile.name));
+            }
+            Some(MenuAction::SelectProfile(idx)) => {
+                // same list the menu was made from
+                if let Some(name) = Profile::list().get(idx) {
+                    self.switch_profile(name);
+                }
+            }
+            Some(MenuAction::NewProfile) => {
+                let name = menu.take_name().unwrap_or_default();
+                self.switch_profile(&name);
+            }
+            Some(MenuAction::Back) => {
+                // back from a main menu page, or back to the game from the save slots
+                if self.tick_count == 0 {
+                    let saved_game = self.saved_game.take();
+                    self.show_main_menu(saved_game);
+                } else {
+                    self.menu = None;
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Leave the main menu for a run, in the ship and with the modules the
+    // profile has switched on, and as many lives as the difficulty gives
+    fn start_run(&mut self) {
+        self.menu = None;
+        self.saved_game = None;
+        let mut loadout = self.progression.loadout();
+        loadout.spare_lives = self.settings.difficulty.lives.saturating_sub(1);
+        loadout.slow_mode = self.settings.difficulty.slow_mode;
+        loadout.pilot_skill = self.settings.difficulty.pilot_skill;
+        self.apply_loadout(&loadout);
+    }
+
+    // Fit out the player's ship for a run: its class and the modules it starts
+    // with, the run's spare lives, whether it's in slow mode and how well the
+    // faction ships are flown
+    pub fn apply_loadout(&mut self, loadout: &Loadout) {
+        self.lives = Lives::new(loadout.spare_lives);
+        self.set_slow_mode(loadout.slow_mode);
+        self.pilot_skill = loadout.pilot_skill;
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship = self.entity_store.get_mut(ctrl_id);
+        ship.set_ship_class(loadout.ship);
+        if let Some(cargo) = ship.cargo.as_mut() {
+            cargo.upgrades = loadout.modules.clone();
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.loadout = loadout.clone();
+        }
+        log::info!("loadout {}", loadout.to_text());
+    }
+
+    // What the player's ship is fitted with now
+    fn loadout(&self) -> Loadout {
+        let ship = self.control_object.map(|id| self.entity_store.get(id));
+        Loadout {
+            ship: ShipClass::from_variant(ship.map_or(0, |ship| ship.variant)),
+            modules: ship
+                .and_then(|ship| ship.cargo.as_ref())
+                .map_or(Vec::new(), |cargo| cargo.upgrades.clone()),
+            spare_lives: self.lives.spare(),
+            slow_mode: self.slow_mode,
+            pilot_skill: self.pilot_skill,
+        }
+    }
+
+    // The accessibility slow mode goes through the tuning, which a new world
+    // starts over with, so it's set again whenever a world is made for a run.
+    // Left as it is, the tuning keeps any changes made in the panel.
+    fn set_slow_mode(&mut self, slow_mode: bool) {
+        if slow_mode != self.slow_mode {
+            self.tuning.set_slow_mode(slow_mode);
+        }
+        self.slow_mode = slow_mode;
+    }
+
+    // The style was changed in the paint shop, selected is the item that changed it
+    fn restyle_ship(&mut self, selected: usize) {
+        let style = self.ship_style;
+        log::info!(
+            "ship_style hull={} stripes={} flame={}",
+            style.hull.key(),
+            style.stripes.key(),
+            style.flame.key()
+        );
+        let path = self.profile.path(SHIP_STYLE_FILE);
+        if let Err(err) = self.platform.store_cloud_file(&path, style.to_text().as_bytes()) {
+            log::warn!("Failed to save ship style {}: {}", path.display(), err);
+        }
+        self.apply_ship_style();
+        self.menu = Some(Menu::paint_shop(style, selected));
+    }
+
+    // Paint the player's ship the way the profile has it. It's just the look,
+    // so it isn't part of the loadout and replays don't keep it.
+    fn apply_ship_style(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship = self.entity_store.get_mut(ctrl_id);
+        if ship.object_type == GameObjectType::Ship && ship.faction_ship.is_none() {
+            ship.shape = Some(ship_shape(&self.ship_style));
+        }
+    }
+
+    fn save_progression(&self) {
+        let path = self.profile.path(PROGRESSION_FILE);
+        if let Err(err) = self.platform.store_cloud_file(&path, self.progression.to_text().as_bytes()) {
+            log::warn!("Failed to save progression {}: {}", path.display(), err);
+        }
+    }
+
+    // A finished run earns credits for the hangar. Replays and ghosts have been
+    // played before, and the tutorial is practice, so they don't count.
+    fn update_run_credits(&mut self) {
+        if self.run_credits.is_some()
+            || self.playback.is_some()
+            || self.mode == GameMode::Tutorial
+            || !self.is_game_over()
+        {
+            return;
+        }
+        let credits = credits_for_run(self.get_score(), self.tick_count, &self.modifiers);
+        self.progression.credits += credits;
+        self.run_credits = Some(credits);
+        log::info!("run_credits credits={} total={}", credits, self.progression.credits);
+        self.save_progression();
+    }
+
+    // Carry on a saved run in place of this world, keeping the player's setup.
+    // Like a run that went back, it can't be replayed, so it doesn't go on the
+    // leaderboard.
+    fn continue_from(&mut self, snapshot: &WorldSnapshot) {
+        if self.recording.is_some() {
+            log::warn!("Recording stopped, a continued run can't be replayed");
+        }
+        self.take_over(GameWorld::from_snapshot(snapshot));
+    }
+
+    // Replace this world with another, keeping the player's setup
+    fn take_over(&mut self, mut world: GameWorld) {
+        self.hand_setup_to(&mut world);
+        *self = world;
+        self.apply_ship_style();
+    }
+
+    // Move the player's setup over to another world
+    fn hand_setup_to(&mut self, world: &mut GameWorld) {
+        world.settings = self.settings.clone();
+        world.actions = ActionState::new(self.actions.bindings().clone());
+        world.autosave = self.autosave.take();
+        world.metrics = self.metrics.take();
+        world.crash = self.crash.take();
+        world.check_invariants = self.check_invariants;
+        world.spawn_tables = self.spawn_tables.clone();
+        world.online = self.online.take();
+        world.platform = self.platform.clone();
+        world.profile = self.profile.clone();
+        world.hints = std::mem::take(&mut self.hints);
+        world.audio = self.audio.take();
+        world.haptics = self.haptics.take();
+        world.feedback_enabled = self.feedback_enabled;
+        world.sprites = self.sprites.clone();
+        world.progression = std::mem::take(&mut self.progression);
+        world.ship_style = self.ship_style;
+    }
+
+    pub fn get_tick_count(&self) -> u32 {
+        self.tick_count
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        let escort_over = self.escort.as_ref().is_some_and(|escort| escort.outcome.is_some());
+        let race_over = self.race.as_ref().is_some_and(|race| race.finish.is_some());
+        let out_of_air = self
+            .control_object
+            .and_then(|id| self.entity_store.get(id).air_suuply.as_ref())
+            .map_or(true, |air| air.air == 0);
+        // a ship with a life to spare is coming back
+        let coming_back = self.lives.is_respawning() || self.lives.left() > 0;
+        escort_over || race_over || (out_of_air && !coming_back)
+    }
+
+    pub fn get_score(&self) -> u64 {
+        self.control_object
+            .and_then(|id| self.entity_store.get(id).score)
+            .map_or(0, |score| score.0)
+    }
+
+    // Put object somewhere in pos_range where it doesn't overlap anything, trying
+    // retry_checks spots at random. If none of them are free, objects that
+    // must_place go in the free spot nearest the last one tried, others aren't added.
+    fn add_object(
+        &mut self,
+        object: GameObject,
+        pos_range: Range<Vec2>,
+        retry_checks: u32,
+        must_place: bool,
+    ) -> Option<EntityId> {
+        let mut object = object;
+
+        let our_rad = object.collision.radius();
+
+        // adjust position range to account for radius, and keep it inside the arena
+        let mut pos_range = pos_range;
+        let area = self.arena.spawn_range(self.tick_count as f64);
+        let range_min = area.start + Vec2::new(our_rad, our_rad);
+        let range_max = area.end - Vec2::new(our_rad, our_rad);
+        pos_range.start.x = pos_range.start.x.max(range_min.x);
+        pos_range.start.y = pos_range.start.y.max(range_min.y);
+        pos_range.end.x = pos_range.end.x.min(range_max.x);
+        pos_range.end.y = pos_range.end.y.min(range_max.y);
+
+        if object.collision.radius() > self.max_radius {
+            self.max_radius = object.collision.radius();
+        }
+
+        let mut clear = false;
+        for _ in 0..retry_checks {
+            object.pick_position(&mut self.placement_rng, pos_range.clone());
+            if self.is_clear(object.transform.translation(), our_rad, None) {
+                clear = true;
+                break;
+            }
+        }
+        if !clear {
+            if !must_place {
+                return None;
+            }
+            let wanted = object.transform.translation();
+            match self.find_clear_spot(wanted, our_rad, range_min..range_max, None) {
+                Some(pos) => object.teleport(pos),
+                None => log::warn!("no_clear_spot radius={:.0} x={:.0} y={:.0}", our_rad, wanted.x, wanted.y),
+            }
+        }
+
+        Some(self.insert_object(object))
+    }
+
+    // Nothing is within clearance of pos, other than debris (which bounces off
+    // whatever it hits) and ignore
+    fn is_clear(&self, pos: Vec2, clearance: f64, ignore: Option<EntityId>) -> bool {
+        let reach = Vec2::new(clearance, clearance);
+        let mut clear = true;
+        self.spatial_db.probe_range(pos - reach..pos + reach, self.max_radius, &mut |other_id| {
+            let other = self.entity_store.get(other_id);
+            if Some(other_id) == ignore || other.object_type == GameObjectType::Debris {
+                return;
+            }
+            if (pos - other.transform.translation()).length() < clearance + other.collision.radius() {
+                clear = false;
+            }
+        });
+        clear
+    }
+
+    // The spot nearest near, inside area, with nothing within clearance of it (see
+    // is_clear). Looked for in square rings growing out from near, half a spatial
+    // db node apart, closest first within each ring. None if the area is full.
+    pub fn find_clear_spot(
+        &self,
+        near: Vec2,
+        clearance: f64,
+        area: Range<Vec2>,
+        ignore: Option<EntityId>,
+    ) -> Option<Vec2> {
+        let inside = |pos: Vec2| {
+            pos.x >= area.start.x && pos.x <= area.end.x && pos.y >= area.start.y && pos.y <= area.end.y
+        };
+        let near = Vec2::new(near.x.clamp(area.start.x, area.end.x), near.y.clamp(area.start.y, area.end.y));
+        let step = 0.5 * self.spatial_db.get_node_size();
+        for ring in 0..=2 * self.spatial_db.get_dim() as i32 {
+            let mut spots = Vec::new();
+            for y in -ring..=ring {
+                for x in -ring..=ring {
+                    let pos = near + step * Vec2::new(x as f64, y as f64);
+                    if (x.abs() == ring || y.abs() == ring) && inside(pos) {
+                        spots.push(pos);
+                    }
+                }
+            }
+            if spots.is_empty() {
+                // the ring has gone past the area all around
+                break;
+            }
+            spots.sort_by(|a, b| (*a - near).length().total_cmp(&(*b - near).length()));
+            if let Some(pos) = spots.into_iter().find(|&pos| self.is_clear(pos, clearance, ignore)) {
+                return Some(pos);
+            }
+        }
+        None
+    }
+
+    // Insert object at its current position
+    fn insert_object(&mut self, object: GameObject) -> EntityId {
+        if object.collision.radius() > self.max_radius {
+            self.max_radius = object.collision.radius();
+        }
+
+        let id = self.get_entities_mut().insert(object);
+        let obj = self.entity_store.get_mut(id);
+        if !obj.flags.contains(EntityFlags::RENDER_ONLY) {
+            let pos = obj.transform.translation();
+            self.spatial_db.update(id, pos, &mut obj.spatial_db_ref);
+        }
+        id
+    }
+
+    pub fn get_resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    pub fn get_entities(&self) -> &EntityStore {
+        &self.entity_store
+    }
+
+    pub fn get_entities_mut(&mut self) -> &mut EntityStore {
+        &mut self.entity_store
+    }
+
+    pub fn get_spatial_db(&self) -> &SpatialDb {
+        &self.spatial_db
+    }
+
+    pub fn add_ship(&mut self, pos_range: Range<Vec2>) -> EntityId {
+        let seq = self.get_sequence();
+        let ship = GameObject::new_ship(&self.get_resources(), self.get_seed(), seq);
+
+        self.add_object(ship, pos_range, 10, true).unwrap()
+    }
+
+    pub fn add_asteroid(
+        &mut self,
+        pos_range: Range<Vec2>,
+        vel_range: Range<f64>,
+        ang_vel_range: Range<f64>,
+        must_place: bool,
+    ) -> Option<EntityId> {
+        let (rng, materials) = (&mut self.asteroid_rng, &self.spawn_tables.asteroid_material);
+        let asteroid = GameObject::new_asteroid(&self.resources, rng, materials, vel_range, ang_vel_range);
+
+        self.add_object(asteroid, pos_range, 10, must_place)
+    }
+
+    pub fn add_derelict(&mut self, pos_range: Range<Vec2>, loot: LootKind) -> Option<EntityId> {
+        let seq = self.get_sequence();
+        let derelict = GameObject::new_derelict(&self.get_resources(), self.get_seed(), seq, loot);
+        self.add_object(derelict, pos_range, 10, false)
+    }
+
+    pub fn add_air_pod(&mut self, pos_range: Range<Vec2>) -> EntityId {
+        let seq = self.get_sequence();
+        let tier = PodTier::default();
+        let mut air_pod = GameObject::new_air_pod(&self.get_resources(), self.get_seed(), seq, tier);
+        // pulses from when it shows up
+        if let Some(animation) = air_pod.animation.as_mut() {
+            animation.start_time = self.get_tick_secs();
+        }
+        self.add_object(air_pod, pos_range, 10, true).unwrap()
+    }
+
+    pub fn add_station(&mut self, pos_range: Range<Vec2>) -> Option<EntityId> {
+        let station = GameObject::new_station(&self.get_resources());
+        self.add_object(station, pos_range, 10, false)
+    }
+
+    // Stations have to be added first, traders get all of them as their route
+    pub fn add_faction_ship(&mut self, faction: Faction, pos_range: Range<Vec2>) -> Option<EntityId> {
+        let skill = PilotSkill::for_level(self.pilot_skill);
+        let mut ship = GameObject::new_faction_ship(&self.get_resources(), faction, skill);
+        if faction == Faction::Traders {
+            if let Some(cargo) = ship.cargo.as_mut() {
+                cargo.ore = TRADER_CARGO_ORE;
+            }
+        }
+
+        let id = self.add_object(ship, pos_range, 10, false)?;
+        self.set_up_faction_ship(id);
+        Some(id)
+    }
+
+    // Barriers lie at a random angle and sweep across it
+    pub fn add_barrier(&mut self, pos_range: Range<Vec2>) -> Option<EntityId> {
+        let seq = self.get_sequence();
+        let mut barrier = GameObject::new_barrier(&self.get_resources());
+        let rotation = (0.0..PI).hash_rand(self.seed, (seq, "barrier_angle"));
+        barrier.transform = Transform::new(Vec2::ZERO, rotation);
+        barrier.prev_transform = barrier.transform;
+        let id = self.add_object(barrier, pos_range, 10, false)?;
+        self.set_up_barrier(id);
+        Some(id)
+    }
+
+    // Sweep through where the barrier is now. Works out the center from the tick
+    // too, so a barrier restored from a save carries on along the same line.
+    fn set_up_barrier(&mut self, id: EntityId) {
+        let tick = self.tick_count;
+        let barrier = self.entity_store.get_mut(id);
+        let across = Transform::new(Vec2::ZERO, barrier.transform.rotation());
+        let reach = across.local_to_world(Vec2::new(0.0, BARRIER_SWEEP_REACH));
+        let period = BARRIER_SWEEP_SECS * TICKS_PER_SECOND as u32;
+        let offset = Kinematic::Sweep {
+            center: Vec2::ZERO,
+            reach,
+            period,
+        }
+        .position_at(tick)
+        .unwrap_or_default();
+        barrier.kinematic = Some(Kinematic::Sweep {
+            center: barrier.transform.translation() - offset,
+            reach,
+            period,
+        });
+    }
+
+    // Clusters of debris drifting through the field (see debris.rs). They go by
+    // the seed and the tick rather than the sequence, so they come out the same
+    // in a world rebuilt from a snapshot without changing what comes after.
+    fn add_debris(&mut self) {
+        for cluster in 0..NUM_DEBRIS_CLUSTERS {
+            let pieces: Vec<EntityId> = (0..DEBRIS_PER_CLUSTER)
+                .map(|piece| {
+                    let num = (cluster * DEBRIS_PER_CLUSTER + piece) as u32;
+                    let debris = GameObject::new_debris(&self.resources, self.seed, num);
+                    self.insert_object(debris)
+                })
+                .collect();
+            self.scatter_debris(cluster, &pieces);
+            self.debris.add_cluster(pieces);
+        }
+    }
+
+    // Scatter a cluster's pieces around a spot away from the player, all
+    // drifting the same way
+    fn scatter_debris(&mut self, cluster: usize, pieces: &[EntityId]) {
+        let player = self.control_object.map(|id| self.entity_store.get(id).transform.translation());
+        let area = self.arena.spawn_range(self.tick_count as f64);
+        let key = (cluster, self.tick_count);
+        // a few tries, the last one goes anyway
+        let mut center = Vec2::ZERO;
+        for attempt in 0..8 {
+            center = area.clone().hash_rand(self.seed, (key, attempt, "debris_center"));
+            if !player.is_some_and(|pos| (center - pos).length() < DEBRIS_PLAYER_DIST) {
+                break;
+            }
+        }
+        let drift_angle = (0.0..TAU).hash_rand(self.seed, (key, "debris_drift"));
+        let drift = DEBRIS_DRIFT_SPEED * Vec2::from_angle(drift_angle);
+        let spread = Vec2::new(DEBRIS_SPREAD_SPEED, DEBRIS_SPREAD_SPEED);
+
+        for (idx, &id) in pieces.iter().enumerate() {
+            let angle = (0.0..TAU).hash_rand(self.seed, (key, idx, "debris_angle"));
+            // the square root spreads them evenly over the disk
+            let dist: f64 = (0.0..1.0).hash_rand(self.seed, (key, idx, "debris_dist"));
+            let dist = DEBRIS_CLUSTER_RADIUS * dist.sqrt();
+            let piece = self.entity_store.get_mut(id);
+            piece.teleport(center + dist * Vec2::from_angle(angle));
+            piece.rigid.velocity = drift + (-spread..spread).hash_rand(self.seed, (key, idx, "debris_vel"));
+            piece.rigid.angular_velocity = (-0.1..0.1).hash_rand(self.seed, (key, idx, "debris_spin"));
+        }
+        log::debug!(
+            "debris_scattered cluster={} x={:.0} y={:.0} tick={}",
+            cluster,
+            center.x,
+            center.y,
+            self.tick_count
+        );
+    }
+
+    // Every so often the cluster scattered longest ago is gathered up and
+    // scattered again, unless the player is close enough to see it go
+    fn update_debris(&mut self) {
+        let Some(pieces) = self.debris.due(self.tick_count) else {
+            return;
+        };
+        let pieces = pieces.to_vec();
+        let cluster = self.debris.advance();
+        if let Some(ctrl_id) = self.control_object {
+            let pos = self.entity_store.get(ctrl_id).transform.translation();
+            let near = |id: &EntityId| {
+                (self.entity_store.get(*id).transform.translation() - pos).length() < DEBRIS_PLAYER_DIST
+            };
+            if pieces.iter().any(near) {
+                return;
+            }
+        }
+        self.scatter_debris(cluster, &pieces);
+    }
+
+    // Bolts that didn't hit anything run out
+    fn update_projectiles(&mut self) {
+        let mut spent = Vec::new();
+        for (id, entity) in self.entity_store.iter_mut_entity() {
+            let Some(projectile) = entity.projectile.as_mut() else {
+                continue;
+            };
+            projectile.ticks_left = projectile.ticks_left.saturating_sub(1);
+            if projectile.ticks_left == 0 {
+                spent.push(id);
+            }
+        }
+        for id in spent {
+            self.despawn(id);
+        }
+    }
+
+    // Dust around the ship and ships far off, see scenery.rs. Like the debris
+    // they go by the seed rather than the sequence.
+    fn add_scenery(&mut self) {
+        let ship_pos = self
+            .control_object
+            .map_or(Vec2::ZERO, |id| self.entity_store.get(id).transform.translation());
+        for idx in 0..NUM_DUST_MOTES {
+            let angle = (0.0..TAU).hash_rand(self.seed, (idx, "dust_angle"));
+            // the square root spreads them evenly over the disk
+            let dist: f64 = (0.0..1.0).hash_rand(self.seed, (idx, "dust_dist"));
+            let drift_angle = (0.0..TAU).hash_rand(self.seed, (idx, "dust_drift"));
+            let mut mote = GameObject::new_dust_mote(&self.resources);
+            mote.teleport(ship_pos + DUST_RADIUS * dist.sqrt() * Vec2::from_angle(angle));
+            mote.rigid.velocity = DUST_DRIFT_SPEED * Vec2::from_angle(drift_angle);
+            self.insert_object(mote);
+        }
+
+        let area = self.arena.spawn_range(0.0);
+        for idx in 0..NUM_BACKGROUND_SHIPS {
+            let faction = FACTIONS[idx % FACTIONS.len()];
+            let heading = (0.0..TAU).hash_rand(self.seed, (idx, "background_ship_heading"));
+            let mut ship = GameObject::new_background_ship(&self.resources, faction);
+            ship.teleport(area.clone().hash_rand(self.seed, (idx, "background_ship_pos")));
+            // ships point along their y axis
+            ship.transform.rotation = heading - FRAC_PI_2;
+            ship.prev_transform.rotation = ship.transform.rotation;
+            ship.rigid.velocity = BACKGROUND_SHIP_SPEED * Vec2::from_angle(heading);
+            self.insert_object(ship);
+        }
+
+        // engine flames on the ships, and the ore the player's ship tows
+        let ships: Vec<EntityId> = (0..self.entity_store.entities.len())
+            .map(EntityId)
+            .filter(|&id| self.entity_store.get(id).object_type == GameObjectType::Ship)
+            .collect();
+        for ship in ships {
+            let flame = self.insert_object(GameObject::new_attached_scenery(SCENERY_FLAME));
+            self.attach(flame, ship, Vec2::ZERO, 0.0);
+        }
+        if let Some(ctrl_id) = self.control_object {
+            let pod = self.insert_object(GameObject::new_attached_scenery(SCENERY_CARGO));
+            self.attach(pod, ctrl_id, CARGO_POD_OFFSET, 0.0);
+        }
+    }
+
+    // Scenery drifts along on its own, outside physics. Dust that falls too far
+    // behind the ship comes back in ahead of it, ships that leave the field come
+    // back in on the far side.
+    fn update_scenery(&mut self) {
+        let ship_pos = self.control_object.map(|id| self.entity_store.get(id).transform.translation());
+        let area = self.arena.spawn_range(self.tick_count as f64);
+        for idx in 0..self.entity_store.entities.len() {
+            let entity = &self.entity_store.entities[idx];
+            if entity.object_type != GameObjectType::Scenery {
+                continue;
+            }
+            if let Some(attachment) = entity.attachment {
+                self.update_attached_scenery(EntityId(idx), attachment);
+                continue;
+            }
+            let entity = &mut self.entity_store.entities[idx];
+            let pos = entity.transform.translation() + entity.rigid.velocity;
+            let wrapped = if entity.variant == SCENERY_SHIP {
+                wrap_background_ship(pos, area.clone())
+            } else {
+                ship_pos.and_then(|ship_pos| wrap_dust(pos, ship_pos))
+            };
+            match wrapped {
+                Some(pos) => entity.teleport(pos),
+                None => entity.transform.apply_translation(entity.rigid.velocity),
+            }
+        }
+    }
+
+    // Put attached scenery on its parent, showing what the parent is up to
+    fn update_attached_scenery(&mut self, id: EntityId, attachment: Attachment) {
+        let parent = self.entity_store.get(attachment.parent);
+        let transform = attachment.world_transform(&parent.transform);
+        let (animation, shape) = match self.entity_store.get(id).variant {
+            SCENERY_FLAME => {
+                // only the player's ship has its flame colored, and it shows none while cloaked
+                let player = Some(attachment.parent) == self.control_object;
+                let color = if player {
+                    self.ship_style.flame
+                } else {
+                    FlameColor::default()
+                };
+                let clip = AnimationClip::Flame(color);
+                let thrust_start = parent.thrust_start.filter(|_| !(player && self.cloaked));
+                (thrust_start.map(|start| Animation::looping(clip, start)), None)
+            }
+            _ => {
+                let towing = parent.cargo.as_ref().is_some_and(|cargo| cargo.ore > 0);
+                (None, towing.then(|| self.resources.cargo_pod_shape.clone()))
+            }
+        };
+        let entity = self.entity_store.get_mut(id);
+        entity.transform = transform;
+        entity.animation = animation;
+        entity.shape = shape;
+    }
+
+    // A gun on every pirate ship and two on every station. Like the scenery they
+    // go in after everything that's saved, and are mounted again on a load.
+    fn add_turrets(&mut self) {
+        let mounts: Vec<(EntityId, Vec<Vec2>)> = (0..self.entity_store.entities.len())
+            .map(EntityId)
+            .filter_map(|id| {
+                let entity = self.entity_store.get(id);
+                let faction = entity.faction_ship.as_ref().map(|ship| ship.faction);
+                if entity.object_type == GameObjectType::Station {
+                    Some((id, STATION_TURRET_OFFSETS.to_vec()))
+                } else if faction == Some(Faction::Pirates) {
+                    Some((id, vec![SHIP_TURRET_OFFSET]))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for (parent, offsets) in mounts {
+            let turret = match self.entity_store.get(parent).object_type {
+                GameObjectType::Station => Turret::station(),
+                _ => Turret::pirate(),
+            };
+            for offset in offsets {
+                let gun = self.insert_object(GameObject::new_gun(&self.resources, turret.clone()));
+                self.attach(gun, parent, offset, 0.0);
+            }
+        }
+    }
+
+    pub fn add_mine(&mut self, pos_range: Range<Vec2>, variant: u32) -> Option<EntityId> {
+        let mine = GameObject::new_mine(&self.get_resources(), variant);
+        self.add_object(mine, pos_range, 10, false)
+    }
+
+    // Turn the current world into an escort mission: a convoy crosses from the
+    // left edge to the right one through a field of mines
+    pub fn start_escort(&mut self) {
+        let Range { start: min, end: max } = self.arena.spawn_range(self.tick_count as f64);
+        let start = Vec2::new(min.x + ESCORT_EDGE_MARGIN, 0.5 * (min.y + max.y));
+        let destination = Vec2::new(max.x - ESCORT_EDGE_MARGIN, start.y);
+
+        let convoy = GameObject::new_convoy(&self.get_resources());
+        let convoy_id = self.add_object(convoy, start..start, 1, true).unwrap();
+        if let Some(pilot) = self.entity_store.get_mut(convoy_id).pilot.as_mut() {
+            pilot.target = Some(destination);
+        }
+
+        // mines spread out along the route
+        for i in 0..NUM_ESCORT_MINES {
+            let t = (i as f64 + 1.0) / (NUM_ESCORT_MINES as f64 + 1.0);
+            let center = start + t * (destination - start);
+            let spread = Vec2::new(150.0, 400.0);
+            self.add_mine(center - spread..center + spread, 0);
+        }
+
+        self.mode = GameMode::Escort;
+        self.timeline = None;
+        self.escort = Some(Escort {
+            convoy: convoy_id,
+            destination,
+            nav_path: NavPath::default(),
+            outcome: None,
+        });
+        if let Some(recording) = self.recording.as_mut() {
+            recording.mode = GameMode::Escort;
+        }
+        log::info!(
+            "escort_started convoy={} distance={:.0}",
+            convoy_id.0,
+            (destination - start).length()
+        );
+    }
+
+    // Time attack on a course generated from the world. The ship starts where it
+    // is, and the clock starts right away.
+    pub fn start_race(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let course = self.course_id();
+        let start = self.entity_store.get(ctrl_id).transform.translation();
+        // the times on the leaderboard were all set with the built in spawn tables
+        let best = Leaderboard::load(&self.profile.path(LEADERBOARD_PATH))
+            .best(&course)
+            .filter(|_| self.spawn_tables.is_builtin())
+            .cloned();
+
+        // recorded ticks are relative to the race start, so the ghost's clock starts at 0
+        self.ghost = match Replay::load(&self.profile.path(course.ghost_path())) {
+            Ok(replay) if best.is_some() && replay.spawn_tables == self.spawn_tables.hash() => {
+                let mut ghost = self.spawn_ghost(replay);
+                ghost.timeline = None;
+                Some(ghost)
+            }
+            _ => None,
+        };
+
+        let mut race = Race::new(course, start, self.tick_count, best);
+        race.recording.mode = GameMode::Race;
+        race.recording.loadout = self.loadout();
+        race.recording.spawn_tables = self.spawn_tables.hash();
+        log::info!(
+            "race_started gates={} best={:?} ghost={}",
+            race.gates.len(),
+            race.best.as_ref().map(|best| best.ticks),
+            self.ghost.is_some()
+        );
+        self.race = Some(race);
+        self.mode = GameMode::Race;
+        self.timeline = None;
+        if let Some(recording) = self.recording.as_mut() {
+            recording.mode = GameMode::Race;
+        }
+    }
+
+    // The world as it was generated, to tell courses and ghosts apart
+    pub fn course_id(&self) -> CourseId {
+        CourseId {
+            seed: self.seed,
+            extent: self.spatial_db.get_max().x,
+            asteroids: self
+                .entity_store
+                .entities
+                .iter()
+                .filter(|entity| entity.object_type == GameObjectType::Asteroid)
+                .count() as u32,
+            arena: self.arena.variant,
+            arena_shape: self.arena.shape,
+        }
+    }
+
+    // Copy of the world as it is now, with its clock at 0, flying a recorded run
+    fn spawn_ghost(&self, replay: Replay) -> Box<GameWorld> {
+        let mut snapshot = self.snapshot();
+        snapshot.tick = 0;
+        let mut ghost = GameWorld::from_snapshot(&snapshot);
+        // pilot skill changes how the ai flies, and the recorded keys only mean
+        // something with the same bindings
+        ghost.settings = self.settings.clone();
+        ghost.spawn_tables = self.spawn_tables.clone();
+        ghost.actions = ActionState::new(self.actions.bindings().clone());
+        ghost.apply_loadout(&replay.loadout);
+        ghost.start_playback(replay);
+        Box::new(ghost)
+    }
+
+    // An endless run from the start races the longest one so far in the same
+    // world. The whole world is simulated alongside with the recorded inputs,
+    // so the ghost lasts exactly as long as that run did.
+    fn start_endless(&mut self) {
+        let (course, tables) = (self.course_id(), self.spawn_tables.hash());
+        self.ghost = match Replay::load(&self.profile.path(course.endless_ghost_path())) {
+            Ok(replay) if replay.mode == GameMode::Endless && replay.spawn_tables == tables => {
+                Some(self.spawn_ghost(replay))
+            }
+            _ => None,
+        };
+        // a tuned world plays out differently, and its runs don't count
+        self.run_recording = (!self.tuned).then(|| self.new_recording());
+        log::info!("endless_started ghost={}", self.ghost.is_some());
+    }
+
+    // An empty recording of a run in this world as it's set up now
+    fn new_recording(&self) -> Replay {
+        let course = self.course_id();
+        let mut recording = Replay::new(course.seed, course.extent, course.asteroids);
+        recording.mode = self.mode;
+        recording.arena = course.arena;
+        recording.arena_shape = course.arena_shape;
+        recording.loadout = self.loadout();
+        recording.spawn_tables = self.spawn_tables.hash();
+        recording
+    }
+
+    // Once the run is over, submit it to the online leaderboard, and keep it as the
+    // ghost if it outlasted the last one
+    fn update_endless_run(&mut self) {
+        let survivor_ticks = (SURVIVOR_SECS * TICKS_PER_SECOND) as u32;
+        if self.mode == GameMode::Endless && !self.is_game_over() && self.tick_count == survivor_ticks {
+            self.unlock_achievement(Achievement::Survivor);
+        }
+        if self.run_recording.is_none() || !self.is_game_over() {
+            return;
+        }
+        let mut recording = self.run_recording.take().unwrap();
+        recording.rng_check = Some((self.tick_count, self.rng_draws()));
+        // the leaderboard only ranks runs in worlds made with the built in spawn tables
+        let ranked = recording.spawn_tables == spawn_table::builtin_hash();
+        if let Some(online) = self.online.as_mut().filter(|_| ranked) {
+            online.submit(Submission {
+                seed: self.seed,
+                mode: GameMode::Endless,
+                score: self.get_score(),
+                ticks: self.tick_count,
+                name: self.platform.get_user_name(),
+                replay_hash: recording.hash(),
+                slow_mode: self.slow_mode,
+            });
+        }
+        if self.ghost.as_ref().is_some_and(|ghost| !ghost.is_game_over()) {
+            log::info!("endless_ghost_kept ticks={}", self.tick_count);
+            return;
+        }
+
+        let path = self.profile.path(self.course_id().endless_ghost_path());
+        match self.platform.store_cloud_file(&path, recording.to_text().as_bytes()) {
+            Ok(()) => log::info!("endless_ghost_saved ticks={}", self.tick_count),
+            Err(err) => log::error!("Failed to save ghost {}: {}", path.display(), err),
+        }
+    }
+
+    // Walk the player through the controls in the current world, with the
+    // timeline held off until it's over
+    pub fn start_tutorial(&mut self) {
+        self.tutorial = Some(Tutorial::new(self.tick_count));
+        self.mode = GameMode::Tutorial;
+        self.timeline = None;
+        if let Some(recording) = self.recording.as_mut() {
+            recording.mode = GameMode::Tutorial;
+        }
+        log::info!("tutorial_started tick={}", self.tick_count);
+    }
+
+    fn update_tutorial(&mut self) {
+        let tick = self.tick_count;
+        let Some(tutorial) = self.tutorial.as_mut() else {
+            return;
+        };
+        if tutorial.is_finished(tick) {
+            self.tutorial = None;
+            return;
+        }
+        if tutorial.step == TutorialStep::Done {
+            return;
+        }
+
+        let skipped = self.actions.just_pressed(Action::SkipTutorial);
+        if skipped {
+            tutorial.skip(tick);
+        }
+        if skipped || tutorial.update(&self.events, tick) {
+            log::info!("tutorial_step step={} skipped={} tick={}", tutorial.step.key(), skipped, tick);
+        }
+        if tutorial.step == TutorialStep::AvoidAsteroid {
+            self.update_tutorial_asteroid();
+        }
+
+        if self.tutorial.as_ref().is_some_and(|tutorial| tutorial.step == TutorialStep::Done) {
+            // carry on as an endless run, milestones and all
+            self.mode = GameMode::Endless;
+            self.timeline = Some(Timeline::new(tick));
+        } else if let Some(ctrl_id) = self.control_object {
+            // nothing to lose while learning
+            if let Some(air) = self.entity_store.get_mut(ctrl_id).air_suuply.as_mut() {
+                air.air = air.air.max(TUTORIAL_MIN_AIR);
+            }
+        }
+    }
+
+    fn update_hints(&mut self) {
+        let hints: Vec<Hint> = self
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::LowAir => Some(Hint::LowAir),
+                GameEvent::ShipHit { .. } => Some(Hint::Collision),
+                _ => None,
+            })
+            .collect();
+        for hint in hints {
+            self.show_hint(hint);
+        }
+        self.hints.update(self.tick_count);
+    }
+
+    // Show a tip the first time it comes up for the player, replays don't count
+    fn show_hint(&mut self, hint: Hint) {
+        if self.playback.is_some() || !self.hints.trigger(hint) {
+            return;
+        }
+        log::info!("hint_triggered hint={} tick={}", hint.key(), self.tick_count);
+        let path = self.profile.path(HINTS_FILE);
+        if let Err(err) = self.platform.store_cloud_file(&path, self.hints.to_text().as_bytes()) {
+            log::warn!("Failed to save hints {}: {}", path.display(), err);
+        }
+    }
+
+    // Send an asteroid at the ship from ahead of it, and see if it gets past
+    fn update_tutorial_asteroid(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship = self.entity_store.get(ctrl_id);
+        let (ship_pos, ship_velocity) = (ship.transform.translation(), ship.rigid.velocity);
+        let (heading, ship_radius) = (ship.transform.get_y_vector(), ship.collision.radius());
+        let Some(tutorial) = self.tutorial.as_ref() else {
+            return;
+        };
+
+        let Some(asteroid_id) = tutorial.asteroid else {
+            // keep clear of the border so the spawn range can't turn inside out
+            let margin = Vec2::new(TUTORIAL_ASTEROID_DIST, TUTORIAL_ASTEROID_DIST) * 0.5;
+            let area = self.arena.spawn_range(self.tick_count as f64);
+            let ahead = ship_pos + heading * TUTORIAL_ASTEROID_DIST;
+            let center = Vec2::new(
+                ahead.x.clamp(area.start.x + margin.x, area.end.x - margin.x),
+                ahead.y.clamp(area.start.y + margin.y, area.end.y - margin.y),
+            );
+            let spread = Vec2::new(TUTORIAL_ASTEROID_SPREAD, TUTORIAL_ASTEROID_SPREAD);
+            // tried again next tick if there's no room
+            let pos_range = center - spread..center + spread;
+            let Some(id) = self.add_asteroid(pos_range, 0.0..1.0, 0.0..0.1, false) else {
+                return;
+            };
+            let asteroid = self.entity_store.get_mut(id);
+            let dir = (ship_pos - asteroid.transform.translation()).normalize();
+            asteroid.rigid.velocity = dir * TUTORIAL_ASTEROID_SPEED;
+            log::info!("tutorial_asteroid id={} tick={}", id.0, self.tick_count);
+            if let Some(tutorial) = self.tutorial.as_mut() {
+                tutorial.asteroid = Some(id);
+                tutorial.asteroid_close = false;
+            }
+            return;
+        };
+
+        let asteroid = self.entity_store.get(asteroid_id);
+        let offset = asteroid.transform.translation() - ship_pos;
+        let gap = offset.length() - ship_radius - asteroid.collision.radius();
+        let receding = offset.dot(asteroid.rigid.velocity - ship_velocity) > 0.0;
+        let tick = self.tick_count;
+        let Some(tutorial) = self.tutorial.as_mut() else {
+            return;
+        };
+        tutorial.asteroid_close |= gap < TUTORIAL_DODGE_GAP;
+        // a near miss counts too, but a wide berth is just as good
+        if tutorial.asteroid_close && receding {
+            tutorial.advance(tick);
+            log::info!("tutorial_step step={} skipped=false tick={}", tutorial.step.key(), tick);
+        }
+    }
+
+    fn update_race(&mut self) {
+        let (Some(race), Some(ctrl_id)) = (self.race.as_mut(), self.control_object) else {
+            return;
+        };
+        if race.finish.is_none() {
+            race.recording.record(self.tick_count - race.start_tick, self.actions.inputs());
+        }
+
+        let ship_pos = self.entity_store.get(ctrl_id).transform.translation();
+        if !race.update(ship_pos, self.tick_count) {
+            return;
+        }
+        log::info!(
+            "race_gate gate={} split={} delta={:?}",
+            race.next_gate,
+            race.splits.last().copied().unwrap_or(0),
+            race.last_split_delta()
+        );
+        if race.is_complete() {
+            self.finish_race();
+        }
+    }
+
+    fn finish_race(&mut self) {
+        let Some(race) = self.race.as_mut() else {
+            return;
+        };
+        let ticks = race.splits.last().copied().unwrap_or(0);
+
+        let path = self.profile.path(LEADERBOARD_PATH);
+        let mut leaderboard = Leaderboard::load(&path);
+        // watching a replay or racing with changed physics or spawn tables doesn't set times
+        let rank = if self.playback.is_none() && !self.tuned && self.spawn_tables.is_builtin() {
+            let entry = LeaderboardEntry {
+                ticks,
+                splits: race.splits.clone(),
+                slow_mode: self.slow_mode,
+            };
+            let rank = leaderboard.insert(&race.course, entry);
+            if let Err(err) = self.platform.store_cloud_file(&path, leaderboard.to_text().as_bytes()) {
+                log::error!("Failed to save leaderboard {}: {}", path.display(), err);
+            }
+            if rank == Some(0) {
+                let ghost_path = self.profile.path(race.course.ghost_path());
+                let ghost = race.recording.to_text();
+                if let Err(err) = self.platform.store_cloud_file(&ghost_path, ghost.as_bytes()) {
+                    log::error!("Failed to save ghost {}: {}", ghost_path.display(), err);
+                }
+            }
+            rank
+        } else {
+            None
+        };
+
+        log::info!("race_finished ticks={} rank={:?}", ticks, rank);
+        race.finish = Some(RaceFinish {
+            ticks,
+            rank,
+            top: leaderboard.top(&race.course).to_vec(),
+        });
+        self.unlock_achievement(Achievement::CheckeredFlag);
+        if rank == Some(0) {
+            self.unlock_achievement(Achievement::CourseRecord);
+        }
+    }
+
+    fn update_timeline(&mut self) {
+        let Some(timeline) = self.timeline.as_mut() else {
+            return;
+        };
+        for (event, cycle) in timeline.update(self.tick_count) {
+            log::info!("milestone event={} cycle={} tick={}", event.key(), cycle, self.tick_count);
+            self.checkpoints.milestone();
+            match event {
+                MilestoneEvent::AsteroidShower => self.start_asteroid_shower(cycle),
+                MilestoneEvent::DenseFog => {}
+                MilestoneEvent::DoublePods => self.double_air_pods(),
+                MilestoneEvent::Boss => self.spawn_boss(),
+            }
+        }
+    }
+
+    fn is_milestone_active(&self, event: MilestoneEvent) -> bool {
+        self.timeline.as_ref().is_some_and(|timeline| timeline.is_active(event))
+    }
+
+    // A wave of asteroids from a strip along one edge, heading across the field
+    fn start_asteroid_shower(&mut self, cycle: u32) {
+        let Range { start: min, end: max } = self.arena.spawn_range(self.tick_count as f64);
+        let side = (0..4u32).hash_rand(self.seed, (self.tick_count, "shower_side"));
+        let (pos_range, dir) = match side {
+            0 => (min..Vec2::new(max.x, min.y + SHOWER_DEPTH), Vec2::new(0.0, 1.0)),
+            1 => (Vec2::new(min.x, max.y - SHOWER_DEPTH)..max, Vec2::new(0.0, -1.0)),
+            2 => (min..Vec2::new(min.x + SHOWER_DEPTH, max.y), Vec2::new(1.0, 0.0)),
+            _ => (Vec2::new(max.x - SHOWER_DEPTH, min.y)..max, Vec2::new(-1.0, 0.0)),
+        };
+
+        let count = SHOWER_ASTEROIDS + cycle * SHOWER_ASTEROIDS_PER_CYCLE;
+        let mut added = 0;
+        for _ in 0..count {
+            // the whole wave comes in, those that don't fit in the strip right next to it
+            let Some(id) = self.add_asteroid(pos_range.clone(), 0.0..0.0, 0.0..0.1, true) else {
+                continue;
+            };
+            let speed = (SHOWER_MIN_SPEED..SHOWER_MAX_SPEED).hash_rand(self.seed, (id.0, "shower_speed"));
+            self.entity_store.get_mut(id).rigid.velocity = speed * dir;
+            added += 1;
+        }
+        log::debug!("asteroid_shower side={} asteroids={}", side, added);
+        let center = 0.5 * (pos_range.start + pos_range.end);
+        self.events.push(GameEvent::ShowerStarted { pos: center });
+    }
+
+    // Keeps the field as full as it was at the start of the run, for as long
+    // as it goes on. Asteroids heading out through a wall far from the ship
+    // are taken away, and the count made up again with ones drifting in from
+    // an edge. The arena shrinking takes the count down with it.
+    fn update_asteroid_spawner(&mut self) {
+        if self.timeline.is_none() || self.tick_count % SPAWNER_INTERVAL_TICKS != 0 {
+            return;
+        }
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship_pos = self.entity_store.get(ctrl_id).transform.translation();
+        let tick = self.tick_count as f64;
+        let apothem = self.arena.apothem_at(tick);
+
+        // the carrier's rocks come and go with it
+        let field_asteroid = |id: EntityId, entity: &GameObject| {
+            entity.object_type == GameObjectType::Asteroid
+                && !self.boss.as_ref().is_some_and(|boss| boss.owns(id))
+        };
+        let mut leaving = Vec::new();
+        let mut count = 0;
+        for (idx, entity) in self.entity_store.entities.iter().enumerate() {
+            if !field_asteroid(EntityId(idx), entity) {
+                continue;
+            }
+            count += 1;
+            let pos = entity.transform.translation();
+            if (pos - ship_pos).length() < SPAWNER_MIN_PLAYER_DIST {
+                continue;
+            }
+            let radius = entity.collision.radius() + SPAWNER_EXIT_GAP;
+            let mut outward = false;
+            self.arena.wall_contacts(pos, radius, tick, &mut |_, normal, _| {
+                outward |= entity.rigid.velocity.dot(normal) > 0.0;
+            });
+            if outward {
+                leaving.push(EntityId(idx));
+            }
+        }
+        let density = *self.asteroid_density.get_or_insert(count as f64 / (apothem * apothem));
+
+        for id in &leaving {
+            self.despawn(*id);
+        }
+        let target = (density * apothem * apothem).round() as usize;
+        let missing = target.saturating_sub(count - leaving.len()).min(SPAWNER_MAX_ADDED);
+        let mut added = 0;
+        for _ in 0..missing {
+            added += self.spawn_border_asteroid(ship_pos) as usize;
+        }
+        if !leaving.is_empty() || added > 0 {
+            log::debug!(
+                "asteroid_spawner despawned={} spawned={} target={} tick={}",
+                leaving.len(),
+                added,
+                target,
+                self.tick_count
+            );
+        }
+    }
+
+    // An asteroid coming in from an edge away from the ship, returns false if
+    // there was no room for it
+    fn spawn_border_asteroid(&mut self, ship_pos: Vec2) -> bool {
+        let Range { start: min, end: max } = self.arena.spawn_range(self.tick_count as f64);
+        let mut side = self.asteroid_rng.next(0..4u32);
+        // the edge next to the ship would have it appear in sight, use the one across instead
+        let near_side = match side {
+            0 => ship_pos.y - min.y,
+            1 => max.y - ship_pos.y,
+            2 => ship_pos.x - min.x,
+            _ => max.x - ship_pos.x,
+        } < SPAWNER_MIN_PLAYER_DIST;
+        if near_side {
+            side ^= 1;
+        }
+        let (pos_range, dir) = match side {
+            0 => (min..Vec2::new(max.x, min.y + SPAWNER_EDGE_DEPTH), Vec2::new(0.0, 1.0)),
+            1 => (Vec2::new(min.x, max.y - SPAWNER_EDGE_DEPTH)..max, Vec2::new(0.0, -1.0)),
+            2 => (min..Vec2::new(min.x + SPAWNER_EDGE_DEPTH, max.y), Vec2::new(1.0, 0.0)),
+            _ => (Vec2::new(max.x - SPAWNER_EDGE_DEPTH, min.y)..max, Vec2::new(-1.0, 0.0)),
+        };
+
+        let Some(id) = self.add_asteroid(pos_range, 0.0..0.0, 0.0..0.1, false) else {
+            return false;
+        };
+        let speed = self.asteroid_rng.next(SPAWNER_MIN_SPEED..SPAWNER_MAX_SPEED);
+        // spread a little either side of straight in
+        let spread: f64 = self.asteroid_rng.next(-FRAC_PI_4..FRAC_PI_4);
+        let angle = dir.atan2() + spread;
+        self.entity_store.get_mut(id).rigid.velocity = speed * Vec2::from_angle(angle);
+        true
+    }
+
+    // Takes an object out of the world. The store can't shrink, so its slot is
+    // left holding a dummy for the rest of the run. Slots aren't reused, so an
+    // id can't come to mean something else, but everything that holds on to
+    // one lets go of it here rather than be left with the dummy.
+    fn despawn(&mut self, id: EntityId) {
+        let entity = self.entity_store.get_mut(id);
+        self.spatial_db.remove(id, &mut entity.spatial_db_ref);
+        *entity = GameObject::new_dummy();
+        self.discovered.retain(|&other| other != id);
+        if self.grapple.is_some_and(|grapple| grapple.anchor == id) {
+            self.release_grapple("anchor_gone");
+        }
+        self.forget_entity(id);
+
+        // whatever was attached goes with it
+        let children: Vec<EntityId> = (0..self.entity_store.entities.len())
+            .map(EntityId)
+            .filter(|&child| self.entity_store.get(child).attachment.is_some_and(|att| att.parent == id))
+            .collect();
+        for child in children {
+            self.despawn(child);
+        }
+    }
+
+    // Drop what refers to an entity that was taken out of the world
+    fn forget_entity(&mut self, id: EntityId) {
+        if self.boss.as_ref().is_some_and(|boss| boss.core == id) {
+            log::warn!("Carrier despawned, the fight is over");
+            self.boss = None;
+        }
+        if let Some(boss) = self.boss.as_mut() {
+            boss.forget(id);
+        }
+        self.debris.forget(id);
+        for entity in &mut self.entity_store.entities {
+            if let Some(faction_ship) = entity.faction_ship.as_mut() {
+                faction_ship.forget_station(id);
+            }
+            if let Some(projectile) = entity.projectile.as_mut().filter(|shot| shot.owner == Some(id)) {
+                projectile.owner = None;
+            }
+        }
+        if self.docked_at == Some(id) {
+            self.docked_at = None;
+        }
+        self.near_passes.retain(|pass| pass.asteroid != id);
+        // the mission is lost along with its convoy
+        if let Some(escort) = self.escort.as_mut().filter(|escort| escort.convoy == id) {
+            escort.outcome.get_or_insert(EscortOutcome::Destroyed);
+        }
+        if let Some(photo_mode) = self.photo_mode.as_mut() {
+            photo_mode.selected = photo_mode.selected.filter(|&selected| selected != id);
+        }
+        // another one is sent
+        if let Some(tutorial) = self.tutorial.as_mut().filter(|tutorial| tutorial.asteroid == Some(id)) {
+            tutorial.asteroid = None;
+            tutorial.asteroid_close = false;
+        }
+    }
+
+    // Hold child at offset and rotation in parent's frame from now on. It's put
+    // there right away.
+    fn attach(&mut self, child: EntityId, parent: EntityId, offset: Vec2, rotation: f64) {
+        assert!(self.entity_store.get(parent).attachment.is_none(), "Attached to a child");
+        let attachment = Attachment { parent, offset, rotation };
+        let transform = attachment.world_transform(&self.entity_store.get(parent).transform);
+        let child = self.entity_store.get_mut(child);
+        child.teleport(transform.translation());
+        child.transform.rotation = transform.rotation();
+        child.prev_transform.rotation = transform.rotation();
+        child.attachment = Some(attachment);
+    }
+
+    // Pods out there now get topped up, new ones are placed with double air
+    fn double_air_pods(&mut self) {
+        for entity in &mut self.entity_store.entities {
+            if entity.object_type != GameObjectType::AidPod {
+                continue;
+            }
+            if let Some(air) = entity.air_suuply.as_mut() {
+                air.air *= 2;
+            }
+        }
+    }
+
+    // The carrier comes in at the quarter of the field furthest from the player, facing them
+    fn spawn_boss(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship_pos = self.entity_store.get(ctrl_id).transform.translation();
+        let quarter = 0.5 * self.arena.spawn_range(self.tick_count as f64).end;
+        let pos = [
+            Vec2::new(-quarter.x, -quarter.y),
+            Vec2::new(quarter.x, -quarter.y),
+            Vec2::new(-quarter.x, quarter.y),
+            quarter,
+        ]
+        .into_iter()
+        .max_by(|a, b| (*a - ship_pos).length().total_cmp(&(*b - ship_pos).length()))
+        .unwrap();
+        let rotation = (ship_pos - pos).atan2() - 0.5 * PI;
+
+        let core_shape = self.resources.carrier_core_shape.clone();
+        let mut core = GameObject::new_boss_part(core_shape, "Carrier", Some(CORE_HEALTH));
+        core.collision = Collision::compound(boss::body_circles());
+        let core = self.insert_boss_part(core, pos, rotation);
+
+        let mut turrets = Vec::new();
+        for offset in TURRET_OFFSETS {
+            let shape = self.resources.turret_shape.clone();
+            let mut turret = GameObject::new_boss_part(shape, "Turret", Some(TURRET_HEALTH));
+            turret.turret = Some(Turret::boss(BossPhase::Turrets.attack_interval()));
+            let id = self.insert_boss_part(turret, pos, rotation);
+            self.attach(id, core, offset, 0.0);
+            turrets.push(id);
+        }
+
+        log::info!("boss_spawned pos=({:.0}, {:.0}) tick={}", pos.x, pos.y, self.tick_count);
+        self.boss = Some(Boss::new(core, turrets));
+        self.events.push(GameEvent::BossArrived { pos });
+    }
+
+    fn insert_boss_part(&mut self, mut part: GameObject, pos: Vec2, rotation: f64) -> EntityId {
+        part.transform = Transform::new(pos, rotation);
+        part.prev_transform = Transform::new(pos, rotation);
+        part.render_transform = Transform::new(pos, rotation);
+        self.insert_object(part)
+    }
+
+    // Move the carrier by script (nothing can push it) and run the attacks of the
+    // current phase. The turrets aim and throw rocks on their own, see update_turrets.
+    fn update_boss(&mut self) {
+        let Some(core_id) = self.boss.as_ref().map(|boss| boss.core) else {
+            return;
+        };
+        // it looks out across the whole field
+        let core = self.entity_store.get(core_id);
+        let core_pos = core.transform.translation();
+        let senses = Senses::all_around(f64::INFINITY, BOSS_MEMORY_TICKS);
+        let seen_player = self.perceive_player(core_pos, core.transform.get_y_vector(), &senses);
+        let (Some(boss), Some(_)) = (self.boss.as_mut(), self.control_object) else {
+            return;
+        };
+        let seen_player = boss.perception.update(seen_player, core_pos, self.tick_count, &senses);
+        if boss.phase == BossPhase::Destroyed {
+            return;
+        }
+
+        let alive = |id: EntityId| {
+            self.entity_store
+                .get(id)
+                .hull
+                .as_ref()
+                .is_some_and(|hull| hull.health > 0.0)
+        };
+        let turrets_left: Vec<EntityId> = boss.turrets.iter().copied().filter(|id| alive(*id)).collect();
+        let phase = BossPhase::for_state(turrets_left.len(), alive(boss.core));
+        if phase != boss.phase {
+            log::info!("boss_phase phase={} tick={}", phase.name(), self.tick_count);
+            boss.phase = phase;
+            boss.attack_timer = phase.attack_interval();
+            boss.charge = None;
+            match phase {
+                BossPhase::Destroyed => {
+                    self.defeat_boss();
+                    return;
+                }
+                BossPhase::Exposed => {
+                    let shape = self.resources.carrier_exposed_core_shape.clone();
+                    self.entity_store.get_mut(boss.core).shape = Some(shape);
+                }
+                BossPhase::Turrets | BossPhase::Charge => {
+                    for id in &turrets_left {
+                        if let Some(turret) = self.entity_store.get_mut(*id).turret.as_mut() {
+                            turret.reload_ticks = phase.attack_interval();
+                        }
+                    }
+                }
+            }
+        }
+
+        let core = self.entity_store.get_mut(boss.core);
+        let Some(ship_pos) = seen_player else {
+            // lost track of the player, it holds where it is and holds its fire
+            core.rigid.velocity = Vec2::ZERO;
+            core.rigid.angular_velocity = 0.0;
+            return;
+        };
+        let core_rot = core.transform.rotation();
+        let to_ship = ship_pos - core_pos;
+
+        let mut vel = match boss.charge.as_mut() {
+            Some((dir, ticks)) => {
+                *ticks -= 1;
+                BOSS_CHARGE_SPEED * *dir
+            }
+            None => {
+                // close in to the standoff distance, back off when the player gets closer
+                let dist = to_ship.length();
+                let standoff = phase.standoff();
+                if dist > standoff + 100.0 {
+                    phase.cruise_speed() * to_ship.normalize()
+                } else if dist < standoff - 100.0 {
+                    -phase.cruise_speed() * to_ship.normalize()
+                } else {
+                    Vec2::ZERO
+                }
+            }
+        };
+        if boss.charge.is_some_and(|(_, ticks)| ticks == 0) {
+            boss.charge = None;
+        }
+        // all of the carrier stays inside the arena
+        let half = (self.arena.spawn_range(self.tick_count as f64).end.x - BOUNDING_RADIUS).max(0.0);
+        let next_pos = core_pos + vel;
+        let next_pos = Vec2::new(next_pos.x.clamp(-half, half), next_pos.y.clamp(-half, half));
+        vel = next_pos - core_pos;
+
+        let turn = (to_ship.atan2() - 0.5 * PI - core_rot + PI).rem_euclid(TAU) - PI;
+        let ang_vel = turn.clamp(-BOSS_TURN_RATE, BOSS_TURN_RATE);
+
+        // the turrets follow, see update_attachments
+        let core = self.entity_store.get_mut(boss.core);
+        core.rigid.velocity = vel;
+        core.rigid.angular_velocity = ang_vel;
+
+        boss.attack_timer = boss.attack_timer.saturating_sub(1);
+        if boss.attack_timer > 0 {
+            return;
+        }
+        boss.attack_timer = phase.attack_interval();
+
+        // (from, direction) of each rock thrown
+        let mut throws = Vec::new();
+        match phase {
+            BossPhase::Charge => boss.charge = Some((to_ship.normalize(), BOSS_CHARGE_TICKS)),
+            BossPhase::Exposed => {
+                for idx in 0..BOSS_BURST_ROCKS {
+                    let dir = Vec2::from_angle(core_rot + TAU * idx as f64 / BOSS_BURST_ROCKS as f64);
+                    throws.push((core_pos + (boss::BOUNDING_RADIUS + 40.0) * dir, dir));
+                }
+            }
+            BossPhase::Turrets | BossPhase::Destroyed => {}
+        }
+        log::debug!("boss_attack phase={} rocks={}", phase.name(), throws.len());
+        for (from, dir) in throws {
+            self.throw_boss_rock(from, dir, vel);
+        }
+    }
+
+    fn throw_boss_rock(&mut self, pos: Vec2, dir: Vec2, carrier_vel: Vec2) {
+        let reused = self.boss.as_mut().and_then(|boss| boss.reuse_rock());
+        let id = match reused {
+            Some(id) => {
+                self.entity_store.get_mut(id).teleport(pos);
+                id
+            }
+            None => {
+                let mut rock = GameObject::new_asteroid_variant(&self.resources, 0);
+                rock.transform = Transform::new(pos, 0.0);
+                rock.prev_transform = Transform::new(pos, 0.0);
+                let id = self.insert_object(rock);
+                if let Some(boss) = self.boss.as_mut() {
+                    boss.add_rock(id);
+                }
+                id
+            }
+        };
+        let rock = self.entity_store.get_mut(id);
+        rock.rigid.velocity = carrier_vel + BOSS_ROCK_SPEED * dir;
+        rock.rigid.angular_velocity = 0.05;
+    }
+
+    // Turrets and the exposed core take damage from anything hitting them hard
+    // enough, and the player loses air to rocks and charges
+    fn handle_boss_hits(&mut self, hits: &[(EntityId, EntityId, f64)]) {
+        let Some(boss) = self.boss.as_ref() else {
+            return;
+        };
+
+        let mut damage = Vec::new();
+        let mut struck = false;
+        for &(id, other, speed) in hits {
+            if Some(other) == self.control_object
+                && speed >= HEAVY_HIT_DELTA_V
+                && (!boss.is_part(id) || boss.charge.is_some())
+            {
+                struck = true;
+            }
+            let shielded = id == boss.core && boss.phase != BossPhase::Exposed;
+            if boss.is_part(id) && !shielded && speed > BOSS_DAMAGE_MIN_SPEED {
+                damage.push((id, (speed - BOSS_DAMAGE_MIN_SPEED) * BOSS_DAMAGE_PER_SPEED));
+            }
+        }
+
+        let turrets = boss.turrets.clone();
+        let is_alive = |entity: &GameObject| entity.hull.as_ref().is_some_and(|hull| hull.health > 0.0);
+        for (id, dmg) in damage {
+            let was_alive = is_alive(self.entity_store.get(id));
+            self.damage_hull(id, dmg);
+            if was_alive && !is_alive(self.entity_store.get(id)) && turrets.contains(&id) {
+                log::info!("boss_turret_destroyed turret={} tick={}", id.0, self.tick_count);
+                // a slower, heavier blast than a mine
+                let blast = Animation::once(AnimationClip::Explosion, self.get_tick_secs()).with_speed(0.6);
+                let turret = self.entity_store.get_mut(id);
+                turret.shape = Some(self.resources.wrecked_turret_shape.clone());
+                turret.animation = Some(blast);
+                self.add_player_score(BOSS_TURRET_SCORE);
+            }
+        }
+
+        if let Some(ctrl_id) = self.control_object.filter(|_| struck) {
+            if let Some(air) = self.entity_store.get_mut(ctrl_id).air_suuply.as_mut() {
+                air.air = air.air.saturating_sub(BOSS_HIT_AIR);
+                log::info!("boss_hit air={} tick={}", air.air, self.tick_count);
+            }
+            if let Some(metrics) = self.metrics.as_mut() {
+                metrics.air_lost("carrier_rock", self.tick_count);
+            }
+        }
+    }
+
+    // The carrier stops where it is and stays as a wreck
+    fn defeat_boss(&mut self) {
+        let Some(boss) = self.boss.as_ref() else {
+            return;
+        };
+        for id in std::iter::once(boss.core).chain(boss.turrets.iter().copied()) {
+            let part = self.entity_store.get_mut(id);
+            part.rigid.velocity = Vec2::ZERO;
+            part.rigid.angular_velocity = 0.0;
+        }
+        let shape = self.resources.carrier_wreck_core_shape.clone();
+        self.entity_store.get_mut(boss.core).shape = Some(shape);
+
+        self.add_player_score(BOSS_SCORE);
+        if let Some(timeline) = self.timeline.as_mut() {
+            timeline.announce("CARRIER DESTROYED".to_string(), BOSS_COLOR, self.tick_count);
+        }
+        log::info!("boss_defeated score={} tick={}", BOSS_SCORE, self.tick_count);
+        self.unlock_achievement(Achievement::GiantSlayer);
+    }
+
+    fn add_player_score(&mut self, points: u64) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let player = self.entity_store.get_mut(ctrl_id);
+        if let Some(score) = player.score.as_mut() {
+            score.0 += points;
+            let pos = player.transform.translation();
+            self.spawn_floating_text(format!("+{}", points), pos, SCORE_LABEL_COLOR);
+        }
+    }
+
+    fn spawn_floating_text(&mut self, text: String, pos: Vec2, color: xilem::Color) {
+        let time = self.get_tick_secs();
+        let budget = self.settings.quality().effect_budget();
+        self.floating_texts.spawn(text, pos, color, time, budget);
+    }
+
+    // Home is where the ship is now, and the route starts at the nearest station
+    fn set_up_faction_ship(&mut self, id: EntityId) {
+        let stations: Vec<(EntityId, Vec2)> = self
+            .entity_store
+            .entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| entity.object_type == GameObjectType::Station)
+            .map(|(idx, entity)| (EntityId(idx), entity.transform.translation()))
+            .collect();
+
+        let ship = self.entity_store.get_mut(id);
+        let pos = ship.transform.translation();
+        let Some(faction_ship) = ship.faction_ship.as_mut() else {
+            return;
+        };
+        faction_ship.home = pos;
+        faction_ship.route = stations.iter().map(|(id, _)| *id).collect();
+        faction_ship.route_idx = stations
+            .iter()
+            .enumerate()
+            .min_by(|(_, (_, a)), (_, (_, b))| (*a - pos).length().total_cmp(&(*b - pos).length()))
+            .map_or(0, |(idx, _)| idx);
+    }
+
+    fn update_player_controls(&mut self) {
+        if self.actions.just_pressed(Action::FlightAssist) {
+            self.flight_assist = !self.flight_assist;
+            log::info!("flight_assist enabled={} tick={}", self.flight_assist, self.tick_count);
+        }
+        if self.actions.just_pressed(Action::Cloak) {
+            self.cloaked = !self.cloaked;
+            log::info!("cloak enabled={} tick={}", self.cloaked, self.tick_count);
+        }
+        let ctrl_id = self.get_control_object();
+        let time = self.get_tick_secs();
+        if let Some(ctrl_id) = ctrl_id {
+            let ctrl_obj = &mut self.entity_store.get_mut(ctrl_id);
+            let out_of_air = ctrl_obj.air_suuply.as_ref().map(|air| air.air).unwrap_or(0) == 0;
+            if out_of_air || self.cutscene.is_some() {
+                // ship is out of air or the camera is busy, no controls
+                Self::apply_ship_controls(ctrl_obj, ShipControls::default(), time);
+                self.cloaked &= !out_of_air;
+                return;
+            }
+            let left_down = self.actions.is_down(Action::TurnLeft);
+            let right_down = self.actions.is_down(Action::TurnRight);
+            let thrust_down = self.actions.is_down(Action::Thrust);
+            let turn = match (left_down, right_down) {
+                (true, false) => -1.0,
+                (false, true) => 1.0,
+                _ => 0.0,
+            };
+            let strafe_left = self.actions.is_down(Action::StrafeLeft);
+            let strafe_right = self.actions.is_down(Action::StrafeRight);
+            let strafe = match (strafe_left, strafe_right) {
+                (true, false) => -1.0,
+                (false, true) => 1.0,
+                _ => 0.0,
+            };
+            let mut controls = ShipControls {
+                turn,
+                thrust: thrust_down,
+                brake: self.actions.is_down(Action::Brake),
+                strafe,
+            };
+            if self.flight_assist && Self::assist_controls(ctrl_obj, &mut controls) {
+                if let Some(air) = ctrl_obj.air_suuply.as_mut() {
+                    air.air = air.air.saturating_sub(FLIGHT_ASSIST_AIR);
+                }
+            }
+            let stunned = ctrl_obj.status.has(StatusKind::Stunned);
+            if self.cloaked && stunned {
+                // an EMP knocks the cloak out along with everything else
+                self.cloaked = false;
+                log::info!("cloak enabled=false reason=stunned tick={}", self.tick_count);
+            }
+            if self.cloaked {
+                if let Some(air) = ctrl_obj.air_suuply.as_mut() {
+                    air.air = air.air.saturating_sub(CLOAK_AIR);
+                }
+            }
+            Self::apply_ship_controls(ctrl_obj, controls, time);
+            // fires when let go, overcharged if it was held long enough
+            if self.actions.just_released(Action::Repulsor) && self.repulsor.is_ready() && !stunned {
+                let overcharged = self.actions.pressed_for(Action::Repulsor, REPULSOR_HOLD);
+                self.fire_repulsor(ctrl_id, overcharged);
+            }
+            if self.actions.just_pressed(Action::Grapple) && self.grapple.is_none() && !stunned {
+                self.fire_grapple(ctrl_id);
+            }
+            if thrust_down {
+                self.events.push(GameEvent::Thrust);
+            }
+            if turn != 0.0 {
+                self.events.push(GameEvent::Turn);
+            }
+        }
+    }
+
+    // Push everything within reach of the player's ship away from it, the
+    // closer the harder. Kinematic bodies don't budge.
+    fn fire_repulsor(&mut self, ctrl_id: EntityId, overcharged: bool) {
+        let ship_pos = self.entity_store.get(ctrl_id).transform.translation();
+        let mut bodies = Vec::new();
+        let reach = Vec2::new(REPULSOR_RADIUS, REPULSOR_RADIUS);
+        self.spatial_db.probe_range(ship_pos - reach..ship_pos + reach, self.max_radius, &mut |id| {
+            let other = self.entity_store.get(id);
+            let dist = (other.transform.translation() - ship_pos).length() - other.collision.radius();
+            if id != ctrl_id && dist < REPULSOR_RADIUS && !other.rigid.is_kinematic() {
+                bodies.push(id);
+            }
+        });
+        for &id in &bodies {
+            let body = self.entity_store.get_mut(id);
+            let offset = body.transform.translation() - ship_pos;
+            if offset.length() < 1e-6 {
+                continue;
+            }
+            let dist = offset.length() - body.collision.radius();
+            let delta_v = repulsor_delta_v(dist, body.rigid.inv_mass, overcharged);
+            body.rigid.velocity += delta_v * offset.normalize();
+        }
+        self.repulsor.fire(ship_pos, self.get_tick_secs());
+        log::info!(
+            "repulsor_fired pushed={} overcharged={} tick={}",
+            bodies.len(),
+            overcharged,
+            self.tick_count
+        );
+    }
+
+    // Shoot the grappling hook out of the ship's nose, sticking it in the first
+    // asteroid in range
+    fn fire_grapple(&mut self, ctrl_id: EntityId) {
+        let ship = self.entity_store.get(ctrl_id);
+        let ship_pos = ship.transform.translation();
+        let aim = ship.transform.get_y_vector();
+        let is_asteroid = |_, other: &GameObject| other.object_type == GameObjectType::Asteroid;
+        let Some((anchor, hook)) = self.raycast(ship_pos, aim, GRAPPLE_RANGE, is_asteroid) else {
+            log::info!("grapple_missed tick={}", self.tick_count);
+            return;
+        };
+        let anchor_transform = self.entity_store.get(anchor).transform;
+        let offset = Transform::new(Vec2::ZERO, -anchor_transform.rotation())
+            .local_to_world(hook - anchor_transform.translation());
+        let grapple = Grapple::new(anchor, offset, (hook - ship_pos).length());
+        log::info!(
+            "grapple_attached anchor={} length={:.0} tick={}",
+            anchor.0,
+            grapple.length,
+            self.tick_count
+        );
+        self.grapple = Some(grapple);
+    }
+
+    fn release_grapple(&mut self, reason: &str) {
+        let Some(grapple) = self.grapple.take() else {
+            return;
+        };
+        let speed = self.control_object.map_or(0.0, |id| self.entity_store.get(id).rigid.velocity.length());
+        log::info!(
+            "grapple_released reason={} anchor={} speed={:.1} tick={}",
+            reason,
+            grapple.anchor.0,
+            speed,
+            self.tick_count
+        );
+    }
+
+    // Keep the player's ship on the end of its grappling line. Like a rope it only
+    // pulls when taut: both ends are pulled back to the line's length, and the part
+    // of their velocities stretching it is taken away, mass-weighted, so the ship's
+    // momentum along the line turns into swinging around the rock.
+    fn update_grapple(&mut self) {
+        let Some(mut grapple) = self.grapple else {
+            return;
+        };
+        let Some(ctrl_id) = self.control_object else {
+            self.grapple = None;
+            return;
+        };
+        let out_of_air = !self.entity_store.get(ctrl_id).air_suuply.as_ref().is_some_and(|air| air.air > 0);
+        if !self.actions.is_down(Action::Grapple) || out_of_air || self.cutscene.is_some() {
+            self.release_grapple("let_go");
+            return;
+        }
+        if !grapple.update() {
+            self.release_grapple("timeout");
+            return;
+        }
+
+        let (ship, anchor) = self.entity_store.get_mut_pair(ctrl_id, grapple.anchor);
+        let anchor_pos = anchor.transform.translation();
+        let hook = anchor.transform.local_to_world(grapple.offset);
+        let line = ship.transform.translation() - hook;
+        let dist = line.length();
+        if dist > grapple.length + GRAPPLE_SNAP_STRETCH {
+            self.release_grapple("snapped");
+            return;
+        }
+        self.grapple = Some(grapple);
+        if dist <= grapple.length {
+            return;
+        }
+        let normal = line / dist;
+        let inv_mass_sum = ship.rigid.inv_mass + anchor.rigid.inv_mass;
+        let correction = (dist - grapple.length) / inv_mass_sum;
+        ship.transform.apply_translation(-correction * ship.rigid.inv_mass * normal);
+        anchor.transform.apply_translation(correction * anchor.rigid.inv_mass * normal);
+
+        let hook_vel = anchor.rigid.get_world_offset_vel(&(hook - anchor_pos));
+        let stretch_vel = (ship.rigid.velocity - hook_vel).dot(normal);
+        if stretch_vel > 0.0 {
+            let impulse = stretch_vel / inv_mass_sum * normal;
+            ship.rigid.apply_impulse(-impulse, Vec2::ZERO);
+            anchor.rigid.apply_impulse(impulse, hook - anchor_pos);
+        }
+    }
+
+    // First entity keep accepts along the ray from origin in dir (a unit vector),
+    // no further than max_dist, and where the ray meets its collision circle
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_dist: f64,
+        keep: impl Fn(EntityId, &GameObject) -> bool,
+    ) -> Option<(EntityId, Vec2)> {
+        let end = origin + max_dist * dir;
+        let min = Vec2::new(origin.x.min(end.x), origin.y.min(end.y));
+        let max = Vec2::new(origin.x.max(end.x), origin.y.max(end.y));
+        let mut nearest: Option<(EntityId, f64)> = None;
+        self.spatial_db.probe_range(min..max, self.max_radius, &mut |id| {
+            let other = self.entity_store.get(id);
+            if !keep(id, other) {
+                return;
+            }
+            let to_center = other.transform.translation() - origin;
+            let along = to_center.dot(dir);
+            let radius = other.collision.radius();
+            let miss_sq = to_center.hypot2() - along * along;
+            if miss_sq > radius * radius {
+                return;
+            }
+            let dist = (along - (radius * radius - miss_sq).sqrt()).max(0.0);
+            if dist <= max_dist && !nearest.is_some_and(|(_, best)| best <= dist) {
+                nearest = Some((id, dist));
+            }
+        });
+        nearest.map(|(id, dist)| (id, origin + dist * dir))
+    }
+
+    // Rebuild the grid that AI ships plan their routes on from where things are
+    // now. How cluttered a cell is goes by the area of the rock and wrecks in it,
+    // and the cells outside the walls are blocked. Mines are left to the player to
+    // clear out of the way.
+    fn update_nav_grid(&mut self) {
+        if self.nav_grid.is_some() && self.tick_count % NAV_GRID_TICKS != 0 {
+            return;
+        }
+        let cell_size = self.spatial_db.get_node_size();
+        let cell_area = cell_size * cell_size;
+        let mut clutter = self.spatial_db.node_sums(|id| {
+            let entity = self.entity_store.get(id);
+            match entity.object_type {
+                GameObjectType::Asteroid | GameObjectType::Derelict => {
+                    PI * entity.collision.radius().powi(2) / cell_area
+                }
+                _ => 0.0,
+            }
+        });
+        let (min, dim) = (self.spatial_db.get_min(), self.spatial_db.get_dim());
+        for (idx, cell) in clutter.iter_mut().enumerate() {
+            let (x, y) = (idx as u32 % dim, idx as u32 / dim);
+            let center = min + cell_size * Vec2::new(x as f64 + 0.5, y as f64 + 0.5);
+            if self.arena.nearest_wall(center, self.tick_count as f64).0 < 0.0 {
+                *cell = BLOCKED_CLUTTER;
+            }
+        }
+        self.nav_grid = Some(NavGrid::new(min, cell_size, dim, clutter));
+    }
+
+    // Pick what each faction ship is flying towards this tick
+    fn update_faction_ships(&mut self) {
+        let hostile_player = |faction: Faction, seen: Option<Vec2>| {
+            seen.filter(|_| self.reputation.stance(faction) == Stance::Hostile)
+        };
+
+        for idx in 0..self.entity_store.entities.len() {
+            let entity = &self.entity_store.entities[idx];
+            let (Some(faction_ship), Some(_)) = (entity.faction_ship.as_ref(), entity.pilot.as_ref()) else {
+                continue;
+            };
+            let pos = entity.transform.translation();
+            let senses = Senses::faction_ship(FACTION_SIGHT);
+            let sighting = self.perceive_player(pos, entity.transform.get_y_vector(), &senses);
+            let mut perception = faction_ship.perception;
+            let seen_player = perception.update(sighting, pos, self.tick_count, &senses);
+
+            let mut route_idx = faction_ship.route_idx;
+            let mut docked = false;
+            let target = if faction_ship.cooldown > 0 {
+                faction_ship.home
+            } else {
+                match faction_ship.faction {
+                    Faction::Traders => match faction_ship.route.get(route_idx) {
+                        Some(station_id) => {
+                            let station = self.entity_store.get(*station_id);
+                            let gap = (station.transform.translation() - pos).length()
+                                - station.collision.radius();
+                            if gap < DOCK_RANGE {
+                                docked = true;
+                                route_idx = (route_idx + 1) % faction_ship.route.len();
+                            }
+                            let next_id = faction_ship.route[route_idx];
+                            self.entity_store.get(next_id).transform.translation()
+                        }
+                        None => faction_ship.home,
+                    },
+                    Faction::Pirates => {
+                        // go after loaded traders, and the player when hostile
+                        let trader = self.nearest_faction_ship(pos, Faction::Traders, true, FACTION_SIGHT);
+                        Self::nearest_in_sight(pos, [trader, hostile_player(Faction::Pirates, seen_player)])
+                            .unwrap_or(faction_ship.home)
+                    }
+                    Faction::Patrol => {
+                        let pirate = self.nearest_faction_ship(pos, Faction::Pirates, false, FACTION_SIGHT);
+                        let player = hostile_player(Faction::Patrol, seen_player);
+                        pirate
+                            .or_else(|| Self::nearest_in_sight(pos, [player]))
+                            .unwrap_or(faction_ship.home)
+                    }
+                }
+            };
+
+            let entity = &mut self.entity_store.entities[idx];
+            let mut steer_to = target;
+            if let Some(faction_ship) = entity.faction_ship.as_mut() {
+                faction_ship.route_idx = route_idx;
+                faction_ship.cooldown = faction_ship.cooldown.saturating_sub(1);
+                faction_ship.perception = perception;
+                // traders go around the thick of the rocks on their rounds
+                if let (Faction::Traders, Some(grid)) = (faction_ship.faction, self.nav_grid.as_ref()) {
+                    steer_to = faction_ship.nav_path.steer(grid, pos, target, self.tick_count);
+                }
+            }
+            if let Some(pilot) = entity.pilot.as_mut() {
+                pilot.target = Some(steer_to);
+            }
+            if docked {
+                log::debug!("trader_docked ship={} next_station={}", idx, route_idx);
+                if let Some(cargo) = entity.cargo.as_mut() {
+                    cargo.ore = cargo.ore.max(TRADER_CARGO_ORE);
+                }
+            }
+        }
+    }
+
+    // Where the player's ship is if AI at observer, facing along facing, can see it
+    // with its senses: None when there's no ship, it's out of air, cloaked, out of
+    // sight or hidden behind a big asteroid. AI picks the player as a target through
+    // this (and what it remembers, see Perception), never from the entity itself.
+    fn perceive_player(&self, observer: Vec2, facing: Vec2, senses: &Senses) -> Option<Vec2> {
+        if self.cloaked {
+            return None;
+        }
+        let ship = self.entity_store.get(self.control_object?);
+        if !ship.air_suuply.as_ref().is_some_and(|air| air.air > 0) {
+            return None;
+        }
+        let pos = ship.transform.translation();
+        let visibility = nebula_visibility(nebula::depth(&self.nebulae, pos));
+        let in_sight = senses.can_see(pos - observer, facing, visibility);
+        (in_sight && self.has_line_of_sight(observer, pos)).then_some(pos)
+    }
+
+    // No big asteroid between from and to
+    fn has_line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let dist = (to - from).length();
+        if dist < 1e-6 {
+            return true;
+        }
+        let is_blocker = |_, other: &GameObject| {
+            other.object_type == GameObjectType::Asteroid && other.collision.radius() >= SIGHT_BLOCKER_RADIUS
+        };
+        self.raycast(from, (to - from) / dist, dist, is_blocker).is_none()
+    }
+
+    // Position of the nearest active ship of a faction within range
+    fn nearest_faction_ship(&self, pos: Vec2, faction: Faction, with_ore: bool, range: f64) -> Option<Vec2> {
+        self.entity_store
+            .entities
+            .iter()
+            .filter(|entity| {
+                entity.pilot.is_some()
+                    && entity.faction_ship.as_ref().is_some_and(|ship| ship.faction == faction)
+                    && (!with_ore || entity.cargo.as_ref().is_some_and(|cargo| cargo.ore > 0))
+            })
+            .map(|entity| entity.transform.translation())
+            .filter(|other| (*other - pos).length() < range)
+            .min_by(|a, b| (*a - pos).length().total_cmp(&(*b - pos).length()))
+    }
+
+    fn nearest_in_sight<const N: usize>(pos: Vec2, candidates: [Option<Vec2>; N]) -> Option<Vec2> {
+        candidates
+            .into_iter()
+            .flatten()
+            .filter(|other| (*other - pos).length() < FACTION_SIGHT)
+            .min_by(|a, b| (*a - pos).length().total_cmp(&(*b - pos).length()))
+    }
+
+    // Ship on ship collisions hard enough to count as ramming. What happens
+    // depends on who rammed whom.
+    fn handle_rams(&mut self, rams: &[(EntityId, EntityId)]) {
+        let player = self.control_object.map(|id| id.0);
+        for &(rammer, victim) in rams {
+            let rammer_faction = self.entity_store.get(rammer).faction_ship.as_ref().map(|ship| ship.faction);
+            let victim_faction = self.entity_store.get(victim).faction_ship.as_ref().map(|ship| ship.faction);
+            let rammer_is_player = Some(rammer.0) == player;
+            let victim_is_player = Some(victim.0) == player;
+            // a shield keeps pirates from getting at the air
+            let victim_shielded = self.entity_store.get(victim).status.has(StatusKind::Shielded);
+            if !rammer_is_player && self.entity_store.get(rammer).pilot.is_none() {
+                // a disabled ship drifting into something isn't ramming
+                continue;
+            }
+            log::debug!("ram rammer={} victim={}", rammer.0, victim.0);
+
+            match (rammer_faction, victim_faction) {
+                (None, Some(Faction::Traders)) if rammer_is_player => {
+                    if self.rob_ship(victim, rammer) {
+                        self.reputation.adjust(Faction::Traders, -20, "robbed_trader");
+                        self.reputation.adjust(Faction::Patrol, -15, "robbed_trader");
+                        self.reputation.adjust(Faction::Pirates, 10, "robbed_trader");
+                    }
+                }
+                (None, Some(Faction::Pirates)) if rammer_is_player => {
+                    let pos = self.entity_store.get(victim).transform.translation();
+                    if self.disable_ship(victim) {
+                        self.reputation.adjust(Faction::Pirates, -10, "disabled_pirate");
+                        self.reputation.adjust(Faction::Patrol, 10, "disabled_pirate");
+                        if self.nearest_faction_ship(pos, Faction::Traders, false, PROTECT_RANGE).is_some() {
+                            self.reputation.adjust(Faction::Traders, 10, "protected_trader");
+                        }
+                    }
+                }
+                (None, Some(Faction::Patrol)) if rammer_is_player => {
+                    self.reputation.adjust(Faction::Patrol, -20, "rammed_patrol");
+                }
+                (Some(Faction::Pirates), None)
+                    if victim_is_player
+                        && !victim_shielded
+                        && self.reputation.stance(Faction::Pirates) == Stance::Hostile =>
+                {
+                    // leave the last tick of air, running out is up to check_air
+                    if let Some(air) = self.entity_store.get_mut(victim).air_suuply.as_mut() {
+                        let stolen = PIRATE_AIR_STEAL.min(air.air.saturating_sub(1));
+                        air.air -= stolen;
+                        log::info!("air_stolen ship={} pirate={} air={}", victim.0, rammer.0, stolen);
+                    }
+                    if let Some(metrics) = self.metrics.as_mut() {
+                        metrics.air_lost("pirate", self.tick_count);
+                    }
+                }
+                (Some(Faction::Patrol), None)
+                    if victim_is_player && self.reputation.stance(Faction::Patrol) == Stance::Hostile =>
+                {
+                    // patrol confiscates the player's ore
+                    self.rob_ship(victim, rammer);
+                }
+                (Some(Faction::Pirates), Some(Faction::Traders)) => {
+                    self.rob_ship(victim, rammer);
+                }
+                (Some(Faction::Patrol), Some(Faction::Pirates)) => {
+                    self.disable_ship(victim);
+                }
+                _ => {}
+            }
+
+            if let Some(faction_ship) = self.entity_store.get_mut(rammer).faction_ship.as_mut() {
+                faction_ship.cooldown = RAM_COOLDOWN_TICKS;
+            }
+        }
+    }
+
+    // Move the victim's ore to the robber. Returns false if there was nothing to take.
+    fn rob_ship(&mut self, victim: EntityId, robber: EntityId) -> bool {
+        let ore = self
+            .entity_store
+            .get_mut(victim)
+            .cargo
+            .as_mut()
+            .map_or(0, |cargo| std::mem::take(&mut cargo.ore));
+        if ore == 0 {
+            return false;
+        }
+
+        log::info!("ship_robbed victim={} robber={} ore={}", victim.0, robber.0, ore);
+        self.entity_store.get_mut(robber).collect_loot(Loot::Ore(ore));
+        true
+    }
+
+    // Knock out a faction ship's pilot, leaving a wreck with its cargo to salvage
+    fn disable_ship(&mut self, id: EntityId) -> bool {
+        let time = self.get_tick_secs();
+        let ship = self.entity_store.get_mut(id);
+        if ship.pilot.take().is_none() {
+            return false;
+        }
+        Self::apply_ship_controls(ship, ShipControls::default(), time);
+
+        let ore = ship.cargo.as_mut().map_or(0, |cargo| std::mem::take(&mut cargo.ore));
+        if ore > 0 {
+            ship.salvage = Some(Salvage {
+                loot: Loot::Ore(ore),
+                progress: 0,
+            });
+        }
+        log::info!(
+            "ship_disabled ship={} faction={} ore={}",
+            id.0,
+            ship.faction_ship.as_ref().map_or("-", |ship| ship.faction.key()),
+            ore
+        );
+        true
+    }
+
+    fn damage_hull(&mut self, id: EntityId, damage: f64) {
+        let entity = self.entity_store.get_mut(id);
+        let Some(hull) = entity.hull.as_mut() else {
+            return;
+        };
+        hull.health = (hull.health - damage).max(0.0);
+        log::debug!("hull_damage entity={} damage={:.1} health={:.1}", id.0, damage, hull.health);
+        entity.tint = Some(Tint::flash(xilem::Color::rgb8(0xff, 0x40, 0x40), HIT_FLASH_TICKS));
+        if damage >= 1.0 {
+            let pos = entity.transform.translation();
+            self.spawn_floating_text(format!("-{:.0}", damage), pos, DAMAGE_LABEL_COLOR);
+        }
+    }
+
+    // An armed mine goes off when a ship touches it, throwing the ship back. Hulls
+    // take damage, the player just gets knocked around and scores for clearing it.
+    // An EMP mine leaves the ship drifting with its controls knocked out instead.
+    fn detonate_mines(&mut self, hits: &[(EntityId, EntityId)]) {
+        let time = self.get_tick_secs();
+        for &(mine_id, ship_id) in hits {
+            let mine = self.entity_store.get(mine_id);
+            let emp = mine.variant == MINE_EMP;
+            let spent_shape = if emp {
+                self.resources.spent_emp_mine_shape.clone()
+            } else {
+                self.resources.spent_mine_shape.clone()
+            };
+            let mine = self.entity_store.get_mut(mine_id);
+            let Some(state) = mine.mine.as_mut() else {
+                continue;
+            };
+            if !state.armed {
+                // already went off this tick
+                continue;
+            }
+            state.armed = false;
+            mine.shape = Some(spent_shape);
+            mine.animation = Some(Animation::once(AnimationClip::Explosion, time));
+            let mine_pos = mine.transform.translation();
+
+            let ship = self.entity_store.get_mut(ship_id);
+            if emp {
+                ship.status.apply(StatusKind::Stunned, EMP_STUN_TICKS);
+                ship.status.apply(StatusKind::Slowed, EMP_STUN_TICKS + EMP_SLOW_TICKS);
+                ship.tint = Some(Tint::flash(xilem::Color::rgb8(0x40, 0xa0, 0xff), EMP_STUN_TICKS));
+                log::info!("emp_detonated mine={} ship={} tick={}", mine_id.0, ship_id.0, self.tick_count);
+                continue;
+            }
+            let dir = (ship.transform.translation() - mine_pos).normalize();
+            ship.rigid.velocity += MINE_KNOCKBACK * dir;
+            log::info!("mine_detonated mine={} ship={}", mine_id.0, ship_id.0);
+
+            if ship.hull.is_some() {
+                self.damage_hull(ship_id, MINE_DAMAGE);
+            } else if let Some(score) = ship.score.as_mut() {
+                score.0 += MINE_SCORE;
+                let pos = ship.transform.translation();
+                self.spawn_floating_text(format!("+{}", MINE_SCORE), pos, SCORE_LABEL_COLOR);
+            }
+        }
+    }
+
+    // Rubble hit hard enough breaks up into small pieces flying apart, each
+    // moving on with the velocity the rubble had
+    fn split_rubble(&mut self, mut hits: Vec<EntityId>) {
+        hits.sort_by_key(|id| id.0);
+        hits.dedup();
+        for id in hits {
+            let rubble = self.entity_store.get(id);
+            let pos = rubble.transform.translation();
+            let vel = rubble.rigid.velocity;
+            let ang_vel = rubble.rigid.angular_velocity;
+            let shape = rubble.variant % NUM_ASTEROID_SHAPES;
+            // large rubble breaks into three, medium into two
+            let pieces = if shape >= 4 { 3 } else { 2 };
+            let offset = 0.5 * rubble.collision.radius();
+            self.despawn(id);
+
+            let seq = self.get_sequence();
+            let start_angle = (0.0..TAU).hash_rand(self.seed, (seq, "rubble_angle"));
+            for piece in 0..pieces {
+                let dir = Vec2::from_angle(start_angle + TAU * piece as f64 / pieces as f64);
+                let small = (0..2).hash_rand(self.seed, (seq, piece, "rubble_shape"));
+                let variant = AsteroidMaterial::Rubble.variant(small);
+                let mut rock = GameObject::new_asteroid_variant(&self.resources, variant);
+                rock.transform = Transform::new(pos + offset * dir, 0.0);
+                rock.prev_transform = rock.transform;
+                rock.rigid.velocity = vel + RUBBLE_SPREAD_SPEED * dir;
+                rock.rigid.angular_velocity = ang_vel;
+                self.insert_object(rock);
+            }
+            log::info!("rubble_split id={} pieces={} tick={}", id.0, pieces, self.tick_count);
+        }
+    }
+
+    // A bolt is spent on whatever it hits. The player loses air, hulls take
+    // damage, except the carrier's, which only big hits get through.
+    fn handle_bolt_hits(&mut self, mut hits: Vec<(EntityId, Option<EntityId>)>) {
+        hits.sort_by_key(|(bolt, _)| bolt.0);
+        hits.dedup_by_key(|(bolt, _)| *bolt);
+        for (bolt, other) in hits {
+            self.despawn(bolt);
+            let Some(other) = other else {
+                continue;
+            };
+            if Some(other) == self.control_object {
+                if self.entity_store.get(other).status.has(StatusKind::Shielded) {
+                    continue;
+                }
+                if let Some(air) = self.entity_store.get_mut(other).air_suuply.as_mut() {
+                    air.air = air.air.saturating_sub(BOLT_HIT_AIR);
+                    log::info!("bolt_hit air={} tick={}", air.air, self.tick_count);
+                }
+                if let Some(metrics) = self.metrics.as_mut() {
+                    metrics.air_lost("turret_bolt", self.tick_count);
+                }
+            } else if !self.boss.as_ref().is_some_and(|boss| boss.owns(other)) {
+                self.damage_hull(other, BOLT_HULL_DAMAGE);
+            }
+        }
+    }
+
+    fn update_escort(&mut self) {
+        let Some(escort) = self.escort.as_mut() else {
+            return;
+        };
+        if escort.outcome.is_some() {
+            return;
+        }
+
+        let time = self.get_tick_secs();
+        let convoy = self.entity_store.get_mut(escort.convoy);
+        // the convoy finds its way around the thick of the rocks
+        if let (Some(pilot), Some(grid)) = (convoy.pilot.as_mut(), self.nav_grid.as_ref()) {
+            let pos = convoy.transform.translation();
+            pilot.target = Some(escort.nav_path.steer(grid, pos, escort.destination, self.tick_count));
+        }
+        let (health, max_health) = convoy.hull.as_ref().map_or((0.0, 1.0), |hull| (hull.health, hull.max));
+        let outcome = if health <= 0.0 {
+            EscortOutcome::Destroyed
+        } else if (convoy.transform.translation() - escort.destination).length() < ESCORT_ARRIVE_DIST {
+            EscortOutcome::Delivered
+        } else {
+            return;
+        };
+
+        // convoy stops where it is either way
+        convoy.pilot = None;
+        Self::apply_ship_controls(convoy, ShipControls::default(), time);
+        escort.outcome = Some(outcome);
+
+        let mut bonus = 0;
+        if outcome == EscortOutcome::Delivered {
+            bonus = ESCORT_DELIVERY_SCORE + (ESCORT_HULL_SCORE * health / max_health) as u64;
+            if let Some(ctrl_id) = self.control_object {
+                if let Some(score) = self.entity_store.get_mut(ctrl_id).score.as_mut() {
+                    score.0 += bonus;
+                }
+            }
+        }
+        log::info!(
+            "escort_finished outcome={:?} hull={:.0} bonus={} ticks={}",
+            outcome,
+            health,
+            bonus,
+            self.tick_count
+        );
+        if outcome == EscortOutcome::Delivered {
+            self.unlock_achievement(Achievement::SafePassage);
+        }
+    }
+
+    // Air pods within reach of the player's magnets drift in towards the ship,
+    // pulled like on a spring and damped so they don't swing past it
+    fn update_magnet(&mut self) {
+        let Some(ship) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let Some(radius) = ship.magnet_radius() else {
+            return;
+        };
+        let (ship_pos, ship_vel) = (ship.transform.translation(), ship.rigid.velocity);
+
+        let mut pods = Vec::new();
+        let reach = Vec2::new(radius, radius);
+        self.spatial_db.probe_range(ship_pos - reach..ship_pos + reach, self.max_radius, &mut |id| {
+            let other = self.entity_store.get(id);
+            if other.object_type == GameObjectType::AidPod
+                && (other.transform.translation() - ship_pos).length() < radius
+            {
+                pods.push(id);
+            }
+        });
+        for id in pods {
+            let pod = self.entity_store.get_mut(id);
+            let offset = ship_pos - pod.transform.translation();
+            let accel = MAGNET_STIFFNESS * offset - MAGNET_DAMPING * (pod.rigid.velocity - ship_vel);
+            let len = accel.length();
+            let scale = if len > MAGNET_MAX_ACCEL { MAGNET_MAX_ACCEL / len } else { 1.0 };
+            pod.rigid.velocity += scale * accel;
+        }
+    }
+
+    fn update_ai_pilots(&mut self) {
+        let seed = self.get_seed();
+        let skill = PilotSkill::for_level(self.pilot_skill);
+        let time = self.get_tick_secs();
+        for (id, entity) in self.entity_store.iter_mut_entity() {
+            let Some(pilot) = entity.pilot.as_mut() else {
+                continue;
+            };
+            if entity.air_suuply.as_ref().is_some_and(|air| air.air == 0) {
+                Self::apply_ship_controls(entity, ShipControls::default(), time);
+                continue;
+            }
+
+            // faction ships can be made before the run's loadout is applied and
+            // come back from snapshots with the default, so they get it here
+            if entity.faction_ship.is_some() {
+                pilot.set_skill(skill);
+            }
+            let ship = ShipState {
+                pos: entity.transform.translation(),
+                rotation: entity.transform.rotation(),
+                velocity: entity.rigid.velocity,
+            };
+            let controls = pilot.update(&ship, seed, id.0, self.tick_count);
+            Self::apply_ship_controls(entity, controls, time);
+        }
+    }
+
+    // Flight assist adds to what the player is doing: strafing against sideways
+    // drift (as much as it takes to stop it, up to full) and braking when over
+    // the speed limit. Returns true if it fired anything.
+    fn assist_controls(ship: &GameObject, controls: &mut ShipControls) -> bool {
+        let velocity = ship.rigid.velocity;
+        let mut fired = false;
+        if controls.strafe == 0.0 {
+            // the ship's right is its -x, see apply_ship_controls
+            let drift = -velocity.dot(ship.transform.get_x_vector());
+            if drift.abs() > FLIGHT_ASSIST_MIN_DRIFT {
+                controls.strafe = (-drift * ship.rigid.mass() / RCS_THRUST).clamp(-1.0, 1.0);
+                fired = true;
+            }
+        }
+        // only going forwards too fast, the brake can't help going backwards
+        let speed = velocity.dot(ship.transform.get_y_vector());
+        if !controls.thrust && !controls.brake && speed > FLIGHT_ASSIST_MAX_SPEED {
+            controls.brake = true;
+            fired = true;
+        }
+        fired
+    }
+
+    // time is the virtual time of the tick in seconds, for the animations
+    fn apply_ship_controls(ship: &mut GameObject, mut controls: ShipControls, time: f64) {
+        if let Some(cargo) = ship.cargo.as_ref() {
+            ship.rigid.set_load(cargo.mass());
+        }
+        if ship.status.has(StatusKind::Stunned) {
+            // nothing responds, the ship carries on as it was going
+            controls = ShipControls::default();
+        }
+        let power = if ship.status.has(StatusKind::Slowed) { SLOWED_THRUST } else { 1.0 };
+        if let Some(heat) = ship.heat.as_mut() {
+            if ship.status.has(StatusKind::Overheated) {
+                // only turning while the engines cool down
+                controls.thrust = false;
+                controls.brake = false;
+                controls.strafe = 0.0;
+            }
+            heat.update(&controls);
+            if heat.heat >= 1.0 && !ship.status.has(StatusKind::Overheated) {
+                ship.status.apply(StatusKind::Overheated, OVERHEAT_TICKS);
+                log::info!("engines_overheated");
+            }
+        }
+        ship.transform.apply_rotation(0.15 * controls.turn);
+
+        // the ship's right is the way its nose swings turning right, which is its -x
+        let mut rcs_dir = -controls.strafe * ship.transform.get_x_vector();
+        if controls.brake {
+            rcs_dir -= ship.transform.get_y_vector();
+        }
+        ship.rigid.apply_force(power * RCS_THRUST * rcs_dir);
+        if let Some(rcs) = ship.rcs.as_mut() {
+            rcs.fire(controls.brake, controls.strafe, time);
+        }
+
+        if controls.thrust {
+            let thrusters = ship
+                .cargo
+                .as_ref()
+                .map_or(0, |cargo| cargo.count(UpgradeModule::Thrusters));
+            // heavier ships and full holds pick up speed slower
+            let thrust = SHIP_THRUST + THRUSTERS_UPGRADE_THRUST * thrusters as f64;
+            ship.rigid.apply_force(power * thrust * ship.transform.get_y_vector());
+            ship.thrust_start.get_or_insert(time);
+        } else {
+            ship.thrust_start = None;
+        }
+    }
+
+    // Point kinematic bodies to where their script has them at the end of this tick
+    fn update_kinematics(&mut self) {
+        let next_tick = self.tick_count + 1;
+        for entity in &mut self.entity_store.entities {
+            let Some(kinematic) = entity.kinematic else {
+                continue;
+            };
+            if let Some(pos) = kinematic.position_at(next_tick) {
+                entity.rigid.velocity = pos - entity.transform.translation();
+            }
+            entity.rigid.angular_velocity = kinematic.angular_velocity();
+        }
+    }
+
+    // Turn every turret towards its target at its slew rate, and fire once it's
+    // on it and reloaded. Runs before update_attachments, which turns the
+    // carrier's turrets to the new aim.
+    fn update_turrets(&mut self) {
+        for idx in 0..self.entity_store.entities.len() {
+            let entity = &self.entity_store.entities[idx];
+            let (Some(turret), Some(attachment)) = (entity.turret.as_ref(), entity.attachment) else {
+                continue;
+            };
+            if entity.hull.as_ref().is_some_and(|hull| hull.health <= 0.0) {
+                // knocked out
+                continue;
+            }
+            let parent = self.entity_store.get(attachment.parent);
+            let transform = attachment.world_transform(&parent.transform);
+            let pos = transform.translation();
+            // guns point along y, like the ships
+            let desired = self
+                .turret_target(attachment.parent, pos, turret.range)
+                .map(|target| (target - pos).atan2() - FRAC_PI_2 - parent.transform.rotation());
+            let (parent_rot, carrier_vel) = (parent.transform.rotation(), parent.rigid.velocity);
+
+            let entity = &mut self.entity_store.entities[idx];
+            let turret = entity.turret.as_mut().unwrap();
+            let fire = turret.update(attachment.rotation, desired);
+            let aim = match desired {
+                Some(desired) => turret.slew(attachment.rotation, desired),
+                None => attachment.rotation,
+            };
+            let ammo = turret.ammo;
+            entity.attachment = Some(Attachment { rotation: aim, ..attachment });
+            if entity.flags.contains(EntityFlags::RENDER_ONLY) {
+                entity.transform = Transform::new(pos, parent_rot + aim);
+            }
+            if !fire {
+                continue;
+            }
+
+            let dir = Vec2::from_angle(transform.rotation() + FRAC_PI_2);
+            log::debug!("turret_fired turret={} ammo={:?} tick={}", idx, ammo, self.tick_count);
+            match ammo {
+                TurretAmmo::Rock => {
+                    let muzzle = pos + (boss::TURRET_RADIUS + 40.0) * dir;
+                    self.throw_boss_rock(muzzle, dir, carrier_vel);
+                }
+                TurretAmmo::Bolt => self.fire_bolt(attachment.parent, pos, dir, carrier_vel),
+            }
+        }
+    }
+
+    // Where a turret on parent at pos should aim, if anywhere. The carrier's go
+    // after the player, pirates' after the player once pirates are hostile, and
+    // stations' after pirates.
+    fn turret_target(&self, parent: EntityId, pos: Vec2, range: f64) -> Option<Vec2> {
+        let parent = self.entity_store.get(parent);
+        let player_pos = self.perceive_player(pos, Vec2::ZERO, &Senses::all_around(range, 0));
+        match parent.object_type {
+            GameObjectType::Boss => player_pos,
+            GameObjectType::Station => self.nearest_faction_ship(pos, Faction::Pirates, false, range),
+            _ => {
+                // a disabled ship has no one to fire its gun
+                let pirate = parent.pilot.is_some()
+                    && parent.faction_ship.as_ref().is_some_and(|ship| ship.faction == Faction::Pirates);
+                player_pos.filter(|_| pirate && self.reputation.stance(Faction::Pirates) == Stance::Hostile)
+            }
+        }
+    }
+
+    fn fire_bolt(&mut self, owner: EntityId, pos: Vec2, dir: Vec2, carrier_vel: Vec2) {
+        let mut bolt = GameObject::new_bolt(&self.resources, owner);
+        bolt.transform = Transform::new(pos, dir.atan2() - FRAC_PI_2);
+        bolt.prev_transform = bolt.transform;
+        bolt.rigid.velocity = carrier_vel + BOLT_SPEED * dir;
+        self.insert_object(bolt);
+    }
+
+    // Simulated children get the velocity that takes them to their spot on the
+    // parent as it will be at the end of this tick
+    fn update_attachments(&mut self) {
+        for idx in 0..self.entity_store.entities.len() {
+            let child = &self.entity_store.entities[idx];
+            let Some(attachment) = child.attachment else {
+                continue;
+            };
+            if child.flags.contains(EntityFlags::RENDER_ONLY) {
+                continue;
+            }
+            let parent = self.entity_store.get(attachment.parent);
+            let next = Transform::new(
+                parent.transform.translation() + parent.rigid.velocity,
+                parent.transform.rotation() + parent.rigid.angular_velocity,
+            );
+            let target = attachment.world_transform(&next);
+            let turn = (target.rotation() - child.transform.rotation() + PI).rem_euclid(TAU) - PI;
+            let velocity = target.translation() - child.transform.translation();
+            let child = &mut self.entity_store.entities[idx];
+            child.rigid.velocity = velocity;
+            child.rigid.angular_velocity = turn;
+        }
+    }
+
+    fn apply_physics(&mut self) {
+        for (id, entity) in &mut self.entity_store.iter_mut_entity() {
+            let kept_out = entity.flags.contains(EntityFlags::RENDER_ONLY);
+            if entity.object_type == GameObjectType::Dummy || kept_out {
+                // despawned or scenery, and kept out of the spatial db
+                continue;
+            }
+            let pos = entity.transform.translation();
+            entity.rigid.integrate_forces();
+            let vel = entity.rigid.velocity;
+            entity.transform.apply_translation(vel);
+            entity
+                .transform
+                .apply_rotation(entity.rigid.angular_velocity);
+            self.spatial_db.update(id, pos, &mut entity.spatial_db_ref);
+        }
+        let dampening = self.tuning.dampening;
+        let max_ship_speed = self.tuning.max_ship_speed;
+        for entity in &mut self.entity_store.entities {
+            if entity.flags.contains(EntityFlags::RENDER_ONLY) {
+                continue;
+            }
+            let rigid = &mut entity.rigid;
+            rigid.velocity *= 1.0 - (dampening * rigid.dampening).min(1.0);
+            rigid.angular_velocity *= 1.0 - (dampening * rigid.angular_dampening).min(1.0);
+
+            if entity.object_type == GameObjectType::Ship {
+                let vel = entity.rigid.velocity.length();
+                if vel > max_ship_speed {
+                    entity.rigid.velocity *= max_ship_speed / vel;
+                }
+            }
+        }
+    }
+
+    fn detect_collisions(&mut self, contacts: &mut Vec<Contact>) {
+        let max_radius = self.max_radius;
+        let margin = self.tuning.collision_margin;
+        let pickup_reach = self.tuning.pickup_reach;
+
+        self.get_spatial_db()
+            .find_neighbors(max_radius, &mut |id1, id2| {
+                let obj1 = &self.entity_store.entities[id1.0];
+                let obj2 = &self.entity_store.entities[id2.0];
+                if obj1.rigid.is_kinematic() && obj2.rigid.is_kinematic() {
+                    // neither can be moved (carrier parts overlapping, a barrier sweeping through a station)
+                    return;
+                }
+                if obj1.object_type == GameObjectType::Debris && obj2.object_type == GameObjectType::Debris {
+                    // debris drifts through itself
+                    return;
+                }
+                let fired_by =
+                    |bolt: &GameObject, other| bolt.projectile.is_some_and(|shot| shot.owner == Some(other));
+                if fired_by(obj1, id2) || fired_by(obj2, id1) {
+                    // bolts fly out through whatever fired them
+                    return;
+                }
+
+                // ships pick up pods from a little further away in slow mode
+                let types = (obj1.object_type, obj2.object_type);
+                let reach = match types {
+                    (GameObjectType::Ship, GameObjectType::AidPod)
+                    | (GameObjectType::AidPod, GameObjectType::Ship) => pickup_reach,
+                    _ => 0.0,
+                };
+
+                // the broadphase went by bounding radius, now check circle by circle
+                for (pos1, rad1) in obj1.collision.world_circles(&obj1.transform) {
+                    for (pos2, rad2) in obj2.collision.world_circles(&obj2.transform) {
+                        let (rad1, rad2) = ((rad1 - margin).max(0.0), (rad2 - margin).max(0.0));
+                        let dist = (pos1 - pos2).length();
+                        let min_dist = rad1 + rad2 + reach;
+                        if dist < min_dist {
+                            // collision
+                            let normal = (pos2 - pos1).normalize();
+                            let c1 = pos1 + normal * rad1;
+                            let c2 = pos2 - normal * rad2;
+                            contacts.push(Contact {
+                                id1: Some(id1),
+                                id2: Some(id2),
+                                pos: 0.5 * (c1 + c2),
+                                normal1: normal,
+                                depth: min_dist - dist,
+                            });
+                        }
+                    }
+                }
+            });
+
+        // the border can turn and move, so check everything near it against the arena walls
+        let tick = self.tick_count as f64;
+        // (entity, point, normal, speed into the wall) for things hitting it hard enough to spark
+        let mut border_hits: Vec<(usize, Vec2, Vec2, f64)> = Vec::new();
+        for (idx, obj) in self.entity_store.entities.iter().enumerate() {
+            if obj.rigid.is_kinematic() || obj.flags.contains(EntityFlags::RENDER_ONLY) {
+                // scripted bodies keep themselves inside, scenery goes where it likes
+                continue;
+            }
+            let mut hardest: Option<(usize, Vec2, Vec2, f64)> = None;
+            for (pos, rad) in obj.collision.world_circles(&obj.transform) {
+                let rad = (rad - margin).max(0.0);
+                self.arena.wall_contacts(pos, rad, tick, &mut |point, normal, depth| {
+                    // out of bounds
+                    contacts.push(Contact {
+                        id1: Some(EntityId(idx)),
+                        id2: None,
+                        pos: point,
+                        normal1: normal,
+                        depth,
+                    });
+                    let speed = obj.rigid.velocity.dot(normal);
+                    if speed > BORDER_HIT_SPEED && !hardest.is_some_and(|hit| hit.3 >= speed) {
+                        hardest = Some((idx, point, normal, speed));
+                    }
+                });
+            }
+            border_hits.extend(hardest);
+        }
+
+        let time = self.get_tick_secs();
+        let budget = self.settings.quality().effect_budget();
+        for (idx, point, normal, speed) in border_hits {
+            let key = (self.tick_count, idx, "border_sparks");
+            self.border_fx.hit(point, normal, speed, time, (self.seed, key), budget);
+        }
+    }
+
+    fn resolve_collisions(&mut self, contacts: &mut Vec<Contact>) {
+        // stands in for the border, turning with the arena so the walls push things along
+        let mut dummy_obj = GameObject::new_dummy();
+        dummy_obj.rigid.angular_velocity = self.arena.angular_velocity();
+
+        //
+        let mut relocate_air = None;
+        let mut ship_loc = None;
+
+        let ctrl_id = self.control_object;
+        for entity in &mut self.entity_store.entities {
+            entity.rigid.contact_impulse = Vec2::ZERO;
+        }
+
+        // (rammer, victim) pairs
+        let mut rams: Vec<(EntityId, EntityId)> = Vec::new();
+        // (entity, delta v) for entities with a hull
+        let mut hull_hits: Vec<(EntityId, f64)> = Vec::new();
+        // (mine, ship) for armed mines touched by a ship
+        let mut mine_hits: Vec<(EntityId, EntityId)> = Vec::new();
+        // (carrier part or rock, other entity, impact speed)
+        let mut boss_hits: Vec<(EntityId, EntityId, f64)> = Vec::new();
+        // (entity, impact speed) for collisions hard enough to be heard
+        let mut impacts: Vec<(EntityId, f64)> = Vec::new();
+        // rubble hit hard enough to break up
+        let mut rubble_hits: Vec<EntityId> = Vec::new();
+        // (bolt, what it hit, None for the border)
+        let mut bolt_hits: Vec<(EntityId, Option<EntityId>)> = Vec::new();
+
+        let TuningParams {
+            restitution: restitution_scale,
+            friction: friction_coeff,
+            solver_iterations,
+            ..
+        } = self.tuning;
+        for i in 0..solver_iterations {
+            for contact in contacts.iter() {
+                let id1 = contact.id1.unwrap();
+
+                let (obj1, obj2) = if let Some(id2) = contact.id2 {
+                    self.entity_store.get_mut_pair(id1, id2)
+                } else {
+                    (self.entity_store.get_mut(id1), &mut dummy_obj)
+                };
+                // debris bounces off without anything counting it as a hit
+                let cosmetic = obj1.object_type == GameObjectType::Debris
+                    || obj2.object_type == GameObjectType::Debris;
+
+                if i == 0 && !cosmetic {
+                    if obj1.projectile.is_some() {
+                        bolt_hits.push((id1, contact.id2));
+                    }
+                    if let (Some(id2), Some(_)) = (contact.id2, obj2.projectile) {
+                        bolt_hits.push((id2, Some(id1)));
+                    }
+                }
+                if i == 0 {
+                    if let Some(id2) = contact.id2 {
+                        let armed = |obj: &GameObject| obj.mine.as_ref().is_some_and(|mine| mine.armed);
+                        if armed(obj1) && obj2.object_type == GameObjectType::Ship {
+                            mine_hits.push((id1, id2));
+                        } else if armed(obj2) && obj1.object_type == GameObjectType::Ship {
+                            mine_hits.push((id2, id1));
+                        }
+                    }
+                }
+
+                if (obj1.object_type == GameObjectType::AidPod
+                    && obj2.object_type == GameObjectType::Ship)
+                    || (obj2.object_type == GameObjectType::AidPod
+                        && obj1.object_type == GameObjectType::Ship)
+                {
+                    // air collection
+                    if i == 0 {
+                        let (Some(air1), Some(air2)) =
+                            (obj1.air_suuply.as_mut(), obj2.air_suuply.as_mut())
+                        else {
+                            continue;
+                        };
+                        if relocate_air.is_some() {
+                            // possible to have same collision twice, so make sure to only do this once
+                            continue;
+                        }
+                        if obj1.object_type == GameObjectType::Ship {
+                            let shield_ticks = PodTier::from_variant(obj2.variant).shield_ticks();
+                            if shield_ticks > 0 {
+                                obj1.status.apply(StatusKind::Shielded, shield_ticks);
+                            }
+                            air1.air += air2.air;
+                            if let Some(score) = obj1.score.as_mut() {
+                                score.0 += air2.air + 1000;
+                            }
+
+                            // save some data for finding next air pod location
+                            relocate_air = contact.id2;
+                            ship_loc = Some(obj1.transform.translation());
+                            log::info!(
+                                "air_collected ship={} pod={} air={} total={}",
+                                id1.0,
+                                contact.id2.map_or(0, |id| id.0),
+                                air2.air,
+                                air1.air
+                            );
+                        } else {
+                            let shield_ticks = PodTier::from_variant(obj1.variant).shield_ticks();
+                            if shield_ticks > 0 {
+                                obj2.status.apply(StatusKind::Shielded, shield_ticks);
+                            }
+                            air2.air += air1.air;
+                            if let Some(score) = obj2.score.as_mut() {
+                                score.0 += air1.air + 1000;
+                            }
+
+                            // save some data for finding next air pod location
+                            relocate_air = contact.id1;
+                            ship_loc = Some(obj2.transform.translation());
+                            log::info!(
+                                "air_collected ship={} pod={} air={} total={}",
+                                contact.id2.map_or(0, |id| id.0),
+                                id1.0,
+                                air1.air,
+                                air2.air
+                            );
+                        }
+                    }
+                    continue;
+                }
+
+                // get relative velocity of contact points on obj1 and obj2
+                let offset1 = contact.pos - obj1.transform.translation();
+                let offset2 = contact.pos - obj2.transform.translation();
+                let v1 = obj1.rigid.get_world_offset_vel(&offset1);
+                let v2: Vec2 = obj2.rigid.get_world_offset_vel(&offset2);
+                let delta_vel = v2 - v1;
+                let contact_vel = delta_vel.dot(contact.normal1);
+                let tangent_vel = delta_vel - contact_vel * contact.normal1;
+
+                let inv_mass1 = obj1.rigid.inv_mass;
+                let inv_mass2 = obj2.rigid.inv_mass;
+                let inv_inertia1 = obj1.rigid.inv_inertia;
+                let inv_inertia2 = obj2.rigid.inv_inertia;
+
+                let cross1 = offset1.x * contact.normal1.y - offset1.y * contact.normal1.x;
+                let cross2 = -offset2.x * contact.normal1.y + offset2.y * contact.normal1.x;
+                let inv_mass_inertia =
+                    inv_mass1 + inv_mass2 + cross1 * cross1 * inv_inertia1 + cross2 * cross2 * inv_inertia2;
+
+                if contact_vel >= 0.0 {
+                    // moving apart...
+                    continue;
+                }
+
+                if i == 0 && tangent_vel.length_squared() > 1e-4 {
+                    // apply a frictional force to asteroids. Since everything is a circle, this is the only
+                    // way we get angular velocity. Ship and air pod objects are not affected.
+
+                    let friction = friction_coeff * obj1.rigid.friction.min(obj2.rigid.friction);
+                    let tangent_impulse = friction * tangent_vel / inv_mass_inertia;
+
+                    if obj1.object_type == GameObjectType::Asteroid {
+                        obj1.rigid.apply_impulse(tangent_impulse, offset1);
+                    }
+                    if obj2.object_type == GameObjectType::Asteroid {
+                        obj2.rigid.apply_impulse(-tangent_impulse, offset2);
+                    }
+                }
+
+                // Restitution is min of restitutions.
+                let restitution =
+                    restitution_scale * obj1.rigid.restitution.min(obj2.rigid.restitution);
+
+                let mag = (1.0 + restitution) * contact_vel / inv_mass_inertia;
+
+                let impulse = contact.normal1 * mag;
+                if i == 0 && !cosmetic {
+                    if obj1.hull.is_some() {
+                        hull_hits.push((id1, impulse.length() * inv_mass1));
+                    }
+                    if let (Some(id2), Some(_)) = (contact.id2, obj2.hull.as_ref()) {
+                        hull_hits.push((id2, impulse.length() * inv_mass2));
+                    }
+                }
+                if let (0, false, Some(boss), Some(id2)) = (i, cosmetic, self.boss.as_ref(), contact.id2) {
+                    // the carrier doesn't hurt itself with its own rocks
+                    if boss.owns(id1) && !boss.owns(id2) {
+                        boss_hits.push((id1, id2, -contact_vel));
+                    } else if boss.owns(id2) && !boss.owns(id1) {
+                        boss_hits.push((id2, id1, -contact_vel));
+                    }
+                }
+                if i == 0
+                    && obj1.object_type == GameObjectType::Ship
+                    && obj2.object_type == GameObjectType::Ship
+                    && impulse.length() * obj1.rigid.inv_mass >= RAM_DELTA_V
+                {
+                    // whoever was moving into the other faster did the ramming
+                    let id2 = contact.id2.unwrap();
+                    let approach1 = obj1.rigid.velocity.dot(contact.normal1);
+                    let approach2 = -obj2.rigid.velocity.dot(contact.normal1);
+                    let ram = if approach1 >= approach2 { (id1, id2) } else { (id2, id1) };
+                    if !rams.iter().any(|(a, b)| a.0 == ram.0 .0 && b.0 == ram.1 .0) {
+                        rams.push(ram);
+                    }
+                }
+                if i == 0 && !cosmetic && -contact_vel >= IMPACT_SOUND_SPEED {
+                    impacts.push((id1, -contact_vel));
+                }
+                if i == 0 && !cosmetic && -contact_vel >= RUBBLE_SPLIT_SPEED {
+                    if obj1.is_breakable_rubble() {
+                        rubble_hits.push(id1);
+                    }
+                    if let (Some(id2), true) = (contact.id2, obj2.is_breakable_rubble()) {
+                        rubble_hits.push(id2);
+                    }
+                }
+                if let (0, false, Some(ctrl_id)) = (i, cosmetic, ctrl_id) {
+                    if id1 == ctrl_id {
+                        self.events.push(GameEvent::ShipHit { other: contact.id2 });
+                    } else if contact.id2 == Some(ctrl_id) {
+                        self.events.push(GameEvent::ShipHit { other: Some(id1) });
+                    }
+                }
+                if i == 0 {
+                    log::trace!(
+                        "contact_impulse id1={} id2={:?} magnitude={:.1}",
+                        id1.0,
+                        contact.id2.map(|id| id.0),
+                        impulse.length()
+                    );
+                }
+                let apply = if cosmetic { Rigid::apply_impulse } else { Rigid::apply_contact_impulse };
+                apply(&mut obj1.rigid, impulse, offset1);
+                if obj2.object_type != GameObjectType::Dummy {
+                    apply(&mut obj2.rigid, -impulse, offset2);
+                }
+            }
+        }
+
+        if let Some(ctrl_id) = ctrl_id {
+            let ctrl_impulse = self.entity_store.get(ctrl_id).rigid.contact_impulse;
+            if ctrl_impulse.length_squared() > 0.0 {
+                self.interrupt_salvage();
+            }
+            self.update_g_meter(ctrl_id);
+            self.apply_hit_feedback(ctrl_id, ctrl_impulse);
+        }
+        self.handle_rams(&rams);
+        impacts.sort_by(|(_, speed1), (_, speed2)| speed2.total_cmp(speed1));
+        for (id, speed) in impacts.into_iter().take(MAX_IMPACT_SOUNDS) {
+            self.play_sound_at(Cue::Impact, id, speed / IMPACT_SOUND_FULL_SPEED);
+        }
+        // compound bodies can touch in several places at once, only the hardest counts
+        hull_hits.sort_by(|(id1, dv1), (id2, dv2)| id1.0.cmp(&id2.0).then(dv2.total_cmp(dv1)));
+        hull_hits.dedup_by(|(id1, _), (id2, _)| id1 == id2);
+        boss_hits.sort_by(|(id1, other1, speed1), (id2, other2, speed2)| {
+            (id1.0, other1.0).cmp(&(id2.0, other2.0)).then(speed2.total_cmp(speed1))
+        });
+        boss_hits.dedup_by(|(id1, other1, _), (id2, other2, _)| id1 == id2 && other1 == other2);
+        for (id, delta_v) in hull_hits {
+            if delta_v > HULL_DAMAGE_MIN_DELTA_V {
+                self.damage_hull(id, (delta_v - HULL_DAMAGE_MIN_DELTA_V) * HULL_DAMAGE_PER_DELTA_V);
+            }
+        }
+        self.detonate_mines(&mine_hits);
+        self.handle_boss_hits(&boss_hits);
+
+        // one more pass to apply anti-penetration force
+        for contact in contacts.iter() {
+            let id1 = contact.id1.unwrap();
+
+            let (obj1, obj2) = if let Some(id2) = contact.id2 {
+                self.entity_store.get_mut_pair(id1, id2)
+            } else {
+                (self.entity_store.get_mut(id1), &mut dummy_obj)
+            };
+
+            if (obj1.object_type == GameObjectType::AidPod
+                && obj2.object_type == GameObjectType::Ship)
+                || (obj2.object_type == GameObjectType::AidPod
+                    && obj1.object_type == GameObjectType::Ship)
+            {
+                continue;
+            }
+
+            // apply position correction, moving in proportion to mass
+            let percent = 0.5;
+            let inv_mass1 = obj1.rigid.inv_mass;
+            let inv_mass2 = obj2.rigid.inv_mass;
+            let correction =
+                contact.normal1 * percent * contact.depth.max(0.0) / (inv_mass1 + inv_mass2);
+            obj1.transform.apply_translation(-correction * inv_mass1);
+            obj2.transform.apply_translation(correction * inv_mass2);
+        }
+        // after the last pass over the contacts, which still refer to the rubble and bolts
+        self.split_rubble(rubble_hits);
+        self.handle_bolt_hits(bolt_hits);
+
+        // slip this in here but really this is nothing to do with resolving collisions,
+        // this is responding to special collision between ship and air pod
+        if let Some(air_id) = relocate_air {
+            self.events.push(GameEvent::AirCollected { pod: air_id });
+            if let Some(metrics) = self.metrics.as_mut() {
+                metrics.pod_collected();
+            }
+            if let Some(audio) = self.audio.as_ref() {
+                audio.play(Cue::Pickup);
+            }
+            let pod = self.entity_store.get(air_id);
+            let pod_pos = pod.transform.translation();
+            let pod_air = pod.air_suuply.as_ref().map_or(0, |air| air.air);
+            let label = format!("Air +{}s\n+{}", pod_air / TICKS_PER_SECOND, pod_air + 1000);
+            self.spawn_floating_text(label, pod_pos, AIR_LABEL_COLOR);
+
+            let double_pods = self.is_milestone_active(MilestoneEvent::DoublePods);
+            let area = self.arena.spawn_range(self.tick_count as f64);
+            let tier = self.spawn_tables.air_pod.sample(&mut self.loot_rng);
+            let air = self.entity_store.get_mut(air_id);
+            air.pick_position(&mut self.placement_rng, area.clone());
+            air.set_pod_tier(tier);
+            self.max_radius = self.max_radius.max(air.collision.radius());
+            // not inside a rock or right on top of the ship
+            let (wanted, radius) = (air.transform.translation(), air.collision.radius());
+            let inset = Vec2::new(radius, radius);
+            let area = area.start + inset..area.end - inset;
+            if let Some(pos) = self.find_clear_spot(wanted, radius, area, Some(air_id)) {
+                self.entity_store.get_mut(air_id).teleport(pos);
+            }
+            let air = self.entity_store.get_mut(air_id);
+
+            // use distance of pod from ship and max speed ship can travel to determine air supply
+            let dist = (air.transform.translation() - ship_loc.unwrap()).length();
+            let time = dist / self.tuning.max_ship_speed; // speed is measured in units/tick (TODO: convert to time)
+            let mult = if double_pods { 8.0 } else { 4.0 };
+            air.air_suuply = Some(AirSupply {
+                air: (mult * tier.air_factor() * time) as u64,
+            });
+            log::info!("air_pod_moved pod={} tier={} tick={}", air_id.0, tier.key(), self.tick_count);
+        }
+    }
+
+    fn update_g_meter(&mut self, ctrl_id: EntityId) {
+        let rigid = &self.entity_store.get(ctrl_id).rigid;
+        let g = rigid.contact_impulse.length() * rigid.inv_mass / DELTA_V_PER_G;
+        self.g_meter = (self.g_meter * G_METER_DECAY).max(g);
+    }
+
+    fn apply_hit_feedback(&mut self, ctrl_id: EntityId, impulse: Vec2) {
+        let ctrl_obj = self.entity_store.get_mut(ctrl_id);
+        let delta_v = impulse.length() * ctrl_obj.rigid.inv_mass;
+        if delta_v < HEAVY_HIT_DELTA_V {
+            return;
+        }
+
+        log::debug!(
+            "heavy_hit entity={} impulse={:.1} delta_v={:.2}",
+            ctrl_id.0,
+            impulse.length(),
+            delta_v
+        );
+
+        // impulse pushes the ship away from the impact
+        let hit_dir = -impulse.normalize();
+        ctrl_obj.tint = Some(Tint::flash(xilem::Color::rgb8(0xff, 0xff, 0xff), HIT_FLASH_TICKS));
+        ctrl_obj.status.apply(StatusKind::Leaking, LEAK_TICKS);
+
+        self.camera
+            .kick(-hit_dir * (10.0 * delta_v).min(MAX_CAMERA_KICK));
+        self.hit_indicator = Some(HitIndicator {
+            dir: hit_dir,
+            start_time: self.get_virtual_time_secs(),
+            strength: (delta_v / (2.0 * HEAVY_HIT_DELTA_V)).min(1.0),
+        });
+    }
+
+    fn interrupt_salvage(&mut self) {
+        for entity in &mut self.entity_store.entities {
+            if let Some(salvage) = entity.salvage.as_mut() {
+                if salvage.progress > 0 {
+                    log::debug!("salvage_interrupted progress={}", salvage.progress);
+                    salvage.progress = 0;
+                }
+            }
+        }
+    }
+
+    // Salvage progresses while the ship holds position next to a derelict with loot
+    fn update_salvage(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship = self.entity_store.get(ctrl_id);
+        if ship.air_suuply.as_ref().map_or(0, |air| air.air) == 0 {
+            return;
+        }
+        let ship_pos = ship.transform.translation();
+        let ship_vel = ship.rigid.velocity;
+        let ship_rad = ship.collision.radius();
+
+        // only one derelict is salvaged at a time, progress on the others is lost
+        let target = self.entity_store.entities.iter().position(|entity| {
+            let gap = (entity.transform.translation() - ship_pos).length()
+                - entity.collision.radius()
+                - ship_rad;
+            let rel_speed = (entity.rigid.velocity - ship_vel).length();
+            entity.salvage.is_some() && gap < SALVAGE_RANGE && rel_speed < SALVAGE_MAX_REL_SPEED
+        });
+
+        let mut loot = None;
+        for (id, entity) in self.entity_store.iter_mut_entity() {
+            let Some(salvage) = entity.salvage.as_mut() else {
+                continue;
+            };
+            if Some(id.0) != target {
+                salvage.progress = 0;
+                continue;
+            }
+
+            salvage.progress += 1;
+            if salvage.progress >= SALVAGE_TICKS {
+                log::info!("salvage_complete derelict={} loot={:?}", id.0, salvage.loot);
+                loot = Some(salvage.loot);
+                entity.salvage = None;
+            }
+        }
+
+        if let Some(loot) = loot {
+            self.entity_store.get_mut(ctrl_id).collect_loot(loot);
+        }
+    }
+
+    fn update_trail(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship = self.entity_store.get(ctrl_id);
+
+        // don't draw a line across the map after a teleport
+        if ship.transform.skip_interp {
+            self.trail.clear();
+        }
+        if self.tick_count % TRAIL_SAMPLE_TICKS != 0 {
+            return;
+        }
+
+        let max_len =
+            (self.settings.trail_secs * TICKS_PER_SECOND as f64 / TRAIL_SAMPLE_TICKS as f64) as usize;
+        self.trail.push(ship.transform.translation(), max_len);
+    }
+
+    // Stations and derelicts come onto the star map once the ship has been near them
+    fn update_discovery(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship_pos = self.entity_store.get(ctrl_id).transform.translation();
+        for (idx, entity) in self.entity_store.entities.iter().enumerate() {
+            let poi = matches!(entity.object_type, GameObjectType::Station | GameObjectType::Derelict);
+            let id = EntityId(idx);
+            if !poi
+                || self.discovered.contains(&id)
+                || (entity.transform.translation() - ship_pos).length() > DISCOVER_RADIUS
+            {
+                continue;
+            }
+            log::info!("discovered type={:?} id={} tick={}", entity.object_type, idx, self.tick_count);
+            self.discovered.push(id);
+        }
+    }
+
+    fn update_radar(&mut self) {
+        let center = self
+            .control_object
+            .map_or(Vec2::ZERO, |id| self.entity_store.get(id).transform.translation());
+        // a nebula jams it down to a short range
+        let range = if nebula::depth(&self.nebulae, center) > NEBULA_JAM_DEPTH {
+            NEBULA_SCAN_RADIUS
+        } else {
+            MINI_MAP_WORLD_RADIUS
+        };
+        self.radar.update(center, range, &self.entity_store, self.control_object, self.tick_count);
+    }
+
+    fn update_tints(&mut self) {
+        for entity in &mut self.entity_store.entities {
+            if let Some(ticks) = entity.tint.as_mut().and_then(|tint| tint.ticks.as_mut()) {
+                *ticks = ticks.saturating_sub(1);
+                if *ticks == 0 {
+                    entity.tint = None;
+                }
+            }
+        }
+    }
+
+    // Take away one-shot animations that have played out and run their finish callbacks
+    fn update_animations(&mut self) {
+        let time = self.get_tick_secs();
+        let mut finished = Vec::new();
+        for (id, entity) in self.entity_store.iter_mut_entity() {
+            if entity.animation.as_ref().is_some_and(|animation| animation.is_finished(time)) {
+                let animation = entity.animation.take().unwrap();
+                finished.push((id, animation.on_finish));
+            }
+        }
+        for (id, on_finish) in finished {
+            if let Some(on_finish) = on_finish {
+                on_finish(self, id);
+            }
+        }
+    }
+
+    fn check_air(&mut self) {
+        let ctrl_id = self.control_object;
+        // running out with a life to spare only loses the life (see update_lives)
+        let last_life = self.lives.left() == 0;
+        for (id, obj) in self.entity_store.iter_mut_entity() {
+            // each recycler saves one in five ticks of air
+            let recyclers = obj
+                .cargo
+                .as_ref()
+                .map_or(0, |cargo| cargo.count(UpgradeModule::AirRecycler).min(4));
+            if (self.tick_count % 5) < recyclers as u32 {
+                continue;
+            }
+
+            if let Some(air) = obj.air_suuply.as_mut() {
+                let had_air = air.air;
+                let leak = LEAK_AIR_PER_TICK * obj.status.stacks(StatusKind::Leaking) as u64;
+                air.air = air.air.saturating_sub(1 + leak);
+
+                let is_player = Some(id) == ctrl_id;
+                if obj.object_type == GameObjectType::AidPod
+                    && air.air < POD_BLINK_SECS * TICKS_PER_SECOND
+                    && obj.tint.is_none()
+                {
+                    obj.tint = Some(Tint::blink(POD_BLINK_RATE));
+                }
+                let low_air = LOW_AIR_SECS * TICKS_PER_SECOND;
+                if is_player && had_air >= low_air && air.air < low_air {
+                    self.events.push(GameEvent::LowAir);
+                }
+
+                if had_air > 0 && air.air == 0 && is_player && last_life {
+                    log::info!(
+                        "game_over ship={} score={} ticks={}",
+                        id.0,
+                        obj.score.map_or(0, |score| score.0),
+                        self.tick_count
+                    );
+                }
+            }
+        }
+    }
+    // The player's ship ran out of air with a life to spare: it costs the life
+    // and part of the score, and after a countdown the ship comes back in a
+    // clear spot near where it drifted to, with some air and a shield
+    fn update_lives(&mut self) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship = self.entity_store.get_mut(ctrl_id);
+        let out_of_air = ship.air_suuply.as_ref().is_some_and(|air| air.air == 0);
+        if out_of_air && !self.lives.is_respawning() && self.lives.lose_life() {
+            if let Some(score) = ship.score.as_mut() {
+                score.0 = score_after_life_lost(score.0);
+            }
+            log::info!(
+                "life_lost left={} score={} tick={}",
+                self.lives.left(),
+                ship.score.map_or(0, |score| score.0),
+                self.tick_count
+            );
+            self.combo.reset();
+            return;
+        }
+        if !self.lives.update() {
+            return;
+        }
+
+        let pos = ship.transform.translation();
+        let area = self.arena.spawn_range(self.tick_count as f64);
+        let inset = Vec2::new(RESPAWN_CLEARANCE, RESPAWN_CLEARANCE);
+        let pos = self
+            .find_clear_spot(pos, RESPAWN_CLEARANCE, area.start + inset..area.end - inset, Some(ctrl_id))
+            .unwrap_or(pos);
+        let ship = self.entity_store.get_mut(ctrl_id);
+        ship.teleport(pos);
+        ship.rigid.velocity = Vec2::ZERO;
+        ship.rigid.angular_velocity = 0.0;
+        ship.status = StatusEffects::default();
+        let shield_ticks = (RESPAWN_SHIELD_TICKS as f64 * self.tuning.reaction_scale) as u32;
+        ship.status.apply(StatusKind::Shielded, shield_ticks);
+        ship.heat = ship.heat.map(|_| Heat::default());
+        if let Some(air) = ship.air_suuply.as_mut() {
+            air.air = RESPAWN_AIR_SECS * TICKS_PER_SECOND;
+        }
+        log::info!("respawned x={:.0} y={:.0} tick={}", pos.x, pos.y, self.tick_count);
+    }
+
+    fn update_status_effects(&mut self) {
+        for entity in &mut self.entity_store.entities {
+            entity.status.update();
+        }
+    }
+
+    // How much of the EMP stun the player's ship has left, 1 just hit to 0 not stunned
+    fn player_stun(&self) -> f64 {
+        self.control_object.map_or(0.0, |id| {
+            let stunned = self.entity_store.get(id).status.remaining(StatusKind::Stunned);
+            (stunned as f64 / EMP_STUN_TICKS as f64).min(1.0)
+        })
+    }
+
+    fn flip_transforms(&mut self) {
+        for entity in &mut self.entity_store.entities {
+            // a teleport only skips interpolation until the next tick
+            entity.transform.skip_interp = false;
+            entity.prev_transform = entity.transform.clone();
+        }
+    }
+
+    pub fn interpolate_transforms(&mut self) {
+        let interp = self.get_interp();
+        self.interpolate_transforms_at(interp);
+        // the ghost ticks in lockstep with us, so it uses our interpolation
+        if let Some(ghost) = self.ghost.as_mut() {
+            ghost.interpolate_transforms_at(interp);
+        }
+    }
+
+    fn interpolate_transforms_at(&mut self, interp: f64) {
+        for entity in &mut self.entity_store.entities {
+            if entity.transform.skip_interp || entity.flags.contains(EntityFlags::NO_INTERP) {
+                entity.render_transform.translation = entity.transform.translation;
+                entity.render_transform.rotation = entity.transform.rotation;
+                continue;
+            }
+
+            entity.render_transform.translation = entity
+                .prev_transform
+                .translation
+                .lerp(entity.transform.translation, interp);
+            let delta_rot = entity.transform.rotation - entity.prev_transform.rotation;
+            let delta_rot = if delta_rot > PI {
+                delta_rot - TAU
+            } else if delta_rot < -PI {
+                delta_rot + TAU
+            } else {
+                delta_rot
+            };
+            entity.render_transform.rotation = entity.prev_transform.rotation + interp * delta_rot;
+        }
+
+        // children go with their parent as it's drawn
+        for idx in 0..self.entity_store.entities.len() {
+            let Some(attachment) = self.entity_store.entities[idx].attachment else {
+                continue;
+            };
+            let parent = self.entity_store.get(attachment.parent).render_transform;
+            let render_transform = attachment.world_transform(&parent);
+            let child = &mut self.entity_store.entities[idx].render_transform;
+            child.translation = render_transform.translation;
+            child.rotation = render_transform.rotation;
+        }
+    }
+
+    fn update_time(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = now - self.last_time;
+        self.last_time = now;
+        self.frame_secs = elapsed.as_secs_f64();
+
+        let elapsed = elapsed.as_micros();
+
+        // virtual time stands still while paused, and runs faster or slower than the
+        // wall clock if the tick rate is tuned
+        if !self.is_paused() {
+            let rate = self.tuning.tick_rate / TICKS_PER_SECOND as f64 * self.slow_mo_rate(now);
+            self.virtual_time += (elapsed as f64 * rate) as u128;
+        }
+        let tick = (self.virtual_time / MICROS_PER_TICK as u128) as u32;
+
+        let num_tick = tick - self.last_tick;
+        self.last_tick = tick;
+
+        // This is a bit awkward doing this here (and storing as bool) but we don't pass mutable self to render
+        // so this is most convenient
+        self.render_ready =
+            self.last_render.elapsed().as_micros() as u64 > MICROS_PER_SECOND / TARGET_FPS;
+        // HACK: turn off frame rate cap for now since it seems to cause backoff stragegy for some event loops.
+        self.render_ready = true;
+        if self.render_ready {
+            self.last_render = now;
+        }
+
+        num_tick
+    }
+
+    // How fast time runs after a near miss, from the slow-mo rate back up to 1
+    fn slow_mo_rate(&self, now: Instant) -> f64 {
+        let Some(start) = self.slow_mo_start else {
+            return 1.0;
+        };
+        let t = (now - start).as_secs_f64() / NEAR_MISS_SLOW_MO_SECS;
+        if t >= 1.0 {
+            return 1.0;
+        }
+        let slow = self.tuning.near_miss_slow_mo;
+        slow + (1.0 - slow) * t * t
+    }
+
+    pub fn get_virtual_time_secs(&self) -> f64 {
+        self.virtual_time as f64 / MICROS_PER_SECOND as f64
+    }
+
+    // Virtual time of the current tick
+    pub fn get_tick_secs(&self) -> f64 {
+        self.tick_count as f64 / TICKS_PER_SECOND as f64
+    }
+
+    // Virtual time of what's drawn, in ticks. Goes a tick behind like the
+    // interpolated entities do.
+    fn render_tick(&self) -> f64 {
+        (self.tick_count as f64 - 1.0 + self.get_interp()).max(0.0)
+    }
+
+    // The border shape is made for the arena as it starts, this turns and scales
+    // it to match.
+    fn border_transform(&self) -> Affine {
+        let tick = self.render_tick();
+        let scale = self.arena.apothem_at(tick) / self.arena.apothem_at(0.0);
+        Affine::scale(scale).then_rotate(self.arena.rotation_at(tick))
+    }
+
+    pub fn get_interp(&self) -> f64 {
+        let interp = self.virtual_time % MICROS_PER_TICK as u128;
+        let interp = interp as f64 / MICROS_PER_TICK as f64;
+        interp
+    }
+
+    fn update_settings_controls(&mut self) {
+        if self.actions.just_pressed(Action::DebugLabels) {
+            self.settings.debug_labels = !self.settings.debug_labels;
+        }
+        if self.actions.just_pressed(Action::SensorMode) {
+            self.settings.sensor_mode = !self.settings.sensor_mode;
+        }
+        if self.actions.just_pressed(Action::ExposureDown) {
+            self.settings.exposure = (self.settings.exposure - 0.1).max(0.1);
+        }
+        if self.actions.just_pressed(Action::ExposureUp) {
+            self.settings.exposure = (self.settings.exposure + 0.1).min(4.0);
+        }
+    }
+
+    pub fn update(&mut self) {
+        let num_tick = self.update_time();
+
+        // Set exit on make or break event just for code coverage
+        let esc = PhysicalKey::Code(KeyCode::Escape);
+        if self.input_manager.is_break(esc) || self.input_manager.is_make(esc) {
+            self.exit_ready = true;
+        }
+
+        self.update_focus_pause();
+        if let Some(online) = self.online.as_mut() {
+            online.poll();
+        }
+        self.update_attract();
+        self.update_menu();
+        self.update_photo_mode();
+        self.update_star_map();
+
+        for _ in 0..num_tick {
+            // going back takes the place of ticking
+            if self.rewind.is_active() {
+                self.rewind_step();
+                break;
+            }
+            self.tick();
+        }
+        if std::mem::take(&mut self.checkpoint_requested) {
+            self.load_checkpoint();
+        }
+
+        self.update_autosave();
+        self.update_checkpoints();
+    }
+
+    // Run a single simulation tick. Normally called from update, but can be called
+    // directly to run the simulation without wall clock time (e.g., headless).
+    pub fn tick(&mut self) {
+        self.check_playback_rng();
+        // before this tick's inputs go into the run's recording, so the crash
+        // report can carry on from the snapshot with them
+        if self.crash.is_some() && self.tick_count % CRASH_SNAPSHOT_TICKS == 0 {
+            let snapshot = self.snapshot();
+            self.crash.as_ref().unwrap().record_snapshot(snapshot, self.run_recording.as_ref());
+        }
+        self.update_rewind_buffer();
+
+        // played back runs are flown by the actions they recorded, not the keys
+        match self.playback.as_mut() {
+            Some(playback) => {
+                let inputs = playback.tick_inputs(self.tick_count);
+                self.actions.update_from(&inputs, self.tick_count);
+            }
+            None => {
+                for event in self.input_manager.events() {
+                    let latency_ms = event.time.elapsed().as_secs_f64() * 1000.0;
+                    log::trace!(
+                        "input_used tick={} latency_ms={:.1} kind={:?}",
+                        self.tick_count,
+                        latency_ms,
+                        event.kind
+                    );
+                }
+                self.actions.update(&self.input_manager, self.tick_count);
+            }
+        }
+        let inputs = self.actions.inputs();
+        if let Some(crash) = self.crash.as_ref() {
+            crash.record_tick(self.seed, self.tick_count, inputs, || self.new_recording());
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.record(self.tick_count, inputs);
+        }
+        if let Some(recording) = self.run_recording.as_mut() {
+            recording.record(self.tick_count, inputs);
+        }
+
+        self.events.clear();
+
+        self.flip_transforms();
+        self.update_settings_controls();
+        self.update_save_request();
+        self.update_checkpoint_request();
+        self.update_rewind_request();
+        self.update_player_controls();
+        self.update_nav_grid();
+        self.update_faction_ships();
+        self.update_boss();
+        self.update_autopilot();
+        self.update_ai_pilots();
+        self.update_magnet();
+        self.update_kinematics();
+        self.update_turrets();
+        self.update_attachments();
+        self.apply_physics();
+        self.update_grapple();
+
+        let mut contacts = Vec::new();
+        self.detect_collisions(&mut contacts);
+        if self.check_invariants {
+            self.assert_invariants(&contacts);
+        }
+        self.resolve_collisions(&mut contacts);
+        self.update_salvage();
+        self.update_escort();
+        self.update_race();
+        self.update_timeline();
+        self.update_asteroid_spawner();
+        self.update_debris();
+        self.update_projectiles();
+        self.update_scenery();
+
+        self.check_air();
+        self.update_lives();
+        self.update_status_effects();
+        self.update_metrics();
+        self.update_endless_run();
+        self.update_run_credits();
+        self.update_danger_zones();
+        self.update_near_misses(&contacts);
+        self.update_combo();
+        self.update_docking();
+        self.update_cutscene();
+        self.update_tutorial();
+        self.update_hints();
+        self.update_music();
+        self.update_trail();
+        self.update_discovery();
+        self.update_waypoint();
+        if self.settings.difficulty.radar_sweep {
+            self.update_radar();
+        }
+        self.update_tints();
+        self.update_animations();
+        self.floating_texts.update(self.get_tick_secs());
+        self.border_fx.update(self.get_tick_secs());
+        self.repulsor.update(self.get_tick_secs());
+        self.camera.update();
+
+        // this goes here, so if more than one tick processed the make/break
+        // events won't be processed more than once
+        self.input_manager.clear_events();
+        self.tick_count += 1;
+
+        if let Some(ghost) = self.ghost.as_mut() {
+            ghost.tick();
+        }
+    }
+
+    // Numbers for the top left corner of the HUD, None without a player
+    fn hud_numbers(&self) -> Option<HudNumbers> {
+        let player = self.get_control_object().map(|id| self.get_entities().get(id))?;
+
+        let air = player.air_suuply.as_ref().map_or(0, |air| air.air);
+        let cargo = player.cargo.as_ref();
+        let modules = cargo.map_or(Vec::new(), |cargo| {
+            [UpgradeModule::Thrusters, UpgradeModule::AirRecycler, UpgradeModule::Magnet]
+                .into_iter()
+                .map(|module| (module.name(), cargo.count(module)))
+                .filter(|(_, count)| *count > 0)
+                .collect()
+        });
+        let reputation = if self.entity_store.entities.iter().any(|entity| entity.faction_ship.is_some()) {
+            FACTIONS
+                .iter()
+                .map(|faction| {
+                    let stance = self.reputation.stance(*faction);
+                    (faction.name(), self.reputation.get(*faction), stance.name())
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Some(HudNumbers {
+            score: player.score.map_or(0, |score| score.0),
+            slow_mode: self.slow_mode,
+            air,
+            flight_assist: self.flight_assist,
+            cloaked: self.cloaked,
+            lives: Some(self.lives.left()).filter(|_| self.lives.spare() > 0),
+            respawn_secs: self.lives.respawn_secs(),
+            rewind_charges: Some(self.rewind.charges())
+                .filter(|_| self.can_rewind() && !self.check_invariants),
+            rewinding: self.rewind.is_active(),
+            ore: cargo.map_or(0, |cargo| cargo.ore),
+            modules,
+            reputation,
+            credits: self.run_credits,
+            // only worth fetching the text once it's shown
+            online_top: if self.is_game_over() { self.online_top_text() } else { None },
+            checkpoint: self.checkpoint_score().filter(|_| self.is_game_over()).map(|score| {
+                let keys = self.actions.bindings().key_names(Action::LoadCheckpoint);
+                format!("{}: back to the last checkpoint with {} points", keys, score_after_load(score))
+            }),
+        })
+    }
+
+    // Top ten of the online leaderboard for this seed, shown under the game over text
+    fn online_top_text(&self) -> Option<String> {
+        let online = self.online.as_ref().filter(|_| self.mode == GameMode::Endless)?;
+        let (title, top) = match online.top() {
+            TopScores::Loading => ("Online leaderboard: loading...", None),
+            TopScores::Online(top) => ("Online leaderboard", Some(top)),
+            TopScores::Cached(top) => ("Online leaderboard (offline, cached)", Some(top)),
+            TopScores::Unavailable => ("Online leaderboard unavailable", None),
+        };
+        let mut txt = title.to_string();
+        if self.slow_mode {
+            txt += ", this run sent as slow mode";
+        }
+        for (rank, entry) in top.into_iter().flatten().enumerate() {
+            write!(txt, "\n{:>2}. {:>8}  {}", rank + 1, entry.score, entry.name).unwrap();
+        }
+        Some(txt)
+    }
+
+    // Labelled bar centered at the top of the screen
+    fn render_health_bar(
+        scene: &mut Scene,
+        ctx: &mut FontContext,
+        bar_size: Size,
+        size: Size,
+        label: &str,
+        fraction: f64,
+        color: xilem::Color,
+    ) {
+        let bar = Rect::from_center_size(Point::new(0.5 * size.width, 40.0), bar_size);
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0x40, 0x40, 0x40, 0xc0),
+            None,
+            &bar,
+        );
+        let fill = Rect::new(bar.x0, bar.y0, bar.x0 + fraction * bar.width(), bar.y1);
+        scene.fill(vello::peniko::Fill::NonZero, Affine::IDENTITY, color, None, &fill);
+        scene.stroke(
+            &vello::kurbo::Stroke::new(2.0),
+            Affine::IDENTITY,
+            xilem::Color::rgb8(0xff, 0xff, 0xff),
+            None,
+            &bar,
+        );
+
+        let label_style = TextStyle {
+            font_size: 18.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0xff),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        draw_text(scene, ctx, label, &label_style, Point::new(bar.center().x, bar.y0 - 4.0), Vec2::new(0.5, 1.0));
+    }
+
+    // Engine heat in the bottom left corner, once the engines have warmed up at all
+    fn render_heat_gauge(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some(player) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let Some(heat) = player.heat.as_ref().filter(|heat| heat.heat > 0.0) else {
+            return;
+        };
+        let overheated = player.status.has(StatusKind::Overheated);
+        let margin = 0.05 * size.width.min(size.height);
+        let bar = Rect::new(margin, size.height - margin - 14.0, margin + 200.0, size.height - margin);
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0x40, 0x40, 0x40, 0xc0),
+            None,
+            &bar,
+        );
+        // yellow while it's fine, red as it gets close to shutting down
+        let hot = ((heat.heat - HEAT_GLOW) / (1.0 - HEAT_GLOW)).clamp(0.0, 1.0);
+        let color = if overheated {
+            HEAT_COLOR
+        } else {
+            xilem::Color::rgb8(0xff, (0xd0 as f64 * (1.0 - 0.7 * hot)) as u8, 0x30)
+        };
+        let fill = Rect::new(bar.x0, bar.y0, bar.x0 + heat.heat * bar.width(), bar.y1);
+        scene.fill(vello::peniko::Fill::NonZero, Affine::IDENTITY, color, None, &fill);
+        scene.stroke(
+            &vello::kurbo::Stroke::new(2.0),
+            Affine::IDENTITY,
+            xilem::Color::rgb8(0xff, 0xff, 0xff),
+            None,
+            &bar,
+        );
+
+        let label = if overheated { "OVERHEATED - COOLING DOWN" } else { "Engine heat" };
+        let style = TextStyle {
+            font_size: 18.0,
+            color: if overheated { HEAT_COLOR } else { xilem::Color::rgb8(0xff, 0xff, 0xff) },
+            alignment: xilem::TextAlignment::Start,
+        };
+        draw_text(scene, ctx, label, &style, Point::new(bar.x0, bar.y0 - 4.0), Vec2::new(0.0, 1.0));
+    }
+
+    // Right of the heat gauge, a ring filling up as the repulsor charges and
+    // lighting up once it's ready to fire, reading MAX once it's held long
+    // enough to overcharge
+    fn render_repulsor_charge(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        if self.control_object.is_none() {
+            return;
+        }
+        let margin = 0.05 * size.width.min(size.height);
+        let radius = 18.0;
+        let center = Point::new(margin + 250.0, size.height - margin - radius);
+        let charge = self.repulsor.charge();
+        let ready = self.repulsor.is_ready();
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            REPULSOR_COLOR.with_alpha_factor(if ready { 0.45 } else { 0.15 }),
+            None,
+            &vello::kurbo::Circle::new(center, radius),
+        );
+        let filled = vello::kurbo::Arc::new(center, (radius, radius), -0.5 * PI, TAU * charge, 0.0);
+        scene.stroke(&vello::kurbo::Stroke::new(3.0), Affine::IDENTITY, REPULSOR_COLOR, None, &filled);
+        let style = TextStyle {
+            font_size: 11.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0xff).with_alpha_factor(if ready { 1.0 } else { 0.5 }),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        // held long enough that letting go fires an overcharged pulse
+        let held = self.actions.pressed_for(Action::Repulsor, REPULSOR_HOLD);
+        let label = if ready && held && self.actions.is_down(Action::Repulsor) { "MAX" } else { "PULSE" };
+        draw_text(scene, ctx, label, &style, center, Vec2::new(0.5, 0.5));
+    }
+
+    // A row of icons for the status effects on the player's ship, above the heat gauge.
+    // A ring around each one runs down with the time it has left.
+    fn render_status_icons(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some(player) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let margin = 0.05 * size.width.min(size.height);
+        let radius = 20.0;
+        let style = TextStyle {
+            font_size: 12.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0xff),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        for (idx, effect) in player.status.active().enumerate() {
+            let center = Point::new(
+                margin + radius + idx as f64 * (2.0 * radius + 10.0),
+                size.height - margin - 60.0 - radius,
+            );
+            let color = effect.kind.color();
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                color.with_alpha_factor(0.35),
+                None,
+                &vello::kurbo::Circle::new(center, radius),
+            );
+            let left = vello::kurbo::Arc::new(
+                center,
+                (radius, radius),
+                -0.5 * PI,
+                TAU * effect.fraction_left(),
+                0.0,
+            );
+            scene.stroke(&vello::kurbo::Stroke::new(3.0), Affine::IDENTITY, color, None, &left);
+
+            let label = match effect.stacks {
+                1 => effect.kind.label().to_string(),
+                stacks => format!("{}\nx{}", effect.kind.label(), stacks),
+            };
+            draw_text(scene, ctx, &label, &style, center, Vec2::new(0.5, 0.5));
+        }
+    }
+
+    // Speed against the top speed in the bottom right corner, with a dial beside
+    // it showing which way the ship is drifting relative to where it's facing
+    fn render_speedometer(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some(ship) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let velocity = ship.rigid.velocity;
+        let speed = velocity.length();
+        let max_speed = self.tuning.max_ship_speed;
+
+        let margin = 0.05 * size.width.min(size.height);
+        let bar = Rect::new(
+            size.width - margin - 200.0,
+            size.height - margin - 14.0,
+            size.width - margin,
+            size.height - margin,
+        );
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0x40, 0x40, 0x40, 0xc0),
+            None,
+            &bar,
+        );
+        // orange close to top speed
+        let fraction = (speed / max_speed).min(1.0);
+        let color = if fraction > 0.9 {
+            xilem::Color::rgb8(0xff, 0x90, 0x30)
+        } else {
+            xilem::Color::rgb8(0x0, 0xd4, 0xf8)
+        };
+        let fill = Rect::new(bar.x0, bar.y0, bar.x0 + fraction * bar.width(), bar.y1);
+        scene.fill(vello::peniko::Fill::NonZero, Affine::IDENTITY, color, None, &fill);
+        scene.stroke(
+            &vello::kurbo::Stroke::new(2.0),
+            Affine::IDENTITY,
+            xilem::Color::rgb8(0xff, 0xff, 0xff),
+            None,
+            &bar,
+        );
+        let style = TextStyle {
+            font_size: 18.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0xff),
+            alignment: xilem::TextAlignment::End,
+        };
+        let label = format!("Speed {:.1} / {:.0}", speed, max_speed);
+        let label_size =
+            draw_text(scene, ctx, &label, &style, Point::new(bar.x1, bar.y0 - 4.0), Vec2::new(1.0, 1.0));
+
+        // G meter, red as it nears the hardest hits
+        let hard = (self.g_meter / G_TINT_FULL).min(1.0);
+        let fade = |full: f64| (0xff as f64 * (1.0 - full * hard)) as u8;
+        let style = TextStyle {
+            color: xilem::Color::rgb8(0xff, fade(0.8), fade(1.0)),
+            ..style
+        };
+        let g_pos = Point::new(bar.x1, bar.y0 - 8.0 - label_size.height);
+        draw_text(scene, ctx, &format!("{:.1} G", self.g_meter), &style, g_pos, Vec2::new(1.0, 1.0));
+
+        // facing is straight up on the dial, the needle is the way the ship is going
+        let dial = vello::kurbo::Circle::new((bar.x0 - 36.0, bar.y1 - 20.0), 20.0);
+        let white = xilem::Color::rgb8(0xff, 0xff, 0xff);
+        scene.stroke(&vello::kurbo::Stroke::new(1.5), Affine::IDENTITY, white, None, &dial);
+        let nose = dial.center - Vec2::new(0.0, dial.radius);
+        let notch = vello::kurbo::Line::new(nose, nose + Vec2::new(0.0, 6.0));
+        scene.stroke(&vello::kurbo::Stroke::new(2.0), Affine::IDENTITY, white, None, &notch);
+        if speed > 0.1 {
+            let forward = ship.render_transform.get_y_vector();
+            let drift = forward.cross(velocity).atan2(forward.dot(velocity));
+            let needle_end = dial.center + Vec2::from_angle(drift - FRAC_PI_2) * (dial.radius - 3.0);
+            let needle = vello::kurbo::Line::new(dial.center, needle_end);
+            scene.stroke(&vello::kurbo::Stroke::new(2.5), Affine::IDENTITY, color, None, &needle);
+        }
+    }
+
+    // Faint line from the ship to where it'll be shortly if it keeps drifting
+    fn render_velocity_vector(&self, scene: &mut Scene, world_to_screen: Affine) {
+        let Some(ship) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let velocity = ship.rigid.velocity;
+        if velocity.length() < 0.5 {
+            return;
+        }
+        let pos = ship.render_transform.translation();
+        let start = world_to_screen * pos.to_point();
+        let end = world_to_screen * (pos + VELOCITY_ARROW_TICKS * velocity).to_point();
+        let color = xilem::Color::rgba8(0xff, 0xff, 0xff, 0x50);
+        let line = vello::kurbo::Line::new(start, end);
+        scene.stroke(&vello::kurbo::Stroke::new(1.5), Affine::IDENTITY, color, None, &line);
+        let dot = vello::kurbo::Circle::new(end, 3.0);
+        scene.fill(vello::peniko::Fill::NonZero, Affine::IDENTITY, color, None, &dot);
+    }
+
+    // Carrier health across the top of the screen while it's around
+    fn render_boss(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some(boss) = self.boss.as_ref().filter(|boss| boss.phase != BossPhase::Destroyed) else {
+            return;
+        };
+
+        let (health, max) = std::iter::once(boss.core)
+            .chain(boss.turrets.iter().copied())
+            .filter_map(|id| self.entity_store.get(id).hull.as_ref())
+            .fold((0.0, 0.0), |(health, max), hull| (health + hull.health, max + hull.max));
+        let label = format!("Carrier - {}", boss.phase.name());
+        let bar_size = Size::new(0.6 * size.width, 16.0);
+        Self::render_health_bar(scene, ctx, bar_size, size, &label, health / max, BOSS_COLOR);
+    }
+
+    // Convoy hull bar at the top of the screen, and the result once the mission is over
+    fn render_escort(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some(escort) = self.escort.as_ref() else {
+            return;
+        };
+        let convoy = self.entity_store.get(escort.convoy);
+        let fraction = convoy.hull.as_ref().map_or(0.0, |hull| hull.health / hull.max);
+        let color = if fraction > 0.3 {
+            ESCORT_DESTINATION_COLOR
+        } else {
+            xilem::Color::rgb8(0xff, 0x40, 0x40)
+        };
+        Self::render_health_bar(scene, ctx, Size::new(300.0, 16.0), size, "Convoy", fraction, color);
+
+        let Some(outcome) = escort.outcome else {
+            return;
+        };
+        let (txt, color) = match outcome {
+            EscortOutcome::Delivered => ("CONVOY DELIVERED", ESCORT_DESTINATION_COLOR),
+            EscortOutcome::Destroyed => ("CONVOY DESTROYED", xilem::Color::rgb8(0xff, 0x00, 0x00)),
+        };
+        let style = TextStyle {
+            font_size: 48.0,
+            color,
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let center = Point::new(0.5 * size.width, 0.35 * size.height);
+        draw_text(scene, ctx, txt, &style, center, Vec2::new(0.5, 0.5));
+    }
+
+    // Next gate stands out, the one after is outlined so the line can be planned
+    fn render_race_gates(&self, scene: &mut Scene, world_to_screen: Affine) {
+        let Some(race) = self.race.as_ref() else {
+            return;
+        };
+
+        for (idx, gate) in race.gates.iter().enumerate().skip(race.next_gate).take(2) {
+            let ring = vello::kurbo::Circle::new(gate.pos.to_point(), gate.radius);
+            if idx == race.next_gate {
+                scene.fill(
+                    vello::peniko::Fill::NonZero,
+                    world_to_screen,
+                    RACE_GATE_COLOR.with_alpha_factor(0.15),
+                    None,
+                    &ring,
+                );
+                scene.stroke(&vello::kurbo::Stroke::new(12.0), world_to_screen, RACE_GATE_COLOR, None, &ring);
+            } else {
+                scene.stroke(
+                    &vello::kurbo::Stroke::new(4.0),
+                    world_to_screen,
+                    xilem::Color::rgb8(0x80, 0x80, 0x80),
+                    None,
+                    &ring,
+                );
+            }
+        }
+    }
+
+    // Translucent ship flying the best run, until it has crossed the finish (or run
+    // out of air, in endless mode)
+    fn ghost_entity(&self, world_to_screen: Affine) -> Option<RenderEntity> {
+        let ghost = self.ghost.as_ref()?;
+        let done = match self.race.as_ref() {
+            Some(race) => {
+                let best_ticks = race.best.as_ref().map_or(0, |best| best.ticks);
+                ghost.tick_count > best_ticks + TICKS_PER_SECOND as u32
+            }
+            None => ghost.is_game_over(),
+        };
+        if done {
+            return None;
+        }
+        let ship = ghost.entity_store.get(ghost.control_object?);
+
+        Some(RenderEntity {
+            transform: world_to_screen
+                * Affine::rotate(ship.render_transform.rotation())
+                    .then_translate(ship.render_transform.translation()),
+            shape: ship.shape.clone(),
+            tint: None,
+            alpha: GHOST_ALPHA,
+            animation: None,
+            rcs: None,
+        })
+    }
+
+    // Clock, gate count and split against the best run; results once finished
+    fn render_race(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some(race) = self.race.as_ref() else {
+            return;
+        };
+
+        let elapsed = match race.finish.as_ref() {
+            Some(finish) => finish.ticks,
+            None => self.tick_count - race.start_tick,
+        };
+        let white = xilem::Color::rgb8(0xff, 0xff, 0xff);
+        let style = |font_size: f32, color: xilem::Color| TextStyle {
+            font_size,
+            color,
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let top = Point::new(0.5 * size.width, 24.0);
+        draw_text(scene, ctx, &format_ticks(elapsed), &style(36.0, white), top, Vec2::new(0.5, 0.0));
+        let gates = format!("Gate {}/{}", race.next_gate.min(race.gates.len() - 1) + 1, race.gates.len());
+        draw_text(scene, ctx, &gates, &style(18.0, white), top + Vec2::new(0.0, 44.0), Vec2::new(0.5, 0.0));
+
+        // split for the gate just passed
+        let last_split = race.splits.last().copied();
+        if let Some(split) = last_split.filter(|split| elapsed < split + RACE_SPLIT_TICKS) {
+            let (txt, color) = match race.last_split_delta() {
+                Some(delta) if delta <= 0 => (
+                    format!("-{}", format_ticks(delta.unsigned_abs() as u32)),
+                    xilem::Color::rgb8(0x40, 0xff, 0x80),
+                ),
+                Some(delta) => (
+                    format!("+{}", format_ticks(delta as u32)),
+                    xilem::Color::rgb8(0xff, 0x40, 0x40),
+                ),
+                None => (format_ticks(split), white),
+            };
+            draw_text(scene, ctx, &txt, &style(24.0, color), top + Vec2::new(0.0, 70.0), Vec2::new(0.5, 0.0));
+        }
+
+        let Some(finish) = race.finish.as_ref() else {
+            return;
+        };
+        let center = Point::new(0.5 * size.width, 0.3 * size.height);
+        let title = match finish.rank {
+            Some(0) => format!("NEW BEST {}", format_ticks(finish.ticks)),
+            _ => format!("FINISHED {}", format_ticks(finish.ticks)),
+        };
+        draw_text(scene, ctx, &title, &style(48.0, RACE_GATE_COLOR), center, Vec2::new(0.5, 1.0));
+
+        for (idx, entry) in finish.top.iter().take(RACE_TOP_SHOWN).enumerate() {
+            let color = if Some(idx) == finish.rank {
+                RACE_GATE_COLOR
+            } else {
+                xilem::Color::rgb8(0xc0, 0xc0, 0xc0)
+            };
+            let slow = if entry.slow_mode { " (slow mode)" } else { "" };
+            let txt = format!("{}. {}{}", idx + 1, format_ticks(entry.ticks), slow);
+            let anchor = center + Vec2::new(0.0, 16.0 + 30.0 * idx as f64);
+            draw_text(scene, ctx, &txt, &style(24.0, color), anchor, Vec2::new(0.5, 0.0));
+        }
+    }
+
+    // Fog closes in around the ship, everything past its edge is hidden
+    // Over the border: the walls that were hit pulsing, then the glows and the sparks
+    fn render_border_fx(&self, scene: &mut Scene, world_to_screen: Affine, time: f64) {
+        let rotation = self.arena.rotation_at(self.render_tick());
+        let border_to_screen = world_to_screen * self.border_transform();
+        for hit in self.border_fx.hits() {
+            let pulse = hit.progress(time, PULSE_SECS);
+            if pulse < 1.0 {
+                let alpha = (0.3 + 0.5 * hit.strength) * (1.0 - pulse) * (1.0 - pulse);
+                let angle = hit.normal.atan2() - rotation;
+                let apothem = self.arena.apothem_at(0.0);
+                let pulse_scene = border_pulse_scene(self.arena.shape, apothem, angle, alpha);
+                scene.append(&pulse_scene, Some(border_to_screen));
+            }
+        }
+
+        let glow_color = xilem::Color::rgb8(0xff, 0xd0, 0xa0);
+        for hit in self.border_fx.hits() {
+            let glow = hit.progress(time, GLOW_SECS);
+            if glow >= 1.0 {
+                continue;
+            }
+            let radius = (20.0 + 40.0 * hit.strength) * (0.5 + 0.5 * glow);
+            let center = hit.pos.to_point();
+            let gradient = vello::peniko::Gradient::new_radial(center, radius as f32).with_stops([
+                vello::peniko::ColorStop {
+                    offset: 0.0,
+                    color: glow_color.with_alpha_factor((1.0 - glow) as f32),
+                },
+                vello::peniko::ColorStop {
+                    offset: 1.0,
+                    color: glow_color.with_alpha_factor(0.0),
+                },
+            ]);
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                world_to_screen,
+                &gradient,
+                None,
+                &vello::kurbo::Circle::new(center, radius),
+            );
+        }
+
+        let spark_color = xilem::Color::rgb8(0xff, 0xe0, 0x80);
+        for spark in self.border_fx.sparks() {
+            let (pos, alpha) = spark.pos_and_alpha(time);
+            if alpha <= 0.0 {
+                continue;
+            }
+            // streaks shrinking as they slow down
+            let tail = pos - (4.0 + 12.0 * alpha) * spark.dir();
+            scene.stroke(
+                &vello::kurbo::Stroke::new(2.0),
+                world_to_screen,
+                spark_color.with_alpha_factor(alpha as f32),
+                None,
+                &vello::kurbo::Line::new(tail.to_point(), pos.to_point()),
+            );
+        }
+    }
+
+    // Bubble around the player's ship while a golden pod's shield lasts,
+    // flickering once it's about to run out
+    fn render_shield(&self, scene: &mut Scene, world_to_screen: Affine, time: f64) {
+        let Some(ship) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let left = ship.status.remaining(StatusKind::Shielded);
+        if left == 0 {
+            return;
+        }
+        let ending = left < 2 * TICKS_PER_SECOND as u32;
+        if ending && (8.0 * time).fract() < 0.5 {
+            return;
+        }
+        let color = StatusKind::Shielded.color();
+        let bubble = vello::kurbo::Circle::new(
+            ship.render_transform.translation().to_point(),
+            ship.collision.radius() + 10.0,
+        );
+        let fill = color.with_alpha_factor(0.15);
+        scene.fill(vello::peniko::Fill::NonZero, world_to_screen, fill, None, &bubble);
+        scene.stroke(
+            &vello::kurbo::Stroke::new(3.0),
+            world_to_screen,
+            color.with_alpha_factor(0.7),
+            None,
+            &bubble,
+        );
+    }
+
+    // Rings racing out from where the repulsor was fired, fading as they go
+    fn render_shockwaves(&self, scene: &mut Scene, world_to_screen: Affine, time: f64) {
+        for wave in self.repulsor.shockwaves() {
+            let (reach, alpha) = wave.reach_and_alpha(time);
+            let ring = vello::kurbo::Circle::new(wave.pos.to_point(), reach * REPULSOR_RADIUS);
+            scene.stroke(
+                &vello::kurbo::Stroke::new(4.0 + 12.0 * (1.0 - reach)),
+                world_to_screen,
+                REPULSOR_COLOR.with_alpha_factor((0.8 * alpha) as f32),
+                None,
+                &ring,
+            );
+        }
+    }
+
+    // The grappling line from the ship's nose to the hook, drawn slack until
+    // it pulls tight
+    fn render_grapple(&self, scene: &mut Scene, world_to_screen: Affine) {
+        let (Some(grapple), Some(ctrl_id)) = (self.grapple, self.control_object) else {
+            return;
+        };
+        let ship = &self.entity_store.get(ctrl_id).render_transform;
+        let hook = self.entity_store.get(grapple.anchor).render_transform.local_to_world(grapple.offset);
+        // the tip of the hull
+        let nose = ship.local_to_world(Vec2::new(0.0, 25.0));
+        let line = hook - nose;
+        let slack = (1.0 - line.length() / grapple.length).clamp(0.0, 0.3) * grapple.length;
+        let sag = 0.5 * (nose + hook) + slack * Vec2::new(-line.y, line.x).normalize();
+        let mut path = vello::kurbo::BezPath::new();
+        path.move_to(nose.to_point());
+        path.quad_to(sag.to_point(), hook.to_point());
+        scene.stroke(&vello::kurbo::Stroke::new(2.0), world_to_screen, GRAPPLE_COLOR, None, &path);
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            world_to_screen,
+            GRAPPLE_COLOR,
+            None,
+            &vello::kurbo::Circle::new(hook.to_point(), 5.0),
+        );
+    }
+
+    // Faint ring showing how far the magnets reach, fading in as an air pod
+    // comes within half as far again
+    fn render_magnet_field(&self, scene: &mut Scene, world_to_screen: Affine) {
+        let Some(ship) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let Some(radius) = ship.magnet_radius() else {
+            return;
+        };
+        let ship_pos = ship.render_transform.translation();
+        let Some(pod_pos) = self.nearest_air_pod(ship_pos) else {
+            return;
+        };
+        let closeness = (1.5 * radius - (pod_pos - ship_pos).length()) / (0.5 * radius);
+        if closeness <= 0.0 {
+            return;
+        }
+        let center = world_to_screen * ship_pos.to_point();
+        let edge = world_to_screen * (ship_pos + Vec2::new(radius, 0.0)).to_point();
+        scene.stroke(
+            &vello::kurbo::Stroke::new(1.5),
+            Affine::IDENTITY,
+            COMPASS_COLOR.with_alpha_factor((0.25 * closeness.min(1.0)) as f32),
+            None,
+            &vello::kurbo::Circle::new(center, (edge - center).length()),
+        );
+    }
+
+    fn render_fog(&self, scene: &mut Scene, world_to_screen: Affine) {
+        let density = self
+            .timeline
+            .as_ref()
+            .map_or(0.0, |timeline| timeline.fog_density(self.tick_count));
+        let Some(ctrl_id) = self.control_object.filter(|_| density > 0.0) else {
+            return;
+        };
+
+        let center = self.entity_store.get(ctrl_id).render_transform.translation().to_point();
+        let fog_color = xilem::Color::rgb8(0x30, 0x34, 0x3a);
+        let gradient = vello::peniko::Gradient::new_radial(center, FOG_RADIUS as f32).with_stops([
+            vello::peniko::ColorStop {
+                offset: 0.0,
+                color: fog_color.with_alpha_factor(0.0),
+            },
+            vello::peniko::ColorStop {
+                offset: (FOG_CLEAR_RADIUS / FOG_RADIUS) as f32,
+                color: fog_color.with_alpha_factor(0.0),
+            },
+            vello::peniko::ColorStop {
+                offset: 1.0,
+                color: fog_color.with_alpha_factor(density as f32),
+            },
+        ]);
+        // cover anything the viewport could show
+        let extent = 2.0 * self.spatial_db.get_max().x;
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            world_to_screen,
+            &gradient,
+            None,
+            &Rect::from_center_size(center, Size::new(2.0 * extent, 2.0 * extent)),
+        );
+    }
+
+    fn is_fogged(&self, pos: Vec2) -> bool {
+        let Some(timeline) = self.timeline.as_ref() else {
+            return false;
+        };
+        let Some(ctrl_id) = self.control_object else {
+            return false;
+        };
+        let ship_pos = self.entity_store.get(ctrl_id).transform.translation();
+        timeline.fog_density(self.tick_count) > 0.5 && (pos - ship_pos).length() > FOG_RADIUS
+    }
+
+    // How deep the player's ship is in a nebula, 0 when it's in none
+    fn nebula_depth(&self) -> f64 {
+        self.control_object.map_or(0.0, |id| {
+            nebula::depth(&self.nebulae, self.entity_store.get(id).render_transform.translation())
+        })
+    }
+
+    // In a nebula the scanners only pick up what's close to the ship
+    fn is_jammed(&self, pos: Vec2) -> bool {
+        let Some(ctrl_id) = self.control_object else {
+            return false;
+        };
+        let ship_pos = self.entity_store.get(ctrl_id).transform.translation();
+        nebula::depth(&self.nebulae, ship_pos) > NEBULA_JAM_DEPTH
+            && (pos - ship_pos).length() > NEBULA_SCAN_RADIUS
+    }
+
+    // Static over the minimap, thicker the deeper the ship is in a nebula. It
+    // changes every tick, and only the picture uses the hash, not the world.
+    fn render_jamming(&self, scene: &mut Scene, layout: &MiniMapLayout) {
+        let depth = self.nebula_depth();
+        let dots = (NEBULA_NOISE_DOTS as f64 * depth) as u32;
+        for dot in 0..dots {
+            let key = (self.tick_count, dot);
+            let angle = (0.0..TAU).hash_rand(self.seed, (key, "noise_angle"));
+            let dist: f64 = (0.0..1.0).hash_rand(self.seed, (key, "noise_dist"));
+            let pos = layout.center + layout.radius * dist.sqrt() * Vec2::from_angle(angle);
+            let alpha = (0.2..0.8).hash_rand(self.seed, (key, "noise_alpha"));
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                xilem::Color::rgb8(0xc0, 0xb0, 0xd0).with_alpha_factor(alpha as f32),
+                None,
+                &Rect::from_center_size(pos, Size::new(2.0, 2.0)),
+            );
+        }
+    }
+
+    // Nebulae show from outside as glowing clouds, under everything else
+    fn render_nebulae(&self, scene: &mut Scene, world_to_screen: Affine) {
+        let color = xilem::Color::rgb8(0x60, 0x30, 0x80);
+        for nebula in &self.nebulae {
+            let center = nebula.center.to_point();
+            let gradient = vello::peniko::Gradient::new_radial(center, nebula.radius as f32).with_stops([
+                vello::peniko::ColorStop {
+                    offset: 0.0,
+                    color: color.with_alpha_factor(0.45),
+                },
+                vello::peniko::ColorStop {
+                    offset: 0.7,
+                    color: color.with_alpha_factor(0.3),
+                },
+                vello::peniko::ColorStop {
+                    offset: 1.0,
+                    color: color.with_alpha_factor(0.0),
+                },
+            ]);
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                world_to_screen,
+                &gradient,
+                None,
+                &vello::kurbo::Circle::new(center, nebula.radius),
+            );
+        }
+    }
+
+    // Announcement of the latest milestone, across the upper part of the screen
+    fn render_milestone_banner(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some((banner, alpha)) = self
+            .timeline
+            .as_ref()
+            .and_then(|timeline| timeline.banner(self.tick_count))
+        else {
+            return;
+        };
+
+        let style = TextStyle {
+            font_size: 48.0,
+            color: banner.color.with_alpha_factor(alpha as f32),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let anchor = Point::new(0.5 * size.width, 0.2 * size.height);
+        draw_text(scene, ctx, &banner.text, &style, anchor, Vec2::new(0.5, 0.5));
+    }
+
+    // Under the menu, so it's clear the game behind it is playing itself
+    fn render_attract(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let style = TextStyle {
+            font_size: 20.0,
+            color: xilem::Color::rgb8(0xc0, 0xc0, 0xc0),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let anchor = Point::new(0.5 * size.width, size.height - 40.0);
+        draw_text(scene, ctx, "DEMO - press any key", &style, anchor, Vec2::new(0.5, 1.0));
+    }
+
+    fn render_focus_pause(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let center = Point::new(0.5 * size.width, 0.5 * size.height);
+        let title = TextStyle {
+            font_size: 48.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0xff),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        draw_text(scene, ctx, "PAUSED", &title, center, Vec2::new(0.5, 1.0));
+        let hint = TextStyle {
+            font_size: 20.0,
+            color: xilem::Color::rgb8(0xc0, 0xc0, 0xc0),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let hint_text = if self.playback.is_some() {
+            "Click back into the window to carry on"
+        } else {
+            "Press any key to carry on"
+        };
+        let hint_anchor = center + Vec2::new(0.0, 16.0);
+        draw_text(scene, ctx, hint_text, &hint, hint_anchor, Vec2::new(0.5, 0.0));
+    }
+
+    fn render_mini_map(&self, scene: &mut Scene, size: Size, cam_pos: Vec2) {
+        let layout = MiniMapLayout::new(size);
+        let map_radius = layout.radius;
+        let map_scale = layout.scale;
+        let map_center = layout.center;
+        let world_to_map = layout.world_to_map(cam_pos);
+
+        scene.push_layer(
+            vello::peniko::BlendMode::default(),
+            1.0,
+            Affine::IDENTITY,
+            &vello::kurbo::Circle::new(map_center, map_radius),
+        );
+
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgb8(0, 0, 0),
+            None,
+            &vello::kurbo::Circle::new(map_center, map_radius),
+        );
+
+        self.render_trail(scene, world_to_map);
+
+        // compute oscillation for air animation, TODO: oscillate in sync with animation, make rate a function of air left
+        let t = self.virtual_time as f64 / MICROS_PER_SECOND as f64;
+        let rate = 4.0;
+        let oscillation = ((t % (1.0 / rate)) - 0.5 / rate).abs() * 2.0 * rate;
+
+        // with the radar sweep only the ship itself is always visible, the rest are blips
+        let radar_sweep = self.settings.difficulty.radar_sweep;
+        if radar_sweep {
+            self.render_radar(scene, &layout, world_to_map);
+        }
+
+        // crowded, the asteroids go on as density dots
+        let reach = Vec2::new(MINI_MAP_WORLD_RADIUS, MINI_MAP_WORLD_RADIUS);
+        let density = if radar_sweep { None } else { self.map_density(cam_pos - reach..cam_pos + reach) };
+        if let Some(cells) = density.as_ref() {
+            self.render_map_density(scene, cells, world_to_map, map_scale);
+        }
+
+        for (idx, entity) in self.entity_store.entities.iter().enumerate() {
+            // debris, scenery, guns and their shots would only clutter the map
+            let clutter = matches!(
+                entity.object_type,
+                GameObjectType::Dummy
+                    | GameObjectType::Debris
+                    | GameObjectType::Scenery
+                    | GameObjectType::Turret
+                    | GameObjectType::Projectile
+            );
+            if clutter
+                || (radar_sweep && Some(idx) != self.control_object.map(|id| id.0))
+                || (density.is_some() && entity.object_type == GameObjectType::Asteroid)
+            {
+                continue;
+            }
+            // fog and nebulae blind the scanners too, but the air pod beacon gets through
+            let pos = entity.transform.translation();
+            if entity.object_type != GameObjectType::AidPod && (self.is_fogged(pos) || self.is_jammed(pos)) {
+                continue;
+            }
+
+            let color = Self::mini_map_color(entity);
+            let radius_scale = match entity.object_type {
+                GameObjectType::Ship => 2.0,
+                GameObjectType::Asteroid => 1.0,
+                GameObjectType::AidPod => 2.0 * (0.1 + 0.9 * oscillation),
+                GameObjectType::Derelict => 1.5,
+                GameObjectType::Station => 1.0,
+                GameObjectType::Mine => 2.0,
+                GameObjectType::Boss => 1.0,
+                GameObjectType::Barrier => 1.0,
+                GameObjectType::Debris => unreachable!("Debris on minimap"),
+                GameObjectType::Scenery => unreachable!("Scenery on minimap"),
+                GameObjectType::Turret => unreachable!("Turret on minimap"),
+                GameObjectType::Projectile => unreachable!("Projectile on minimap"),
+                GameObjectType::Dummy => unreachable!("Dummy object in render"),
+            };
+            let radius = radius_scale * entity.collision.radius();
+
+            let pos = world_to_map * entity.render_transform.translation().to_point();
+
+            let dist = pos.distance(map_center);
+            if dist - map_scale * radius > map_radius
+                && entity.object_type != GameObjectType::AidPod
+            {
+                // object is off screen, don't render
+                continue;
+            }
+
+            let pos = if dist - map_scale * radius > map_radius {
+                // this is only for air object
+                let dir = (pos - map_center).normalize();
+                map_center + map_radius * dir
+            } else {
+                pos
+            };
+
+            if let Some(shape) = entity.shape.as_ref() {
+                // render asteroid or ship
+                let transform = Affine::rotate(entity.transform.rotation)
+                    .then_scale(map_scale * radius_scale)
+                    .then_translate(pos.to_vec2());
+                scene.append(shape.scene(), Some(transform));
+            } else {
+                // render flashing blue dot for air
+                scene.fill(
+                    vello::peniko::Fill::NonZero,
+                    Affine::translate(pos.to_vec2()),
+                    color,
+                    None,
+                    &vello::kurbo::Circle::new((0.0, 0.0), map_scale * radius),
+                );
+            }
+        }
+
+        self.render_jamming(scene, &layout);
+
+        scene.append(
+            self.get_resources().border_shape.scene(),
+            Some(world_to_map * self.border_transform()),
+        );
+
+        for (marker, color) in self.map_markers() {
+            // pin the marker to the edge of the map when it's out of range
+            let pos = world_to_map * marker.to_point();
+            let offset = pos - map_center;
+            let pos = if offset.length() > map_radius - 6.0 {
+                map_center + (map_radius - 6.0) * offset.normalize()
+            } else {
+                pos
+            };
+            Self::render_waypoint_marker(scene, pos, 6.0, color);
+        }
+
+        scene.pop_layer();
+
+        scene.stroke(
+            &vello::kurbo::Stroke::new(4.0),
+            Affine::IDENTITY,
+            xilem::Color::rgb8(0xff, 0xff, 0xff),
+            None,
+            &vello::kurbo::Circle::new(map_center, map_radius),
+        );
+    }
+
+    // Asteroids on the star map, as the scanners would pick them up: one by
+    // one when there are few in view, density dots per cell past that
+    fn render_star_map_field(
+        &self,
+        scene: &mut Scene,
+        layout: &StarMapLayout,
+        size: Size,
+        world_to_map: Affine,
+    ) {
+        let view = layout.map_to_world(Point::ZERO)..layout.map_to_world(size.to_vec2().to_point());
+        if let Some(cells) = self.map_density(view.clone()) {
+            self.render_map_density(scene, &cells, world_to_map, layout.scale);
+            return;
+        }
+        self.spatial_db.probe_range(view, 0.0, &mut |id| {
+            let entity = self.entity_store.get(id);
+            let pos = entity.transform.translation();
+            if entity.object_type != GameObjectType::Asteroid || self.is_fogged(pos) || self.is_jammed(pos) {
+                return;
+            }
+            let radius = (layout.scale * entity.collision.radius()).max(1.5);
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                Self::mini_map_color(entity),
+                None,
+                &vello::kurbo::Circle::new(world_to_map * pos.to_point(), radius),
+            );
+        });
+    }
+
+    // Spatial grid cells in view with how many entities each holds, or None
+    // when there are few enough to draw one by one. Only goes through the
+    // cells, so it costs the same however crowded the world is.
+    fn map_density(&self, view: Range<Vec2>) -> Option<Vec<(Vec2, usize)>> {
+        let cells = self.spatial_db.node_counts(view);
+        let total: usize = cells.iter().map(|(_, count)| count).sum();
+        (total > MAP_LOD_ENTITIES).then_some(cells)
+    }
+
+    // A dot per cell from map_density, bigger and brighter the more is in it.
+    // Everything in a cell counts, the few things the maps always show one
+    // by one too, and cells the scanners can't see into are left out.
+    fn render_map_density(
+        &self,
+        scene: &mut Scene,
+        cells: &[(Vec2, usize)],
+        world_to_map: Affine,
+        scale: f64,
+    ) {
+        let max_radius = 0.5 * scale * self.spatial_db.get_node_size();
+        for &(center, count) in cells {
+            if count == 0 || self.is_fogged(center) || self.is_jammed(center) {
+                continue;
+            }
+            let fill = (count as f64 / MAP_LOD_FULL_CELL as f64).min(1.0);
+            let radius = (max_radius * fill.sqrt()).max(1.0);
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                MAP_LOD_COLOR.with_alpha_factor((0.3 + 0.5 * fill) as f32),
+                None,
+                &vello::kurbo::Circle::new(world_to_map * center.to_point(), radius),
+            );
+        }
+    }
+
+    // Waypoint, escort destination and next race gate, where there are any
+    fn map_markers(&self) -> impl Iterator<Item = (Vec2, xilem::Color)> {
+        let markers = [
+            self.waypoint.map(|pos| (pos, WAYPOINT_COLOR)),
+            self.escort.as_ref().map(|escort| (escort.destination, ESCORT_DESTINATION_COLOR)),
+            self.race
+                .as_ref()
+                .and_then(|race| race.gates.get(race.next_gate))
+                .map(|gate| (gate.pos, RACE_GATE_COLOR)),
+        ];
+        markers.into_iter().flatten()
+    }
+
+    fn mini_map_color(entity: &GameObject) -> xilem::Color {
+        if entity.object_type == GameObjectType::Boss {
+            return BOSS_COLOR;
+        }
+        if entity.hull.is_some() {
+            return xilem::Color::rgb8(0x40, 0xff, 0x80);
+        }
+        if let Some(faction_ship) = entity.faction_ship.as_ref() {
+            return faction_ship.faction.color();
+        }
+        match entity.object_type {
+            GameObjectType::Ship => xilem::Color::rgb8(0xff, 0xff, 0xff),
+            GameObjectType::Asteroid => asteroid_colors(AsteroidMaterial::from_variant(entity.variant)).0,
+            GameObjectType::AidPod => PodTier::from_variant(entity.variant).colors().0,
+            GameObjectType::Derelict => xilem::Color::rgb8(0xb0, 0x7a, 0x40),
+            GameObjectType::Station => xilem::Color::rgb8(0xa0, 0xb0, 0xc0),
+            GameObjectType::Mine if entity.variant == MINE_EMP => xilem::Color::rgb8(0x40, 0xa0, 0xff),
+            GameObjectType::Mine => xilem::Color::rgb8(0xff, 0x30, 0x20),
+            GameObjectType::Boss => BOSS_COLOR,
+            GameObjectType::Barrier => xilem::Color::rgb8(0xe0, 0xc0, 0x30),
+            GameObjectType::Debris => xilem::Color::rgb8(0x70, 0x68, 0x60),
+            GameObjectType::Scenery => unreachable!("Scenery on a map"),
+            GameObjectType::Turret => unreachable!("Turret on a map"),
+            GameObjectType::Projectile => unreachable!("Projectile on a map"),
+            GameObjectType::Dummy => unreachable!("Dummy object in render"),
+        }
+    }
+
+    // Radar beam and the blips it left behind, fading until swept again
+    fn render_radar(&self, scene: &mut Scene, layout: &MiniMapLayout, world_to_map: Affine) {
+        let beam_end = layout.center + layout.radius * Vec2::from_angle(self.radar.angle);
+        scene.stroke(
+            &vello::kurbo::Stroke::new(2.0),
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0x40, 0xff, 0x60, 0xc0),
+            None,
+            &vello::kurbo::Line::new(layout.center, beam_end),
+        );
+
+        for blip in &self.radar.blips {
+            let age = self.tick_count.saturating_sub(blip.tick) as f64 / RADAR_SWEEP_TICKS as f64;
+            let fade = (1.0 - age).clamp(0.0, 1.0) as f32;
+
+            let pos = world_to_map * blip.pos.to_point();
+            let offset = pos - layout.center;
+            let pos = if offset.length() > layout.radius {
+                // only air pods are tracked beyond radar range, pinned to the edge
+                layout.center + layout.radius * offset.normalize()
+            } else {
+                pos
+            };
+
+            let radius = (layout.scale * blip.radius).max(2.0);
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                blip.color.with_alpha_factor(fade),
+                None,
+                &vello::kurbo::Circle::new(pos, radius),
+            );
+        }
+    }
+
+    fn render_waypoint_marker(scene: &mut Scene, pos: Point, size: f64, color: xilem::Color) {
+        let mut diamond = vello::kurbo::BezPath::new();
+        diamond.move_to((pos.x, pos.y - size));
+        diamond.line_to((pos.x + size, pos.y));
+        diamond.line_to((pos.x, pos.y + size));
+        diamond.line_to((pos.x - size, pos.y));
+        diamond.close_path();
+        scene.fill(vello::peniko::Fill::NonZero, Affine::IDENTITY, color, None, &diamond);
+    }
+
+    // Waypoint marker in the world, or an arrow at the edge of the view pointing at it
+    fn render_waypoint(&self, scene: &mut Scene, viewport: &Viewport, world_to_screen: Affine) {
+        let Some(waypoint) = self.waypoint else {
+            return;
+        };
+
+        let pos = world_to_screen * waypoint.to_point();
+        let view = viewport.rect.inset(-24.0);
+        if view.contains(pos) {
+            Self::render_waypoint_marker(scene, pos, 16.0, WAYPOINT_COLOR);
+            return;
+        }
+
+        Self::render_edge_arrow(scene, view, pos, WAYPOINT_COLOR);
+    }
+
+    // Arrow at the edge of the view pointing at something outside it
+    fn render_edge_arrow(scene: &mut Scene, view: Rect, pos: Point, color: xilem::Color) {
+        // scale the direction to it until it hits the edge of the view
+        let center = view.center();
+        let dir = pos - center;
+        let half = 0.5 * view.size().to_vec2();
+        let t = (half.x / dir.x.abs()).min(half.y / dir.y.abs());
+        let edge = center + dir * t;
+        Self::render_arrow(scene, edge, dir.atan2(), color);
+    }
+
+    // Arrow with its tip at a point, pointing along the angle
+    fn render_arrow(scene: &mut Scene, tip: Point, angle: f64, color: xilem::Color) {
+        let transform = Affine::rotate(angle).then_translate(tip.to_vec2());
+        let mut arrow = vello::kurbo::BezPath::new();
+        arrow.move_to((0.0, 0.0));
+        arrow.line_to((-24.0, -12.0));
+        arrow.line_to((-24.0, 12.0));
+        arrow.close_path();
+        scene.fill(vello::peniko::Fill::NonZero, transform, color, None, &arrow);
+    }
+
+    // Points out what the tutorial step is about: bobbing above it on screen, or
+    // from the edge of the view
+    fn render_tutorial_pointer(&self, scene: &mut Scene, viewport: &Viewport, world_to_screen: Affine) {
+        let Some(tutorial) = self.tutorial.as_ref() else {
+            return;
+        };
+        let target = match tutorial.step {
+            TutorialStep::CollectPod => self
+                .entity_store
+                .entities
+                .iter()
+                .position(|entity| entity.object_type == GameObjectType::AidPod)
+                .map(EntityId),
+            TutorialStep::AvoidAsteroid => tutorial.asteroid,
+            _ => None,
+        };
+        let Some(target) = target else {
+            return;
+        };
+        let entity = self.entity_store.get(target);
+        let center = entity.render_transform.translation();
+        let pos = world_to_screen * center.to_point();
+        let top = world_to_screen * (center - Vec2::new(0.0, entity.collision.radius())).to_point();
+
+        let view = viewport.rect.inset(-24.0);
+        if !view.contains(pos) {
+            Self::render_edge_arrow(scene, view, pos, TUTORIAL_COLOR);
+            return;
+        }
+        let bob = 8.0 * (TAU * self.get_virtual_time_secs()).sin().abs();
+        let tip = Point::new(pos.x, pos.y - (pos - top).length() - 12.0 - bob);
+        Self::render_arrow(scene, tip, 0.5 * PI, TUTORIAL_COLOR);
+    }
+
+    // Flashing warning while the player's air is low
+    fn render_low_air(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let air = self
+            .control_object
+            .and_then(|id| self.entity_store.get(id).air_suuply.as_ref())
+            .map_or(0, |air| air.air);
+        if air == 0 || air >= LOW_AIR_SECS * TICKS_PER_SECOND {
+            return;
+        }
+        let flash = 0.5 + 0.5 * (TAU * self.get_virtual_time_secs()).cos();
+        let style = TextStyle {
+            font_size: 28.0,
+            color: LOW_AIR_COLOR.with_alpha_factor((0.4 + 0.6 * flash) as f32),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let anchor = Point::new(0.5 * size.width, 0.3 * size.height);
+        draw_text(scene, ctx, "LOW AIR", &style, anchor, Vec2::new(0.5, 0.5));
+    }
+
+    // Combo multiplier at the top of the screen, bigger, redder and pulsing faster
+    // as it rises, with a bar under it running down the time left to keep it going
+    fn render_combo(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        if self.combo.chain() == 0 {
+            return;
+        }
+        let multiplier = self.combo.multiplier();
+        let intensity = (multiplier - 1) as f64 / (COMBO_MAX_MULTIPLIER - 1) as f64;
+        let pulse = 0.5 + 0.5 * (TAU * (1.0 + 2.0 * intensity) * self.get_virtual_time_secs()).sin();
+        let color = lerp_color(COMBO_LOW_COLOR, COMBO_HIGH_COLOR, intensity);
+        let center = Point::new(0.5 * size.width, 0.1 * size.height);
+
+        let style = TextStyle {
+            font_size: 28.0 + 20.0 * intensity + 4.0 * intensity * pulse,
+            color: color.with_alpha_factor((0.8 + 0.2 * pulse) as f32),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let txt = format!("x{} COMBO", multiplier);
+        let text_size = draw_text(scene, ctx, &txt, &style, center, Vec2::new(0.5, 1.0));
+
+        let bar = Rect::from_center_size(center + Vec2::new(0.0, 8.0), Size::new(text_size.width, 6.0));
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0x40, 0x40, 0x40, 0xc0),
+            None,
+            &bar,
+        );
+        let left = self.combo.window_left(self.tick_count);
+        let fill = Rect::new(bar.x0, bar.y0, bar.x0 + left * bar.width(), bar.y1);
+        scene.fill(vello::peniko::Fill::NonZero, Affine::IDENTITY, color, None, &fill);
+    }
+
+    // Tip along the bottom of the screen, fading in and out
+    fn render_hint(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some((hint, alpha)) = self.hints.current(self.tick_count) else {
+            return;
+        };
+        let center = Point::new(0.5 * size.width, 0.85 * size.height);
+        let panel = Rect::from_center_size(center, Size::new(0.7 * size.width, 44.0));
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0, 0, 0, 0xb0).with_alpha_factor(alpha as f32),
+            None,
+            &panel.to_rounded_rect(8.0),
+        );
+        let style = TextStyle {
+            font_size: 20.0,
+            color: xilem::Color::rgb8(0xff, 0xe0, 0x80).with_alpha_factor(alpha as f32),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        draw_text(scene, ctx, hint.text(), &style, center, Vec2::new(0.5, 0.5));
+    }
+
+    // Prompt for the step the player is on, and how to skip the rest
+    fn render_tutorial(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let Some(tutorial) = self.tutorial.as_ref() else {
+            return;
+        };
+        let anchor = Point::new(0.5 * size.width, 0.2 * size.height);
+        let style = TextStyle {
+            font_size: 32.0,
+            color: TUTORIAL_COLOR,
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let prompt = tutorial.prompt(self.actions.bindings());
+        draw_text(scene, ctx, &prompt, &style, anchor, Vec2::new(0.5, 1.0));
+
+        if tutorial.step == TutorialStep::Done {
+            return;
+        }
+        let style = TextStyle {
+            font_size: 18.0,
+            color: xilem::Color::rgb8(0xc0, 0xc0, 0xc0),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let txt = format!(
+            "Tutorial {}/{} - {} to skip",
+            tutorial.step.number(),
+            NUM_STEPS,
+            self.actions.bindings().key_names(Action::SkipTutorial)
+        );
+        draw_text(scene, ctx, &txt, &style, anchor + Vec2::new(0.0, 8.0), Vec2::new(0.5, 0.0));
+    }
+
+    // Recent flight path of the ship, fading out with age
+    fn render_trail(&self, scene: &mut Scene, world_to_map: Affine) {
+        let Some(ctrl_id) = self.control_object else {
+            return;
+        };
+        let ship_pos = self.entity_store.get(ctrl_id).render_transform.translation();
+
+        // oldest first, ending at the ship
+        let points: Vec<Vec2> = self.trail.iter().chain(std::iter::once(ship_pos)).collect();
+        let stroke = vello::kurbo::Stroke::new(2.0);
+        for (idx, segment) in points.windows(2).enumerate() {
+            let age = 1.0 - (idx + 1) as f32 / points.len() as f32;
+            let line = vello::kurbo::Line::new(
+                world_to_map * segment[0].to_point(),
+                world_to_map * segment[1].to_point(),
+            );
+            scene.stroke(
+                &stroke,
+                Affine::IDENTITY,
+                xilem::Color::rgb8(0xff, 0xff, 0xff).with_alpha_factor(0.8 * (1.0 - age)),
+                None,
+                &line,
+            );
+        }
+    }
+
+    // Whole world map: the walls, the ship and its trail, the asteroids, air
+    // pods, the stations and derelicts found so far, and the waypoint markers
+    fn render_star_map(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size, star_map: &StarMap) {
+        let layout = StarMapLayout::new(size, self.spatial_db.get_max().x, star_map);
+        let world_to_map = layout.world_to_map();
+
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0x04, 0x06, 0x10, 0xf0),
+            None,
+            &Rect::from_origin_size(Point::ZERO, size),
+        );
+        scene.append(
+            self.get_resources().border_shape.scene(),
+            Some(world_to_map * self.border_transform()),
+        );
+        self.render_trail(scene, world_to_map);
+        self.render_star_map_field(scene, &layout, size, world_to_map);
+
+        // shapes are kept big enough to make out when zoomed out
+        let icon = |scene: &mut Scene, entity: &GameObject, min_radius: f64| {
+            let pos = world_to_map * entity.render_transform.translation().to_point();
+            let radius = entity.collision.radius();
+            let scale = (layout.scale * radius).max(min_radius) / radius;
+            match entity.shape.as_ref() {
+                Some(shape) => {
+                    let transform = Affine::rotate(entity.render_transform.rotation())
+                        .then_scale(scale)
+                        .then_translate(pos.to_vec2());
+                    scene.append(shape.scene(), Some(transform));
+                }
+                None => scene.fill(
+                    vello::peniko::Fill::NonZero,
+                    Affine::IDENTITY,
+                    Self::mini_map_color(entity),
+                    None,
+                    &vello::kurbo::Circle::new(pos, radius * scale),
+                ),
+            }
+            pos + Vec2::new(0.0, radius * scale + 4.0)
+        };
+
+        let style = TextStyle {
+            font_size: 14.0,
+            color: xilem::Color::rgb8(0xc0, 0xc8, 0xd0),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        for &id in &self.discovered {
+            let entity = self.entity_store.get(id);
+            let below = icon(scene, entity, 8.0);
+            let name = match entity.object_type {
+                GameObjectType::Station => "Station",
+                _ => "Derelict",
+            };
+            draw_text(scene, ctx, name, &style, below, Vec2::new(0.5, 0.0));
+        }
+        for entity in &self.entity_store.entities {
+            if entity.object_type == GameObjectType::AidPod {
+                icon(scene, entity, 4.0);
+            }
+        }
+        if let Some(ctrl_id) = self.control_object {
+            icon(scene, self.entity_store.get(ctrl_id), 10.0);
+        }
+        for (marker, color) in self.map_markers() {
+            Self::render_waypoint_marker(scene, world_to_map * marker.to_point(), 10.0, color);
+        }
+
+        let hint = TextStyle {
+            font_size: 18.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0xff),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let txt = "Tab to close - arrows to pan - scroll to zoom - click to set a waypoint, \
+                   right click to clear";
+        let anchor = Point::new(0.5 * size.width, size.height - 16.0);
+        draw_text(scene, ctx, txt, &hint, anchor, Vec2::new(0.5, 1.0));
+    }
+
+    fn render_hit_indicator(&self, scene: &mut Scene, size: Size) {
+        let Some(hit) = self.hit_indicator.as_ref() else {
+            return;
+        };
+
+        let age = self.get_virtual_time_secs() - hit.start_time;
+        if age > HIT_INDICATOR_SECS {
+            return;
+        }
+
+        // arc around the ship (center of screen) facing the direction the hit came from
+        let fade = 1.0 - age / HIT_INDICATOR_SECS;
+        let min_dim = size.width.min(size.height);
+        let sweep = PI / 3.0;
+        let arc = vello::kurbo::Arc::new(
+            (0.5 * size.width, 0.5 * size.height),
+            Vec2::new(0.2 * min_dim, 0.2 * min_dim),
+            hit.dir.atan2() - 0.5 * sweep,
+            sweep,
+            0.0,
+        );
+        scene.stroke(
+            &vello::kurbo::Stroke::new(6.0 + 10.0 * hit.strength),
+            Affine::IDENTITY,
+            xilem::Color::rgb8(0xff, 0x20, 0x20).with_alpha_factor(fade as f32),
+            None,
+            &arc,
+        );
+    }
+
+    // Frame around the screen in the color of the most recently entered zone
+    fn render_danger_tint(&self, scene: &mut Scene, size: Size) {
+        let Some(zone) = self.danger_zones.last() else {
+            return;
+        };
+        let age = self.get_virtual_time_secs() - self.danger_entered_time;
+        let fade = (age / DANGER_TINT_FADE_SECS).min(1.0);
+        // brighter just after entering, then a slow breathing glow
+        let flash = (1.0 - age / DANGER_TINT_FADE_SECS).max(0.0);
+        let alpha = fade * (0.25 + 0.05 * (2.0 * age).sin()) + 0.3 * flash;
+
+        let width = 0.03 * size.width.min(size.height);
+        scene.stroke(
+            &vello::kurbo::Stroke::new(width),
+            Affine::IDENTITY,
+            zone.color().with_alpha_factor(alpha as f32),
+            None,
+            &Rect::from_origin_size(Point::ZERO, size).inset(-0.5 * width),
+        );
+    }
+
+    // Red glow on the side of the screen facing the closest wall, with how far
+    // away it is. It gets stronger as the ship closes in, and starts further
+    // out the faster the ship heads there, so there's time to turn around.
+    fn render_border_warning(
+        &self,
+        scene: &mut Scene,
+        ctx: &mut FontContext,
+        size: Size,
+        world_to_screen: Affine,
+    ) {
+        let Some(ship) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let pos = ship.render_transform.translation();
+        let (dist, normal) = self.arena.nearest_wall(pos, self.render_tick());
+        // units/second towards the wall
+        let closing = ship.rigid.velocity.dot(normal).max(0.0) * TICKS_PER_SECOND as f64;
+        let warn_dist = BORDER_WARN_DIST + BORDER_WARN_SECS * self.tuning.reaction_scale * closing;
+        let strength = (1.0 - dist / warn_dist).clamp(0.0, 1.0);
+        if strength <= 0.0 {
+            return;
+        }
+
+        // from where the direction of the wall leaves the screen, in towards the middle
+        let ship_on_screen = world_to_screen * pos.to_point();
+        let dir = (world_to_screen * (pos + normal).to_point() - ship_on_screen).normalize();
+        let center = Point::new(0.5 * size.width, 0.5 * size.height);
+        let edge = center + (0.5 * size.width / dir.x.abs()).min(0.5 * size.height / dir.y.abs()) * dir;
+        let depth = 0.3 * size.width.min(size.height);
+        let color = DangerZone::NearBorder.color();
+        let gradient = vello::peniko::Gradient::new_linear(edge, edge - depth * dir).with_stops([
+            vello::peniko::ColorStop {
+                offset: 0.0,
+                color: color.with_alpha_factor((0.6 * strength) as f32),
+            },
+            vello::peniko::ColorStop {
+                offset: 1.0,
+                color: color.with_alpha_factor(0.0),
+            },
+        ]);
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            &gradient,
+            None,
+            &Rect::from_origin_size(Point::ZERO, size),
+        );
+
+        let style = TextStyle {
+            font_size: 16.0,
+            color: color.with_alpha_factor(strength.sqrt() as f32),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let txt = format!("BORDER {:.0}", dist.max(0.0));
+        draw_text(scene, ctx, &txt, &style, edge - 60.0 * dir, Vec2::new(0.5, 0.5));
+    }
+
+    // Screen edges going red with hard hits, as they register on the G meter
+    fn render_g_tint(&self, scene: &mut Scene, size: Size) {
+        let alpha = (self.g_meter / G_TINT_FULL).min(1.0) * 0.5;
+        if alpha < 0.01 {
+            return;
+        }
+        let width = 0.04 * size.width.min(size.height);
+        scene.stroke(
+            &vello::kurbo::Stroke::new(width),
+            Affine::IDENTITY,
+            xilem::Color::rgb8(0xff, 0x20, 0x20).with_alpha_factor(alpha as f32),
+            None,
+            &Rect::from_origin_size(Point::ZERO, size).inset(-0.5 * width),
+        );
+    }
+
+    // Air pod closest to a spot and where it is
+    fn nearest_air_pod(&self, pos: Vec2) -> Option<Vec2> {
+        let mut position_of = |id: EntityId| {
+            let entity = self.entity_store.get(id);
+            (entity.object_type == GameObjectType::AidPod).then(|| entity.render_transform.translation())
+        };
+        let (id, _) = self.spatial_db.find_nearest(pos, &mut position_of)?;
+        Some(self.entity_store.get(id).render_transform.translation())
+    }
+
+    // Ring around the ship with a needle pointing at the nearest air pod, and how far it is
+    fn render_compass(&self, scene: &mut Scene, ctx: &mut FontContext, world_to_screen: Affine) {
+        let Some(ship) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let ship_pos = ship.render_transform.translation();
+        let Some(pod_pos) = self.nearest_air_pod(ship_pos) else {
+            return;
+        };
+
+        let center = world_to_screen * ship_pos.to_point();
+        let dir = world_to_screen * pod_pos.to_point() - center;
+        if dir.length() < COMPASS_RADIUS {
+            // it's right there
+            return;
+        }
+        let ring = vello::kurbo::Circle::new(center, COMPASS_RADIUS);
+        let faint = COMPASS_COLOR.with_alpha_factor(0.35);
+        scene.stroke(&vello::kurbo::Stroke::new(1.5), Affine::IDENTITY, faint, None, &ring);
+
+        let angle = dir.atan2();
+        let tip = center + Vec2::from_angle(angle) * (COMPASS_RADIUS + 12.0);
+        let transform = Affine::rotate(angle).then_translate(tip.to_vec2());
+        let mut needle = vello::kurbo::BezPath::new();
+        needle.move_to((0.0, 0.0));
+        needle.line_to((-16.0, -7.0));
+        needle.line_to((-16.0, 7.0));
+        needle.close_path();
+        scene.fill(vello::peniko::Fill::NonZero, transform, COMPASS_COLOR, None, &needle);
+
+        let style = TextStyle {
+            font_size: 14.0,
+            color: COMPASS_COLOR,
+            alignment: xilem::TextAlignment::Middle,
+        };
+        let dist = (pod_pos - ship_pos).length();
+        let label_pos = center + Vec2::from_angle(angle) * (COMPASS_RADIUS + 32.0);
+        draw_text(scene, ctx, &format!("{:.0}", dist), &style, label_pos, Vec2::new(0.5, 0.5));
+    }
+
+    // Faint lines ahead of the moving asteroids closest to the ship, fading out
+    // towards where they'll be in a few seconds. Collisions aren't looked at, so
+    // they're only right until something gets in the way.
+    fn render_threat_lines(&self, scene: &mut Scene, world_to_screen: Affine) {
+        let Some(ship) = self.control_object.map(|id| self.entity_store.get(id)) else {
+            return;
+        };
+        let ship_pos = ship.render_transform.translation();
+        let dampening = self.tuning.dampening;
+        // further ahead, for more warning, in slow mode
+        let ticks = (THREAT_LINE_TICKS as f64 * self.tuning.reaction_scale) as u32;
+        let ship_path = ship.rigid.predict_path(ship_pos, dampening, ticks, THREAT_LINE_STEP);
+
+        let mut nearby = Vec::new();
+        let reach = Vec2::new(THREAT_LINE_RANGE, THREAT_LINE_RANGE);
+        self.spatial_db.probe_range(ship_pos - reach..ship_pos + reach, self.max_radius, &mut |id| {
+            let other = self.entity_store.get(id);
+            if other.object_type == GameObjectType::Asteroid
+                && other.rigid.velocity.length() >= THREAT_LINE_MIN_SPEED
+            {
+                nearby.push(((other.render_transform.translation() - ship_pos).length(), id));
+            }
+        });
+        nearby.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for &(_, id) in nearby.iter().take(MAX_THREAT_LINES) {
+            let asteroid = self.entity_store.get(id);
+            let pos = asteroid.render_transform.translation();
+            let radius = asteroid.collision.radius();
+            let path = asteroid.rigid.predict_path(pos, dampening, ticks, THREAT_LINE_STEP);
+
+            // heading into the ship if they'd overlap at the same point in time
+            let hit_dist = radius + ship.collision.radius();
+            let threat = path.iter().zip(&ship_path).any(|(a, b)| (*a - *b).length() < hit_dist);
+            let (color, alpha) = if threat { (THREAT_LINE_COLOR, 0.6) } else { (COMPASS_COLOR, 0.25) };
+
+            // the line starts at the asteroid's edge
+            let ahead: Vec<Vec2> =
+                path.iter().copied().filter(|point| (*point - pos).length() > radius).collect();
+            for (idx, segment) in ahead.windows(2).enumerate() {
+                let fade = 1.0 - idx as f64 / ahead.len() as f64;
+                let line = vello::kurbo::Line::new(
+                    world_to_screen * segment[0].to_point(),
+                    world_to_screen * segment[1].to_point(),
+                );
+                let color = color.with_alpha_factor((alpha * fade) as f32);
+                scene.stroke(&vello::kurbo::Stroke::new(1.5), Affine::IDENTITY, color, None, &line);
+            }
+        }
+    }
+
+    fn render_salvage_progress(&self, scene: &mut Scene, viewport: &Viewport, world_to_screen: Affine) {
+        for entity in &self.entity_store.entities {
+            let Some(salvage) = entity.salvage.as_ref() else {
+                continue;
+            };
+            if salvage.progress == 0 {
+                continue;
+            }
+
+            let center = world_to_screen * entity.render_transform.translation().to_point();
+            let radius = viewport.scale * entity.collision.radius() + 20.0;
+            let fraction = salvage.progress as f64 / SALVAGE_TICKS as f64;
+
+            scene.stroke(
+                &vello::kurbo::Stroke::new(6.0),
+                Affine::IDENTITY,
+                xilem::Color::rgba8(0xff, 0xff, 0xff, 0x40),
+                None,
+                &vello::kurbo::Circle::new(center, radius),
+            );
+            // fills clockwise starting at the top
+            let arc = vello::kurbo::Arc::new(
+                center,
+                Vec2::new(radius, radius),
+                -0.5 * PI,
+                fraction * TAU,
+                0.0,
+            );
+            scene.stroke(
+                &vello::kurbo::Stroke::new(6.0),
+                Affine::IDENTITY,
+                xilem::Color::rgb8(0xff, 0xc0, 0x40),
+                None,
+                &arc,
+            );
+        }
+    }
+
+    fn render_debug_labels(
+        &self,
+        scene: &mut Scene,
+        ctx: &mut FontContext,
+        viewport: &Viewport,
+        world_to_screen: Affine,
+    ) {
+        let fill_color = xilem::Color::rgb8(0xff, 0xff, 0x80);
+
+        for (idx, entity) in self.entity_store.entities.iter().enumerate() {
+            let pos = world_to_screen * entity.render_transform.translation().to_point();
+            if entity.object_type == GameObjectType::Dummy || !viewport.rect.contains(pos) {
+                continue;
+            }
+
+            let name = entity
+                .name
+                .as_ref()
+                .map(|name| name.0.clone())
+                .unwrap_or_else(|| format!("{:?}", entity.object_type));
+            let vel = entity.rigid.velocity;
+            let txt = format!("#{} {}\nv=({:.1}, {:.1})", idx, name, vel.x, vel.y);
+
+            // what physics sees, to hold up against what's drawn
+            for (center, radius) in entity.collision.world_circles(&entity.render_transform) {
+                scene.stroke(
+                    &vello::kurbo::Stroke::new(1.0 / viewport.scale),
+                    world_to_screen,
+                    xilem::Color::rgba8(0xff, 0xff, 0x80, 0x80),
+                    None,
+                    &vello::kurbo::Circle::new(center.to_point(), radius),
+                );
+            }
+            // the way a trader is taking to its next station
+            if let Some(nav_path) = entity.faction_ship.as_ref().map(|ship| &ship.nav_path) {
+                let mut path = vello::kurbo::BezPath::new();
+                path.move_to(entity.render_transform.translation().to_point());
+                for waypoint in nav_path.waypoints() {
+                    path.line_to(waypoint.to_point());
+                }
+                scene.stroke(
+                    &vello::kurbo::Stroke::new(1.0 / viewport.scale),
+                    world_to_screen,
+                    xilem::Color::rgba8(0x60, 0xff, 0x60, 0x80),
+                    None,
+                    &path,
+                );
+            }
+            // where a faction ship last saw the player
+            if let Some(seen) = entity.faction_ship.as_ref().and_then(|ship| ship.perception.last_seen()) {
+                let from = entity.render_transform.translation().to_point();
+                scene.stroke(
+                    &vello::kurbo::Stroke::new(1.0 / viewport.scale),
+                    world_to_screen,
+                    xilem::Color::rgba8(0xff, 0x60, 0x60, 0x80),
+                    None,
+                    &vello::kurbo::Line::new(from, seen.to_point()),
+                );
+            }
+
+            let mut lcx = masonry::parley::LayoutContext::new();
+            let mut text_layout_builder = lcx.ranged_builder(ctx, &txt, 1.0);
+
+            text_layout_builder.push_default(&StyleProperty::FontStack(FontStack::Single(
+                FontFamily::Generic(parley::style::GenericFamily::Monospace),
+            )));
+            text_layout_builder.push_default(&StyleProperty::FontSize(12.0));
+            text_layout_builder.push_default(&StyleProperty::Brush(
+                vello::peniko::Brush::Solid(fill_color).into(),
+            ));
+
+            let mut text_layout = text_layout_builder.build();
+            text_layout.break_all_lines(None, xilem::TextAlignment::Middle);
+            let w = text_layout.width() as f64;
+            let h = text_layout.height() as f64;
+
+            // place label just above the entity's collision circle
+            let above = viewport.scale * entity.collision.radius();
+            let mut scratch_scene = Scene::new();
+            masonry::text_helpers::render_text(
+                scene,
+                &mut scratch_scene,
+                Affine::translate(Vec2::new(pos.x - 0.5 * w, pos.y - above - h)),
+                &text_layout,
+            );
+        }
+    }
+
+    fn render_letterbox(&self, scene: &mut Scene, size: Size, viewport: &Viewport) {
+        if !viewport.letterbox {
+            return;
+        }
+
+        // black out everything outside the viewport rect
+        let view = viewport.rect;
+        let bars = [
+            Rect::new(0.0, 0.0, size.width, view.y0),
+            Rect::new(0.0, view.y1, size.width, size.height),
+            Rect::new(0.0, view.y0, view.x0, view.y1),
+            Rect::new(view.x1, view.y0, size.width, view.y1),
+        ];
+        for bar in bars.iter().filter(|bar| bar.area() > 0.0) {
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                xilem::Color::rgb8(0, 0, 0),
+                None,
+                bar,
+            );
+        }
+    }
+
+    // Black bars across the top and bottom of the screen while a cutscene plays,
+    // sliding in at the start and out at the end
+    fn render_cutscene_bars(&self, scene: &mut Scene, size: Size) {
+        let Some(cutscene) = self.cutscene.as_ref() else {
+            return;
+        };
+        let slide = cutscene.bars_at(self.render_tick());
+        let height = slide * CUTSCENE_BAR_HEIGHT * size.height;
+        for bar in [
+            Rect::new(0.0, 0.0, size.width, height),
+            Rect::new(0.0, size.height - height, size.width, size.height),
+        ] {
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                xilem::Color::rgb8(0, 0, 0),
+                None,
+                &bar,
+            );
+        }
+    }
+
+    // Copy what's needed to draw a frame for a window of the given (logical) size
+    // out of the world, see render_frame.rs. The panels that aren't copied are
+    // drawn here, with the sim thread's font context.
+    pub fn extract_render_frame(
+        &mut self,
+        ctx: &mut FontContext,
+        size: Size,
+        scale_factor: f64,
+    ) -> RenderFrame {
+        let mut frame = RenderFrame::new(size);
+        frame.sprites = self.sprites.clone();
+        frame.params = self.frame_params(Size::new(size.width * scale_factor, size.height * scale_factor));
+        frame.screenshot_supersample = self
+            .take_screenshot_request()
+            .then(|| self.settings.screenshot_supersample);
+
+        let cam_pos = self.get_camera_pos();
+
+        let viewport = self.get_viewport(size);
+        let world_to_screen = viewport.world_to_screen(cam_pos);
+
+        self.render_nebulae(&mut frame.underlay, world_to_screen);
+        self.render_race_gates(&mut frame.underlay, world_to_screen);
+
+        // animations run on virtual time, interpolated between ticks like the transforms
+        let render_secs = self.render_tick() / TICKS_PER_SECOND as f64;
+        let mut debris = Vec::new();
+        let mut scenery = Vec::new();
+
+        for (idx, entity) in self.entity_store.entities.iter().enumerate() {
+            if entity.object_type == GameObjectType::AidPod {
+                // if air pod is off screen, render blip at edge of screen
+                let rad = entity.collision.radius();
+                let half_size = viewport.half_extent();
+                let pos = entity.render_transform.translation() - cam_pos;
+                if pos.x + rad < -half_size.x
+                    || pos.x - rad > half_size.x
+                    || pos.y + rad < -half_size.y
+                    || pos.y - rad > half_size.y
+                {
+                    let clip_end = |p0, p1, c0, c1, c_clip| -> Vec2 {
+                        let t = (c_clip - c0) / (c1 - c0);
+                        if t < 0.0 {
+                            p1
+                        } else if t > 1.0 {
+                            p1
+                        } else {
+                            p0 + t * (p1 - p0)
+                        }
+                    };
+
+                    let p0 = Vec2::new(0.0, 0.0);
+                    let pos = clip_end(p0, pos, 0.0, pos.x, -half_size.x);
+                    let pos = clip_end(p0, pos, 0.0, pos.x, half_size.x);
+                    let pos = clip_end(p0, pos, 0.0, pos.y, -half_size.y);
+                    let pos = clip_end(p0, pos, 0.0, pos.y, half_size.y);
+
+                    // compute oscillation for air animation on edge of screen. This is copy-pasted from minimap
+                    let t = self.virtual_time as f64 / MICROS_PER_SECOND as f64;
+                    let rate = 4.0;
+                    let oscillation = ((t % (1.0 / rate)) - 0.5 / rate).abs() * 2.0 * rate;
+
+                    // blip is sized in screen space, so only its position goes through the viewport
+                    let screen_pos = world_to_screen * (cam_pos + pos).to_point();
+                    frame.blips.push((screen_pos, 16.0 + oscillation * 48.0));
+                    continue;
+                }
+            }
+            let overheated = entity.status.has(StatusKind::Overheated);
+            let time_secs = self.get_virtual_time_secs();
+            let glow = entity.heat.as_ref().and_then(|heat| heat.glow(overheated, time_secs));
+            let alpha = entity.tint.map_or(1.0, |tint| tint.alpha_at(render_secs));
+            if alpha <= 0.0 {
+                continue;
+            }
+            let cloaked = self.cloaked && self.control_object == Some(EntityId(idx));
+            let render_entity = RenderEntity {
+                transform: world_to_screen
+                    * Affine::rotate(entity.render_transform.rotation())
+                        .then_translate(entity.render_transform.translation()),
+                shape: entity.shape.clone(),
+                tint: entity.tint.and_then(|tint| tint.color).or(glow),
+                alpha: if cloaked { CLOAK_ALPHA * alpha } else { alpha },
+                animation: entity
+                    .animation
+                    .as_ref()
+                    .map(|animation| (animation.clip, animation.clip_time(render_secs))),
+                rcs: entity
+                    .rcs
+                    .as_ref()
+                    .filter(|_| !cloaked)
+                    .and_then(|rcs| rcs.start_time.map(|start| (rcs.clone(), (render_secs - start).max(0.0)))),
+            };
+            if entity.object_type == GameObjectType::Debris {
+                debris.push(render_entity);
+            } else if entity.object_type == GameObjectType::Scenery {
+                scenery.push(render_entity);
+            } else {
+                frame.entities.push(render_entity);
+            }
+        }
+        // the hint explains the blip, so it's only worth it while playing
+        let playing = !self.is_paused() && !self.is_game_over();
+        if !frame.blips.is_empty() && playing {
+            self.show_hint(Hint::PodOffScreen);
+        }
+
+        // behind everything else
+        frame.entities.splice(0..0, scenery);
+        frame.entities.extend(self.ghost_entity(world_to_screen));
+        // over everything else, so it gets in the way of seeing what's behind
+        frame.entities.extend(debris);
+
+        for text in self.floating_texts.iter() {
+            let (rise, alpha) = text.rise_and_alpha(render_secs);
+            frame.labels.push(RenderLabel {
+                text: text.text.clone(),
+                pos: world_to_screen * text.pos.to_point() - Vec2::new(0.0, rise),
+                color: text.color,
+                alpha,
+            });
+        }
+
+        let scene = &mut frame.overlay;
+        scene.append(
+            self.get_resources().border_shape.scene(),
+            Some(world_to_screen * self.border_transform()),
+        );
+        self.render_border_fx(scene, world_to_screen, render_secs);
+        self.render_shockwaves(scene, world_to_screen, render_secs);
+        self.render_grapple(scene, world_to_screen);
+        self.render_shield(scene, world_to_screen, render_secs);
+        self.render_magnet_field(scene, world_to_screen);
+        self.render_fog(scene, world_to_screen);
+
+        // photo mode, the star map and cutscenes hide the HUD, and an EMP knocks it out
+        let show_hud = self.photo_mode.is_none()
+            && self.star_map.is_none()
+            && self.cutscene.is_none()
+            && self.player_stun() == 0.0;
+
+        if self.settings.debug_labels && show_hud {
+            self.render_debug_labels(scene, ctx, &viewport, world_to_screen);
+        }
+        let tuning_panel_shown = self.tuning_panel_shown();
+
+        self.render_letterbox(scene, size, &viewport);
+        self.render_cutscene_bars(scene, size);
+        self.render_inspector(scene, ctx, size, world_to_screen, &viewport);
+        if show_hud {
+            self.render_waypoint(scene, &viewport, world_to_screen);
+            self.render_tutorial_pointer(scene, &viewport, world_to_screen);
+            self.render_salvage_progress(scene, &viewport, world_to_screen);
+            if self.settings.compass {
+                self.render_compass(scene, ctx, world_to_screen);
+            }
+            if self.settings.threat_lines {
+                self.render_threat_lines(scene, world_to_screen);
+            }
+            self.render_velocity_vector(scene, world_to_screen);
+            self.render_hit_indicator(scene, size);
+            self.render_danger_tint(scene, size);
+            self.render_border_warning(scene, ctx, size, world_to_screen);
+            self.render_g_tint(scene, size);
+            self.render_mini_map(scene, size, cam_pos);
+            frame.hud = self.hud_numbers();
+
+            let scene = &mut frame.top;
+            self.render_escort(scene, ctx, size);
+            self.render_race(scene, ctx, size);
+            self.render_tutorial(scene, ctx, size);
+            self.render_low_air(scene, ctx, size);
+            self.render_combo(scene, ctx, size);
+            self.render_heat_gauge(scene, ctx, size);
+            self.render_repulsor_charge(scene, ctx, size);
+            self.render_status_icons(scene, ctx, size);
+            self.render_speedometer(scene, ctx, size);
+            self.render_hint(scene, ctx, size);
+            self.render_boss(scene, ctx, size);
+            self.render_milestone_banner(scene, ctx, size);
+        }
+        let scene = &mut frame.top;
+        if let Some(star_map) = self.star_map.as_ref() {
+            self.render_star_map(scene, ctx, size, star_map);
+        }
+        if tuning_panel_shown {
+            self.tuning_panel.render(scene, ctx, size, &self.tuning);
+        }
+        if self.focus_paused {
+            self.render_focus_pause(scene, ctx, size);
+        }
+
+        if let Some(menu) = self.menu.as_ref() {
+            menu.render(scene, ctx, size);
+        }
+        if self.attract.is_some() {
+            self.render_attract(scene, ctx, size);
+        }
+        frame
+    }
+
+    // Values for the wgpu passes, for a window of the given size in pixels
+    fn frame_params(&self, pixel_size: Size) -> FrameParams {
+        let viewport = self.get_viewport(pixel_size);
+        let unzoomed = Viewport::new(self.settings.viewport_mode, pixel_size);
+        let photo_filter = self.get_photo_filter();
+        let sensor_mode = self.settings.sensor_mode && self.settings.quality().post_effects();
+        let night_vision = sensor_mode || photo_filter == PhotoFilter::NightVision;
+
+        FrameParams {
+            camera: CameraSnapshot {
+                pos: self.get_camera_pos(),
+                view_scale: viewport.scale,
+                time: self.get_virtual_time_secs(),
+                zoom: viewport.scale / unzoomed.scale,
+                interp: self.get_interp(),
+                viewport: viewport.rect,
+            },
+            post_params: PostParams {
+                exposure: self.settings.exposure,
+                sensor_mode: if night_vision { 1.0 } else { 0.0 },
+                photo_filter: photo_filter.shader_index(),
+                nebula: self.nebula_depth() as f32,
+                glitch: self.player_stun() as f32,
+                _padding: [0.0; 3],
+            },
+        }
+    }
+}
+
+// --- MARK: GameObject ---
+
+//-------------------------------------------------------------------------
+// GameObject: every game object has all the components, but some are optional.
+// In a larger game you would use an ecs sysem like hecs.
+//----------------------------------------------------------------------
+
+pub struct GameObject {
+    pub transform: Transform,
+    pub prev_transform: Transform,
+    pub render_transform: Transform,
+    pub spatial_db_ref: SpatialDbRef,
+    pub collision: Collision,
+    pub rigid: Rigid,
+    pub shape: Option<Shape>,
+    pub animation: Option<Animation>,
+    pub tint: Option<Tint>,
+    pub name: Option<Name>,
+    pub air_suuply: Option<AirSupply>,
+    pub score: Option<Score>,
+    pub salvage: Option<Salvage>,
+    pub cargo: Option<Cargo>,
+    // ai ships only, the player's ship is flown by input
+    pub pilot: Option<Pilot>,
+    pub faction_ship: Option<FactionShip>,
+    pub hull: Option<Hull>,
+    pub mine: Option<Mine>,
+    // scripted motion, for kinematic bodies
+    pub kinematic: Option<Kinematic>,
+    // maneuvering thrusters, ships only
+    pub rcs: Option<Rcs>,
+    // of the engines, the player's ship only
+    pub heat: Option<Heat>,
+    // timed effects: stunned, slowed, leaking air, overheated engines
+    pub status: StatusEffects,
+    pub flags: EntityFlags,
+    // held in another entity's frame, see GameWorld::attach
+    pub attachment: Option<Attachment>,
+    // virtual time in seconds the main engine started firing, for its flame
+    pub thrust_start: Option<f64>,
+    // aims on its own, needs an attachment, see GameWorld::update_turrets
+    pub turret: Option<Turret>,
+    pub projectile: Option<Projectile>,
+    pub object_type: GameObjectType,
+    // which shape variant (outline and material of asteroids, class of the player's ship), needed to
+    // rebuild the object from a save
+    pub variant: u32,
+}
+
+impl GameObject {
+    fn new_ship(resources: &Resources, _seed: u64, _seq: u32) -> Self {
+        let shape = resources.ship_shape.clone();
+        let collision = Collision::compound(SHIP_CIRCLES.to_vec());
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+        let rigid = Rigid::new(shape.radius(), 1.0, 0.0, 0.01, 1.0, 0.3);
+
+        GameObject {
+            transform: Transform::new(Vec2::ZERO, PI),
+            prev_transform: Transform::new(Vec2::ZERO, PI),
+            render_transform: Transform::new(Vec2::ZERO, PI),
+            spatial_db_ref,
+            collision,
+            rigid,
+            shape: Some(shape),
+            animation: None,
+            tint: None,
+            name: Some(Name::new("Player")),
+            air_suuply: Some(AirSupply {
+                air: TICKS_PER_SECOND * 60,
+            }),
+            score: Some(Score(0)),
+            salvage: None,
+            cargo: Some(Cargo::default()),
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: None,
+            kinematic: None,
+            rcs: Some(Rcs::default()),
+            heat: Some(Heat::default()),
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Ship,
+            variant: 0,
+        }
+    }
+
+    fn new_air_pod(_resources: &Resources, _seed: u64, _seq: u32, tier: PodTier) -> Self {
+        // get air pod shape at first frame to figure out radius
+        let shape = air_pod_shape(0.0, tier);
+
+        let collision = Collision::new(shape.collision_radius());
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+        let rigid = Rigid::new(shape.radius(), 1.0, 0.0, 0.01, 0.99, 0.3);
+
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref,
+            collision,
+            rigid,
+            shape: None,
+            animation: Some(Animation::looping(AnimationClip::AirPod(tier), 0.0)),
+            tint: None,
+            name: None,
+            air_suuply: Some(AirSupply {
+                air: (tier.air_factor() * (TICKS_PER_SECOND * 15) as f64) as u64,
+            }),
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: None,
+            kinematic: None,
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::AidPod,
+            variant: tier.variant(),
+        }
+    }
+
+    fn new_asteroid(
+        resources: &Resources,
+        rng: &mut RngStream,
+        materials: &SpawnTable<AsteroidMaterial>,
+        vel_range: Range<f64>,
+        ang_vel_range: Range<f64>,
+    ) -> Self {
+        let vel = rng.next(vel_range);
+        let vel_angle = rng.next(0.0..TAU);
+        let vel = Vec2::new(vel * vel_angle.cos(), vel * vel_angle.sin());
+        let ang_vel = rng.next(ang_vel_range);
+
+        let asteroid_num = rng.next(0..NUM_ASTEROID_SHAPES);
+        let material = materials.sample(rng);
+        let mut asteroid = Self::new_asteroid_variant(resources, material.variant(asteroid_num));
+        asteroid.rigid.velocity = vel;
+        asteroid.rigid.angular_velocity = ang_vel;
+        asteroid
+    }
+
+    fn new_asteroid_variant(resources: &Resources, variant: u32) -> Self {
+        let shape = resources.asteroids[variant as usize].clone();
+        let material = AsteroidMaterial::from_variant(variant);
+
+        let collision = Collision::new(shape.collision_radius());
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+        // Note: rock's restitution is 1.01 in order to add a little entergy to the system when asteroids
+        // collide, picking up intensity
+        // weighed by the outline, so a small jagged rock is lighter than its bounding circle
+        let area = shape.area().unwrap_or_else(|| ShapeArea::disk(shape.radius()));
+        let density = material.density_factor();
+        let rigid = Rigid::from_area(area, 1.5 * density, 1.0 * density, 0.0, 0.0, material.restitution())
+            .with_friction(material.friction());
+
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref,
+            collision,
+            rigid,
+            shape: Some(shape),
+            animation: None,
+            tint: None,
+            name: None,
+            air_suuply: None,
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: None,
+            kinematic: None,
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Asteroid,
+            variant,
+        }
+    }
+
+    // A piece of drifting debris, see GameWorld::add_debris
+    fn new_debris(resources: &Resources, seed: u64, num: u32) -> Self {
+        let variant = (0..resources.debris_shapes.len() as u32).hash_rand(seed, (num, "debris_shape"));
+        let shape = resources.debris_shapes[variant as usize].clone();
+        let collision = Collision::new(shape.collision_radius());
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+        let rigid = Rigid::new(shape.radius(), DEBRIS_DENSITY, DEBRIS_DENSITY, 0.0, 0.0, 0.5);
+
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref,
+            collision,
+            rigid,
+            shape: Some(shape),
+            animation: None,
+            tint: None,
+            name: None,
+            air_suuply: None,
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: None,
+            kinematic: None,
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Debris,
+            variant,
+        }
+    }
+
+    // A speck of dust, see GameWorld::add_scenery
+    fn new_dust_mote(resources: &Resources) -> Self {
+        let mut mote = Self::new_dummy();
+        mote.shape = Some(resources.dust_shape.clone());
+        mote.flags = EntityFlags::RENDER_ONLY;
+        mote.object_type = GameObjectType::Scenery;
+        mote.variant = SCENERY_DUST;
+        mote
+    }
+
+    // Something drawn on another entity, see GameWorld::update_attached_scenery
+    fn new_attached_scenery(variant: u32) -> Self {
+        let mut scenery = Self::new_dummy();
+        scenery.flags = EntityFlags::RENDER_ONLY;
+        scenery.object_type = GameObjectType::Scenery;
+        scenery.variant = variant;
+        scenery
+    }
+
+    // Gun on a ship or station, see GameWorld::add_turrets. It's only drawn, its
+    // bolts are what hit things.
+    fn new_gun(resources: &Resources, turret: Turret) -> Self {
+        let mut gun = Self::new_dummy();
+        gun.shape = Some(resources.gun_shape.clone());
+        gun.turret = Some(turret);
+        gun.flags = EntityFlags::RENDER_ONLY;
+        gun.object_type = GameObjectType::Turret;
+        gun
+    }
+
+    // Fired by a gun, see GameWorld::fire_bolt
+    fn new_bolt(resources: &Resources, owner: EntityId) -> Self {
+        let mut bolt = Self::new_dummy();
+        bolt.shape = Some(resources.bolt_shape.clone());
+        bolt.collision = Collision::new(BOLT_RADIUS);
+        // light enough to only nudge what it hits, and it keeps its speed
+        bolt.rigid = Rigid::new(BOLT_RADIUS, 1.0, 1.0, 0.0, 0.0, 0.2);
+        bolt.projectile = Some(Projectile::new(owner));
+        bolt.flags = EntityFlags::default();
+        bolt.object_type = GameObjectType::Projectile;
+        bolt
+    }
+
+    // A faint ship far off crossing the field, see GameWorld::add_scenery
+    fn new_background_ship(resources: &Resources, faction: Faction) -> Self {
+        let mut ship = Self::new_dummy();
+        ship.shape = Some(resources.faction_ship_shape(faction).clone());
+        ship.tint = Some(Tint {
+            color: None,
+            alpha: BACKGROUND_SHIP_ALPHA,
+            blink_rate: 0.0,
+            ticks: None,
+        });
+        ship.flags = EntityFlags::RENDER_ONLY;
+        ship.object_type = GameObjectType::Scenery;
+        ship.variant = SCENERY_SHIP;
+        ship
+    }
+
+    fn new_derelict(resources: &Resources, seed: u64, seq: u32, loot: LootKind) -> Self {
+        let loot = match loot {
+            LootKind::Air => Loot::Air(TICKS_PER_SECOND * (10..30u32).hash_rand(seed, (seq, "air")) as u64),
+            LootKind::Ore => Loot::Ore((5..20).hash_rand(seed, (seq, "ore"))),
+            LootKind::Upgrade(module) => Loot::Upgrade(module),
+        };
+
+        // derelicts drift slowly
+        let vel = (0.0..2.0).hash_rand(seed, (seq, "vel"));
+        let vel_angle = (0.0..TAU).hash_rand(seed, (seq, "vel_angle"));
+
+        let mut derelict = Self::new_empty_derelict(resources);
+        derelict.rigid.velocity = Vec2::new(vel * vel_angle.cos(), vel * vel_angle.sin());
+        derelict.rigid.angular_velocity = (-0.01..0.01).hash_rand(seed, (seq, "ang_vel"));
+        derelict.salvage = Some(Salvage { loot, progress: 0 });
+        derelict
+    }
+
+    fn new_empty_derelict(resources: &Resources) -> Self {
+        let shape = resources.derelict_shape.clone();
+        let collision = Collision::new(shape.collision_radius());
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+        let rigid = Rigid::new(shape.radius(), 1.0, 1.0, 0.0, 0.0, 0.8);
+
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref,
+            collision,
+            rigid,
+            shape: Some(shape),
+            animation: None,
+            tint: None,
+            name: Some(Name::new("Derelict")),
+            air_suuply: None,
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: None,
+            kinematic: None,
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Derelict,
+            variant: 0,
+        }
+    }
+
+    fn new_faction_ship(resources: &Resources, faction: Faction, skill: PilotSkill) -> Self {
+        let mut ship = Self::new_ship(resources, 0, 0);
+        ship.shape = Some(resources.faction_ship_shape(faction).clone());
+        ship.name = Some(Name::new(faction.ship_name()));
+        // ai ships don't need air and don't score
+        ship.air_suuply = None;
+        ship.score = None;
+        ship.pilot = Some(Pilot::new(skill));
+        ship.heat = None;
+        ship.faction_ship = Some(FactionShip::new(faction, Vec2::ZERO));
+        ship
+    }
+
+    // Escorted cargo ship, slow and heavy with a hull that can be worn down
+    fn new_convoy(resources: &Resources) -> Self {
+        let mut convoy = Self::new_ship(resources, 0, 0);
+        let shape = resources.convoy_shape.clone();
+        convoy.collision = Collision::compound(CONVOY_CIRCLES.to_vec());
+        convoy.rigid = Rigid::new(shape.radius(), 2.0, 0.0, 0.01, 1.0, 0.3);
+        convoy.shape = Some(shape);
+        // points along +x, towards the destination
+        convoy.transform = Transform::new(Vec2::ZERO, -0.5 * PI);
+        convoy.prev_transform = convoy.transform.clone();
+        convoy.render_transform = convoy.transform.clone();
+        convoy.name = Some(Name::new("Convoy"));
+        convoy.air_suuply = None;
+        convoy.score = None;
+        convoy.cargo = None;
+        convoy.heat = None;
+        convoy.pilot = Some(
+            Pilot::new(PilotSkill::for_level(SkillLevel::Ace)).with_cruise_speed(ESCORT_CRUISE_SPEED),
+        );
+        convoy.hull = Some(Hull {
+            health: CONVOY_HULL,
+            max: CONVOY_HULL,
+        });
+        convoy
+    }
+
+    fn new_mine(resources: &Resources, variant: u32) -> Self {
+        let shape = if variant == MINE_EMP {
+            resources.emp_mine_shape.clone()
+        } else {
+            resources.mine_shape.clone()
+        };
+        let collision = Collision::new(shape.collision_radius());
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+        let rigid = Rigid::new(shape.radius(), 1.0, 1.0, 0.02, 0.02, 0.5);
+
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref,
+            collision,
+            rigid,
+            shape: Some(shape),
+            animation: None,
+            tint: None,
+            name: Some(Name::new("Mine")),
+            air_suuply: None,
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: Some(Mine { armed: true }),
+            kinematic: None,
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Mine,
+            variant,
+        }
+    }
+
+    fn new_boss_part(shape: Shape, name: &str, health: Option<f64>) -> Self {
+        let collision = Collision::new(shape.collision_radius());
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+        // kinematic, update_boss moves the carrier and it shoves everything else aside
+        let rigid = Rigid::kinematic(0.5);
+
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref,
+            collision,
+            rigid,
+            shape: Some(shape),
+            animation: None,
+            tint: None,
+            name: Some(Name::new(name)),
+            air_suuply: None,
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: health.map(|max| Hull { health: max, max }),
+            mine: None,
+            kinematic: None,
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Boss,
+            variant: 0,
+        }
+    }
+
+    fn new_station(resources: &Resources) -> Self {
+        let shape = resources.station_shape.clone();
+        let collision = Collision::compound(station_circles());
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+        // turns slowly in place, its arms sweeping aside whatever drifts in
+        let rigid = Rigid::kinematic(0.5);
+
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref,
+            collision,
+            rigid,
+            shape: Some(shape),
+            animation: None,
+            tint: None,
+            name: Some(Name::new("Station")),
+            air_suuply: None,
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: None,
+            kinematic: Some(Kinematic::Spin { rate: STATION_SPIN_RATE }),
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Station,
+            variant: 0,
+        }
+    }
+
+    // Long bar sweeping back and forth, set up with set_up_barrier once placed
+    fn new_barrier(resources: &Resources) -> Self {
+        let shape = resources.barrier_shape.clone();
+        let count = (BARRIER_HALF_LENGTH / BARRIER_RADIUS) as u32;
+        let spacing = 2.0 * (BARRIER_HALF_LENGTH - BARRIER_RADIUS) / count as f64;
+        let circles = (0..=count)
+            .map(|i| {
+                let x = -BARRIER_HALF_LENGTH + BARRIER_RADIUS + i as f64 * spacing;
+                (Vec2::new(x, 0.0), BARRIER_RADIUS)
+            })
+            .collect();
+        let collision = Collision::compound(circles);
+        let spatial_db_ref = SpatialDbRef {
+            spatial_id: SpatialId::new(),
+        };
+
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref,
+            collision,
+            rigid: Rigid::kinematic(0.8),
+            shape: Some(shape),
+            animation: None,
+            tint: None,
+            name: Some(Name::new("Barrier")),
+            air_suuply: None,
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: None,
+            kinematic: None,
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            flags: EntityFlags::default(),
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Barrier,
+            variant: 0,
+        }
+    }
+
+    fn new_dummy() -> Self {
+        GameObject {
+            transform: Transform::identity(),
+            prev_transform: Transform::identity(),
+            render_transform: Transform::identity(),
+            spatial_db_ref: SpatialDbRef {
+                spatial_id: SpatialId::new(),
+            },
+            collision: Collision::new(0.0),
+            rigid: Rigid::new(0.0, 0.0, 0.0, 0.0, 0.0, 1.0),
+            shape: None,
+            animation: None,
+            tint: None,
+            name: None,
+            air_suuply: None,
+            score: None,
+            salvage: None,
+            cargo: None,
+            pilot: None,
+            faction_ship: None,
+            hull: None,
+            mine: None,
+            kinematic: None,
+            rcs: None,
+            heat: None,
+            status: StatusEffects::default(),
+            // despawned, it stays put until the slot is used again
+            flags: EntityFlags::NO_INTERP,
+            attachment: None,
+            thrust_start: None,
+            turret: None,
+            projectile: None,
+            object_type: GameObjectType::Dummy,
+            variant: 0,
+        }
+    }
+
+    fn from_snapshot(resources: &Resources, snapshot: &EntitySnapshot) -> Self {
+        let mut object = match snapshot.object_type {
+            GameObjectType::Ship => match snapshot.faction {
+                // skill is set to the run's every tick
+                Some(faction) => {
+                    let skill = PilotSkill::for_level(SkillLevel::default());
+                    Self::new_faction_ship(resources, faction, skill)
+                }
+                None => {
+                    let mut ship = Self::new_ship(resources, 0, 0);
+                    ship.set_ship_class(ShipClass::from_variant(snapshot.variant));
+                    ship
+                }
+            },
+            GameObjectType::Asteroid => Self::new_asteroid_variant(resources, snapshot.variant),
+            GameObjectType::AidPod => {
+                Self::new_air_pod(resources, 0, 0, PodTier::from_variant(snapshot.variant))
+            }
+            GameObjectType::Derelict => Self::new_empty_derelict(resources),
+            GameObjectType::Station => Self::new_station(resources),
+            // the sweep is set up again by the world, which knows the tick
+            GameObjectType::Barrier => Self::new_barrier(resources),
+            GameObjectType::Mine => Self::new_mine(resources, snapshot.variant),
+            // the carrier isn't saved
+            GameObjectType::Boss => unreachable!("Boss in save"),
+            GameObjectType::Debris => unreachable!("Debris in save"),
+            GameObjectType::Scenery => unreachable!("Scenery in save"),
+            GameObjectType::Turret => unreachable!("Turret in save"),
+            GameObjectType::Projectile => unreachable!("Projectile in save"),
+            GameObjectType::Dummy => unreachable!("Dummy object in save"),
+        };
+
+        let transform = Transform::new(snapshot.pos, snapshot.rotation);
+        object.transform = transform;
+        object.prev_transform = transform;
+        object.render_transform = transform;
+        object.rigid.velocity = snapshot.velocity;
+        object.rigid.angular_velocity = snapshot.angular_velocity;
+        object.air_suuply = snapshot.air.map(|air| AirSupply { air });
+        object.score = snapshot.score.map(Score);
+        object.salvage = snapshot.loot.map(|loot| Salvage { loot, progress: 0 });
+        object.cargo = snapshot.cargo.clone();
+        if snapshot.disabled {
+            object.pilot = None;
+        }
+        if let Some(heat) = snapshot.heat {
+            object.heat = Some(Heat { heat });
+        }
+        object.status = snapshot.status.clone();
+        object
+    }
+
+    // Player's ship only: its mass and starting air for the class
+    // An air pod turning into another tier, keeping where it is in its pulse
+    fn set_pod_tier(&mut self, tier: PodTier) {
+        self.variant = tier.variant();
+        self.collision = Collision::new(air_pod_shape(0.0, tier).collision_radius());
+        if let Some(animation) = self.animation.as_mut() {
+            animation.clip = AnimationClip::AirPod(tier);
+        }
+    }
+
+    // How far the ship's magnets reach, None without any
+    fn magnet_radius(&self) -> Option<f64> {
+        let magnets = self.cargo.as_ref().map_or(0, |cargo| cargo.count(UpgradeModule::Magnet));
+        if magnets == 0 {
+            return None;
+        }
+        Some(MAGNET_RADIUS * (1.0 + 0.5 * (magnets.min(MAX_MAGNETS) - 1) as f64))
+    }
+
+    fn set_ship_class(&mut self, class: ShipClass) {
+        let old = ShipClass::from_variant(self.variant);
+        self.rigid.scale_mass(class.mass_factor() / old.mass_factor());
+        self.variant = class.variant();
+        if let Some(air) = self.air_suuply.as_mut() {
+            air.air = class.start_air_secs() * TICKS_PER_SECOND;
+        }
+    }
+
+    fn collect_loot(&mut self, loot: Loot) {
+        if let Some(score) = self.score.as_mut() {
+            score.0 += 500;
+        }
+        match loot {
+            Loot::Air(air) => {
+                if let Some(air_supply) = self.air_suuply.as_mut() {
+                    air_supply.air += air;
+                }
+            }
+            Loot::Ore(ore) => {
+                if let Some(cargo) = self.cargo.as_mut() {
+                    cargo.ore += ore;
+                }
+                if let Some(score) = self.score.as_mut() {
+                    score.0 += 100 * ore as u64;
+                }
+            }
+            Loot::Upgrade(module) => {
+                if let Some(cargo) = self.cargo.as_mut() {
+                    cargo.upgrades.push(module);
+                }
+            }
+        }
+    }
+
+    fn snapshot(&self) -> EntitySnapshot {
+        EntitySnapshot {
+            object_type: self.object_type,
+            variant: self.variant,
+            pos: self.transform.translation(),
+            rotation: self.transform.rotation(),
+            velocity: self.rigid.velocity,
+            angular_velocity: self.rigid.angular_velocity,
+            air: self.air_suuply.as_ref().map(|air| air.air),
+            score: self.score.map(|score| score.0),
+            loot: self.salvage.as_ref().map(|salvage| salvage.loot),
+            cargo: self.cargo.clone(),
+            faction: self.faction_ship.as_ref().map(|ship| ship.faction),
+            disabled: self.faction_ship.is_some() && self.pilot.is_none(),
+            heat: self.heat.as_ref().map(|heat| heat.heat),
+            status: self.status.clone(),
+            nav_path: self.faction_ship.as_ref().map(|ship| ship.nav_path.clone()),
+        }
+    }
+
+    fn pick_position(&mut self, rng: &mut RngStream, pos_range: Range<Vec2>) {
+        let pos = rng.next(pos_range);
+        self.teleport(pos);
+    }
+
+    // Move instantly to pos without interpolating from the old position
+    pub fn teleport(&mut self, pos: Vec2) {
+        self.transform.teleport(pos);
+        self.prev_transform.translation = pos;
+    }
+
+    // medium and large rubble, small pieces don't break up any further
+    fn is_breakable_rubble(&self) -> bool {
+        self.object_type == GameObjectType::Asteroid
+            && AsteroidMaterial::from_variant(self.variant) == AsteroidMaterial::Rubble
+            && self.variant % NUM_ASTEROID_SHAPES >= 2
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameObjectType {
+    Ship,
+    Asteroid,
+    AidPod,
+    Derelict,
+    Station,
+    Mine,
+    Boss,
+    Barrier,
+    Debris,
+    // only there to be looked at, see scenery.rs
+    Scenery,
+    // a gun mounted on a ship or station, the carrier's turrets are part of the boss
+    Turret,
+    Projectile,
+    Dummy,
+}
+
+//-------------------------------------------------------------------------
+// Flags that take an entity out of parts of the tick. RENDER_ONLY is for
+// scenery and guns: physics and the spatial db skip it altogether, and only
+// GameWorld::update_scenery or update_turrets moves it. NO_INTERP draws it where it is as of
+// the last tick instead of between ticks, for entities that don't move.
+//-------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EntityFlags(u8);
+
+impl EntityFlags {
+    pub const RENDER_ONLY: Self = Self(1);
+    pub const NO_INTERP: Self = Self(2);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+//-------------------------------------------------------------------------
+// What an asteroid is made of, picked by the seed when it's placed. Ice is
+// slippery and bouncy, iron heavy and dull, and rubble breaks up into small
+// pieces when hit hard. Most are plain rock.
+//-------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AsteroidMaterial {
+    Rock,
+    Ice,
+    Iron,
+    Rubble,
+}
+
+// in variant order
+pub const ASTEROID_MATERIALS: [AsteroidMaterial; 4] = [
+    AsteroidMaterial::Rock,
+    AsteroidMaterial::Ice,
+    AsteroidMaterial::Iron,
+    AsteroidMaterial::Rubble,
+];
+
+impl AsteroidMaterial {
+    // name used in the spawn tables
+    pub fn key(self) -> &'static str {
+        match self {
+            AsteroidMaterial::Rock => "rock",
+            AsteroidMaterial::Ice => "ice",
+            AsteroidMaterial::Iron => "iron",
+            AsteroidMaterial::Rubble => "rubble",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        ASTEROID_MATERIALS.iter().copied().find(|material| material.key() == key)
+    }
+
+    // Variant of an asteroid with the outline (0..NUM_ASTEROID_SHAPES) made of this
+    pub fn variant(self, shape: u32) -> u32 {
+        let index = ASTEROID_MATERIALS.iter().position(|&material| material == self).unwrap();
+        index as u32 * NUM_ASTEROID_SHAPES + shape
+    }
+
+    // saves from before materials only have rock
+    pub fn from_variant(variant: u32) -> Self {
+        ASTEROID_MATERIALS[(variant / NUM_ASTEROID_SHAPES) as usize % ASTEROID_MATERIALS.len()]
+    }
+
+    // of rock's
+    fn density_factor(self) -> f64 {
+        match self {
+            AsteroidMaterial::Rock => 1.0,
+            AsteroidMaterial::Ice => 0.6,
+            AsteroidMaterial::Iron => 2.5,
+            AsteroidMaterial::Rubble => 0.8,
+        }
+    }
+
+    fn restitution(self) -> f64 {
+        match self {
+            AsteroidMaterial::Rock => 1.01,
+            AsteroidMaterial::Ice => 1.1,
+            AsteroidMaterial::Iron => 0.6,
+            AsteroidMaterial::Rubble => 0.4,
+        }
+    }
+
+    // of the tuning's friction coefficient
+    fn friction(self) -> f64 {
+        match self {
+            AsteroidMaterial::Rock => 1.0,
+            AsteroidMaterial::Ice => 0.1,
+            AsteroidMaterial::Iron => 1.0,
+            AsteroidMaterial::Rubble => 1.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Score(pub u64);
+
+//-------------------------------------------------------------------------
+// Optional debug name for an entity, shown by the debug labels.
+//-------------------------------------------------------------------------
+#[derive(Clone, Debug)]
+pub struct Name(pub String);
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        Name(name.to_string())
+    }
+}
+
+//-------------------------------------------------------------------------
+// Salvage component for derelicts. The loot goes to the player after
+// holding position next to the derelict for a few seconds, any collision
+// starts the salvage over.
+//-------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Loot {
+    Air(u64),
+    Ore(u32),
+    Upgrade(UpgradeModule),
+}
+
+// What a derelict holds, before the amount is picked
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LootKind {
+    Air,
+    Ore,
+    Upgrade(UpgradeModule),
+}
+
+impl LootKind {
+    // name used in the spawn tables
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "air" => Some(LootKind::Air),
+            "ore" => Some(LootKind::Ore),
+            module => save::module_from_name(module).map(LootKind::Upgrade),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpgradeModule {
+    // more thrust
+    Thrusters,
+    // less air used
+    AirRecycler,
+    // pulls air pods in
+    Magnet,
+}
+
+impl UpgradeModule {
+    pub fn name(self) -> &'static str {
+        match self {
+            UpgradeModule::Thrusters => "Thrusters",
+            UpgradeModule::AirRecycler => "Air Recycler",
+            UpgradeModule::Magnet => "Magnet",
+        }
+    }
+}
+
+pub struct Salvage {
+    pub loot: Loot,
+    // ticks spent salvaging so far
+    pub progress: u32,
+}
+
+//-------------------------------------------------------------------------
+// Cargo component: what the ship has salvaged so far.
+//-------------------------------------------------------------------------
+#[derive(Clone, Debug, Default)]
+pub struct Cargo {
+    pub ore: u32,
+    pub upgrades: Vec<UpgradeModule>,
+}
+
+impl Cargo {
+    pub fn count(&self, module: UpgradeModule) -> usize {
+        self.upgrades.iter().filter(|upgrade| **upgrade == module).count()
+    }
+
+    pub fn mass(&self) -> f64 {
+        self.ore as f64 * ORE_MASS
+    }
+}
+
+// --- MARK: EntityStore ---
+
+//-------------------------------------------------------------------------
+// EntityStore for GameObject that includes all our components.
+// EntityStore and GameObject could be replaced with a  generic entity
+// component system like HECS.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EntityId(usize);
+
+pub struct EntityStore {
+    entities: Vec<GameObject>,
+}
+
+impl EntityStore {
+    pub fn new() -> Self {
+        EntityStore {
+            entities: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, id: EntityId) -> &GameObject {
+        &self.entities[id.0]
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> &mut GameObject {
+        &mut self.entities[id.0]
+    }
+
+    pub fn get_mut_pair(
+        &mut self,
+        id1: EntityId,
+        id2: EntityId,
+    ) -> (&mut GameObject, &mut GameObject) {
+        if id1.0 < id2.0 {
+            let (split1, split2) = self.entities.split_at_mut(id2.0);
+            (&mut split1[id1.0], &mut split2[0])
+        } else if id1.0 > id2.0 {
+            let (split1, split2) = self.entities.split_at_mut(id1.0);
+            (&mut split2[0], &mut split1[id2.0])
+        } else {
+            panic!("Cannot get pair of same id");
+        }
+    }
+
+    // Always in a new slot, the ones of despawned objects stay empty (see
+    // GameWorld::despawn), so everything stays in the order it was made
+    pub fn insert(&mut self, object: GameObject) -> EntityId {
+        let id = EntityId(self.entities.len());
+        self.entities.push(object);
+        id
+    }
+
+    // pub fn iter_entity(&self) -> impl Iterator<Item = (EntityId, &GameObject)> {
+    //     self.entities.iter().enumerate().map(|(idx, obj)| (EntityId(idx), obj))
+    // }
+
+    pub fn iter_mut_entity(&mut self) -> impl Iterator<Item = (EntityId, &mut GameObject)> {
+        self.entities
+            .iter_mut()
+            .enumerate()
+            .map(|(idx, obj)| (EntityId(idx), obj))
+    }
+}
+
+// --- MARK: Shape ---
+
+//-------------------------------------------------------------------------
+// Shape component for rendering a static shape. The radius reaches the
+// furthest point drawn, the collision radius is the circle that stands in
+// for the shape in physics. For spiky and lopsided shapes that's fitted to
+// the outline (see game_shapes.rs) and smaller, so things don't bump into
+// each other before they visibly touch.
+//-------------------------------------------------------------------------
+#[derive(Clone)]
+pub struct Shape {
+    scene: Arc<Scene>,
+    radius: f64,
+    collision_radius: f64,
+    // of the outline, for shapes that have one to weigh bodies by
+    area: Option<ShapeArea>,
+}
+
+impl Shape {
+    pub fn new(scene: Arc<Scene>, radius: f64) -> Self {
+        Shape {
+            scene,
+            radius,
+            collision_radius: radius,
+            area: None,
+        }
+    }
+
+    pub fn with_collision_radius(mut self, collision_radius: f64) -> Self {
+        self.collision_radius = collision_radius;
+        self
+    }
+
+    pub fn with_area(mut self, area: ShapeArea) -> Self {
+        self.area = Some(area);
+        self
+    }
+
+    pub fn area(&self) -> Option<ShapeArea> {
+        self.area
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    pub fn collision_radius(&self) -> f64 {
+        self.collision_radius
+    }
+}
+
+// Area and second moment of area (about the origin) of a filled shape. With a
+// density these give its mass and rotational inertia.
+#[derive(Clone, Copy, Debug)]
+pub struct ShapeArea {
+    pub area: f64,
+    pub second_moment: f64,
+}
+
+impl ShapeArea {
+    pub fn disk(radius: f64) -> Self {
+        Self {
+            area: PI * radius * radius,
+            second_moment: 0.5 * PI * radius.powi(4),
+        }
+    }
+
+    // of the disk with the same area
+    pub fn disk_radius(&self) -> f64 {
+        (self.area / PI).sqrt()
+    }
+}
+
+// --- MARK: Animation ---
+
+//-------------------------------------------------------------------------
+// Animation component for rendering an animated shape. Plays a clip (see
+// game_shapes.rs), looping or once. A one-shot clip takes the component
+// away when it's done and then runs its finish callback, if it has one,
+// e.g. GameWorld::despawn for effects that are only there to be seen.
+//-------------------------------------------------------------------------
+pub struct Animation {
+    pub clip: AnimationClip,
+    // virtual time in seconds, so animations stop while paused and follow the
+    // tick rate and replays
+    pub start_time: f64,
+    // 1 plays the clip as made, 2 twice as fast
+    pub speed: f64,
+    pub looping: bool,
+    pub on_finish: Option<fn(&mut GameWorld, EntityId)>,
+}
+
+impl Animation {
+    pub fn looping(clip: AnimationClip, start_time: f64) -> Self {
+        Self {
+            clip,
+            start_time,
+            speed: 1.0,
+            looping: true,
+            on_finish: None,
+        }
+    }
+
+    pub fn once(clip: AnimationClip, start_time: f64) -> Self {
+        Self {
+            looping: false,
+            ..Self::looping(clip, start_time)
+        }
+    }
+
+    pub fn with_speed(self, speed: f64) -> Self {
+        Self { speed, ..self }
+    }
+
+    // Seconds into the clip at a virtual time
+    pub fn clip_time(&self, time: f64) -> f64 {
+        let t = (time - self.start_time).max(0.0) * self.speed;
+        match self.clip.length() {
+            Some(length) if self.looping => t % length,
+            Some(length) => t.min(length),
+            None => t,
+        }
+    }
+
+    fn is_finished(&self, time: f64) -> bool {
+        let t = (time - self.start_time) * self.speed;
+        !self.looping && self.clip.length().is_some_and(|length| t >= length)
+    }
+}
+
+//-------------------------------------------------------------------------
+// Tint component changes how an entity's shape is drawn, for a number of
+// ticks (e.g., flashing when hit hard) or until it's taken away: its color
+// replaced, see-through, or blinking on and off.
+//-------------------------------------------------------------------------
+#[derive(Clone, Copy)]
+pub struct Tint {
+    pub color: Option<xilem::Color>,
+    // 1 is opaque
+    pub alpha: f64,
+    // times a second it blinks, 0 for steady
+    pub blink_rate: f64,
+    // None for no end
+    pub ticks: Option<u32>,
+}
+
+impl Tint {
+    pub fn flash(color: xilem::Color, ticks: u32) -> Self {
+        Tint {
+            color: Some(color),
+            alpha: 1.0,
+            blink_rate: 0.0,
+            ticks: Some(ticks),
+        }
+    }
+
+    pub fn blink(rate: f64) -> Self {
+        Tint {
+            color: None,
+            alpha: 1.0,
+            blink_rate: rate,
+            ticks: None,
+        }
+    }
+
+    // Alpha to draw with at a virtual time, 0 while blinked off
+    pub fn alpha_at(&self, time: f64) -> f64 {
+        let off = self.blink_rate > 0.0 && (time * self.blink_rate).fract() >= 0.5;
+        if off {
+            0.0
+        } else {
+            self.alpha
+        }
+    }
+}
+
+//-------------------------------------------------------------------------
+// Game component for tracking air supply. Air pod and ship have this
+// component. Every tick one unit of air is lost. Ship picking up air
+// pod adds remaining air in pod to ship's supply.
+//-------------------------------------------------------------------------
+pub struct AirSupply {
+    pub air: u64,
+}
+
+// Damage an entity can take before it's destroyed
+pub struct Hull {
+    pub health: f64,
+    pub max: f64,
+}
+
+pub struct Mine {
+    pub armed: bool,
+}
+
+// Script for a kinematic body. Its velocity is set from this every tick so it
+// ends up where the script says, whatever it ran into.
+#[derive(Clone, Copy, Debug)]
+pub enum Kinematic {
+    // turn in place (radians/tick)
+    Spin { rate: f64 },
+    // swing between center - reach and center + reach, once there and back per period
+    Sweep {
+        center: Vec2,
+        reach: Vec2,
+        period: u32,
+    },
+}
+
+impl Kinematic {
+    // Where the script puts the body at a tick, None if it stays put
+    pub fn position_at(&self, tick: u32) -> Option<Vec2> {
+        match self {
+            Kinematic::Spin { .. } => None,
+            Kinematic::Sweep {
+                center,
+                reach,
+                period,
+            } => {
+                let phase = TAU * (tick % period) as f64 / *period as f64;
+                Some(*center + phase.sin() * *reach)
+            }
+        }
+    }
 
-            // use distance of pod from ship and max speed ship can travel to determine air supply
-            let dist = (air.transform.translation() - ship_loc.unwrap()).length();
-            let time = dist / MAX_SHIP_SPEED; // speed is measured in units/tick (TODO: convert to time)
-            let mult = 4.0;
-            air.air_suuply = Some(AirSupply {
-                air: (mult * time) as u64,
-            });
+    pub fn angular_velocity(&self) -> f64 {
+        match self {
+            Kinematic::Spin { rate } => *rate,
+            Kinematic::Sweep { .. } => 0.0,
         }
     }
+}
 
-    fn check_air(&mut self) {
-        for obj in &mut self.entity_store.entities {
-            if let Some(air) = obj.air_suuply.as_mut() {
-                air.air = air.air.saturating_sub(1);
-            }
+//-------------------------------------------------------------------------
+// Heat component: engines warm up while firing and cool down while off.
+// When they get too hot the ship is Overheated (see status.rs) and they shut
+// down until they've cooled off.
+//-------------------------------------------------------------------------
+#[derive(Clone, Debug, Default)]
+pub struct Heat {
+    // 0..1
+    pub heat: f64,
+}
+
+impl Heat {
+    fn update(&mut self, controls: &ShipControls) {
+        let rcs = controls.brake || controls.strafe != 0.0;
+        if controls.thrust || rcs {
+            self.heat += if controls.thrust { HEAT_THRUST } else { 0.0 };
+            self.heat += if rcs { HEAT_RCS } else { 0.0 };
+        } else {
+            self.heat -= HEAT_COOLING;
         }
+        self.heat = self.heat.clamp(0.0, 1.0);
     }
-    fn flip_transforms(&mut self) {
-        for entity in &mut self.entity_store.entities {
-            entity.prev_transform = entity.transform.clone();
+
+    // Color glowing through the hull, pulsing while overheated
+    pub fn glow(&self, overheated: bool, time_secs: f64) -> Option<xilem::Color> {
+        let mut glow = (self.heat - HEAT_GLOW) / (1.0 - HEAT_GLOW);
+        if overheated {
+            glow = glow.max(0.3) * (0.75 + 0.25 * (TAU * 2.0 * time_secs).sin());
         }
+        (glow > 0.0).then(|| HEAT_COLOR.with_alpha_factor((0.7 * glow) as f32))
     }
+}
 
-    pub fn interpolate_transforms(&mut self) {
-        let interp = self.get_interp();
-        for entity in &mut self.entity_store.entities {
-            entity.render_transform.translation = entity
-                .prev_transform
-                .translation
-                .lerp(entity.transform.translation, interp);
-            let delta_rot = entity.transform.rotation - entity.prev_transform.rotation;
-            let delta_rot = if delta_rot > PI {
-                delta_rot - TAU
-            } else if delta_rot < -PI {
-                delta_rot + TAU
-            } else {
-                delta_rot
-            };
-            entity.render_transform.rotation = entity.prev_transform.rotation + interp * delta_rot;
+//-------------------------------------------------------------------------
+// Rcs component: the small maneuvering thrusters of a ship, for braking and
+// strafing. Keeps which ones fired this tick, for their flames.
+//-------------------------------------------------------------------------
+#[derive(Clone, Debug, Default)]
+pub struct Rcs {
+    pub brake: bool,
+    // -1 left, 1 right, 0 not strafing
+    pub strafe: f64,
+    // virtual time in seconds they started firing, for the flame animation
+    pub start_time: Option<f64>,
+}
+
+impl Rcs {
+    fn fire(&mut self, brake: bool, strafe: f64, time: f64) {
+        self.brake = brake;
+        self.strafe = strafe;
+        if !self.is_firing() {
+            self.start_time = None;
+        } else if self.start_time.is_none() {
+            self.start_time = Some(time);
         }
     }
 
-    fn update_time(&mut self) -> u32 {
-        let now = Instant::now();
-        let elapsed = now - self.last_time;
-        self.last_time = now;
+    pub fn is_firing(&self) -> bool {
+        self.brake || self.strafe != 0.0
+    }
+}
 
-        let elapsed = elapsed.as_micros();
+// --- MARK: Collision ---
 
-        self.virtual_time += elapsed;
-        let tick = (self.virtual_time / MICROS_PER_TICK as u128) as u32;
+//-------------------------------------------------------------------------
+// Simple collision component -- everything is a circle, or a compound of
+// offset circles on one rigid body for bigger or oddly shaped things. The
+// broadphase only sees the bounding radius, the narrowphase checks each
+// circle.
+//-------------------------------------------------------------------------
+pub enum Collision {
+    Circle {
+        radius: f64,
+    },
+    Compound {
+        // (offset in the body's frame, radius)
+        circles: Vec<(Vec2, f64)>,
+        bounding_radius: f64,
+    },
+}
 
-        let num_tick = tick - self.last_tick;
-        self.last_tick = tick;
+impl Collision {
+    pub fn new(radius: f64) -> Self {
+        Collision::Circle { radius }
+    }
 
-        // This is a bit awkward doing this here (and storing as bool) but we don't pass mutable self to render
-        // so this is most convenient
-        self.render_ready =
-            self.last_render.elapsed().as_micros() as u64 > MICROS_PER_SECOND / TARGET_FPS;
-        // HACK: turn off frame rate cap for now since it seems to cause backoff stragegy for some event loops.
-        self.render_ready = true;
-        if self.render_ready {
-            self.last_render = now;
+    pub fn compound(circles: Vec<(Vec2, f64)>) -> Self {
+        let bounding_radius = circles
+            .iter()
+            .map(|(offset, radius)| offset.length() + radius)
+            .fold(0.0, f64::max);
+        Collision::Compound {
+            circles,
+            bounding_radius,
         }
+    }
 
-        num_tick
+    // bounding radius for compounds
+    pub fn radius(&self) -> f64 {
+        match self {
+            Collision::Circle { radius } => *radius,
+            Collision::Compound {
+                bounding_radius, ..
+            } => *bounding_radius,
+        }
     }
 
-    pub fn get_interp(&self) -> f64 {
-        let interp = self.virtual_time % MICROS_PER_TICK as u128;
-        let interp = interp as f64 / MICROS_PER_TICK as f64;
-        interp
+    // (center, radius) of each circle in world space
+    pub fn world_circles<'a>(
+        &'a self,
+        transform: &'a Transform,
+    ) -> impl Iterator<Item = (Vec2, f64)> + 'a {
+        let (single, circles) = match self {
+            Collision::Circle { radius } => (Some((transform.translation(), *radius)), &[][..]),
+            Collision::Compound { circles, .. } => (None, circles.as_slice()),
+        };
+        single.into_iter().chain(
+            circles
+                .iter()
+                .map(|(offset, radius)| (transform.local_to_world(*offset), *radius)),
+        )
     }
+}
 
-    pub fn update(&mut self) {
-        let num_tick = self.update_time();
+// Circles around the station's ring and on the ends of its arms, the inside
+// of the ring can't be reached anyway
+fn station_circles() -> Vec<(Vec2, f64)> {
+    let ring = (0..12).map(|i| (80.0 * Vec2::from_angle(i as f64 * TAU / 12.0), 22.0));
+    let arms = (0..4).map(|i| (100.0 * Vec2::from_angle(i as f64 * 0.5 * PI), 14.0));
+    ring.chain(arms).collect()
+}
 
-        // Set exit on make or break event just for code coverage
-        let esc = PhysicalKey::Code(KeyCode::Escape);
-        if self.input_manager.is_break(esc) || self.input_manager.is_make(esc) {
-            self.exit_ready = true;
-        }
+#[derive(Debug)]
+pub struct Contact {
+    id1: Option<EntityId>,
+    id2: Option<EntityId>,
+    pos: Vec2,
+    normal1: Vec2,
+    // normal2 is -normal1
+    depth: f64,
+}
 
-        for _ in 0..num_tick {
-            self.flip_transforms();
-            self.update_player_controls();
-            self.apply_physics();
+// --- MARK: Invariants ---
 
-            let mut contacts = Vec::new();
-            self.detect_collisions(&mut contacts);
-            self.resolve_collisions(&mut contacts);
+//-------------------------------------------------------------------------
+// Checks run every tick with --check-invariants, for chasing physics bugs:
+// no NaN or infinite transforms or velocities, nothing that the walls push
+// on well outside the border, the spatial db listing each entity once and
+// near where it is, and each pair of bodies touching found from one side
+// only (from both, it would be pushed apart twice). They run between
+// finding the tick's contacts and resolving them. When one fails, the
+// world is dumped as a save next to the crash reports and the game panics,
+// so the crash report has the inputs leading up to it.
+//-------------------------------------------------------------------------
 
-            self.check_air();
+// how far past the border something can get before it counts as escaped
+const INVARIANT_BORDER_SLACK: f64 = 200.0;
 
-            // this goes here, so if more than one tick processed the make/break
-            // events won't be processed more than once
-            self.input_manager.clear_events();
-        }
+impl GameWorld {
+    pub fn enable_invariant_checks(&mut self) {
+        self.check_invariants = true;
     }
 
-    fn render_game_state(&self, scene: &mut Scene, ctx: &mut PaintCtx, size: Size) {
-        let min_dim = size.width.min(size.height);
-        let margin = 0.05 * min_dim;
-
-        let Some(player) = self
-            .get_control_object()
-            .map(|id| self.get_entities().get(id))
-        else {
-            // no player no game state
+    fn assert_invariants(&self, contacts: &[Contact]) {
+        let violations = self.find_invariant_violations(contacts);
+        if violations.is_empty() {
             return;
-        };
+        }
+        for violation in &violations {
+            log::error!("invariant_broken tick={} {}", self.tick_count, violation);
+        }
 
-        let score = format!("Score: {}", player.score.map(|score| score.0).unwrap_or(0));
-        let air = format!(
-            "Air: {:.1} seconds",
-            player.air_suuply.as_ref().map_or(0, |air| air.air) as f32 / TICKS_PER_SECOND as f32
-        );
-        let txt = format!("{}\n{}", score, air);
+        let dir = self.profile.path(CRASHES_DIR);
+        let path = dir.join(format!("invariants_tick{}.save", self.tick_count));
+        let txt = self.snapshot().serialize();
+        match std::fs::create_dir_all(&dir).and_then(|()| std::fs::write(&path, txt)) {
+            Ok(()) => log::info!("invariants_dumped path={}", path.display()),
+            Err(err) => log::error!("Failed to dump world to {}: {}", path.display(), err),
+        }
+        panic!("Simulation invariant broken at tick {}: {}", self.tick_count, violations[0]);
+    }
 
-        let fill_color = xilem::Color::rgb8(0xff, 0xff, 0xff);
+    fn find_invariant_violations(&self, contacts: &[Contact]) -> Vec<String> {
+        let mut violations = Vec::new();
+        let tick = self.tick_count as f64;
 
-        // To render text, we first create a LayoutBuilder and set the text properties.
-        let mut lcx = masonry::parley::LayoutContext::new();
-        let mut text_layout_builder = lcx.ranged_builder(ctx.text_contexts().0, &txt, 1.0);
+        for (idx, entity) in self.entity_store.entities.iter().enumerate() {
+            if entity.object_type == GameObjectType::Dummy {
+                continue;
+            }
+            let pos = entity.transform.translation();
+            let vel = entity.rigid.velocity;
+            let rotation = entity.transform.rotation();
+            let values = [pos.x, pos.y, rotation, vel.x, vel.y, entity.rigid.angular_velocity];
+            if values.iter().any(|value| !value.is_finite()) {
+                violations.push(format!(
+                    "entity {} ({:?}) isn't finite: pos={:?} rotation={} vel={:?}",
+                    idx, entity.object_type, pos, rotation, vel
+                ));
+                continue;
+            }
+            if entity.rigid.is_kinematic() || entity.flags.contains(EntityFlags::RENDER_ONLY) {
+                // scripted bodies keep themselves inside, the walls don't push on them
+                // or on scenery
+                continue;
+            }
+            let mut outside = 0.0f64;
+            self.arena.wall_contacts(pos, 0.0, tick, &mut |_, _, depth| outside = outside.max(depth));
+            if outside > INVARIANT_BORDER_SLACK {
+                violations.push(format!(
+                    "entity {} ({:?}) is {:.1} outside the border at {:?}",
+                    idx, entity.object_type, outside, pos
+                ));
+            }
+        }
 
-        text_layout_builder.push_default(&StyleProperty::FontStack(FontStack::Single(
-            FontFamily::Generic(parley::style::GenericFamily::Serif),
-        )));
-        text_layout_builder.push_default(&StyleProperty::FontSize(24.0));
-        text_layout_builder.push_default(&StyleProperty::Brush(
-            vello::peniko::Brush::Solid(fill_color).into(),
-        ));
+        self.spatial_db.check_refs(&self.entity_store, &mut violations);
+
+        let pairs: HashSet<(usize, usize)> = contacts
+            .iter()
+            .filter_map(|contact| Some((contact.id1?.0, contact.id2?.0)))
+            .collect();
+        for &(id1, id2) in &pairs {
+            if id1 == id2 {
+                violations.push(format!("entity {} is touching itself", id1));
+            } else if id1 < id2 && pairs.contains(&(id2, id1)) {
+                violations.push(format!("contact between {} and {} was found from both sides", id1, id2));
+            }
+        }
+        for contact in contacts {
+            let normal_ok = (contact.normal1.length() - 1.0).abs() < 1e-6;
+            if !normal_ok || !contact.depth.is_finite() || contact.depth < 0.0 {
+                violations.push(format!(
+                    "contact between {:?} and {:?} has normal {:?} and depth {}",
+                    contact.id1, contact.id2, contact.normal1, contact.depth
+                ));
+            }
+        }
+        violations
+    }
+}
 
-        let mut text_layout = text_layout_builder.build();
-        text_layout.break_all_lines(None, xilem::TextAlignment::Start);
+// --- MARK: Camera ---
 
-        let mut scratch_scene = Scene::new();
-        // We can pass a transform matrix to rotate the text we render
-        masonry::text_helpers::render_text(
-            scene,
-            &mut scratch_scene,
-            Affine::translate(Vec2::new(margin, margin)),
-            &text_layout,
-        );
+//-------------------------------------------------------------------------
+// Camera follows the control object. Kicks offset the camera briefly
+// (e.g., when hit) and settle back over a few ticks.
+//-------------------------------------------------------------------------
 
-        if player.air_suuply.as_ref().map(|air| air.air).unwrap_or(0) == 0 {
-            // Game Over
-            let txt = "    GAME OVER\nYou are out of air!";
-            let fill_color = xilem::Color::rgb8(0xff, 0x00, 0x00);
+pub struct Camera {
+    kick: Vec2,
+}
 
-            let mut lcx = masonry::parley::LayoutContext::new();
-            let mut text_layout_builder = lcx.ranged_builder(ctx.text_contexts().0, &txt, 1.0);
+impl Camera {
+    pub fn new() -> Self {
+        Self { kick: Vec2::ZERO }
+    }
 
-            text_layout_builder.push_default(&StyleProperty::FontStack(FontStack::Single(
-                FontFamily::Generic(parley::style::GenericFamily::Serif),
-            )));
-            text_layout_builder.push_default(&StyleProperty::FontSize(48.0));
-            text_layout_builder.push_default(&StyleProperty::Brush(
-                vello::peniko::Brush::Solid(fill_color).into(),
-            ));
+    pub fn kick(&mut self, offset: Vec2) {
+        self.kick += offset;
+    }
 
-            let mut text_layout = text_layout_builder.build();
-            text_layout.break_all_lines(None, xilem::TextAlignment::Middle);
-            let w = text_layout.width();
-            let h = text_layout.height();
+    pub fn get_offset(&self) -> Vec2 {
+        self.kick
+    }
 
-            let mut scratch_scene = Scene::new();
-            // We can pass a transform matrix to rotate the text we render
-            masonry::text_helpers::render_text(
-                scene,
-                &mut scratch_scene,
-                Affine::translate(Vec2::new(
-                    0.5 * (size.width - w as f64),
-                    0.5 * (size.height - h as f64),
-                )),
-                &text_layout,
-            );
+    fn update(&mut self) {
+        self.kick *= 0.7;
+        if self.kick.length_squared() < 0.01 {
+            self.kick = Vec2::ZERO;
         }
     }
+}
 
-    fn render_mini_map(&self, scene: &mut Scene, size: Size, cam_pos: Vec2) {
-        let min_dim = size.width.min(size.height);
+// --- MARK: MiniMap ---
+
+//-------------------------------------------------------------------------
+// Where the minimap is on screen and how it maps to the world. Shared by
+// rendering and pointer hit-testing.
+//-------------------------------------------------------------------------
+
+struct MiniMapLayout {
+    center: Point,
+    radius: f64,
+    // map units per world unit
+    scale: f64,
+}
+
+impl MiniMapLayout {
+    fn new(screen_size: Size) -> Self {
+        let min_dim = screen_size.width.min(screen_size.height);
         let map_size = 0.25 * min_dim;
-        let map_radius = 0.5 * map_size;
+        let radius = 0.5 * map_size;
         let margin = 0.05 * min_dim;
 
-        let render_radius = 4000.0;
-        let map_scale = map_size / render_radius;
+        // mini-map is in top right corner, with margin
+        Self {
+            center: Point::new(screen_size.width - radius - margin, radius + margin),
+            radius,
+            scale: radius / MINI_MAP_WORLD_RADIUS,
+        }
+    }
 
-        // render mini-map in top right corner, with margin
-        let map_center = masonry::Point::new(size.width - map_radius - margin, map_radius + margin);
-        let world_to_map = Affine::translate(-cam_pos)
-            .then_scale(map_scale)
-            .then_translate(map_center.to_vec2());
+    fn world_to_map(&self, cam_pos: Vec2) -> Affine {
+        Affine::translate(-cam_pos)
+            .then_scale(self.scale)
+            .then_translate(self.center.to_vec2())
+    }
 
-        scene.push_layer(
-            vello::peniko::BlendMode::default(),
-            1.0,
-            Affine::IDENTITY,
-            &vello::kurbo::Circle::new(map_center, map_radius),
-        );
+    fn map_to_world(&self, pos: Point, cam_pos: Vec2) -> Vec2 {
+        cam_pos + (pos - self.center) / self.scale
+    }
+}
 
-        scene.fill(
-            vello::peniko::Fill::NonZero,
-            Affine::IDENTITY,
-            xilem::Color::rgb8(0, 0, 0),
-            None,
-            &vello::kurbo::Circle::new(map_center, map_radius),
-        );
+// --- MARK: StarMap ---
 
-        // compute oscillation for air animation, TODO: oscillate in sync with animation, make rate a function of air left
-        let t = self.virtual_time as f64 / MICROS_PER_SECOND as f64;
-        let rate = 4.0;
-        let oscillation = ((t % (1.0 / rate)) - 0.5 / rate).abs() * 2.0 * rate;
+//-------------------------------------------------------------------------
+// Full screen map of the whole world, opened and closed with Tab. It pauses
+// the game like photo mode. Arrows/WASD pan, +/- (or the scroll wheel) zoom,
+// and clicking anywhere places the waypoint, right clicking clears it.
+//-------------------------------------------------------------------------
 
-        for entity in &self.entity_store.entities {
-            let color = match entity.object_type {
-                GameObjectType::Ship => xilem::Color::rgb8(0xff, 0xff, 0xff),
-                GameObjectType::Asteroid => xilem::Color::rgb8(0x7f, 0x7f, 0x7f),
-                GameObjectType::AidPod => xilem::Color::rgb8(0x0, 0xb4, 0xd8),
-                GameObjectType::Dummy => unreachable!("Dummy object in render"),
-            };
-            let radius_scale = match entity.object_type {
-                GameObjectType::Ship => 2.0,
-                GameObjectType::Asteroid => 1.0,
-                GameObjectType::AidPod => 2.0 * (0.1 + 0.9 * oscillation),
-                GameObjectType::Dummy => unreachable!("Dummy object in render"),
-            };
-            let radius = radius_scale * entity.collision.radius();
+// world units per second at the fitted zoom
+const STAR_MAP_PAN_SPEED: f64 = 4000.0;
+const STAR_MAP_ZOOM_SPEED: f64 = 1.5;
+// zoom per line scrolled
+const STAR_MAP_SCROLL_ZOOM: f64 = 1.1;
+// 1 fits the whole world on screen
+const STAR_MAP_ZOOM_RANGE: Range<f64> = 1.0..16.0;
+
+struct StarMap {
+    // world position in the middle of the screen
+    center: Vec2,
+    zoom: f64,
+}
 
-            let pos = world_to_map * entity.render_transform.translation().to_point();
+impl StarMap {
+    fn new(center: Vec2) -> Self {
+        Self { center, zoom: 1.0 }
+    }
 
-            let dist = pos.distance(map_center);
-            if dist - map_scale * radius > map_radius
-                && entity.object_type != GameObjectType::AidPod
-            {
-                // object is off screen, don't render
-                continue;
-            }
+    fn update(&mut self, input: &InputManager, dt: f64, extent: f64) {
+        let down = |keys: &[KeyCode]| keys.iter().any(|key| input.is_down(PhysicalKey::Code(*key)));
 
-            let pos = if dist - map_scale * radius > map_radius {
-                // this is only for air object
-                let dir = (pos - map_center).normalize();
-                map_center + map_radius * dir
-            } else {
-                pos
-            };
+        let mut pan = Vec2::ZERO;
+        if down(&[KeyCode::ArrowLeft, KeyCode::KeyA]) {
+            pan.x -= 1.0;
+        }
+        if down(&[KeyCode::ArrowRight, KeyCode::KeyD]) {
+            pan.x += 1.0;
+        }
+        if down(&[KeyCode::ArrowUp, KeyCode::KeyW]) {
+            pan.y -= 1.0;
+        }
+        if down(&[KeyCode::ArrowDown, KeyCode::KeyS]) {
+            pan.y += 1.0;
+        }
+        self.center += pan * STAR_MAP_PAN_SPEED * dt / self.zoom;
+        self.center = Vec2::new(self.center.x.clamp(-extent, extent), self.center.y.clamp(-extent, extent));
 
-            if let Some(shape) = entity.shape.as_ref() {
-                // render asteroid or ship
-                let transform = Affine::rotate(entity.transform.rotation)
-                    .then_scale(map_scale * radius_scale)
-                    .then_translate(pos.to_vec2());
-                scene.append(shape.scene(), Some(transform));
-            } else {
-                // render flashing blue dot for air
-                scene.fill(
-                    vello::peniko::Fill::NonZero,
-                    Affine::translate(pos.to_vec2()),
-                    color,
-                    None,
-                    &vello::kurbo::Circle::new((0.0, 0.0), map_scale * radius),
-                );
-            }
+        if down(&[KeyCode::Equal, KeyCode::NumpadAdd]) {
+            self.zoom *= (STAR_MAP_ZOOM_SPEED * dt).exp();
+        }
+        if down(&[KeyCode::Minus, KeyCode::NumpadSubtract]) {
+            self.zoom *= (-STAR_MAP_ZOOM_SPEED * dt).exp();
+        }
+        self.zoom *= STAR_MAP_SCROLL_ZOOM.powf(input.get_scroll().y);
+        self.zoom = self.zoom.clamp(STAR_MAP_ZOOM_RANGE.start, STAR_MAP_ZOOM_RANGE.end);
+    }
+}
+
+// Where the star map is on screen, shared by rendering and pointer hit-testing
+struct StarMapLayout {
+    screen_center: Point,
+    center: Vec2,
+    // map units per world unit
+    scale: f64,
+}
+
+impl StarMapLayout {
+    fn new(screen_size: Size, extent: f64, star_map: &StarMap) -> Self {
+        let min_dim = screen_size.width.min(screen_size.height);
+        Self {
+            screen_center: Point::new(0.5 * screen_size.width, 0.5 * screen_size.height),
+            center: star_map.center,
+            // zoomed all the way out the world fits with a margin
+            scale: star_map.zoom * 0.45 * min_dim / extent,
+        }
+    }
+
+    fn world_to_map(&self) -> Affine {
+        Affine::translate(-self.center)
+            .then_scale(self.scale)
+            .then_translate(self.screen_center.to_vec2())
+    }
+
+    fn map_to_world(&self, pos: Point) -> Vec2 {
+        self.center + (pos - self.screen_center) / self.scale
+    }
+}
+
+// --- MARK: GameMode ---
+
+//-------------------------------------------------------------------------
+// Game modes. Endless is the plain game with its milestone timeline, the
+// others add an objective on top of the same world.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameMode {
+    Endless,
+    Escort,
+    Race,
+    Tutorial,
+}
+
+impl GameMode {
+    // name used in replays
+    pub fn key(self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::Escort => "escort",
+            GameMode::Race => "race",
+            GameMode::Tutorial => "tutorial",
         }
+    }
 
-        scene.append(
-            self.get_resources().border_shape.scene(),
-            Some(world_to_map),
-        );
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            // before the timeline, endless was called survival
+            "endless" | "survival" => Some(GameMode::Endless),
+            "escort" => Some(GameMode::Escort),
+            "race" => Some(GameMode::Race),
+            "tutorial" => Some(GameMode::Tutorial),
+            _ => None,
+        }
+    }
+}
 
-        scene.pop_layer();
+// An asteroid going by the player's ship close and fast, turns into a near
+// miss when it's gone by without touching
+#[derive(Clone, Copy, Debug)]
+struct NearPass {
+    asteroid: EntityId,
+    touched: bool,
+    // closest it has come, surface to surface
+    gap: f64,
+}
 
-        scene.stroke(
-            &vello::kurbo::Stroke::new(4.0),
-            Affine::IDENTITY,
-            xilem::Color::rgb8(0xff, 0xff, 0xff),
-            None,
-            &vello::kurbo::Circle::new(map_center, 0.5 * map_size),
-        );
+// Distance from the origin to the closest point on the segment from start to end
+fn closest_approach(start: Vec2, end: Vec2) -> f64 {
+    let along = end - start;
+    let length2 = along.hypot2();
+    if length2 <= 0.0 {
+        return start.length();
     }
+    let t = (-start.dot(along) / length2).clamp(0.0, 1.0);
+    (start + t * along).length()
+}
 
-    pub fn render(&mut self, scene: &mut Scene, ctx: &mut PaintCtx) {
-        let size = ctx.size();
-        let ctrl_id = self.control_object;
-        let cam_pos = if let Some(ctrl_id) = ctrl_id {
-            let ctrl = &self.entity_store.entities[ctrl_id.0];
-            ctrl.render_transform.translation()
-        } else {
-            Vec2::new(0.0, 0.0)
-        };
+// Something that happened in a tick. Gameplay code adds them as it goes and
+// they're cleared at the start of the next tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    // the player thrusted or turned this tick
+    Thrust,
+    Turn,
+    AirCollected { pod: EntityId },
+    // the player's ship ran into something (None for the border)
+    ShipHit { other: Option<EntityId> },
+    // the player's air dropped below the warning level
+    LowAir,
+    NearMiss { asteroid: EntityId },
+    // the player's ship came to rest by a station
+    Docked { station: EntityId },
+    // an asteroid shower started coming in from around pos
+    ShowerStarted { pos: Vec2 },
+    BossArrived { pos: Vec2 },
+}
 
-        for entity in &self.entity_store.entities {
-            if entity.object_type == GameObjectType::AidPod {
-                // if air pod is off screen, render blip at edge of screen
-                let rad = entity.collision.radius();
-                let half_size = 0.5 * size.to_vec2();
-                let pos = entity.render_transform.translation() - cam_pos;
-                if pos.x + rad < -half_size.x
-                    || pos.x - rad > half_size.x
-                    || pos.y + rad < -half_size.y
-                    || pos.y - rad > half_size.y
-                {
-                    let clip_end = |p0, p1, c0, c1, c_clip| -> Vec2 {
-                        let t = (c_clip - c0) / (c1 - c0);
-                        if t < 0.0 {
-                            p1
-                        } else if t > 1.0 {
-                            p1
-                        } else {
-                            p0 + t * (p1 - p0)
-                        }
-                    };
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EscortOutcome {
+    Delivered,
+    Destroyed,
+}
 
-                    let p0 = Vec2::new(0.0, 0.0);
-                    let pos = clip_end(p0, pos, 0.0, pos.x, -half_size.x);
-                    let pos = clip_end(p0, pos, 0.0, pos.x, half_size.x);
-                    let pos = clip_end(p0, pos, 0.0, pos.y, -half_size.y);
-                    let pos = clip_end(p0, pos, 0.0, pos.y, half_size.y);
+struct Escort {
+    convoy: EntityId,
+    destination: Vec2,
+    // the way the convoy is taking there
+    nav_path: NavPath,
+    outcome: Option<EscortOutcome>,
+}
 
-                    // compute oscillation for air animation on edge of screen. This is copy-pasted from minimap
-                    let t = self.virtual_time as f64 / MICROS_PER_SECOND as f64;
-                    let rate = 4.0;
-                    let oscillation = ((t % (1.0 / rate)) - 0.5 / rate).abs() * 2.0 * rate;
+// --- MARK: Radar ---
 
-                    scene.fill(
-                        vello::peniko::Fill::NonZero,
-                        Affine::translate(pos + half_size),
-                        xilem::Color::rgb8(0x0, 0xd4, 0xf8),
-                        None,
-                        &vello::kurbo::Circle::new((0.0, 0.0), 16.0 + oscillation * 48.0),
-                    );
-                    continue;
-                }
-            }
-            let transform = Affine::rotate(entity.render_transform.rotation()).then_translate(
-                entity.render_transform.translation() - cam_pos + 0.5 * size.to_vec2(),
+//-------------------------------------------------------------------------
+// Radar sweep for the minimap. Entities only show up as blips when the
+// rotating beam passes over them, and the blips stay where the entity was
+// at that moment.
+//-------------------------------------------------------------------------
+
+struct RadarBlip {
+    entity: usize,
+    pos: Vec2,
+    color: xilem::Color,
+    radius: f64,
+    // tick the blip was swept
+    tick: u32,
+}
+
+struct Radar {
+    // current beam direction
+    angle: f64,
+    blips: Vec<RadarBlip>,
+}
+
+impl Radar {
+    fn new() -> Self {
+        Self {
+            angle: 0.0,
+            blips: Vec::new(),
+        }
+    }
+
+    // Sweep on around center, picking up what's within range
+    fn update(
+        &mut self,
+        center: Vec2,
+        range: f64,
+        entities: &EntityStore,
+        ctrl_id: Option<EntityId>,
+        tick: u32,
+    ) {
+        let step = TAU / RADAR_SWEEP_TICKS as f64;
+        let start = self.angle;
+        self.angle = (self.angle + step) % TAU;
+
+        for (idx, entity) in entities.entities.iter().enumerate() {
+            let clutter = matches!(
+                entity.object_type,
+                GameObjectType::Dummy
+                    | GameObjectType::Debris
+                    | GameObjectType::Scenery
+                    | GameObjectType::Turret
+                    | GameObjectType::Projectile
             );
-            if let Some(animation) = &entity.animation {
-                let elapsed = animation.start_time.elapsed().as_secs_f64();
-                let animation = (animation.animation)(elapsed);
+            if clutter || Some(idx) == ctrl_id.map(|id| id.0) {
+                continue;
+            }
 
-                scene.append(&animation, Some(transform));
+            // air pods are tracked at any range so there is always a way to find them
+            let offset = entity.transform.translation() - center;
+            let in_range = offset.length() - entity.collision.radius() < range;
+            if !in_range && entity.object_type != GameObjectType::AidPod {
+                continue;
             }
 
-            if let Some(shape) = &entity.shape {
-                scene.append(shape.scene(), Some(transform));
+            let rel_angle = (offset.atan2() - start).rem_euclid(TAU);
+            if rel_angle >= step {
+                continue;
             }
+
+            self.blips.retain(|blip| blip.entity != idx);
+            self.blips.push(RadarBlip {
+                entity: idx,
+                pos: entity.transform.translation(),
+                color: GameWorld::mini_map_color(entity),
+                radius: entity.collision.radius(),
+                tick,
+            });
         }
-        let border_transform = Affine::translate(-cam_pos + 0.5 * size.to_vec2());
-        scene.append(
-            self.get_resources().border_shape.scene(),
-            Some(border_transform),
-        );
 
-        self.render_mini_map(scene, size, cam_pos);
-        self.render_game_state(scene, ctx, size);
+        self.blips
+            .retain(|blip| tick.saturating_sub(blip.tick) < RADAR_SWEEP_TICKS);
     }
 }
 
-// --- MARK: GameObject ---
+// --- MARK: Trail ---
 
 //-------------------------------------------------------------------------
-// GameObject: every game object has all the components, but some are optional.
-// In a larger game you would use an ecs sysem like hecs.
-//----------------------------------------------------------------------
+// Ring buffer of recent positions, oldest first.
+//-------------------------------------------------------------------------
 
-pub struct GameObject {
-    pub transform: Transform,
-    pub prev_transform: Transform,
-    pub render_transform: Transform,
-    pub spatial_db_ref: SpatialDbRef,
-    pub collision: Collision,
-    pub rigid: Rigid,
-    pub shape: Option<Shape>,
-    pub animation: Option<Animation>,
-    pub air_suuply: Option<AirSupply>,
-    pub score: Option<Score>,
-    pub object_type: GameObjectType,
+pub struct Trail {
+    points: VecDeque<Vec2>,
 }
 
-impl GameObject {
-    fn new_ship(resources: &Resources, _seed: u64, _seq: u32) -> Self {
-        let shape = resources.ship_shape.clone();
-        let collision = Collision::new(shape.radius());
-        let spatial_db_ref = SpatialDbRef {
-            spatial_id: SpatialId::new(),
-        };
-        let rigid = Rigid::new(shape.radius(), 1.0, 0.0, 0.01, 1.0, 0.3);
-
-        GameObject {
-            transform: Transform::new(Vec2::ZERO, PI),
-            prev_transform: Transform::new(Vec2::ZERO, PI),
-            render_transform: Transform::new(Vec2::ZERO, PI),
-            spatial_db_ref,
-            collision,
-            rigid,
-            shape: Some(shape),
-            animation: None,
-            air_suuply: Some(AirSupply {
-                air: TICKS_PER_SECOND * 60,
-            }),
-            score: Some(Score(0)),
-            object_type: GameObjectType::Ship,
+impl Trail {
+    pub fn new() -> Self {
+        Self {
+            points: VecDeque::new(),
         }
     }
 
-    fn new_air_pod(_resources: &Resources, _seed: u64, _seq: u32) -> Self {
-        // get air pod shape at first frame to figure out radius
-        let shape = air_pod_shape(0.0);
-
-        let collision = Collision::new(shape.radius());
-        let spatial_db_ref = SpatialDbRef {
-            spatial_id: SpatialId::new(),
-        };
-        let rigid = Rigid::new(shape.radius(), 1.0, 0.0, 0.01, 0.99, 0.3);
-
-        GameObject {
-            transform: Transform::identity(),
-            prev_transform: Transform::identity(),
-            render_transform: Transform::identity(),
-            spatial_db_ref,
-            collision,
-            rigid,
-            shape: None,
-            animation: Some(Animation {
-                start_time: Instant::now(),
-                animation: air_pod_scene,
-            }),
-            air_suuply: Some(AirSupply {
-                air: TICKS_PER_SECOND * 15,
-            }),
-            score: None,
-            object_type: GameObjectType::AidPod,
+    pub fn push(&mut self, pos: Vec2, max_len: usize) {
+        self.points.push_back(pos);
+        while self.points.len() > max_len {
+            self.points.pop_front();
         }
     }
 
-    fn new_asteroid(
-        resources: &Resources,
-        seed: u64,
-        seq: u32,
-        vel_range: Range<f64>,
-        ang_vel_range: Range<f64>,
-    ) -> Self {
-        let vel = vel_range.hash_rand(seed, (seq, "vel"));
-        let vel_angle = (0.0..TAU).hash_rand(seed, (seq, "vel_angle"));
-        let vel = Vec2::new(vel * vel_angle.cos(), vel * vel_angle.sin());
-        let ang_vel = ang_vel_range.hash_rand(seed, (seq, "ang_vel"));
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
 
-        let asteroid_num = (0..6).hash_rand(seed, (seq, "asteroid_num"));
-        let shape = match asteroid_num {
-            0 => resources.small_asteroid1.clone(),
-            1 => resources.small_asteroid2.clone(),
-            2 => resources.medium_asteroid1.clone(),
-            3 => resources.medium_asteroid2.clone(),
-            4 => resources.large_asteroid1.clone(),
-            5 => resources.large_asteroid2.clone(),
-            _ => panic!("Invalid asteroid_num"),
-        };
+    pub fn iter(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.points.iter().copied()
+    }
+}
 
-        let collision = Collision::new(shape.radius());
-        let spatial_db_ref = SpatialDbRef {
-            spatial_id: SpatialId::new(),
-        };
-        // Note: resitution is 1.01 in order to add a little entergy to the system when asteroids collide, picking up intensity
-        let mut rigid = Rigid::new(shape.radius(), 1.5, 1.0, 0.0, 0.0, 1.01);
-        rigid.velocity = vel;
-        rigid.angular_velocity = ang_vel;
+// HUD indicator showing which direction the last heavy hit came from
+struct HitIndicator {
+    // direction from the ship towards the impact
+    dir: Vec2,
+    start_time: f64,
+    // 0..1
+    strength: f64,
+}
 
-        GameObject {
-            transform: Transform::identity(),
-            prev_transform: Transform::identity(),
-            render_transform: Transform::identity(),
-            spatial_db_ref,
-            collision,
-            rigid,
-            shape: Some(shape),
-            animation: None,
-            air_suuply: None,
-            score: None,
-            object_type: GameObjectType::Asteroid,
+// Parts of the world worth a warning when the player's ship gets into them
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DangerZone {
+    // lots of asteroids close by
+    AsteroidField,
+    NearBorder,
+}
+
+impl DangerZone {
+    fn key(self) -> &'static str {
+        match self {
+            DangerZone::AsteroidField => "asteroid_field",
+            DangerZone::NearBorder => "near_border",
         }
     }
 
-    fn new_dummy() -> Self {
-        GameObject {
-            transform: Transform::identity(),
-            prev_transform: Transform::identity(),
-            render_transform: Transform::identity(),
-            spatial_db_ref: SpatialDbRef {
-                spatial_id: SpatialId::new(),
-            },
-            collision: Collision::new(0.0),
-            rigid: Rigid::new(0.0, 0.0, 0.0, 0.0, 0.0, 1.0),
-            shape: None,
-            animation: None,
-            air_suuply: None,
-            score: None,
-            object_type: GameObjectType::Dummy,
+    fn cue(self) -> Cue {
+        match self {
+            DangerZone::AsteroidField => Cue::DangerAsteroids,
+            DangerZone::NearBorder => Cue::DangerBorder,
         }
     }
 
-    fn pick_position(&mut self, seed: u64, seq: u32, pos_range: Range<Vec2>) {
-        let pos = pos_range.hash_rand(seed, seq);
-        self.transform.translation = pos;
-        self.prev_transform.translation = pos;
+    fn color(self) -> xilem::Color {
+        match self {
+            DangerZone::AsteroidField => xilem::Color::rgb8(0xff, 0x90, 0x20),
+            DangerZone::NearBorder => xilem::Color::rgb8(0xff, 0x30, 0x60),
+        }
     }
 }
 
-#[derive(PartialEq)]
-pub enum GameObjectType {
-    Ship,
-    Asteroid,
-    AidPod,
-    Dummy,
+// --- MARK: PhotoMode ---
+
+//-------------------------------------------------------------------------
+// Photo mode pauses the game and frees the camera. Arrows/WASD (or dragging
+// with the mouse) pan, +/- (or the scroll wheel) zoom, F cycles filters and
+// Enter (or F12) saves a screenshot. Clicking an entity selects it for the
+// inspector.
+//-------------------------------------------------------------------------
+
+const PHOTO_PAN_SPEED: f64 = 800.0;
+const PHOTO_ZOOM_SPEED: f64 = 1.5;
+// zoom per line scrolled
+const PHOTO_SCROLL_ZOOM: f64 = 1.1;
+const PHOTO_ZOOM_RANGE: Range<f64> = 0.1..8.0;
+
+struct PhotoMode {
+    cam_pos: Vec2,
+    zoom: f64,
+    filter: PhotoFilter,
+    // shown in the inspector
+    selected: Option<EntityId>,
+    // inspector field being edited
+    field: usize,
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct Score(pub u64);
+impl PhotoMode {
+    fn new(cam_pos: Vec2) -> Self {
+        Self {
+            cam_pos,
+            zoom: 1.0,
+            filter: PhotoFilter::None,
+            selected: None,
+            field: 0,
+        }
+    }
 
-// --- MARK: EntityStore ---
+    fn update(&mut self, input: &InputManager, dt: f64) {
+        let down = |keys: &[KeyCode]| keys.iter().any(|key| input.is_down(PhysicalKey::Code(*key)));
+
+        // pan in screen space, so zoomed in moves slower through the world
+        let mut pan = Vec2::ZERO;
+        if down(&[KeyCode::ArrowLeft, KeyCode::KeyA]) {
+            pan.x -= 1.0;
+        }
+        if down(&[KeyCode::ArrowRight, KeyCode::KeyD]) {
+            pan.x += 1.0;
+        }
+        if down(&[KeyCode::ArrowUp, KeyCode::KeyW]) {
+            pan.y -= 1.0;
+        }
+        if down(&[KeyCode::ArrowDown, KeyCode::KeyS]) {
+            pan.y += 1.0;
+        }
+        self.cam_pos += pan * PHOTO_PAN_SPEED * dt / self.zoom;
+
+        if down(&[KeyCode::Equal, KeyCode::NumpadAdd]) {
+            self.zoom *= (PHOTO_ZOOM_SPEED * dt).exp();
+        }
+        if down(&[KeyCode::Minus, KeyCode::NumpadSubtract]) {
+            self.zoom *= (-PHOTO_ZOOM_SPEED * dt).exp();
+        }
+        // dragging with the mouse grabs the world, scrolling zooms in steps
+        if input.is_mouse_down(MouseButton::Left) {
+            self.cam_pos -= input.get_mouse_motion() / self.zoom;
+        }
+        self.zoom *= PHOTO_SCROLL_ZOOM.powf(input.get_scroll().y);
+        self.zoom = self.zoom.clamp(PHOTO_ZOOM_RANGE.start, PHOTO_ZOOM_RANGE.end);
+
+        if input.is_make(PhysicalKey::Code(KeyCode::KeyF)) {
+            self.filter = self.filter.next();
+            log::info!("photo_filter filter={:?}", self.filter);
+        }
+    }
+}
+
+// --- MARK: Inspector ---
 
 //-------------------------------------------------------------------------
-// EntityStore for GameObject that includes all our components.
-// EntityStore and GameObject could be replaced with a  generic entity
-// component system like HECS.
+// Inspector panel for the entity selected in photo mode. Shows its
+// components and lets the numbers that drive the physics be edited in place
+// for debugging and tuning: Tab (shift+Tab) picks a field, comma and period
+// step it down and up (ten steps at a time with shift held). Edits change the
+// simulation, so they stop the run's recordings (as the tuning panel does).
 //-------------------------------------------------------------------------
 
+const INSPECTOR_WIDTH: f64 = 320.0;
+
 #[derive(Clone, Copy, Debug)]
-pub struct EntityId(usize);
+enum InspectorField {
+    VelocityX,
+    VelocityY,
+    AngularVelocity,
+    Mass,
+    Restitution,
+    Dampening,
+    Air,
+}
 
-pub struct EntityStore {
-    entities: Vec<GameObject>,
+const INSPECTOR_FIELDS: [InspectorField; 7] = [
+    InspectorField::VelocityX,
+    InspectorField::VelocityY,
+    InspectorField::AngularVelocity,
+    InspectorField::Mass,
+    InspectorField::Restitution,
+    InspectorField::Dampening,
+    InspectorField::Air,
+];
+
+impl InspectorField {
+    fn label(self) -> &'static str {
+        match self {
+            InspectorField::VelocityX => "vel x",
+            InspectorField::VelocityY => "vel y",
+            InspectorField::AngularVelocity => "ang vel",
+            InspectorField::Mass => "mass",
+            InspectorField::Restitution => "restitution",
+            InspectorField::Dampening => "dampening",
+            InspectorField::Air => "air (s)",
+        }
+    }
+
+    fn step(self) -> f64 {
+        match self {
+            InspectorField::VelocityX | InspectorField::VelocityY => 0.5,
+            InspectorField::AngularVelocity => 0.005,
+            InspectorField::Mass => 100.0,
+            InspectorField::Restitution => 0.05,
+            InspectorField::Dampening => 0.005,
+            InspectorField::Air => 1.0,
+        }
+    }
+
+    // None if the entity doesn't have it (or it can't be changed, like the mass
+    // of a kinematic body)
+    fn get(self, entity: &GameObject) -> Option<f64> {
+        let rigid = &entity.rigid;
+        match self {
+            InspectorField::VelocityX => Some(rigid.velocity.x),
+            InspectorField::VelocityY => Some(rigid.velocity.y),
+            InspectorField::AngularVelocity => Some(rigid.angular_velocity),
+            InspectorField::Mass if rigid.is_kinematic() => None,
+            InspectorField::Mass => Some(1.0 / rigid.inv_mass),
+            InspectorField::Restitution => Some(rigid.restitution),
+            InspectorField::Dampening => Some(rigid.dampening),
+            InspectorField::Air => entity
+                .air_suuply
+                .as_ref()
+                .map(|air| air.air as f64 / TICKS_PER_SECOND as f64),
+        }
+    }
+
+    fn set(self, entity: &mut GameObject, value: f64) {
+        let rigid = &mut entity.rigid;
+        match self {
+            InspectorField::VelocityX => rigid.velocity.x = value,
+            InspectorField::VelocityY => rigid.velocity.y = value,
+            InspectorField::AngularVelocity => rigid.angular_velocity = value,
+            InspectorField::Mass => rigid.inv_mass = 1.0 / value.max(1.0),
+            InspectorField::Restitution => rigid.restitution = value.clamp(0.0, 1.0),
+            InspectorField::Dampening => rigid.dampening = value.clamp(0.0, 1.0),
+            InspectorField::Air => {
+                if let Some(air) = entity.air_suuply.as_mut() {
+                    air.air = (value.max(0.0) * TICKS_PER_SECOND as f64) as u64;
+                }
+            }
+        }
+    }
 }
 
-impl EntityStore {
-    pub fn new() -> Self {
-        EntityStore {
-            entities: Vec::new(),
+impl GameWorld {
+    // Pointer press in screen coordinates while in photo mode selects the entity
+    // under it, or clears the selection on empty space. Returns true if handled.
+    pub fn handle_select_press(&mut self, pos: Point, screen_size: Size) -> bool {
+        let Some(cam_pos) = self.photo_mode.as_ref().map(|photo_mode| photo_mode.cam_pos) else {
+            return false;
+        };
+        let viewport = self.get_viewport(screen_size);
+        let world_pos = (viewport.world_to_screen(cam_pos).inverse() * pos).to_vec2();
+
+        // smallest entity whose bounds the press is in, so something small in
+        // front of something big (like a ship docked at a station) can be picked
+        let selected = self
+            .entity_store
+            .entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| {
+                let render_only = entity.flags.contains(EntityFlags::RENDER_ONLY);
+                entity.object_type != GameObjectType::Dummy && !render_only
+            })
+            .filter(|(_, entity)| {
+                let dist = (entity.render_transform.translation() - world_pos).length();
+                dist <= entity.collision.radius()
+            })
+            .min_by(|a, b| a.1.collision.radius().total_cmp(&b.1.collision.radius()))
+            .map(|(idx, _)| EntityId(idx));
+
+        log::debug!("inspector_select id={:?}", selected.map(|id| id.0));
+        let photo_mode = self.photo_mode.as_mut().unwrap();
+        photo_mode.selected = selected;
+        photo_mode.field = 0;
+        true
+    }
+
+    fn update_inspector(&mut self) {
+        let Some(photo_mode) = self.photo_mode.as_mut() else {
+            return;
+        };
+        let Some(id) = photo_mode.selected else {
+            return;
+        };
+
+        let input = &self.input_manager;
+        let shift = input.is_down(PhysicalKey::Code(KeyCode::ShiftLeft))
+            || input.is_down(PhysicalKey::Code(KeyCode::ShiftRight));
+        if input.is_make(PhysicalKey::Code(KeyCode::Tab)) {
+            let count = INSPECTOR_FIELDS.len();
+            photo_mode.field = if shift {
+                (photo_mode.field + count - 1) % count
+            } else {
+                (photo_mode.field + 1) % count
+            };
         }
-    }
 
-    pub fn get(&self, id: EntityId) -> &GameObject {
-        &self.entities[id.0]
-    }
+        let mut steps = 0.0;
+        if input.is_make(PhysicalKey::Code(KeyCode::Comma)) {
+            steps -= 1.0;
+        }
+        if input.is_make(PhysicalKey::Code(KeyCode::Period)) {
+            steps += 1.0;
+        }
+        if steps == 0.0 {
+            return;
+        }
+        if shift {
+            steps *= 10.0;
+        }
 
-    pub fn get_mut(&mut self, id: EntityId) -> &mut GameObject {
-        &mut self.entities[id.0]
+        let field = INSPECTOR_FIELDS[photo_mode.field];
+        let entity = self.entity_store.get_mut(id);
+        let Some(value) = field.get(entity) else {
+            return;
+        };
+        field.set(entity, value + steps * field.step());
+        log::info!(
+            "inspector_edit id={} field={:?} value={:.3}",
+            id.0,
+            field,
+            field.get(entity).unwrap_or_default()
+        );
+        self.stop_recordings("an entity was edited in the inspector");
     }
 
-    pub fn get_mut_pair(
-        &mut self,
-        id1: EntityId,
-        id2: EntityId,
-    ) -> (&mut GameObject, &mut GameObject) {
-        if id1.0 < id2.0 {
-            let (split1, split2) = self.entities.split_at_mut(id2.0);
-            (&mut split1[id1.0], &mut split2[0])
-        } else if id1.0 > id2.0 {
-            let (split1, split2) = self.entities.split_at_mut(id1.0);
-            (&mut split2[0], &mut split1[id2.0])
+    fn render_inspector(
+        &self,
+        scene: &mut Scene,
+        ctx: &mut FontContext,
+        size: Size,
+        world_to_screen: Affine,
+        viewport: &Viewport,
+    ) {
+        let Some(photo_mode) = self.photo_mode.as_ref() else {
+            return;
+        };
+        let Some(id) = photo_mode.selected else {
+            return;
+        };
+        let entity = self.entity_store.get(id);
+
+        // ring around the selection
+        let pos = world_to_screen * entity.render_transform.translation().to_point();
+        scene.stroke(
+            &vello::kurbo::Stroke::new(2.0).with_dashes(0.0, [8.0, 6.0]),
+            Affine::IDENTITY,
+            xilem::Color::rgb8(0xff, 0xff, 0x80),
+            None,
+            &vello::kurbo::Circle::new(pos, viewport.scale * entity.collision.radius() + 6.0),
+        );
+
+        let panel = Rect::new(size.width - INSPECTOR_WIDTH, 0.0, size.width, size.height);
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0, 0, 0, 0xc0),
+            None,
+            &panel,
+        );
+
+        let name = entity
+            .name
+            .as_ref()
+            .map(|name| name.0.clone())
+            .unwrap_or_else(|| format!("{:?}", entity.object_type));
+        let mut txt = format!("#{} {}\n\n", id.0, name);
+
+        let translation = entity.transform.translation();
+        writeln!(txt, "{:<12}({:.1}, {:.1})", "pos", translation.x, translation.y).unwrap();
+        writeln!(txt, "{:<12}{:.3}", "rotation", entity.transform.rotation()).unwrap();
+        writeln!(txt, "{:<12}{:.2}", "speed", entity.rigid.velocity.length()).unwrap();
+        let spatial_id = &entity.spatial_db_ref.spatial_id;
+        if spatial_id.is_valid() {
+            let dim = self.spatial_db.dim;
+            writeln!(txt, "{:<12}({}, {})", "cell", spatial_id.0 % dim, spatial_id.0 / dim).unwrap();
         } else {
-            panic!("Cannot get pair of same id");
+            writeln!(txt, "{:<12}-", "cell").unwrap();
+        }
+        let collision = match &entity.collision {
+            Collision::Circle { radius } => format!("circle r={:.0}", radius),
+            Collision::Compound {
+                circles,
+                bounding_radius,
+            } => format!("{} circles r={:.0}", circles.len(), bounding_radius),
+        };
+        writeln!(txt, "{:<12}{}", "collision", collision).unwrap();
+        writeln!(txt, "{:<12}{:.0}", "mass", entity.rigid.mass()).unwrap();
+        writeln!(txt, "{:<12}{:.0}", "inertia", entity.rigid.inertia()).unwrap();
+        if let Some(area) = entity.shape.as_ref().and_then(Shape::area) {
+            writeln!(txt, "{:<12}{:.0}", "area", area.area).unwrap();
+        }
+        if let Some(kinematic) = entity.kinematic.as_ref() {
+            let kind = match kinematic {
+                Kinematic::Spin { .. } => "spin",
+                Kinematic::Sweep { .. } => "sweep",
+            };
+            writeln!(txt, "{:<12}{}", "kinematic", kind).unwrap();
         }
-    }
 
-    pub fn insert(&mut self, object: GameObject) -> EntityId {
-        let id = EntityId(self.entities.len());
-        self.entities.push(object);
-        id
-    }
+        let components = [
+            ("air", entity.air_suuply.is_some()),
+            ("score", entity.score.is_some()),
+            ("salvage", entity.salvage.is_some()),
+            ("cargo", entity.cargo.is_some()),
+            ("pilot", entity.pilot.is_some()),
+            ("faction", entity.faction_ship.is_some()),
+            ("hull", entity.hull.is_some()),
+            ("mine", entity.mine.is_some()),
+            ("animation", entity.animation.is_some()),
+            ("tint", entity.tint.is_some()),
+        ];
+        let components: Vec<&str> = components
+            .iter()
+            .filter(|(_, present)| *present)
+            .map(|(name, _)| *name)
+            .collect();
+        writeln!(txt, "{:<12}{}", "components", components.join(", ")).unwrap();
+        if let Some(hull) = entity.hull.as_ref() {
+            writeln!(txt, "{:<12}{:.0}/{:.0}", "hull", hull.health, hull.max).unwrap();
+        }
 
-    // pub fn iter_entity(&self) -> impl Iterator<Item = (EntityId, &GameObject)> {
-    //     self.entities.iter().enumerate().map(|(idx, obj)| (EntityId(idx), obj))
-    // }
+        txt.push('\n');
+        for (idx, field) in INSPECTOR_FIELDS.iter().enumerate() {
+            let marker = if idx == photo_mode.field { '>' } else { ' ' };
+            let value = match field.get(entity) {
+                Some(value) => format!("{:.3}", value),
+                None => "-".to_string(),
+            };
+            writeln!(txt, "{} {:<11}{}", marker, field.label(), value).unwrap();
+        }
+        txt.push_str("\nTab: field  , .: adjust");
 
-    pub fn iter_mut_entity(&mut self) -> impl Iterator<Item = (EntityId, &mut GameObject)> {
-        self.entities
-            .iter_mut()
-            .enumerate()
-            .map(|(idx, obj)| (EntityId(idx), obj))
+        let style = TextStyle {
+            font_size: 13.0,
+            color: xilem::Color::rgb8(0xe0, 0xe0, 0xe0),
+            alignment: xilem::TextAlignment::Start,
+        };
+        let anchor = Point::new(panel.x0 + 16.0, panel.y0 + 16.0);
+        draw_mono_text(scene, ctx, &txt, &style, anchor, Vec2::ZERO);
     }
 }
 
-// --- MARK: Shape ---
+// --- MARK: Viewport ---
 
 //-------------------------------------------------------------------------
-// Shape component for rendering a static shape
+// Viewport maps the world around the camera onto the screen. Depending on
+// the viewport mode this is either 1:1 with the window or a fixed area of
+// the world, scaled to fit with letterboxing or to fill with the rest cropped.
+// Either way no more than the fixed area is ever visible.
 //-------------------------------------------------------------------------
-#[derive(Clone)]
-pub struct Shape {
-    scene: Arc<Scene>,
-    radius: f64,
+
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    // screen space rect the world is visible in
+    pub rect: Rect,
+    // screen units per world unit
+    pub scale: f64,
+    pub letterbox: bool,
 }
 
-impl Shape {
-    pub fn new(scene: Arc<Scene>, radius: f64) -> Self {
-        Shape { scene, radius }
+impl Viewport {
+    pub fn new(mode: ViewportMode, screen_size: Size) -> Self {
+        match mode {
+            ViewportMode::Window => Viewport {
+                rect: screen_size.to_rect(),
+                scale: 1.0,
+                letterbox: false,
+            },
+            ViewportMode::Fixed { size, letterbox } => {
+                let (fit_x, fit_y) = (screen_size.width / size.width, screen_size.height / size.height);
+                let scale = if letterbox { fit_x.min(fit_y) } else { fit_x.max(fit_y) };
+                let view_size = Size::new(scale * size.width, scale * size.height);
+                let origin = Point::new(
+                    0.5 * (screen_size.width - view_size.width),
+                    0.5 * (screen_size.height - view_size.height),
+                );
+                Viewport {
+                    // filling the window, what's off it is cropped
+                    rect: Rect::from_origin_size(origin, view_size).intersect(screen_size.to_rect()),
+                    scale,
+                    letterbox,
+                }
+            }
+        }
     }
 
-    pub fn scene(&self) -> &Scene {
-        &self.scene
+    // Scale the view around its center, zoom > 1 shows less of the world
+    pub fn with_zoom(mut self, zoom: f64) -> Self {
+        self.scale *= zoom;
+        self
     }
 
-    pub fn radius(&self) -> f64 {
-        self.radius
+    pub fn world_to_screen(&self, cam_pos: Vec2) -> Affine {
+        Affine::translate(-cam_pos)
+            .then_scale(self.scale)
+            .then_translate(self.rect.center().to_vec2())
     }
-}
-
-// --- MARK: Animation ---
-
-//-------------------------------------------------------------------------
-// Animation component for rendering an animated shape
-//-------------------------------------------------------------------------
-pub struct Animation {
-    pub start_time: Instant,
-    pub animation: fn(f64) -> Scene,
-}
 
-//-------------------------------------------------------------------------
-// Game component for tracking air supply. Air pod and ship have this
-// component. Every tick one unit of air is lost. Ship picking up air
-// pod adds remaining air in pod to ship's supply.
-//-------------------------------------------------------------------------
-pub struct AirSupply {
-    pub air: u64,
+    // half size of the visible part of the world, in world units. In a fixed
+    // mode it's never more than half the fixed size.
+    pub fn half_extent(&self) -> Vec2 {
+        0.5 * self.rect.size().to_vec2() / self.scale
+    }
 }
 
-// --- MARK: Collision ---
+// --- MARK: Attachment ---
 
 //-------------------------------------------------------------------------
-// Simple collision component -- everything is a circle.
+// Attachment component: holds an entity at a spot in another's frame, like
+// the carrier's turrets on its body or a ship's engine flame. A simulated
+// child is given the velocity that takes it to its spot by the end of the
+// tick (see GameWorld::update_attachments), so it still collides like any
+// other body. Render-only children are just put there. Either way, render
+// transforms are composed from the parent's when interpolating, so children
+// never lag or jitter. Only one level deep: a parent can't be attached.
 //-------------------------------------------------------------------------
-pub struct Collision {
-    // we're all spheres
-    radius: f64,
-}
-
-impl Collision {
-    pub fn new(radius: f64) -> Self {
-        Collision { radius }
-    }
 
-    pub fn radius(&self) -> f64 {
-        self.radius
-    }
+#[derive(Clone, Copy, Debug)]
+pub struct Attachment {
+    pub parent: EntityId,
+    // in the parent's frame
+    pub offset: Vec2,
+    pub rotation: f64,
 }
 
-#[derive(Debug)]
-pub struct Contact {
-    id1: Option<EntityId>,
-    id2: Option<EntityId>,
-    pos: Vec2,
-    normal1: Vec2,
-    // normal2 is -normal1
-    depth: f64,
+impl Attachment {
+    // Where the child goes with the parent at parent
+    pub fn world_transform(&self, parent: &Transform) -> Transform {
+        Transform::new(parent.local_to_world(self.offset), parent.rotation() + self.rotation)
+    }
 }
 
 // --- MARK: Transform ---
@@ -1208,6 +10011,8 @@ pub struct Contact {
 pub struct Transform {
     translation: Vec2,
     rotation: f64,
+    // set by teleport, render uses this transform as is until the next tick
+    skip_interp: bool,
 }
 
 impl Transform {
@@ -1215,6 +10020,7 @@ impl Transform {
         Transform {
             translation,
             rotation,
+            skip_interp: false,
         }
     }
 
@@ -1222,13 +10028,27 @@ impl Transform {
         Transform {
             translation: Vec2::new(0.0, 0.0),
             rotation: 0.0,
+            skip_interp: false,
         }
     }
 
+    // Jump to pos. Interpolation is skipped for the rest of the tick so rendering
+    // doesn't streak from the old position to the new one.
+    pub fn teleport(&mut self, pos: Vec2) {
+        self.translation = pos;
+        self.skip_interp = true;
+    }
+
     pub fn translation(&self) -> Vec2 {
         self.translation
     }
 
+    // Point in the object's frame to world space
+    pub fn local_to_world(&self, local: Vec2) -> Vec2 {
+        let (sin, cos) = self.rotation.sin_cos();
+        self.translation + Vec2::new(local.x * cos - local.y * sin, local.x * sin + local.y * cos)
+    }
+
     pub fn rotation(&self) -> f64 {
         self.rotation
     }
@@ -1241,9 +10061,9 @@ impl Transform {
         self.translation += translation;
     }
 
-    // pub fn get_x_vector(&self) -> Vec2 {
-    //     Vec2::new(self.rotation.cos(), self.rotation.sin())
-    // }
+    pub fn get_x_vector(&self) -> Vec2 {
+        Vec2::new(self.rotation.cos(), self.rotation.sin())
+    }
 
     pub fn get_y_vector(&self) -> Vec2 {
         Vec2::new(-self.rotation.sin(), self.rotation.cos())
@@ -1262,9 +10082,18 @@ pub struct Rigid {
     dampening: f64,
     angular_dampening: f64,
     restitution: f64,
+    // scales the friction coefficient, the lower of the two bodies' counts
+    friction: f64,
     inv_mass: f64,
-    // simplified inertia since we're all circles here
-    inv_ang_inertia_sqrt: f64,
+    // of the rotational inertia about the body's origin
+    inv_inertia: f64,
+    // carried on top of the body's own mass (ore in the hold), included in inv_mass
+    load: f64,
+    // summed over the tick by apply_force/apply_torque, used up in apply_physics
+    force: Vec2,
+    torque: f64,
+    // summed over the solver's passes by apply_contact_impulse, for the tick just resolved
+    contact_impulse: Vec2,
 }
 
 impl Rigid {
@@ -1276,13 +10105,25 @@ impl Rigid {
         ang_dampening: f64,
         restitution: f64,
     ) -> Self {
-        let inv_mass = if density > 0.01 {
-            1.0 / (density * PI * radius * radius)
+        Self::from_area(ShapeArea::disk(radius), density, ang_density, dampening, ang_dampening, restitution)
+    }
+
+    // Weighed by the actual outline instead of the bounding circle
+    pub fn from_area(
+        area: ShapeArea,
+        density: f64,
+        ang_density: f64,
+        dampening: f64,
+        ang_dampening: f64,
+        restitution: f64,
+    ) -> Self {
+        let inv_mass = if density > 0.01 && area.area > 0.0 {
+            1.0 / (density * area.area)
         } else {
             0.0
         };
-        let inv_ang_inertia_sqrt = if ang_density > 0.01 {
-            SQRT_2 / ((ang_density * PI).sqrt() * radius * radius)
+        let inv_inertia = if ang_density > 0.01 && area.second_moment > 0.0 {
+            1.0 / (ang_density * area.second_moment)
         } else {
             0.0
         };
@@ -1292,9 +10133,75 @@ impl Rigid {
             dampening,
             angular_dampening: ang_dampening,
             restitution,
+            friction: 1.0,
             inv_mass,
-            inv_ang_inertia_sqrt,
+            inv_inertia,
+            load: 0.0,
+            force: Vec2::ZERO,
+            torque: 0.0,
+            contact_impulse: Vec2::ZERO,
+        }
+    }
+
+    pub fn with_friction(mut self, friction: f64) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    // Infinite mass body moved by script (see Kinematic). It pushes dynamic bodies
+    // like the border does, but collisions never change its velocity.
+    pub fn kinematic(restitution: f64) -> Self {
+        Self::new(0.0, 0.0, 0.0, 0.0, 0.0, restitution)
+    }
+
+    pub fn is_kinematic(&self) -> bool {
+        self.inv_mass == 0.0
+    }
+
+    // Where a body at pos drifts to over the next ticks if nothing touches it or
+    // pushes it, a point every step ticks starting with pos. Dampening is the
+    // world's scale on the body's own, like in apply_physics.
+    pub fn predict_path(&self, pos: Vec2, dampening: f64, ticks: u32, step: u32) -> Vec<Vec2> {
+        let keep = 1.0 - (dampening * self.dampening).min(1.0);
+        let (mut pos, mut velocity) = (pos, self.velocity);
+        let mut path = vec![pos];
+        for tick in 1..=ticks {
+            pos += velocity;
+            velocity *= keep;
+            if tick % step == 0 {
+                path.push(pos);
+            }
+        }
+        path
+    }
+
+    // infinite for kinematic bodies
+    pub fn mass(&self) -> f64 {
+        1.0 / self.inv_mass
+    }
+
+    pub fn inertia(&self) -> f64 {
+        1.0 / self.inv_inertia
+    }
+
+    // Make the body itself heavier or lighter, keeping what it carries
+    pub fn scale_mass(&mut self, factor: f64) {
+        if self.is_kinematic() {
+            return;
+        }
+        let own_mass = self.mass() - self.load;
+        self.inv_mass = 1.0 / (own_mass * factor + self.load);
+        self.inv_inertia /= factor;
+    }
+
+    // Change what's carried, keeping the body's own mass
+    pub fn set_load(&mut self, load: f64) {
+        if self.is_kinematic() || load == self.load {
+            return;
         }
+        let own_mass = self.mass() - self.load;
+        self.load = load;
+        self.inv_mass = 1.0 / (own_mass + load);
     }
 
     #[inline]
@@ -1308,9 +10215,31 @@ impl Rigid {
 
     pub fn apply_impulse(&mut self, impulse: Vec2, offset: Vec2) {
         self.velocity += impulse * self.inv_mass;
-        self.angular_velocity += (offset.x * impulse.y - offset.y * impulse.x)
-            * self.inv_ang_inertia_sqrt
-            * self.inv_ang_inertia_sqrt;
+        self.angular_velocity += (offset.x * impulse.y - offset.y * impulse.x) * self.inv_inertia;
+    }
+
+    // Impulse from resolving a collision, which is also totted up for the tick
+    pub fn apply_contact_impulse(&mut self, impulse: Vec2, offset: Vec2) {
+        self.apply_impulse(impulse, offset);
+        self.contact_impulse += impulse;
+    }
+
+    // Force and torque act over the whole tick, adding up until apply_physics
+    // turns them into velocity. They don't move kinematic bodies.
+    pub fn apply_force(&mut self, force: Vec2) {
+        self.force += force;
+    }
+
+    pub fn apply_torque(&mut self, torque: f64) {
+        self.torque += torque;
+    }
+
+    // Velocity change from this tick's forces, which are then cleared
+    fn integrate_forces(&mut self) {
+        self.velocity += self.force * self.inv_mass;
+        self.angular_velocity += self.torque * self.inv_inertia;
+        self.force = Vec2::ZERO;
+        self.torque = 0.0;
     }
 }
 
@@ -1364,6 +10293,22 @@ impl SpatialDb {
         self.max
     }
 
+    pub fn get_node_size(&self) -> f64 {
+        self.node_size
+    }
+
+    pub fn get_dim(&self) -> u32 {
+        self.dim
+    }
+
+    // Sum of weight over the objects in each node, row by row
+    pub fn node_sums(&self, weight: impl Fn(EntityId) -> f64) -> Vec<f64> {
+        self.nodes
+            .iter()
+            .map(|node| node.objects.iter().map(|&id| weight(id)).sum())
+            .collect()
+    }
+
     fn get_spatial_id(&self, pos: Vec2) -> SpatialId {
         // clamp x and y to valid range (border nodes will have infinte range)
 
@@ -1386,21 +10331,30 @@ impl SpatialDb {
         SpatialId(x + y * self.dim)
     }
 
-    pub fn probe_range(
+    // Columns and rows of the nodes overlapping the range grown by max_radius
+    fn node_range(
         &self,
         pos_range: Range<Vec2>,
         max_radius: f64,
-        callback: &mut impl FnMut(EntityId),
-    ) {
+    ) -> (RangeInclusive<u32>, RangeInclusive<u32>) {
         let minx = ((pos_range.start.x - max_radius - self.min.x).max(0.0) / self.node_size) as u32;
         let maxx = (((pos_range.end.x + max_radius - self.min.x) / self.node_size) as u32)
             .min(self.dim - 1);
         let miny = ((pos_range.start.y - max_radius - self.min.y).max(0.0) / self.node_size) as u32;
         let maxy = (((pos_range.end.y + max_radius - self.min.y) / self.node_size) as u32)
             .min(self.dim - 1);
+        (minx..=maxx, miny..=maxy)
+    }
 
-        for y in miny..=maxy {
-            for x in minx..=maxx {
+    pub fn probe_range(
+        &self,
+        pos_range: Range<Vec2>,
+        max_radius: f64,
+        callback: &mut impl FnMut(EntityId),
+    ) {
+        let (xs, ys) = self.node_range(pos_range, max_radius);
+        for y in ys {
+            for x in xs.clone() {
                 let idx = (x + y * self.dim) as usize;
                 let node = &self.nodes[idx];
                 for obj in &node.objects {
@@ -1410,6 +10364,19 @@ impl SpatialDb {
         }
     }
 
+    // Center and number of objects of each node overlapping the range
+    pub fn node_counts(&self, pos_range: Range<Vec2>) -> Vec<(Vec2, usize)> {
+        let (xs, ys) = self.node_range(pos_range, 0.0);
+        let mut counts = Vec::new();
+        for y in ys {
+            for x in xs.clone() {
+                let center = self.min + self.node_size * Vec2::new(x as f64 + 0.5, y as f64 + 0.5);
+                counts.push((center, self.nodes[(x + y * self.dim) as usize].objects.len()));
+            }
+        }
+        counts
+    }
+
     pub fn update(&mut self, entity_id: EntityId, pos: Vec2, spatial_ref: &mut SpatialDbRef) {
         let new_spatial_id = self.get_spatial_id(pos);
 
@@ -1441,6 +10408,95 @@ impl SpatialDb {
         spatial_ref.spatial_id = SpatialId::new();
     }
 
+    // Adds to violations where the nodes' lists and the entities' refs don't
+    // agree, or a ref is for a node away from the entity. Refs are updated from
+    // the position before the tick's move, so they can be a node behind.
+    fn check_refs(&self, entities: &EntityStore, violations: &mut Vec<String>) {
+        let mut listed: Vec<Option<u32>> = vec![None; entities.entities.len()];
+        for (node_idx, node) in self.nodes.iter().enumerate() {
+            for id in &node.objects {
+                let violation = match listed.get_mut(id.0) {
+                    None => format!("spatial node {} lists unknown entity {}", node_idx, id.0),
+                    Some(Some(other)) => {
+                        format!("entity {} is in spatial nodes {} and {}", id.0, other, node_idx)
+                    }
+                    Some(slot) => {
+                        *slot = Some(node_idx as u32);
+                        continue;
+                    }
+                };
+                violations.push(violation);
+            }
+        }
+
+        for (idx, entity) in entities.entities.iter().enumerate() {
+            let spatial_id = &entity.spatial_db_ref.spatial_id;
+            let kept_out = entity.flags.contains(EntityFlags::RENDER_ONLY);
+            if entity.object_type == GameObjectType::Dummy || kept_out {
+                if let Some(node) = listed[idx] {
+                    violations.push(format!("entity {} is kept out but is in spatial node {}", idx, node));
+                }
+                continue;
+            }
+            if !spatial_id.is_valid() || listed[idx] != Some(spatial_id.0) {
+                violations.push(format!(
+                    "entity {} refers to spatial node {} but is listed in {:?}",
+                    idx, spatial_id.0, listed[idx]
+                ));
+                continue;
+            }
+            let node = self.get_spatial_id(entity.transform.translation()).0;
+            let dx = (node % self.dim).abs_diff(spatial_id.0 % self.dim);
+            let dy = (node / self.dim).abs_diff(spatial_id.0 / self.dim);
+            if dx > 1 || dy > 1 {
+                violations.push(format!(
+                    "entity {} is in spatial node {} but its position is in node {}",
+                    idx, spatial_id.0, node
+                ));
+            }
+        }
+    }
+
+    // Closest entity to pos that position_of gives a position for, and its distance.
+    // Searches rings of nodes outward from pos until no node left can hold anything
+    // closer. Entities outside the grid are only found from the border nodes they
+    // are kept in, so they may be missed for a slightly closer one.
+    pub fn find_nearest(
+        &self,
+        pos: Vec2,
+        position_of: &mut impl FnMut(EntityId) -> Option<Vec2>,
+    ) -> Option<(EntityId, f64)> {
+        let center = self.get_spatial_id(pos).0;
+        let (cx, cy) = ((center % self.dim) as i64, (center / self.dim) as i64);
+        let mut nearest: Option<(EntityId, f64)> = None;
+
+        for ring in 0..self.dim as i64 {
+            // everything in this ring and beyond is at least this far away
+            let min_dist = (ring - 1).max(0) as f64 * self.node_size;
+            if nearest.is_some_and(|(_, dist)| dist < min_dist) {
+                break;
+            }
+            for y in (cy - ring).max(0)..=(cy + ring).min(self.dim as i64 - 1) {
+                for x in (cx - ring).max(0)..=(cx + ring).min(self.dim as i64 - 1) {
+                    if (x - cx).abs() != ring && (y - cy).abs() != ring {
+                        continue;
+                    }
+                    let node = &self.nodes[(x + y * self.dim as i64) as usize];
+                    for &obj in &node.objects {
+                        let Some(obj_pos) = position_of(obj) else {
+                            continue;
+                        };
+                        let dist = (obj_pos - pos).length();
+                        if nearest.map_or(true, |(_, nearest_dist)| dist < nearest_dist) {
+                            nearest = Some((obj, dist));
+                        }
+                    }
+                }
+            }
+        }
+        nearest
+    }
+
     pub fn find_neighbors(&self, max_radius: f64, callback: &mut impl FnMut(EntityId, EntityId)) {
         let num_check_nodes = (2.0 * max_radius / self.node_size) as u32 + 1;
 
@@ -1518,94 +10574,83 @@ struct SpatialDbNode {
 
 pub struct Resources {
     pub ship_shape: Shape,
-    pub small_asteroid1: Shape,
-    pub small_asteroid2: Shape,
-    pub medium_asteroid1: Shape,
-    pub medium_asteroid2: Shape,
-    pub large_asteroid1: Shape,
-    pub large_asteroid2: Shape,
+    // by variant, two small, two medium and two large outlines for each material
+    pub asteroids: Vec<Shape>,
+    pub debris_shapes: Vec<Shape>,
+    pub dust_shape: Shape,
+    pub cargo_pod_shape: Shape,
+    pub derelict_shape: Shape,
+    pub station_shape: Shape,
+    pub barrier_shape: Shape,
+    pub convoy_shape: Shape,
+    pub mine_shape: Shape,
+    pub spent_mine_shape: Shape,
+    pub emp_mine_shape: Shape,
+    pub spent_emp_mine_shape: Shape,
+    pub carrier_core_shape: Shape,
+    pub carrier_exposed_core_shape: Shape,
+    pub carrier_wreck_core_shape: Shape,
+    pub turret_shape: Shape,
+    pub wrecked_turret_shape: Shape,
+    pub gun_shape: Shape,
+    pub bolt_shape: Shape,
+    pub pirate_ship_shape: Shape,
+    pub trader_ship_shape: Shape,
+    pub patrol_ship_shape: Shape,
     pub border_shape: Shape,
 }
 
 impl Resources {
     pub fn new(extent: f64) -> Self {
         Resources {
-            ship_shape: ship_shape(),
-            small_asteroid1: asteroid_shape(0, 30.0),
-            small_asteroid2: asteroid_shape(1, 30.0),
-            medium_asteroid1: asteroid_shape(2, 100.0),
-            medium_asteroid2: asteroid_shape(3, 100.0),
-            large_asteroid1: asteroid_shape(4, 150.0),
-            large_asteroid2: asteroid_shape(5, 150.0),
-            border_shape: border_shape(extent),
-        }
-    }
-}
-
-// --- MARK: InputManager ---
-
-//-------------------------------------------------------------------------
-// InputManager for handling input events.
-//-------------------------------------------------------------------------
-
-pub struct InputManager {
-    make_events: Vec<PhysicalKey>,
-    break_events: Vec<PhysicalKey>,
-    key_down: HashSet<PhysicalKey>,
-}
-
-impl InputManager {
-    pub fn new() -> Self {
-        Self {
-            make_events: Vec::default(),
-            break_events: Vec::default(),
-            key_down: HashSet::default(),
-        }
-    }
-
-    pub fn input(&mut self, event: &DeviceEvent) -> bool {
-        match event {
-            DeviceEvent::Key(key) => {
-                if key.state == ElementState::Pressed {
-                    self.make_events.push(key.physical_key.clone());
-                    self.key_down.insert(key.physical_key.clone());
-                } else {
-                    self.break_events.push(key.physical_key.clone());
-                    self.key_down.remove(&key.physical_key);
-                }
-            }
-            _ => {}
-        }
-        // We don't really care if key is consumed or not for this simple input manager
-        false
-    }
-
-    pub fn is_down(&self, key: PhysicalKey) -> bool {
-        self.key_down.contains(&key)
-    }
-
-    pub fn is_make(&self, key: PhysicalKey) -> bool {
-        for k in self.make_events.iter() {
-            if *k == key {
-                return true;
-            }
+            ship_shape: ship_shape(&ShipStyle::default()),
+            asteroids: ASTEROID_MATERIALS
+                .iter()
+                .flat_map(|&material| {
+                    (0..NUM_ASTEROID_SHAPES as usize).map(move |num| {
+                        asteroid_shape(num, ASTEROID_RADII[num / 2], material)
+                    })
+                })
+                .collect(),
+            debris_shapes: (0..9).map(debris_shape).collect(),
+            dust_shape: dust_shape(),
+            cargo_pod_shape: cargo_pod_shape(-CARGO_POD_OFFSET.y),
+            derelict_shape: derelict_shape(),
+            station_shape: station_shape(),
+            barrier_shape: barrier_shape(BARRIER_HALF_LENGTH, BARRIER_RADIUS),
+            convoy_shape: convoy_shape(),
+            mine_shape: mine_shape(true, false),
+            spent_mine_shape: mine_shape(false, false),
+            emp_mine_shape: mine_shape(true, true),
+            spent_emp_mine_shape: mine_shape(false, true),
+            // shielded, exposed and destroyed
+            carrier_core_shape: carrier_core_shape(xilem::Color::rgb8(0x50, 0x80, 0xd0)),
+            carrier_exposed_core_shape: carrier_core_shape(xilem::Color::rgb8(0xff, 0x50, 0x30)),
+            carrier_wreck_core_shape: carrier_core_shape(xilem::Color::rgb8(0x28, 0x20, 0x20)),
+            turret_shape: turret_shape(true),
+            wrecked_turret_shape: turret_shape(false),
+            gun_shape: gun_shape(),
+            bolt_shape: bolt_shape(BOLT_RADIUS),
+            pirate_ship_shape: colored_ship_shape(Faction::Pirates.color()),
+            trader_ship_shape: colored_ship_shape(Faction::Traders.color()),
+            patrol_ship_shape: colored_ship_shape(Faction::Patrol.color()),
+            border_shape: border_shape(ArenaShape::Square, extent),
         }
-        return false;
     }
 
-    pub fn is_break(&self, key: PhysicalKey) -> bool {
-        for k in self.break_events.iter() {
-            if *k == key {
-                return true;
-            }
+    pub fn faction_ship_shape(&self, faction: Faction) -> &Shape {
+        match faction {
+            Faction::Pirates => &self.pirate_ship_shape,
+            Faction::Traders => &self.trader_ship_shape,
+            Faction::Patrol => &self.patrol_ship_shape,
         }
-        return false;
     }
+}
 
-    pub fn clear_events(&mut self) {
-        self.make_events.clear();
-        self.break_events.clear();
-    }
+// Color t of the way from a to b
+fn lerp_color(a: xilem::Color, b: xilem::Color, t: f64) -> xilem::Color {
+    let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t.clamp(0.0, 1.0)).round() as u8;
+    xilem::Color::rgba8(lerp(a.r, b.r), lerp(a.g, b.g), lerp(a.b, b.b), lerp(a.a, b.a))
 }
 
 //-------------------------------------------------------------------------
@@ -1671,3 +10716,62 @@ impl HashRand<Vec2> for Range<Vec2> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 4242;
+    const TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+
+    fn world() -> GameWorld {
+        let arena = (ArenaVariant::Rotating, ArenaShape::Hexagon);
+        GameWorld::generate(SEED, 4000.0, 80, arena, &[], Arc::default())
+    }
+
+    // A world continued from a save goes on as the one saved. It's saved before
+    // the first tick: debris and guns are made again where they start, not
+    // where they'd got to.
+    #[test]
+    fn continued_world_ticks_on_the_same() {
+        let mut world = world();
+        let mut continued = GameWorld::from_snapshot(&world.snapshot());
+        assert_eq!(continued.snapshot().serialize(), world.snapshot().serialize());
+        for _ in 0..TICKS {
+            world.tick();
+            continued.tick();
+        }
+        assert_eq!(world.get_tick_count(), TICKS);
+        assert_eq!(continued.snapshot().serialize(), world.snapshot().serialize());
+        assert_eq!(continued.entity_store.entities.len(), world.entity_store.entities.len());
+        assert!(continued.run_recording.is_none());
+    }
+
+    // Going back to the checkpoint takes a quarter of its score off every time,
+    // and forgets the rewinds and air losses since
+    #[test]
+    fn load_checkpoint_takes_the_penalty() {
+        let mut world = world();
+        world.enable_metrics(MetricsRecorder::new(std::path::PathBuf::new(), None));
+        let ship = world.control_object.unwrap();
+        world.entity_store.get_mut(ship).score = Some(Score(12_000));
+        world.checkpoints.take(world.snapshot(), 12_000);
+        for _ in 0..TICKS {
+            world.tick();
+        }
+        world.metrics.as_mut().unwrap().air_lost("pirate", TICKS);
+        world.rewind.push(world.snapshot());
+
+        world.load_checkpoint();
+        assert_eq!(world.get_tick_count(), 0);
+        assert_eq!(world.get_score(), score_after_load(12_000));
+        assert_eq!(world.get_score(), 9_000);
+        assert_eq!(world.checkpoints.loads, 1);
+        assert!(!world.rewind.start(true), "rewind snapshots after the checkpoint are kept");
+        assert_eq!(world.metrics.as_ref().unwrap().death_cause(TICKS), "suffocated");
+
+        world.load_checkpoint();
+        assert_eq!(world.get_score(), 9_000);
+        assert_eq!(world.checkpoints.loads, 2);
+    }
+}
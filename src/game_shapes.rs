@@ -8,7 +8,25 @@ use vello::{
 };
 use xilem::Color;
 
-pub fn ship_shape() -> crate::game::Shape {
+use crate::{
+    air_pod::PodTier,
+    arena::ArenaShape,
+    boss::{CORE_RADIUS, HULL_OFFSETS, HULL_RADIUS, TURRET_OFFSETS, TURRET_RADIUS},
+    game::{AsteroidMaterial, ShapeArea},
+    ship_style::{FlameColor, ShipStyle, Stripes},
+};
+
+// The player's ship, painted the way they picked in the paint shop
+pub fn ship_shape(style: &ShipStyle) -> crate::game::Shape {
+    painted_ship_shape(style.hull.color(), style.stripes, style.hull.stripe_color())
+}
+
+// same hull as the player's ship, used for faction ships
+pub fn colored_ship_shape(color: Color) -> crate::game::Shape {
+    painted_ship_shape(color, Stripes::None, color)
+}
+
+fn painted_ship_shape(color: Color, stripes: Stripes, stripe_color: Color) -> crate::game::Shape {
     let yrad: f64 = 25.0;
     let xrad = 15.0;
     let radius = (yrad * yrad + xrad * xrad).sqrt();
@@ -22,52 +40,475 @@ pub fn ship_shape() -> crate::game::Shape {
     path.line_to((0.0, yrad));
     path.close_path();
 
+    scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &path);
+
+    // kept inside the hull, the outline goes over their ends
+    let mut stripe_path = kurbo::BezPath::new();
+    match stripes {
+        Stripes::None => {}
+        Stripes::Center => {
+            stripe_path.move_to((0.0, yrad));
+            stripe_path.line_to((0.0, -yrad));
+        }
+        Stripes::Twin => {
+            for x in [-6.0, 6.0] {
+                stripe_path.move_to((x, yrad));
+                stripe_path.line_to((x, -yrad));
+            }
+        }
+        Stripes::Chevron => {
+            stripe_path.move_to((-xrad, -0.6 * yrad));
+            stripe_path.line_to((0.0, 0.2 * yrad));
+            stripe_path.line_to((xrad, -0.6 * yrad));
+        }
+    }
+    if stripes != Stripes::None {
+        scene.push_layer(vello::peniko::BlendMode::default(), 1.0, Affine::IDENTITY, &path);
+        scene.stroke(&Stroke::new(4.0), Affine::IDENTITY, stripe_color, None, &stripe_path);
+        scene.pop_layer();
+    }
+    scene.stroke(&Stroke::new(4.0), Affine::IDENTITY, color, None, &path);
+
+    crate::game::Shape::new(Arc::new(scene), radius)
+}
+
+pub fn station_shape() -> crate::game::Shape {
+    // hub with a ring and four docking arms
+    let radius = 120.0;
+    let ring_radius = 80.0;
+
+    let mut scene = Scene::new();
+    let mut arms = kurbo::BezPath::new();
+    for i in 0..4 {
+        let angle = i as f64 * 0.5 * std::f64::consts::PI;
+        let dir = kurbo::Vec2::from_angle(angle);
+        arms.move_to((30.0 * dir).to_point());
+        arms.line_to(((radius - 6.0) * dir).to_point());
+    }
+    scene.stroke(
+        &Stroke::new(10.0),
+        Affine::IDENTITY,
+        Color::rgb8(0x70, 0x80, 0x90),
+        None,
+        &arms,
+    );
+    scene.stroke(
+        &Stroke::new(8.0),
+        Affine::IDENTITY,
+        Color::rgb8(0xa0, 0xb0, 0xc0),
+        None,
+        &kurbo::Circle::new((0.0, 0.0), ring_radius),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::rgb8(0x50, 0x60, 0x70),
+        None,
+        &kurbo::Circle::new((0.0, 0.0), 30.0),
+    );
+
+    crate::game::Shape::new(Arc::new(scene), radius)
+}
+
+pub fn barrier_shape(half_length: f64, radius: f64) -> crate::game::Shape {
+    // capsule along x with hazard stripes
+    let capsule = kurbo::RoundedRect::new(-half_length, -radius, half_length, radius, radius);
+
+    let mut scene = Scene::new();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::rgb8(0xe0, 0xc0, 0x30),
+        None,
+        &capsule,
+    );
+    let mut stripes = kurbo::BezPath::new();
+    let mut x = -half_length + radius;
+    while x < half_length - radius {
+        stripes.move_to((x - 0.6 * radius, radius * 0.8));
+        stripes.line_to((x + 0.6 * radius, -radius * 0.8));
+        x += 1.5 * radius;
+    }
+    scene.stroke(
+        &Stroke::new(0.5 * radius),
+        Affine::IDENTITY,
+        Color::rgb8(0x30, 0x28, 0x20),
+        None,
+        &stripes,
+    );
+    scene.stroke(
+        &Stroke::new(4.0),
+        Affine::IDENTITY,
+        Color::rgb8(0x80, 0x70, 0x30),
+        None,
+        &capsule,
+    );
+
+    crate::game::Shape::new(Arc::new(scene), half_length)
+}
+
+pub fn convoy_shape() -> crate::game::Shape {
+    // long cargo hauler pointing along y, with container blocks along the spine
+    let hull = [
+        (0.0, 70.0),
+        (-22.0, 45.0),
+        (-22.0, -60.0),
+        (-12.0, -70.0),
+        (12.0, -70.0),
+        (22.0, -60.0),
+        (22.0, 45.0),
+    ];
+
+    let mut scene = Scene::new();
+    let mut path = kurbo::BezPath::new();
+    path.move_to(hull[0]);
+    for vert in hull.iter().skip(1) {
+        path.line_to(*vert);
+    }
+    path.close_path();
+
+    let radius = hull
+        .iter()
+        .map(|(x, y)| (x * x + y * y).sqrt())
+        .fold(0.0, f64::max);
+
     scene.fill(
         Fill::NonZero,
         Affine::IDENTITY,
-        Color::rgb8(0xff, 0xff, 0xff),
+        Color::rgb8(0x30, 0x50, 0x40),
         None,
         &path,
     );
     scene.stroke(
         &Stroke::new(4.0),
         Affine::IDENTITY,
-        Color::rgb8(0xff, 0xff, 0xff),
+        Color::rgb8(0x40, 0xff, 0x80),
         None,
         &path,
     );
+    for i in 0..3 {
+        let y = -55.0 + 32.0 * i as f64;
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            Color::rgb8(0x80, 0xa0, 0x90),
+            None,
+            &kurbo::Rect::new(-14.0, y, 14.0, y + 24.0),
+        );
+    }
+
+    crate::game::Shape::new(Arc::new(scene), radius)
+}
+
+// Spiked mine, red core while armed (blue for an EMP mine) and dark once it has gone off
+pub fn mine_shape(armed: bool, emp: bool) -> crate::game::Shape {
+    let radius = 20.0;
+    let body_radius = 13.0;
+    let (body, core) = match (armed, emp) {
+        (true, false) => (Color::rgb8(0x60, 0x60, 0x60), Color::rgb8(0xff, 0x30, 0x20)),
+        (true, true) => (Color::rgb8(0x60, 0x60, 0x60), Color::rgb8(0x40, 0xa0, 0xff)),
+        (false, false) => (Color::rgb8(0x30, 0x30, 0x30), Color::rgb8(0x40, 0x20, 0x20)),
+        (false, true) => (Color::rgb8(0x30, 0x30, 0x30), Color::rgb8(0x20, 0x28, 0x40)),
+    };
+
+    let mut scene = Scene::new();
+    let mut spikes = kurbo::BezPath::new();
+    for i in 0..8 {
+        let dir = kurbo::Vec2::from_angle(i as f64 * 0.25 * std::f64::consts::PI);
+        spikes.move_to((10.0 * dir).to_point());
+        spikes.line_to((radius * dir).to_point());
+    }
+    scene.stroke(&Stroke::new(4.0), Affine::IDENTITY, body, None, &spikes);
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        body,
+        None,
+        &kurbo::Circle::new((0.0, 0.0), body_radius),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        core,
+        None,
+        &kurbo::Circle::new((0.0, 0.0), 6.0),
+    );
+
+    // the spikes are thin and there are gaps between them, so it takes getting
+    // halfway out along them to set the mine off
+    let collision_radius = 0.5 * (body_radius + radius);
+    crate::game::Shape::new(Arc::new(scene), radius).with_collision_radius(collision_radius)
+}
+
+pub fn carrier_core_shape(core: Color) -> crate::game::Shape {
+    // armored core and hull sections (one compound body), with struts out to the turrets
+    let plating = Color::rgb8(0x58, 0x50, 0x58);
+    let radius = HULL_OFFSETS
+        .iter()
+        .map(|offset| offset.length() + HULL_RADIUS)
+        .fold(CORE_RADIUS, f64::max);
+
+    let mut scene = Scene::new();
+    let mut struts = kurbo::BezPath::new();
+    for offset in HULL_OFFSETS.iter().chain(TURRET_OFFSETS.iter()) {
+        struts.move_to((0.0, 0.0));
+        struts.line_to(offset.to_point());
+    }
+    scene.stroke(&Stroke::new(40.0), Affine::IDENTITY, plating, None, &struts);
+
+    // round hull sections with panel seams
+    let seam_color = Color::rgb8(0x70, 0x68, 0x74);
+    for offset in HULL_OFFSETS {
+        let section = kurbo::Circle::new(offset.to_point(), HULL_RADIUS);
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            Color::rgb8(0x48, 0x44, 0x4c),
+            None,
+            &section,
+        );
+        let mut seams = kurbo::BezPath::new();
+        for i in 0..6 {
+            let dir = kurbo::Vec2::from_angle(i as f64 * std::f64::consts::PI / 3.0);
+            seams.move_to((offset + 0.35 * HULL_RADIUS * dir).to_point());
+            seams.line_to((offset + HULL_RADIUS * dir).to_point());
+        }
+        scene.stroke(&Stroke::new(4.0), Affine::IDENTITY, seam_color, None, &seams);
+        scene.stroke(&Stroke::new(6.0), Affine::IDENTITY, seam_color, None, &section);
+    }
+
+    let mut plate = kurbo::BezPath::new();
+    for i in 0..8 {
+        let dir = kurbo::Vec2::from_angle((i as f64 + 0.5) * 0.25 * std::f64::consts::PI);
+        let pt = (CORE_RADIUS * dir).to_point();
+        if i == 0 {
+            plate.move_to(pt);
+        } else {
+            plate.line_to(pt);
+        }
+    }
+    plate.close_path();
+    scene.fill(Fill::NonZero, Affine::IDENTITY, plating, None, &plate);
+    scene.stroke(
+        &Stroke::new(8.0),
+        Affine::IDENTITY,
+        Color::rgb8(0x90, 0x80, 0x88),
+        None,
+        &plate,
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        core,
+        None,
+        &kurbo::Circle::new((0.0, 0.0), 0.4 * CORE_RADIUS),
+    );
+
+    crate::game::Shape::new(Arc::new(scene), radius)
+}
+
+pub fn turret_shape(alive: bool) -> crate::game::Shape {
+    // dome with a barrel along y, lit while it still works
+    let (body, light) = if alive {
+        (Color::rgb8(0x80, 0x70, 0x70), Color::rgb8(0xff, 0x70, 0x30))
+    } else {
+        (Color::rgb8(0x38, 0x30, 0x30), Color::rgb8(0x20, 0x18, 0x18))
+    };
+
+    let mut scene = Scene::new();
+    scene.stroke(
+        &Stroke::new(14.0),
+        Affine::IDENTITY,
+        body,
+        None,
+        &kurbo::Line::new((0.0, 0.0), (0.0, 1.3 * TURRET_RADIUS)),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        body,
+        None,
+        &kurbo::Circle::new((0.0, 0.0), TURRET_RADIUS),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        light,
+        None,
+        &kurbo::Circle::new((0.0, 0.0), 0.4 * TURRET_RADIUS),
+    );
+
+    crate::game::Shape::new(Arc::new(scene), TURRET_RADIUS)
+}
+
+// Small gun for ships and stations (see turret.rs), barrel along y
+pub fn gun_shape() -> crate::game::Shape {
+    let radius = 9.0;
+    let body = Color::rgb8(0x60, 0x60, 0x68);
+
+    let mut scene = Scene::new();
+    scene.stroke(
+        &Stroke::new(4.0),
+        Affine::IDENTITY,
+        body,
+        None,
+        &kurbo::Line::new((0.0, 0.0), (0.0, 2.2 * radius)),
+    );
+    scene.fill(Fill::NonZero, Affine::IDENTITY, body, None, &kurbo::Circle::new((0.0, 0.0), radius));
+    scene.stroke(
+        &Stroke::new(1.5),
+        Affine::IDENTITY,
+        Color::rgb8(0xa0, 0xa0, 0xa8),
+        None,
+        &kurbo::Circle::new((0.0, 0.0), radius),
+    );
+    crate::game::Shape::new(Arc::new(scene), radius)
+}
 
+// Bolt a gun fires, stretched out along y the way it flies
+pub fn bolt_shape(radius: f64) -> crate::game::Shape {
+    let mut scene = Scene::new();
+    let streak = kurbo::Ellipse::new((0.0, -radius), (0.6 * radius, 2.5 * radius), 0.0);
+    scene.fill(Fill::NonZero, Affine::IDENTITY, Color::rgba8(0xff, 0x60, 0x20, 0x90), None, &streak);
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::rgb8(0xff, 0xe0, 0x90),
+        None,
+        &kurbo::Circle::new((0.0, 0.0), 0.6 * radius),
+    );
     crate::game::Shape::new(Arc::new(scene), radius)
 }
 
-pub fn border_shape(extent: f64) -> crate::game::Shape {
-    let border_width = 64.0;
-    // half the border width minus a little bit to make collisions look a little better (due to all collision shapes being circles)
-    let extent_slack = border_width / 2.0 - 4.0;
+pub fn derelict_shape() -> crate::game::Shape {
+    // abandoned freighter: a wide hull with a chunk missing from one side
+    let hull = [
+        (0.0, 60.0),
+        (-30.0, 35.0),
+        (-40.0, -30.0),
+        (-25.0, -50.0),
+        (-5.0, -40.0),
+        (10.0, -55.0),
+        (30.0, -45.0),
+        (22.0, -10.0),
+        (38.0, 5.0),
+        (30.0, 35.0),
+    ];
 
-    let extent = extent + extent_slack;
     let mut scene = Scene::new();
     let mut path = kurbo::BezPath::new();
-    path.move_to((-extent, -extent));
-    path.line_to((extent, -extent));
-    path.line_to((extent, extent));
-    path.line_to((-extent, extent));
-    path.line_to((-extent, -extent));
+    path.move_to(hull[0]);
+    for vert in hull.iter().skip(1) {
+        path.line_to(*vert);
+    }
     path.close_path();
 
+    let radius = hull
+        .iter()
+        .map(|(x, y)| (x * x + y * y).sqrt())
+        .fold(0.0, f64::max);
+
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::rgb8(0x5a, 0x4a, 0x3a),
+        None,
+        &path,
+    );
+    scene.stroke(
+        &Stroke::new(4.0),
+        Affine::IDENTITY,
+        Color::rgb8(0xb0, 0x7a, 0x40),
+        None,
+        &path,
+    );
+
+    // hull plating seams
+    let mut seams = kurbo::BezPath::new();
+    seams.move_to((-30.0, 0.0));
+    seams.line_to((20.0, 0.0));
+    seams.move_to((-20.0, 30.0));
+    seams.line_to((20.0, 30.0));
+    scene.stroke(
+        &Stroke::new(2.0),
+        Affine::IDENTITY,
+        Color::rgb8(0x8a, 0x6a, 0x4a),
+        None,
+        &seams,
+    );
+
+    let collision_radius = fitted_radius(line_loop_area(&hull, 1.0), radius, 4.0);
+    crate::game::Shape::new(Arc::new(scene), radius).with_collision_radius(collision_radius)
+}
+
+const BORDER_WIDTH: f64 = 64.0;
+// half the border width minus a little bit to make collisions look a little better (due to all
+// collision shapes being circles)
+const BORDER_SLACK: f64 = BORDER_WIDTH / 2.0 - 4.0;
+
+pub fn border_shape(shape: ArenaShape, apothem: f64) -> crate::game::Shape {
+    let apothem = apothem + BORDER_SLACK;
+    let mut scene = Scene::new();
+    let mut path = kurbo::BezPath::new();
+    let radius = match shape.sides() {
+        Some(sides) => {
+            // corners between the walls, which face along 0, 1/sides, ... of a turn
+            let step = 2.0 * std::f64::consts::PI / sides as f64;
+            let radius = apothem / (0.5 * step).cos();
+            for i in 0..sides {
+                let corner = (radius * kurbo::Vec2::from_angle((i as f64 + 0.5) * step)).to_point();
+                if i == 0 {
+                    path.move_to(corner);
+                } else {
+                    path.line_to(corner);
+                }
+            }
+            path.close_path();
+            radius
+        }
+        None => {
+            path = kurbo::Shape::to_path(&kurbo::Circle::new((0.0, 0.0), apothem), 0.1);
+            apothem
+        }
+    };
+
     scene.stroke(
-        &Stroke::new(border_width),
+        &Stroke::new(BORDER_WIDTH),
         Affine::IDENTITY,
         Color::rgb8(0xff, 0x1f, 0x1f),
         None,
         &path,
     );
 
-    let radius = extent * 2.0_f64.sqrt();
     crate::game::Shape::new(Arc::new(scene), radius)
 }
 
-fn line_loop_shape(line_loop: &[(f64, f64)], scale: f64) -> (Scene, f64) {
+// The wall of the border facing along angle lit up by a hit, drawn over the
+// border in its frame (see border_shape): the whole side for polygons, a
+// stretch of the ring for the circle. Alpha 0..1 for how bright.
+pub fn border_pulse_scene(shape: ArenaShape, apothem: f64, angle: f64, alpha: f64) -> Scene {
+    let apothem = apothem + BORDER_SLACK;
+    let color = Color::rgb8(0xff, 0xa0, 0x70).with_alpha_factor(alpha as f32);
+    let mut scene = Scene::new();
+    let stroke = Stroke::new(BORDER_WIDTH);
+    match shape.sides() {
+        Some(sides) => {
+            let step = 2.0 * std::f64::consts::PI / sides as f64;
+            let side = (angle / step).round();
+            let radius = apothem / (0.5 * step).cos();
+            let corner = |offset: f64| (radius * kurbo::Vec2::from_angle((side + offset) * step)).to_point();
+            let wall = kurbo::Line::new(corner(-0.5), corner(0.5));
+            scene.stroke(&stroke, Affine::IDENTITY, color, None, &wall);
+        }
+        None => {
+            let arc = kurbo::Arc::new((0.0, 0.0), (apothem, apothem), angle - 0.25, 0.5, 0.0);
+            scene.stroke(&stroke, Affine::IDENTITY, color, None, &arc);
+        }
+    }
+    scene
+}
+
+fn line_loop_shape(line_loop: &[(f64, f64)], scale: f64, (fill, stroke): (Color, Color)) -> (Scene, f64) {
     let mut scene = Scene::new();
     let mut path = kurbo::BezPath::new();
     let start = line_loop[0];
@@ -87,14 +528,14 @@ fn line_loop_shape(line_loop: &[(f64, f64)], scale: f64) -> (Scene, f64) {
     scene.fill(
         Fill::NonZero,
         Affine::IDENTITY,
-        Color::rgb8(0x7f, 0x7f, 0x7f),
+        fill,
         None,
         &path,
     );
     scene.stroke(
         &Stroke::new(8.0),
         Affine::IDENTITY,
-        Color::rgb8(0x8f, 0x8f, 0x8f),
+        stroke,
         None,
         &path,
     );
@@ -102,7 +543,42 @@ fn line_loop_shape(line_loop: &[(f64, f64)], scale: f64) -> (Scene, f64) {
     (scene, radius)
 }
 
-pub fn asteroid_shape(num: usize, radius: f64) -> crate::game::Shape {
+// Collision radius of a filled outline: the disk with the same area, out to
+// the middle of the stroke around it, but never past the furthest point. The
+// bumps then poke out of the circle about as much as the dents cut into it.
+fn fitted_radius(area: ShapeArea, outer_radius: f64, stroke_width: f64) -> f64 {
+    (area.disk_radius() + 0.5 * stroke_width).min(outer_radius)
+}
+
+// Area and second moment of area about the origin of the filled loop, from
+// the triangles between the origin and each edge
+fn line_loop_area(line_loop: &[(f64, f64)], scale: f64) -> ShapeArea {
+    let mut area = 0.0;
+    let mut second_moment = 0.0;
+    for (idx, &(x0, y0)) in line_loop.iter().enumerate() {
+        let (x1, y1) = line_loop[(idx + 1) % line_loop.len()];
+        let cross = x0 * y1 - x1 * y0;
+        area += 0.5 * cross;
+        second_moment += cross * (x0 * x0 + x0 * x1 + x1 * x1 + y0 * y0 + y0 * y1 + y1 * y1) / 12.0;
+    }
+    // either winding, and scaled by the square and fourth power
+    ShapeArea {
+        area: area.abs() * scale * scale,
+        second_moment: second_moment.abs() * scale.powi(4),
+    }
+}
+
+// Fill and outline of an asteroid made of the material
+pub fn asteroid_colors(material: AsteroidMaterial) -> (Color, Color) {
+    match material {
+        AsteroidMaterial::Rock => (Color::rgb8(0x7f, 0x7f, 0x7f), Color::rgb8(0x8f, 0x8f, 0x8f)),
+        AsteroidMaterial::Ice => (Color::rgb8(0xa8, 0xd8, 0xf0), Color::rgb8(0xd8, 0xf0, 0xff)),
+        AsteroidMaterial::Iron => (Color::rgb8(0x58, 0x4c, 0x48), Color::rgb8(0x9a, 0x5a, 0x3a)),
+        AsteroidMaterial::Rubble => (Color::rgb8(0x8a, 0x74, 0x5a), Color::rgb8(0x6a, 0x58, 0x44)),
+    }
+}
+
+pub fn asteroid_shape(num: usize, radius: f64, material: AsteroidMaterial) -> crate::game::Shape {
     // Below are several 20-sided polygons representing asteroids. They were generated from the following spreadsheet:
     // https://docs.google.com/spreadsheets/d/1xR1n7GgObxkecqYXtzoObPnjP1TU0OGz7YYxIOX1x20/edit?usp=sharing
 
@@ -254,15 +730,81 @@ pub fn asteroid_shape(num: usize, radius: f64) -> crate::game::Shape {
         _ => &verts0,
     };
 
-    let (shape, outer_radius) = line_loop_shape(verts, radius);
+    let (shape, outer_radius) = line_loop_shape(verts, radius, asteroid_colors(material));
+    let area = line_loop_area(verts, radius);
 
     crate::game::Shape::new(Arc::new(shape), outer_radius)
+        .with_area(area)
+        .with_collision_radius(fitted_radius(area, outer_radius, 8.0))
+}
+
+// Pod of ore towed behind the player's ship (see scenery.rs), with its
+// tether running along +y to the ship length away
+pub fn cargo_pod_shape(length: f64) -> crate::game::Shape {
+    let radius = 12.0;
+    let mut scene = Scene::new();
+    scene.stroke(
+        &Stroke::new(1.5),
+        Affine::IDENTITY,
+        Color::rgba8(0xa0, 0xa0, 0xa0, 0xc0),
+        None,
+        &kurbo::Line::new((0.0, radius), (0.0, length - 20.0)),
+    );
+    let pod = kurbo::RoundedRect::new(-radius, -radius, radius, radius, 4.0);
+    scene.fill(Fill::NonZero, Affine::IDENTITY, Color::rgb8(0x8a, 0x70, 0x50), None, &pod);
+    scene.stroke(&Stroke::new(2.0), Affine::IDENTITY, Color::rgb8(0xc0, 0xa0, 0x70), None, &pod);
+    crate::game::Shape::new(Arc::new(scene), radius)
 }
 
-pub fn air_pod_scene(t: f64) -> Scene {
+// Speck of dust hanging in space (see scenery.rs)
+pub fn dust_shape() -> crate::game::Shape {
+    let radius = 1.5;
     let mut scene = Scene::new();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::rgba8(0xc0, 0xc0, 0xb0, 0x90),
+        None,
+        &kurbo::Circle::new((0.0, 0.0), radius),
+    );
+    crate::game::Shape::new(Arc::new(scene), radius)
+}
+
+// Shard of drifting debris (see debris.rs), three outlines in three sizes.
+// See-through, so a cluster dims what's behind it without hiding it outright.
+pub fn debris_shape(num: usize) -> crate::game::Shape {
+    let verts: &[(f64, f64)] = match num % 3 {
+        0 => &[(1.0, 0.0), (-0.6, 0.8), (-0.8, -0.5)],
+        1 => &[(1.0, 0.2), (0.1, 0.9), (-0.9, 0.3), (-0.4, -0.8), (0.6, -0.7)],
+        _ => &[(0.9, -0.1), (0.2, 1.0), (-1.0, 0.1), (0.1, -0.9)],
+    };
+    let radius = [6.0, 9.0, 12.0][num / 3 % 3];
+
     let mut path = kurbo::BezPath::new();
-    let radius = 100.0;
+    path.move_to((radius * verts[0].0, radius * verts[0].1));
+    for vert in &verts[1..] {
+        path.line_to((radius * vert.0, radius * vert.1));
+    }
+    path.close_path();
+
+    let mut scene = Scene::new();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::rgba8(0x70, 0x68, 0x60, 0xd0),
+        None,
+        &path,
+    );
+    let area = line_loop_area(verts, radius);
+    crate::game::Shape::new(Arc::new(scene), 1.05 * radius)
+        .with_collision_radius(fitted_radius(area, radius, 0.0))
+}
+
+pub fn air_pod_scene(t: f64, tier: PodTier) -> Scene {
+    let mut scene = Scene::new();
+    let mut path = kurbo::BezPath::new();
+    let radius = tier.radius();
+    let (fill, outline) = tier.colors();
 
     // t -> 0..1
     let t = t - t.floor();
@@ -281,26 +823,42 @@ pub fn air_pod_scene(t: f64) -> Scene {
     scene.fill(
         Fill::NonZero,
         Affine::IDENTITY,
-        Color::rgb8(0x0, 0xb4, 0xd8),
+        fill,
         None,
         &path,
     );
     scene.stroke(
         &Stroke::new(2.0),
         Affine::IDENTITY,
-        Color::rgb8(0xff, 0xff, 0xff),
+        outline,
         None,
         &path,
     );
     scene
 }
 
-pub fn air_pod_shape(t: f64) -> crate::game::Shape {
-    let radius = 100.0;
-    crate::game::Shape::new(Arc::new(air_pod_scene(t)), radius)
+// Collides out to its full radius even though the star is thinner than that,
+// so it's easy to pick up
+pub fn air_pod_shape(t: f64, tier: PodTier) -> crate::game::Shape {
+    crate::game::Shape::new(Arc::new(air_pod_scene(t, tier)), tier.radius())
+}
+
+// Small flickering flame of a maneuvering thruster, from the origin along +y
+pub fn rcs_flame_scene(t: f64) -> Scene {
+    let mut scene = Scene::new();
+    let flicker = 0.75 + 0.25 * (30.0 * t).sin() * (7.0 * t).cos();
+    let mut path = kurbo::BezPath::new();
+    path.move_to((-3.0, 0.0));
+    path.quad_to((-2.0, 6.0 * flicker), (0.0, 12.0 * flicker));
+    path.quad_to((2.0, 6.0 * flicker), (3.0, 0.0));
+    path.close_path();
+    scene.fill(Fill::NonZero, Affine::IDENTITY, Color::rgb8(0xff, 0xd0, 0x60), None, &path);
+    scene.stroke(&Stroke::new(1.0), Affine::IDENTITY, Color::rgb8(0xff, 0xff, 0xe0), None, &path);
+    scene
 }
 
-pub fn flame_scene(t: f64) -> Scene {
+pub fn flame_scene(t: f64, flame: FlameColor) -> Scene {
+    let (fill, edge) = flame.colors();
     let mut scene = Scene::new();
 
     let t = 20.0 * t;
@@ -333,20 +891,8 @@ pub fn flame_scene(t: f64) -> Scene {
         );
         path.line_to((x_base1, y_base));
 
-        scene.fill(
-            Fill::NonZero,
-            Affine::IDENTITY,
-            Color::rgb8(0xcf, 0x00, 0x00),
-            None,
-            &path,
-        );
-        scene.stroke(
-            &Stroke::new(2.0),
-            Affine::IDENTITY,
-            Color::rgb8(0xff, 0xa5, 0x00),
-            None,
-            &path,
-        );
+        scene.fill(Fill::NonZero, Affine::IDENTITY, fill, None, &path);
+        scene.stroke(&Stroke::new(2.0), Affine::IDENTITY, edge, None, &path);
     };
 
     create_flame(14.0, 0.0, 10.0, -25.0, -39.5, t1);
@@ -356,3 +902,70 @@ pub fn flame_scene(t: f64) -> Scene {
 
     scene
 }
+
+// Flash and expanding ring of something blowing up, t in seconds up to EXPLOSION_SECS
+pub fn explosion_scene(t: f64) -> Scene {
+    let mut scene = Scene::new();
+    // 0..1 over the explosion
+    let t = (t / EXPLOSION_SECS).clamp(0.0, 1.0);
+    let fade = 1.0 - t;
+
+    // bright core that shrinks away quickly
+    let core = 30.0 * (1.0 - t).powi(2);
+    if core > 0.0 {
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            Color::rgb8(0xff, 0xf0, 0xc0).with_alpha_factor(fade as f32),
+            None,
+            &kurbo::Circle::new((0.0, 0.0), core),
+        );
+    }
+    // ring of the blast, easing out
+    let ring = 20.0 + 70.0 * (1.0 - (1.0 - t).powi(3));
+    scene.stroke(
+        &Stroke::new(6.0 * fade + 1.0),
+        Affine::IDENTITY,
+        Color::rgb8(0xff, 0x80, 0x20).with_alpha_factor(fade as f32),
+        None,
+        &kurbo::Circle::new((0.0, 0.0), ring),
+    );
+    scene
+}
+
+//-------------------------------------------------------------------------
+// The animations there are, for the Animation component. Each one is a
+// function of the seconds into the clip. Some have a length: looping ones
+// start over after it, one-shot ones are done.
+//-------------------------------------------------------------------------
+
+pub const EXPLOSION_SECS: f64 = 0.6;
+// of a pulse of the air pod
+const AIR_POD_PULSE_SECS: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationClip {
+    // main engine, for as long as it's firing
+    Flame(FlameColor),
+    AirPod(PodTier),
+    Explosion,
+}
+
+impl AnimationClip {
+    pub fn scene(self, t: f64) -> Scene {
+        match self {
+            AnimationClip::Flame(flame) => flame_scene(t, flame),
+            AnimationClip::AirPod(tier) => air_pod_scene(t, tier),
+            AnimationClip::Explosion => explosion_scene(t),
+        }
+    }
+
+    // None for clips that just keep going
+    pub fn length(self) -> Option<f64> {
+        match self {
+            AnimationClip::Flame(_) => None,
+            AnimationClip::AirPod(_) => Some(AIR_POD_PULSE_SECS),
+            AnimationClip::Explosion => Some(EXPLOSION_SECS),
+        }
+    }
+}
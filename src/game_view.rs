@@ -4,21 +4,55 @@ use accesskit::Role;
 use masonry::{
     Widget, WidgetId,
     AccessCtx, AccessEvent, BoxConstraints, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
-    PointerEvent, Size, StatusChange, TextEvent,
+    Point, PointerButton, PointerEvent, Size, StatusChange, TextEvent,
 };
 use smallvec::SmallVec;
 use vello::Scene;
 use xilem::{Pod, ViewCtx};
 use xilem::core::{MessageResult, DynMessage, Mut, View, ViewId};
 
-use crate::game::GameWorld;
+use crate::{game::GameWorld, sim_thread::Frames};
 
 pub struct GamePortal {
+    // only locked for input, the world is drawn on the sim thread
     game_world: Arc<Mutex<GameWorld>>,
+    frames: Arc<Frames>,
 }
 
 impl Widget for GamePortal {
-    fn on_pointer_event(&mut self, _: &mut EventCtx<'_>, _: &PointerEvent) {}
+    fn on_pointer_event(&mut self, ctx: &mut EventCtx<'_>, event: &PointerEvent) {
+        let (button, state) = match event {
+            PointerEvent::PointerDown(button, state) => (button, state),
+            PointerEvent::PointerMove(state) => {
+                let pos = Point::new(state.position.x, state.position.y);
+                if self.game_world.lock().unwrap().handle_tuning_drag(pos, ctx.size()) {
+                    ctx.set_handled();
+                }
+                return;
+            }
+            PointerEvent::PointerUp(..) | PointerEvent::PointerLeave(..) => {
+                self.game_world.lock().unwrap().handle_tuning_release();
+                return;
+            }
+            _ => return,
+        };
+        let primary = match button {
+            PointerButton::Primary => true,
+            PointerButton::Secondary => false,
+            _ => return,
+        };
+
+        // the portal fills the window, so window coordinates are local coordinates
+        let pos = Point::new(state.position.x, state.position.y);
+        let mut game_world = self.game_world.lock().unwrap();
+        if primary && game_world.handle_tuning_press(pos, ctx.size()) {
+            ctx.set_handled();
+        } else if game_world.handle_minimap_press(pos, ctx.size(), primary) {
+            ctx.set_handled();
+        } else if primary && game_world.handle_select_press(pos, ctx.size()) {
+            ctx.set_handled();
+        }
+    }
 
     fn on_text_event(&mut self, _: &mut EventCtx<'_>, _: &TextEvent) {}
 
@@ -32,9 +66,9 @@ impl Widget for GamePortal {
         bc.max()
     }
 
-    fn paint(&mut self, ctx: &mut PaintCtx<'_>, scene: &mut Scene) {
-        let mut game_world = self.game_world.lock().unwrap();
-        game_world.render(scene, ctx);
+    fn paint(&mut self, _ctx: &mut PaintCtx<'_>, scene: &mut Scene) {
+        // the sim thread drew the frame at the portal's size, which fills the window
+        scene.append(&self.frames.shown().scene, None);
     }
 
     fn accessibility_role(&self) -> accesskit::Role {
@@ -50,6 +84,7 @@ impl Widget for GamePortal {
 
 pub struct GameView {
     game_world: Arc<Mutex<GameWorld>>,
+    frames: Arc<Frames>,
 }
 
 impl<State, Action> View<State, Action, ViewCtx> for GameView {
@@ -59,6 +94,7 @@ impl<State, Action> View<State, Action, ViewCtx> for GameView {
     fn build(&self, _ctx: &mut ViewCtx) -> (Self::Element, Self::ViewState) {
         let widget = GamePortal {
             game_world: self.game_world.clone(),
+            frames: self.frames.clone(),
         };
         (Pod::new(widget), ())
     }
@@ -100,7 +136,7 @@ impl<State, Action> View<State, Action, ViewCtx> for GameView {
 }
 
 impl GameView {
-    pub fn new(game_world: Arc<Mutex<GameWorld>>) -> Self {
-        Self { game_world }
+    pub fn new(game_world: Arc<Mutex<GameWorld>>, frames: Arc<Frames>) -> Self {
+        Self { game_world, frames }
     }
 }
@@ -0,0 +1,49 @@
+use masonry::Vec2;
+
+use crate::game::{EntityId, TICKS_PER_SECOND};
+
+//-------------------------------------------------------------------------
+// The ship's grappling hook. Fired along the nose, it sticks in the first
+// asteroid the ray hits (see GameWorld::raycast) and holds the ship on a
+// line for as long as the key is held. The line is a rope: it only pulls
+// when taut, so the ship swings around the rock and can slingshot off it.
+// Letting go just drops the line, whatever velocity the swing left the
+// ship with it keeps. GameWorld::update_grapple keeps the line taut.
+//-------------------------------------------------------------------------
+
+// how far the hook flies
+pub const GRAPPLE_RANGE: f64 = 700.0;
+// the line never gets shorter than this, so the ship isn't reeled into the rock
+const GRAPPLE_MIN_LENGTH: f64 = 80.0;
+// lets go on its own after this long
+const GRAPPLE_MAX_TICKS: u32 = 6 * TICKS_PER_SECOND as u32;
+// pulled this far past its length in one go (a rock going off somewhere), it snaps
+pub const GRAPPLE_SNAP_STRETCH: f64 = 200.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Grapple {
+    // what the hook is stuck in
+    pub anchor: EntityId,
+    // where it's stuck, in the anchor's frame
+    pub offset: Vec2,
+    pub length: f64,
+    ticks: u32,
+}
+
+impl Grapple {
+    // Stuck at offset in anchor, dist away from the ship
+    pub fn new(anchor: EntityId, offset: Vec2, dist: f64) -> Self {
+        Self {
+            anchor,
+            offset,
+            length: dist.max(GRAPPLE_MIN_LENGTH),
+            ticks: 0,
+        }
+    }
+
+    // A tick has passed, false once it has held as long as it can
+    pub fn update(&mut self) -> bool {
+        self.ticks += 1;
+        self.ticks < GRAPPLE_MAX_TICKS
+    }
+}
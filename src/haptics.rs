@@ -0,0 +1,111 @@
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use gilrs::{
+    ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Replay, Ticks},
+    Gilrs,
+};
+
+//-------------------------------------------------------------------------
+// Rumble on connected controllers that support it. Gilrs has to be polled
+// to notice controllers coming and going and can't be shared between
+// threads, so like the audio it lives on its own thread.
+//-------------------------------------------------------------------------
+
+// how often the controller list is refreshed while nothing is asked for
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Pulse {
+    // 0..1
+    pub strength: f32,
+    pub duration: Duration,
+}
+
+pub struct Haptics {
+    sender: Option<Sender<Pulse>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Haptics {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel::<Pulse>();
+
+        let thread = std::thread::Builder::new()
+            .name("haptics".to_string())
+            .spawn(move || {
+                let mut gilrs = match Gilrs::new() {
+                    Ok(gilrs) => gilrs,
+                    Err(err) => {
+                        log::warn!("Controllers unavailable, no rumble: {}", err);
+                        return;
+                    }
+                };
+                // an effect stops when dropped, so the last one is kept until replaced
+                let mut playing: Option<Effect> = None;
+                loop {
+                    let pulse = match receiver.recv_timeout(POLL_INTERVAL) {
+                        Ok(pulse) => pulse,
+                        Err(RecvTimeoutError::Timeout) => {
+                            while gilrs.next_event().is_some() {}
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+                    while gilrs.next_event().is_some() {}
+
+                    let gamepads: Vec<_> = gilrs
+                        .gamepads()
+                        .filter(|(_, gamepad)| gamepad.is_ff_supported())
+                        .map(|(id, _)| id)
+                        .collect();
+                    if gamepads.is_empty() {
+                        continue;
+                    }
+                    let magnitude = (pulse.strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+                    let effect = EffectBuilder::new()
+                        .add_effect(BaseEffect {
+                            kind: BaseEffectType::Weak { magnitude },
+                            scheduling: Replay {
+                                play_for: Ticks::from_ms(pulse.duration.as_millis() as u32),
+                                ..Default::default()
+                            },
+                            envelope: Default::default(),
+                        })
+                        .gamepads(&gamepads)
+                        .finish(&mut gilrs)
+                        .and_then(|effect| effect.play().map(|_| effect));
+                    match effect {
+                        Ok(effect) => playing = Some(effect),
+                        Err(err) => log::warn!("Failed to rumble: {}", err),
+                    }
+                }
+                drop(playing);
+            })
+            .expect("Failed to start haptics thread");
+
+        Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn pulse(&self, pulse: Pulse) {
+        if let Some(sender) = self.sender.as_ref() {
+            // only fails without controller support, which has already been logged
+            let _ = sender.send(pulse);
+        }
+    }
+}
+
+impl Drop for Haptics {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
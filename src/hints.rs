@@ -0,0 +1,110 @@
+use std::{collections::VecDeque, path::Path};
+
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// One-time tips, shown the first time something comes up in play. Which
+// ones have been seen is kept in the player's profile so they don't come
+// up again. Only one is up at a time, the rest wait their turn.
+//-------------------------------------------------------------------------
+
+const HINTS_HEADER: &str = "space_survival hints 1";
+// how long each one stays up
+const HINT_SECS: u32 = 6;
+const HINT_FADE_SECS: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hint {
+    LowAir,
+    Collision,
+    PodOffScreen,
+}
+
+const HINTS: [Hint; 3] = [Hint::LowAir, Hint::Collision, Hint::PodOffScreen];
+
+impl Hint {
+    // name used in the profile and logs
+    pub fn key(self) -> &'static str {
+        match self {
+            Hint::LowAir => "low_air",
+            Hint::Collision => "collision",
+            Hint::PodOffScreen => "pod_off_screen",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        HINTS.iter().copied().find(|hint| hint.key() == key)
+    }
+
+    pub fn text(self) -> &'static str {
+        match self {
+            Hint::LowAir => "Air is running low! Grab the air pod before it runs out",
+            Hint::Collision => "Collisions knock you off course and stop any salvage, mind your speed",
+            Hint::PodOffScreen => "The pulsing blip at the edge of the screen points the way to the air pod",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Hints {
+    seen: Vec<Hint>,
+    // the one up and the tick it came up
+    showing: Option<(Hint, u32)>,
+    waiting: VecDeque<Hint>,
+}
+
+impl Hints {
+    // The hints a profile has seen. Without a file none have been.
+    pub fn load(path: &Path) -> Self {
+        let mut hints = Self::default();
+        let Ok(txt) = std::fs::read_to_string(path) else {
+            return hints;
+        };
+        let mut lines = txt.lines();
+        if lines.next() != Some(HINTS_HEADER) {
+            log::warn!("Ignoring hints {}: not a hints file", path.display());
+            return hints;
+        }
+        // unknown keys are from another version, and are dropped
+        hints.seen = lines.filter_map(|line| Hint::from_key(line.trim())).collect();
+        hints
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut txt = format!("{}\n", HINTS_HEADER);
+        for hint in &self.seen {
+            txt += hint.key();
+            txt += "\n";
+        }
+        txt
+    }
+
+    // Queue a hint if it hasn't been seen. Returns true if it's new, so the
+    // seen list needs saving.
+    pub fn trigger(&mut self, hint: Hint) -> bool {
+        if self.seen.contains(&hint) {
+            return false;
+        }
+        self.seen.push(hint);
+        self.waiting.push_back(hint);
+        true
+    }
+
+    // Retire the hint that's up once its time is over, and bring up the next
+    pub fn update(&mut self, tick: u32) {
+        let expired = self
+            .showing
+            .is_some_and(|(_, since)| tick >= since + HINT_SECS * TICKS_PER_SECOND as u32);
+        if self.showing.is_none() || expired {
+            self.showing = self.waiting.pop_front().map(|hint| (hint, tick));
+        }
+    }
+
+    // The hint that's up, and 0..1 for fading it in and out
+    pub fn current(&self, tick: u32) -> Option<(Hint, f64)> {
+        let (hint, since) = self.showing?;
+        let secs = tick.saturating_sub(since) as f64 / TICKS_PER_SECOND as f64;
+        let left = HINT_SECS as f64 - secs;
+        Some((hint, (secs.min(left) / HINT_FADE_SECS).clamp(0.0, 1.0)))
+    }
+}
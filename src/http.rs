@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+//-------------------------------------------------------------------------
+// Blocking http requests, for the online leaderboard and pushing run
+// metrics. Only made from worker threads, never the game's. The client is
+// only built with the `http` feature (which `online_leaderboard` turns on);
+// without it every request fails.
+//-------------------------------------------------------------------------
+
+// POST body to url. A response other than 2xx is an error too.
+#[cfg(feature = "http")]
+pub fn post(url: &str, content_type: &str, body: String, timeout: Duration) -> Result<(), String> {
+    reqwest::blocking::Client::new()
+        .post(url)
+        .header("Content-Type", content_type)
+        .body(body)
+        .timeout(timeout)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+// GET url's body as text. A response other than 2xx is an error too.
+#[cfg(feature = "http")]
+pub fn get(url: &str, timeout: Duration) -> Result<String, String> {
+    reqwest::blocking::Client::new()
+        .get(url)
+        .timeout(timeout)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "http"))]
+pub fn post(_url: &str, _content_type: &str, _body: String, _timeout: Duration) -> Result<(), String> {
+    Err("built without the http feature".to_string())
+}
+
+#[cfg(not(feature = "http"))]
+pub fn get(_url: &str, _timeout: Duration) -> Result<String, String> {
+    Err("built without the http feature".to_string())
+}
@@ -0,0 +1,205 @@
+use std::{collections::HashSet, time::Instant};
+
+use masonry::Vec2;
+use winit::{
+    event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::PhysicalKey,
+};
+
+//-------------------------------------------------------------------------
+// Input from the window and from devices, normalized into one stream of
+// events, each stamped with when it came in. Keys come from device events where possible (they
+// arrive a bit earlier and don't suffer from key repeat buffering on x11),
+// but wayland has no keyboard device events so there they come from the
+// window. Mouse buttons and scrolling come from the window, mouse motion
+// from the device (raw, so it isn't stopped by the edge of the window).
+//-------------------------------------------------------------------------
+
+// pixels per line for touchpads that scroll by pixel
+const SCROLL_PIXELS_PER_LINE: f64 = 40.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Button {
+    Key(PhysicalKey),
+    Mouse(MouseButton),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputKind {
+    Button { button: Button, state: ElementState },
+    // raw device units
+    MouseMotion { delta: Vec2 },
+    // in lines, positive y is scrolling up
+    Scroll { delta: Vec2 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputEvent {
+    pub kind: InputKind,
+    // when it came in from the window or device
+    pub time: Instant,
+}
+
+impl InputEvent {
+    pub fn now(kind: InputKind) -> Self {
+        Self { kind, time: Instant::now() }
+    }
+}
+
+// Where keys come from on this platform, see above
+pub fn keys_from_window(event_loop: &ActiveEventLoop) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use winit::platform::wayland::ActiveEventLoopExtWayland;
+        event_loop.is_wayland()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = event_loop;
+        false
+    }
+}
+
+pub fn from_window_event(event: &WindowEvent, keys_from_window: bool) -> Option<InputEvent> {
+    let kind = match event {
+        // held keys are down until they're let go, the repeats aren't presses
+        WindowEvent::KeyboardInput { event, .. } if keys_from_window && !event.repeat => InputKind::Button {
+            button: Button::Key(event.physical_key),
+            state: event.state,
+        },
+        WindowEvent::MouseInput { button, state, .. } => InputKind::Button {
+            button: Button::Mouse(*button),
+            state: *state,
+        },
+        WindowEvent::MouseWheel { delta, .. } => {
+            let delta = match delta {
+                MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x as f64, *y as f64),
+                MouseScrollDelta::PixelDelta(pos) => Vec2::new(pos.x, pos.y) / SCROLL_PIXELS_PER_LINE,
+            };
+            InputKind::Scroll { delta }
+        }
+        _ => return None,
+    };
+    Some(InputEvent::now(kind))
+}
+
+pub fn from_device_event(event: &DeviceEvent, keys_from_window: bool) -> Option<InputEvent> {
+    let kind = match event {
+        DeviceEvent::Key(key) if !keys_from_window => InputKind::Button {
+            button: Button::Key(key.physical_key),
+            state: key.state,
+        },
+        DeviceEvent::MouseMotion { delta } => InputKind::MouseMotion {
+            delta: Vec2::new(delta.0, delta.1),
+        },
+        _ => return None,
+    };
+    Some(InputEvent::now(kind))
+}
+
+// --- MARK: InputManager ---
+
+//-------------------------------------------------------------------------
+// InputManager keeps what's held down plus the events since the last
+// clear, which is once per tick (or per frame while the simulation is
+// paused). The tick they're cleared after is the one that uses them, and can
+// see when each came in.
+//-------------------------------------------------------------------------
+
+pub struct InputManager {
+    start: Instant,
+    // since the last clear, oldest first
+    events: Vec<InputEvent>,
+    down: HashSet<Button>,
+    mouse_motion: Vec2,
+    scroll: Vec2,
+}
+
+impl InputManager {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            events: Vec::default(),
+            down: HashSet::default(),
+            mouse_motion: Vec2::ZERO,
+            scroll: Vec2::ZERO,
+        }
+    }
+
+    pub fn input(&mut self, event: InputEvent) {
+        match event.kind {
+            InputKind::Button { button, state } => {
+                if state == ElementState::Pressed {
+                    self.down.insert(button);
+                } else {
+                    self.down.remove(&button);
+                }
+            }
+            InputKind::MouseMotion { delta } => self.mouse_motion += delta,
+            InputKind::Scroll { delta } => self.scroll += delta,
+        }
+        self.events.push(event);
+        let time_ms = event.time.saturating_duration_since(self.start).as_millis();
+        log::trace!("input time_ms={} kind={:?}", time_ms, event.kind);
+    }
+
+    // Events since the last clear, oldest first
+    pub fn events(&self) -> &[InputEvent] {
+        &self.events
+    }
+
+    pub fn is_down(&self, key: PhysicalKey) -> bool {
+        self.down.contains(&Button::Key(key))
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.down.contains(&Button::Mouse(button))
+    }
+
+    pub fn any_make(&self) -> bool {
+        self.events.iter().any(|event| button_event(event).is_some_and(|(_, pressed)| pressed))
+    }
+
+    // Release everything that's down, as if each had been let go
+    pub fn release_all(&mut self) {
+        for button in std::mem::take(&mut self.down) {
+            self.input(InputEvent::now(InputKind::Button {
+                button,
+                state: ElementState::Released,
+            }));
+        }
+    }
+
+    pub fn is_make(&self, key: PhysicalKey) -> bool {
+        self.events.iter().any(|event| button_event(event) == Some((Button::Key(key), true)))
+    }
+
+    pub fn is_break(&self, key: PhysicalKey) -> bool {
+        self.events.iter().any(|event| button_event(event) == Some((Button::Key(key), false)))
+    }
+
+    // Raw mouse motion since the last clear
+    pub fn get_mouse_motion(&self) -> Vec2 {
+        self.mouse_motion
+    }
+
+    // Lines scrolled since the last clear
+    pub fn get_scroll(&self) -> Vec2 {
+        self.scroll
+    }
+
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+        self.mouse_motion = Vec2::ZERO;
+        self.scroll = Vec2::ZERO;
+    }
+}
+
+// The button of a button event, and whether it was pressed
+fn button_event(event: &InputEvent) -> Option<(Button, bool)> {
+    match event.kind {
+        InputKind::Button { button, state } => Some((button, state == ElementState::Pressed)),
+        _ => None,
+    }
+}
@@ -0,0 +1,109 @@
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// Lives, for runs that don't end the first time the ship runs out of air.
+// How many ships a run gets is a difficulty setting, kept in the loadout so
+// replays and ghosts play back the same. With a spare one left, running out
+// of air costs a life and part of the score instead, and after a countdown
+// the ship comes back somewhere clear with some air and a shield for a few
+// seconds (GameWorld::update_lives). Once the spares are gone, running out
+// of air ends the run as usual.
+//-------------------------------------------------------------------------
+
+// the ship drifts, out of air, for this long before it comes back
+const RESPAWN_COUNTDOWN_TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+pub const RESPAWN_AIR_SECS: u64 = 30;
+// nothing gets at the ship for this long after it comes back
+pub const RESPAWN_SHIELD_TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+// room left around the ship where it comes back
+pub const RESPAWN_CLEARANCE: f64 = 250.0;
+// of the score each life costs
+const LIFE_SCORE_PENALTY: f64 = 0.2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Lives {
+    // ships the run started with on top of the first
+    spare: u32,
+    lost: u32,
+    // ticks until the ship comes back, while it's waiting to
+    countdown: Option<u32>,
+}
+
+impl Lives {
+    pub fn new(spare: u32) -> Self {
+        Self {
+            spare,
+            ..Self::default()
+        }
+    }
+
+    pub fn spare(&self) -> u32 {
+        self.spare
+    }
+
+    // spares not used up yet
+    pub fn left(&self) -> u32 {
+        self.spare - self.lost
+    }
+
+    pub fn is_respawning(&self) -> bool {
+        self.countdown.is_some()
+    }
+
+    // Seconds until the ship comes back, while it's waiting to
+    pub fn respawn_secs(&self) -> Option<f64> {
+        self.countdown.map(|ticks| ticks as f64 / TICKS_PER_SECOND as f64)
+    }
+
+    // The ship ran out of air. Uses up a spare and starts the countdown, false
+    // when there's none left and the run is over.
+    pub fn lose_life(&mut self) -> bool {
+        if self.left() == 0 {
+            return false;
+        }
+        self.lost += 1;
+        self.countdown = Some(RESPAWN_COUNTDOWN_TICKS);
+        true
+    }
+
+    // A tick has passed, true when the countdown has just run out and the ship
+    // should come back
+    pub fn update(&mut self) -> bool {
+        let Some(ticks) = self.countdown.as_mut() else {
+            return false;
+        };
+        *ticks = ticks.saturating_sub(1);
+        if *ticks > 0 {
+            return false;
+        }
+        self.countdown = None;
+        true
+    }
+
+    // "SPARE LOST COUNTDOWN", as saved
+    pub fn to_text(&self) -> String {
+        let countdown = self.countdown.map_or_else(|| "-".to_string(), |ticks| ticks.to_string());
+        format!("{} {} {}", self.spare, self.lost, countdown)
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        let &[spare, lost, countdown] = parts.as_slice() else {
+            return None;
+        };
+        let lives = Self {
+            spare: spare.parse().ok()?,
+            lost: lost.parse().ok()?,
+            countdown: match countdown {
+                "-" => None,
+                ticks => Some(ticks.parse().ok()?),
+            },
+        };
+        (lives.lost <= lives.spare).then_some(lives)
+    }
+}
+
+// What's left of a score after losing a life
+pub fn score_after_life_lost(score: u64) -> u64 {
+    score - (score as f64 * LIFE_SCORE_PENALTY).round() as u64
+}
@@ -0,0 +1,31 @@
+use std::{io::Write, time::Instant};
+
+//-------------------------------------------------------------------------
+// Logging goes through env_logger. Filtering is controlled by the RUST_LOG
+// environment variable, e.g. "info", "space_survival::game=debug,warn".
+// Lines start with the seconds since the game started rather than the
+// time of day.
+//-------------------------------------------------------------------------
+
+// our own logs at info, everyone else (wgpu is chatty) only warnings and up
+const DEFAULT_FILTER: &str = "space_survival=info,warn";
+
+pub fn init() {
+    let start = Instant::now();
+    let env = env_logger::Env::default().default_filter_or(DEFAULT_FILTER);
+    let result = env_logger::Builder::from_env(env)
+        .format(move |buf, record| {
+            writeln!(
+                buf,
+                "[{:8.3}s {:<5} {}] {}",
+                start.elapsed().as_secs_f64(),
+                record.level(),
+                record.target(),
+                record.args()
+            )
+        })
+        .try_init();
+    if result.is_err() {
+        eprintln!("Logger already initialized");
+    }
+}
@@ -1,16 +1,39 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
-use masonry::{app_driver::AppDriver, event_loop_runner::WindowState, widget::RootWidget, Vec2};
+use clap::Parser;
+use masonry::{app_driver::AppDriver, event_loop_runner::WindowState, widget::RootWidget};
 use render_mgr::RenderManager;
+use replay::Replay;
+use settings::Settings;
 use starfield_render::StarfieldRenderer;
 use winit::{self, application::ApplicationHandler, error::EventLoopError};
 
-#[cfg(target_os = "linux")]
-use winit::platform::wayland::ActiveEventLoopExtWayland;
-
 use xilem::{WidgetView, Xilem};
 
+mod actions;
+mod air_pod;
+
+mod arena;
+mod attract;
+
+mod audio;
+mod border_fx;
+mod boss;
+mod cli;
+mod checkpoint;
+mod combo;
+mod crash;
+mod cutscene;
+mod debris;
+mod faction;
+
+mod floating_text;
+
 mod game_view;
+mod grapple;
 use game_view::{GamePortal, GameView};
 
 mod game;
@@ -19,20 +42,82 @@ use xilem_render::XilemRenderer;
 
 mod game_shapes;
 
+mod haptics;
+mod hints;
+mod http;
+mod input;
+mod lives;
+mod logger;
+mod menu;
+
+mod metrics;
+mod navigation;
+mod nebula;
+mod perception;
+use metrics::MetricsRecorder;
+
+mod online_leaderboard;
+
+mod platform_services;
+mod pilot;
+
+mod profile;
+use profile::Profile;
+
+mod progression;
+use progression::Progression;
+
+mod quality;
+
+mod post_process;
+mod race;
+mod render_frame;
 mod render_mgr;
+mod repulsor;
+mod rewind;
+mod replay;
+mod rng;
+mod save;
+mod scenery;
+mod screenshot;
+mod settings;
+mod ship_style;
+mod sim_thread;
+use sim_thread::{Frames, SimThread, WindowMessage};
+
+mod spawn_table;
+use spawn_table::SpawnTables;
+
+mod sprites;
+use sprites::SpriteSheets;
+
 mod starfield_render;
+mod status;
+mod stress;
+mod text;
+mod timeline;
+mod tuning;
+mod turret;
+mod tutorial;
 mod xilem_render;
 
 mod vello_ext;
 
 fn app_logic(data: &mut GameState) -> impl WidgetView<GameState> {
-    GameView::new(data.clone())
+    GameView::new(data.world.clone(), data.frames.clone())
 }
 
-pub type GameState = Arc<Mutex<GameWorld>>;
+// The world is updated and drawn on the sim thread. The window sends it input
+// through the sim thread, and shows the frames the sim thread draws.
+#[derive(Clone)]
+pub struct GameState {
+    pub world: Arc<Mutex<GameWorld>>,
+    pub frames: Arc<Frames>,
+}
 
 impl ApplicationHandler<accesskit_winit::Event> for AppInterface {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        self.send_to_world(WindowMessage::Suspended(false));
         self.masonry_state.handle_resumed(event_loop);
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
         self.masonry_state
@@ -46,16 +131,25 @@ impl ApplicationHandler<accesskit_winit::Event> for AppInterface {
             return;
         };
 
+        let settings = self.game_state.world.lock().unwrap().get_settings().clone();
+
         if let Some((device, queue)) = self.masonry_state.get_render_device_and_queue() {
-            if let WindowState::Rendering { surface, .. } = self.masonry_state.get_window_state() {
-                self.render_mgr.setup(device);
+            if let WindowState::Rendering { .. } = self.masonry_state.get_window_state() {
+                // renderers kept over a suspend are made again by setup
+                self.render_mgr.setup(device, queue, surface_format, &settings);
+                if self.render_mgr.has_renderers() {
+                    return;
+                }
+                let sample_count = self.render_mgr.get_sample_count();
+                let scene_format = self.render_mgr.get_scene_format();
 
                 let global_buffer = self.render_mgr.get_global_buffer().unwrap();
-                let starfield = StarfieldRenderer::setup(device, queue, global_buffer, surface.format);
+                let num_stars = settings.quality().star_count();
+                let starfield = StarfieldRenderer::setup(device, queue, global_buffer, scene_format, sample_count, num_stars);
                 self.render_mgr.add_renderer(Box::new(starfield));
 
                 let global_buffer = self.render_mgr.get_global_buffer().unwrap();
-                let xilem_renderer = XilemRenderer::setup(device, queue, global_buffer, surface_format);
+                let xilem_renderer = XilemRenderer::setup(device, queue, global_buffer, scene_format, sample_count, settings.vello_aa());
                 self.render_mgr.add_renderer(Box::new(xilem_renderer));
             }
         }
@@ -63,7 +157,9 @@ impl ApplicationHandler<accesskit_winit::Event> for AppInterface {
 
     fn suspended(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Wait);
-        self.render_mgr.clear();
+        // the clock stops until resumed, and the renderers are kept to be made again then
+        self.send_to_world(WindowMessage::Suspended(true));
+        self.render_mgr.suspend();
         self.masonry_state.handle_suspended(event_loop);
     }
 
@@ -76,19 +172,12 @@ impl ApplicationHandler<accesskit_winit::Event> for AppInterface {
         if event == winit::event::WindowEvent::RedrawRequested {
             return;
         }
+        if let winit::event::WindowEvent::Focused(focused) = event {
+            self.send_to_world(WindowMessage::Focused(focused));
+        }
 
-        // wayland doesn't support keyboard device events so use window events instead
-        // Note: on x11 keyboard events have buffering issue with repeat keys, so can't need to
-        // use device events there. Also device events seem to arrive slightly earlier than window
-        // events so they are preferable.
-        #[cfg(target_os = "linux")]
-        if event_loop.is_wayland() {
-            if let winit::event::WindowEvent::KeyboardInput { .. } = &event{
-                self.game_state
-                .lock()
-                .unwrap()
-                .handle_window_key_event(&event);
-            }    
+        if let Some(input) = input::from_window_event(&event, input::keys_from_window(event_loop)) {
+            self.send_to_world(WindowMessage::Input(input));
         }
 
         self.masonry_state.handle_window_event(
@@ -107,91 +196,252 @@ impl ApplicationHandler<accesskit_winit::Event> for AppInterface {
         self.masonry_state.handle_user_event(event_loop, event, self.app_driver.as_mut());
     }
 
-    fn device_event(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop, _device_id: winit::event::DeviceId, event: winit::event::DeviceEvent,
+    fn device_event(&mut self, event_loop: &winit::event_loop::ActiveEventLoop, _device_id: winit::event::DeviceId, event: winit::event::DeviceEvent,
     ) {
-        self.game_state
-            .lock()
-            .unwrap()
-            .handle_device_event(&event);
+        if let Some(input) = input::from_device_event(&event, input::keys_from_window(event_loop)) {
+            self.send_to_world(WindowMessage::Input(input));
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        // the world must stop ticking before the run is wrapped up
+        drop(self.sim_thread.take());
+        self.game_state.world.lock().unwrap().stop_attract();
+        self.save_recording();
+        self.game_state.world.lock().unwrap().finish_autosave();
+        self.game_state.world.lock().unwrap().finish_metrics();
     }
 
     fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        {
-            let mut game_state = self.game_state.lock().unwrap();
-            game_state.update();
-            if game_state.is_exit_ready() {
-                event_loop.exit();
-            }
+        let frames = self.game_state.frames.clone();
+        if frames.is_exit_ready() {
+            event_loop.exit();
+        }
 
-            if !game_state.ready_for_redraw() {
+        if let WindowState::Rendering { window, .. } = self.masonry_state.get_window_state() {
+            let size = window.inner_size();
+            frames.set_window_size(size.width, size.height, window.scale_factor());
+        }
+
+        // while paused in the background, draw the pause screen once and then
+        // sleep instead of spinning. The sim thread picks up the key that resumes
+        // the game after the event that woke us, so check back now and then.
+        if frames.is_idle() {
+            let wake = std::time::Instant::now() + IDLE_POLL;
+            event_loop.set_control_flow(winit::event_loop::ControlFlow::WaitUntil(wake));
+            if self.idle_frame_drawn {
                 return;
             }
+        } else {
+            event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
+            self.idle_frame_drawn = false;
+        }
 
-            // The rest of this method is rendering
-            game_state.interpolate_transforms();
-
-            // Need to let go of mutex because render will need game data
-            drop(game_state);
-
-            self.masonry_state.get_root().edit_root_widget(|mut root| {
-                root.downcast::<RootWidget<GamePortal>>()
-                    .get_element()
-                    .ctx
-                    .request_paint();
-            });
-    
-            self.render_mgr.render(&mut self.masonry_state, &self.game_state);
-
-            // TODO: masonry calls poll here. Should we do the same?
-//            if let Some((device, _queue)) = self.masonry_state.get_render_device_and_queue() {
-//                device.poll(vello::wgpu::Maintain::Wait);
-//            }
+        // the rest of this method is rendering, once the sim thread has drawn a new frame
+        if !frames.has_new_frame() {
+            return;
         }
+        self.idle_frame_drawn = frames.is_idle();
+
+        self.masonry_state.get_root().edit_root_widget(|mut root| {
+            root.downcast::<RootWidget<GamePortal>>()
+                .get_element()
+                .ctx
+                .request_paint();
+        });
+
+        self.render_mgr.render(&mut self.masonry_state, &self.game_state.frames);
     }
 }
 
-fn create_game_world() -> GameWorld {
+// how often the event loop checks on the sim thread while the game is idle
+const IDLE_POLL: std::time::Duration = std::time::Duration::from_millis(100);
+
+fn time_seed() -> u64 {
     // generate seed from time
     let time = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap();
-    let seed = time.as_secs() as u64 ^ time.subsec_nanos() as u64;
-
-    let mut game_world = GameWorld::new(seed, 4000.0);
-
-    // add the player ship at the origin
-    let world_center = Vec2::new(0.0, 0.0);
-    let ship_id = game_world.add_ship(world_center..world_center);
-    game_world.set_control_object(ship_id);
-
-    let upper_left = game_world.get_spatial_db().get_min();
-    let lower_right = game_world.get_spatial_db().get_max();
+    time.as_secs() as u64 ^ time.subsec_nanos() as u64
+}
 
-    // add some asteroids
-    for _ in 0..80 {
-        game_world.add_asteroid(upper_left..lower_right, 0.0..10.0, 0.0..0.1);
+// Run the simulation as fast as possible without a window until the game is over or a replay runs out
+fn run_headless(game_world: &mut GameWorld) {
+    while !game_world.is_game_over() && !game_world.is_exit_ready() && !game_world.is_playback_finished() {
+        game_world.tick();
     }
 
-    game_world.add_air_pod(upper_left..lower_right);
+    println!(
+        "Seed {}: survived {:.1} seconds with score {}",
+        game_world.get_seed(),
+        game_world.get_tick_count() as f64 / game::TICKS_PER_SECOND as f64,
+        game_world.get_score()
+    );
+}
 
-    game_world
+fn exit_with_usage(err: &str) -> ! {
+    eprintln!("{}\n\n{}", err, cli::usage());
+    std::process::exit(2);
 }
 
 pub struct AppInterface {
     masonry_state: masonry::event_loop_runner::MasonryState<'static>,
     app_driver: Box<dyn AppDriver>,
     game_state: GameState,
+    // None once stopped for exiting
+    sim_thread: Option<SimThread>,
     render_mgr: RenderManager,
+    record_path: Option<PathBuf>,
+    // the pause screen has been drawn since the game went idle
+    idle_frame_drawn: bool,
+}
+
+impl AppInterface {
+    // Nothing is passed on once the sim thread has stopped for exiting
+    fn send_to_world(&self, message: WindowMessage) {
+        if let Some(sim_thread) = self.sim_thread.as_ref() {
+            sim_thread.send(message);
+        }
+    }
+
+    fn save_recording(&mut self) {
+        let Some(path) = self.record_path.take() else {
+            return;
+        };
+        if let Some(recording) = self.game_state.world.lock().unwrap().take_recording() {
+            if let Err(err) = recording.save(&path) {
+                log::error!("Failed to save recording to {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+// The preset picked for this machine on the first run, probing for it if there isn't one yet
+fn pick_quality(profile: &Profile) -> quality::Quality {
+    let path = profile.path(profile::QUALITY_FILE);
+    if let Some(quality) = quality::Quality::load(&path) {
+        return quality;
+    }
+    let quality = quality::probe();
+    log::info!("quality_picked quality={}", quality.name());
+    quality.save(&path);
+    quality
 }
 
 fn main() -> Result<(), EventLoopError> {
-    let game_state = GameState::new(Mutex::new(create_game_world()));
+    logger::init();
+
+    let args = cli::Args::parse();
+
+    let profile = Profile::startup(args.profile.as_deref()).unwrap_or_else(|err| exit_with_usage(&err));
+    profile.remember();
+    let crash_reporter = crash::CrashReporter::install(profile.path(crash::CRASHES_DIR));
+    let mut settings = match &args.config {
+        Some(path) => Settings::load(path),
+        None => profile.load_settings(),
+    }
+    .unwrap_or_else(|err| exit_with_usage(&err));
+    let bindings = profile.load_bindings().unwrap_or_else(|err| exit_with_usage(&err));
+    if settings.quality.is_none() && !args.headless && args.stress.is_none() {
+        settings.quality = Some(pick_quality(&profile));
+    }
+
+    // a replay has to run on the same world it was recorded on
+    let replay = args.replay.as_ref().map(|path| Replay::load(path).unwrap_or_else(|err| exit_with_usage(&err)));
+    let (seed, extent, num_asteroids, arena) = match &replay {
+        Some(replay) => (replay.seed, replay.extent, replay.asteroids, (replay.arena, replay.arena_shape)),
+        None => {
+            let asteroids = args.stress.unwrap_or(args.asteroids);
+            (args.seed.unwrap_or_else(time_seed), args.extent, asteroids, (args.arena, args.border))
+        }
+    };
+
+    // replays were made with the modifiers already in their world parameters
+    let modifiers = match &replay {
+        Some(_) => Vec::new(),
+        None => Progression::load(&profile.path(profile::PROGRESSION_FILE)).modifiers(),
+    };
+    let spawn_tables = match &args.spawn_tables {
+        Some(path) => SpawnTables::load(path).unwrap_or_else(|err| exit_with_usage(&err)),
+        None => SpawnTables::default(),
+    };
+    if replay.as_ref().is_some_and(|replay| replay.spawn_tables != spawn_tables.hash()) {
+        exit_with_usage("Replay was recorded with other spawn tables (see --spawn-tables)");
+    }
+    let spawn_tables = Arc::new(spawn_tables);
+    let mut game_world = GameWorld::generate(seed, extent, num_asteroids, arena, &modifiers, spawn_tables);
+    // replays have been played before, so they don't count, and nobody plays a stress run
+    if settings.metrics && replay.is_none() && args.stress.is_none() {
+        let path = PathBuf::from(metrics::METRICS_PATH);
+        game_world.enable_metrics(MetricsRecorder::new(path, settings.metrics_push.clone()));
+    }
+    game_world.enable_crash_reports(crash_reporter);
+    if args.check_invariants {
+        game_world.enable_invariant_checks();
+    }
+    let autosave_path = profile.path(save::AUTOSAVE_PATH);
+    let leaderboard_url = settings.leaderboard_url.clone();
+    game_world.set_profile(profile, settings, bindings);
+    if let (Some(url), None) = (leaderboard_url.as_ref(), replay.as_ref()) {
+        game_world.enable_online_leaderboard(url);
+    }
+    if args.record.is_some() {
+        let course = game_world.course_id();
+        let mut recording = Replay::new(course.seed, course.extent, course.asteroids);
+        (recording.arena, recording.arena_shape) = (course.arena, course.arena_shape);
+        game_world.start_recording(recording);
+    }
+    let playing_replay = replay.is_some();
+    if let Some(replay) = replay {
+        game_world.apply_loadout(&replay.loadout);
+        match replay.mode {
+            game::GameMode::Endless => {}
+            game::GameMode::Escort => game_world.start_escort(),
+            game::GameMode::Race => game_world.start_race(),
+            game::GameMode::Tutorial => game_world.start_tutorial(),
+        }
+        game_world.start_playback(replay);
+    }
+
+    if let Some(asteroids) = args.stress {
+        game_world.disable_player();
+        stress::run_stress(&mut game_world, asteroids);
+        return Ok(());
+    }
+    if args.headless {
+        run_headless(&mut game_world);
+        game_world.finish_metrics();
+        if let (Some(path), Some(recording)) = (&args.record, game_world.take_recording()) {
+            if let Err(err) = recording.save(path) {
+                log::error!("Failed to save recording to {}: {}", path.display(), err);
+            }
+        }
+        return Ok(());
+    }
+
+    game_world.enable_feedback();
+    game_world.set_sprites(Arc::new(SpriteSheets::load(Path::new(sprites::SPRITES_DIR))));
+
+    // replays just play, no menu and nothing worth autosaving
+    if !playing_replay {
+        game_world.show_main_menu(save::load_save(&autosave_path));
+        game_world.enable_autosave();
+    }
+
+    let game_state = GameState {
+        world: Arc::new(Mutex::new(game_world)),
+        frames: Arc::new(Frames::new()),
+    };
+    let sim_thread = SimThread::start(game_state.world.clone(), game_state.frames.clone());
 
     let window_size = winit::dpi::LogicalSize::new(1200.0, 1200.0);
-    let window_attributes = winit::window::Window::default_attributes()
+    let mut window_attributes = winit::window::Window::default_attributes()
         .with_title("Space Survival".to_string())
         .with_resizable(true)
         .with_min_inner_size(window_size);
+    if args.fullscreen {
+        window_attributes = window_attributes.with_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+    }
 
     let xilem = Xilem::new(game_state.clone(), app_logic);
 
@@ -204,6 +454,9 @@ fn main() -> Result<(), EventLoopError> {
         masonry_state,
         app_driver: Box::new(xilem.driver),
         game_state,
+        sim_thread: Some(sim_thread),
+        record_path: args.record,
+        idle_frame_drawn: false,
     };
     event_loop.run_app(&mut app)
 }
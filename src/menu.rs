@@ -0,0 +1,299 @@
+use masonry::{parley::FontContext, Affine, Point, Rect, Size, Vec2};
+use vello::Scene;
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+use crate::{
+    game_shapes::{flame_scene, ship_shape},
+    input::InputManager,
+    profile::MAX_NAME_LEN,
+    progression::{Progression, UNLOCKS},
+    ship_style::ShipStyle,
+    text::{draw_text, TextStyle},
+};
+
+//-------------------------------------------------------------------------
+// Simple keyboard driven menu drawn over the (paused) game. Up/down to
+// select, enter or space to activate. The new profile item takes a name
+// typed in before it counts as activated. The paint shop shows the ship as
+// it's painted next to the items.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuAction {
+    Endless,
+    Escort,
+    TimeAttack,
+    Tutorial,
+    Continue,
+    LoadGame,
+    Profiles,
+    Hangar,
+    // index into UNLOCKS, buys it or switches it on or off
+    Unlock(usize),
+    PaintShop,
+    // each goes on to the next choice there is, back to the first after the last
+    NextHullPaint,
+    NextStripes,
+    NextFlame,
+    // index into the profile list the menu was made with
+    SelectProfile(usize),
+    // the typed name is taken with take_name()
+    NewProfile,
+    LoadSlot(usize),
+    SaveSlot(usize),
+    Back,
+}
+
+// Keys that type a profile name
+const NAME_KEYS: &[(KeyCode, char)] = &[
+    (KeyCode::KeyA, 'a'),
+    (KeyCode::KeyB, 'b'),
+    (KeyCode::KeyC, 'c'),
+    (KeyCode::KeyD, 'd'),
+    (KeyCode::KeyE, 'e'),
+    (KeyCode::KeyF, 'f'),
+    (KeyCode::KeyG, 'g'),
+    (KeyCode::KeyH, 'h'),
+    (KeyCode::KeyI, 'i'),
+    (KeyCode::KeyJ, 'j'),
+    (KeyCode::KeyK, 'k'),
+    (KeyCode::KeyL, 'l'),
+    (KeyCode::KeyM, 'm'),
+    (KeyCode::KeyN, 'n'),
+    (KeyCode::KeyO, 'o'),
+    (KeyCode::KeyP, 'p'),
+    (KeyCode::KeyQ, 'q'),
+    (KeyCode::KeyR, 'r'),
+    (KeyCode::KeyS, 's'),
+    (KeyCode::KeyT, 't'),
+    (KeyCode::KeyU, 'u'),
+    (KeyCode::KeyV, 'v'),
+    (KeyCode::KeyW, 'w'),
+    (KeyCode::KeyX, 'x'),
+    (KeyCode::KeyY, 'y'),
+    (KeyCode::KeyZ, 'z'),
+    (KeyCode::Digit0, '0'),
+    (KeyCode::Digit1, '1'),
+    (KeyCode::Digit2, '2'),
+    (KeyCode::Digit3, '3'),
+    (KeyCode::Digit4, '4'),
+    (KeyCode::Digit5, '5'),
+    (KeyCode::Digit6, '6'),
+    (KeyCode::Digit7, '7'),
+    (KeyCode::Digit8, '8'),
+    (KeyCode::Digit9, '9'),
+    (KeyCode::Minus, '_'),
+];
+
+const MAIN_MENU_TITLE: &str = "SPACE SURVIVAL";
+
+pub struct Menu {
+    title: &'static str,
+    items: Vec<(MenuAction, String)>,
+    selected: usize,
+    // name being typed for a new profile
+    name_entry: Option<String>,
+    // ship drawn next to the items
+    preview: Option<ShipStyle>,
+}
+
+impl Menu {
+    fn new(title: &'static str, items: Vec<(MenuAction, String)>) -> Self {
+        Self {
+            title,
+            items,
+            selected: 0,
+            name_entry: None,
+            preview: None,
+        }
+    }
+
+    pub fn main_menu(can_continue: bool, can_load: bool, profile: &str, credits: u64) -> Self {
+        // continuing is most likely what the player wants if it's available
+        let mut items = Vec::new();
+        if can_continue {
+            items.push((MenuAction::Continue, "Continue".to_string()));
+        }
+        items.push((MenuAction::Endless, "Endless".to_string()));
+        items.push((MenuAction::Escort, "Escort Mission".to_string()));
+        items.push((MenuAction::TimeAttack, "Time Attack".to_string()));
+        items.push((MenuAction::Tutorial, "Tutorial".to_string()));
+        if can_load {
+            items.push((MenuAction::LoadGame, "Load Game".to_string()));
+        }
+        items.push((MenuAction::Hangar, format!("Hangar: {} credits", credits)));
+        items.push((MenuAction::PaintShop, "Paint Shop".to_string()));
+        items.push((MenuAction::Profiles, format!("Profile: {}", profile)));
+        Self::new(MAIN_MENU_TITLE, items)
+    }
+
+    pub fn is_main(&self) -> bool {
+        self.title == MAIN_MENU_TITLE
+    }
+
+    // Everything there is to unlock, with what it costs or whether it's on
+    pub fn hangar(progression: &Progression, selected: usize) -> Self {
+        let mut items: Vec<_> = UNLOCKS
+            .iter()
+            .enumerate()
+            .map(|(idx, unlock)| {
+                let state = if progression.is_active(*unlock) {
+                    "on".to_string()
+                } else if progression.is_owned(*unlock) {
+                    "off".to_string()
+                } else {
+                    format!("{} credits", unlock.cost())
+                };
+                (MenuAction::Unlock(idx), format!("{}: {}", unlock.name(), state))
+            })
+            .collect();
+        items.push((MenuAction::Back, format!("Back ({} credits left)", progression.credits)));
+        let mut menu = Self::new("HANGAR", items);
+        menu.selected = selected;
+        menu
+    }
+
+    // Paint, stripes and flame of the player's ship, each item going through the choices
+    pub fn paint_shop(style: ShipStyle, selected: usize) -> Self {
+        let items = vec![
+            (MenuAction::NextHullPaint, format!("Hull: {}", style.hull.key())),
+            (MenuAction::NextStripes, format!("Stripes: {}", style.stripes.key())),
+            (MenuAction::NextFlame, format!("Flame: {}", style.flame.key())),
+            (MenuAction::Back, "Back".to_string()),
+        ];
+        let mut menu = Self::new("PAINT SHOP", items);
+        menu.selected = selected;
+        menu.preview = Some(style);
+        menu
+    }
+
+    pub fn profiles(names: &[String], current: &str) -> Self {
+        let mut items: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (MenuAction::SelectProfile(idx), name.clone()))
+            .collect();
+        items.push((MenuAction::NewProfile, "New Profile".to_string()));
+        items.push((MenuAction::Back, "Back".to_string()));
+        let mut menu = Self::new("PROFILES", items);
+        menu.selected = names.iter().position(|name| name == current).unwrap_or(0);
+        menu
+    }
+
+    // Slots that have a save, by their description
+    pub fn load_game(slots: &[Option<String>]) -> Self {
+        let mut items: Vec<_> = slots
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, slot)| {
+                let desc = slot.as_ref()?;
+                Some((MenuAction::LoadSlot(idx), format!("Slot {}: {}", idx + 1, desc)))
+            })
+            .collect();
+        items.push((MenuAction::Back, "Back".to_string()));
+        Self::new("LOAD GAME", items)
+    }
+
+    // Every slot, saving over what's there
+    pub fn save_game(slots: &[Option<String>]) -> Self {
+        let mut items: Vec<_> = slots
+            .iter()
+            .enumerate()
+            .map(|(idx, slot)| {
+                let desc = slot.as_deref().unwrap_or("empty");
+                (MenuAction::SaveSlot(idx), format!("Slot {}: {}", idx + 1, desc))
+            })
+            .collect();
+        items.push((MenuAction::Back, "Resume".to_string()));
+        Self::new("SAVE GAME", items)
+    }
+
+    // The name typed for a new profile, once NewProfile has been activated
+    pub fn take_name(&mut self) -> Option<String> {
+        self.name_entry.take()
+    }
+
+    pub fn handle_input(&mut self, input: &InputManager) -> Option<MenuAction> {
+        let pressed = |keys: &[KeyCode]| keys.iter().any(|key| input.is_make(PhysicalKey::Code(*key)));
+
+        if let Some(name) = self.name_entry.as_mut() {
+            for (key, c) in NAME_KEYS {
+                if input.is_make(PhysicalKey::Code(*key)) && name.len() < MAX_NAME_LEN {
+                    name.push(*c);
+                }
+            }
+            if pressed(&[KeyCode::Backspace]) {
+                // backing out of an empty name cancels it
+                if name.pop().is_none() {
+                    self.name_entry = None;
+                }
+            }
+            if pressed(&[KeyCode::Enter, KeyCode::NumpadEnter]) && !name.is_empty() {
+                return Some(MenuAction::NewProfile);
+            }
+            return None;
+        }
+
+        if pressed(&[KeyCode::ArrowUp, KeyCode::KeyW]) {
+            self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+        }
+        if pressed(&[KeyCode::ArrowDown, KeyCode::KeyS]) {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+        if pressed(&[KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Space]) {
+            let (action, _) = self.items[self.selected];
+            if action == MenuAction::NewProfile {
+                self.name_entry = Some(String::new());
+                return None;
+            }
+            return Some(action);
+        }
+        None
+    }
+
+    pub fn render(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        // dim the game behind the menu
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0, 0, 0, 0xa0),
+            None,
+            &Rect::from_origin_size(Point::ZERO, size),
+        );
+
+        let center = Point::new(0.5 * size.width, 0.5 * size.height);
+        let title_style = TextStyle {
+            font_size: 64.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0xff),
+            alignment: xilem::TextAlignment::Middle,
+        };
+        draw_text(scene, ctx, self.title, &title_style, center - Vec2::new(0.0, 80.0), Vec2::new(0.5, 1.0));
+
+        for (idx, (action, label)) in self.items.iter().enumerate() {
+            let label = match self.name_entry.as_ref() {
+                Some(name) if *action == MenuAction::NewProfile => format!("Name: {}_", name),
+                _ => label.clone(),
+            };
+            let (txt, color) = if idx == self.selected {
+                (format!("> {} <", label), xilem::Color::rgb8(0x0, 0xd4, 0xf8))
+            } else {
+                (label, xilem::Color::rgb8(0xc0, 0xc0, 0xc0))
+            };
+            let style = TextStyle {
+                font_size: 32.0,
+                color,
+                alignment: xilem::TextAlignment::Middle,
+            };
+            let anchor = center + Vec2::new(0.0, 48.0 * idx as f64);
+            draw_text(scene, ctx, &txt, &style, anchor, Vec2::new(0.5, 0.0));
+        }
+
+        if let Some(style) = self.preview.as_ref() {
+            // to the right of the items, nose up and with the engine lit
+            let transform = Affine::scale_non_uniform(3.0, -3.0)
+                .then_translate(Vec2::new(0.8 * size.width, center.y + 80.0));
+            scene.append(&flame_scene(0.3, style.flame), Some(transform));
+            scene.append(ship_shape(style).scene(), Some(transform));
+        }
+    }
+}
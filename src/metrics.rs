@@ -0,0 +1,242 @@
+use std::{fmt::Write as _, io::Write, path::PathBuf, thread::JoinHandle, time::Duration};
+
+use crate::{
+    game::{GameMode, TICKS_PER_SECOND},
+    http,
+};
+
+//-------------------------------------------------------------------------
+// Opt-in (`metrics = true` in the settings file) recorder of per-run
+// aggregates for balancing: how long the run lasted, air pods collected,
+// average speed and how it ended. Each run is appended as one JSON line to
+// METRICS_PATH, and optionally pushed to a Prometheus pushgateway
+// (`metrics_push = host:port`, needs the `http` feature).
+//-------------------------------------------------------------------------
+
+pub const METRICS_PATH: &str = "space_survival.metrics.jsonl";
+
+// an air loss this recent when the air runs out gets the blame for it
+const BLAME_SECS: u64 = 10;
+const PUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct MetricsRecorder {
+    path: PathBuf,
+    push_addr: Option<String>,
+    pods: u32,
+    speed_sum: f64,
+    speed_ticks: u32,
+    // what last took air from the player, and on which tick
+    last_air_loss: Option<(&'static str, u32)>,
+    finished: bool,
+    push_thread: Option<JoinHandle<()>>,
+}
+
+// How a run went, at the end of it
+pub struct RunSummary {
+    pub mode: GameMode,
+    pub seed: u64,
+    pub arena: String,
+    pub ticks: u32,
+    pub score: u64,
+    pub end: &'static str,
+}
+
+impl MetricsRecorder {
+    pub fn new(path: PathBuf, push_addr: Option<String>) -> Self {
+        Self {
+            path,
+            push_addr,
+            pods: 0,
+            speed_sum: 0.0,
+            speed_ticks: 0,
+            last_air_loss: None,
+            finished: false,
+            push_thread: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Player speed (units/tick), once per tick
+    pub fn sample_speed(&mut self, speed: f64) {
+        self.speed_sum += speed;
+        self.speed_ticks += 1;
+    }
+
+    pub fn pod_collected(&mut self) {
+        self.pods += 1;
+    }
+
+    pub fn air_lost(&mut self, cause: &'static str, tick: u32) {
+        self.last_air_loss = Some((cause, tick));
+    }
+
+    // The run went back to tick (a rewind or a checkpoint). Air lost after it
+    // never happened, and a run that was recorded already is recorded again
+    // when it ends, with what comes after.
+    pub fn went_back(&mut self, tick: u32) {
+        self.last_air_loss = self.last_air_loss.filter(|&(_, loss_tick)| loss_tick <= tick);
+        if self.finished {
+            self.pods = 0;
+            self.speed_sum = 0.0;
+            self.speed_ticks = 0;
+            self.finished = false;
+        }
+    }
+
+    // Why the air ran out: whatever took air just before, otherwise it simply ran out
+    pub fn death_cause(&self, tick: u32) -> &'static str {
+        let blame_ticks = (BLAME_SECS * TICKS_PER_SECOND) as u32;
+        match self.last_air_loss {
+            Some((cause, loss_tick)) if tick.saturating_sub(loss_tick) <= blame_ticks => cause,
+            _ => "suffocated",
+        }
+    }
+
+    // Record the run, only the first call counts
+    pub fn finish(&mut self, summary: &RunSummary) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        let secs = summary.ticks as f64 / TICKS_PER_SECOND as f64;
+        let avg_speed = if self.speed_ticks > 0 {
+            self.speed_sum / self.speed_ticks as f64 * TICKS_PER_SECOND as f64
+        } else {
+            0.0
+        };
+        log::info!(
+            "run_metrics mode={} secs={:.1} pods={} avg_speed={:.1} end={}",
+            summary.mode.key(),
+            secs,
+            self.pods,
+            avg_speed,
+            summary.end
+        );
+
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let line = serde_json::json!({
+            "time": time,
+            "mode": summary.mode.key(),
+            "seed": summary.seed,
+            "arena": summary.arena,
+            "ticks": summary.ticks,
+            "secs": secs,
+            "score": summary.score,
+            "pods": self.pods,
+            "avg_speed": avg_speed,
+            "death_cause": summary.end,
+        });
+        if let Err(err) = append(&self.path, &format!("{}\n", line)) {
+            log::error!("Failed to write metrics to {}: {}", self.path.display(), err);
+        }
+
+        let Some(addr) = self.push_addr.clone() else {
+            return;
+        };
+        let labels = format!(
+            "mode=\"{}\",death_cause=\"{}\"",
+            label_value(summary.mode.key()),
+            label_value(summary.end)
+        );
+        let mut body = String::new();
+        for (name, value) in [
+            ("run_seconds", secs),
+            ("run_score", summary.score as f64),
+            ("run_pods_collected", self.pods as f64),
+            ("run_avg_speed", avg_speed),
+        ] {
+            writeln!(body, "# TYPE space_survival_{} gauge", name).unwrap();
+            writeln!(body, "space_survival_{}{{{}}} {}", name, labels, value).unwrap();
+        }
+        // don't hold up the game on the network
+        self.push_thread = Some(std::thread::spawn(move || {
+            if let Err(err) = push(&addr, body) {
+                log::warn!("Failed to push metrics to {}: {}", addr, err);
+            }
+        }));
+    }
+
+    // Wait for a push in flight, so it isn't cut off by the game exiting
+    pub fn flush(&mut self) {
+        if let Some(thread) = self.push_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn append(path: &PathBuf, line: &str) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())
+}
+
+// POST the text exposition format to a pushgateway at "host:port"
+fn push(addr: &str, body: String) -> Result<(), String> {
+    let host = addr.strip_prefix("http://").unwrap_or(addr).trim_end_matches('/');
+    let url = format!("http://{}/metrics/job/space_survival", host);
+    http::post(&url, "text/plain; version=0.0.4", body, PUSH_TIMEOUT)
+}
+
+// Escape a label value for the text exposition format
+fn label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(end: &'static str) -> RunSummary {
+        RunSummary {
+            mode: GameMode::Endless,
+            seed: 7,
+            arena: "fixed".to_string(),
+            ticks: 900,
+            score: 1200,
+            end,
+        }
+    }
+
+    #[test]
+    fn air_loss_rewound_past_is_forgotten() {
+        let mut metrics = MetricsRecorder::new(PathBuf::new(), None);
+        metrics.air_lost("pirate", 600);
+        assert_eq!(metrics.death_cause(650), "pirate");
+        // going back before the loss, then running out of air
+        assert_eq!(metrics.death_cause(500), "pirate");
+        metrics.went_back(450);
+        assert_eq!(metrics.death_cause(500), "suffocated");
+
+        metrics.air_lost("turret_bolt", 400);
+        metrics.went_back(420);
+        assert_eq!(metrics.death_cause(430), "turret_bolt");
+    }
+
+    #[test]
+    fn run_that_went_back_is_recorded_again() {
+        let path = std::env::temp_dir().join(format!("space_survival_metrics_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut metrics = MetricsRecorder::new(path.clone(), None);
+        metrics.pod_collected();
+        metrics.finish(&summary("suffocated"));
+        metrics.finish(&summary("suffocated"));
+        assert!(metrics.is_finished());
+
+        metrics.went_back(300);
+        assert!(!metrics.is_finished());
+        metrics.finish(&summary("quit"));
+        let txt = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let runs: Vec<serde_json::Value> =
+            txt.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(runs.len(), 2);
+        assert_eq!((runs[0]["pods"].as_u64(), runs[1]["pods"].as_u64()), (Some(1), Some(0)));
+        assert_eq!(runs[1]["death_cause"], "quit");
+    }
+}
@@ -0,0 +1,332 @@
+use std::{cmp::Ordering, collections::BinaryHeap, f64::consts::SQRT_2};
+
+use masonry::Vec2;
+
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// Route planning for AI ships that have somewhere to be, so traders and
+// the escort convoy fly around thick clusters of rock instead of plowing
+// straight through them. The field is seen as a coarse grid, one cell per
+// SpatialDb node, each with how cluttered it is (the part of it covered by
+// rock and wrecks, see GameWorld::update_nav_grid). A* finds the
+// cheapest way across, going through a cell costing more the more
+// cluttered it is, and the path is then smoothed by cutting corners where
+// a straight line doesn't cross anything worse than what it cuts out.
+//
+// The ship follows its NavPath a waypoint at a time and plans again every
+// now and then, as the rocks drift around. Saves keep the grid and the
+// paths, so a continued run plans the same way the original one did.
+//-------------------------------------------------------------------------
+
+// how much more crossing a cell that's all rock costs than an empty one
+const CLUTTER_COST: f64 = 20.0;
+// clutter of the cells outside the walls, never gone through
+pub const BLOCKED_CLUTTER: f64 = f64::INFINITY;
+// plans again after this long, or when the goal moves
+const REPLAN_TICKS: u32 = 2 * TICKS_PER_SECOND as u32;
+// of a cell, getting this close to a waypoint moves on to the next one
+const WAYPOINT_REACHED: f64 = 0.5;
+
+type Cell = (i32, i32);
+
+pub struct NavGrid {
+    min: Vec2,
+    cell_size: f64,
+    dim: i32,
+    // part of each cell covered (0..1, more where things overlap), row by row
+    clutter: Vec<f64>,
+}
+
+impl NavGrid {
+    pub fn new(min: Vec2, cell_size: f64, dim: u32, clutter: Vec<f64>) -> Self {
+        Self {
+            min,
+            cell_size,
+            dim: dim as i32,
+            clutter,
+        }
+    }
+
+    // How cluttered each cell is, row by row, for saving the grid
+    pub fn clutter(&self) -> &[f64] {
+        &self.clutter
+    }
+
+    fn cell(&self, pos: Vec2) -> Cell {
+        let x = ((pos.x - self.min.x) / self.cell_size).floor() as i32;
+        let y = ((pos.y - self.min.y) / self.cell_size).floor() as i32;
+        (x.clamp(0, self.dim - 1), y.clamp(0, self.dim - 1))
+    }
+
+    fn center(&self, (x, y): Cell) -> Vec2 {
+        self.min + self.cell_size * Vec2::new(x as f64 + 0.5, y as f64 + 0.5)
+    }
+
+    // of going a unit through the cell, infinite when it's blocked
+    fn cost(&self, (x, y): Cell) -> f64 {
+        1.0 + CLUTTER_COST * self.clutter[(x + y * self.dim) as usize]
+    }
+
+    // Waypoints from from to to, ending with to, going around clutter. Just to
+    // when there's no way through.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Vec<Vec2> {
+        let (start, goal) = (self.cell(from), self.cell(to));
+        let Some(cells) = self.search(start, goal) else {
+            return vec![to];
+        };
+        let cells = self.smooth(cells);
+        // the first and last cells are where the ship and the goal are
+        let inner = cells.len().saturating_sub(1);
+        let mut path: Vec<Vec2> = cells[1.min(inner)..inner].iter().map(|&cell| self.center(cell)).collect();
+        path.push(to);
+        path
+    }
+
+    // A* across the cells, 8 ways from each one
+    fn search(&self, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        let num_cells = (self.dim * self.dim) as usize;
+        let idx = |(x, y): Cell| (x + y * self.dim) as usize;
+        let heuristic = |(x, y): Cell| {
+            let (dx, dy) = ((x - goal.0).abs() as f64, (y - goal.1).abs() as f64);
+            dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy)
+        };
+        let mut best = vec![f64::INFINITY; num_cells];
+        let mut came_from: Vec<Option<Cell>> = vec![None; num_cells];
+        let mut open = BinaryHeap::new();
+        best[idx(start)] = 0.0;
+        open.push(Open {
+            estimate: heuristic(start),
+            cell: start,
+        });
+
+        while let Some(Open { cell, estimate }) = open.pop() {
+            if cell == goal {
+                let mut cells = vec![goal];
+                while let Some(prev) = came_from[idx(*cells.last().unwrap())] {
+                    cells.push(prev);
+                }
+                cells.reverse();
+                return Some(cells);
+            }
+            let cost_so_far = best[idx(cell)];
+            if estimate > cost_so_far + heuristic(cell) {
+                // already got here a cheaper way
+                continue;
+            }
+            for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                let next = (cell.0 + dx, cell.1 + dy);
+                if next.0 < 0 || next.1 < 0 || next.0 >= self.dim || next.1 >= self.dim {
+                    continue;
+                }
+                let step = if dx != 0 && dy != 0 { SQRT_2 } else { 1.0 };
+                let cost = cost_so_far + step * self.cost(next);
+                if cost < best[idx(next)] {
+                    best[idx(next)] = cost;
+                    came_from[idx(next)] = Some(cell);
+                    open.push(Open {
+                        estimate: cost + heuristic(next),
+                        cell: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // Drop the cells a straight line can skip, going from each kept one to the
+    // furthest one after it that's reachable without crossing a cell worse than
+    // the worst one the path took in between
+    fn smooth(&self, cells: Vec<Cell>) -> Vec<Cell> {
+        let mut kept = vec![cells[0]];
+        let mut from = 0;
+        while from < cells.len() - 1 {
+            let mut to = from + 1;
+            for ahead in (from + 2..cells.len()).rev() {
+                let worst = cells[from..=ahead].iter().map(|&cell| self.cost(cell)).fold(0.0, f64::max);
+                let crossed = self.line_cells(cells[from], cells[ahead]);
+                if crossed.iter().all(|&cell| self.cost(cell) <= worst && self.cost(cell).is_finite()) {
+                    to = ahead;
+                    break;
+                }
+            }
+            kept.push(cells[to]);
+            from = to;
+        }
+        kept
+    }
+
+    // Cells a straight line between the centers of two cells goes through
+    fn line_cells(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        let (from, to) = (self.center(a), self.center(b));
+        let steps = (4.0 * (to - from).length() / self.cell_size).ceil().max(1.0) as u32;
+        let mut cells: Vec<Cell> = Vec::new();
+        for step in 0..=steps {
+            let cell = self.cell(from + (step as f64 / steps as f64) * (to - from));
+            if cells.last() != Some(&cell) {
+                cells.push(cell);
+            }
+        }
+        cells
+    }
+}
+
+// A cell waiting to be looked at, cheapest estimate first out of the heap
+struct Open {
+    estimate: f64,
+    cell: Cell,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then_with(|| self.cell.cmp(&other.cell))
+    }
+}
+
+// The way an AI ship is following to its goal
+#[derive(Clone, Debug, Default)]
+pub struct NavPath {
+    goal: Option<Vec2>,
+    waypoints: Vec<Vec2>,
+    planned_tick: u32,
+}
+
+impl NavPath {
+    // Where a ship at pos should fly to get to goal: the next waypoint on the
+    // way, planning again first when the goal has moved or the plan is old
+    pub fn steer(&mut self, grid: &NavGrid, pos: Vec2, goal: Vec2, tick: u32) -> Vec2 {
+        let stale = tick.saturating_sub(self.planned_tick) >= REPLAN_TICKS;
+        if self.goal != Some(goal) || stale {
+            self.waypoints = grid.find_path(pos, goal);
+            self.goal = Some(goal);
+            self.planned_tick = tick;
+        }
+        let reached = WAYPOINT_REACHED * grid.cell_size;
+        while self.waypoints.len() > 1 && (self.waypoints[0] - pos).length() < reached {
+            self.waypoints.remove(0);
+        }
+        self.waypoints.first().copied().unwrap_or(goal)
+    }
+
+    pub fn waypoints(&self) -> &[Vec2] {
+        &self.waypoints
+    }
+
+    // "TICK:GOAL:WAYPOINTS", the goal "-" or "X,Y" and the waypoints "-" or "X,Y+X,Y..."
+    pub fn to_text(&self) -> String {
+        let goal = self.goal.map_or_else(|| "-".to_string(), point_text);
+        let waypoints = if self.waypoints.is_empty() {
+            "-".to_string()
+        } else {
+            let points: Vec<String> = self.waypoints.iter().copied().map(point_text).collect();
+            points.join("+")
+        };
+        format!("{}:{}:{}", self.planned_tick, goal, waypoints)
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let parts: Vec<&str> = value.split(':').collect();
+        let &[planned_tick, goal, waypoints] = parts.as_slice() else {
+            return None;
+        };
+        Some(Self {
+            goal: match goal {
+                "-" => None,
+                goal => Some(parse_point(goal)?),
+            },
+            waypoints: match waypoints {
+                "-" => Vec::new(),
+                waypoints => waypoints.split('+').map(parse_point).collect::<Option<_>>()?,
+            },
+            planned_tick: planned_tick.parse().ok()?,
+        })
+    }
+}
+
+// f64 Display round trips, so a loaded path is the one that was saved
+fn point_text(point: Vec2) -> String {
+    format!("{},{}", point.x, point.y)
+}
+
+fn parse_point(value: &str) -> Option<Vec2> {
+    let (x, y) = value.split_once(',')?;
+    Some(Vec2::new(x.parse().ok()?, y.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIM: u32 = 5;
+
+    // A grid of unit cells with the given ones blocked, the rest empty
+    fn grid(blocked: &[Cell]) -> NavGrid {
+        let mut clutter = vec![0.0; (DIM * DIM) as usize];
+        for &(x, y) in blocked {
+            clutter[(x + y * DIM as i32) as usize] = BLOCKED_CLUTTER;
+        }
+        NavGrid::new(Vec2::ZERO, 1.0, DIM, clutter)
+    }
+
+    #[test]
+    fn goes_around_blocked_cells() {
+        // a wall down the middle with a gap at the bottom
+        let grid = grid(&[(2, 0), (2, 1), (2, 2), (2, 3)]);
+        let cells = grid.search((0, 0), (4, 0)).unwrap();
+        assert!(cells.contains(&(2, 4)));
+        assert!(cells.iter().all(|&cell| grid.cost(cell).is_finite()));
+
+        let smoothed = grid.smooth(cells);
+        assert_eq!((smoothed.first(), smoothed.last()), (Some(&(0, 0)), Some(&(4, 0))));
+        for leg in smoothed.windows(2) {
+            assert!(grid.line_cells(leg[0], leg[1]).iter().all(|&cell| grid.cost(cell).is_finite()));
+        }
+
+        let to = Vec2::new(4.5, 0.5);
+        let path = grid.find_path(Vec2::new(0.5, 0.5), to);
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.iter().any(|waypoint| waypoint.y > 4.0));
+    }
+
+    #[test]
+    fn unreachable_goal_goes_straight() {
+        let grid = grid(&[(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)]);
+        assert!(grid.search((0, 0), (4, 0)).is_none());
+        let to = Vec2::new(4.5, 0.5);
+        assert_eq!(grid.find_path(Vec2::new(0.5, 0.5), to), vec![to]);
+    }
+
+    #[test]
+    fn start_at_goal() {
+        let grid = grid(&[]);
+        assert_eq!(grid.search((1, 1), (1, 1)), Some(vec![(1, 1)]));
+        assert_eq!(grid.smooth(vec![(1, 1)]), vec![(1, 1)]);
+        let to = Vec2::new(1.75, 1.25);
+        assert_eq!(grid.find_path(Vec2::new(1.5, 1.5), to), vec![to]);
+    }
+
+    #[test]
+    fn path_text_round_trip() {
+        let grid = grid(&[(2, 1), (2, 2)]);
+        let mut path = NavPath::default();
+        path.steer(&grid, Vec2::new(0.5, 1.5), Vec2::new(4.5, 1.5), 90);
+        let loaded = NavPath::parse(&path.to_text()).unwrap();
+        assert_eq!(loaded.to_text(), path.to_text());
+        assert_eq!(loaded.waypoints(), path.waypoints());
+        assert_eq!(NavPath::parse(&NavPath::default().to_text()).unwrap().to_text(), "0:-:-");
+    }
+}
@@ -0,0 +1,61 @@
+use std::ops::Range;
+
+use masonry::Vec2;
+
+use crate::game::HashRand;
+
+//-------------------------------------------------------------------------
+// Nebulae: clouds of gas lying in the field. Flying into one closes the
+// view in around the ship (the nebula pass in post_process.rs, stronger
+// the deeper in it is) and jams the scanners, so the minimap and radar only
+// pick up what's close and fill with static. They don't move or touch
+// anything. Like the debris they come from the seed alone, so a world
+// rebuilt from a snapshot has the same ones.
+//-------------------------------------------------------------------------
+
+const NUM_NEBULAE: u32 = 3;
+const NEBULA_MIN_RADIUS: f64 = 700.0;
+const NEBULA_MAX_RADIUS: f64 = 1200.0;
+// depth goes from nothing at the edge to full this far in
+const NEBULA_EDGE: f64 = 300.0;
+// kept this far from the ship's start, so a run doesn't begin blind
+const NEBULA_START_CLEAR: f64 = 1500.0;
+// while jammed, the scanners only pick things up this close
+pub const NEBULA_SCAN_RADIUS: f64 = 500.0;
+// deeper than this in a nebula jams the scanners
+pub const NEBULA_JAM_DEPTH: f64 = 0.5;
+
+pub struct Nebula {
+    pub center: Vec2,
+    pub radius: f64,
+}
+
+impl Nebula {
+    // 0 outside, rising to 1 past the edge
+    fn depth(&self, pos: Vec2) -> f64 {
+        ((self.radius - (pos - self.center).length()) / NEBULA_EDGE).clamp(0.0, 1.0)
+    }
+}
+
+// Nebulae for a world, somewhere in the area but away from the start
+pub fn generate(seed: u64, area: Range<Vec2>) -> Vec<Nebula> {
+    (0..NUM_NEBULAE)
+        .map(|idx| {
+            let radius = (NEBULA_MIN_RADIUS..NEBULA_MAX_RADIUS).hash_rand(seed, (idx, "nebula_radius"));
+            // a few tries, the last one goes anyway
+            let mut center = Vec2::ZERO;
+            for attempt in 0..8 {
+                center = area.clone().hash_rand(seed, (idx, attempt, "nebula_center"));
+                if center.length() - radius >= NEBULA_START_CLEAR {
+                    break;
+                }
+            }
+            Nebula { center, radius }
+        })
+        .collect()
+}
+
+// How deep pos is in the nebula it's deepest in, 0 outside them all
+pub fn depth(nebulae: &[Nebula], pos: Vec2) -> f64 {
+    nebulae.iter().map(|nebula| nebula.depth(pos)).fold(0.0, f64::max)
+}
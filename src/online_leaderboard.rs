@@ -0,0 +1,339 @@
+use std::{
+    fmt::Write as _,
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{game::GameMode, http, platform_services::PlatformServices, profile::Profile};
+
+//-------------------------------------------------------------------------
+// Client for an online leaderboard of endless runs, at the url set with
+// `leaderboard_url` in the settings file. Finished runs are submitted as
+// JSON (seed, score, name and a hash of the run's replay, so the server can
+// ask for it to check the run). Runs played in the accessibility slow mode
+// are flagged, for the server to mark or rank apart:
+//
+//   POST {url}/scores  {"seed":..,"mode":"endless","score":..,"ticks":..,"name":"..","replay_hash":"..",
+//                       "slow_mode":false}
+//
+// and the top ten for the seed are fetched back as plain text, one
+// "SCORE NAME" per line:
+//
+//   GET {url}/top?seed=..&mode=endless
+//
+// The network is only touched from a worker thread, which also owns the
+// profile's queue and cache files. Runs that can't be submitted are queued
+// on disk and go out with the next one, and the last top ten fetched per
+// seed is cached to show while offline. The http client (see http.rs)
+// is only built with the `online_leaderboard` feature; without it runs stay
+// queued and only the cache is shown.
+//-------------------------------------------------------------------------
+
+pub const QUEUE_PATH: &str = "space_survival.leaderboard_queue";
+pub const CACHE_PATH: &str = "space_survival.leaderboard_cache";
+
+const QUEUE_HEADER: &str = "space_survival leaderboard queue 2";
+// before slow mode
+const QUEUE_HEADER_V1: &str = "space_survival leaderboard queue 1";
+const CACHE_HEADER: &str = "space_survival leaderboard cache 1";
+const TOP_COUNT: usize = 10;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct Submission {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub score: u64,
+    pub ticks: u32,
+    pub name: String,
+    pub replay_hash: u64,
+    // played in the accessibility slow mode (see tuning.rs)
+    pub slow_mode: bool,
+}
+
+impl Submission {
+    fn to_json(&self) -> String {
+        let mut json = String::new();
+        write!(json, "{{\"seed\":{},\"mode\":\"{}\",", self.seed, self.mode.key()).unwrap();
+        write!(json, "\"score\":{},\"ticks\":{},", self.score, self.ticks).unwrap();
+        write!(json, "\"name\":\"{}\",", json_escape(&self.name)).unwrap();
+        write!(json, "\"replay_hash\":\"{:016x}\",", self.replay_hash).unwrap();
+        write!(json, "\"slow_mode\":{}}}", self.slow_mode).unwrap();
+        json
+    }
+
+    // "SEED MODE SCORE TICKS HASH SLOW NAME" in the queue file, SLOW being "slow"
+    // or "-", the name goes last since it can have spaces
+    fn to_line(&self) -> String {
+        format!(
+            "{} {} {} {} {:016x} {} {}",
+            self.seed,
+            self.mode.key(),
+            self.score,
+            self.ticks,
+            self.replay_hash,
+            if self.slow_mode { "slow" } else { "-" },
+            self.name
+        )
+    }
+
+    // Queues from before slow mode have no SLOW
+    fn from_line(line: &str, has_slow_mode: bool) -> Option<Self> {
+        let mut parts = line.splitn(if has_slow_mode { 7 } else { 6 }, ' ');
+        Some(Self {
+            seed: parts.next()?.parse().ok()?,
+            mode: GameMode::from_key(parts.next()?)?,
+            score: parts.next()?.parse().ok()?,
+            ticks: parts.next()?.parse().ok()?,
+            replay_hash: u64::from_str_radix(parts.next()?, 16).ok()?,
+            slow_mode: has_slow_mode && parts.next()? == "slow",
+            name: parts.next()?.to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OnlineEntry {
+    pub score: u64,
+    pub name: String,
+}
+
+pub enum TopScores {
+    Loading,
+    Online(Vec<OnlineEntry>),
+    // from the last time the leaderboard could be reached
+    Cached(Vec<OnlineEntry>),
+    Unavailable,
+}
+
+pub struct LeaderboardClient {
+    url: String,
+    sender: Option<Sender<Submission>>,
+    // top ten of each (seed, mode) a run was submitted for, as the worker got them
+    results: Receiver<((u64, GameMode), TopScores)>,
+    thread: Option<JoinHandle<()>>,
+    // (seed, mode) of the top ten being fetched or shown
+    key: Option<(u64, GameMode)>,
+    top: TopScores,
+}
+
+impl LeaderboardClient {
+    // The queue and the cache are files of the profile, written through the platform
+    pub fn new(url: &str, profile: &Profile, platform: Arc<dyn PlatformServices>) -> Self {
+        let url = url.trim_end_matches('/').to_string();
+        let (queue_path, cache_path) = (profile.path(QUEUE_PATH), profile.path(CACHE_PATH));
+        let (sender, receiver) = mpsc::channel::<Submission>();
+        let (result_sender, results) = mpsc::channel();
+
+        // the one thread that touches the network and the files, so submissions
+        // go out one after the other and the queue is never written twice at once
+        let worker_url = url.clone();
+        let thread = std::thread::Builder::new()
+            .name("online_leaderboard".to_string())
+            .spawn(move || {
+                for submission in receiver {
+                    let key = (submission.seed, submission.mode);
+                    send_queued(&worker_url, &queue_path, platform.as_ref(), submission);
+                    let top = fetch_top(&worker_url, &cache_path, platform.as_ref(), key);
+                    if result_sender.send((key, top)).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to start online leaderboard thread");
+
+        Self {
+            url,
+            sender: Some(sender),
+            results,
+            thread: Some(thread),
+            key: None,
+            top: TopScores::Unavailable,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    // Send the run (and any queued ones) and then fetch the top ten for its seed
+    pub fn submit(&mut self, submission: Submission) {
+        self.key = Some((submission.seed, submission.mode));
+        self.top = TopScores::Loading;
+        if let Some(sender) = self.sender.as_ref() {
+            // the worker only stops once the client is dropped
+            let _ = sender.send(submission);
+        }
+    }
+
+    // Pick up the top ten once the worker has it, called every frame
+    pub fn poll(&mut self) {
+        while let Ok((key, top)) = self.results.try_recv() {
+            // one for an earlier run is old news
+            if Some(key) == self.key {
+                self.top = top;
+            }
+        }
+    }
+
+    pub fn top(&self) -> &TopScores {
+        &self.top
+    }
+}
+
+// Runs still being sent go out (or into the queue) before the game exits
+impl Drop for LeaderboardClient {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Submit everything in the queue plus the new run, and queue whatever didn't go through
+fn send_queued(url: &str, path: &Path, platform: &dyn PlatformServices, submission: Submission) {
+    let mut queue = load_queue(path);
+    queue.push(submission);
+
+    let mut failed = Vec::new();
+    for submission in queue {
+        let scores_url = format!("{}/scores", url);
+        match http::post(&scores_url, "application/json", submission.to_json(), REQUEST_TIMEOUT) {
+            Ok(()) => log::info!(
+                "online_score_submitted seed={} score={} slow_mode={}",
+                submission.seed,
+                submission.score,
+                submission.slow_mode
+            ),
+            Err(err) => {
+                log::warn!("Couldn't submit score, queued for later: {}", err);
+                failed.push(submission);
+            }
+        }
+    }
+    if let Err(err) = save_queue(path, platform, &failed) {
+        log::error!("Failed to save leaderboard queue {}: {}", path.display(), err);
+    }
+}
+
+// The top ten for (seed, mode), from the cache if the leaderboard can't be reached
+fn fetch_top(url: &str, path: &Path, platform: &dyn PlatformServices, key: (u64, GameMode)) -> TopScores {
+    let (seed, mode) = key;
+    let top_url = format!("{}/top?seed={}&mode={}", url, seed, mode.key());
+    match http::get(&top_url, REQUEST_TIMEOUT).map(|txt| parse_top(&txt)) {
+        Ok(top) => {
+            log::info!("online_leaderboard_fetched entries={}", top.len());
+            if let Err(err) = save_cache(path, platform, seed, mode, &top) {
+                log::warn!("Failed to save leaderboard cache {}: {}", path.display(), err);
+            }
+            TopScores::Online(top)
+        }
+        Err(err) => {
+            log::warn!("Couldn't fetch the online leaderboard: {}", err);
+            match load_cache(path, seed, mode) {
+                Some(top) => TopScores::Cached(top),
+                None => TopScores::Unavailable,
+            }
+        }
+    }
+}
+
+fn parse_top(txt: &str) -> Vec<OnlineEntry> {
+    txt.lines()
+        .filter_map(|line| {
+            let (score, name) = line.trim().split_once(' ')?;
+            Some(OnlineEntry {
+                score: score.parse().ok()?,
+                name: name.trim().to_string(),
+            })
+        })
+        .take(TOP_COUNT)
+        .collect()
+}
+
+fn json_escape(txt: &str) -> String {
+    let mut escaped = String::new();
+    for c in txt.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// --- MARK: Offline files ---
+
+fn load_queue(path: &Path) -> Vec<Submission> {
+    let Ok(txt) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut lines = txt.lines();
+    let has_slow_mode = match lines.next() {
+        Some(QUEUE_HEADER) => true,
+        Some(QUEUE_HEADER_V1) => false,
+        _ => {
+            log::warn!("Ignoring leaderboard queue {}: not a queue file", path.display());
+            return Vec::new();
+        }
+    };
+    lines.filter_map(|line| Submission::from_line(line, has_slow_mode)).collect()
+}
+
+fn save_queue(path: &Path, platform: &dyn PlatformServices, queue: &[Submission]) -> std::io::Result<()> {
+    if queue.is_empty() {
+        return platform.remove_cloud_file(path);
+    }
+    let mut txt = String::new();
+    writeln!(txt, "{}", QUEUE_HEADER).unwrap();
+    for submission in queue {
+        writeln!(txt, "{}", submission.to_line()).unwrap();
+    }
+    platform.store_cloud_file(path, txt.as_bytes())
+}
+
+// "SEED MODE SCORE NAME" per entry, best first for each seed
+fn load_cache(path: &Path, seed: u64, mode: GameMode) -> Option<Vec<OnlineEntry>> {
+    let txt = std::fs::read_to_string(path).ok()?;
+    let mut lines = txt.lines();
+    if lines.next() != Some(CACHE_HEADER) {
+        return None;
+    }
+    let prefix = format!("{} {} ", seed, mode.key());
+    let top: Vec<OnlineEntry> = lines
+        .filter_map(|line| line.strip_prefix(&prefix))
+        .flat_map(|entry| parse_top(entry))
+        .collect();
+    (!top.is_empty()).then_some(top)
+}
+
+fn save_cache(
+    path: &Path,
+    platform: &dyn PlatformServices,
+    seed: u64,
+    mode: GameMode,
+    top: &[OnlineEntry],
+) -> std::io::Result<()> {
+    // keep the other seeds
+    let prefix = format!("{} {} ", seed, mode.key());
+    let old = std::fs::read_to_string(path).unwrap_or_default();
+    let mut txt = String::new();
+    writeln!(txt, "{}", CACHE_HEADER).unwrap();
+    if old.lines().next() == Some(CACHE_HEADER) {
+        for line in old.lines().skip(1).filter(|line| !line.starts_with(&prefix)) {
+            writeln!(txt, "{}", line).unwrap();
+        }
+    }
+    for entry in top {
+        writeln!(txt, "{}{} {}", prefix, entry.score, entry.name).unwrap();
+    }
+    platform.store_cloud_file(path, txt.as_bytes())
+}
@@ -0,0 +1,108 @@
+use std::f64::consts::PI;
+
+use masonry::Vec2;
+
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// What the AI can tell about the player's ship, so hunting it feels fair.
+// Each AI that goes after the player has Senses: how far it sees, and how
+// wide a cone in front of it it sees in (turrets and the carrier look all
+// around). On top of that a big asteroid in between blocks the view (see
+// GameWorld::has_line_of_sight), a ship deep in a nebula can only be made
+// out from close by, and a cloaked one can't be seen at all.
+//
+// What an AI saw last is kept in its Perception for a while, so a pirate
+// that loses sight of the player heads for where they were last seen and
+// only gives up once it gets there, or its memory runs out.
+// GameWorld::perceive_player does the looking.
+//-------------------------------------------------------------------------
+
+// faction ships only see ahead of them, this far either side of the nose
+const FACTION_SHIP_HALF_ANGLE: f64 = 0.6 * PI;
+const FACTION_SHIP_MEMORY_TICKS: u32 = 5 * TICKS_PER_SECOND as u32;
+pub const BOSS_MEMORY_TICKS: u32 = 8 * TICKS_PER_SECOND as u32;
+// asteroids at least this big block the view
+pub const SIGHT_BLOCKER_RADIUS: f64 = 90.0;
+// of the usual range a ship all the way in a nebula can be seen from
+const NEBULA_VISIBILITY: f64 = 0.3;
+// getting this close to where the player was last seen without seeing them
+// again, the AI forgets about them
+const SEARCH_RADIUS: f64 = 150.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Senses {
+    pub range: f64,
+    // of the cone either side of where it faces, PI for all around
+    pub half_angle: f64,
+    // how long it remembers where it last saw the player
+    pub memory_ticks: u32,
+}
+
+impl Senses {
+    pub fn faction_ship(range: f64) -> Self {
+        Self {
+            range,
+            half_angle: FACTION_SHIP_HALF_ANGLE,
+            memory_ticks: FACTION_SHIP_MEMORY_TICKS,
+        }
+    }
+
+    pub fn all_around(range: f64, memory_ticks: u32) -> Self {
+        Self {
+            range,
+            half_angle: PI,
+            memory_ticks,
+        }
+    }
+
+    // Whether something offset from the observer, which faces along facing (a
+    // unit vector), is in range and in the cone. Visibility (0..1) scales the
+    // range, for how hard the target is to make out.
+    pub fn can_see(&self, offset: Vec2, facing: Vec2, visibility: f64) -> bool {
+        let dist = offset.length();
+        if dist >= self.range * visibility {
+            return false;
+        }
+        self.half_angle >= PI || dist < 1e-6 || offset.dot(facing) / dist >= self.half_angle.cos()
+    }
+}
+
+// How visible a ship is from afar at a depth (0..1) into a nebula
+pub fn nebula_visibility(depth: f64) -> f64 {
+    1.0 - (1.0 - NEBULA_VISIBILITY) * depth.clamp(0.0, 1.0)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Memory {
+    pos: Vec2,
+    tick: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Perception {
+    last_seen: Option<Memory>,
+}
+
+impl Perception {
+    // Fold in what an AI at pos saw of the player this tick (None when it didn't
+    // see them). Returns where it thinks they are: where it sees them, or where
+    // they were last seen until it gets there or forgets.
+    pub fn update(&mut self, sighting: Option<Vec2>, pos: Vec2, tick: u32, senses: &Senses) -> Option<Vec2> {
+        if let Some(seen) = sighting {
+            self.last_seen = Some(Memory { pos: seen, tick });
+            return sighting;
+        }
+        let memory = self.last_seen?;
+        let expired = tick.saturating_sub(memory.tick) > senses.memory_ticks;
+        if expired || (memory.pos - pos).length() < SEARCH_RADIUS {
+            self.last_seen = None;
+            return None;
+        }
+        Some(memory.pos)
+    }
+
+    pub fn last_seen(&self) -> Option<Vec2> {
+        self.last_seen.map(|memory| memory.pos)
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::VecDeque,
+    f64::consts::{PI, TAU},
+};
+
+use masonry::Vec2;
+
+use crate::game::HashRand;
+
+//-------------------------------------------------------------------------
+// Imperfect pilot model for ai controlled ships. The ai works out the
+// ideal controls, then the pilot's skill degrades them: decisions only
+// take effect after a reaction delay, aim is off by a wandering error
+// and the pilot sometimes hesitates to thrust. Difficulty changes how
+// well enemies fly rather than how strong they are.
+//-------------------------------------------------------------------------
+
+// how often the aim error picks a new value, so the ship wobbles rather than jitters
+const AIM_ERROR_TICKS: u32 = 20;
+// rotation per tick at full turn, matches the player's ship
+const TURN_RATE: f64 = 0.15;
+// only thrust when roughly facing the way we want to go
+const THRUST_ANGLE: f64 = 0.5;
+// default speed (units/tick) the ai tries to approach its target with
+const CRUISE_SPEED: f64 = 12.0;
+
+// Controls for a ship for one tick, same for the player and the ai
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ShipControls {
+    // -1 is full left, 1 is full right
+    pub turn: f64,
+    pub thrust: bool,
+    // reverse thrust with the maneuvering thrusters
+    pub brake: bool,
+    // sideways with the maneuvering thrusters, -1 left, 1 right
+    pub strafe: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SkillLevel {
+    Rookie,
+    #[default]
+    Veteran,
+    Ace,
+}
+
+impl SkillLevel {
+    pub fn key(self) -> &'static str {
+        match self {
+            SkillLevel::Rookie => "rookie",
+            SkillLevel::Veteran => "veteran",
+            SkillLevel::Ace => "ace",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "rookie" => Some(SkillLevel::Rookie),
+            "veteran" => Some(SkillLevel::Veteran),
+            "ace" => Some(SkillLevel::Ace),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PilotSkill {
+    // ticks between the pilot seeing the situation and acting on it
+    pub reaction_ticks: u32,
+    // max aim error in radians
+    pub aim_noise: f64,
+    // chance per tick of not thrusting when the pilot should
+    pub hesitation: f64,
+}
+
+impl PilotSkill {
+    pub fn for_level(level: SkillLevel) -> Self {
+        match level {
+            SkillLevel::Rookie => Self {
+                reaction_ticks: 12,
+                aim_noise: 0.5,
+                hesitation: 0.4,
+            },
+            SkillLevel::Veteran => Self {
+                reaction_ticks: 6,
+                aim_noise: 0.2,
+                hesitation: 0.15,
+            },
+            SkillLevel::Ace => Self {
+                reaction_ticks: 2,
+                aim_noise: 0.05,
+                hesitation: 0.0,
+            },
+        }
+    }
+}
+
+// Where the ship is, as seen by the pilot
+pub struct ShipState {
+    pub pos: Vec2,
+    pub rotation: f64,
+    pub velocity: Vec2,
+}
+
+pub struct Pilot {
+    skill: PilotSkill,
+    // where the ai wants to go, set by whatever controls the ship's behavior
+    pub target: Option<Vec2>,
+    cruise_speed: f64,
+    // decisions waiting out the reaction delay
+    pending: VecDeque<ShipControls>,
+}
+
+impl Pilot {
+    pub fn new(skill: PilotSkill) -> Self {
+        Self {
+            skill,
+            target: None,
+            cruise_speed: CRUISE_SPEED,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn with_cruise_speed(mut self, cruise_speed: f64) -> Self {
+        self.cruise_speed = cruise_speed;
+        self
+    }
+
+    pub fn set_skill(&mut self, skill: PilotSkill) {
+        self.skill = skill;
+    }
+
+    // Decide on controls this tick and return the ones that are due. Randomness is
+    // hashed from seed, pilot id and tick so runs stay reproducible.
+    pub fn update(&mut self, ship: &ShipState, seed: u64, pilot_id: usize, tick: u32) -> ShipControls {
+        let ideal = match self.target {
+            Some(target) => self.steer(ship, target),
+            None => ShipControls::default(),
+        };
+        let controls = self.degrade(ship, ideal, seed, pilot_id, tick);
+
+        self.pending.push_back(controls);
+        if self.pending.len() > self.skill.reaction_ticks as usize {
+            self.pending.pop_front().unwrap_or_default()
+        } else {
+            // still reacting, hands off the controls
+            ShipControls::default()
+        }
+    }
+
+    // Perfect controls: aim for the velocity that brings us to the target at
+    // cruise speed and thrust when facing the needed correction.
+    fn steer(&self, ship: &ShipState, target: Vec2) -> ShipControls {
+        let to_target = target - ship.pos;
+        let desired_vel = if to_target.length() > self.cruise_speed {
+            self.cruise_speed * to_target.normalize()
+        } else {
+            to_target
+        };
+        let correction = desired_vel - ship.velocity;
+        if correction.length() < 0.5 {
+            return ShipControls::default();
+        }
+
+        let delta = Self::heading_delta(ship, correction.atan2());
+        ShipControls {
+            turn: (delta / TURN_RATE).clamp(-1.0, 1.0),
+            thrust: delta.abs() < THRUST_ANGLE,
+            ..ShipControls::default()
+        }
+    }
+
+    fn degrade(
+        &self,
+        ship: &ShipState,
+        ideal: ShipControls,
+        seed: u64,
+        pilot_id: usize,
+        tick: u32,
+    ) -> ShipControls {
+        if ideal == ShipControls::default() {
+            return ideal;
+        }
+
+        // aim error stays put for a while and is applied to the turn
+        let aim_period = tick / AIM_ERROR_TICKS;
+        let aim_error = (-self.skill.aim_noise..self.skill.aim_noise)
+            .hash_rand(seed, (pilot_id, aim_period, "aim_error"));
+        let turn = (ideal.turn + aim_error / TURN_RATE).clamp(-1.0, 1.0);
+
+        let hesitates = (0.0..1.0).hash_rand(seed, (pilot_id, tick, "hesitation")) < self.skill.hesitation;
+        let thrust = ideal.thrust && !hesitates && ship.velocity.length() < 2.0 * self.cruise_speed;
+
+        ShipControls {
+            turn,
+            thrust,
+            ..ideal
+        }
+    }
+
+    // signed angle to turn from the ship's heading to the given direction
+    fn heading_delta(ship: &ShipState, angle: f64) -> f64 {
+        // ships point along their y axis
+        let heading = ship.rotation + 0.5 * PI;
+        (angle - heading + PI).rem_euclid(TAU) - PI
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+//-------------------------------------------------------------------------
+// What the game needs from the store it ships on (achievements, cloud
+// saves and who's playing), kept behind a trait so gameplay code doesn't
+// care which one it is. LocalServices is the plain build: achievements are
+// logged and kept in a file in the player's profile, and cloud files are
+// ordinary files. A Steamworks backend
+// would implement the same trait and be handed to the game in main.rs.
+//
+// Everything takes &self and may be called from the autosave thread, so
+// backends need to handle their own locking.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Achievement {
+    // last 5 minutes in an endless run
+    Survivor,
+    GiantSlayer,
+    SafePassage,
+    CheckeredFlag,
+    // best time on a course
+    CourseRecord,
+}
+
+impl Achievement {
+    // API name, the same as the achievement is registered under with the store
+    pub fn key(self) -> &'static str {
+        match self {
+            Achievement::Survivor => "survivor",
+            Achievement::GiantSlayer => "giant_slayer",
+            Achievement::SafePassage => "safe_passage",
+            Achievement::CheckeredFlag => "checkered_flag",
+            Achievement::CourseRecord => "course_record",
+        }
+    }
+}
+
+const ACHIEVEMENTS_HEADER: &str = "space_survival achievements 1";
+
+pub trait PlatformServices: Send + Sync {
+    // Unlocking one that's already unlocked does nothing
+    fn unlock_achievement(&self, achievement: Achievement);
+
+    // Write a whole file, replacing what was there. The path is relative to the
+    // game's save location.
+    fn store_cloud_file(&self, path: &Path, contents: &[u8]) -> std::io::Result<()>;
+
+    // Missing files are fine
+    fn remove_cloud_file(&self, path: &Path) -> std::io::Result<()>;
+
+    fn get_user_name(&self) -> String;
+}
+
+pub struct LocalServices {
+    user_name: String,
+    // where unlocked achievements are kept, one key per line
+    achievements_path: Option<PathBuf>,
+    unlocked: Mutex<Vec<String>>,
+}
+
+impl LocalServices {
+    pub fn new(user_name: String, achievements_path: Option<PathBuf>) -> Self {
+        let unlocked = achievements_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .filter(|txt| txt.lines().next() == Some(ACHIEVEMENTS_HEADER))
+            .map(|txt| txt.lines().skip(1).map(str::to_string).collect())
+            .unwrap_or_default();
+        Self {
+            user_name,
+            achievements_path,
+            unlocked: Mutex::new(unlocked),
+        }
+    }
+}
+
+impl PlatformServices for LocalServices {
+    fn unlock_achievement(&self, achievement: Achievement) {
+        let mut unlocked = self.unlocked.lock().unwrap();
+        if unlocked.iter().any(|key| key == achievement.key()) {
+            return;
+        }
+        unlocked.push(achievement.key().to_string());
+        log::info!("achievement_unlocked name={}", achievement.key());
+
+        let Some(path) = self.achievements_path.as_ref() else {
+            return;
+        };
+        let txt = format!("{}\n{}\n", ACHIEVEMENTS_HEADER, unlocked.join("\n"));
+        if let Err(err) = self.store_cloud_file(path, txt.as_bytes()) {
+            log::error!("Failed to save achievements {}: {}", path.display(), err);
+        }
+    }
+
+    // Written to a temp file that is then renamed over the old one, so a crash
+    // mid-write leaves the previous file intact
+    fn store_cloud_file(&self, path: &Path, contents: &[u8]) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        std::fs::write(&tmp_path, contents).and_then(|_| std::fs::rename(&tmp_path, path))
+    }
+
+    fn remove_cloud_file(&self, path: &Path) -> std::io::Result<()> {
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_user_name(&self) -> String {
+        self.user_name.clone()
+    }
+}
@@ -0,0 +1,500 @@
+use bytemuck::{Pod, Zeroable};
+use vello::wgpu::{self, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, Queue, RenderPipeline, Sampler, TextureFormat, TextureView};
+
+use crate::render_mgr::{GlobalRenderData, GLOBAL_RENDER_DATA_SHADER};
+
+//-------------------------------------------------------------------------
+// Post-processing chain. The scene is rendered into an offscreen (HDR)
+// texture, then each enabled effect runs as a full screen pass reading the
+// previous result. Tone mapping is always the last pass and writes to the
+// surface. Each pass also sees the GlobalRenderData the scene was drawn
+// with, so animated effects run on the same clock as the starfield.
+//-------------------------------------------------------------------------
+
+// Format of the offscreen scene and intermediate textures
+pub const SCENE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct PostParams {
+    pub exposure: f32,
+    // 1.0 when sensor (night vision) mode is on
+    pub sensor_mode: f32,
+    // PhotoFilter::shader_index of the active photo mode filter
+    pub photo_filter: f32,
+    // how deep the ship is in a nebula, 0 outside to 1 (see nebula.rs)
+    pub nebula: f32,
+    // EMP stun the player's ship has left, 1 just hit to 0 (see status.rs)
+    pub glitch: f32,
+    // keeps the uniform a multiple of 16 bytes
+    pub _padding: [f32; 3],
+}
+
+// Filters offered in photo mode. Night vision reuses the sensor mode pass, the
+// others are handled by the photo filter pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhotoFilter {
+    None,
+    Mono,
+    Sepia,
+    Vignette,
+    NightVision,
+}
+
+impl PhotoFilter {
+    pub fn next(self) -> Self {
+        match self {
+            PhotoFilter::None => PhotoFilter::Mono,
+            PhotoFilter::Mono => PhotoFilter::Sepia,
+            PhotoFilter::Sepia => PhotoFilter::Vignette,
+            PhotoFilter::Vignette => PhotoFilter::NightVision,
+            PhotoFilter::NightVision => PhotoFilter::None,
+        }
+    }
+
+    pub fn shader_index(self) -> f32 {
+        match self {
+            PhotoFilter::None | PhotoFilter::NightVision => 0.0,
+            PhotoFilter::Mono => 1.0,
+            PhotoFilter::Sepia => 2.0,
+            PhotoFilter::Vignette => 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PostEffectKind {
+    NightVision,
+    Nebula,
+    Glitch,
+    PhotoFilter,
+    ToneMap,
+}
+
+impl PostEffectKind {
+    fn is_enabled(self, params: &PostParams) -> bool {
+        match self {
+            PostEffectKind::NightVision => params.sensor_mode > 0.0,
+            PostEffectKind::Nebula => params.nebula > 0.0,
+            PostEffectKind::Glitch => params.glitch > 0.0,
+            PostEffectKind::PhotoFilter => params.photo_filter > 0.0,
+            PostEffectKind::ToneMap => true,
+        }
+    }
+
+    fn fragment_shader(self) -> &'static str {
+        match self {
+            PostEffectKind::NightVision => NIGHT_VISION_SHADER,
+            PostEffectKind::Nebula => NEBULA_SHADER,
+            PostEffectKind::Glitch => GLITCH_SHADER,
+            PostEffectKind::PhotoFilter => PHOTO_FILTER_SHADER,
+            PostEffectKind::ToneMap => TONE_MAP_SHADER,
+        }
+    }
+}
+
+struct PostEffect {
+    kind: PostEffectKind,
+    pipeline: RenderPipeline,
+}
+
+struct PostTarget {
+    view: TextureView,
+    // bind group for reading from this target
+    bind_group: BindGroup,
+    width: u32,
+    height: u32,
+}
+
+pub struct PostChain {
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    params_buffer: Buffer,
+    // the shared GlobalRenderData, bound as group 1 in every pass
+    global_bind_group: BindGroup,
+    effects: Vec<PostEffect>,
+    // ping-pong targets, scene is rendered into the first one
+    targets: Option<[PostTarget; 2]>,
+}
+
+impl PostChain {
+    pub fn new(device: &Device, surface_format: TextureFormat, global_buffer: &Buffer) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post process bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<PostParams>() as u64),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post process sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("PostParamsBuffer"),
+            size: std::mem::size_of::<PostParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let global_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post process global bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<GlobalRenderData>() as u64),
+                },
+                count: None,
+            }],
+        });
+        let global_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post process global bind group"),
+            layout: &global_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(global_buffer.as_entire_buffer_binding()),
+            }],
+        });
+
+        // tone mapping must be last since it is the only pass writing to the surface
+        let effects = [
+            PostEffectKind::NightVision,
+            PostEffectKind::Nebula,
+            PostEffectKind::Glitch,
+            PostEffectKind::PhotoFilter,
+            PostEffectKind::ToneMap,
+        ]
+        .into_iter()
+        .map(|kind| {
+            let format = if kind == PostEffectKind::ToneMap { surface_format } else { SCENE_FORMAT };
+            PostEffect {
+                kind,
+                pipeline: Self::create_pipeline(device, &[&bind_group_layout, &global_layout], kind, format),
+            }
+        })
+        .collect();
+
+        Self {
+            bind_group_layout,
+            sampler,
+            params_buffer,
+            global_bind_group,
+            effects,
+            targets: None,
+        }
+    }
+
+    fn create_pipeline(device: &Device, bind_group_layouts: &[&BindGroupLayout], kind: PostEffectKind, format: TextureFormat) -> RenderPipeline {
+        let source = format!("{}{}{}", GLOBAL_RENDER_DATA_SHADER, POST_PRELUDE_SHADER, kind.fragment_shader());
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("post process shaders"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("post process pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn create_target(&self, device: &Device, width: u32, height: u32) -> PostTarget {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("PostTarget"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SCENE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post process bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(self.params_buffer.as_entire_buffer_binding()),
+                },
+            ],
+        });
+
+        PostTarget {
+            view,
+            bind_group,
+            width,
+            height,
+        }
+    }
+
+    // Resize targets if needed and upload this frame's parameters
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, width: u32, height: u32, params: &PostParams) {
+        let need_resize = self
+            .targets
+            .as_ref()
+            .map(|targets| targets[0].width != width || targets[0].height != height)
+            .unwrap_or(true);
+        if need_resize {
+            self.targets = Some([
+                self.create_target(device, width, height),
+                self.create_target(device, width, height),
+            ]);
+        }
+
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[*params]));
+    }
+
+    // View the scene should be rendered (or resolved) into
+    pub fn get_scene_view(&self) -> &TextureView {
+        &self.targets.as_ref().unwrap()[0].view
+    }
+
+    pub fn run(&self, encoder: &mut CommandEncoder, surface_view: &TextureView, params: &PostParams) {
+        let targets = self.targets.as_ref().unwrap();
+        let active: Vec<&PostEffect> = self.effects.iter().filter(|effect| effect.kind.is_enabled(params)).collect();
+
+        let mut input = 0;
+        for (i, effect) in active.iter().enumerate() {
+            let output_view = if i + 1 == active.len() {
+                surface_view
+            } else {
+                &targets[1 - input].view
+            };
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("post process pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output_view,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    resolve_target: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&effect.pipeline);
+            render_pass.set_bind_group(0, &targets[input].bind_group, &[]);
+            render_pass.set_bind_group(1, &self.global_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+            drop(render_pass);
+
+            input = 1 - input;
+        }
+    }
+}
+
+const POST_PRELUDE_SHADER: &str = r#"
+struct PostParams {
+    exposure: f32,
+    sensor_mode: f32,
+    photo_filter: f32,
+    nebula: f32,
+    glitch: f32,
+    padding0: f32,
+    padding1: f32,
+    padding2: f32,
+};
+
+@group(0) @binding(0) var t_input: texture_2d<f32>;
+@group(0) @binding(1) var s_input: sampler;
+@group(0) @binding(2) var<uniform> u_post: PostParams;
+// time and camera, shared with the passes drawing the scene
+@group(1) @binding(0) var<uniform> u_global: GlobalRenderData;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) ix: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((ix << 1u) & 2u), f32(ix & 2u));
+    let position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    return VertexOutput(position, uv);
+}
+"#;
+
+const TONE_MAP_SHADER: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var rgb = textureSample(t_input, s_input, in.uv).rgb * u_post.exposure;
+
+    // leave colors below the knee alone and roll off anything brighter towards 1.0
+    let knee = 0.8;
+    let over = max(rgb - vec3<f32>(knee), vec3<f32>(0.0));
+    rgb = min(rgb, vec3<f32>(knee)) + (1.0 - knee) * over / (over + vec3<f32>(1.0 - knee));
+    return vec4<f32>(rgb, 1.0);
+}
+"#;
+
+const NIGHT_VISION_SHADER: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let rgb = textureSample(t_input, s_input, in.uv).rgb;
+    let luma = dot(rgb, vec3<f32>(0.299, 0.587, 0.114));
+
+    // threshold away the faint starfield so entities stand out
+    let signal = smoothstep(0.15, 0.6, luma);
+
+    let dims = vec2<f32>(textureDimensions(t_input));
+    let scanline = 0.85 + 0.15 * sin(in.uv.y * dims.y * 1.5 + u_global.time * 4.0);
+    return vec4<f32>(vec3<f32>(0.15, 1.0, 0.3) * (0.05 + 1.2 * signal) * scanline, 1.0);
+}
+"#;
+
+const NEBULA_SHADER: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let rgb = textureSample(t_input, s_input, in.uv).rgb;
+
+    // the view closes in around the middle of the screen the deeper the ship is
+    let dims = vec2<f32>(textureDimensions(t_input));
+    let offset = (in.uv - vec2<f32>(0.5, 0.5)) * vec2<f32>(dims.x / dims.y, 1.0);
+    let clear = mix(0.9, 0.2, u_post.nebula);
+    let seen = 1.0 - smoothstep(clear, clear + 0.25, length(offset));
+
+    // slowly shifting gas where it closes in
+    let swirl = sin(offset.x * 7.0 + u_global.time * 0.3) * sin(offset.y * 9.0 - u_global.time * 0.2);
+    let gas = vec3<f32>(0.2, 0.09, 0.28) * (0.75 + 0.25 * swirl);
+    return vec4<f32>(mix(gas, rgb, seen), 1.0);
+}
+"#;
+
+const GLITCH_SHADER: &str = r#"
+fn glitch_hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // a new pattern a few times a second
+    let frame = floor(u_global.time * 12.0);
+
+    // some horizontal bands are torn sideways, more of them right after the hit
+    let band = floor(in.uv.y * 24.0);
+    let torn = step(1.0 - 0.5 * u_post.glitch, glitch_hash(vec2<f32>(band, frame)));
+    let shift = torn * (glitch_hash(vec2<f32>(frame, band)) - 0.5) * 0.1 * u_post.glitch;
+    let uv = vec2<f32>(fract(in.uv.x + shift), in.uv.y);
+
+    // color channels split apart in the torn bands
+    let split = torn * 0.01 * u_post.glitch;
+    let r = textureSample(t_input, s_input, uv + vec2<f32>(split, 0.0)).r;
+    let g = textureSample(t_input, s_input, uv).g;
+    let b = textureSample(t_input, s_input, uv - vec2<f32>(split, 0.0)).b;
+
+    // and static over all of it
+    let dims = vec2<f32>(textureDimensions(t_input));
+    let noise = glitch_hash(floor(in.uv * dims * 0.5) + vec2<f32>(frame, frame));
+    let rgb = mix(vec3<f32>(r, g, b), vec3<f32>(noise), 0.35 * u_post.glitch);
+    return vec4<f32>(rgb, 1.0);
+}
+"#;
+
+const PHOTO_FILTER_SHADER: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var rgb = textureSample(t_input, s_input, in.uv).rgb;
+    let luma = dot(rgb, vec3<f32>(0.299, 0.587, 0.114));
+
+    let filter = u32(u_post.photo_filter + 0.5);
+    if filter == 1u {
+        // mono
+        rgb = vec3<f32>(luma);
+    } else if filter == 2u {
+        // sepia
+        rgb = luma * vec3<f32>(1.07, 0.74, 0.43);
+    } else if filter == 3u {
+        // vignette
+        let dist = distance(in.uv, vec2<f32>(0.5, 0.5));
+        rgb *= 1.0 - smoothstep(0.35, 0.75, dist);
+    }
+    return vec4<f32>(rgb, 1.0);
+}
+"#;
@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use crate::{actions::Bindings, settings::Settings};
+
+//-------------------------------------------------------------------------
+// Player profiles. Each one is a directory under PROFILES_DIR holding
+// everything that belongs to that player: settings and key bindings (text
+// files they can edit), best times and ghosts, achievements, credits and
+// unlocks, the autosave and the save slots. The profile used last is picked
+// again at startup.
+//-------------------------------------------------------------------------
+
+pub const PROFILES_DIR: &str = "space_survival_profiles";
+pub const DEFAULT_PROFILE: &str = "pilot";
+pub const NUM_SAVE_SLOTS: usize = 3;
+pub const MAX_NAME_LEN: usize = 16;
+
+pub const SETTINGS_FILE: &str = "settings";
+pub const BINDINGS_FILE: &str = "bindings";
+pub const ACHIEVEMENTS_FILE: &str = "achievements";
+pub const HINTS_FILE: &str = "hints";
+// credits and unlocks, see progression.rs
+pub const PROGRESSION_FILE: &str = "progression";
+// paint and flame color of the ship, see ship_style.rs
+pub const SHIP_STYLE_FILE: &str = "ship_style";
+// the graphics preset picked for this machine
+pub const QUALITY_FILE: &str = "quality";
+
+// name of the last used profile
+const LAST_PROFILE_FILE: &str = "last";
+
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub name: String,
+    dir: PathBuf,
+}
+
+impl Profile {
+    // The profile's directory is only made once something is saved to it, see open()
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            dir: Path::new(PROFILES_DIR).join(name),
+        }
+    }
+
+    // Open a profile, making it if it doesn't exist yet
+    pub fn open(name: &str) -> Result<Self, String> {
+        if !is_valid_name(name) {
+            return Err(format!(
+                "Invalid profile name '{}': up to {} letters, digits, '-' or '_'",
+                name, MAX_NAME_LEN
+            ));
+        }
+        let profile = Self::new(name);
+        std::fs::create_dir_all(&profile.dir)
+            .map_err(|err| format!("Failed to create profile {}: {}", profile.dir.display(), err))?;
+        Ok(profile)
+    }
+
+    // The one asked for on the command line, or the last one used, or the default
+    pub fn startup(requested: Option<&str>) -> Result<Self, String> {
+        let last = std::fs::read_to_string(Path::new(PROFILES_DIR).join(LAST_PROFILE_FILE)).ok();
+        let name = requested
+            .or(last.as_deref().map(str::trim).filter(|name| is_valid_name(name)))
+            .unwrap_or(DEFAULT_PROFILE);
+        Self::open(name)
+    }
+
+    // Names of all the profiles, sorted
+    pub fn list() -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(PROFILES_DIR) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| is_valid_name(name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    // Pick this profile at the next startup
+    pub fn remember(&self) {
+        let path = Path::new(PROFILES_DIR).join(LAST_PROFILE_FILE);
+        if let Err(err) = std::fs::write(&path, &self.name) {
+            log::warn!("Failed to write {}: {}", path.display(), err);
+        }
+    }
+
+    // A file in the profile's directory
+    pub fn path(&self, file: impl AsRef<Path>) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    pub fn slot_path(&self, slot: usize) -> PathBuf {
+        self.path(format!("slot{}", slot + 1))
+    }
+
+    // Settings are all defaults until the player writes a settings file
+    pub fn load_settings(&self) -> Result<Settings, String> {
+        let path = self.path(SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(Settings::default());
+        }
+        Settings::load(&path)
+    }
+
+    pub fn load_bindings(&self) -> Result<Bindings, String> {
+        Bindings::load(&self.path(BINDINGS_FILE))
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
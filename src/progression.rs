@@ -0,0 +1,356 @@
+use std::path::Path;
+
+use crate::{
+    arena::ArenaVariant,
+    game::{UpgradeModule, TICKS_PER_SECOND},
+    pilot::SkillLevel,
+    save::{module_from_name, module_name},
+};
+
+//-------------------------------------------------------------------------
+// What carries over from one run to the next. Every finished run earns
+// credits, and credits unlock other ships, upgrade modules to start with and
+// world modifiers in the hangar on the main menu. Unlocks that are switched
+// on make up the loadout the next run starts with (see GameWorld::
+// apply_loadout), and the modifiers change how the world is generated.
+//
+// Kept in the profile as a text file:
+//
+//   credits 1250
+//   owned hauler
+//   active hauler
+//-------------------------------------------------------------------------
+
+const PROGRESSION_HEADER: &str = "space_survival progression 1";
+
+// a point of score and seconds survived are worth this many credits
+const CREDITS_PER_SCORE: f64 = 0.005;
+const CREDITS_PER_SEC: f64 = 0.2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ShipClass {
+    #[default]
+    Standard,
+    // heavier, shrugs off hits, more air
+    Hauler,
+    // lighter and quicker, less air
+    Interceptor,
+}
+
+impl ShipClass {
+    // the player's ship keeps its class in its variant, so saves have it
+    pub fn variant(self) -> u32 {
+        match self {
+            ShipClass::Standard => 0,
+            ShipClass::Hauler => 1,
+            ShipClass::Interceptor => 2,
+        }
+    }
+
+    pub fn from_variant(variant: u32) -> Self {
+        match variant {
+            1 => ShipClass::Hauler,
+            2 => ShipClass::Interceptor,
+            _ => ShipClass::Standard,
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            ShipClass::Standard => "standard",
+            ShipClass::Hauler => "hauler",
+            ShipClass::Interceptor => "interceptor",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        [ShipClass::Standard, ShipClass::Hauler, ShipClass::Interceptor]
+            .into_iter()
+            .find(|class| class.key() == key)
+    }
+
+    // of the standard ship's
+    pub fn mass_factor(self) -> f64 {
+        match self {
+            ShipClass::Standard => 1.0,
+            ShipClass::Hauler => 1.6,
+            ShipClass::Interceptor => 0.7,
+        }
+    }
+
+    pub fn start_air_secs(self) -> u64 {
+        match self {
+            ShipClass::Standard => 60,
+            ShipClass::Hauler => 90,
+            ShipClass::Interceptor => 45,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldModifier {
+    // half as many asteroids again
+    DenseField,
+    // fixed walls close in, turning ones too
+    ClosingWalls,
+}
+
+impl WorldModifier {
+    // Asteroid count and arena for a world asked for with these
+    pub fn apply(self, asteroids: u32, arena: ArenaVariant) -> (u32, ArenaVariant) {
+        match self {
+            WorldModifier::DenseField => (asteroids + asteroids / 2, arena),
+            WorldModifier::ClosingWalls => {
+                let arena = match arena {
+                    ArenaVariant::Fixed => ArenaVariant::Shrinking,
+                    ArenaVariant::Rotating => ArenaVariant::Royale,
+                    arena => arena,
+                };
+                (asteroids, arena)
+            }
+        }
+    }
+
+    // name used in the profile and saves
+    pub fn key(self) -> &'static str {
+        Unlock::Modifier(self).key()
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match Unlock::from_key(key)? {
+            Unlock::Modifier(modifier) => Some(modifier),
+            _ => None,
+        }
+    }
+
+    // harder worlds pay more
+    fn credit_factor(self) -> f64 {
+        match self {
+            WorldModifier::DenseField => 1.25,
+            WorldModifier::ClosingWalls => 1.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unlock {
+    Ship(ShipClass),
+    StartModule(UpgradeModule),
+    Modifier(WorldModifier),
+}
+
+// In the order the hangar lists them
+pub const UNLOCKS: [Unlock; 7] = [
+    Unlock::Ship(ShipClass::Hauler),
+    Unlock::Ship(ShipClass::Interceptor),
+    Unlock::StartModule(UpgradeModule::Thrusters),
+    Unlock::StartModule(UpgradeModule::AirRecycler),
+    Unlock::StartModule(UpgradeModule::Magnet),
+    Unlock::Modifier(WorldModifier::DenseField),
+    Unlock::Modifier(WorldModifier::ClosingWalls),
+];
+
+impl Unlock {
+    // name used in the profile and logs
+    pub fn key(self) -> &'static str {
+        match self {
+            Unlock::Ship(class) => class.key(),
+            Unlock::StartModule(module) => module_name(module),
+            Unlock::Modifier(WorldModifier::DenseField) => "dense_field",
+            Unlock::Modifier(WorldModifier::ClosingWalls) => "closing_walls",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        UNLOCKS.iter().copied().find(|unlock| unlock.key() == key)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Unlock::Ship(ShipClass::Standard) => "Standard ship",
+            Unlock::Ship(ShipClass::Hauler) => "Hauler",
+            Unlock::Ship(ShipClass::Interceptor) => "Interceptor",
+            Unlock::StartModule(UpgradeModule::Thrusters) => "Start with thrusters",
+            Unlock::StartModule(UpgradeModule::AirRecycler) => "Start with air recycler",
+            Unlock::StartModule(UpgradeModule::Magnet) => "Start with magnet",
+            Unlock::Modifier(WorldModifier::DenseField) => "Dense field",
+            Unlock::Modifier(WorldModifier::ClosingWalls) => "Closing walls",
+        }
+    }
+
+    pub fn cost(self) -> u64 {
+        match self {
+            Unlock::Ship(ShipClass::Standard) => 0,
+            Unlock::Ship(_) => 400,
+            Unlock::StartModule(_) => 150,
+            Unlock::Modifier(_) => 250,
+        }
+    }
+}
+
+// What a run starts with, kept in replays so they play back the same
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Loadout {
+    pub ship: ShipClass,
+    pub modules: Vec<UpgradeModule>,
+    // ships on top of the first (see lives.rs)
+    pub spare_lives: u32,
+    // the accessibility slow mode (see tuning.rs)
+    pub slow_mode: bool,
+    // how well the faction ships are flown
+    pub pilot_skill: SkillLevel,
+}
+
+impl Loadout {
+    // e.g. "hauler thrusters", or "hauler thrusters lives=3 slow pilots=ace" with
+    // spare lives, in slow mode and against ace pilots
+    pub fn to_text(&self) -> String {
+        let mut txt = self.ship.key().to_string();
+        for module in &self.modules {
+            txt += " ";
+            txt += module_name(*module);
+        }
+        if self.spare_lives > 0 {
+            txt += &format!(" lives={}", self.spare_lives + 1);
+        }
+        if self.slow_mode {
+            txt += " slow";
+        }
+        if self.pilot_skill != SkillLevel::default() {
+            txt += &format!(" pilots={}", self.pilot_skill.key());
+        }
+        txt
+    }
+
+    pub fn parse(txt: &str) -> Option<Self> {
+        let mut parts = txt.split_whitespace();
+        let ship = ShipClass::from_key(parts.next()?)?;
+        let mut loadout = Self {
+            ship,
+            ..Self::default()
+        };
+        for part in parts {
+            if part == "slow" {
+                loadout.slow_mode = true;
+                continue;
+            }
+            if let Some(skill) = part.strip_prefix("pilots=") {
+                loadout.pilot_skill = SkillLevel::parse(skill)?;
+                continue;
+            }
+            match part.strip_prefix("lives=") {
+                Some(lives) => loadout.spare_lives = lives.parse::<u32>().ok()?.checked_sub(1)?,
+                None => loadout.modules.push(module_from_name(part)?),
+            }
+        }
+        Some(loadout)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Progression {
+    pub credits: u64,
+    owned: Vec<Unlock>,
+    // switched on, at most one ship
+    active: Vec<Unlock>,
+}
+
+impl Progression {
+    // A profile's progress. Without a file there is none yet.
+    pub fn load(path: &Path) -> Self {
+        let mut progression = Self::default();
+        let Ok(txt) = std::fs::read_to_string(path) else {
+            return progression;
+        };
+        let mut lines = txt.lines();
+        if lines.next() != Some(PROGRESSION_HEADER) {
+            log::warn!("Ignoring progression {}: not a progression file", path.display());
+            return progression;
+        }
+        for line in lines {
+            let Some((key, value)) = line.trim().split_once(' ') else {
+                continue;
+            };
+            // unknown unlocks are from another version, and are dropped
+            match (key, Unlock::from_key(value)) {
+                ("credits", _) => progression.credits = value.parse().unwrap_or(0),
+                ("owned", Some(unlock)) => progression.owned.push(unlock),
+                ("active", Some(unlock)) if progression.owned.contains(&unlock) => {
+                    progression.active.push(unlock)
+                }
+                _ => log::warn!("Ignoring progression line '{}'", line),
+            }
+        }
+        progression
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut txt = format!("{}\ncredits {}\n", PROGRESSION_HEADER, self.credits);
+        for unlock in &self.owned {
+            txt += &format!("owned {}\n", unlock.key());
+        }
+        for unlock in &self.active {
+            txt += &format!("active {}\n", unlock.key());
+        }
+        txt
+    }
+
+    pub fn is_owned(&self, unlock: Unlock) -> bool {
+        self.owned.contains(&unlock)
+    }
+
+    pub fn is_active(&self, unlock: Unlock) -> bool {
+        self.active.contains(&unlock)
+    }
+
+    // Buy an unlock and switch it on, or switch one that's owned on or off.
+    // Switching a ship on switches the others off.
+    pub fn activate(&mut self, unlock: Unlock) -> Result<(), String> {
+        if !self.is_owned(unlock) {
+            if self.credits < unlock.cost() {
+                return Err(format!("{} needs {} credits", unlock.name(), unlock.cost()));
+            }
+            self.credits -= unlock.cost();
+            self.owned.push(unlock);
+        } else if self.is_active(unlock) {
+            self.active.retain(|&other| other != unlock);
+            return Ok(());
+        }
+        if matches!(unlock, Unlock::Ship(_)) {
+            self.active.retain(|other| !matches!(other, Unlock::Ship(_)));
+        }
+        self.active.push(unlock);
+        Ok(())
+    }
+
+    pub fn loadout(&self) -> Loadout {
+        let mut loadout = Loadout::default();
+        for unlock in &self.active {
+            match *unlock {
+                Unlock::Ship(class) => loadout.ship = class,
+                Unlock::StartModule(module) => loadout.modules.push(module),
+                Unlock::Modifier(_) => {}
+            }
+        }
+        loadout
+    }
+
+    // In UNLOCKS order, so worlds made with the same ones are the same
+    pub fn modifiers(&self) -> Vec<WorldModifier> {
+        UNLOCKS
+            .iter()
+            .filter(|unlock| self.is_active(**unlock))
+            .filter_map(|unlock| match unlock {
+                Unlock::Modifier(modifier) => Some(*modifier),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// Credits a finished run earns, more in a world made harder by modifiers
+pub fn credits_for_run(score: u64, ticks: u32, modifiers: &[WorldModifier]) -> u64 {
+    let secs = ticks as f64 / TICKS_PER_SECOND as f64;
+    let factor: f64 = modifiers.iter().map(|modifier| modifier.credit_factor()).product();
+    ((score as f64 * CREDITS_PER_SCORE + secs * CREDITS_PER_SEC) * factor).round() as u64
+}
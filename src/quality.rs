@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use vello::wgpu;
+
+use crate::settings::VelloAa;
+
+//-------------------------------------------------------------------------
+// Graphics quality presets. A preset sets how many stars there are, how
+// many floating labels can be up at once, whether the optional post
+// processing passes run, and the anti-aliasing (which msaa_samples and
+// vello_aa in the settings file still override).
+//
+// Without a quality setting, the first run probes the graphics adapters
+// and picks a preset for the best one, keeping the choice in the profile
+// so later runs start straight away with it.
+//-------------------------------------------------------------------------
+
+const QUALITY_HEADER: &str = "space_survival quality 1";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+}
+
+impl Quality {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "low" => Some(Quality::Low),
+            "medium" => Some(Quality::Medium),
+            "high" => Some(Quality::High),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quality::Low => "low",
+            Quality::Medium => "medium",
+            Quality::High => "high",
+        }
+    }
+
+    pub fn star_count(self) -> u32 {
+        match self {
+            Quality::Low => 1500,
+            Quality::Medium => 4000,
+            Quality::High => 6000,
+        }
+    }
+
+    // floating labels up at once, the oldest go first
+    pub fn effect_budget(self) -> usize {
+        match self {
+            Quality::Low => 8,
+            Quality::Medium => 24,
+            Quality::High => 64,
+        }
+    }
+
+    // the night vision pass for sensor mode; photo mode filters always run
+    pub fn post_effects(self) -> bool {
+        self != Quality::Low
+    }
+
+    pub fn vello_aa(self) -> VelloAa {
+        match self {
+            Quality::Low => VelloAa::Msaa8,
+            Quality::Medium => VelloAa::Area,
+            Quality::High => VelloAa::Msaa16,
+        }
+    }
+
+    pub fn msaa_samples(self) -> u32 {
+        match self {
+            Quality::Low | Quality::Medium => 1,
+            Quality::High => 4,
+        }
+    }
+
+    // The preset picked on an earlier run, if any
+    pub fn load(path: &Path) -> Option<Self> {
+        let txt = std::fs::read_to_string(path).ok()?;
+        let mut lines = txt.lines();
+        if lines.next() != Some(QUALITY_HEADER) {
+            log::warn!("Ignoring quality {}: not a quality file", path.display());
+            return None;
+        }
+        lines.next().and_then(|line| Quality::parse(line.trim()))
+    }
+
+    pub fn save(self, path: &Path) {
+        let txt = format!("{}\n{}\n", QUALITY_HEADER, self.name());
+        if let Err(err) = std::fs::write(path, txt) {
+            log::warn!("Failed to write {}: {}", path.display(), err);
+        }
+    }
+}
+
+// Preset for the best graphics adapter there is. Software and GL adapters, and
+// ones with small textures, get Low; integrated GPUs Medium; discrete ones High.
+pub fn probe() -> Quality {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let best = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .into_iter()
+        .map(|adapter| {
+            let info = adapter.get_info();
+            let max_texture = adapter.limits().max_texture_dimension_2d;
+            let quality = match info.device_type {
+                _ if info.backend == wgpu::Backend::Gl || max_texture < 8192 => Quality::Low,
+                wgpu::DeviceType::DiscreteGpu => Quality::High,
+                wgpu::DeviceType::IntegratedGpu | wgpu::DeviceType::VirtualGpu => Quality::Medium,
+                wgpu::DeviceType::Cpu | wgpu::DeviceType::Other => Quality::Low,
+            };
+            log::info!(
+                "gpu_adapter name=\"{}\" type={:?} backend={:?} max_texture={} quality={}",
+                info.name,
+                info.device_type,
+                info.backend,
+                max_texture,
+                quality.name()
+            );
+            quality
+        })
+        .max_by_key(|quality| *quality as u32);
+    best.unwrap_or(Quality::Low)
+}
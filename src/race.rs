@@ -0,0 +1,290 @@
+use std::{
+    f64::consts::{PI, TAU},
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use masonry::Vec2;
+
+use crate::{
+    arena::{Arena, ArenaShape, ArenaVariant},
+    game::{HashRand, TICKS_PER_SECOND},
+    replay::Replay,
+};
+
+//-------------------------------------------------------------------------
+// Time attack: a course of ring gates generated from the world seed, to fly
+// through in order against the clock. Results go in a local leaderboard,
+// and the inputs of the best run per course are kept for the ghost. The
+// world parameters also identify the world for the endless mode ghost.
+//-------------------------------------------------------------------------
+
+pub const LEADERBOARD_PATH: &str = "space_survival.leaderboard";
+
+const LEADERBOARD_HEADER: &str = "space_survival leaderboard 2";
+// before slow mode
+const LEADERBOARD_HEADER_V1: &str = "space_survival leaderboard 1";
+const MAX_ENTRIES_PER_COURSE: usize = 10;
+
+const NUM_GATES: u32 = 8;
+const GATE_RADIUS: f64 = 150.0;
+const MIN_GATE_SPACING: f64 = 800.0;
+const MAX_GATE_SPACING: f64 = 1400.0;
+// max change in course direction from one gate to the next
+const MAX_GATE_TURN: f64 = PI / 3.0;
+
+pub struct Gate {
+    pub pos: Vec2,
+    pub radius: f64,
+}
+
+// A course depends on the world it's flown in, so it's identified by all the
+// parameters that went into generating that world
+#[derive(Clone, Debug, PartialEq)]
+pub struct CourseId {
+    pub seed: u64,
+    pub extent: f64,
+    pub asteroids: u32,
+    pub arena: ArenaVariant,
+    pub arena_shape: ArenaShape,
+}
+
+impl CourseId {
+    fn key(&self) -> String {
+        let key = format!("{}_{}_{}", self.seed, self.extent, self.asteroids);
+        match (self.arena, self.arena_shape) {
+            // courses from before arena variants keep their key
+            (ArenaVariant::Fixed, ArenaShape::Square) => key,
+            (arena, ArenaShape::Square) => format!("{}_{}", key, arena.key()),
+            (arena, shape) => format!("{}_{}_{}", key, arena.key(), shape.key()),
+        }
+    }
+
+    pub fn ghost_path(&self) -> PathBuf {
+        PathBuf::from(format!("space_survival.ghost_{}", self.key()))
+    }
+
+    // Longest endless run in the same world
+    pub fn endless_ghost_path(&self) -> PathBuf {
+        PathBuf::from(format!("space_survival.ghost_{}_endless", self.key()))
+    }
+
+    // Gates chain out from the start with a random heading change between each,
+    // turning back into the arena when one would land outside it
+    pub fn generate_gates(&self, start: Vec2) -> Vec<Gate> {
+        let margin = 2.0 * GATE_RADIUS;
+        let arena = Arena::new(self.arena, self.arena_shape, self.extent);
+        let limit = arena.spawn_range(0.0).end.x - margin;
+
+        let mut gates = Vec::new();
+        let mut pos = start;
+        let mut heading = (0.0..TAU).hash_rand(self.seed, "race_heading");
+        for idx in 0..NUM_GATES {
+            let spacing = (MIN_GATE_SPACING..MAX_GATE_SPACING).hash_rand(self.seed, (idx, "race_spacing"));
+            heading += (-MAX_GATE_TURN..MAX_GATE_TURN).hash_rand(self.seed, (idx, "race_turn"));
+
+            let mut next = pos + spacing * Vec2::from_angle(heading);
+            if next.x.abs() > limit || next.y.abs() > limit {
+                // head back towards the middle instead
+                heading = (-pos).atan2();
+                next = pos + spacing * Vec2::from_angle(heading);
+            }
+            pos = Vec2::new(next.x.clamp(-limit, limit), next.y.clamp(-limit, limit));
+            gates.push(Gate {
+                pos,
+                radius: GATE_RADIUS,
+            });
+        }
+        gates
+    }
+}
+
+// State of a race in progress
+pub struct Race {
+    pub course: CourseId,
+    pub gates: Vec<Gate>,
+    pub next_gate: usize,
+    pub start_tick: u32,
+    // ticks from the start to passing each gate
+    pub splits: Vec<u32>,
+    pub best: Option<LeaderboardEntry>,
+    // inputs of this run, saved as the ghost if it's a new best
+    pub recording: Replay,
+    pub finish: Option<RaceFinish>,
+}
+
+pub struct RaceFinish {
+    pub ticks: u32,
+    // place on the course leaderboard, if it made it on
+    pub rank: Option<usize>,
+    pub top: Vec<LeaderboardEntry>,
+}
+
+impl Race {
+    pub fn new(course: CourseId, start: Vec2, start_tick: u32, best: Option<LeaderboardEntry>) -> Self {
+        let gates = course.generate_gates(start);
+        let mut recording = Replay::new(course.seed, course.extent, course.asteroids);
+        recording.arena = course.arena;
+        recording.arena_shape = course.arena_shape;
+        Self {
+            course,
+            gates,
+            next_gate: 0,
+            start_tick,
+            splits: Vec::new(),
+            best,
+            recording,
+            finish: None,
+        }
+    }
+
+    // Check the ship against the next gate, returns true when that gate was passed
+    pub fn update(&mut self, ship_pos: Vec2, tick: u32) -> bool {
+        if self.finish.is_some() {
+            return false;
+        }
+        let Some(gate) = self.gates.get(self.next_gate) else {
+            return false;
+        };
+        if (ship_pos - gate.pos).length() > gate.radius {
+            return false;
+        }
+
+        self.splits.push(tick - self.start_tick);
+        self.next_gate += 1;
+        true
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next_gate >= self.gates.len()
+    }
+
+    // Split of the last gate passed compared to the best run, in ticks (negative is faster)
+    pub fn last_split_delta(&self) -> Option<i64> {
+        let idx = self.splits.len().checked_sub(1)?;
+        let best = self.best.as_ref()?.splits.get(idx)?;
+        Some(self.splits[idx] as i64 - *best as i64)
+    }
+}
+
+// Race clock as seconds with hundredths
+pub fn format_ticks(ticks: u32) -> String {
+    format!("{:.2}", ticks as f64 / TICKS_PER_SECOND as f64)
+}
+
+// --- MARK: Leaderboard ---
+
+#[derive(Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub ticks: u32,
+    pub splits: Vec<u32>,
+    // flown in the accessibility slow mode, shown marked
+    pub slow_mode: bool,
+}
+
+// Best times per course, fastest first
+pub struct Leaderboard {
+    courses: Vec<(String, Vec<LeaderboardEntry>)>,
+}
+
+impl Leaderboard {
+    // A missing leaderboard is just empty, a broken one is logged and replaced
+    pub fn load(path: &Path) -> Self {
+        let empty = Self { courses: Vec::new() };
+        if !path.exists() {
+            return empty;
+        }
+
+        let result = std::fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|txt| Self::parse(&txt));
+        match result {
+            Ok(leaderboard) => leaderboard,
+            Err(err) => {
+                log::warn!("Ignoring leaderboard {}: {}", path.display(), err);
+                empty
+            }
+        }
+    }
+
+    fn parse(txt: &str) -> Result<Self, String> {
+        let mut lines = txt.lines();
+        if !matches!(lines.next(), Some(LEADERBOARD_HEADER | LEADERBOARD_HEADER_V1)) {
+            return Err("Not a leaderboard file (or unsupported version)".to_string());
+        }
+
+        let mut leaderboard = Self { courses: Vec::new() };
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let invalid = || format!("Invalid leaderboard entry: {}", line);
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            // "COURSE TICKS SPLITS", then "slow" for a slow mode run
+            let (entry, slow_mode) = match parts.as_slice() {
+                [entry @ .., "slow"] => (entry, true),
+                entry => (entry, false),
+            };
+            let &[course, ticks, splits] = entry else {
+                return Err(invalid());
+            };
+            let entry = LeaderboardEntry {
+                ticks: ticks.parse().map_err(|_| invalid())?,
+                splits: splits
+                    .split(',')
+                    .map(|split| split.parse().ok())
+                    .collect::<Option<_>>()
+                    .ok_or_else(invalid)?,
+                slow_mode,
+            };
+            leaderboard.insert_entry(course, entry);
+        }
+        Ok(leaderboard)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut txt = String::new();
+        writeln!(txt, "{}", LEADERBOARD_HEADER).unwrap();
+        for (course, entries) in &self.courses {
+            for entry in entries {
+                let splits: Vec<String> = entry.splits.iter().map(|split| split.to_string()).collect();
+                let slow = if entry.slow_mode { " slow" } else { "" };
+                writeln!(txt, "{} {} {}{}", course, entry.ticks, splits.join(","), slow).unwrap();
+            }
+        }
+        txt
+    }
+
+    pub fn top(&self, course: &CourseId) -> &[LeaderboardEntry] {
+        let key = course.key();
+        self.courses
+            .iter()
+            .find(|(other, _)| *other == key)
+            .map_or(&[], |(_, entries)| entries.as_slice())
+    }
+
+    pub fn best(&self, course: &CourseId) -> Option<&LeaderboardEntry> {
+        self.top(course).first()
+    }
+
+    // Returns the place the entry got, or None if it didn't make the leaderboard
+    pub fn insert(&mut self, course: &CourseId, entry: LeaderboardEntry) -> Option<usize> {
+        self.insert_entry(&course.key(), entry)
+    }
+
+    fn insert_entry(&mut self, key: &str, entry: LeaderboardEntry) -> Option<usize> {
+        let idx = match self.courses.iter().position(|(other, _)| other == key) {
+            Some(idx) => idx,
+            None => {
+                self.courses.push((key.to_string(), Vec::new()));
+                self.courses.len() - 1
+            }
+        };
+
+        let entries = &mut self.courses[idx].1;
+        let rank = entries.iter().position(|other| entry.ticks < other.ticks).unwrap_or(entries.len());
+        if rank >= MAX_ENTRIES_PER_COURSE {
+            return None;
+        }
+        entries.insert(rank, entry);
+        entries.truncate(MAX_ENTRIES_PER_COURSE);
+        Some(rank)
+    }
+}
@@ -0,0 +1,345 @@
+use std::sync::Arc;
+
+use masonry::{parley::FontContext, Affine, Point, Rect, Size, Vec2};
+use vello::Scene;
+
+use crate::{
+    game::{Rcs, Shape, RCS_BRAKE_NOZZLES, RCS_LEFT_NOZZLE, RCS_RIGHT_NOZZLE, TICKS_PER_SECOND},
+    game_shapes::{rcs_flame_scene, AnimationClip},
+    post_process::PostParams,
+    sprites::SpriteSheets,
+    text::{draw_mono_text, draw_text, TextCache, TextStyle},
+};
+
+//-------------------------------------------------------------------------
+// What gets drawn in a frame, copied out of the world so it can be drawn
+// without holding on to it. GameWorld::extract_render_frame fills it in:
+// transforms and shape handles of the entities and the numbers for the HUD
+// are copied as they are. The panels that read a lot of game state (menus,
+// minimap, tutorial and so on) are drawn into scenes during extraction, and
+// the frame just layers those with the rest.
+//-------------------------------------------------------------------------
+
+// Per frame values for the wgpu passes
+#[derive(Clone)]
+pub struct FrameParams {
+    pub camera: CameraSnapshot,
+    pub post_params: PostParams,
+}
+
+impl Default for FrameParams {
+    fn default() -> Self {
+        Self {
+            camera: CameraSnapshot::default(),
+            post_params: bytemuck::Zeroable::zeroed(),
+        }
+    }
+}
+
+// How the camera saw the world for a frame, all the render thread needs of it
+// for the shared uniform and the renderers. Copied out with the rest of the
+// frame, so drawing never waits on the world lock the simulation holds.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraSnapshot {
+    pub pos: Vec2,
+    // screen pixels per world unit at window resolution
+    pub view_scale: f64,
+    // seconds of virtual time, for what moves on its own
+    pub time: f64,
+    // on top of the viewport's own scale, in photo mode and cutscenes
+    pub zoom: f64,
+    // of the way from the last tick to the next, that the entities are drawn at
+    pub interp: f64,
+    // pixels of the window the world is drawn into, less any letterbox
+    pub viewport: Rect,
+}
+
+impl Default for CameraSnapshot {
+    fn default() -> Self {
+        Self {
+            pos: Vec2::ZERO,
+            view_scale: 1.0,
+            time: 0.0,
+            zoom: 1.0,
+            interp: 0.0,
+            viewport: Rect::ZERO,
+        }
+    }
+}
+
+pub struct RenderEntity {
+    // object to screen
+    pub transform: Affine,
+    pub shape: Option<Shape>,
+    // replaces the shape's colors, for hits and hot engines
+    pub tint: Option<xilem::Color>,
+    // of the shape, 1 is opaque
+    pub alpha: f64,
+    // clip and seconds into it
+    pub animation: Option<(AnimationClip, f64)>,
+    // maneuvering thrusters that are firing and seconds since they started
+    pub rcs: Option<(Rcs, f64)>,
+}
+
+// A floating text label, centered on a screen position
+pub struct RenderLabel {
+    pub text: String,
+    pub pos: Point,
+    pub color: xilem::Color,
+    pub alpha: f64,
+}
+
+// The numbers in the top left corner, and the game over text
+pub struct HudNumbers {
+    pub score: u64,
+    // the run is in the accessibility slow mode, its score is marked
+    pub slow_mode: bool,
+    // in ticks
+    pub air: u64,
+    pub flight_assist: bool,
+    pub cloaked: bool,
+    // spare ships left, None in runs that didn't start with any
+    pub lives: Option<u32>,
+    // until the ship comes back, while it's out of air with a life to spare
+    pub respawn_secs: Option<f64>,
+    // None where time can't be rewound, or it's free
+    pub rewind_charges: Option<u32>,
+    pub rewinding: bool,
+    pub ore: u32,
+    // name and count of the upgrade modules on board
+    pub modules: Vec<(&'static str, usize)>,
+    // name, reputation and stance of each faction, while there are faction ships
+    pub reputation: Vec<(&'static str, i32, &'static str)>,
+    // earned by the run, once it's over
+    pub credits: Option<u64>,
+    // online top ten, shown under the game over text
+    pub online_top: Option<String>,
+    // how to go back to the last checkpoint, shown right under the game over text
+    pub checkpoint: Option<String>,
+}
+
+pub struct RenderFrame {
+    // logical window size the frame is drawn for
+    pub size: Size,
+    pub params: FrameParams,
+    // set on the frame a photo mode screenshot was asked for
+    pub screenshot_supersample: Option<u32>,
+    // drawn under the entities
+    pub underlay: Scene,
+    // screen position and radius of the blips for air pods off screen
+    pub blips: Vec<(Point, f64)>,
+    pub entities: Vec<RenderEntity>,
+    // image strips drawn in place of the clips that have one
+    pub sprites: Arc<SpriteSheets>,
+    // drawn over the entities, under the overlay
+    pub labels: Vec<RenderLabel>,
+    // drawn over the entities and under the HUD numbers
+    pub overlay: Scene,
+    // None without a player
+    pub hud: Option<HudNumbers>,
+    // drawn over everything
+    pub top: Scene,
+}
+
+impl RenderFrame {
+    pub fn new(size: Size) -> Self {
+        Self {
+            size,
+            params: FrameParams::default(),
+            screenshot_supersample: None,
+            underlay: Scene::new(),
+            blips: Vec::new(),
+            entities: Vec::new(),
+            sprites: Arc::default(),
+            labels: Vec::new(),
+            overlay: Scene::new(),
+            hud: None,
+            top: Scene::new(),
+        }
+    }
+
+    pub fn render(&self, scene: &mut Scene, ctx: &mut FontContext, text_cache: &mut TextCache) {
+        scene.append(&self.underlay, None);
+
+        for (pos, radius) in &self.blips {
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::translate(pos.to_vec2()),
+                xilem::Color::rgb8(0x0, 0xd4, 0xf8),
+                None,
+                &vello::kurbo::Circle::new((0.0, 0.0), *radius),
+            );
+        }
+
+        for entity in &self.entities {
+            if let Some((clip, t)) = entity.animation {
+                match (self.sprites.get(clip), clip.length()) {
+                    (Some(sheet), Some(length)) => sheet.render(scene, t, length, entity.transform),
+                    _ => scene.append(&clip.scene(t), Some(entity.transform)),
+                }
+            }
+            if let Some((rcs, elapsed)) = &entity.rcs {
+                render_rcs_flames(scene, rcs, *elapsed, entity.transform);
+            }
+            if let Some(shape) = &entity.shape {
+                append_shape(scene, shape, entity.transform, entity.tint, entity.alpha);
+            }
+        }
+
+        for label in &self.labels {
+            let style = TextStyle {
+                font_size: 20.0,
+                color: label.color,
+                alignment: xilem::TextAlignment::Start,
+            };
+            let (text, size) = text_cache.get(ctx, &label.text, &style);
+            let transform = Affine::translate(label.pos.to_vec2() - 0.5 * size.to_vec2());
+            let clip = vello::kurbo::Rect::from_origin_size(Point::ZERO, size);
+            scene.push_layer(vello::peniko::BlendMode::default(), label.alpha as f32, transform, &clip);
+            scene.append(text, Some(transform));
+            scene.pop_layer();
+        }
+        text_cache.end_frame();
+
+        scene.append(&self.overlay, None);
+        if let Some(hud) = &self.hud {
+            hud.render(scene, ctx, self.size);
+        }
+        scene.append(&self.top, None);
+    }
+}
+
+impl HudNumbers {
+    fn render(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size) {
+        let min_dim = size.width.min(size.height);
+        let margin = 0.05 * min_dim;
+
+        let air_secs = self.air as f64 / TICKS_PER_SECOND as f64;
+        let slow_mode = if self.slow_mode { " (slow mode)" } else { "" };
+        let mut txt = format!("Score: {}{}\nAir: {:.1} seconds", self.score, slow_mode, air_secs);
+        if self.flight_assist {
+            txt += "\nFlight assist";
+        }
+        if self.cloaked {
+            txt += "\nCloaked";
+        }
+        if let Some(lives) = self.lives {
+            txt += &format!("\nSpare ships: {}", lives);
+        }
+        if let Some(charges) = self.rewind_charges {
+            txt += &format!("\nRewinds: {}", charges);
+        }
+        if self.ore > 0 {
+            txt += &format!("\nOre: {}", self.ore);
+        }
+        for (name, count) in &self.modules {
+            txt += &format!("\n{} x{}", name, count);
+        }
+        for (name, reputation, stance) in &self.reputation {
+            txt += &format!("\n{}: {} ({})", name, reputation, stance);
+        }
+        if let Some(credits) = self.credits {
+            txt += &format!("\nCredits earned: {}", credits);
+        }
+        let style = TextStyle {
+            font_size: 24.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0xff),
+            alignment: xilem::TextAlignment::Start,
+        };
+        draw_text(scene, ctx, &txt, &style, Point::new(margin, margin), Vec2::ZERO);
+
+        if self.rewinding {
+            // a blue wash over the world going backwards
+            let screen = Rect::from_origin_size(Point::ZERO, size);
+            let wash = xilem::Color::rgba8(0x40, 0x80, 0xff, 0x30);
+            scene.fill(vello::peniko::Fill::NonZero, Affine::IDENTITY, wash, None, &screen);
+            let style = TextStyle {
+                font_size: 48.0,
+                color: xilem::Color::rgb8(0x80, 0xc0, 0xff),
+                alignment: xilem::TextAlignment::Middle,
+            };
+            let top = Point::new(0.5 * size.width, 0.2 * size.height);
+            draw_text(scene, ctx, "<< REWIND", &style, top, Vec2::new(0.5, 0.5));
+        }
+        if let Some(secs) = self.respawn_secs {
+            let style = TextStyle {
+                font_size: 48.0,
+                color: xilem::Color::rgb8(0xff, 0xc0, 0x20),
+                alignment: xilem::TextAlignment::Middle,
+            };
+            let center = Point::new(0.5 * size.width, 0.5 * size.height);
+            let txt = format!("  SHIP LOST\nBack in {}...", secs.ceil());
+            draw_text(scene, ctx, &txt, &style, center, Vec2::new(0.5, 0.5));
+        } else if self.air == 0 {
+            let style = TextStyle {
+                font_size: 48.0,
+                color: xilem::Color::rgb8(0xff, 0x00, 0x00),
+                alignment: xilem::TextAlignment::Middle,
+            };
+            let center = Point::new(0.5 * size.width, 0.5 * size.height);
+            let txt = "    GAME OVER\nYou are out of air!";
+            let game_over = draw_text(scene, ctx, txt, &style, center, Vec2::new(0.5, 0.5));
+            let mut below = center + Vec2::new(0.0, 0.5 * game_over.height + 24.0);
+
+            if let Some(checkpoint) = self.checkpoint.as_ref() {
+                let style = TextStyle {
+                    font_size: 24.0,
+                    color: xilem::Color::rgb8(0xff, 0xff, 0xff),
+                    alignment: xilem::TextAlignment::Middle,
+                };
+                let prompt = draw_text(scene, ctx, checkpoint, &style, below, Vec2::new(0.5, 0.0));
+                below.y += prompt.height + 24.0;
+            }
+            if let Some(online_top) = self.online_top.as_ref() {
+                let style = TextStyle {
+                    font_size: 18.0,
+                    color: xilem::Color::rgb8(0xff, 0xff, 0x80),
+                    alignment: xilem::TextAlignment::Start,
+                };
+                draw_mono_text(scene, ctx, online_top, &style, below, Vec2::new(0.5, 0.0));
+            }
+        }
+    }
+}
+
+// Flames of the maneuvering thrusters that are firing, on the ship's transform
+fn render_rcs_flames(scene: &mut Scene, rcs: &Rcs, elapsed: f64, transform: Affine) {
+    let flame = rcs_flame_scene(elapsed);
+    let mut nozzles = Vec::new();
+    if rcs.brake {
+        nozzles.extend(RCS_BRAKE_NOZZLES);
+    }
+    if rcs.strafe < 0.0 {
+        nozzles.push(RCS_LEFT_NOZZLE);
+    } else if rcs.strafe > 0.0 {
+        nozzles.push(RCS_RIGHT_NOZZLE);
+    }
+    for (pos, angle) in nozzles {
+        scene.append(&flame, Some(transform * Affine::rotate(angle).then_translate(pos)));
+    }
+}
+
+// Render shape with its color replaced by the tint color, if any, and faded by alpha
+fn append_shape(scene: &mut Scene, shape: &Shape, transform: Affine, tint: Option<xilem::Color>, alpha: f64) {
+    if tint.is_none() && alpha >= 1.0 {
+        scene.append(shape.scene(), Some(transform));
+        return;
+    }
+
+    let clip = vello::kurbo::Circle::new((0.0, 0.0), shape.radius());
+    scene.push_layer(vello::peniko::BlendMode::default(), alpha as f32, transform, &clip);
+    scene.append(shape.scene(), Some(transform));
+
+    // src-atop only draws the tint color where the shape was drawn
+    if let Some(color) = tint {
+        scene.push_layer(
+            vello::peniko::BlendMode::new(vello::peniko::Mix::Normal, vello::peniko::Compose::SrcAtop),
+            1.0,
+            transform,
+            &clip,
+        );
+        scene.fill(vello::peniko::Fill::NonZero, transform, color, None, &clip);
+        scene.pop_layer();
+    }
+    scene.pop_layer();
+}
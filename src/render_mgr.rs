@@ -1,14 +1,34 @@
 use bytemuck::{Pod, Zeroable};
-use masonry::{event_loop_runner::{MasonryState, WindowState}, Vec2};
-use vello::wgpu::{self, Buffer, Device, RenderPass};
+use masonry::event_loop_runner::{MasonryState, WindowState};
+use vello::wgpu::{self, Buffer, CommandEncoder, Device, Queue, RenderPass, TextureFormat, TextureView};
 
-use crate::GameState;
+use crate::{
+    post_process::{PostChain, PostParams, SCENE_FORMAT},
+    render_frame::{CameraSnapshot, FrameParams},
+    screenshot::{self, Readback},
+    settings::Settings,
+    sim_thread::Frames,
+};
 
+// Camera and time for every custom pass, written once a frame by
+// RenderManager::render from the frame's CameraSnapshot. The starfield reads it as group 0 and the post
+// passes as group 1, declared with GLOBAL_RENDER_DATA_SHADER.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct GlobalRenderData {
     pub pos: [f32; 2],
     pub screen_size: [f32; 2],
+    // screen pixels per world unit
+    pub view_scale: f32,
+    // seconds of virtual time, for what moves on its own (the far stars drift)
+    pub time: f32,
+    // photo mode and cutscene zoom, already part of view_scale
+    pub zoom: f32,
+    // of the way from the last tick to the next, that the entities are drawn at
+    pub interp: f32,
+    // x, y, width and height in pixels of where the world is drawn, inside any
+    // letterbox
+    pub viewport: [f32; 4],
 }
 impl GlobalRenderData {
     pub fn setup(device: &Device) -> Buffer {
@@ -25,15 +45,81 @@ impl GlobalRenderData {
     }
 }
 
+// GlobalRenderData as the shaders see it, put in front of their source
+pub const GLOBAL_RENDER_DATA_SHADER: &str = r#"
+struct GlobalRenderData {
+    cam_pos: vec2<f32>,
+    screen_size: vec2<f32>,
+    view_scale: f32,
+    time: f32,
+    zoom: f32,
+    interp: f32,
+    viewport: vec4<f32>,
+};
+"#;
+
+// Renderers only get the camera of the frame being shown, never the world, so
+// preparing a frame can't end up waiting on the simulation.
+//
+// The RenderManager tells them when what they draw into changes, so they don't
+// have to check every frame: resize before the first frame and whenever the
+// size changes (screenshots are drawn bigger), and recreate when they are
+// carried over to a new device after the app was suspended, followed by a
+// resize. The surface format only matters to renderers that draw straight to
+// the surface, the rest draw into the scene texture.
 pub trait Renderer {
-    fn prepare(&mut self,masonry_state: &mut MasonryState, game_state: &GameState, width: u32, height: u32);
+    fn prepare(&mut self,masonry_state: &mut MasonryState, camera: &CameraSnapshot, width: u32, height: u32);
     fn render<'rpass>(&'rpass self, render_pass: &mut RenderPass<'rpass>, width: u32, height: u32);
-    fn finish_render(&mut self, masonry_state: &mut MasonryState, game_state: &GameState);
+    fn finish_render(&mut self, masonry_state: &mut MasonryState);
+
+    fn resize(&mut self, _device: &Device, _width: u32, _height: u32) {}
+    fn on_surface_format_changed(&mut self, _device: &Device, _format: TextureFormat) {}
+    // Everything made on the old device is gone, make it again on this one
+    fn recreate(&mut self, device: &Device, queue: &Queue, global_buffer: &Buffer);
+}
+
+// Multisampled color target that the custom passes render into before being
+// resolved to the surface.
+struct MsaaTarget {
+    view: TextureView,
+    width: u32,
+    height: u32,
+}
+
+impl MsaaTarget {
+    fn new(device: &Device, format: TextureFormat, sample_count: u32, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MsaaTarget"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { view, width, height }
+    }
+
+    fn need_resize(&self, width: u32, height: u32) -> bool {
+        self.width != width || self.height != height
+    }
 }
 
 pub struct RenderManager {
     renderers: Vec<Box<dyn Renderer>>,
     global_render_data_buffer: Option<Buffer>,
+    sample_count: u32,
+    msaa_target: Option<MsaaTarget>,
+    post_chain: Option<PostChain>,
+    surface_format: TextureFormat,
+    // the renderers were last resized to, None when they need to be again
+    size: Option<(u32, u32)>,
 }
 
 impl RenderManager {
@@ -41,16 +127,64 @@ impl RenderManager {
         Self {
             renderers: Vec::new(),
             global_render_data_buffer: None,
+            sample_count: 1,
+            msaa_target: None,
+            post_chain: None,
+            surface_format: TextureFormat::Bgra8Unorm,
+            size: None,
         }
     }
 
-    pub fn setup(&mut self, device: &Device) {
-        self.global_render_data_buffer = Some(GlobalRenderData::setup(device));
+    // Make the manager's own resources on device, and again those of the
+    // renderers kept from before the app was suspended
+    pub fn setup(&mut self, device: &Device, queue: &Queue, surface_format: TextureFormat, settings: &Settings) {
+        let global_buffer = GlobalRenderData::setup(device);
+        self.post_chain = Some(PostChain::new(device, surface_format, &global_buffer));
+        let format_changed = surface_format != self.surface_format;
+        self.surface_format = surface_format;
+        for renderer in &mut self.renderers {
+            renderer.recreate(device, queue, &global_buffer);
+            if format_changed {
+                renderer.on_surface_format_changed(device, surface_format);
+            }
+        }
+        self.global_render_data_buffer = Some(global_buffer);
+        self.size = None;
+
+        // Without adapter specific format features only 1x and 4x are guaranteed to work
+        let adapter_specific = device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        self.sample_count = match settings.msaa_samples() {
+            0 | 1 => 1,
+            2 if adapter_specific => 2,
+            4 => 4,
+            samples => {
+                log::warn!("Unsupported msaa sample count {}, using 4x instead", samples);
+                4
+            }
+        };
     }
 
-    pub fn clear(&mut self) {
+    // The device goes away while suspended. The renderers are kept, to be made
+    // again by setup on the next one.
+    pub fn suspend(&mut self) {
         self.global_render_data_buffer = None;
-        self.renderers.clear();
+        self.msaa_target = None;
+        self.post_chain = None;
+        self.size = None;
+    }
+
+    pub fn has_renderers(&self) -> bool {
+        !self.renderers.is_empty()
+    }
+
+    // format renderers need to target, the scene is post processed before reaching the surface
+    pub fn get_scene_format(&self) -> TextureFormat {
+        SCENE_FORMAT
+    }
+
+    // sample count renderers need to use for their pipelines
+    pub fn get_sample_count(&self) -> u32 {
+        self.sample_count
     }
 
     pub fn get_global_buffer(&self) -> Option<&Buffer> {
@@ -59,9 +193,45 @@ impl RenderManager {
 
     pub fn add_renderer(&mut self, renderer: Box<dyn Renderer>) {
         self.renderers.push(renderer);
+        self.size = None;
     }
 
-    pub fn render(&mut self, masonry_state: &mut MasonryState, game_state: &GameState) {
+    fn resize_renderers(&mut self, device: &Device, width: u32, height: u32) {
+        if self.size == Some((width, height)) {
+            return;
+        }
+        for renderer in &mut self.renderers {
+            renderer.resize(device, width, height);
+        }
+        self.size = Some((width, height));
+    }
+
+    fn write_global_data(&self, masonry_state: &mut MasonryState, camera: &CameraSnapshot, width: u32, height: u32, scale: f64) {
+        let (Some((_device, queue)), Some(global_buffer)) =
+            (masonry_state.get_render_device_and_queue(), self.global_render_data_buffer.as_ref())
+        else {
+            return;
+        };
+
+        let viewport = camera.viewport.scale_from_origin(scale);
+        let global_render_data = GlobalRenderData {
+            pos: [camera.pos.x as f32, camera.pos.y as f32],
+            screen_size: [width as f32, height as f32],
+            view_scale: (scale * camera.view_scale) as f32,
+            time: camera.time as f32,
+            zoom: camera.zoom as f32,
+            interp: camera.interp as f32,
+            viewport: [
+                viewport.x0 as f32,
+                viewport.y0 as f32,
+                viewport.width() as f32,
+                viewport.height() as f32,
+            ],
+        };
+        queue.write_buffer(global_buffer, 0, bytemuck::cast_slice(&[global_render_data]));
+    }
+
+    pub fn render(&mut self, masonry_state: &mut MasonryState, frames: &Frames) {
         let (width, height) = if let WindowState::Rendering {
             window, ..
         } = &mut masonry_state.get_window_state() {
@@ -72,29 +242,21 @@ impl RenderManager {
             return ;
         };
 
-        if let Some((_device, queue)) = masonry_state.get_render_device_and_queue() {
-            let game_world = game_state.lock().unwrap();
-            let cam_pos = if let Some(control_obj) = game_world.get_control_object() {
-                let control_obj = &game_world.get_entities().get(control_obj);
-                control_obj.render_transform.translation()
-            }
-            else {
-                // no control object, put camera at origin
-                Vec2::ZERO
-            };
-
-            // fill global buffer
-            if let Some(global_buffer) = self.global_render_data_buffer.as_ref() {
-                let global_render_data = GlobalRenderData { pos: [cam_pos.x as f32, cam_pos.y as f32], screen_size: [width as f32, height as f32] };
-                queue.write_buffer(global_buffer, 0, bytemuck::cast_slice(&[global_render_data]));
-            }    
-        }
-        else {
-            unreachable!()
+        // the sim thread has already read what's needed from the world, so no need to lock it
+        let (frame, screenshot_supersample) = frames.latch();
+
+        if let Some(supersample) = screenshot_supersample {
+            self.render_screenshot(masonry_state, &frame, width, height, supersample);
         }
 
+        self.write_global_data(masonry_state, &frame.camera, width, height, 1.0);
+
+        let Some((device, _queue)) = masonry_state.get_render_device_and_queue() else {
+            unreachable!();
+        };
+        self.resize_renderers(device, width, height);
         for renderer in &mut self.renderers {
-            renderer.prepare(masonry_state, &game_state, width, height);
+            renderer.prepare(masonry_state, &frame.camera, width, height);
         }
 
         let surface_texture = masonry_state.get_next_frame();
@@ -112,14 +274,104 @@ impl RenderManager {
         };
 
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.encode_passes(device, queue, &mut encoder, width, height, &frame.post_params, &surface_view);
 
-        let color_attachment = wgpu::RenderPassColorAttachment {
-            view: &surface_view,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                store: wgpu::StoreOp::Store,
+        queue.submit(Some(encoder.finish()));
+        surface_texture.present();
+
+        for renderer in &mut self.renderers {
+            renderer.finish_render(masonry_state);
+        }
+    }
+
+    // Render the current frame at a multiple of the window resolution and save it as a png
+    fn render_screenshot(&mut self, masonry_state: &mut MasonryState, frame: &FrameParams, width: u32, height: u32, supersample: u32) {
+        let Some((device, _queue)) = masonry_state.get_render_device_and_queue() else {
+            unreachable!();
+        };
+
+        // stay within the texture size the device supports
+        let max_supersample = device.limits().max_texture_dimension_2d / width.max(height).max(1);
+        let supersample = supersample.clamp(1, max_supersample.max(1));
+        let (shot_width, shot_height) = (width * supersample, height * supersample);
+        self.resize_renderers(device, shot_width, shot_height);
+
+        self.write_global_data(masonry_state, &frame.camera, shot_width, shot_height, supersample as f64);
+        for renderer in &mut self.renderers {
+            renderer.prepare(masonry_state, &frame.camera, shot_width, shot_height);
+        }
+
+        let Some((device, queue)) = masonry_state.get_render_device_and_queue() else {
+            unreachable!();
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("ScreenshotTexture"),
+            size: wgpu::Extent3d {
+                width: shot_width,
+                height: shot_height,
+                depth_or_array_layers: 1,
             },
-            resolve_target: None,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.encode_passes(device, queue, &mut encoder, shot_width, shot_height, &frame.post_params, &view);
+        let readback = Readback::new(device, &mut encoder, &texture);
+        queue.submit(Some(encoder.finish()));
+
+        let (shot_width, shot_height) = (readback.width(), readback.height());
+        match readback.read(device) {
+            Ok(pixels) => screenshot::save_png_in_background(screenshot::screenshot_path(), shot_width, shot_height, pixels),
+            Err(err) => log::error!("Failed to take screenshot: {}", err),
+        }
+
+        for renderer in &mut self.renderers {
+            renderer.finish_render(masonry_state);
+        }
+    }
+
+    // Render all renderers into the scene texture and post process it into output_view
+    fn encode_passes(&mut self, device: &Device, queue: &Queue, encoder: &mut CommandEncoder, width: u32, height: u32, post_params: &PostParams, output_view: &TextureView) {
+        if self.sample_count > 1 {
+            if self.msaa_target.as_ref().map(|t| t.need_resize(width, height)).unwrap_or(true) {
+                self.msaa_target = Some(MsaaTarget::new(device, SCENE_FORMAT, self.sample_count, width, height));
+            }
+        }
+
+        let Some(post_chain) = self.post_chain.as_mut() else {
+            unreachable!("Render manager has not been set up");
+        };
+        post_chain.prepare(device, queue, width, height, post_params);
+        let post_chain = self.post_chain.as_ref().unwrap();
+        let scene_view = post_chain.get_scene_view();
+
+        // when multisampling, render into the msaa target and resolve into the scene texture
+        let color_attachment = if let Some(msaa_target) = self.msaa_target.as_ref() {
+            wgpu::RenderPassColorAttachment {
+                view: &msaa_target.view,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Discard,
+                },
+                resolve_target: Some(scene_view),
+            }
+        }
+        else {
+            wgpu::RenderPassColorAttachment {
+                view: scene_view,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                resolve_target: None,
+            }
         };
 
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -135,12 +387,6 @@ impl RenderManager {
         }
         drop(render_pass);
 
-        queue.submit(Some(encoder.finish()));
-        surface_texture.present();
-
-        for renderer in &mut self.renderers {
-            renderer.finish_render(masonry_state, game_state);
-        }
+        post_chain.run(encoder, output_view, post_params);
     }
 }
-
@@ -0,0 +1,283 @@
+use std::{fmt::Write as _, path::Path};
+
+use crate::{
+    actions::{Action, ActionInput, ACTIONS, NUM_ACTIONS},
+    arena::{arena_name, parse_arena_name, ArenaShape, ArenaVariant},
+    game::GameMode,
+    progression::Loadout,
+    rng::NUM_RNG_STREAMS,
+    spawn_table,
+};
+
+//-------------------------------------------------------------------------
+// Replay of a run: the world parameters plus what the player's keys did to
+// each action (see actions.rs) and the tick it happened on. Since the
+// simulation only depends on the seed and the actions, this is enough to
+// reproduce a run, and it plays back the same with any key bindings. Worlds
+// also depend on the spawn tables, so the hash of those is kept too.
+//-------------------------------------------------------------------------
+
+const REPLAY_HEADER: &str = "space_survival replay 10";
+// Versions before 5 were recorded before objects were placed from random
+// streams (see rng.rs), in worlds that can't be made again, versions before
+// 7 recorded keys rather than actions, versions before 8 don't say what
+// spawn tables they were recorded with, before 9 the repulsor fired when
+// its key was pressed rather than let go, and before 10 objects that didn't
+// fit were placed differently
+const REPLAY_HEADER_PREFIX: &str = "space_survival replay ";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayEvent {
+    pub tick: u32,
+    pub action: Action,
+    pub input: ActionInput,
+}
+
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub extent: f64,
+    pub asteroids: u32,
+    pub mode: GameMode,
+    pub arena: ArenaVariant,
+    pub arena_shape: ArenaShape,
+    // ship and modules the run started with
+    pub loadout: Loadout,
+    // hash of the spawn tables the world was made with (see spawn_table.rs)
+    pub spawn_tables: u64,
+    // tick the recording was finished at and the draws of each random stream
+    // by then, in RNG_STREAMS order. Playback checks it came out the same.
+    pub rng_check: Option<(u32, [u32; NUM_RNG_STREAMS])>,
+    events: Vec<ReplayEvent>,
+    // next event to play back
+    cursor: usize,
+    // actions held after the last event of each, recorded or played back
+    held: [bool; NUM_ACTIONS],
+}
+
+impl Replay {
+    pub fn new(seed: u64, extent: f64, asteroids: u32) -> Self {
+        Self {
+            seed,
+            extent,
+            asteroids,
+            mode: GameMode::Endless,
+            arena: ArenaVariant::Fixed,
+            arena_shape: ArenaShape::Square,
+            loadout: Loadout::default(),
+            spawn_tables: spawn_table::builtin_hash(),
+            rng_check: None,
+            events: Vec::new(),
+            cursor: 0,
+            held: [false; NUM_ACTIONS],
+        }
+    }
+
+    // Record what the keys did to the actions this tick. Only the actions
+    // something happened to are kept, the rest are held or not as they were.
+    pub fn record(&mut self, tick: u32, inputs: &[ActionInput; NUM_ACTIONS]) {
+        for (idx, input) in inputs.iter().enumerate() {
+            let action = ACTIONS[idx];
+            // a key let go while the simulation was paused only shows in down
+            let changed = input.made || input.broke || input.down != self.held[idx];
+            if !action.affects_simulation() || !changed {
+                continue;
+            }
+            self.held[idx] = input.down;
+            self.events.push(ReplayEvent {
+                tick,
+                action,
+                input: *input,
+            });
+        }
+    }
+
+    // What the keys did to the actions on this tick: the events up to it,
+    // and the actions without one held or not as after their last event
+    pub fn tick_inputs(&mut self, tick: u32) -> [ActionInput; NUM_ACTIONS] {
+        let mut inputs = self.held.map(|down| ActionInput {
+            down,
+            ..ActionInput::default()
+        });
+        while self.cursor < self.events.len() && self.events[self.cursor].tick <= tick {
+            let event = self.events[self.cursor];
+            inputs[event.action as usize] = event.input;
+            self.held[event.action as usize] = event.input.down;
+            self.cursor += 1;
+        }
+        inputs
+    }
+
+    // Drops the events before tick, for a recording that only looks back so far
+    pub fn forget_before(&mut self, tick: u32) {
+        self.events.retain(|event| event.tick >= tick);
+        self.cursor = 0;
+    }
+
+    // Carries on with the events of another recording of the same world from tick on
+    pub fn append_from(&mut self, other: &Replay, tick: u32) {
+        self.events.extend(other.events.iter().filter(|event| event.tick >= tick).copied());
+    }
+
+    // Adds the events other has past the ones this has, when this is an earlier
+    // copy of other. False, with nothing added, when it isn't.
+    pub fn catch_up(&mut self, other: &Replay) -> bool {
+        let len = self.events.len();
+        let same_run = self.seed == other.seed
+            && self.mode == other.mode
+            && self.spawn_tables == other.spawn_tables
+            && len <= other.events.len()
+            && self.events.last() == len.checked_sub(1).map(|last| &other.events[last]);
+        if same_run {
+            self.events.extend_from_slice(&other.events[len..]);
+        }
+        same_run
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.events.len()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    // FNV-1a of the saved text, identifies a run without sending the whole replay
+    pub fn hash(&self) -> u64 {
+        fnv1a(self.to_text().as_bytes())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut txt = String::new();
+        writeln!(txt, "{}", REPLAY_HEADER).unwrap();
+        writeln!(txt, "seed {}", self.seed).unwrap();
+        writeln!(txt, "extent {}", self.extent).unwrap();
+        writeln!(txt, "asteroids {}", self.asteroids).unwrap();
+        writeln!(txt, "mode {}", self.mode.key()).unwrap();
+        writeln!(txt, "arena {}", arena_name(self.arena, self.arena_shape)).unwrap();
+        writeln!(txt, "loadout {}", self.loadout.to_text()).unwrap();
+        writeln!(txt, "spawn_tables {:016x}", self.spawn_tables).unwrap();
+        for event in &self.events {
+            writeln!(txt, "{} {} {}", event.tick, event.action.key(), input_name(&event.input)).unwrap();
+        }
+        if let Some((tick, draws)) = self.rng_check {
+            let draws: Vec<String> = draws.iter().map(u32::to_string).collect();
+            writeln!(txt, "rng {} {}", tick, draws.join(" ")).unwrap();
+        }
+        txt
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let txt = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read replay {}: {}", path.display(), err))?;
+        Self::parse(&txt)
+    }
+
+    pub fn parse(txt: &str) -> Result<Self, String> {
+        let mut lines = txt.lines();
+        match lines.next() {
+            Some(REPLAY_HEADER) => {}
+            Some(header) if header.starts_with(REPLAY_HEADER_PREFIX) => {
+                return Err("Replay is from an older version and can't be played back".to_string())
+            }
+            _ => return Err("Not a replay file (or unsupported version)".to_string()),
+        }
+
+        let mut header_value = |name: &str| -> Result<String, String> {
+            let line = lines.next().unwrap_or_default();
+            match line.split_once(' ') {
+                Some((key, value)) if key == name => Ok(value.to_string()),
+                _ => Err(format!("Expected '{}' in replay header, found '{}'", name, line)),
+            }
+        };
+        let seed = header_value("seed")?;
+        let extent = header_value("extent")?;
+        let asteroids = header_value("asteroids")?;
+        let mode = header_value("mode")?;
+        let mode = GameMode::from_key(&mode).ok_or_else(|| format!("Invalid mode: {}", mode))?;
+        let arena = header_value("arena")?;
+        let (arena, arena_shape) =
+            parse_arena_name(&arena).ok_or_else(|| format!("Invalid arena: {}", arena))?;
+        let loadout = header_value("loadout")?;
+        let loadout = Loadout::parse(&loadout).ok_or_else(|| format!("Invalid loadout: {}", loadout))?;
+        let spawn_tables = header_value("spawn_tables")?;
+        let spawn_tables = u64::from_str_radix(&spawn_tables, 16)
+            .map_err(|_| format!("Invalid spawn tables: {}", spawn_tables))?;
+
+        let mut replay = Replay::new(
+            seed.parse().map_err(|_| format!("Invalid seed: {}", seed))?,
+            extent.parse().map_err(|_| format!("Invalid extent: {}", extent))?,
+            asteroids.parse().map_err(|_| format!("Invalid asteroids: {}", asteroids))?,
+        );
+        replay.mode = mode;
+        replay.arena = arena;
+        replay.arena_shape = arena_shape;
+        replay.loadout = loadout;
+        replay.spawn_tables = spawn_tables;
+
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            if let Some(check) = line.strip_prefix("rng ") {
+                let check = parse_rng_check(check).ok_or_else(|| format!("Invalid rng: {}", line))?;
+                replay.rng_check = Some(check);
+                continue;
+            }
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let &[tick, action, input] = parts.as_slice() else {
+                return Err(format!("Invalid replay event: {}", line));
+            };
+            let tick = tick.parse().map_err(|_| format!("Invalid tick: {}", line))?;
+            let action = Action::from_key(action).ok_or_else(|| format!("Unknown action: {}", line))?;
+            let input = parse_input(input).ok_or_else(|| format!("Invalid action input: {}", line))?;
+            replay.events.push(ReplayEvent { tick, action, input });
+        }
+
+        Ok(replay)
+    }
+}
+
+// "TICK DRAWS...", draws in RNG_STREAMS order
+fn parse_rng_check(value: &str) -> Option<(u32, [u32; NUM_RNG_STREAMS])> {
+    let values: Vec<u32> = value
+        .split_whitespace()
+        .map(|value| value.parse().ok())
+        .collect::<Option<_>>()?;
+    let (tick, draws) = values.split_first()?;
+    Some((*tick, draws.try_into().ok()?))
+}
+
+// e.g. "made+down" for a key pressed and held, "made+broke" for a tap, and
+// "up" for one let go while the simulation was paused
+fn input_name(input: &ActionInput) -> String {
+    let parts: Vec<&str> = [(input.made, "made"), (input.broke, "broke"), (input.down, "down")]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect();
+    if parts.is_empty() {
+        return "up".to_string();
+    }
+    parts.join("+")
+}
+
+fn parse_input(name: &str) -> Option<ActionInput> {
+    let mut input = ActionInput::default();
+    if name == "up" {
+        return Some(input);
+    }
+    for part in name.split('+') {
+        match part {
+            "made" => input.made = true,
+            "broke" => input.broke = true,
+            "down" => input.down = true,
+            _ => return None,
+        }
+    }
+    Some(input)
+}
+
+// FNV-1a, stable across builds and platforms unlike std's hasher
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
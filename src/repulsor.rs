@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use masonry::Vec2;
+
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// The ship's repulsor: a pulse that shoves everything around the ship away
+// from it, for clearing a path through the rocks without weapons. Close by
+// things get the full push and it falls off to nothing at the edge of its
+// reach. The push is an impulse, so heavy rocks barely move while debris
+// goes flying (up to REPULSOR_MAX_DELTA_V). It then takes a while to charge
+// back up. GameWorld::fire_repulsor finds what it hits and pushes it; this
+// keeps the cooldown and the shockwave rings it leaves behind, which like
+// the border effects are only for show and run on virtual time.
+//
+// It fires when the key is let go. Holding it down for REPULSOR_HOLD first
+// overcharges the pulse, pushing REPULSOR_OVERCHARGE times as hard.
+//-------------------------------------------------------------------------
+
+// reaches this far past the ship's center, to the edge of what it hits
+pub const REPULSOR_RADIUS: f64 = 400.0;
+// impulse given to something right next to the ship, a mid-sized rock
+// moves off at a few units/tick
+pub const REPULSOR_IMPULSE: f64 = 90000.0;
+// units/tick, the most it speeds anything up by
+pub const REPULSOR_MAX_DELTA_V: f64 = 15.0;
+// how long the key has to be held to overcharge the pulse
+pub const REPULSOR_HOLD: Duration = Duration::from_secs(1);
+pub const REPULSOR_OVERCHARGE: f64 = 1.5;
+const REPULSOR_COOLDOWN_TICKS: u32 = 8 * TICKS_PER_SECOND as u32;
+const SHOCKWAVE_SECS: f64 = 0.5;
+
+pub struct Shockwave {
+    // world position of the ship that fired it
+    pub pos: Vec2,
+    start_time: f64,
+}
+
+impl Shockwave {
+    // How far out the ring is (0 to 1 of REPULSOR_RADIUS) and how bright
+    // (1 down to 0) at a virtual time. It races out and slows at the edge.
+    pub fn reach_and_alpha(&self, time: f64) -> (f64, f64) {
+        let t = ((time - self.start_time) / SHOCKWAVE_SECS).clamp(0.0, 1.0);
+        (1.0 - (1.0 - t) * (1.0 - t), 1.0 - t)
+    }
+}
+
+#[derive(Default)]
+pub struct Repulsor {
+    // ticks until it can fire again
+    cooldown: u32,
+    shockwaves: Vec<Shockwave>,
+}
+
+impl Repulsor {
+    pub fn is_ready(&self) -> bool {
+        self.cooldown == 0
+    }
+
+    // 0 right after firing, 1 when it's ready
+    pub fn charge(&self) -> f64 {
+        1.0 - self.cooldown as f64 / REPULSOR_COOLDOWN_TICKS as f64
+    }
+
+    // Starts charging up again and sends a ring out from pos
+    pub fn fire(&mut self, pos: Vec2, time: f64) {
+        self.cooldown = REPULSOR_COOLDOWN_TICKS;
+        self.shockwaves.push(Shockwave { pos, start_time: time });
+    }
+
+    // A tick has passed, drop the rings that have faded out
+    pub fn update(&mut self, time: f64) {
+        self.cooldown = self.cooldown.saturating_sub(1);
+        self.shockwaves.retain(|wave| time - wave.start_time < SHOCKWAVE_SECS);
+    }
+
+    pub fn shockwaves(&self) -> impl Iterator<Item = &Shockwave> {
+        self.shockwaves.iter()
+    }
+}
+
+// Change in velocity (units/tick) of something with inv_mass whose near edge
+// is dist from the ship's center
+pub fn repulsor_delta_v(dist: f64, inv_mass: f64, overcharged: bool) -> f64 {
+    let falloff = (1.0 - dist / REPULSOR_RADIUS).clamp(0.0, 1.0);
+    let scale = if overcharged { REPULSOR_OVERCHARGE } else { 1.0 };
+    (scale * REPULSOR_IMPULSE * falloff * inv_mass).min(scale * REPULSOR_MAX_DELTA_V)
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+use crate::{game::TICKS_PER_SECOND, save::WorldSnapshot};
+
+//-------------------------------------------------------------------------
+// Rewinding time in an endless run. The world is snapshotted in memory (the
+// same way as for a save) every few ticks, keeping the last few seconds.
+// Using a charge goes back through them one a tick, so the last seconds
+// play backwards at several times the speed, and the run carries on from
+// the oldest. A few charges to a run, but with
+// --check-invariants they're free, for going back over whatever just broke.
+//
+// Like going back to a checkpoint, this drops the run's recording, so it
+// doesn't make the leaderboard or leave a ghost.
+//-------------------------------------------------------------------------
+
+const REWIND_CHARGES: u32 = 3;
+// ticks between snapshots
+const REWIND_STEP_TICKS: u32 = 5;
+// how far back it goes
+const REWIND_SNAPSHOTS: usize = 5 * TICKS_PER_SECOND as usize / REWIND_STEP_TICKS as usize;
+
+pub struct Rewind {
+    // oldest first
+    snapshots: VecDeque<WorldSnapshot>,
+    charges: u32,
+    // going back through the snapshots
+    active: bool,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            charges: REWIND_CHARGES,
+            active: false,
+        }
+    }
+}
+
+impl Rewind {
+    pub fn charges(&self) -> u32 {
+        self.charges
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn is_snapshot_due(&self, tick: u32) -> bool {
+        !self.active && tick % REWIND_STEP_TICKS == 0
+    }
+
+    pub fn push(&mut self, snapshot: WorldSnapshot) {
+        if self.snapshots.len() == REWIND_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    // Forget the snapshots, they're of another timeline
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.active = false;
+    }
+
+    // Start going back, using up a charge unless it's free. False if there's no
+    // charge left or nothing to go back to.
+    pub fn start(&mut self, free: bool) -> bool {
+        if self.snapshots.is_empty() || (!free && self.charges == 0) {
+            return false;
+        }
+        if !free {
+            self.charges -= 1;
+        }
+        self.active = true;
+        true
+    }
+
+    // The next snapshot going back, the last one stops the rewind
+    pub fn step(&mut self) -> Option<WorldSnapshot> {
+        let snapshot = self.snapshots.pop_back();
+        self.active = !self.snapshots.is_empty();
+        snapshot
+    }
+}
@@ -0,0 +1,45 @@
+use crate::game::HashRand;
+
+//-------------------------------------------------------------------------
+// Named streams of reproducible random numbers. A stream hashes the world's
+// seed with its name and how many draws it has made, so a system draws from
+// its own stream without threading seed and sequence numbers around, and
+// systems drawing more or less often don't shift each other's numbers.
+//
+// How many draws each stream has made is saved with the world, and checked
+// at the end of a replay's playback: a count that doesn't match means the
+// playback went its own way.
+//-------------------------------------------------------------------------
+
+// Streams of a world, in the order saves and replays keep their draws
+pub const NUM_RNG_STREAMS: usize = 4;
+pub const RNG_STREAMS: [&str; NUM_RNG_STREAMS] = ["placement", "asteroids", "loot", "encounters"];
+
+#[derive(Clone, Debug)]
+pub struct RngStream {
+    seed: u64,
+    name: &'static str,
+    draws: u32,
+}
+
+impl RngStream {
+    pub fn new(seed: u64, name: &'static str) -> Self {
+        Self { seed, name, draws: 0 }
+    }
+
+    // A value in range, different for every draw
+    pub fn next<T>(&mut self, range: impl HashRand<T>) -> T {
+        let value = range.hash_rand(self.seed, (self.name, self.draws));
+        self.draws += 1;
+        value
+    }
+
+    pub fn draws(&self) -> u32 {
+        self.draws
+    }
+
+    // Carry on from a saved world
+    pub fn set_draws(&mut self, draws: u32) {
+        self.draws = draws;
+    }
+}
@@ -0,0 +1,842 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use masonry::Vec2;
+
+use crate::{
+    arena::{arena_name, parse_arena_name, ArenaShape, ArenaVariant},
+    faction::Faction,
+    combo::Combo,
+    game::{Cargo, GameObjectType, Loot, UpgradeModule, NUM_ASTEROID_VARIANTS, NUM_MINE_VARIANTS},
+    lives::Lives,
+    navigation::NavPath,
+    pilot::SkillLevel,
+    platform_services::PlatformServices,
+    progression::WorldModifier,
+    replay::fnv1a,
+    rng::NUM_RNG_STREAMS,
+    status::{StatusEffect, StatusEffects, StatusKind},
+};
+
+//-------------------------------------------------------------------------
+// Snapshot of the simulation state that is enough to rebuild the world and
+// keep playing. Transient state (tints, camera kick, input) is not saved.
+//
+// A save also carries what the run needs to go on as it was: the combo, the
+// lives, slow mode, the pilots' skill, the status effects and the world
+// modifiers. The grid the AI ships plan on and the routes they planned are
+// kept too, so they fly on rather than all turning off somewhere else at once.
+// Debris, guns and the carrier aren't saved, so a continued run can't be
+// played back and has no recording. The last line is a checksum of
+// everything before it, so a damaged save is turned down rather than
+// continued.
+//-------------------------------------------------------------------------
+
+pub const AUTOSAVE_PATH: &str = "space_survival.autosave";
+
+const SAVE_HEADER: &str = "space_survival save 10";
+// before the AI ships' routes
+const SAVE_HEADER_V9: &str = "space_survival save 9";
+// before the pilots' skill
+const SAVE_HEADER_V8: &str = "space_survival save 8";
+// before slow mode
+const SAVE_HEADER_V7: &str = "space_survival save 7";
+// before lives
+const SAVE_HEADER_V6: &str = "space_survival save 6";
+// before random streams
+const SAVE_HEADER_V5: &str = "space_survival save 5";
+// before the run state and the checksum
+const SAVE_HEADER_V4: &str = "space_survival save 4";
+// before arena variants, always fixed
+const SAVE_HEADER_V3: &str = "space_survival save 3";
+
+#[derive(Clone, Debug)]
+pub struct EntitySnapshot {
+    pub object_type: GameObjectType,
+    // outline and material of asteroids, class of the player's ship
+    pub variant: u32,
+    pub pos: Vec2,
+    pub rotation: f64,
+    pub velocity: Vec2,
+    pub angular_velocity: f64,
+    pub air: Option<u64>,
+    pub score: Option<u64>,
+    // loot left to salvage (derelicts)
+    pub loot: Option<Loot>,
+    pub cargo: Option<Cargo>,
+    pub faction: Option<Faction>,
+    // faction ship that has been knocked out and drifts
+    pub disabled: bool,
+    pub heat: Option<f64>,
+    pub status: StatusEffects,
+    // the way a faction ship is following
+    pub nav_path: Option<NavPath>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WorldSnapshot {
+    pub seed: u64,
+    pub extent: f64,
+    pub arena: ArenaVariant,
+    pub arena_shape: ArenaShape,
+    pub sequence: u32,
+    pub tick: u32,
+    // index into entities
+    pub control_object: Option<usize>,
+    // player's reputation with each faction, in FACTIONS order
+    pub reputation: [i32; 3],
+    pub modifiers: Vec<WorldModifier>,
+    pub combo: Combo,
+    pub lives: Lives,
+    // the accessibility slow mode (see tuning.rs)
+    pub slow_mode: bool,
+    // of the faction ships' pilots, from the difficulty the run started with
+    pub pilot_skill: SkillLevel,
+    // draws of each random stream, in RNG_STREAMS order
+    pub rng_draws: [u32; NUM_RNG_STREAMS],
+    // clutter of the cells AI ships plan their routes on, as of when it was
+    // last built (see navigation.rs)
+    pub nav_grid: Option<Vec<f64>>,
+    pub entities: Vec<EntitySnapshot>,
+}
+
+impl WorldSnapshot {
+    pub fn serialize(&self) -> String {
+        let mut txt = String::new();
+        writeln!(txt, "{}", SAVE_HEADER).unwrap();
+        writeln!(txt, "seed {}", self.seed).unwrap();
+        writeln!(txt, "extent {}", self.extent).unwrap();
+        writeln!(txt, "arena {}", arena_name(self.arena, self.arena_shape)).unwrap();
+        writeln!(txt, "sequence {}", self.sequence).unwrap();
+        writeln!(txt, "tick {}", self.tick).unwrap();
+        match self.control_object {
+            Some(idx) => writeln!(txt, "control {}", idx).unwrap(),
+            None => writeln!(txt, "control -").unwrap(),
+        }
+        let [pirates, traders, patrol] = self.reputation;
+        writeln!(txt, "reputation {} {} {}", pirates, traders, patrol).unwrap();
+        let modifiers: Vec<&str> = self.modifiers.iter().map(|modifier| modifier.key()).collect();
+        if modifiers.is_empty() {
+            writeln!(txt, "modifiers -").unwrap();
+        } else {
+            writeln!(txt, "modifiers {}", modifiers.join("+")).unwrap();
+        }
+        writeln!(txt, "combo {}", self.combo.to_text()).unwrap();
+        writeln!(txt, "lives {}", self.lives.to_text()).unwrap();
+        writeln!(txt, "slow_mode {}", self.slow_mode).unwrap();
+        writeln!(txt, "pilot_skill {}", self.pilot_skill.key()).unwrap();
+        let rng_draws: Vec<String> = self.rng_draws.iter().map(u32::to_string).collect();
+        writeln!(txt, "rng {}", rng_draws.join(" ")).unwrap();
+        match self.nav_grid.as_ref() {
+            Some(clutter) => {
+                let clutter: Vec<String> = clutter.iter().map(f64::to_string).collect();
+                writeln!(txt, "nav_grid {}", clutter.join(" ")).unwrap();
+            }
+            None => writeln!(txt, "nav_grid -").unwrap(),
+        }
+
+        // f64 Display round trips, so the restored world matches exactly
+        for entity in &self.entities {
+            writeln!(
+                txt,
+                "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+                type_name(entity.object_type),
+                entity.variant,
+                entity.pos.x,
+                entity.pos.y,
+                entity.rotation,
+                entity.velocity.x,
+                entity.velocity.y,
+                entity.angular_velocity,
+                optional_value(entity.air),
+                optional_value(entity.score),
+                entity.loot.map_or_else(|| "-".to_string(), loot_name),
+                entity.cargo.as_ref().map_or_else(|| "-".to_string(), cargo_name),
+                faction_name(entity.faction, entity.disabled),
+                optional_value(entity.heat),
+                status_name(&entity.status),
+                entity.nav_path.as_ref().map_or_else(|| "-".to_string(), NavPath::to_text),
+            )
+            .unwrap();
+        }
+        writeln!(txt, "checksum {:016x}", fnv1a(txt.as_bytes())).unwrap();
+        txt
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let txt = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read save {}: {}", path.display(), err))?;
+        Self::parse(&txt)
+    }
+
+    fn parse(txt: &str) -> Result<Self, String> {
+        // what each version has on top of the one before: arena variants, the run
+        // state, random streams, lives, slow mode, the pilots' skill and the AI routes
+        let version = match txt.lines().next() {
+            Some(SAVE_HEADER) => 10,
+            Some(SAVE_HEADER_V9) => 9,
+            Some(SAVE_HEADER_V8) => 8,
+            Some(SAVE_HEADER_V7) => 7,
+            Some(SAVE_HEADER_V6) => 6,
+            Some(SAVE_HEADER_V5) => 5,
+            Some(SAVE_HEADER_V4) => 4,
+            Some(SAVE_HEADER_V3) => 3,
+            _ => return Err("Not a save file (or unsupported version)".to_string()),
+        };
+        let (has_arena, has_run, has_rng) = (version >= 4, version >= 5, version >= 6);
+        let (has_lives, has_slow_mode, has_pilot_skill) = (version >= 7, version >= 8, version >= 9);
+        let has_nav = version >= 10;
+        let txt = if has_run { verify_checksum(txt)? } else { txt };
+        // saves used to end in the run's recording, it can't go on from here
+        let txt = match txt.split_once("\nrecording\n") {
+            Some((world, _)) if has_run => world,
+            _ => txt,
+        };
+
+        let mut lines = txt.lines().skip(1);
+
+        let mut header_value = |name: &str| -> Result<String, String> {
+            let line = lines.next().unwrap_or_default();
+            match line.split_once(' ') {
+                Some((key, value)) if key == name => Ok(value.to_string()),
+                _ => Err(format!("Expected '{}' in save header, found '{}'", name, line)),
+            }
+        };
+        let seed = header_value("seed")?;
+        let extent = header_value("extent")?;
+        let (arena, arena_shape) = if has_arena {
+            let arena = header_value("arena")?;
+            parse_arena_name(&arena).ok_or_else(|| format!("Invalid arena: {}", arena))?
+        } else {
+            (ArenaVariant::Fixed, ArenaShape::Square)
+        };
+        let sequence = header_value("sequence")?;
+        let tick = header_value("tick")?;
+        let control = header_value("control")?;
+        let reputation = header_value("reputation")?;
+        let (modifiers, combo) = if has_run {
+            let modifiers = header_value("modifiers")?;
+            let combo = header_value("combo")?;
+            (
+                parse_modifiers(&modifiers).ok_or_else(|| format!("Invalid modifiers: {}", modifiers))?,
+                Combo::parse(&combo).ok_or_else(|| format!("Invalid combo: {}", combo))?,
+            )
+        } else {
+            (Vec::new(), Combo::default())
+        };
+        let lives = if has_lives {
+            let lives = header_value("lives")?;
+            Lives::parse(&lives).ok_or_else(|| format!("Invalid lives: {}", lives))?
+        } else {
+            Lives::default()
+        };
+        let slow_mode = if has_slow_mode {
+            let slow_mode = header_value("slow_mode")?;
+            slow_mode.parse().map_err(|_| format!("Invalid slow_mode: {}", slow_mode))?
+        } else {
+            false
+        };
+        let pilot_skill = if has_pilot_skill {
+            let skill = header_value("pilot_skill")?;
+            SkillLevel::parse(&skill).ok_or_else(|| format!("Invalid pilot_skill: {}", skill))?
+        } else {
+            SkillLevel::default()
+        };
+        let rng = if has_rng { Some(header_value("rng")?) } else { None };
+        let nav_grid = if has_nav {
+            let nav_grid = header_value("nav_grid")?;
+            parse_nav_grid(&nav_grid).ok_or_else(|| format!("Invalid nav_grid: {}", nav_grid))?
+        } else {
+            None
+        };
+
+        let sequence: u32 = sequence.parse().map_err(|_| format!("Invalid sequence: {}", sequence))?;
+        let rng_draws = match rng {
+            Some(rng) => parse_rng_draws(&rng, sequence).ok_or_else(|| format!("Invalid rng: {}", rng))?,
+            // anything past the draws that made the world will do
+            None => [sequence; NUM_RNG_STREAMS],
+        };
+
+        let mut snapshot = WorldSnapshot {
+            seed: seed.parse().map_err(|_| format!("Invalid seed: {}", seed))?,
+            extent: extent.parse().map_err(|_| format!("Invalid extent: {}", extent))?,
+            arena,
+            arena_shape,
+            sequence,
+            tick: tick.parse().map_err(|_| format!("Invalid tick: {}", tick))?,
+            control_object: parse_optional(&control).ok_or_else(|| format!("Invalid control: {}", control))?,
+            reputation: parse_reputation(&reputation)
+                .ok_or_else(|| format!("Invalid reputation: {}", reputation))?,
+            modifiers,
+            combo,
+            lives,
+            slow_mode,
+            pilot_skill,
+            rng_draws,
+            nav_grid,
+            entities: Vec::new(),
+        };
+
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            snapshot.entities.push(parse_entity(line, has_run, has_nav)?);
+        }
+
+        if snapshot.control_object.is_some_and(|idx| idx >= snapshot.entities.len()) {
+            return Err(format!("Invalid control: {}", control));
+        }
+
+        Ok(snapshot)
+    }
+}
+
+// The text before the checksum line, if the checksum matches it
+fn verify_checksum(txt: &str) -> Result<&str, String> {
+    let missing = || "Save has no checksum".to_string();
+    let (body, last) = txt.trim_end().rsplit_once('\n').ok_or_else(missing)?;
+    let checksum = last
+        .strip_prefix("checksum ")
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+        .ok_or_else(missing)?;
+    // the checksum takes in the newline ending the last line before it
+    let body = &txt[..body.len() + 1];
+    if fnv1a(body.as_bytes()) != checksum {
+        return Err("Save is damaged, its checksum doesn't match".to_string());
+    }
+    Ok(body)
+}
+
+fn parse_entity(line: &str, has_run: bool, has_nav: bool) -> Result<EntitySnapshot, String> {
+    let invalid = || format!("Invalid entity: {}", line);
+    let mut parts: Vec<&str> = line.split_whitespace().collect();
+    let nav_path = if has_nav {
+        match parts.pop().ok_or_else(invalid)? {
+            "-" => None,
+            nav_path => Some(NavPath::parse(nav_path).ok_or_else(invalid)?),
+        }
+    } else {
+        None
+    };
+    let (heat, status) = if has_run {
+        let status = parts.pop().ok_or_else(invalid)?;
+        let heat = parts.pop().ok_or_else(invalid)?;
+        (
+            parse_optional(heat).ok_or_else(invalid)?,
+            parse_status(status).ok_or_else(invalid)?,
+        )
+    } else {
+        (None, StatusEffects::default())
+    };
+    let &[object_type, variant, x, y, rotation, vx, vy, ang_vel, air, score, loot, cargo, faction] =
+        parts.as_slice()
+    else {
+        return Err(invalid());
+    };
+
+    let float = |value: &str| value.parse::<f64>().map_err(|_| invalid());
+
+    let object_type = type_from_name(object_type).ok_or_else(invalid)?;
+    let variant: u32 = variant.parse().map_err(|_| invalid())?;
+    if object_type == GameObjectType::Asteroid && variant >= NUM_ASTEROID_VARIANTS {
+        return Err(invalid());
+    }
+    if object_type == GameObjectType::Mine && variant >= NUM_MINE_VARIANTS {
+        return Err(invalid());
+    }
+
+    let (faction, disabled) = parse_faction(faction).ok_or_else(invalid)?;
+    if faction.is_some() && object_type != GameObjectType::Ship {
+        return Err(invalid());
+    }
+    if nav_path.is_some() && faction.is_none() {
+        return Err(invalid());
+    }
+
+    Ok(EntitySnapshot {
+        object_type,
+        variant,
+        pos: Vec2::new(float(x)?, float(y)?),
+        rotation: float(rotation)?,
+        velocity: Vec2::new(float(vx)?, float(vy)?),
+        angular_velocity: float(ang_vel)?,
+        air: parse_optional(air).ok_or_else(invalid)?,
+        score: parse_optional(score).ok_or_else(invalid)?,
+        loot: match loot {
+            "-" => None,
+            loot => Some(parse_loot(loot).ok_or_else(invalid)?),
+        },
+        cargo: match cargo {
+            "-" => None,
+            cargo => Some(parse_cargo(cargo).ok_or_else(invalid)?),
+        },
+        faction,
+        disabled,
+        heat,
+        status,
+        nav_path,
+    })
+}
+
+// "-" or "KIND:TICKS:DURATION:STACKS+..."
+fn status_name(status: &StatusEffects) -> String {
+    let effects: Vec<String> = status
+        .active()
+        .map(|effect| {
+            let kind = effect.kind.key();
+            format!("{}:{}:{}:{}", kind, effect.ticks, effect.duration, effect.stacks)
+        })
+        .collect();
+    if effects.is_empty() {
+        "-".to_string()
+    } else {
+        effects.join("+")
+    }
+}
+
+fn parse_status(value: &str) -> Option<StatusEffects> {
+    if value == "-" {
+        return Some(StatusEffects::default());
+    }
+    let effects = value
+        .split('+')
+        .map(|effect| {
+            let parts: Vec<&str> = effect.split(':').collect();
+            let &[kind, ticks, duration, stacks] = parts.as_slice() else {
+                return None;
+            };
+            Some(StatusEffect {
+                kind: StatusKind::from_key(kind)?,
+                ticks: ticks.parse().ok()?,
+                duration: duration.parse().ok()?,
+                stacks: stacks.parse().ok()?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    StatusEffects::from_effects(effects)
+}
+
+fn parse_modifiers(value: &str) -> Option<Vec<WorldModifier>> {
+    match value {
+        "-" => Some(Vec::new()),
+        value => value.split('+').map(WorldModifier::from_key).collect(),
+    }
+}
+
+fn parse_reputation(value: &str) -> Option<[i32; 3]> {
+    let values: Vec<i32> = value
+        .split_whitespace()
+        .map(|value| value.parse().ok())
+        .collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+// "-" or the clutter of each cell, row by row
+fn parse_nav_grid(value: &str) -> Option<Option<Vec<f64>>> {
+    if value == "-" {
+        return Some(None);
+    }
+    value.split_whitespace().map(|value| value.parse().ok()).collect::<Option<_>>().map(Some)
+}
+
+// Draws in RNG_STREAMS order. Saves from before the later streams were added
+// have fewer, those streams go on from fallback.
+fn parse_rng_draws(value: &str, fallback: u32) -> Option<[u32; NUM_RNG_STREAMS]> {
+    let values: Vec<u32> = value
+        .split_whitespace()
+        .map(|value| value.parse().ok())
+        .collect::<Option<_>>()?;
+    if values.is_empty() || values.len() > NUM_RNG_STREAMS {
+        return None;
+    }
+    let mut draws = [fallback; NUM_RNG_STREAMS];
+    draws[..values.len()].copy_from_slice(&values);
+    Some(draws)
+}
+
+// "-", "FACTION" or "FACTION:disabled"
+fn faction_name(faction: Option<Faction>, disabled: bool) -> String {
+    match faction {
+        Some(faction) if disabled => format!("{}:disabled", faction.key()),
+        Some(faction) => faction.key().to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn parse_faction(value: &str) -> Option<(Option<Faction>, bool)> {
+    match value {
+        "-" => Some((None, false)),
+        value => match value.split_once(':') {
+            Some((faction, "disabled")) => Some((Some(Faction::from_key(faction)?), true)),
+            Some(_) => None,
+            None => Some((Some(Faction::from_key(value)?), false)),
+        },
+    }
+}
+
+// "air:N", "ore:N" or "upgrade:NAME"
+fn loot_name(loot: Loot) -> String {
+    match loot {
+        Loot::Air(air) => format!("air:{}", air),
+        Loot::Ore(ore) => format!("ore:{}", ore),
+        Loot::Upgrade(module) => format!("upgrade:{}", module_name(module)),
+    }
+}
+
+fn parse_loot(value: &str) -> Option<Loot> {
+    match value.split_once(':')? {
+        ("air", air) => Some(Loot::Air(air.parse().ok()?)),
+        ("ore", ore) => Some(Loot::Ore(ore.parse().ok()?)),
+        ("upgrade", module) => Some(Loot::Upgrade(module_from_name(module)?)),
+        _ => None,
+    }
+}
+
+// "ORE:MODULE+MODULE..."
+fn cargo_name(cargo: &Cargo) -> String {
+    let modules: Vec<&str> = cargo.upgrades.iter().map(|module| module_name(*module)).collect();
+    format!("{}:{}", cargo.ore, modules.join("+"))
+}
+
+fn parse_cargo(value: &str) -> Option<Cargo> {
+    let (ore, modules) = value.split_once(':')?;
+    let upgrades = modules
+        .split('+')
+        .filter(|module| !module.is_empty())
+        .map(module_from_name)
+        .collect::<Option<Vec<_>>>()?;
+    Some(Cargo {
+        ore: ore.parse().ok()?,
+        upgrades,
+    })
+}
+
+pub fn module_name(module: UpgradeModule) -> &'static str {
+    match module {
+        UpgradeModule::Thrusters => "thrusters",
+        UpgradeModule::AirRecycler => "air_recycler",
+        UpgradeModule::Magnet => "magnet",
+    }
+}
+
+pub fn module_from_name(name: &str) -> Option<UpgradeModule> {
+    match name {
+        "thrusters" => Some(UpgradeModule::Thrusters),
+        "air_recycler" => Some(UpgradeModule::AirRecycler),
+        "magnet" => Some(UpgradeModule::Magnet),
+        _ => None,
+    }
+}
+
+fn type_name(object_type: GameObjectType) -> &'static str {
+    match object_type {
+        GameObjectType::Ship => "ship",
+        GameObjectType::Asteroid => "asteroid",
+        GameObjectType::AidPod => "air_pod",
+        GameObjectType::Derelict => "derelict",
+        GameObjectType::Station => "station",
+        GameObjectType::Barrier => "barrier",
+        GameObjectType::Mine => "mine",
+        GameObjectType::Boss => unreachable!("Boss in save"),
+        GameObjectType::Debris => unreachable!("Debris in save"),
+        GameObjectType::Scenery => unreachable!("Scenery in save"),
+        GameObjectType::Turret => unreachable!("Turret in save"),
+        GameObjectType::Projectile => unreachable!("Projectile in save"),
+        GameObjectType::Dummy => unreachable!("Dummy object in save"),
+    }
+}
+
+fn type_from_name(name: &str) -> Option<GameObjectType> {
+    match name {
+        "ship" => Some(GameObjectType::Ship),
+        "asteroid" => Some(GameObjectType::Asteroid),
+        "air_pod" => Some(GameObjectType::AidPod),
+        "derelict" => Some(GameObjectType::Derelict),
+        "station" => Some(GameObjectType::Station),
+        "barrier" => Some(GameObjectType::Barrier),
+        "mine" => Some(GameObjectType::Mine),
+        _ => None,
+    }
+}
+
+fn optional_value<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+// "-" is None, anything else has to parse. Returns None if it doesn't.
+fn parse_optional<T: std::str::FromStr>(value: &str) -> Option<Option<T>> {
+    if value == "-" {
+        Some(None)
+    } else {
+        value.parse().ok().map(Some)
+    }
+}
+
+// Load a save (the autosave or a save slot) if there is one. An autosave only
+// survives quitting mid-run or an unclean exit, so finding one means the last
+// run can be continued.
+pub fn load_save(path: &Path) -> Option<WorldSnapshot> {
+    if !path.exists() {
+        return None;
+    }
+
+    match WorldSnapshot::load(path) {
+        Ok(snapshot) => {
+            log::info!("save_found path={} tick={}", path.display(), snapshot.tick);
+            Some(snapshot)
+        }
+        Err(err) => {
+            log::warn!("Ignoring save {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+// --- MARK: Autosaver ---
+
+//-------------------------------------------------------------------------
+// Writes autosaves on a background thread so the game loop never waits on
+// the disk (or the cloud). They go through the platform's cloud files,
+// which keep the previous autosave intact if the game crashes mid-write.
+//-------------------------------------------------------------------------
+
+enum AutosaveRequest {
+    Write(String),
+    Remove,
+}
+
+pub struct Autosaver {
+    sender: Option<Sender<AutosaveRequest>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Autosaver {
+    pub fn new(path: PathBuf, platform: Arc<dyn PlatformServices>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = std::thread::Builder::new()
+            .name("autosave".to_string())
+            .spawn(move || {
+                for request in receiver {
+                    match request {
+                        AutosaveRequest::Write(txt) => {
+                            if let Err(err) = platform.store_cloud_file(&path, txt.as_bytes()) {
+                                log::warn!("Failed to write autosave {}: {}", path.display(), err);
+                            }
+                        }
+                        AutosaveRequest::Remove => {
+                            if let Err(err) = platform.remove_cloud_file(&path) {
+                                log::warn!("Failed to remove autosave {}: {}", path.display(), err);
+                            }
+                        }
+                    }
+                }
+            })
+            .expect("Failed to start autosave thread");
+
+        Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn save(&self, snapshot: &WorldSnapshot) {
+        log::debug!("autosave tick={}", snapshot.tick);
+        self.send(AutosaveRequest::Write(snapshot.serialize()));
+    }
+
+    pub fn remove(&self) {
+        self.send(AutosaveRequest::Remove);
+    }
+
+    fn send(&self, request: AutosaveRequest) {
+        if let Some(sender) = self.sender.as_ref() {
+            // only fails if the thread is gone, which has already been logged
+            let _ = sender.send(request);
+        }
+    }
+}
+
+impl Drop for Autosaver {
+    // finish any pending writes before going away
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::Replay;
+
+    const SEED: u64 = 1234;
+    const SEQUENCE: u32 = 7;
+
+    fn sample() -> WorldSnapshot {
+        let ship = EntitySnapshot {
+            object_type: GameObjectType::Ship,
+            variant: 0,
+            pos: Vec2::new(12.5, -3.25),
+            rotation: 0.1,
+            velocity: Vec2::new(1.0 / 3.0, 0.0),
+            angular_velocity: -0.02,
+            air: Some(900),
+            score: Some(42),
+            loot: None,
+            cargo: None,
+            faction: None,
+            disabled: false,
+            heat: Some(0.375),
+            status: StatusEffects::default(),
+            nav_path: None,
+        };
+        let unmanned = EntitySnapshot { air: None, score: None, heat: None, ..ship.clone() };
+        let trader = EntitySnapshot {
+            faction: Some(Faction::Traders),
+            disabled: true,
+            nav_path: NavPath::parse("240:100.5,-20:60,0.25+100.5,-20"),
+            ..unmanned.clone()
+        };
+        let derelict = EntitySnapshot {
+            object_type: GameObjectType::Derelict,
+            loot: Some(Loot::Ore(5)),
+            ..unmanned.clone()
+        };
+        let asteroid = EntitySnapshot { object_type: GameObjectType::Asteroid, variant: 3, ..unmanned };
+        WorldSnapshot {
+            seed: SEED,
+            extent: 4000.0,
+            arena: ArenaVariant::Rotating,
+            arena_shape: ArenaShape::Hexagon,
+            sequence: SEQUENCE,
+            tick: 300,
+            control_object: Some(0),
+            reputation: [-5, 10, 0],
+            modifiers: vec![WorldModifier::DenseField],
+            combo: Combo::default(),
+            lives: Lives::new(2),
+            slow_mode: true,
+            pilot_skill: SkillLevel::Ace,
+            rng_draws: std::array::from_fn(|idx| 100 + idx as u32),
+            nav_grid: Some(vec![0.0, 0.125, f64::INFINITY, 1.5]),
+            entities: vec![ship, trader, derelict, asteroid],
+        }
+    }
+
+    // The sample as an older version wrote it
+    fn older_save(version: u32) -> String {
+        // header lines and the version they came in with
+        let added = [
+            ("arena", 4),
+            ("modifiers", 5),
+            ("combo", 5),
+            ("rng", 6),
+            ("lives", 7),
+            ("slow_mode", 8),
+            ("pilot_skill", 9),
+            ("nav_grid", 10),
+        ];
+        let mut txt = format!("space_survival save {}\n", version);
+        for line in sample().serialize().lines().skip(1) {
+            let key = line.split(' ').next().unwrap();
+            if key == "checksum" {
+                break;
+            }
+            if added.iter().any(|&(name, since)| key == name && version < since) {
+                continue;
+            }
+            if type_from_name(key).is_some() {
+                // no heat or status effects before 5, and no route before 10
+                let parts: Vec<&str> = line.split(' ').collect();
+                let dropped = if version < 5 { 3 } else { 1 };
+                writeln!(txt, "{}", parts[..parts.len() - dropped].join(" ")).unwrap();
+            } else {
+                writeln!(txt, "{}", line).unwrap();
+            }
+        }
+        if version >= 5 {
+            let checksum = fnv1a(txt.as_bytes());
+            writeln!(txt, "checksum {:016x}", checksum).unwrap();
+        }
+        txt
+    }
+
+    #[test]
+    fn round_trip() {
+        let txt = sample().serialize();
+        let snapshot = WorldSnapshot::parse(&txt).unwrap();
+        assert_eq!(snapshot.serialize(), txt);
+        assert_eq!((snapshot.arena, snapshot.arena_shape), (ArenaVariant::Rotating, ArenaShape::Hexagon));
+        assert_eq!(snapshot.lives, Lives::new(2));
+        assert_eq!(snapshot.pilot_skill, SkillLevel::Ace);
+        assert!(snapshot.slow_mode);
+        assert_eq!(snapshot.entities[1].faction, Some(Faction::Traders));
+        assert_eq!(snapshot.entities[2].loot, Some(Loot::Ore(5)));
+        let nav_path = snapshot.entities[1].nav_path.as_ref().unwrap();
+        assert_eq!(nav_path.waypoints(), &[Vec2::new(60.0, 0.25), Vec2::new(100.5, -20.0)]);
+        assert_eq!(snapshot.nav_grid.as_ref().map(Vec::len), Some(4));
+    }
+
+    #[test]
+    fn damaged_save_is_rejected() {
+        let txt = sample().serialize();
+        let patched = txt.replace("asteroid 3 ", "asteroid 2 ");
+        assert_ne!(patched, txt);
+        assert!(WorldSnapshot::parse(&patched).unwrap_err().contains("checksum"));
+
+        let (body, _) = txt.trim_end().rsplit_once('\n').unwrap();
+        assert!(WorldSnapshot::parse(body).is_err());
+        assert!(WorldSnapshot::parse(&txt[..txt.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn recording_in_older_save_is_dropped() {
+        let mut txt = String::new();
+        for line in sample().serialize().lines().filter(|line| !line.starts_with("checksum ")) {
+            writeln!(txt, "{}", line).unwrap();
+        }
+        writeln!(txt, "recording").unwrap();
+        txt.push_str(&Replay::new(SEED, 4000.0, 80).to_text());
+        let checksum = fnv1a(txt.as_bytes());
+        writeln!(txt, "checksum {:016x}", checksum).unwrap();
+        let snapshot = WorldSnapshot::parse(&txt).unwrap();
+        assert_eq!(snapshot.entities.len(), 4);
+        assert_eq!(snapshot.serialize(), sample().serialize());
+    }
+
+    #[test]
+    fn older_versions_load_with_defaults() {
+        for version in 3..=9 {
+            let snapshot = WorldSnapshot::parse(&older_save(version))
+                .unwrap_or_else(|err| panic!("version {}: {}", version, err));
+            assert_eq!(snapshot.seed, SEED);
+            assert_eq!(snapshot.entities.len(), 4);
+            assert_eq!(snapshot.control_object, Some(0));
+            let skill = if version >= 9 { SkillLevel::Ace } else { SkillLevel::default() };
+            assert_eq!(snapshot.pilot_skill, skill);
+            assert!(snapshot.nav_grid.is_none());
+            assert!(snapshot.entities.iter().all(|entity| entity.nav_path.is_none()));
+            assert_eq!(snapshot.slow_mode, version >= 8);
+            let lives = if version >= 7 { Lives::new(2) } else { Lives::default() };
+            assert_eq!(snapshot.lives, lives);
+            if version < 6 {
+                assert_eq!(snapshot.rng_draws, [SEQUENCE; NUM_RNG_STREAMS]);
+            }
+            if version < 5 {
+                assert!(snapshot.modifiers.is_empty());
+                assert!(snapshot.entities.iter().all(|entity| entity.heat.is_none()));
+            }
+            if version < 4 {
+                assert_eq!((snapshot.arena, snapshot.arena_shape), (ArenaVariant::Fixed, ArenaShape::Square));
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::ops::Range;
+
+use masonry::Vec2;
+
+//-------------------------------------------------------------------------
+// Scenery: things that are only there to be looked at. Specks of dust hang
+// around the ship, so even in an empty stretch of the field there's
+// something to show how fast it's going, and a few ships far off cross the
+// field on their own business. Ships show their engine flames, and the
+// player's ship tows the ore it has picked up behind it. They're render-only entities (see
+// EntityFlags::RENDER_ONLY): physics and the spatial db skip them, so
+// nothing can run into them or find them, they aren't saved and they don't
+// show up on the maps. They're still moved every tick and interpolated and
+// drawn like everything else, behind it all.
+//
+// Like the debris they come from the seed and are made once with the
+// world, so a world rebuilt from a snapshot has the same ones. Flames and
+// the ore pod are attached to their ship (see Attachment) and go where it
+// goes.
+//-------------------------------------------------------------------------
+
+// variants of scenery entities
+pub const SCENERY_DUST: u32 = 0;
+pub const SCENERY_SHIP: u32 = 1;
+// attached to a ship, see GameWorld::update_attached_scenery
+pub const SCENERY_FLAME: u32 = 2;
+pub const SCENERY_CARGO: u32 = 3;
+
+pub const NUM_DUST_MOTES: usize = 80;
+// motes are kept within this of the ship
+pub const DUST_RADIUS: f64 = 1200.0;
+// world units per tick
+pub const DUST_DRIFT_SPEED: f64 = 0.3;
+pub const NUM_BACKGROUND_SHIPS: usize = 3;
+pub const BACKGROUND_SHIP_SPEED: f64 = 3.0;
+pub const BACKGROUND_SHIP_ALPHA: f64 = 0.3;
+// past the edge of the field a background ship goes this far before it
+// comes back in on the other side
+const BACKGROUND_SHIP_MARGIN: f64 = 600.0;
+// where the player's ship tows the ore it carries, behind it
+pub const CARGO_POD_OFFSET: Vec2 = Vec2::new(0.0, -80.0);
+
+// Where a mote that drifted (or was left behind) too far from center comes
+// back in, on the opposite side. None while it's close enough.
+pub fn wrap_dust(pos: Vec2, center: Vec2) -> Option<Vec2> {
+    let offset = pos - center;
+    if offset.length() <= DUST_RADIUS {
+        return None;
+    }
+    Some(center - offset.normalize() * (DUST_RADIUS - 1.0))
+}
+
+// Where a background ship that flew out of the field comes back in, on the
+// opposite side. None while it's still in.
+pub fn wrap_background_ship(pos: Vec2, area: Range<Vec2>) -> Option<Vec2> {
+    let min = area.start - Vec2::new(BACKGROUND_SHIP_MARGIN, BACKGROUND_SHIP_MARGIN);
+    let max = area.end + Vec2::new(BACKGROUND_SHIP_MARGIN, BACKGROUND_SHIP_MARGIN);
+    let wrap = |value: f64, min: f64, max: f64| {
+        if value < min {
+            max
+        } else if value > max {
+            min
+        } else {
+            value
+        }
+    };
+    let wrapped = Vec2::new(wrap(pos.x, min.x, max.x), wrap(pos.y, min.y, max.y));
+    (wrapped != pos).then_some(wrapped)
+}
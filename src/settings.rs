@@ -0,0 +1,277 @@
+use std::path::Path;
+
+use masonry::Size;
+
+use crate::{pilot::SkillLevel, quality::Quality};
+
+//-------------------------------------------------------------------------
+// Settings for the game. These are options chosen before (or while)
+// running the game that don't affect the simulation itself, apart from
+// the difficulty options.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub viewport_mode: ViewportMode,
+    // graphics preset, None to have it picked for the GPU (see quality.rs)
+    pub quality: Option<Quality>,
+    // sample count for the custom wgpu passes (1 = no msaa, 2 or 4), None for the preset's
+    pub msaa_samples: Option<u32>,
+    // None for the preset's
+    pub vello_aa: Option<VelloAa>,
+    // exposure applied before tone mapping
+    pub exposure: f32,
+    // green night vision filter that highlights entities over the starfield
+    pub sensor_mode: bool,
+    // show id, type and velocity above entities
+    pub debug_labels: bool,
+    // seconds of play between autosaves, 0 disables autosave
+    pub autosave_secs: f64,
+    // photo mode screenshots are rendered at this multiple of the window resolution
+    pub screenshot_supersample: u32,
+    // seconds of flight path shown on the minimap, 0 hides the trail
+    pub trail_secs: f64,
+    // pause when the window loses focus, until a key is pressed back in it
+    pub pause_on_unfocus: bool,
+    // append per-run metrics to the metrics file (see metrics.rs)
+    pub metrics: bool,
+    // pushgateway ("host:port") to also send the metrics to
+    pub metrics_push: Option<String>,
+    // of sound effects, 0..1, 0 turns sound off
+    pub volume: f64,
+    // of the music, 0..1, 0 turns it off
+    pub music_volume: f64,
+    // controller rumble
+    pub rumble: bool,
+    // online leaderboard for endless runs (see online_leaderboard.rs)
+    pub leaderboard_url: Option<String>,
+    // shown next to your scores on the online leaderboard, instead of the profile name
+    pub player_name: Option<String>,
+    // assist: ring around the ship pointing at the nearest air pod
+    pub compass: bool,
+    // assist: faint lines showing where the nearby asteroids are drifting
+    pub threat_lines: bool,
+    pub difficulty: Difficulty,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            viewport_mode: ViewportMode::Window,
+            quality: None,
+            msaa_samples: None,
+            vello_aa: None,
+            exposure: 1.0,
+            sensor_mode: false,
+            debug_labels: false,
+            autosave_secs: 30.0,
+            screenshot_supersample: 2,
+            trail_secs: 20.0,
+            pause_on_unfocus: true,
+            metrics: false,
+            metrics_push: None,
+            volume: 0.7,
+            music_volume: 0.5,
+            rumble: true,
+            leaderboard_url: None,
+            player_name: None,
+            compass: false,
+            threat_lines: false,
+            difficulty: Difficulty::default(),
+        }
+    }
+}
+
+impl Settings {
+    // Load settings from a simple "key = value" file, '#' starts a comment.
+    // Keys that aren't in the file keep their default value.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let txt = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read config {}: {}", path.display(), err))?;
+
+        let mut settings = Settings::default();
+        for (line_num, line) in txt.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("{}:{}: expected 'key = value'", path.display(), line_num + 1));
+            };
+            settings
+                .set(key.trim(), value.trim())
+                .map_err(|err| format!("{}:{}: {}", path.display(), line_num + 1, err))?;
+        }
+        Ok(settings)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value for {}: {}", key, value);
+        match key {
+            "viewport" => self.viewport_mode = ViewportMode::parse(value).ok_or_else(invalid)?,
+            "quality" => self.quality = Some(Quality::parse(value).ok_or_else(invalid)?),
+            "msaa_samples" => self.msaa_samples = Some(value.parse().map_err(|_| invalid())?),
+            "vello_aa" => {
+                self.vello_aa = Some(match value {
+                    "area" => VelloAa::Area,
+                    "msaa8" => VelloAa::Msaa8,
+                    "msaa16" => VelloAa::Msaa16,
+                    _ => return Err(invalid()),
+                })
+            }
+            "exposure" => self.exposure = value.parse().map_err(|_| invalid())?,
+            "sensor_mode" => self.sensor_mode = value.parse().map_err(|_| invalid())?,
+            "debug_labels" => self.debug_labels = value.parse().map_err(|_| invalid())?,
+            "autosave_secs" => {
+                self.autosave_secs = value.parse().map_err(|_| invalid())?;
+                if self.autosave_secs < 0.0 {
+                    return Err(invalid());
+                }
+            }
+            "screenshot_supersample" => {
+                self.screenshot_supersample = value.parse().map_err(|_| invalid())?;
+                if !(1..=8).contains(&self.screenshot_supersample) {
+                    return Err(invalid());
+                }
+            }
+            "trail_secs" => {
+                self.trail_secs = value.parse().map_err(|_| invalid())?;
+                if self.trail_secs < 0.0 {
+                    return Err(invalid());
+                }
+            }
+            "pause_on_unfocus" => self.pause_on_unfocus = value.parse().map_err(|_| invalid())?,
+            "metrics" => self.metrics = value.parse().map_err(|_| invalid())?,
+            "metrics_push" => self.metrics_push = Some(value.to_string()),
+            "volume" => {
+                self.volume = value.parse().map_err(|_| invalid())?;
+                if !(0.0..=1.0).contains(&self.volume) {
+                    return Err(invalid());
+                }
+            }
+            "music_volume" => {
+                self.music_volume = value.parse().map_err(|_| invalid())?;
+                if !(0.0..=1.0).contains(&self.music_volume) {
+                    return Err(invalid());
+                }
+            }
+            "rumble" => self.rumble = value.parse().map_err(|_| invalid())?,
+            "leaderboard_url" => self.leaderboard_url = Some(value.to_string()),
+            "player_name" => {
+                if value.is_empty() {
+                    return Err(invalid());
+                }
+                self.player_name = Some(value.to_string());
+            }
+            "compass" => self.compass = value.parse().map_err(|_| invalid())?,
+            "threat_lines" => self.threat_lines = value.parse().map_err(|_| invalid())?,
+            "radar_sweep" => self.difficulty.radar_sweep = value.parse().map_err(|_| invalid())?,
+            "pilot_skill" => self.difficulty.pilot_skill = SkillLevel::parse(value).ok_or_else(invalid)?,
+            "lives" => {
+                self.difficulty.lives = value.parse().map_err(|_| invalid())?;
+                if self.difficulty.lives == 0 {
+                    return Err(invalid());
+                }
+            }
+            "slow_mode" => self.difficulty.slow_mode = value.parse().map_err(|_| invalid())?,
+            _ => return Err(format!("unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    // Medium until one's picked
+    pub fn quality(&self) -> Quality {
+        self.quality.unwrap_or(Quality::Medium)
+    }
+
+    pub fn msaa_samples(&self) -> u32 {
+        self.msaa_samples.unwrap_or(self.quality().msaa_samples())
+    }
+
+    pub fn vello_aa(&self) -> VelloAa {
+        self.vello_aa.unwrap_or(self.quality().vello_aa())
+    }
+}
+
+// Options that make the game harder (or easier)
+#[derive(Clone, Debug)]
+pub struct Difficulty {
+    // minimap only shows what a rotating radar beam has swept, instead of everything
+    pub radar_sweep: bool,
+    // how well ai ships are flown (reaction time, aim, hesitation)
+    pub pilot_skill: SkillLevel,
+    // ships to a run, with more than one the ship comes back after running out of air
+    pub lives: u32,
+    // accessibility: half speed, easier pickups and longer warnings (see tuning.rs)
+    pub slow_mode: bool,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self {
+            radar_sweep: false,
+            pilot_skill: SkillLevel::default(),
+            lives: 1,
+            slow_mode: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewportMode {
+    // Visible area of the world matches the window size (bigger window sees more)
+    Window,
+    // A fixed area of the world is visible, so a bigger window doesn't see any
+    // more of it. With letterbox set it's scaled to fit the window and the rest
+    // is blacked out, otherwise it's scaled to fill the window and what doesn't
+    // fit is cropped.
+    Fixed { size: Size, letterbox: bool },
+}
+
+impl ViewportMode {
+    // "window", "WIDTHxHEIGHT" or "WIDTHxHEIGHT letterbox"
+    fn parse(value: &str) -> Option<Self> {
+        if value == "window" {
+            return Some(ViewportMode::Window);
+        }
+
+        let (dims, letterbox) = match value.split_once(' ') {
+            Some((dims, "letterbox")) => (dims, true),
+            Some(_) => return None,
+            None => (value, false),
+        };
+        let (width, height) = dims.split_once('x')?;
+        let size = Size::new(width.parse().ok()?, height.parse().ok()?);
+        if size.width <= 0.0 || size.height <= 0.0 {
+            return None;
+        }
+        Some(ViewportMode::Fixed { size, letterbox })
+    }
+}
+
+// Anti-aliasing method used by vello when rendering the xilem scene
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VelloAa {
+    Area,
+    Msaa8,
+    Msaa16,
+}
+
+impl VelloAa {
+    pub fn aa_config(self) -> vello::AaConfig {
+        match self {
+            VelloAa::Area => vello::AaConfig::Area,
+            VelloAa::Msaa8 => vello::AaConfig::Msaa8,
+            VelloAa::Msaa16 => vello::AaConfig::Msaa16,
+        }
+    }
+
+    pub fn aa_support(self) -> vello::AaSupport {
+        vello::AaSupport {
+            area: self == VelloAa::Area,
+            msaa8: self == VelloAa::Msaa8,
+            msaa16: self == VelloAa::Msaa16,
+        }
+    }
+}
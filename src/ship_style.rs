@@ -0,0 +1,204 @@
+use std::path::Path;
+
+use xilem::Color;
+
+//-------------------------------------------------------------------------
+// How the player's ship looks: the paint on the hull, the stripes over it
+// and the color of the engine flame. Picked in the paint shop on the main
+// menu and purely for show, it doesn't change how the ship flies. The
+// shapes are drawn from it by game_shapes::ship_shape and flame_scene.
+//
+// Kept in the profile as a text file:
+//
+//   space_survival ship_style 1
+//   hull crimson
+//   stripes twin
+//   flame blue
+//-------------------------------------------------------------------------
+
+const SHIP_STYLE_HEADER: &str = "space_survival ship_style 1";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum HullPaint {
+    #[default]
+    White,
+    Crimson,
+    Amber,
+    Jade,
+    Cobalt,
+    Graphite,
+}
+
+impl HullPaint {
+    const ALL: [HullPaint; 6] = [
+        HullPaint::White,
+        HullPaint::Crimson,
+        HullPaint::Amber,
+        HullPaint::Jade,
+        HullPaint::Cobalt,
+        HullPaint::Graphite,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            HullPaint::White => "white",
+            HullPaint::Crimson => "crimson",
+            HullPaint::Amber => "amber",
+            HullPaint::Jade => "jade",
+            HullPaint::Cobalt => "cobalt",
+            HullPaint::Graphite => "graphite",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|paint| paint.key() == key)
+    }
+
+    // The one after this in the paint shop, back to the first after the last
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&paint| paint == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            HullPaint::White => Color::rgb8(0xff, 0xff, 0xff),
+            HullPaint::Crimson => Color::rgb8(0xc8, 0x20, 0x30),
+            HullPaint::Amber => Color::rgb8(0xf0, 0xa0, 0x20),
+            HullPaint::Jade => Color::rgb8(0x30, 0xb0, 0x78),
+            HullPaint::Cobalt => Color::rgb8(0x30, 0x58, 0xd0),
+            HullPaint::Graphite => Color::rgb8(0x50, 0x54, 0x5c),
+        }
+    }
+
+    // stands out against the paint
+    pub fn stripe_color(self) -> Color {
+        match self {
+            HullPaint::White | HullPaint::Amber => Color::rgb8(0x20, 0x28, 0x40),
+            HullPaint::Graphite => Color::rgb8(0xf0, 0xc0, 0x20),
+            _ => Color::rgb8(0xf0, 0xf0, 0xf0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Stripes {
+    #[default]
+    None,
+    // one down the middle, nose to tail
+    Center,
+    // two down the sides
+    Twin,
+    // a V pointing at the nose
+    Chevron,
+}
+
+impl Stripes {
+    const ALL: [Stripes; 4] = [Stripes::None, Stripes::Center, Stripes::Twin, Stripes::Chevron];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Stripes::None => "none",
+            Stripes::Center => "center",
+            Stripes::Twin => "twin",
+            Stripes::Chevron => "chevron",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|stripes| stripes.key() == key)
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&stripes| stripes == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FlameColor {
+    #[default]
+    Red,
+    Blue,
+    Green,
+    Violet,
+}
+
+impl FlameColor {
+    const ALL: [FlameColor; 4] = [FlameColor::Red, FlameColor::Blue, FlameColor::Green, FlameColor::Violet];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            FlameColor::Red => "red",
+            FlameColor::Blue => "blue",
+            FlameColor::Green => "green",
+            FlameColor::Violet => "violet",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|flame| flame.key() == key)
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&flame| flame == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    // fill of the tongues of flame and their edges
+    pub fn colors(self) -> (Color, Color) {
+        match self {
+            FlameColor::Red => (Color::rgb8(0xcf, 0x00, 0x00), Color::rgb8(0xff, 0xa5, 0x00)),
+            FlameColor::Blue => (Color::rgb8(0x10, 0x40, 0xe0), Color::rgb8(0x80, 0xe0, 0xff)),
+            FlameColor::Green => (Color::rgb8(0x10, 0x90, 0x30), Color::rgb8(0xc0, 0xff, 0x60)),
+            FlameColor::Violet => (Color::rgb8(0x80, 0x10, 0xc0), Color::rgb8(0xff, 0x90, 0xf0)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ShipStyle {
+    pub hull: HullPaint,
+    pub stripes: Stripes,
+    pub flame: FlameColor,
+}
+
+impl ShipStyle {
+    // A profile's style, the plain white ship without a file
+    pub fn load(path: &Path) -> Self {
+        let mut style = Self::default();
+        let Ok(txt) = std::fs::read_to_string(path) else {
+            return style;
+        };
+        let mut lines = txt.lines();
+        if lines.next() != Some(SHIP_STYLE_HEADER) {
+            log::warn!("Ignoring ship style {}: not a ship style file", path.display());
+            return style;
+        }
+        for line in lines {
+            let Some((key, value)) = line.trim().split_once(' ') else {
+                continue;
+            };
+            let known = match key {
+                "hull" => HullPaint::from_key(value).map(|hull| style.hull = hull),
+                "stripes" => Stripes::from_key(value).map(|stripes| style.stripes = stripes),
+                "flame" => FlameColor::from_key(value).map(|flame| style.flame = flame),
+                _ => None,
+            };
+            if known.is_none() {
+                log::warn!("Ignoring ship style line '{}'", line);
+            }
+        }
+        style
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "{}\nhull {}\nstripes {}\nflame {}\n",
+            SHIP_STYLE_HEADER,
+            self.hull.key(),
+            self.stripes.key(),
+            self.flame.key()
+        )
+    }
+}
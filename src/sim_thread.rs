@@ -0,0 +1,252 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use masonry::{parley::FontContext, Size};
+use vello::Scene;
+
+use crate::{
+    game::GameWorld,
+    input::InputEvent,
+    render_frame::{FrameParams, RenderFrame},
+    text::TextCache,
+};
+
+//-------------------------------------------------------------------------
+// The simulation runs on its own thread. It updates the world and, whenever
+// the window has taken the last frame, draws the next one into a snapshot:
+// the game scene (with its own font context for the HUD text) and the
+// camera and post processing values for the wgpu passes. The frame is
+// copied out of the world under the lock (see render_frame.rs) and drawn
+// after letting go of it. Snapshots are
+// handed over double buffered, the sim thread draws into its back buffer
+// and swaps it with the front one, and the render thread swaps the front
+// one with the frame it is showing. Neither holds the other up for longer
+// than a swap, so a slow frame no longer delays ticks.
+//
+// The event loop doesn't lock the world to pass on input. It sends it over a
+// channel, with focus and suspend changes in between, and the sim thread
+// hands it all to the world in order before each update.
+//-------------------------------------------------------------------------
+
+// longest the sim thread waits for the render thread before updating again
+const SIM_WAIT: Duration = Duration::from_millis(4);
+
+// Everything needed to put one frame on screen
+pub struct RenderSnapshot {
+    // the game and HUD, in logical window coordinates
+    pub scene: Scene,
+    pub params: FrameParams,
+    // set on the frame a photo mode screenshot was asked for
+    pub screenshot_supersample: Option<u32>,
+}
+
+impl RenderSnapshot {
+    fn new() -> Self {
+        Self {
+            scene: Scene::new(),
+            params: FrameParams::default(),
+            screenshot_supersample: None,
+        }
+    }
+}
+
+struct Front {
+    snapshot: RenderSnapshot,
+    // not taken by the render thread yet
+    fresh: bool,
+}
+
+// Shared between the sim thread and the window
+pub struct Frames {
+    front: Mutex<Front>,
+    taken: Condvar,
+    // the frame on screen, only touched by the render thread
+    shown: Mutex<RenderSnapshot>,
+    // physical width and height, and scale factor, of the window
+    window: Mutex<Option<(u32, u32, f64)>>,
+    exit_ready: AtomicBool,
+    idle: AtomicBool,
+}
+
+impl Frames {
+    pub fn new() -> Self {
+        Self {
+            front: Mutex::new(Front {
+                snapshot: RenderSnapshot::new(),
+                fresh: false,
+            }),
+            taken: Condvar::new(),
+            shown: Mutex::new(RenderSnapshot::new()),
+            window: Mutex::new(None),
+            exit_ready: AtomicBool::new(false),
+            idle: AtomicBool::new(false),
+        }
+    }
+
+    // Frames are drawn at this size from now on. Nothing is drawn until it's known.
+    pub fn set_window_size(&self, width: u32, height: u32, scale_factor: f64) {
+        *self.window.lock().unwrap() = Some((width, height, scale_factor));
+    }
+
+    pub fn has_new_frame(&self) -> bool {
+        self.front.lock().unwrap().fresh
+    }
+
+    // Show the newest frame, if there is one. Returns the values for the wgpu
+    // passes and the screenshot request of the frame shown.
+    pub fn latch(&self) -> (FrameParams, Option<u32>) {
+        let mut shown = self.shown.lock().unwrap();
+        {
+            let mut front = self.front.lock().unwrap();
+            if front.fresh {
+                std::mem::swap(&mut front.snapshot, &mut *shown);
+                front.fresh = false;
+                self.taken.notify_one();
+            }
+        }
+        (shown.params.clone(), shown.screenshot_supersample.take())
+    }
+
+    // The frame on screen, for painting it into the window's scene
+    pub fn shown(&self) -> MutexGuard<'_, RenderSnapshot> {
+        self.shown.lock().unwrap()
+    }
+
+    pub fn is_exit_ready(&self) -> bool {
+        self.exit_ready.load(Ordering::Relaxed)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle.load(Ordering::Relaxed)
+    }
+
+    fn wants_frame(&self) -> bool {
+        !self.front.lock().unwrap().fresh
+    }
+
+    fn publish(&self, back: &mut RenderSnapshot) {
+        let mut front = self.front.lock().unwrap();
+        std::mem::swap(&mut front.snapshot, back);
+        front.fresh = true;
+    }
+
+    fn wait_until_taken(&self, timeout: Duration) {
+        let front = self.front.lock().unwrap();
+        let _ = self.taken.wait_timeout_while(front, timeout, |front| front.fresh);
+    }
+}
+
+// What the window passes on to the world
+pub enum WindowMessage {
+    Input(InputEvent),
+    Focused(bool),
+    Suspended(bool),
+}
+
+pub struct SimThread {
+    stop: Arc<AtomicBool>,
+    messages: Sender<WindowMessage>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SimThread {
+    pub fn start(game_world: Arc<Mutex<GameWorld>>, frames: Arc<Frames>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let (messages, receiver) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("sim".to_string())
+            .spawn(move || run(&game_world, &frames, &receiver, &thread_stop))
+            .expect("Failed to start sim thread");
+        log::info!("sim_thread_started");
+
+        Self {
+            stop,
+            messages,
+            thread: Some(thread),
+        }
+    }
+
+    // The world gets it before its next update
+    pub fn send(&self, message: WindowMessage) {
+        // only fails once the thread has stopped, and the world with it
+        let _ = self.messages.send(message);
+    }
+}
+
+impl Drop for SimThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        log::info!("sim_thread_stopped");
+    }
+}
+
+fn run(
+    game_world: &Mutex<GameWorld>,
+    frames: &Frames,
+    messages: &Receiver<WindowMessage>,
+    stop: &AtomicBool,
+) {
+    let mut font_cx = FontContext::default();
+    let mut text_cache = TextCache::default();
+    let mut back = RenderSnapshot::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        let frame = {
+            let mut game_world = game_world.lock().unwrap();
+            for message in messages.try_iter() {
+                match message {
+                    WindowMessage::Input(input) => game_world.handle_input(input),
+                    WindowMessage::Focused(focused) => game_world.set_focused(focused),
+                    WindowMessage::Suspended(suspended) => game_world.set_suspended(suspended),
+                }
+            }
+            game_world.update();
+            frames.exit_ready.store(game_world.is_exit_ready(), Ordering::Relaxed);
+            frames.idle.store(game_world.is_idle(), Ordering::Relaxed);
+            extract_frame(&mut game_world, frames, &mut font_cx)
+        };
+
+        // drawing and the swap happen outside the world lock so input isn't held up by them
+        let drawn = frame.is_some();
+        if let Some(frame) = frame {
+            back.scene.reset();
+            frame.render(&mut back.scene, &mut font_cx, &mut text_cache);
+            back.params = frame.params;
+            back.screenshot_supersample = frame.screenshot_supersample;
+            frames.publish(&mut back);
+        }
+        if frames.has_new_frame() {
+            frames.wait_until_taken(SIM_WAIT);
+        } else if !drawn {
+            // no window to draw for yet
+            std::thread::sleep(SIM_WAIT);
+        }
+    }
+}
+
+// Copy the next frame out of the world, if the render thread is ready for one
+fn extract_frame(
+    game_world: &mut GameWorld,
+    frames: &Frames,
+    font_cx: &mut FontContext,
+) -> Option<RenderFrame> {
+    let (width, height, scale_factor) = (*frames.window.lock().unwrap())?;
+    if !game_world.ready_for_redraw() || !frames.wants_frame() {
+        return None;
+    }
+
+    game_world.interpolate_transforms();
+    let size = Size::new(width as f64 / scale_factor, height as f64 / scale_factor);
+    Some(game_world.extract_render_frame(font_cx, size, scale_factor))
+}
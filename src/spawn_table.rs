@@ -0,0 +1,302 @@
+use std::path::Path;
+
+use crate::{
+    air_pod::PodTier,
+    faction::Faction,
+    game::{AsteroidMaterial, LootKind},
+    replay::fnv1a,
+    rng::RngStream,
+};
+
+//-------------------------------------------------------------------------
+// Weighted tables of what turns up in a world: what asteroids are made of,
+// what's in the derelicts, which faction ships fly about, how big the air
+// pods are. Each entry has a
+// weight, cut down for rarer entries, and can be drawn at least or at most
+// so many times when a batch is drawn at once. Draws come from the world's
+// random streams, so a seed always gets the same.
+//
+// The tables are data, in data/spawn_tables, built into the game. Another
+// file can be loaded with --spawn-tables to try out changes, but worlds
+// come out differently with it. Replays keep a hash of the tables they were
+// recorded with, so one won't play back or race as a ghost with others, and
+// runs with tables other than the built in ones aren't ranked.
+//-------------------------------------------------------------------------
+
+const SPAWN_TABLES_HEADER: &str = "space_survival spawn tables 1";
+const DEFAULT_SPAWN_TABLES: &str = include_str!("../data/spawn_tables");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+}
+
+impl Rarity {
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "common" => Some(Rarity::Common),
+            "uncommon" => Some(Rarity::Uncommon),
+            "rare" => Some(Rarity::Rare),
+            _ => None,
+        }
+    }
+
+    fn weight_factor(self) -> f64 {
+        match self {
+            Rarity::Common => 1.0,
+            Rarity::Uncommon => 0.5,
+            Rarity::Rare => 0.2,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SpawnEntry<T> {
+    value: T,
+    weight: f64,
+    rarity: Rarity,
+    // times drawn in a batch
+    min: u32,
+    max: Option<u32>,
+}
+
+impl<T> SpawnEntry<T> {
+    fn weight(&self) -> f64 {
+        self.weight * self.rarity.weight_factor()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SpawnTable<T> {
+    entries: Vec<SpawnEntry<T>>,
+}
+
+impl<T: Copy> SpawnTable<T> {
+    // One draw, min and max don't count
+    pub fn sample(&self, rng: &mut RngStream) -> T {
+        let all: Vec<usize> = (0..self.entries.len()).collect();
+        self.entries[self.pick(&all, rng)].value
+    }
+
+    // count draws, each entry at least its min (as far as count goes) and at
+    // most its max times, in random order. Fewer if every entry is at its max.
+    pub fn sample_many(&self, count: usize, rng: &mut RngStream) -> Vec<T> {
+        let mut drawn = vec![0; self.entries.len()];
+        let mut picks = Vec::new();
+        for (idx, entry) in self.entries.iter().enumerate() {
+            for _ in 0..entry.min {
+                if picks.len() < count {
+                    picks.push(idx);
+                    drawn[idx] += 1;
+                }
+            }
+        }
+        while picks.len() < count {
+            let open: Vec<usize> = (0..self.entries.len())
+                .filter(|&idx| !self.entries[idx].max.is_some_and(|max| drawn[idx] >= max))
+                .collect();
+            if open.is_empty() {
+                break;
+            }
+            let idx = self.pick(&open, rng);
+            picks.push(idx);
+            drawn[idx] += 1;
+        }
+
+        // so the ones drawn for their min aren't all first
+        for idx in (1..picks.len()).rev() {
+            let other = rng.next(0..idx as u32 + 1) as usize;
+            picks.swap(idx, other);
+        }
+        picks.into_iter().map(|idx| self.entries[idx].value).collect()
+    }
+
+    // One of the entries at these indices, by weight
+    fn pick(&self, indices: &[usize], rng: &mut RngStream) -> usize {
+        let total: f64 = indices.iter().map(|&idx| self.entries[idx].weight()).sum();
+        let mut left = rng.next(0.0..total);
+        for &idx in indices {
+            left -= self.entries[idx].weight();
+            if left < 0.0 {
+                return idx;
+            }
+        }
+        // the draw can land right on the total
+        *indices.last().unwrap()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SpawnTables {
+    pub asteroid_material: SpawnTable<AsteroidMaterial>,
+    pub derelict_loot: SpawnTable<LootKind>,
+    pub encounter: SpawnTable<Faction>,
+    pub air_pod: SpawnTable<PodTier>,
+    // of the lines the tables were parsed from
+    hash: u64,
+}
+
+impl Default for SpawnTables {
+    fn default() -> Self {
+        Self::parse(DEFAULT_SPAWN_TABLES).expect("Built in spawn tables don't parse")
+    }
+}
+
+impl SpawnTables {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let txt = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read spawn tables {}: {}", path.display(), err))?;
+        Self::parse(&txt).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // The tables built into the game, which runs are ranked with
+    pub fn is_builtin(&self) -> bool {
+        self.hash == builtin_hash()
+    }
+
+    fn parse(txt: &str) -> Result<Self, String> {
+        let mut lines = table_lines(txt);
+        if lines.next() != Some(SPAWN_TABLES_HEADER) {
+            return Err("Not a spawn tables file (or unsupported version)".to_string());
+        }
+
+        // entries of each table by name, in the order they're given
+        let mut tables: Vec<(&str, Vec<(&str, &str)>)> = Vec::new();
+        for line in lines {
+            if let Some(name) = line.strip_prefix("table ") {
+                tables.push((name.trim(), Vec::new()));
+                continue;
+            }
+            let Some((_, entries)) = tables.last_mut() else {
+                return Err(format!("Entry before any table: {}", line));
+            };
+            let (name, fields) = line.split_once(' ').unwrap_or((line, ""));
+            entries.push((name, fields));
+        }
+
+        Ok(Self {
+            asteroid_material: parse_table(&tables, "asteroid_material", AsteroidMaterial::from_key)?,
+            derelict_loot: parse_table(&tables, "derelict_loot", LootKind::from_key)?,
+            encounter: parse_table(&tables, "encounter", Faction::from_key)?,
+            air_pod: parse_table(&tables, "air_pod", PodTier::from_key)?,
+            hash: lines_hash(txt),
+        })
+    }
+}
+
+// Hash of the built in tables, for recordings that don't say otherwise
+pub fn builtin_hash() -> u64 {
+    lines_hash(DEFAULT_SPAWN_TABLES)
+}
+
+// The lines that mean something, without comments and blank lines
+fn table_lines(txt: &str) -> impl Iterator<Item = &str> {
+    txt.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+// so the same tables with other comments or spacing hash the same
+fn lines_hash(txt: &str) -> u64 {
+    fnv1a(table_lines(txt).collect::<Vec<_>>().join("\n").as_bytes())
+}
+
+// The table called name out of the entries of each table
+fn parse_table<T>(
+    tables: &[(&str, Vec<(&str, &str)>)],
+    table: &str,
+    from_key: impl Fn(&str) -> Option<T>,
+) -> Result<SpawnTable<T>, String> {
+    let (_, lines) = tables
+        .iter()
+        .find(|(name, _)| *name == table)
+        .ok_or_else(|| format!("Missing spawn table: {}", table))?;
+    let mut entries = Vec::new();
+    for &(name, fields) in lines {
+        let invalid = || format!("Invalid spawn table entry: {} {}", name, fields);
+        let mut entry = SpawnEntry {
+            value: from_key(name).ok_or_else(|| format!("Unknown entry in {}: {}", table, name))?,
+            weight: 0.0,
+            rarity: Rarity::Common,
+            min: 0,
+            max: None,
+        };
+        for field in fields.split_whitespace() {
+            let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+            match key {
+                "weight" => entry.weight = value.parse().map_err(|_| invalid())?,
+                "rarity" => entry.rarity = Rarity::from_key(value).ok_or_else(invalid)?,
+                "min" => entry.min = value.parse().map_err(|_| invalid())?,
+                "max" => entry.max = Some(value.parse().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+        if !entry.weight.is_finite() || entry.weight <= 0.0 || entry.max.is_some_and(|max| max < entry.min) {
+            return Err(invalid());
+        }
+        entries.push(entry);
+    }
+    if entries.is_empty() {
+        return Err(format!("Empty spawn table: {}", table));
+    }
+    Ok(SpawnTable { entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::UpgradeModule;
+
+    // Tables that parse, with the derelict loot given
+    fn tables(derelict_loot: &str) -> String {
+        format!(
+            "{}\ntable asteroid_material\nrock weight=1\ntable derelict_loot\n{}\n\
+             table encounter\npirates weight=1\ntable air_pod\nsmall weight=1\n",
+            SPAWN_TABLES_HEADER, derelict_loot
+        )
+    }
+
+    #[test]
+    fn bad_entries_dont_parse() {
+        assert!(SpawnTables::parse(&tables("air weight=2 min=1\nore weight=1 max=3")).is_ok());
+        let bad_weights = ["air weight=0", "air weight=-1", "air weight=x", "air weight=inf", "air"];
+        for bad in bad_weights.into_iter().chain(["air weight=1 min=2 max=1", "air weight=1 size=2"]) {
+            let err = SpawnTables::parse(&tables(bad)).unwrap_err();
+            assert!(err.starts_with("Invalid spawn table entry: air"), "{}: {}", bad, err);
+        }
+        let err = SpawnTables::parse(&tables("plutonium weight=1")).unwrap_err();
+        assert_eq!(err, "Unknown entry in derelict_loot: plutonium");
+    }
+
+    // Changing the built in tables changes every world, and needs a new
+    // replay version.
+    #[test]
+    fn builtin_tables_hash_the_same() {
+        assert_eq!(builtin_hash(), 0x249f77e6d9cc758f);
+        assert!(SpawnTables::default().is_builtin());
+        let commented = format!("# other comments\n{}\n\n", DEFAULT_SPAWN_TABLES.replace('\n', "\n  "));
+        assert_eq!(SpawnTables::parse(&commented).unwrap().hash(), builtin_hash());
+    }
+
+    #[test]
+    fn same_seed_draws_the_same() {
+        let loot = &SpawnTables::default().derelict_loot;
+        let draw = |seed| loot.sample_many(12, &mut RngStream::new(seed, "loot"));
+        for seed in 0..20 {
+            let drawn = draw(seed);
+            assert_eq!(drawn, draw(seed));
+            assert_eq!(drawn.len(), 12);
+            let count = |kind| drawn.iter().filter(|&&loot| loot == kind).count();
+            assert!(count(LootKind::Air) >= 1);
+            assert!(count(LootKind::Upgrade(UpgradeModule::Thrusters)) <= 2);
+            assert!(count(LootKind::Upgrade(UpgradeModule::Magnet)) <= 1);
+        }
+        assert_ne!(draw(1), draw(2));
+    }
+}
@@ -0,0 +1,183 @@
+use std::{path::Path, sync::Arc};
+
+use masonry::{Affine, Vec2};
+use vello::{
+    kurbo::Rect,
+    peniko::{Blob, Format, Image},
+    Scene,
+};
+
+use crate::{air_pod::PodTier, game_shapes::AnimationClip};
+
+//-------------------------------------------------------------------------
+// Frame by frame animations from image strips, as an alternative to the
+// procedural clips in game_shapes.rs. The sprites file in SPRITES_DIR lists
+// the strips, one per line:
+//
+//   <clip> <file> <frame width> <radius>
+//
+// e.g. "explosion explosion.pam 64 90". The frames are side by side in the
+// file, left to right, and are spread evenly over the clip's length, so only
+// clips with a length (explosion, air_pod) can have one. Radius is half the
+// width of a frame in world units. Strips are binary PAM files (netpbm P7,
+// RGB or RGB_ALPHA, 8 bits), which any image editor can export to and are
+// trivial to read without pulling in an image crate.
+//
+// Clips without a strip are drawn as before, so the directory is optional.
+// The air_pod strip is only used for medium pods, the other tiers are
+// drawn as before at their own sizes.
+//-------------------------------------------------------------------------
+
+pub const SPRITES_DIR: &str = "space_survival_sprites";
+const SPRITES_FILE: &str = "sprites";
+const SPRITES_HEADER: &str = "space_survival sprites 1";
+
+pub struct SpriteSheet {
+    image: Image,
+    frame_width: u32,
+    frames: u32,
+    radius: f64,
+}
+
+impl SpriteSheet {
+    pub fn load(path: &Path, frame_width: u32, radius: f64) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let (width, height, rgba) =
+            parse_pam(&bytes).map_err(|err| format!("{}: {}", path.display(), err))?;
+        if frame_width == 0 || width % frame_width != 0 {
+            return Err(format!(
+                "{}: width {} is not a whole number of {} pixel frames",
+                path.display(),
+                width,
+                frame_width
+            ));
+        }
+        Ok(Self {
+            image: Image::new(Blob::new(Arc::new(rgba)), Format::Rgba8, width, height),
+            frame_width,
+            frames: width / frame_width,
+            radius,
+        })
+    }
+
+    // Draw the frame for t seconds into a clip of the given length, centered on the transform
+    pub fn render(&self, scene: &mut Scene, t: f64, length: f64, transform: Affine) {
+        let frame = ((t / length * self.frames as f64) as u32).min(self.frames - 1);
+        let (width, height) = (self.frame_width as f64, self.image.height as f64);
+        let scale = 2.0 * self.radius / width;
+        let top_left = Vec2::new(-self.radius, -0.5 * height * scale);
+        let to_world = transform * Affine::scale(scale).then_translate(top_left);
+
+        // only the frame's part of the strip shows
+        let clip = Rect::new(0.0, 0.0, width, height);
+        scene.push_layer(vello::peniko::BlendMode::default(), 1.0, to_world, &clip);
+        scene.draw_image(&self.image, to_world * Affine::translate((-(frame as f64) * width, 0.0)));
+        scene.pop_layer();
+    }
+}
+
+// The strips there are, shared with the render side
+#[derive(Default)]
+pub struct SpriteSheets {
+    explosion: Option<SpriteSheet>,
+    air_pod: Option<SpriteSheet>,
+}
+
+impl SpriteSheets {
+    // The strips listed in the sprites file. Without one there are none, and
+    // a strip that fails to load is left out.
+    pub fn load(dir: &Path) -> Self {
+        let mut sheets = Self::default();
+        let path = dir.join(SPRITES_FILE);
+        let Ok(txt) = std::fs::read_to_string(&path) else {
+            return sheets;
+        };
+        let mut lines = txt.lines();
+        if lines.next() != Some(SPRITES_HEADER) {
+            log::warn!("Ignoring sprites {}: not a sprites file", path.display());
+            return sheets;
+        }
+        for line in lines.map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            if let Err(err) = sheets.load_line(dir, line) {
+                log::warn!("Skipping sprite '{}': {}", line, err);
+            }
+        }
+        sheets
+    }
+
+    fn load_line(&mut self, dir: &Path, line: &str) -> Result<(), String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let [clip, file, frame_width, radius] = parts[..] else {
+            return Err("expected <clip> <file> <frame width> <radius>".to_string());
+        };
+        let frame_width = frame_width.parse().map_err(|_| format!("bad frame width '{}'", frame_width))?;
+        let radius = radius.parse().map_err(|_| format!("bad radius '{}'", radius))?;
+        let slot = match clip {
+            "explosion" => &mut self.explosion,
+            "air_pod" => &mut self.air_pod,
+            _ => return Err(format!("no clip '{}' takes a strip", clip)),
+        };
+        let sheet = SpriteSheet::load(&dir.join(file), frame_width, radius)?;
+        log::info!("sprite_loaded clip={} frames={}", clip, sheet.frames);
+        *slot = Some(sheet);
+        Ok(())
+    }
+
+    pub fn get(&self, clip: AnimationClip) -> Option<&SpriteSheet> {
+        match clip {
+            AnimationClip::Explosion => self.explosion.as_ref(),
+            // the strip is drawn at one size, so the other tiers keep their own look
+            AnimationClip::AirPod(PodTier::Medium) => self.air_pod.as_ref(),
+            AnimationClip::AirPod(_) | AnimationClip::Flame(_) => None,
+        }
+    }
+}
+
+// Width, height and RGBA pixels of a binary PAM image
+fn parse_pam(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    const END: &[u8] = b"ENDHDR\n";
+    let header_len = bytes
+        .windows(END.len())
+        .position(|window| window == END)
+        .ok_or("not a PAM file, no ENDHDR")?;
+    let header = std::str::from_utf8(&bytes[..header_len]).map_err(|_| "header is not text")?;
+    let mut lines = header.lines();
+    if lines.next() != Some("P7") {
+        return Err("not a PAM file, expected P7".to_string());
+    }
+
+    let (mut width, mut height, mut depth, mut maxval) = (0, 0, 0, 0);
+    for line in lines.filter(|line| !line.starts_with('#')) {
+        let mut parts = line.split_whitespace();
+        let (Some(key), Some(value)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let number = || value.parse::<u32>().map_err(|_| format!("bad {} '{}'", key, value));
+        match key {
+            "WIDTH" => width = number()?,
+            "HEIGHT" => height = number()?,
+            "DEPTH" => depth = number()?,
+            "MAXVAL" => maxval = number()?,
+            // the depth says all there is to know
+            _ => {}
+        }
+    }
+    if maxval != 255 || !(depth == 3 || depth == 4) {
+        return Err(format!(
+            "only 8 bit RGB or RGBA is supported, not depth {} maxval {}",
+            depth, maxval
+        ));
+    }
+
+    let pixels = &bytes[header_len + END.len()..];
+    let len = (width * height * depth) as usize;
+    if width == 0 || height == 0 || pixels.len() < len {
+        return Err(format!("expected {}x{} pixels", width, height));
+    }
+    let rgba = match depth {
+        4 => pixels[..len].to_vec(),
+        _ => pixels[..len].chunks(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xff]).collect(),
+    };
+    Ok((width, height, rgba))
+}
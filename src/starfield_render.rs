@@ -4,7 +4,7 @@ use bytemuck::{Pod, Zeroable};
 use masonry::event_loop_runner::MasonryState;
 use vello::wgpu::{self, BindGroup, BlendState, Buffer, Device, Queue, RenderPass, RenderPipeline, TextureFormat};
 
-use crate::{game::HashRand, render_mgr::{GlobalRenderData, Renderer}, GameState};
+use crate::{game::HashRand, render_frame::CameraSnapshot, render_mgr::{GlobalRenderData, Renderer, GLOBAL_RENDER_DATA_SHADER}};
 
 
 #[repr(C)]
@@ -30,10 +30,15 @@ pub struct StarfieldRenderer {
     bind_group: BindGroup,
 
     render_pipeline: RenderPipeline,
+
+    // what it was set up with, to set it up again on a new device
+    surface_format: TextureFormat,
+    sample_count: u32,
+    num_stars: u32,
 }
 
 impl Renderer for StarfieldRenderer {
-    fn prepare(&mut self, _: &mut MasonryState, _: &GameState,_width: u32, _height: u32) {
+    fn prepare(&mut self, _: &mut MasonryState, _: &CameraSnapshot,_width: u32, _height: u32) {
     }
 
     fn render<'rpass>(&'rpass self, render_pass: &mut RenderPass<'rpass>, _width: u32, _height: u32) {
@@ -46,15 +51,20 @@ impl Renderer for StarfieldRenderer {
         render_pass.draw(0..3, 0..self.instance_count);
     }
 
-    fn finish_render(&mut self, _masonry_state: &mut MasonryState, _: &GameState) {
+    fn finish_render(&mut self, _masonry_state: &mut MasonryState) {
+    }
+
+    fn recreate(&mut self, device: &Device, queue: &Queue, global_buffer: &Buffer) {
+        *self = Self::setup(device, queue, global_buffer, self.surface_format, self.sample_count, self.num_stars);
     }
 }
 
 impl StarfieldRenderer {
-    pub fn setup(device: &Device, queue: &Queue, global_buffer: &Buffer, surface_format: TextureFormat) -> Self {
+    pub fn setup(device: &Device, queue: &Queue, global_buffer: &Buffer, surface_format: TextureFormat, sample_count: u32, num_stars: u32) -> Self {
+        let source = format!("{}{}", GLOBAL_RENDER_DATA_SHADER, STARFIELD_VERTEX_SHADER);
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("starfield shaders"),
-            source: wgpu::ShaderSource::Wgsl(STARFIELD_VERTEX_SHADER.into()),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
         });
 
         // Create vertices -- same triangle for each star instance
@@ -66,7 +76,7 @@ impl StarfieldRenderer {
 
         // create the star instance data
         let seed = 2828;
-        let num_stars = 4000;
+        let num_stars = num_stars as usize;
         let size_range: Range<f64> = 10.0..20.0;
         let dim_range: Range<f64> = -2000.0..2000.0;
         let max_depth_ratio = 3.0;
@@ -207,7 +217,7 @@ impl StarfieldRenderer {
                     },
                     depth_stencil: None,
                     multisample: wgpu::MultisampleState {
-                        count: 1,
+                        count: sample_count,
                         mask: !0,
                         alpha_to_coverage_enabled: false,
                     },
@@ -220,6 +230,9 @@ impl StarfieldRenderer {
             instance_count: instances.len() as u32,
             bind_group,
             render_pipeline,
+            surface_format,
+            sample_count,
+            num_stars: num_stars as u32,
         }
     }
 
@@ -278,11 +291,6 @@ fn star_creator(_dist: f32, _size: f32, select: f32) -> [f32;3] {
 }
 
 const STARFIELD_VERTEX_SHADER: &str = r#"
-struct GlobalRenderData {
-    cam_pos: vec2<f32>,
-    screen_size: vec2<f32>,
-};
-
 @group(0) @binding(0) var<uniform> u_global: GlobalRenderData;
 
 struct VertexInput {
@@ -312,16 +320,28 @@ struct FragmentOutput {
 
 @vertex
 fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
-    var local_pos = vec2<f32>(1.0, -1.0) * (instance.position - u_global.cam_pos)/instance.depth;
+    // the far layers drift and turn slowly on their own, so the background is
+    // alive even with the ship sitting still. Depth goes from 1 to 3, the
+    // nearest layer stays put.
+    let far = clamp((instance.depth - 1.0) / 2.0, 0.0, 1.0);
+    let drift = far * vec2<f32>(6.0, 2.5) * u_global.time;
+    let angle = far * 0.004 * u_global.time;
+
+    var local_pos = vec2<f32>(1.0, -1.0) * (instance.position + drift - u_global.cam_pos)/instance.depth;
     let window = 2000.0;
     let twice_window = 2.0 * window;
 
     // this is position of star center
     local_pos = twice_window * fract((local_pos + window) / twice_window) - window;
+    // turned about the middle of the screen, the wrapped window still covers it
+    local_pos = mat2x2<f32>(cos(angle), sin(angle), -sin(angle), cos(angle)) * local_pos;
 
     // apply offsets (scaled by radius)
     local_pos += instance.radius/instance.depth * vertex.offset;
 
+    // scale into screen space the same way the viewport scales the world
+    local_pos *= u_global.view_scale;
+
     var position = vec4<f32>(2.0*local_pos.x/u_global.screen_size.x, 2.0*local_pos.y/u_global.screen_size.y, 0.1, 1.0);
     return VertexOutput(instance.color, vertex.offset, position);
 }
@@ -0,0 +1,188 @@
+//-------------------------------------------------------------------------
+// Timed effects on an entity, put on it by hazards and by its own systems:
+// an EMP mine stuns a ship (no controls at all while momentum carries it)
+// and leaves its engines slowed for a while after, a heavy hit cracks the
+// hull so it leaks air, and engines that run too hot shut down while they
+// cool off. A golden air pod shields the ship for a while, keeping the
+// others off it. Each effect has the ticks it has left and how many times it's
+// stacked up. What happens when one that's already there is put on again
+// depends on its kind (see Stacking). Controls, air and the HUD ask which
+// are active, and the HUD shows an icon for each.
+//-------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusKind {
+    Stunned,
+    Slowed,
+    Leaking,
+    Overheated,
+    Shielded,
+}
+
+// In the order the HUD shows them
+pub const STATUS_KINDS: [StatusKind; 5] = [
+    StatusKind::Shielded,
+    StatusKind::Stunned,
+    StatusKind::Slowed,
+    StatusKind::Leaking,
+    StatusKind::Overheated,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stacking {
+    // keeps whichever lasts longer
+    Refresh,
+    // each one adds a stack, up to this many, and the time starts over
+    Stack(u32),
+}
+
+impl StatusKind {
+    pub fn stacking(self) -> Stacking {
+        match self {
+            // being hit again while stunned doesn't lock the ship up for longer
+            StatusKind::Stunned | StatusKind::Slowed | StatusKind::Overheated | StatusKind::Shielded => {
+                Stacking::Refresh
+            }
+            // every crack leaks some more
+            StatusKind::Leaking => Stacking::Stack(3),
+        }
+    }
+
+    // name used in saves
+    pub fn key(self) -> &'static str {
+        match self {
+            StatusKind::Stunned => "stunned",
+            StatusKind::Slowed => "slowed",
+            StatusKind::Leaking => "leaking",
+            StatusKind::Overheated => "overheated",
+            StatusKind::Shielded => "shielded",
+        }
+    }
+
+    // what a shield keeps off, overheating is the ship's own doing
+    fn is_harmful(self) -> bool {
+        matches!(self, StatusKind::Stunned | StatusKind::Slowed | StatusKind::Leaking)
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        STATUS_KINDS.iter().copied().find(|kind| kind.key() == key)
+    }
+
+    // short enough for a HUD icon
+    pub fn label(self) -> &'static str {
+        match self {
+            StatusKind::Stunned => "EMP",
+            StatusKind::Slowed => "SLOW",
+            StatusKind::Leaking => "LEAK",
+            StatusKind::Overheated => "HOT",
+            StatusKind::Shielded => "SHLD",
+        }
+    }
+
+    pub fn color(self) -> xilem::Color {
+        match self {
+            StatusKind::Stunned => xilem::Color::rgb8(0x40, 0xa0, 0xff),
+            StatusKind::Slowed => xilem::Color::rgb8(0x90, 0x90, 0xd0),
+            StatusKind::Leaking => xilem::Color::rgb8(0x0, 0xb4, 0xd8),
+            StatusKind::Overheated => xilem::Color::rgb8(0xff, 0x50, 0x10),
+            StatusKind::Shielded => xilem::Color::rgb8(0xff, 0xc0, 0x20),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub ticks: u32,
+    // what ticks started at, for how much is left
+    pub duration: u32,
+    pub stacks: u32,
+}
+
+impl StatusEffect {
+    // 1 when it was just put on, going down to 0
+    pub fn fraction_left(&self) -> f64 {
+        self.ticks as f64 / self.duration.max(1) as f64
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    // Effects as saved, None if any of them couldn't have come from apply
+    pub fn from_effects(effects: Vec<StatusEffect>) -> Option<Self> {
+        for (idx, effect) in effects.iter().enumerate() {
+            let max_stacks = match effect.kind.stacking() {
+                Stacking::Refresh => 1,
+                Stacking::Stack(max) => max,
+            };
+            let repeated = effects[..idx].iter().any(|other| other.kind == effect.kind);
+            if repeated || effect.ticks == 0 || effect.stacks == 0 || effect.stacks > max_stacks {
+                return None;
+            }
+        }
+        Some(Self { effects })
+    }
+
+    pub fn apply(&mut self, kind: StatusKind, ticks: u32) {
+        if kind.is_harmful() && self.has(StatusKind::Shielded) {
+            return;
+        }
+        let Some(effect) = self.effects.iter_mut().find(|effect| effect.kind == kind) else {
+            self.effects.push(StatusEffect {
+                kind,
+                ticks,
+                duration: ticks,
+                stacks: 1,
+            });
+            return;
+        };
+        match kind.stacking() {
+            Stacking::Refresh => {
+                if ticks > effect.ticks {
+                    effect.ticks = ticks;
+                    effect.duration = ticks;
+                }
+            }
+            Stacking::Stack(max) => {
+                effect.stacks = (effect.stacks + 1).min(max);
+                effect.ticks = ticks;
+                effect.duration = ticks;
+            }
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.remaining(kind) > 0
+    }
+
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|effect| effect.kind == kind)
+    }
+
+    // ticks the effect has left, 0 when it isn't on
+    pub fn remaining(&self, kind: StatusKind) -> u32 {
+        self.get(kind).map_or(0, |effect| effect.ticks)
+    }
+
+    // how many times the effect has stacked up, 0 when it isn't on
+    pub fn stacks(&self, kind: StatusKind) -> u32 {
+        self.get(kind).map_or(0, |effect| effect.stacks)
+    }
+
+    // Active effects in the order the HUD shows them
+    pub fn active(&self) -> impl Iterator<Item = &StatusEffect> + '_ {
+        STATUS_KINDS.iter().filter_map(|kind| self.get(*kind))
+    }
+
+    // A tick has passed, the effects that ran out come off
+    pub fn update(&mut self) {
+        for effect in &mut self.effects {
+            effect.ticks = effect.ticks.saturating_sub(1);
+        }
+        self.effects.retain(|effect| effect.ticks > 0);
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+use masonry::{parley::FontContext, Size};
+use vello::Scene;
+
+use crate::{
+    game::{GameWorld, TICKS_PER_SECOND},
+    text::TextCache,
+};
+
+//-------------------------------------------------------------------------
+// --stress N, for comparing performance work across machines. The world is
+// generated with N asteroids and no ship (GameWorld::disable_player), so
+// nothing ends the run and every run of it is the same for a given seed.
+// It then runs STRESS_TICKS ticks without a window, drawing a frame after
+// each one the way the sim thread does, copying it out of the world and
+// then into a vello scene (the GPU side isn't timed). The ticks and frames
+// are timed separately, and their percentiles printed at the end.
+//-------------------------------------------------------------------------
+
+// a minute of play
+const STRESS_TICKS: u32 = 60 * TICKS_PER_SECOND as u32;
+// of the window the frames are drawn for, in logical pixels
+const STRESS_WINDOW: Size = Size::new(1200.0, 1200.0);
+const PERCENTILES: [f64; 4] = [50.0, 90.0, 99.0, 100.0];
+
+pub fn run_stress(game_world: &mut GameWorld, asteroids: u32) {
+    let mut font_cx = FontContext::default();
+    let mut text_cache = TextCache::default();
+    let mut scene = Scene::new();
+    let mut updates = Vec::with_capacity(STRESS_TICKS as usize);
+    let mut renders = Vec::with_capacity(STRESS_TICKS as usize);
+
+    let started = Instant::now();
+    for _ in 0..STRESS_TICKS {
+        let tick_start = Instant::now();
+        game_world.tick();
+        updates.push(tick_start.elapsed());
+
+        let render_start = Instant::now();
+        let frame = game_world.extract_render_frame(&mut font_cx, STRESS_WINDOW, 1.0);
+        scene.reset();
+        frame.render(&mut scene, &mut font_cx, &mut text_cache);
+        renders.push(render_start.elapsed());
+    }
+
+    println!(
+        "Stress with {} asteroids, seed {}: {} ticks and frames in {:.2} seconds",
+        asteroids,
+        game_world.get_seed(),
+        STRESS_TICKS,
+        started.elapsed().as_secs_f64()
+    );
+    println!("{}", timing_line("update", &mut updates));
+    println!("{}", timing_line("render", &mut renders));
+}
+
+// "NAME  p50 X ms  p90 X ms  p99 X ms  max X ms"
+fn timing_line(name: &str, times: &mut [Duration]) -> String {
+    times.sort();
+    let mut line = format!("{:<8}", name);
+    for percentile in PERCENTILES {
+        let label = if percentile >= 100.0 { "max".to_string() } else { format!("p{}", percentile) };
+        let time = percentile_of(times, percentile).as_secs_f64() * 1000.0;
+        line += &format!("  {} {:.3} ms", label, time);
+    }
+    line
+}
+
+// Nearest rank of sorted times
+fn percentile_of(sorted: &[Duration], percentile: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
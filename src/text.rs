@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use masonry::{
+    parley::{
+        self,
+        style::{FontFamily, FontStack, StyleProperty},
+        FontContext,
+    },
+    Affine, Point, Size, Vec2,
+};
+use vello::Scene;
+
+//-------------------------------------------------------------------------
+// Helper for drawing a block of text into a scene. The anchor is placed at
+// (align_x, align_y) of the text bounds, e.g. (0.5, 0.5) centers the text
+// on the anchor. Returns the size of the block.
+//-------------------------------------------------------------------------
+
+pub struct TextStyle {
+    pub font_size: f32,
+    pub color: xilem::Color,
+    pub alignment: xilem::TextAlignment,
+}
+
+pub fn draw_text(
+    scene: &mut Scene,
+    ctx: &mut FontContext,
+    txt: &str,
+    style: &TextStyle,
+    anchor: Point,
+    align: Vec2,
+) -> Size {
+    draw_text_in(scene, ctx, txt, style, anchor, align, parley::style::GenericFamily::Serif)
+}
+
+// Same as draw_text, for tables of numbers that need to line up
+pub fn draw_mono_text(
+    scene: &mut Scene,
+    ctx: &mut FontContext,
+    txt: &str,
+    style: &TextStyle,
+    anchor: Point,
+    align: Vec2,
+) -> Size {
+    draw_text_in(scene, ctx, txt, style, anchor, align, parley::style::GenericFamily::Monospace)
+}
+
+fn draw_text_in(
+    scene: &mut Scene,
+    ctx: &mut FontContext,
+    txt: &str,
+    style: &TextStyle,
+    anchor: Point,
+    align: Vec2,
+    family: parley::style::GenericFamily,
+) -> Size {
+    let mut lcx = masonry::parley::LayoutContext::new();
+    let mut text_layout_builder = lcx.ranged_builder(ctx, txt, 1.0);
+
+    text_layout_builder.push_default(&StyleProperty::FontStack(FontStack::Single(
+        FontFamily::Generic(family),
+    )));
+    text_layout_builder.push_default(&StyleProperty::FontSize(style.font_size));
+    text_layout_builder.push_default(&StyleProperty::Brush(
+        vello::peniko::Brush::Solid(style.color).into(),
+    ));
+
+    let mut text_layout = text_layout_builder.build();
+    text_layout.break_all_lines(None, style.alignment);
+    let w = text_layout.width() as f64;
+    let h = text_layout.height() as f64;
+
+    let mut scratch_scene = Scene::new();
+    masonry::text_helpers::render_text(
+        scene,
+        &mut scratch_scene,
+        Affine::translate(anchor.to_vec2() - Vec2::new(align.x * w, align.y * h)),
+        &text_layout,
+    );
+    Size::new(w, h)
+}
+
+//-------------------------------------------------------------------------
+// Text laid out once and drawn again and again, for labels that stay up
+// over many frames. Entries are kept for as long as they're drawn, the ones
+// not drawn in a frame are dropped at the end of it.
+//-------------------------------------------------------------------------
+
+// text, font size bits and color
+type TextKey = (String, u32, [u8; 4]);
+
+struct CachedText {
+    scene: Scene,
+    size: Size,
+    used: bool,
+}
+
+#[derive(Default)]
+pub struct TextCache {
+    entries: HashMap<TextKey, CachedText>,
+}
+
+impl TextCache {
+    // The text drawn with its top left corner at the origin, and its size
+    pub fn get(&mut self, ctx: &mut FontContext, txt: &str, style: &TextStyle) -> (&Scene, Size) {
+        let color = style.color;
+        let key = (txt.to_string(), style.font_size.to_bits(), [color.r, color.g, color.b, color.a]);
+        let cached = self.entries.entry(key).or_insert_with(|| {
+            let mut scene = Scene::new();
+            let size = draw_text(&mut scene, ctx, txt, style, Point::ZERO, Vec2::ZERO);
+            CachedText {
+                scene,
+                size,
+                used: false,
+            }
+        });
+        cached.used = true;
+        (&cached.scene, cached.size)
+    }
+
+    pub fn end_frame(&mut self) {
+        self.entries.retain(|_, cached| std::mem::take(&mut cached.used));
+    }
+}
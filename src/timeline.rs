@@ -0,0 +1,241 @@
+use crate::game::TICKS_PER_SECOND;
+
+//-------------------------------------------------------------------------
+// Milestones of the endless game. Events are scripted at fixed times into
+// the run and announced with a banner; once the timeline runs out it starts
+// over, with each pass through it hitting harder than the last.
+//-------------------------------------------------------------------------
+
+// how long a banner stays up
+const BANNER_SECS: u32 = 3;
+// fog thickens and lifts over this long rather than popping in
+const FOG_FADE_SECS: u32 = 3;
+// the carrier turns up a while after this many asteroid showers
+const BOSS_WAVES: u32 = 3;
+const BOSS_DELAY_SECS: u32 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MilestoneEvent {
+    // a wave of asteroids coming in from one edge of the field
+    AsteroidShower,
+    // visibility drops to a small circle around the ship
+    DenseFog,
+    // air pods hold twice the air
+    DoublePods,
+    // the carrier arrives, once per run
+    Boss,
+}
+
+impl MilestoneEvent {
+    // name used in logs
+    pub fn key(self) -> &'static str {
+        match self {
+            MilestoneEvent::AsteroidShower => "asteroid_shower",
+            MilestoneEvent::DenseFog => "dense_fog",
+            MilestoneEvent::DoublePods => "double_pods",
+            MilestoneEvent::Boss => "boss",
+        }
+    }
+
+    pub fn banner(self) -> &'static str {
+        match self {
+            MilestoneEvent::AsteroidShower => "ASTEROID SHOWER",
+            MilestoneEvent::DenseFog => "DENSE FOG",
+            MilestoneEvent::DoublePods => "DOUBLE AIR PODS",
+            MilestoneEvent::Boss => "CARRIER INBOUND",
+        }
+    }
+
+    pub fn color(self) -> xilem::Color {
+        match self {
+            MilestoneEvent::AsteroidShower => xilem::Color::rgb8(0xff, 0x80, 0x40),
+            MilestoneEvent::DenseFog => xilem::Color::rgb8(0xb0, 0xb8, 0xc0),
+            MilestoneEvent::DoublePods => xilem::Color::rgb8(0x00, 0xb4, 0xd8),
+            MilestoneEvent::Boss => xilem::Color::rgb8(0xd0, 0x40, 0xa0),
+        }
+    }
+}
+
+pub struct Milestone {
+    // time into the pass through the timeline
+    pub secs: u32,
+    // zero for one-off events
+    pub duration_secs: u32,
+    pub event: MilestoneEvent,
+}
+
+// The endless timeline, in order. Times are from the start of each pass.
+pub const ENDLESS_TIMELINE: &[Milestone] = &[
+    Milestone {
+        secs: 60,
+        duration_secs: 0,
+        event: MilestoneEvent::AsteroidShower,
+    },
+    Milestone {
+        secs: 120,
+        duration_secs: 30,
+        event: MilestoneEvent::DenseFog,
+    },
+    Milestone {
+        secs: 180,
+        duration_secs: 45,
+        event: MilestoneEvent::DoublePods,
+    },
+    Milestone {
+        secs: 240,
+        duration_secs: 0,
+        event: MilestoneEvent::AsteroidShower,
+    },
+    Milestone {
+        secs: 270,
+        duration_secs: 20,
+        event: MilestoneEvent::DenseFog,
+    },
+];
+// length of one pass through the timeline
+pub const TIMELINE_LOOP_SECS: u32 = 300;
+
+fn secs_to_ticks(secs: u32) -> u32 {
+    secs * TICKS_PER_SECOND as u32
+}
+
+// An event that has started and not run out yet
+pub struct ActiveEvent {
+    pub event: MilestoneEvent,
+    // pass through the timeline it started in, for scaling
+    pub cycle: u32,
+    pub start_tick: u32,
+    pub end_tick: u32,
+}
+
+pub struct Banner {
+    pub text: String,
+    pub color: xilem::Color,
+    pub tick: u32,
+}
+
+pub struct Timeline {
+    next: usize,
+    cycle: u32,
+    active: Vec<ActiveEvent>,
+    banner: Option<Banner>,
+    // asteroid showers so far
+    waves: u32,
+    boss_tick: Option<u32>,
+}
+
+impl Timeline {
+    // Timeline as it stands at the given tick, for runs continued from a save.
+    // Events already past are not replayed, but ones still running carry on.
+    pub fn new(tick: u32) -> Self {
+        let mut timeline = Self {
+            next: 0,
+            cycle: 0,
+            active: Vec::new(),
+            banner: None,
+            waves: 0,
+            boss_tick: None,
+        };
+        while timeline.next_start() < tick {
+            let active = timeline.advance();
+            if active.end_tick > tick {
+                timeline.active.push(active);
+            }
+        }
+        // the carrier isn't saved, so if it was due it's gone
+        timeline.boss_tick = timeline.boss_tick.filter(|boss_tick| *boss_tick >= tick);
+        timeline
+    }
+
+    fn next_start(&self) -> u32 {
+        secs_to_ticks(self.cycle * TIMELINE_LOOP_SECS + ENDLESS_TIMELINE[self.next].secs)
+    }
+
+    // Move past the next milestone, returning it as started
+    fn advance(&mut self) -> ActiveEvent {
+        let milestone = &ENDLESS_TIMELINE[self.next];
+        let start_tick = self.next_start();
+        let active = ActiveEvent {
+            event: milestone.event,
+            cycle: self.cycle,
+            start_tick,
+            end_tick: start_tick + secs_to_ticks(milestone.duration_secs),
+        };
+        if milestone.event == MilestoneEvent::AsteroidShower {
+            self.waves += 1;
+            if self.waves == BOSS_WAVES {
+                self.boss_tick = Some(start_tick + secs_to_ticks(BOSS_DELAY_SECS));
+            }
+        }
+
+        self.next += 1;
+        if self.next == ENDLESS_TIMELINE.len() {
+            self.next = 0;
+            self.cycle += 1;
+        }
+        active
+    }
+
+    // Start the milestones that are due, returning them with the pass they're in
+    pub fn update(&mut self, tick: u32) -> Vec<(MilestoneEvent, u32)> {
+        self.active.retain(|active| active.end_tick > tick);
+
+        let mut started = Vec::new();
+        while self.next_start() <= tick {
+            let active = self.advance();
+            started.push((active.event, active.cycle));
+
+            let text = match active.cycle {
+                0 => active.event.banner().to_string(),
+                cycle => format!("{} x{}", active.event.banner(), cycle + 1),
+            };
+            self.announce(text, active.event.color(), tick);
+            if active.end_tick > tick {
+                self.active.push(active);
+            }
+        }
+
+        if self.boss_tick.is_some_and(|boss_tick| boss_tick <= tick) {
+            self.boss_tick = None;
+            let event = MilestoneEvent::Boss;
+            started.push((event, self.cycle));
+            self.announce(event.banner().to_string(), event.color(), tick);
+        }
+        started
+    }
+
+    // Put up a banner, replacing whatever was showing
+    pub fn announce(&mut self, text: String, color: xilem::Color, tick: u32) {
+        self.banner = Some(Banner { text, color, tick });
+    }
+
+    pub fn is_active(&self, event: MilestoneEvent) -> bool {
+        self.active.iter().any(|active| active.event == event)
+    }
+
+    // 0 is clear, 1 is as thick as the fog gets
+    pub fn fog_density(&self, tick: u32) -> f64 {
+        let fade = secs_to_ticks(FOG_FADE_SECS) as f64;
+        self.active
+            .iter()
+            .filter(|active| active.event == MilestoneEvent::DenseFog)
+            .map(|active| {
+                let since_start = tick.saturating_sub(active.start_tick) as f64;
+                let until_end = active.end_tick.saturating_sub(tick) as f64;
+                (since_start.min(until_end) / fade).min(1.0)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    // The current banner and how opaque it is, fading out at the end
+    pub fn banner(&self, tick: u32) -> Option<(&Banner, f64)> {
+        let banner = self.banner.as_ref()?;
+        let age = tick.saturating_sub(banner.tick);
+        let duration = secs_to_ticks(BANNER_SECS);
+        if age >= duration {
+            return None;
+        }
+        let fade_ticks = TICKS_PER_SECOND as f64 / 2.0;
+        Some((banner, ((duration - age) as f64 / fade_ticks).min(1.0)))
+    }
+}
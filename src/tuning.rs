@@ -0,0 +1,353 @@
+use std::ops::Range;
+
+use masonry::{parley::FontContext, Affine, Point, Rect, Size, Vec2};
+use vello::Scene;
+
+use crate::{
+    game::{
+        COLLISION_MARGIN, MAX_SHIP_SPEED, NEAR_MISS_GAP, NEAR_MISS_SLOW_MO, NEAR_MISS_SPEED, TICKS_PER_SECOND,
+    },
+    text::{draw_text, TextStyle},
+};
+
+//-------------------------------------------------------------------------
+// Physics constants that can be changed while the game runs, for trying
+// out balance changes without a rebuild. The physics reads them every tick.
+// They are edited with sliders in a debug panel (shown with the debug
+// labels, click the header to open it). Changing the ones the simulation
+// reads drops the run's recordings, which wouldn't replay the same, and
+// keeps the run off the leaderboards. The tick rate and the near miss
+// slow-mo only change how fast the ticks go by, so they can be played with.
+//
+// The slow mode in the settings (for players who can't react or press keys
+// as fast) is a set of these: half the tick rate, pods picked up from
+// further away and longer warnings. It's part of a run's loadout, so it's
+// recorded and saved with the run, and its scores are marked as slow mode.
+//-------------------------------------------------------------------------
+
+// of the normal tick rate
+const SLOW_MODE_RATE: f64 = 0.5;
+// units the ship can be from an air pod and still pick it up
+const SLOW_MODE_PICKUP_REACH: f64 = 40.0;
+// how much longer warnings and the shield after a respawn last
+const SLOW_MODE_REACTION_SCALE: f64 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TuningParams {
+    // scales the restitution of each contact (which is the lower of the two bodies')
+    pub restitution: f64,
+    // friction coefficient for asteroid contacts, the only source of spin
+    pub friction: f64,
+    // scales the dampening of each body
+    pub dampening: f64,
+    // passes over the contacts each tick
+    pub solver_iterations: u32,
+    // ticks per second of wall clock time, the simulation itself is always in ticks
+    pub tick_rate: f64,
+    // units/tick
+    pub max_ship_speed: f64,
+    // an asteroid going by closer than this (surface to surface) and faster than
+    // the speed (units/tick) is a near miss
+    pub near_miss_gap: f64,
+    pub near_miss_speed: f64,
+    // of the tick rate for a moment after a near miss, 1 is no slow-mo
+    pub near_miss_slow_mo: f64,
+    // shrinks every collision circle when looking for contacts
+    pub collision_margin: f64,
+    // units between a ship and an air pod that still pick it up
+    pub pickup_reach: f64,
+    // scales the time the player gets to react to warnings, 1 is normal
+    pub reaction_scale: f64,
+}
+
+impl Default for TuningParams {
+    fn default() -> Self {
+        Self {
+            restitution: 1.0,
+            friction: 0.25,
+            dampening: 1.0,
+            solver_iterations: 5,
+            tick_rate: TICKS_PER_SECOND as f64,
+            max_ship_speed: MAX_SHIP_SPEED,
+            near_miss_gap: NEAR_MISS_GAP,
+            near_miss_speed: NEAR_MISS_SPEED,
+            near_miss_slow_mo: NEAR_MISS_SLOW_MO,
+            collision_margin: COLLISION_MARGIN,
+            pickup_reach: 0.0,
+            reaction_scale: 1.0,
+        }
+    }
+}
+
+impl TuningParams {
+    // Switch the accessibility slow mode on or off, the rest stays as tuned
+    pub fn set_slow_mode(&mut self, slow_mode: bool) {
+        let defaults = Self::default();
+        if slow_mode {
+            self.tick_rate = defaults.tick_rate * SLOW_MODE_RATE;
+            self.pickup_reach = SLOW_MODE_PICKUP_REACH;
+            self.reaction_scale = SLOW_MODE_REACTION_SCALE;
+        } else {
+            self.tick_rate = defaults.tick_rate;
+            self.pickup_reach = defaults.pickup_reach;
+            self.reaction_scale = defaults.reaction_scale;
+        }
+    }
+
+    // Whether a run with these would play out as with the other ones
+    pub fn simulates_like(&self, other: &TuningParams) -> bool {
+        TUNING_FIELDS
+            .iter()
+            .filter(|field| field.affects_simulation())
+            .all(|field| field.get(self) == field.get(other))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TuningField {
+    Restitution,
+    Friction,
+    Dampening,
+    SolverIterations,
+    TickRate,
+    MaxShipSpeed,
+    NearMissGap,
+    NearMissSpeed,
+    NearMissSlowMo,
+    CollisionMargin,
+    PickupReach,
+    ReactionScale,
+}
+
+const TUNING_FIELDS: [TuningField; 12] = [
+    TuningField::Restitution,
+    TuningField::Friction,
+    TuningField::Dampening,
+    TuningField::SolverIterations,
+    TuningField::TickRate,
+    TuningField::MaxShipSpeed,
+    TuningField::NearMissGap,
+    TuningField::NearMissSpeed,
+    TuningField::NearMissSlowMo,
+    TuningField::CollisionMargin,
+    TuningField::PickupReach,
+    TuningField::ReactionScale,
+];
+
+impl TuningField {
+    fn label(self) -> &'static str {
+        match self {
+            TuningField::Restitution => "Restitution",
+            TuningField::Friction => "Friction",
+            TuningField::Dampening => "Dampening",
+            TuningField::SolverIterations => "Solver iterations",
+            TuningField::TickRate => "Tick rate",
+            TuningField::MaxShipSpeed => "Max ship speed",
+            TuningField::NearMissGap => "Near miss gap",
+            TuningField::NearMissSpeed => "Near miss speed",
+            TuningField::NearMissSlowMo => "Near miss slow-mo",
+            TuningField::CollisionMargin => "Collision margin",
+            TuningField::PickupReach => "Pickup reach",
+            TuningField::ReactionScale => "Reaction time",
+        }
+    }
+
+    fn range(self) -> Range<f64> {
+        match self {
+            TuningField::Restitution => 0.0..2.0,
+            TuningField::Friction => 0.0..1.0,
+            TuningField::Dampening => 0.0..4.0,
+            TuningField::SolverIterations => 1.0..20.0,
+            TuningField::TickRate => 5.0..120.0,
+            TuningField::MaxShipSpeed => 5.0..100.0,
+            TuningField::NearMissGap => 0.0..150.0,
+            TuningField::NearMissSpeed => 0.0..30.0,
+            TuningField::NearMissSlowMo => 0.2..1.0,
+            TuningField::CollisionMargin => 0.0..10.0,
+            TuningField::PickupReach => 0.0..100.0,
+            TuningField::ReactionScale => 1.0..3.0,
+        }
+    }
+
+    fn get(self, params: &TuningParams) -> f64 {
+        match self {
+            TuningField::Restitution => params.restitution,
+            TuningField::Friction => params.friction,
+            TuningField::Dampening => params.dampening,
+            TuningField::SolverIterations => params.solver_iterations as f64,
+            TuningField::TickRate => params.tick_rate,
+            TuningField::MaxShipSpeed => params.max_ship_speed,
+            TuningField::NearMissGap => params.near_miss_gap,
+            TuningField::NearMissSpeed => params.near_miss_speed,
+            TuningField::NearMissSlowMo => params.near_miss_slow_mo,
+            TuningField::CollisionMargin => params.collision_margin,
+            TuningField::PickupReach => params.pickup_reach,
+            TuningField::ReactionScale => params.reaction_scale,
+        }
+    }
+
+    fn set(self, params: &mut TuningParams, value: f64) {
+        match self {
+            TuningField::Restitution => params.restitution = value,
+            TuningField::Friction => params.friction = value,
+            TuningField::Dampening => params.dampening = value,
+            TuningField::SolverIterations => params.solver_iterations = value.round() as u32,
+            TuningField::TickRate => params.tick_rate = value.round(),
+            TuningField::MaxShipSpeed => params.max_ship_speed = value,
+            TuningField::NearMissGap => params.near_miss_gap = value,
+            TuningField::NearMissSpeed => params.near_miss_speed = value,
+            TuningField::NearMissSlowMo => params.near_miss_slow_mo = value,
+            TuningField::CollisionMargin => params.collision_margin = value,
+            TuningField::PickupReach => params.pickup_reach = value,
+            TuningField::ReactionScale => params.reaction_scale = value,
+        }
+    }
+
+    // the rest only change the wall clock time of a tick
+    fn affects_simulation(self) -> bool {
+        !matches!(self, TuningField::TickRate | TuningField::NearMissSlowMo)
+    }
+
+    fn format(self, params: &TuningParams) -> String {
+        match self {
+            TuningField::SolverIterations | TuningField::TickRate => format!("{:.0}", self.get(params)),
+            _ => format!("{:.2}", self.get(params)),
+        }
+    }
+}
+
+const PANEL_WIDTH: f64 = 280.0;
+const HEADER_HEIGHT: f64 = 28.0;
+const ROW_HEIGHT: f64 = 40.0;
+const PADDING: f64 = 12.0;
+const KNOB_RADIUS: f64 = 7.0;
+
+pub struct TuningPanel {
+    expanded: bool,
+    // slider being dragged
+    dragging: Option<TuningField>,
+}
+
+impl TuningPanel {
+    pub fn new() -> Self {
+        Self {
+            expanded: false,
+            dragging: None,
+        }
+    }
+
+    // Bottom left corner, above the bottom margin
+    fn panel_rect(&self, size: Size) -> Rect {
+        let margin = 0.05 * size.width.min(size.height);
+        let rows = if self.expanded { TUNING_FIELDS.len() as f64 } else { 0.0 };
+        let height = HEADER_HEIGHT + rows * ROW_HEIGHT;
+        Rect::new(margin, size.height - margin - height, margin + PANEL_WIDTH, size.height - margin)
+    }
+
+    fn header_rect(&self, size: Size) -> Rect {
+        let panel = self.panel_rect(size);
+        Rect::new(panel.x0, panel.y0, panel.x1, panel.y0 + HEADER_HEIGHT)
+    }
+
+    // the line a slider's knob moves along
+    fn track(&self, size: Size, idx: usize) -> (Point, Point) {
+        let panel = self.panel_rect(size);
+        let y = panel.y0 + HEADER_HEIGHT + (idx as f64 + 1.0) * ROW_HEIGHT - 0.3 * ROW_HEIGHT;
+        (Point::new(panel.x0 + PADDING, y), Point::new(panel.x1 - PADDING, y))
+    }
+
+    fn set_from_pos(field: TuningField, track: (Point, Point), pos: Point, params: &mut TuningParams) {
+        let t = ((pos.x - track.0.x) / (track.1.x - track.0.x)).clamp(0.0, 1.0);
+        let range = field.range();
+        field.set(params, range.start + t * (range.end - range.start));
+    }
+
+    // Pointer press in screen coordinates. Returns true if it hit the panel.
+    pub fn press(&mut self, pos: Point, size: Size, params: &mut TuningParams) -> bool {
+        if !self.panel_rect(size).contains(pos) {
+            return false;
+        }
+        if self.header_rect(size).contains(pos) {
+            self.expanded = !self.expanded;
+            return true;
+        }
+
+        for (idx, field) in TUNING_FIELDS.iter().enumerate() {
+            let track = self.track(size, idx);
+            let row_top = track.0.y - 0.7 * ROW_HEIGHT;
+            if pos.y >= row_top && pos.y < row_top + ROW_HEIGHT {
+                Self::set_from_pos(*field, track, pos, params);
+                self.dragging = Some(*field);
+            }
+        }
+        true
+    }
+
+    // Returns true while a slider is being dragged
+    pub fn drag(&mut self, pos: Point, size: Size, params: &mut TuningParams) -> bool {
+        let Some(field) = self.dragging else {
+            return false;
+        };
+        let idx = TUNING_FIELDS.iter().position(|f| *f == field).unwrap();
+        Self::set_from_pos(field, self.track(size, idx), pos, params);
+        true
+    }
+
+    pub fn release(&mut self, params: &TuningParams) {
+        if let Some(field) = self.dragging.take() {
+            log::info!("tuning field={:?} value={}", field, field.format(params));
+        }
+    }
+
+    pub fn render(&self, scene: &mut Scene, ctx: &mut FontContext, size: Size, params: &TuningParams) {
+        let panel = self.panel_rect(size);
+        scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            xilem::Color::rgba8(0, 0, 0, 0xa0),
+            None,
+            &panel,
+        );
+
+        let label_style = TextStyle {
+            font_size: 14.0,
+            color: xilem::Color::rgb8(0xff, 0xff, 0x80),
+            alignment: xilem::TextAlignment::Start,
+        };
+        let header = if self.expanded { "- Tuning" } else { "+ Tuning" };
+        let header_anchor = Point::new(panel.x0 + PADDING, panel.y0 + 0.5 * HEADER_HEIGHT);
+        draw_text(scene, ctx, header, &label_style, header_anchor, Vec2::new(0.0, 0.5));
+        if !self.expanded {
+            return;
+        }
+
+        for (idx, field) in TUNING_FIELDS.iter().enumerate() {
+            let (start, end) = self.track(size, idx);
+            let txt = format!("{}: {}", field.label(), field.format(params));
+            let label_anchor = start - Vec2::new(0.0, 0.5 * ROW_HEIGHT - KNOB_RADIUS);
+            draw_text(scene, ctx, &txt, &label_style, label_anchor, Vec2::new(0.0, 0.5));
+
+            scene.stroke(
+                &vello::kurbo::Stroke::new(3.0),
+                Affine::IDENTITY,
+                xilem::Color::rgb8(0x80, 0x80, 0x80),
+                None,
+                &vello::kurbo::Line::new(start, end),
+            );
+            let range = field.range();
+            let t = ((field.get(params) - range.start) / (range.end - range.start)).clamp(0.0, 1.0);
+            let knob_color = if self.dragging == Some(*field) {
+                xilem::Color::rgb8(0xff, 0xff, 0xff)
+            } else {
+                xilem::Color::rgb8(0xc0, 0xc0, 0xc0)
+            };
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                knob_color,
+                None,
+                &vello::kurbo::Circle::new(start.lerp(end, t), KNOB_RADIUS),
+            );
+        }
+    }
+}
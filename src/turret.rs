@@ -0,0 +1,125 @@
+use std::f64::consts::{PI, TAU};
+
+use masonry::Vec2;
+
+use crate::game::{EntityId, TICKS_PER_SECOND};
+
+//-------------------------------------------------------------------------
+// Turrets: guns attached to another body (see Attachment) that turn on
+// their own to follow a target and fire when they're on it. The aim is the
+// attachment's rotation in the parent's frame, so a turret keeps pointing
+// the same way as its parent turns under it until it slews back around,
+// and it can only turn so fast. The carrier's turrets throw rocks, the
+// ones on pirate ships and stations fire bolts (see Projectile).
+//
+// Who a turret goes after depends on what it's mounted on, see
+// GameWorld::turret_target.
+//-------------------------------------------------------------------------
+
+// fires once the target is within this much of where it's pointing
+const AIM_TOLERANCE: f64 = 0.08;
+
+pub const BOLT_RADIUS: f64 = 5.0;
+// units/tick, on top of the velocity of what fired it
+pub const BOLT_SPEED: f64 = 14.0;
+const BOLT_LIFETIME_TICKS: u32 = 3 * TICKS_PER_SECOND as u32;
+// taken from the player's air by a hit, in ticks
+pub const BOLT_HIT_AIR: u64 = 2 * TICKS_PER_SECOND as u64;
+pub const BOLT_HULL_DAMAGE: f64 = 8.0;
+
+// where the gun sits on a pirate ship and on a station, in their frames
+pub const SHIP_TURRET_OFFSET: Vec2 = Vec2::new(0.0, -8.0);
+// on the ends of two of the arms
+pub const STATION_TURRET_OFFSETS: [Vec2; 2] = [Vec2::new(100.0, 0.0), Vec2::new(-100.0, 0.0)];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TurretAmmo {
+    // one of the carrier's rocks, see GameWorld::throw_boss_rock
+    Rock,
+    Bolt,
+}
+
+#[derive(Clone, Debug)]
+pub struct Turret {
+    // radians/tick
+    pub slew_rate: f64,
+    // doesn't look for targets further than this
+    pub range: f64,
+    // ticks between shots
+    pub reload_ticks: u32,
+    pub reload_left: u32,
+    pub ammo: TurretAmmo,
+}
+
+impl Turret {
+    // Slow and far reaching, the carrier sets how often it fires by phase
+    pub fn boss(reload_ticks: u32) -> Self {
+        Self {
+            slew_rate: 0.02,
+            range: 2500.0,
+            reload_ticks,
+            reload_left: reload_ticks,
+            ammo: TurretAmmo::Rock,
+        }
+    }
+
+    pub fn pirate() -> Self {
+        Self {
+            slew_rate: 0.06,
+            range: 700.0,
+            reload_ticks: 2 * TICKS_PER_SECOND as u32,
+            reload_left: 0,
+            ammo: TurretAmmo::Bolt,
+        }
+    }
+
+    pub fn station() -> Self {
+        Self {
+            slew_rate: 0.04,
+            range: 1000.0,
+            reload_ticks: 3 * TICKS_PER_SECOND as u32 / 2,
+            reload_left: 0,
+            ammo: TurretAmmo::Bolt,
+        }
+    }
+
+    // Aim turned from aim towards desired by at most the slew rate, both angles
+    // in the parent's frame
+    pub fn slew(&self, aim: f64, desired: f64) -> f64 {
+        aim + angle_between(aim, desired).clamp(-self.slew_rate, self.slew_rate)
+    }
+
+    // A tick has passed with the turret pointing at aim and wanting desired.
+    // Returns true if it fires, and starts reloading when it does.
+    pub fn update(&mut self, aim: f64, desired: Option<f64>) -> bool {
+        self.reload_left = self.reload_left.saturating_sub(1);
+        let on_target = desired.is_some_and(|desired| angle_between(aim, desired).abs() < AIM_TOLERANCE);
+        if !on_target || self.reload_left > 0 {
+            return false;
+        }
+        self.reload_left = self.reload_ticks;
+        true
+    }
+}
+
+// From a to b the short way around, -PI..PI
+fn angle_between(a: f64, b: f64) -> f64 {
+    (b - a + PI).rem_euclid(TAU) - PI
+}
+
+// A bolt a turret fired, gone when it hits something or runs out of time
+#[derive(Clone, Copy, Debug)]
+pub struct Projectile {
+    // what the turret is mounted on, bolts go through it. None once it's gone.
+    pub owner: Option<EntityId>,
+    pub ticks_left: u32,
+}
+
+impl Projectile {
+    pub fn new(owner: EntityId) -> Self {
+        Self {
+            owner: Some(owner),
+            ticks_left: BOLT_LIFETIME_TICKS,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    actions::{Action, Bindings},
+    game::{EntityId, GameEvent, TICKS_PER_SECOND},
+};
+
+//-------------------------------------------------------------------------
+// Tutorial. A few steps, each with a prompt, moving on once the player has
+// done what it asks. Whether they have is judged from the events of each
+// tick; the world side (sending an asteroid to dodge, pointing at things)
+// is up to the game. The skip key jumps straight to the end.
+//-------------------------------------------------------------------------
+
+// how long thrusting or turning has to be kept up to count
+const HOLD_TICKS: u32 = TICKS_PER_SECOND as u32;
+// how long the closing message stays up
+const DONE_SECS: u32 = 4;
+
+pub const NUM_STEPS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TutorialStep {
+    Thrust,
+    Turn,
+    CollectPod,
+    AvoidAsteroid,
+    Done,
+}
+
+impl TutorialStep {
+    // name used in logs
+    pub fn key(self) -> &'static str {
+        match self {
+            TutorialStep::Thrust => "thrust",
+            TutorialStep::Turn => "turn",
+            TutorialStep::CollectPod => "collect_pod",
+            TutorialStep::AvoidAsteroid => "avoid_asteroid",
+            TutorialStep::Done => "done",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            TutorialStep::Thrust => TutorialStep::Turn,
+            TutorialStep::Turn => TutorialStep::CollectPod,
+            TutorialStep::CollectPod => TutorialStep::AvoidAsteroid,
+            TutorialStep::AvoidAsteroid | TutorialStep::Done => TutorialStep::Done,
+        }
+    }
+
+    // counting from 1, for "step 2/4"
+    pub fn number(self) -> usize {
+        self as usize + 1
+    }
+}
+
+pub struct Tutorial {
+    pub step: TutorialStep,
+    // tick the current step started
+    pub step_tick: u32,
+    // ticks spent thrusting or turning so far
+    held: u32,
+    // the asteroid to dodge, once the game has sent one
+    pub asteroid: Option<EntityId>,
+    // it has come close, so it getting away again means it was dodged
+    pub asteroid_close: bool,
+    // times the ship was hit trying
+    hits: u32,
+}
+
+impl Tutorial {
+    pub fn new(tick: u32) -> Self {
+        Self {
+            step: TutorialStep::Thrust,
+            step_tick: tick,
+            held: 0,
+            asteroid: None,
+            asteroid_close: false,
+            hits: 0,
+        }
+    }
+
+    // Returns true if the step was done this tick
+    pub fn update(&mut self, events: &[GameEvent], tick: u32) -> bool {
+        let done = match self.step {
+            TutorialStep::Thrust => {
+                self.held += events.iter().any(|event| *event == GameEvent::Thrust) as u32;
+                self.held >= HOLD_TICKS
+            }
+            TutorialStep::Turn => {
+                self.held += events.iter().any(|event| *event == GameEvent::Turn) as u32;
+                self.held >= HOLD_TICKS
+            }
+            TutorialStep::CollectPod => {
+                events.iter().any(|event| matches!(event, GameEvent::AirCollected { .. }))
+            }
+            TutorialStep::AvoidAsteroid => {
+                let Some(asteroid) = self.asteroid else {
+                    return false;
+                };
+                if events.contains(&GameEvent::ShipHit { other: Some(asteroid) }) {
+                    // the game sends another one
+                    self.hits += 1;
+                    self.asteroid = None;
+                    self.asteroid_close = false;
+                    return false;
+                }
+                events.contains(&GameEvent::NearMiss { asteroid })
+            }
+            TutorialStep::Done => false,
+        };
+        if done {
+            self.advance(tick);
+        }
+        done
+    }
+
+    pub fn advance(&mut self, tick: u32) {
+        self.step = self.step.next();
+        self.step_tick = tick;
+        self.held = 0;
+    }
+
+    pub fn skip(&mut self, tick: u32) {
+        self.step = TutorialStep::Done;
+        self.step_tick = tick;
+    }
+
+    // The closing message has been up long enough
+    pub fn is_finished(&self, tick: u32) -> bool {
+        self.step == TutorialStep::Done && tick >= self.step_tick + DONE_SECS * TICKS_PER_SECOND as u32
+    }
+
+    // What to do next, with the keys from the player's bindings
+    pub fn prompt(&self, bindings: &Bindings) -> String {
+        match self.step {
+            TutorialStep::Thrust => format!("Press {} to thrust", bindings.key_names(Action::Thrust)),
+            TutorialStep::Turn => format!(
+                "Turn with {} and {}",
+                bindings.key_names(Action::TurnLeft),
+                bindings.key_names(Action::TurnRight)
+            ),
+            TutorialStep::CollectPod => "Fly into the air pod to refill your air".to_string(),
+            TutorialStep::AvoidAsteroid if self.hits > 0 => "Hit! Here comes another one, dodge it".to_string(),
+            TutorialStep::AvoidAsteroid => "An asteroid is coming, get out of its way".to_string(),
+            TutorialStep::Done => "That's it, you're on your own now".to_string(),
+        }
+    }
+}
@@ -5,8 +5,6 @@ use vello::wgpu::{self, Device, PipelineCompilationOptions, TextureFormat, Textu
 
 pub struct TargetTexture {
     view: TextureView,
-    width: u32,
-    height: u32,
 }
 
 impl TargetTexture {
@@ -26,15 +24,7 @@ impl TargetTexture {
             view_formats: &[],
         });
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        Self {
-            view,
-            width,
-            height,
-        }
-    }
-
-    pub fn need_resize(&self, width: u32, height: u32) -> bool {
-        self.width != width || self.height != height
+        Self { view }
     }
 
     pub fn get_view(&self) -> &TextureView {
@@ -49,11 +39,11 @@ pub struct BlitPipeline {
 
 impl BlitPipeline {
     #[allow(dead_code)]
-    pub fn new(device: &Device, format: TextureFormat) -> Self {
-        Self::new_with_blend(device, format, None)
+    pub fn new(device: &Device, format: TextureFormat, sample_count: u32) -> Self {
+        Self::new_with_blend(device, format, None, sample_count)
     }
 
-    pub fn new_with_blend(device: &Device, format: TextureFormat, blend_state: Option<wgpu::BlendState>) -> Self {
+    pub fn new_with_blend(device: &Device, format: TextureFormat, blend_state: Option<wgpu::BlendState>, sample_count: u32) -> Self {
         const SHADERS: &str = r#"
             @vertex
             fn vs_main(@builtin(vertex_index) ix: u32) -> @builtin(position) vec4<f32> {
@@ -136,7 +126,7 @@ impl BlitPipeline {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -2,7 +2,7 @@ use accesskit::TreeUpdate;
 use masonry::{event_loop_runner::{MasonryState, WindowState}, widget::RootWidget, Affine};
 use vello::{wgpu::{BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, BlendState, Buffer, Device, Queue, RenderPass, TextureFormat}, Scene};
 
-use crate::{game_view::GamePortal, render_mgr::Renderer, vello_ext, GameState};
+use crate::{game_view::GamePortal, render_frame::CameraSnapshot, render_mgr::Renderer, settings::VelloAa, vello_ext};
 
 
 
@@ -13,19 +13,20 @@ pub struct XilemRenderer {
     blit: Option<vello_ext::BlitPipeline>,
     blit_bind_group: Option<BindGroup>,
     renderer: vello::Renderer,
+    aa: VelloAa,
+    // what it was set up with, to set it up again on a new device
+    surface_format: TextureFormat,
+    sample_count: u32,
 }
 
 impl XilemRenderer {
-    pub fn setup(device: &Device, _queue: &Queue, _global_buffer: &Buffer, surface_format: TextureFormat) -> Self {
-        let blit =vello_ext::BlitPipeline::new_with_blend(device, surface_format, Some(BlendState::ALPHA_BLENDING));
+    pub fn setup(device: &Device, _queue: &Queue, _global_buffer: &Buffer, surface_format: TextureFormat, sample_count: u32, aa: VelloAa) -> Self {
+        let blit =vello_ext::BlitPipeline::new_with_blend(device, surface_format, Some(BlendState::ALPHA_BLENDING), sample_count);
         let renderer = vello::Renderer::new(device, vello::RendererOptions {
             surface_format: Some(surface_format),
             use_cpu: false,
-            antialiasing_support: vello::AaSupport {
-                area: true,
-                msaa8: false,
-                msaa16: false,
-            },
+            // only build the pipelines for the aa method we're going to use
+            antialiasing_support: aa.aa_support(),
             num_init_threads: std::num::NonZeroUsize::new(1),
         }).unwrap();
 
@@ -35,6 +36,9 @@ impl XilemRenderer {
             blit: Some(blit),
             blit_bind_group: None,
             renderer,
+            aa,
+            surface_format,
+            sample_count,
         }
     }
 
@@ -51,9 +55,9 @@ impl XilemRenderer {
 }
 
 impl Renderer for XilemRenderer {
-    fn prepare(&mut self, masonry_state: &mut MasonryState, _game_state: &GameState, width: u32, height: u32) {
-        let scale_factor = if let WindowState::Rendering { window, .. } = masonry_state.get_window_state() {
-            window.scale_factor()
+    fn prepare(&mut self, masonry_state: &mut MasonryState, _camera: &CameraSnapshot, width: u32, height: u32) {
+        let (scale_factor, window_width) = if let WindowState::Rendering { window, .. } = masonry_state.get_window_state() {
+            (window.scale_factor(), window.inner_size().width)
         }
         else {
             // should never get here when window state is not rendering
@@ -74,32 +78,32 @@ impl Renderer for XilemRenderer {
             unreachable!("Failed to get render device and queue");
         };
 
-        // fiddle with target texture
-        if self.target_texture.as_ref().map(|t| t.need_resize(width, height)).unwrap_or(true) {
-            let target_texture = vello_ext::TargetTexture::new(device, width, height);
-            self.set_blit_bind_group(device, &target_texture);
-            self.target_texture = Some(target_texture);
-        }
+        // made by resize, which comes before the first frame
+        let Some(target_texture) = self.target_texture.as_ref() else {
+            return;
+        };
 
         let render_params = vello::RenderParams {
             base_color: masonry::Color::BLACK.with_alpha_factor(0.0),
             width,
             height,
-            antialiasing_method: vello::AaConfig::Area,
+            antialiasing_method: self.aa.aa_config(),
         };
 
-        // get surface scale and scale scene by it
-        let transformed_scene = if scale_factor == 1.0 {
+        // get surface scale and scale scene by it. Rendering at a different size than the
+        // window (e.g., supersampled screenshots) scales the scene to match.
+        let scale = scale_factor * width as f64 / window_width.max(1) as f64;
+        let transformed_scene = if scale == 1.0 {
             None
         } else {
             let mut new_scene = Scene::new();
-            new_scene.append(&scene, Some(Affine::scale(scale_factor)));
+            new_scene.append(&scene, Some(Affine::scale(scale)));
             Some(new_scene)
         };
         let scene_ref = transformed_scene.as_ref().unwrap_or(&scene);
 
         // Note: this performas a compute render pass. Might be worth holding onto the encoder and re-using for remaining passes
-        self.renderer.render_to_texture(device, queue, scene_ref, self.target_texture.as_ref().unwrap().get_view(), &render_params).unwrap();
+        self.renderer.render_to_texture(device, queue, scene_ref, target_texture.get_view(), &render_params).unwrap();
     }
 
     fn render<'rpass>(&'rpass self, render_pass: &mut RenderPass<'rpass>, _width: u32, _height: u32) {
@@ -113,9 +117,24 @@ impl Renderer for XilemRenderer {
         }
     }
 
-    fn finish_render(&mut self, masonry_state: &mut MasonryState, _: &GameState) {
+    fn finish_render(&mut self, masonry_state: &mut MasonryState) {
         if let Some(tree_update) = self.tree_update.take() {
             masonry_state.handle_tree_update(tree_update);
         }
     }
+
+    // The scene is drawn into a texture the size of what's being rendered
+    fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        let target_texture = vello_ext::TargetTexture::new(device, width, height);
+        self.set_blit_bind_group(device, &target_texture);
+        self.target_texture = Some(target_texture);
+    }
+
+    // Only what lives on the device is made again, with the same blit setup. A
+    // tree update still waiting to go to accesskit is kept.
+    fn recreate(&mut self, device: &Device, queue: &Queue, global_buffer: &Buffer) {
+        let tree_update = self.tree_update.take();
+        *self = Self::setup(device, queue, global_buffer, self.surface_format, self.sample_count, self.aa);
+        self.tree_update = tree_update;
+    }
 }
\ No newline at end of file